@@ -0,0 +1,331 @@
+//! # Api Tools Derive
+//!
+//! Derive macros for [api-tools](https://docs.rs/api-tools). Do not use this crate directly,
+//! enable the `derive` feature of `api-tools` instead.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, ExprLit, ExprUnary, Fields, GenericArgument, Lit, PathArguments, Type, UnOp,
+    parse_macro_input,
+};
+
+/// Field attributes (`#[api_query(...)]`)
+#[derive(Default)]
+struct FieldOptions {
+    min: Option<Expr>,
+    max: Option<Expr>,
+    regex: Option<String>,
+    default: Option<String>,
+}
+
+/// Derive an Axum extractor parsing and validating the query string
+///
+/// Each field is parsed with `FromStr`. `Option<T>` fields are optional, other fields are
+/// required unless they have a `default`. All errors are aggregated in a single
+/// `422 Unprocessable Entity` response.
+///
+/// Supported attributes:
+///
+/// - `#[api_query(min = 1)]`: minimum value (inclusive)
+/// - `#[api_query(max = 100)]`: maximum value (inclusive)
+/// - `#[api_query(regex = "^[a-z]+$")]`: pattern the value must match, checked at compile time
+/// - `#[api_query(default = 20)]`: value used when the parameter is missing (a literal, or a
+///   negative number)
+///
+/// # Example
+///
+/// ```ignore
+/// use api_tools::ApiQuery;
+///
+/// #[derive(ApiQuery)]
+/// struct Search {
+///     #[api_query(regex = "^[a-z]+$")]
+///     name: String,
+///     #[api_query(min = 1, default = 1)]
+///     page: u32,
+///     #[api_query(min = 10, max = 100)]
+///     limit: Option<u32>,
+/// }
+///
+/// async fn handler(search: Search) {}
+/// ```
+#[proc_macro_derive(ApiQuery, attributes(api_query))]
+pub fn derive_api_query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand_api_query(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_api_query(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "ApiQuery only supports structs with named fields",
+                ));
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(name, "ApiQuery only supports structs")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "ApiQuery does not support generics",
+        ));
+    }
+
+    let mut parsing = Vec::new();
+    let mut idents = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let key = ident.to_string();
+        let key = key.strip_prefix("r#").unwrap_or(&key);
+        let options = parse_field_options(field)?;
+
+        let (inner_ty, optional) = match option_inner_type(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+
+        let read = match (&options.default, optional) {
+            (Some(default), _) => quote! { params.required::<#inner_ty>(#key, Some(#default)) },
+            (None, false) => quote! { params.required::<#inner_ty>(#key, None) },
+            (None, true) => quote! { params.optional::<#inner_ty>(#key) },
+        };
+
+        let mut checks = Vec::new();
+        if let Some(min) = &options.min {
+            checks.push(quote! { params.check_min(#key, value, &(#min)); });
+        }
+        if let Some(max) = &options.max {
+            checks.push(quote! { params.check_max(#key, value, &(#max)); });
+        }
+        if let Some(regex) = &options.regex {
+            checks.push(quote! {
+                static REGEX: ::std::sync::LazyLock<::api_tools::__private::Regex> =
+                    ::std::sync::LazyLock::new(|| {
+                        ::api_tools::__private::Regex::new(#regex).expect("invalid ApiQuery regex")
+                    });
+                params.check_regex(#key, value, &REGEX);
+            });
+        }
+
+        parsing.push(quote! {
+            let #ident: ::std::option::Option<#inner_ty> = #read;
+            if let ::std::option::Option::Some(value) = &#ident {
+                #(#checks)*
+            }
+        });
+        idents.push((ident, optional));
+    }
+
+    let construct = idents.iter().map(|(ident, optional)| {
+        if *optional {
+            quote! { #ident }
+        } else {
+            quote! { #ident: #ident.expect("checked by QueryParams::finish") }
+        }
+    });
+
+    Ok(quote! {
+        impl<S> ::api_tools::__private::FromRequestParts<S> for #name
+        where
+            S: ::std::marker::Send + ::std::marker::Sync,
+        {
+            type Rejection = ::api_tools::server::axum::response::ValidationErrors;
+
+            async fn from_request_parts(
+                parts: &mut ::api_tools::__private::Parts,
+                _state: &S,
+            ) -> ::std::result::Result<Self, Self::Rejection> {
+                let mut params = ::api_tools::server::axum::extractors::QueryParams::from_parts(parts)?;
+                #(#parsing)*
+                params.finish()?;
+
+                ::std::result::Result::Ok(Self { #(#construct),* })
+            }
+        }
+    })
+}
+
+fn parse_field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("api_query")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("min") {
+                options.min = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("max") {
+                options.max = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("regex") {
+                let lit: Lit = meta.value()?.parse()?;
+                match lit {
+                    Lit::Str(lit) => {
+                        let pattern = lit.value();
+                        if let Err(err) = regex::Regex::new(&pattern) {
+                            return Err(syn::Error::new_spanned(lit, format!("invalid regex: {err}")));
+                        }
+                        options.regex = Some(pattern);
+                    }
+                    _ => return Err(meta.error("regex must be a string literal")),
+                }
+            } else if meta.path.is_ident("default") {
+                let expr: Expr = meta.value()?.parse()?;
+                options.default = Some(default_value(&expr)?);
+            } else {
+                return Err(meta.error("unsupported api_query attribute, expected min, max, regex or default"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+/// String form of a `default` value, parsed with `FromStr` by the extractor
+fn default_value(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(lit) => Ok(lit.value()),
+            Lit::Int(lit) => Ok(lit.base10_digits().to_string()),
+            Lit::Float(lit) => Ok(lit.base10_digits().to_string()),
+            Lit::Bool(lit) => Ok(lit.value.to_string()),
+            Lit::Char(lit) => Ok(lit.value().to_string()),
+            _ => Err(syn::Error::new_spanned(lit, "unsupported default literal")),
+        },
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_), expr, ..
+        }) => match expr.as_ref() {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(_) | Lit::Float(_),
+                ..
+            }) => Ok(format!("-{}", default_value(expr)?)),
+            _ => Err(syn::Error::new_spanned(
+                expr,
+                "default must be a literal or a negative number",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "default must be a literal or a negative number",
+        )),
+    }
+}
+
+/// Return `T` if the type is `Option<T>`
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand(input: DeriveInput) -> syn::Result<String> {
+        expand_api_query(&input).map(|tokens| tokens.to_string())
+    }
+
+    #[test]
+    fn test_expand_api_query() {
+        let tokens = expand(parse_quote! {
+            struct Search {
+                #[api_query(regex = "^[a-z]+$")]
+                name: String,
+                #[api_query(min = 1, default = 1)]
+                page: u32,
+                limit: Option<u32>,
+            }
+        })
+        .unwrap();
+        assert!(tokens.contains("params . required :: < String > (\"name\" , None)"));
+        assert!(tokens.contains("params . required :: < u32 > (\"page\" , Some (\"1\"))"));
+        assert!(tokens.contains("params . optional :: < u32 > (\"limit\")"));
+        assert!(tokens.contains("\"^[a-z]+$\""));
+    }
+
+    #[test]
+    fn test_expand_api_query_defaults() {
+        let tokens = expand(parse_quote! {
+            struct Range {
+                #[api_query(default = -1)]
+                from: i32,
+                #[api_query(default = -0.5)]
+                ratio: f64,
+                #[api_query(default = 10u32)]
+                limit: u32,
+                #[api_query(default = "name")]
+                sort: String,
+                #[api_query(default = true)]
+                desc: bool,
+            }
+        })
+        .unwrap();
+        for default in ["-1", "-0.5", "10", "name", "true"] {
+            assert!(tokens.contains(&format!("Some (\"{default}\")")), "{default}");
+        }
+
+        let err = expand(parse_quote! {
+            struct Range {
+                #[api_query(default = -"1")]
+                from: i32,
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "default must be a literal or a negative number");
+    }
+
+    #[test]
+    fn test_expand_api_query_rejects_invalid_regex() {
+        let err = expand(parse_quote! {
+            struct Search {
+                #[api_query(regex = "^[a-z+$")]
+                name: String,
+            }
+        })
+        .unwrap_err();
+        assert!(err.to_string().starts_with("invalid regex: "), "{err}");
+    }
+
+    #[test]
+    fn test_expand_api_query_rejects_unsupported_inputs() {
+        let err = expand(parse_quote! {
+            struct Search(String);
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "ApiQuery only supports structs with named fields");
+
+        let err = expand(parse_quote! {
+            struct Search {
+                #[api_query(pattern = "a")]
+                name: String,
+            }
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported api_query attribute, expected min, max, regex or default"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Per-request overhead of the layers
+//!
+//! ```shell
+//! cargo bench --features bench
+//! cargo bench --features bench,prometheus
+//! ```
+
+use api_tools::bench::{
+    BenchService, basic_auth_layer, basic_auth_request, common_stack, hmac_signature_layer, http_errors_layer,
+    ok_service, request, signed_request, time_limiter_layer,
+};
+use api_tools::server::axum::layers::circuit_breaker::{CircuitBreaker, CircuitBreakerLayer};
+use api_tools::server::axum::layers::injector::{Injector, InjectorLayer};
+use api_tools::server::axum::layers::logger::LoggerLayer;
+use api_tools::server::axum::layers::request_id::{RequestIdLayer, RequestIdScopeLayer};
+use api_tools::server::axum::layers::security_headers::{SecurityHeadersConfig, SecurityHeadersLayer};
+use axum::body::Body;
+use axum::http::Request;
+use axum::response::Response;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::convert::Infallible;
+use tokio::runtime::Runtime;
+use tower::util::BoxCloneService;
+use tower::{Layer, Service, ServiceExt};
+
+/// Measure a request sent to a service
+fn bench_service(
+    c: &mut Criterion,
+    runtime: &Runtime,
+    name: &str,
+    service: BenchService,
+    request: fn() -> Request<Body>,
+) {
+    c.bench_function(name, |b| {
+        b.to_async(runtime).iter(|| {
+            let service = service.clone();
+            async move { service.oneshot(request()).await }
+        })
+    });
+}
+
+/// Measure a layer around the no-op handler
+fn bench_layer<L>(c: &mut Criterion, runtime: &Runtime, name: &str, layer: L, request: fn() -> Request<Body>)
+where
+    L: Layer<BenchService>,
+    L::Service: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    <L::Service as Service<Request<Body>>>::Future: Send + 'static,
+{
+    bench_service(
+        c,
+        runtime,
+        name,
+        BoxCloneService::new(layer.layer(ok_service())),
+        request,
+    );
+}
+
+fn layers(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Tokio runtime");
+
+    bench_service(c, &runtime, "baseline", ok_service(), || request("/users"));
+    bench_layer(c, &runtime, "basic_auth", basic_auth_layer(), || {
+        basic_auth_request("/users")
+    });
+    bench_layer(
+        c,
+        &runtime,
+        "circuit_breaker",
+        CircuitBreakerLayer::new(CircuitBreaker::new("bench")),
+        || request("/users"),
+    );
+    bench_layer(c, &runtime, "hmac_signature", hmac_signature_layer(), || {
+        signed_request("/webhooks", br#"{"event":"bench"}"#)
+    });
+    bench_layer(c, &runtime, "http_errors", http_errors_layer(), || request("/users"));
+    bench_layer(
+        c,
+        &runtime,
+        "injector",
+        InjectorLayer::new(Injector::new().singleton(String::from("bench"))),
+        || request("/users"),
+    );
+    bench_layer(c, &runtime, "logger", LoggerLayer, || request("/users"));
+    bench_layer(c, &runtime, "request_id", RequestIdLayer::new(), || request("/users"));
+    bench_layer(c, &runtime, "request_id_scope", RequestIdScopeLayer::new(), || {
+        request("/users")
+    });
+    bench_layer(
+        c,
+        &runtime,
+        "security_headers",
+        SecurityHeadersLayer::new(SecurityHeadersConfig::default()),
+        || request("/users"),
+    );
+    bench_layer(c, &runtime, "time_limiter", time_limiter_layer(), || request("/users"));
+    #[cfg(feature = "metrics")]
+    bench_layer(
+        c,
+        &runtime,
+        "metrics",
+        api_tools::server::axum::layers::metrics::MetricsLayer {
+            service_name: "bench".to_string(),
+        },
+        || request("/users"),
+    );
+
+    bench_service(c, &runtime, "common_stack", common_stack(ok_service()), || {
+        request("/users")
+    });
+}
+
+criterion_group!(benches, layers);
+criterion_main!(benches);
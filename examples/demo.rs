@@ -0,0 +1,29 @@
+//! Demo application
+//!
+//! ```shell
+//! cargo run --example demo --features examples
+//! curl -X POST localhost:3000/token -H 'Content-Type: application/json' -d '{"username":"demo"}'
+//! ```
+
+use api_tools::demo::{DemoConfig, demo_router};
+use api_tools::server::axum::handlers::prometheus::PrometheusHandler;
+use api_tools::server::axum::shutdown::{Shutdown, ShutdownLayer};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = DemoConfig {
+        prometheus_handle: Some(PrometheusHandler::get_handle()?),
+        ..DemoConfig::default()
+    };
+    let shutdown = Shutdown::new();
+    let app = demo_router(config)?.layer(ShutdownLayer::new(shutdown.clone()));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+    println!("Demo listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.clone().signal())
+        .await?;
+    shutdown.finish()?;
+
+    Ok(())
+}
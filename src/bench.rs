@@ -0,0 +1,160 @@
+//! Benchmark helpers (`bench` feature)
+//!
+//! Minimal services, requests and layer configurations used by the criterion benches
+//! (`cargo bench --features bench`), so that each layer is measured around a no-op handler.
+//!
+//! ```rust
+//! use api_tools::bench::{common_stack, ok_service, request};
+//! use tower::ServiceExt;
+//!
+//! # async fn run() {
+//! let response = common_stack(ok_service()).oneshot(request("/users")).await.unwrap();
+//! assert!(response.status().is_success());
+//! # }
+//! ```
+
+use crate::server::axum::layers::basic_auth::BasicAuthLayer;
+use crate::server::axum::layers::hmac_signature::{
+    HMAC_SIGNATURE_HEADER, HMAC_TIMESTAMP_HEADER, HmacSignatureLayer, sign_request,
+};
+use crate::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+use crate::server::axum::layers::logger::LoggerLayer;
+use crate::server::axum::layers::request_id::RequestIdLayer;
+use crate::server::axum::layers::security_headers::{SecurityHeadersConfig, SecurityHeadersLayer};
+use crate::server::axum::layers::time_limiter::TimeLimiterLayer;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use http_auth_basic::Credentials;
+use std::convert::Infallible;
+use tower::ServiceBuilder;
+use tower::util::BoxCloneService;
+
+/// Boxed service used by the benches
+pub type BenchService = BoxCloneService<Request<Body>, Response, Infallible>;
+
+/// Basic auth credentials of [`basic_auth_layer`]
+const BENCH_USERNAME: &str = "bench";
+const BENCH_PASSWORD: &str = "bench";
+
+/// HMAC secret of [`hmac_signature_layer`]
+const BENCH_HMAC_SECRET: &str = "bench-secret";
+
+/// Handler returning an empty `200 OK` response
+pub fn ok_service() -> BenchService {
+    BoxCloneService::new(tower::service_fn(|_request: Request<Body>| async {
+        Ok::<_, Infallible>(
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::empty())
+                .unwrap_or_default(),
+        )
+    }))
+}
+
+/// `GET` request without body
+pub fn request(path: &str) -> Request<Body> {
+    Request::get(path).body(Body::empty()).unwrap_or_default()
+}
+
+/// `BasicAuthLayer` accepting the [`basic_auth_request`] credentials
+pub fn basic_auth_layer() -> BasicAuthLayer {
+    BasicAuthLayer::new(BENCH_USERNAME, BENCH_PASSWORD)
+}
+
+/// Request authenticated for [`basic_auth_layer`]
+pub fn basic_auth_request(path: &str) -> Request<Body> {
+    let credentials = Credentials::new(BENCH_USERNAME, BENCH_PASSWORD);
+
+    Request::get(path)
+        .header(header::AUTHORIZATION, credentials.as_http_header())
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+/// `HmacSignatureLayer` accepting the [`signed_request`] signatures
+pub fn hmac_signature_layer() -> HmacSignatureLayer {
+    HmacSignatureLayer::new(&[BENCH_HMAC_SECRET])
+}
+
+/// `POST` request signed for [`hmac_signature_layer`]
+pub fn signed_request(path: &str, body: &'static [u8]) -> Request<Body> {
+    let timestamp = chrono::Utc::now().timestamp();
+
+    Request::post(path)
+        .header(HMAC_TIMESTAMP_HEADER, timestamp)
+        .header(HMAC_SIGNATURE_HEADER, sign_request(BENCH_HMAC_SECRET, timestamp, body))
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
+
+/// `HttpErrorsLayer` with a 1 MiB body limit
+pub fn http_errors_layer() -> HttpErrorsLayer {
+    HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024 * 1_024))
+}
+
+/// `TimeLimiterLayer` without time slots (all requests are allowed)
+pub fn time_limiter_layer() -> TimeLimiterLayer {
+    TimeLimiterLayer::new("".into())
+}
+
+/// Stack commonly used in front of an API: request ID, logger, security headers and errors
+/// (and the HTTP metrics with the `metrics` feature)
+pub fn common_stack(service: BenchService) -> BenchService {
+    let service = ServiceBuilder::new()
+        .layer(RequestIdLayer::new())
+        .layer(LoggerLayer)
+        .layer(SecurityHeadersLayer::new(SecurityHeadersConfig::default()))
+        .layer(http_errors_layer())
+        .service(service);
+
+    #[cfg(feature = "metrics")]
+    let service = tower::Layer::layer(
+        &crate::server::axum::layers::metrics::MetricsLayer {
+            service_name: "bench".to_string(),
+        },
+        service,
+    );
+
+    BoxCloneService::new(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{Layer, ServiceExt};
+
+    async fn status(service: BenchService, request: Request<Body>) -> StatusCode {
+        service.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_bench_requests_are_accepted() {
+        assert_eq!(status(ok_service(), request("/")).await, StatusCode::OK);
+        assert_eq!(status(common_stack(ok_service()), request("/")).await, StatusCode::OK);
+        assert_eq!(
+            status(
+                BoxCloneService::new(basic_auth_layer().layer(ok_service())),
+                basic_auth_request("/")
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                BoxCloneService::new(hmac_signature_layer().layer(ok_service())),
+                signed_request("/", b"{}")
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                BoxCloneService::new(time_limiter_layer().layer(ok_service())),
+                request("/")
+            )
+            .await,
+            StatusCode::OK
+        );
+    }
+}
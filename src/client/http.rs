@@ -0,0 +1,816 @@
+//! Outbound HTTP client wrapping `reqwest`
+//!
+//! [`HttpClient`] adds to each request:
+//! - a default timeout (unless the request sets its own)
+//! - retries with exponential backoff for idempotent methods (`GET`, `HEAD`, `PUT`, `DELETE`,
+//!   `OPTIONS`, `TRACE`) on connection errors, timeouts and `429`, `502`, `503`, `504` responses
+//! - an optional [`CircuitBreaker`] failing fast while the upstream service is flapping (connection
+//!   errors, timeouts and 5xx responses count as failures)
+//! - an optional [`AdaptiveThrottle`] limiting the calls per host, tightened when the upstream
+//!   service returns `429` or `503` responses
+//! - an optional [`RetryBudget`] capping the retries to a ratio of the requests, so that the retries
+//!   do not amplify the load of the upstream service during an incident
+//! - an optional cap of the concurrent attempts per host, an attempt waiting for a free slot at
+//!   most its timeout
+//! - optional injected faults (delays and errors) for a named dependency (see [`FaultInjector`]),
+//!   an injected delay counting against the timeout of the attempt
+//! - the `x-request-id` header of the current request (see `RequestIdScopeLayer`) and the trace
+//!   headers of the current span (injected by the global OpenTelemetry propagator)
+//!
+//! Non-2xx responses are returned as [`HttpClientError::Status`], which converts into [`ApiError`]:
+//!
+//! ```rust,no_run
+//! use api_tools::client::http::HttpClient;
+//! use api_tools::server::axum::response::ApiError;
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//!
+//! #[derive(Deserialize)]
+//! struct User {
+//!     id: String,
+//! }
+//!
+//! # async fn run() -> Result<(), ApiError> {
+//! let client = HttpClient::new()?
+//!     .with_timeout(Duration::from_secs(5))
+//!     .with_retries(2, Duration::from_millis(100));
+//! let user: User = client.send_json(client.get("https://users.example.com/users/1")).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::retry_budget::RetryBudget;
+use crate::server::axum::layers::adaptive_throttle::AdaptiveThrottle;
+use crate::server::axum::layers::circuit_breaker::CircuitBreaker;
+use crate::server::axum::layers::fault_injection::{FaultInjector, INJECTED_FAULT_MESSAGE};
+use crate::server::axum::layers::request_id::{REQUEST_ID_HEADER, current_request_id};
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::ApiError;
+use opentelemetry::propagation::Injector;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Default requests timeout
+pub const HTTP_CLIENT_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default delay before the first retry (doubled on each retry)
+pub const HTTP_CLIENT_DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// HTTP client errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum HttpClientError {
+    #[error("HTTP request error: {0}")]
+    Request(String),
+
+    #[error("HTTP request timeout")]
+    Timeout,
+
+    #[error("HTTP response error: {status}")]
+    Status { status: StatusCode, body: String },
+
+    #[error("Invalid HTTP response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Circuit breaker {0} is open")]
+    CircuitOpen(String),
+
+    #[error("Throttle {0} limit is reached")]
+    Throttled(String),
+}
+
+impl HttpClientError {
+    /// Error message of a `Status` error (`message` field of a JSON body, the body otherwise)
+    fn status_message(status: StatusCode, body: &str) -> String {
+        serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|value| value.get("message")?.as_str().map(ToString::to_string))
+            .or_else(|| (!body.is_empty()).then(|| body.to_string()))
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_string())
+    }
+}
+
+impl From<reqwest::Error> for HttpClientError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Request(err.to_string())
+        }
+    }
+}
+
+/// HTTP client error
+impl From<HttpClientError> for ApiError {
+    fn from(value: HttpClientError) -> Self {
+        match value {
+            HttpClientError::Timeout => Self::Timeout,
+            HttpClientError::Throttled(_) => Self::TooManyRequests,
+            HttpClientError::Request(_) | HttpClientError::CircuitOpen(_) => Self::ServiceUnavailable,
+            HttpClientError::Status { status, body } => {
+                let message = HttpClientError::status_message(status, &body);
+                match status {
+                    StatusCode::BAD_REQUEST => Self::BadRequest(message),
+                    StatusCode::NOT_FOUND => Self::NotFound(message),
+                    StatusCode::UNPROCESSABLE_ENTITY => Self::UnprocessableEntity(message),
+                    StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests,
+                    StatusCode::SERVICE_UNAVAILABLE => Self::ServiceUnavailable,
+                    _ => Self::InternalServerError(message),
+                }
+            }
+            HttpClientError::InvalidResponse(message) => Self::InternalServerError(message),
+        }
+    }
+}
+
+/// Outbound HTTP client
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    timeout: Duration,
+    max_retries: u32,
+    retry_delay: Duration,
+    circuit_breaker: Option<CircuitBreaker>,
+    throttle: Option<AdaptiveThrottle>,
+    retry_budget: Option<RetryBudget>,
+    host_concurrency: Option<HostConcurrency>,
+    faults: Option<(FaultInjector, Arc<str>)>,
+}
+
+impl HttpClient {
+    /// Create a new client without retries
+    pub fn new() -> Result<Self, HttpClientError> {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(|err| HttpClientError::Request(err.to_string()))?;
+
+        Ok(Self::from_client(client))
+    }
+
+    /// Create a new client from a configured `reqwest` client
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            timeout: HTTP_CLIENT_DEFAULT_TIMEOUT,
+            max_retries: 0,
+            retry_delay: HTTP_CLIENT_DEFAULT_RETRY_DELAY,
+            circuit_breaker: None,
+            throttle: None,
+            retry_budget: None,
+            host_concurrency: None,
+            faults: None,
+        }
+    }
+
+    /// Set the default requests timeout (per attempt)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of retries and the delay before the first retry
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Protect the calls with a circuit breaker (each attempt is recorded)
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Limit the calls per host with an adaptive throttle (each attempt is counted, `429` and `503`
+    /// responses decrease the limit)
+    pub fn with_throttle(mut self, throttle: AdaptiveThrottle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Cap the retries with a budget (each call counts as a request, whatever its retries)
+    pub fn with_retry_budget(mut self, retry_budget: RetryBudget) -> Self {
+        self.retry_budget = Some(retry_budget);
+        self
+    }
+
+    /// Limit the concurrent attempts per host (an attempt waits for a free slot at most its timeout)
+    pub fn with_max_concurrency_per_host(mut self, max_concurrency: usize) -> Self {
+        self.host_concurrency = Some(HostConcurrency::new(max_concurrency));
+        self
+    }
+
+    /// Inject the faults of a dependency (see [`FaultInjector`]) in the calls, for game-day drills
+    pub fn with_fault_injection(mut self, injector: FaultInjector, dependency: &str) -> Self {
+        self.faults = Some((injector, Arc::from(dependency)));
+        self
+    }
+
+    /// Start building a request
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// Start building a `GET` request
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Start building a `POST` request
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Start building a `PUT` request
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Start building a `PATCH` request
+    pub fn patch(&self, url: &str) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Start building a `DELETE` request
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Send a request, non-2xx responses are returned as [`HttpClientError::Status`]
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, HttpClientError> {
+        let mut request = request.build()?;
+        if request.timeout().is_none() {
+            *request.timeout_mut() = Some(self.timeout);
+        }
+        propagate_context(request.headers_mut());
+
+        let max_retries = if is_idempotent(request.method()) {
+            self.max_retries
+        } else {
+            0
+        };
+        if let Some(retry_budget) = &self.retry_budget {
+            retry_budget.record_request();
+        }
+        let mut attempt = 0;
+        loop {
+            // A request with a streaming body cannot be cloned, so it is not retried
+            let retry = (attempt < max_retries).then(|| request.try_clone()).flatten();
+            let method = request.method().clone();
+            let url = redaction_policy().redact_uri(request.url().as_str()).into_owned();
+
+            if let Some(throttle) = &self.throttle
+                && throttle
+                    .try_acquire(request.url().host_str().unwrap_or_default())
+                    .is_err()
+            {
+                return Err(HttpClientError::Throttled(throttle.name().to_string()));
+            }
+            if let Some(circuit_breaker) = &self.circuit_breaker
+                && !circuit_breaker.try_acquire()
+            {
+                return Err(HttpClientError::CircuitOpen(circuit_breaker.name().to_string()));
+            }
+            let permit = match &self.host_concurrency {
+                Some(host_concurrency) => Some(host_concurrency.acquire(&request, self.timeout).await?),
+                None => None,
+            };
+            let result = match self.inject_fault(&mut request).await {
+                Some(InjectedFault::Response(response)) => Ok(response),
+                Some(InjectedFault::Timeout) => {
+                    if let Some(circuit_breaker) = &self.circuit_breaker {
+                        circuit_breaker.record_failure();
+                    }
+                    drop(permit);
+                    match retry.filter(|_| self.retry_allowed()) {
+                        Some(retry) => {
+                            warn!(%method, %url, attempt, "HTTP request failed, retrying: injected timeout");
+                            request = retry;
+                            tokio::time::sleep(self.retry_delay.saturating_mul(2_u32.saturating_pow(attempt))).await;
+                            attempt += 1;
+                            continue;
+                        }
+                        None => return Err(HttpClientError::Timeout),
+                    }
+                }
+                None => self.client.execute(request).await,
+            };
+            drop(permit);
+            self.record_outcome(&result);
+            request = match (result, retry) {
+                (Ok(response), Some(retry)) if is_retryable_status(response.status()) && self.retry_allowed() => {
+                    warn!(%method, %url, status = %response.status(), attempt, "HTTP request failed, retrying");
+                    retry
+                }
+                (Err(err), Some(retry)) if (err.is_timeout() || err.is_connect()) && self.retry_allowed() => {
+                    warn!(%method, %url, attempt, "HTTP request failed, retrying: {err}");
+                    retry
+                }
+                (Ok(response), _) => return error_for_status(response).await,
+                (Err(err), _) => return Err(err.into()),
+            };
+
+            tokio::time::sleep(self.retry_delay.saturating_mul(2_u32.saturating_pow(attempt))).await;
+            attempt += 1;
+        }
+    }
+
+    /// Return true if the retry budget, if any, allows a retry
+    fn retry_allowed(&self) -> bool {
+        self.retry_budget
+            .as_ref()
+            .is_none_or(|retry_budget| retry_budget.try_retry())
+    }
+
+    /// Apply the injected fault of the dependency, if any, to an attempt
+    ///
+    /// An injected delay shortens the timeout of the request and an injected error returns a
+    /// response without calling the upstream service.
+    async fn inject_fault(&self, request: &mut reqwest::Request) -> Option<InjectedFault> {
+        let (injector, dependency) = self.faults.as_ref()?;
+        let fault = injector.dependency_fault(dependency)?;
+        warn!(dependency = %dependency, ?fault, "Fault injected");
+
+        if let Some(delay) = fault.delay {
+            let timeout = request.timeout().copied().unwrap_or(self.timeout);
+            tokio::time::sleep(delay.min(timeout)).await;
+            if delay >= timeout {
+                return Some(InjectedFault::Timeout);
+            }
+            *request.timeout_mut() = Some(timeout - delay);
+        }
+
+        let status = fault.error?;
+        let response = axum::http::Response::builder()
+            .status(status)
+            .body(INJECTED_FAULT_MESSAGE)
+            .ok()?;
+
+        Some(InjectedFault::Response(Response::from(response)))
+    }
+
+    /// Record the outcome of an attempt in the circuit breaker and the throttle
+    fn record_outcome(&self, result: &Result<Response, reqwest::Error>) {
+        if let (Some(throttle), Ok(response)) = (&self.throttle, result) {
+            throttle.record_status(response.status());
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            match result {
+                Ok(response) if !response.status().is_server_error() => circuit_breaker.record_success(),
+                _ => circuit_breaker.record_failure(),
+            }
+        }
+    }
+
+    /// Send a request and deserialize the JSON response
+    pub async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, HttpClientError> {
+        self.send(request)
+            .await?
+            .json::<T>()
+            .await
+            .map_err(|err| HttpClientError::InvalidResponse(err.to_string()))
+    }
+}
+
+/// Outcome of an injected fault
+enum InjectedFault {
+    /// Response returned instead of calling the upstream service
+    Response(Response),
+
+    /// The injected delay exhausts the timeout of the attempt
+    Timeout,
+}
+
+/// Concurrent attempts cap per host
+#[derive(Debug, Clone)]
+struct HostConcurrency {
+    max_concurrency: usize,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl HostConcurrency {
+    fn new(max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency: max_concurrency.max(1),
+            hosts: Arc::default(),
+        }
+    }
+
+    /// Wait for a free slot of the request host, at most the timeout of the request
+    async fn acquire(
+        &self,
+        request: &reqwest::Request,
+        default_timeout: Duration,
+    ) -> Result<OwnedSemaphorePermit, HttpClientError> {
+        let host = request.url().host_str().unwrap_or_default();
+        let semaphore = self
+            .hosts
+            .lock()
+            .map_err(|err| HttpClientError::Request(err.to_string()))?
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrency)))
+            .clone();
+        let timeout = request.timeout().copied().unwrap_or(default_timeout);
+
+        match tokio::time::timeout(timeout, semaphore.acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(err)) => Err(HttpClientError::Request(err.to_string())),
+            Err(_) => {
+                warn!(
+                    host,
+                    max_concurrency = self.max_concurrency,
+                    "HTTP client host concurrency cap reached"
+                );
+                #[cfg(feature = "metrics")]
+                metrics::counter!("http_client_host_concurrency_exhausted_total", "host" => host.to_string())
+                    .increment(1);
+
+                Err(HttpClientError::Timeout)
+            }
+        }
+    }
+}
+
+/// Return a [`HttpClientError::Status`] error (with the response body) if the response is not 2xx
+pub async fn error_for_status(response: Response) -> Result<Response, HttpClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(HttpClientError::Status { status, body })
+}
+
+/// Idempotent methods (RFC 9110)
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Statuses worth retrying
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Add the request ID and the trace headers of the current request
+fn propagate_context(headers: &mut HeaderMap) {
+    if !headers.contains_key(REQUEST_ID_HEADER.clone())
+        && let Some(value) = current_request_id().and_then(|id| HeaderValue::from_str(&id).ok())
+    {
+        headers.insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// OpenTelemetry injector for `HeaderMap`
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::layers::circuit_breaker::CircuitBreaker;
+    use crate::server::axum::layers::fault_injection::{Fault, FaultTarget};
+    use crate::server::axum::layers::request_id::scope_request_id;
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::HeaderMap as AxumHeaderMap;
+    use axum::routing::{get, post};
+    use opentelemetry::Context;
+    use opentelemetry::propagation::{Extractor, TextMapPropagator};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Propagator injecting a fixed `traceparent` header
+    #[derive(Debug)]
+    struct TestPropagator;
+
+    impl TextMapPropagator for TestPropagator {
+        fn inject_context(&self, _cx: &Context, injector: &mut dyn Injector) {
+            injector.set("traceparent", "00-test".to_string());
+        }
+
+        fn extract_with_context(&self, cx: &Context, _extractor: &dyn Extractor) -> Context {
+            cx.clone()
+        }
+
+        fn fields(&self) -> opentelemetry::propagation::text_map_propagator::FieldIter<'_> {
+            opentelemetry::propagation::text_map_propagator::FieldIter::new(&[])
+        }
+    }
+
+    /// Start a fake upstream server, returning its URL and its calls counter
+    async fn start_server() -> (String, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let app = Router::new()
+            .route(
+                "/headers",
+                get(|headers: AxumHeaderMap| async move {
+                    let header = |name: &str| {
+                        headers
+                            .get(name)
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or_default()
+                            .to_string()
+                    };
+                    axum::Json(serde_json::json!({
+                        "request_id": header("x-request-id"),
+                        "traceparent": header("traceparent"),
+                    }))
+                }),
+            )
+            .route(
+                "/flaky",
+                get(|State(calls): State<Arc<AtomicU32>>| async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    }
+                })
+                .post(|State(calls): State<Arc<AtomicU32>>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::SERVICE_UNAVAILABLE
+                }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    StatusCode::OK
+                }),
+            )
+            .route(
+                "/not-found",
+                post(|| async {
+                    (
+                        StatusCode::NOT_FOUND,
+                        axum::Json(serde_json::json!({ "code": 404, "message": "User not found" })),
+                    )
+                }),
+            )
+            .with_state(calls.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, calls)
+    }
+
+    #[tokio::test]
+    async fn test_send_propagates_request_context() {
+        let (url, _) = start_server().await;
+        opentelemetry::global::set_text_map_propagator(TestPropagator);
+        let client = HttpClient::new().unwrap();
+
+        let headers: serde_json::Value = scope_request_id(
+            "abc-123".to_string(),
+            client.send_json(client.get(&format!("{url}/headers"))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(headers["request_id"], "abc-123");
+        assert_eq!(headers["traceparent"], "00-test");
+
+        // An explicit request ID is kept
+        let headers: serde_json::Value = scope_request_id(
+            "abc-123".to_string(),
+            client.send_json(client.get(&format!("{url}/headers")).header("x-request-id", "custom")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(headers["request_id"], "custom");
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_idempotent_requests() {
+        let (url, calls) = start_server().await;
+        let client = HttpClient::new().unwrap().with_retries(3, Duration::from_millis(1));
+
+        let response = client.send(client.get(&format!("{url}/flaky"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Not idempotent
+        calls.store(0, Ordering::SeqCst);
+        let err = client.send(client.post(&format!("{url}/flaky"))).await.unwrap_err();
+        assert!(matches!(
+            err,
+            HttpClientError::Status {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_without_retries() {
+        let (url, calls) = start_server().await;
+        let client = HttpClient::new().unwrap();
+
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(ApiError::from(err), ApiError::ServiceUnavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_budget() {
+        let (url, calls) = start_server().await;
+        let client = HttpClient::new()
+            .unwrap()
+            .with_retries(3, Duration::from_millis(1))
+            .with_retry_budget(RetryBudget::new("upstream", 0.0, Duration::from_secs(60)).with_min_retries(1));
+
+        // Only one retry is allowed by the budget
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert!(matches!(
+            err,
+            HttpClientError::Status {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_max_concurrency_per_host() {
+        let (url, _) = start_server().await;
+        let client = HttpClient::new()
+            .unwrap()
+            .with_timeout(Duration::from_millis(700))
+            .with_max_concurrency_per_host(1);
+
+        let slow = tokio::spawn({
+            let client = client.clone();
+            let url = url.clone();
+            async move { client.send(client.get(&format!("{url}/slow"))).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The slot of the host is taken by the slow request
+        let err = client
+            .send(
+                client
+                    .get(&format!("{url}/headers"))
+                    .timeout(Duration::from_millis(100)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err, HttpClientError::Timeout);
+
+        assert!(slow.await.unwrap().is_ok());
+        assert!(client.send(client.get(&format!("{url}/headers"))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_with_circuit_breaker() {
+        let (url, calls) = start_server().await;
+        let breaker = CircuitBreaker::new("flaky")
+            .with_window_size(2)
+            .with_minimum_calls(2)
+            .with_open_duration(Duration::from_secs(60));
+        let client = HttpClient::new()
+            .unwrap()
+            .with_retries(3, Duration::from_millis(1))
+            .with_circuit_breaker(breaker);
+
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(err, HttpClientError::CircuitOpen("flaky".to_string()));
+        assert_eq!(ApiError::from(err), ApiError::ServiceUnavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_throttle() {
+        let (url, calls) = start_server().await;
+        let throttle = AdaptiveThrottle::new("flaky", 2, Duration::from_secs(60));
+        let client = HttpClient::new()
+            .unwrap()
+            .with_retries(3, Duration::from_millis(1))
+            .with_throttle(throttle.clone());
+
+        // The first 503 response halves the limit, so the retry is throttled
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(err, HttpClientError::Throttled("flaky".to_string()));
+        assert_eq!(ApiError::from(err), ApiError::TooManyRequests);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(throttle.current_limit(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_timeout() {
+        let (url, _) = start_server().await;
+        let client = HttpClient::new().unwrap().with_timeout(Duration::from_millis(50));
+
+        let err = client.send(client.get(&format!("{url}/slow"))).await.unwrap_err();
+        assert_eq!(err, HttpClientError::Timeout);
+        assert_eq!(ApiError::from(err), ApiError::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_fault_injection() {
+        let (url, calls) = start_server().await;
+        let injector = FaultInjector::new();
+        let breaker = CircuitBreaker::new("users")
+            .with_window_size(2)
+            .with_minimum_calls(2)
+            .with_open_duration(Duration::from_secs(60));
+        let client = HttpClient::new()
+            .unwrap()
+            .with_timeout(Duration::from_millis(50))
+            .with_circuit_breaker(breaker)
+            .with_fault_injection(injector.clone(), "users");
+
+        // Injected delay exhausting the timeout budget
+        injector.set(
+            FaultTarget::dependency("users"),
+            Fault::delay(Duration::from_millis(60)),
+        );
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(err, HttpClientError::Timeout);
+
+        // Injected error opening the circuit breaker
+        injector.set(
+            FaultTarget::dependency("users"),
+            Fault::error(StatusCode::SERVICE_UNAVAILABLE),
+        );
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(ApiError::from(err), ApiError::ServiceUnavailable);
+        let err = client.send(client.get(&format!("{url}/flaky"))).await.unwrap_err();
+        assert_eq!(err, HttpClientError::CircuitOpen("users".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_json_status_error() {
+        let (url, _) = start_server().await;
+        let client = HttpClient::new().unwrap();
+
+        let err = client
+            .send_json::<serde_json::Value>(client.post(&format!("{url}/not-found")))
+            .await
+            .unwrap_err();
+        assert_eq!(ApiError::from(err), ApiError::NotFound("User not found".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_send_connection_error() {
+        let client = HttpClient::new().unwrap();
+
+        let err = client.send(client.get("http://127.0.0.1:1")).await.unwrap_err();
+        assert!(matches!(err, HttpClientError::Request(_)));
+        assert_eq!(ApiError::from(err), ApiError::ServiceUnavailable);
+    }
+
+    #[test]
+    fn test_api_error_from_status() {
+        let error = |status: StatusCode, body: &str| {
+            ApiError::from(HttpClientError::Status {
+                status,
+                body: body.to_string(),
+            })
+        };
+
+        assert_eq!(
+            error(StatusCode::BAD_REQUEST, "Invalid name"),
+            ApiError::BadRequest("Invalid name".to_string())
+        );
+        assert_eq!(
+            error(StatusCode::UNPROCESSABLE_ENTITY, ""),
+            ApiError::UnprocessableEntity("Unprocessable Entity".to_string())
+        );
+        assert_eq!(error(StatusCode::TOO_MANY_REQUESTS, ""), ApiError::TooManyRequests);
+        assert_eq!(
+            error(StatusCode::UNAUTHORIZED, r#"{"message":"Invalid token"}"#),
+            ApiError::InternalServerError("Invalid token".to_string())
+        );
+    }
+}
@@ -0,0 +1,4 @@
+//! Outbound HTTP clients (`client` feature)
+
+pub mod http;
+pub mod retry_budget;
@@ -0,0 +1,178 @@
+//! Retry budget of the outbound HTTP client
+//!
+//! A [`RetryBudget`] caps the retries to a ratio of the requests over a sliding window, so that
+//! the retries do not amplify the load of an upstream service during an incident: with a 10%
+//! ratio, at most one request out of ten can be retried, whatever the number of retries per
+//! request. A minimum number of retries per window keeps the retries available for low-traffic
+//! clients.
+//!
+//! With the `metrics` feature, the `http_client_retry_budget_exhausted_total` counter (label
+//! `name`) counts the retries denied by the budget.
+//!
+//! ```rust
+//! use api_tools::client::retry_budget::RetryBudget;
+//! use std::time::Duration;
+//!
+//! let budget = RetryBudget::new("users-service", 0.1, Duration::from_secs(10)).with_min_retries(0);
+//!
+//! for _ in 0..10 {
+//!     budget.record_request();
+//! }
+//! assert!(budget.try_retry());
+//! assert!(!budget.try_retry());
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default minimum number of retries allowed per window
+pub const RETRY_BUDGET_DEFAULT_MIN_RETRIES: u32 = 10;
+
+/// Number of buckets of the sliding window
+const RETRY_BUDGET_BUCKETS: u32 = 10;
+
+/// Requests and retries of a bucket of the window
+#[derive(Debug)]
+struct Bucket {
+    started_at: Instant,
+    requests: u64,
+    retries: u64,
+}
+
+/// Retry budget (clones share the same state)
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    name: Arc<str>,
+    ratio: f64,
+    window: Duration,
+    min_retries: u32,
+    buckets: Arc<Mutex<VecDeque<Bucket>>>,
+}
+
+impl RetryBudget {
+    /// Create a new budget allowing `ratio` (between 0 and 1) retries per request over `window`
+    pub fn new(name: &str, ratio: f64, window: Duration) -> Self {
+        Self {
+            name: Arc::from(name),
+            ratio: ratio.clamp(0.0, 1.0),
+            window: window.max(Duration::from_millis(RETRY_BUDGET_BUCKETS as u64)),
+            min_retries: RETRY_BUDGET_DEFAULT_MIN_RETRIES,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Set the minimum number of retries allowed per window
+    pub fn with_min_retries(mut self, min_retries: u32) -> Self {
+        self.min_retries = min_retries;
+        self
+    }
+
+    /// Budget name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Count a request (not its retries)
+    pub fn record_request(&self) {
+        self.update(|bucket| bucket.requests += 1);
+    }
+
+    /// Return true if a retry is allowed, and count it
+    pub fn try_retry(&self) -> bool {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return true;
+        };
+        self.current_bucket(&mut buckets);
+        let (requests, retries) = buckets.iter().fold((0, 0), |(requests, retries), bucket| {
+            (requests + bucket.requests, retries + bucket.retries)
+        });
+
+        let allowed = (retries as f64) < requests as f64 * self.ratio + f64::from(self.min_retries);
+        if allowed {
+            if let Some(bucket) = buckets.back_mut() {
+                bucket.retries += 1;
+            }
+        } else {
+            warn!(name = %self.name, requests, retries, "Retry budget exhausted");
+            self.record_exhausted();
+        }
+
+        allowed
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Bucket)) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            self.current_bucket(&mut buckets);
+            if let Some(bucket) = buckets.back_mut() {
+                f(bucket);
+            }
+        }
+    }
+
+    /// Remove the buckets out of the window and add the current bucket if needed
+    fn current_bucket(&self, buckets: &mut VecDeque<Bucket>) {
+        let now = Instant::now();
+        while buckets
+            .front()
+            .is_some_and(|bucket| now.duration_since(bucket.started_at) >= self.window)
+        {
+            buckets.pop_front();
+        }
+
+        let bucket_duration = self.window / RETRY_BUDGET_BUCKETS;
+        if buckets
+            .back()
+            .is_none_or(|bucket| now.duration_since(bucket.started_at) >= bucket_duration)
+        {
+            buckets.push_back(Bucket {
+                started_at: now,
+                requests: 0,
+                retries: 0,
+            });
+        }
+    }
+
+    /// Count a retry denied by the budget
+    fn record_exhausted(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("http_client_retry_budget_exhausted_total", "name" => self.name.to_string()).increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_budget_ratio() {
+        let budget = RetryBudget::new("test", 0.2, Duration::from_secs(60)).with_min_retries(1);
+        assert_eq!(budget.name(), "test");
+
+        // Minimum number of retries without requests
+        assert!(budget.try_retry());
+        assert!(!budget.try_retry());
+
+        for _ in 0..10 {
+            budget.record_request();
+        }
+        assert!(budget.try_retry());
+        assert!(budget.try_retry());
+        assert!(!budget.try_retry());
+    }
+
+    #[test]
+    fn test_retry_budget_sliding_window() {
+        let budget = RetryBudget::new("test", 0.5, Duration::from_millis(50)).with_min_retries(0);
+        budget.record_request();
+        budget.record_request();
+        assert!(budget.try_retry());
+        assert!(!budget.try_retry());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!budget.try_retry());
+        budget.record_request();
+        budget.record_request();
+        assert!(budget.try_retry());
+    }
+}
@@ -0,0 +1,424 @@
+//! Demo application (`examples` feature)
+//!
+//! [`demo_router`] assembles the crate layers, extractors and value objects in a small users API.
+//! Run it with `cargo run --example demo --features examples`.
+//!
+//! | Route              | Description                                                          |
+//! | ------------------ | -------------------------------------------------------------------- |
+//! | `GET /health`      | Health check                                                         |
+//! | `POST /token`      | Mock authentication: returns a JWT for any username                  |
+//! | `GET /users`       | List users (JWT, `ApiQuery` pagination)                              |
+//! | `POST /users`      | Create a user (JWT, `Dto` with `Email` validation)                   |
+//! | `GET /users/{id}`  | Get a user (JWT)                                                     |
+//! | `POST /webhooks`   | Webhook signed with `HmacSignatureLayer`                             |
+//! | `GET /metrics`     | Prometheus metrics (Basic auth), only if a `PrometheusHandle` is set |
+
+use crate::ApiQuery;
+use crate::server::axum::extractors::{Dep, Dto, FromDto, Path, RequestId};
+use crate::server::axum::handlers::prometheus::metrics_router;
+use crate::server::axum::layers::basic_auth::BasicAuthLayer;
+use crate::server::axum::layers::circuit_breaker::{CircuitBreaker, CircuitBreakerLayer};
+use crate::server::axum::layers::context::ContextLayer;
+use crate::server::axum::layers::cors::{CorsConfig, cors};
+use crate::server::axum::layers::hmac_signature::HmacSignatureLayer;
+use crate::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+use crate::server::axum::layers::injector::{Injector, InjectorLayer};
+use crate::server::axum::layers::logger::LoggerLayer;
+use crate::server::axum::layers::metrics::MetricsLayer;
+use crate::server::axum::layers::request_id::RequestIdLayer;
+use crate::server::axum::layers::security_headers::{SecurityHeadersConfig, SecurityHeadersLayer};
+use crate::server::axum::layers::time_limiter::TimeLimiterLayer;
+use crate::server::axum::response::{ApiError, ApiSuccess, ValidationErrors};
+use crate::server::axum::security::jwt::Jwt;
+use crate::server::axum::security::jwt::access_token::AccessToken;
+use crate::value_objects::datetime::UtcDateTime;
+use crate::value_objects::email::Email;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{Method, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::TimeDelta;
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// Demo application configuration
+#[derive(Clone)]
+pub struct DemoConfig {
+    pub jwt_secret: String,
+    pub webhook_secret: String,
+    pub metrics_username: String,
+    pub metrics_password: String,
+    pub prometheus_handle: Option<PrometheusHandle>,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            jwt_secret: "demo-jwt-secret".to_string(),
+            webhook_secret: "demo-webhook-secret".to_string(),
+            metrics_username: "admin".to_string(),
+            metrics_password: "admin".to_string(),
+            prometheus_handle: None,
+        }
+    }
+}
+
+/// Build the demo router
+///
+/// The Prometheus recorder is not installed: set `prometheus_handle` (e.g. with
+/// `PrometheusHandler::get_handle()`) to expose the `/metrics` route.
+pub fn demo_router(config: DemoConfig) -> Result<Router, ApiError> {
+    let jwt = Jwt::init("HS256", 15, 24, Some(&config.jwt_secret), None, None)?;
+    let injector = Injector::new().singleton(jwt).singleton(UserStore::default());
+
+    let mut router = Router::new()
+        .route("/health", get(health))
+        .route("/token", post(token))
+        .route("/users", get(list_users).post(create_user))
+        .route("/users/{id}", get(get_user))
+        .route(
+            "/webhooks",
+            post(webhook).layer(HmacSignatureLayer::new(&[&config.webhook_secret])),
+        );
+
+    if let Some(handle) = config.prometheus_handle {
+        router = router.merge(
+            metrics_router(handle).route_layer(BasicAuthLayer::new(&config.metrics_username, &config.metrics_password)),
+        );
+    }
+
+    Ok(router
+        .layer(InjectorLayer::new(injector))
+        .layer(CircuitBreakerLayer::new(CircuitBreaker::new("demo")))
+        .layer(TimeLimiterLayer::new("".into()))
+        .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024 * 1_024)))
+        .layer(cors(CorsConfig {
+            allow_origin: "*",
+            allow_methods: vec![Method::GET, Method::POST],
+            allow_headers: vec![header::AUTHORIZATION, header::CONTENT_TYPE],
+        }))
+        .layer(SecurityHeadersLayer::new(SecurityHeadersConfig::default()))
+        .layer(MetricsLayer {
+            service_name: "demo".to_string(),
+        })
+        .layer(LoggerLayer)
+        .layer(ContextLayer::new())
+        .layer(RequestIdLayer::new()))
+}
+
+/// User
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct User {
+    pub id: Uuid,
+    pub name: String,
+    pub email: Email,
+    pub created_at: UtcDateTime,
+}
+
+/// In-memory users repository
+#[derive(Debug, Default)]
+pub struct UserStore {
+    users: RwLock<Vec<User>>,
+}
+
+/// JWT claims
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: i64,
+}
+
+/// Authenticated user (valid JWT in the `Authorization` header)
+pub struct Authenticated(pub Claims);
+
+impl<S> FromRequestParts<S> for Authenticated
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Dep(jwt) = Dep::<Jwt>::from_request_parts(parts, state).await?;
+        let token = AccessToken::from_request_parts(parts, state).await?;
+        let claims = jwt
+            .parse::<Claims>(&token)
+            .map_err(|_| ApiError::Unauthorized("Invalid token".to_string()))?;
+
+        Ok(Self(claims))
+    }
+}
+
+/// Users list query
+#[derive(Debug, ApiQuery)]
+pub struct ListUsersQuery {
+    #[api_query(min = 1, default = 1)]
+    pub page: u32,
+    #[api_query(min = 1, max = 100, default = 20)]
+    pub limit: u32,
+}
+
+/// User creation body
+#[derive(Debug, Deserialize)]
+pub struct CreateUserDto {
+    pub name: String,
+    pub email: String,
+}
+
+impl FromDto for User {
+    type Dto = CreateUserDto;
+
+    fn from_dto(dto: CreateUserDto) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if dto.name.trim().is_empty() {
+            errors.add("name", "must not be empty");
+        }
+        let email = Email::new(&dto.email).map_err(|err| errors.add("email", &err.to_string()));
+
+        match email {
+            Ok(email) if errors.is_empty() => Ok(User {
+                id: Uuid::new_v4(),
+                name: dto.name.trim().to_string(),
+                email,
+                created_at: UtcDateTime::now(),
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+/// Token request body
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub username: String,
+}
+
+/// Token response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub expired_at: UtcDateTime,
+}
+
+async fn health(request_id: RequestId) -> String {
+    format!("OK ({})", request_id.0.to_str().unwrap_or_default())
+}
+
+async fn token(Dep(jwt): Dep<Jwt>, Json(request): Json<TokenRequest>) -> Result<impl IntoResponse, ApiError> {
+    let expired_at = UtcDateTime::now().add(TimeDelta::minutes(jwt.access_lifetime()));
+    let claims = Claims {
+        sub: request.username,
+        exp: expired_at.timestamp(),
+    };
+    let token = jwt.generate(claims, expired_at.clone())?;
+
+    Ok(ApiSuccess::new(
+        StatusCode::OK,
+        TokenResponse {
+            access_token: token.token,
+            expired_at,
+        },
+    ))
+}
+
+async fn list_users(
+    _: Authenticated,
+    Dep(store): Dep<UserStore>,
+    query: ListUsersQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let users = store
+        .users
+        .read()
+        .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+    let offset = ((query.page - 1) * query.limit) as usize;
+    let page = users
+        .iter()
+        .skip(offset)
+        .take(query.limit as usize)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Ok(ApiSuccess::new(StatusCode::OK, page))
+}
+
+async fn create_user(
+    _: Authenticated,
+    Dep(store): Dep<UserStore>,
+    Dto(user): Dto<User>,
+) -> Result<impl IntoResponse, ApiError> {
+    store
+        .users
+        .write()
+        .map_err(|err| ApiError::InternalServerError(err.to_string()))?
+        .push(user.clone());
+
+    Ok(ApiSuccess::new(StatusCode::CREATED, user))
+}
+
+async fn get_user(
+    _: Authenticated,
+    Dep(store): Dep<UserStore>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let users = store
+        .users
+        .read()
+        .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+    let user = users
+        .iter()
+        .find(|user| user.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("User {id} not found")))?;
+
+    Ok(ApiSuccess::new(StatusCode::OK, user))
+}
+
+async fn webhook(body: String) -> Response {
+    (StatusCode::ACCEPTED, format!("Webhook received ({} bytes)", body.len())).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::layers::hmac_signature::sign_request;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn call(app: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (
+            status,
+            serde_json::from_slice(&body).unwrap_or_else(|_| String::from_utf8_lossy(&body).into()),
+        )
+    }
+
+    async fn access_token(app: &Router) -> String {
+        let (status, body) = call(
+            app,
+            Request::post("/token")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"username":"demo"}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        body["access_token"].as_str().unwrap().to_string()
+    }
+
+    fn authorized(method: Method, uri: &str, token: &str, body: Body) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_demo_health_and_layers() {
+        let app = demo_router(DemoConfig::default()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/health")
+                    .header("x-request-id", "abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("x-request-id").unwrap(), "abc");
+        assert_eq!(response.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "OK (abc)");
+
+        // No metrics route without Prometheus handle
+        let (status, _) = call(&app, Request::get("/metrics").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_demo_users() {
+        let app = demo_router(DemoConfig::default()).unwrap();
+        let token = access_token(&app).await;
+
+        // Unauthenticated
+        let (status, _) = call(&app, Request::get("/users").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&app, authorized(Method::GET, "/users", "invalid", Body::empty())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Creation
+        let body = Body::from(r#"{"name":"Bob","email":"Bob@Example.com"}"#);
+        let (status, user) = call(&app, authorized(Method::POST, "/users", &token, body)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(user["email"], "bob@example.com");
+
+        let body = Body::from(r#"{"name":"","email":"invalid"}"#);
+        let (status, errors) = call(&app, authorized(Method::POST, "/users", &token, body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(errors["message"].as_array().unwrap().len(), 2);
+
+        // List
+        let (status, users) = call(
+            &app,
+            authorized(Method::GET, "/users?page=1&limit=10", &token, Body::empty()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(users.as_array().unwrap().len(), 1);
+        let (status, _) = call(
+            &app,
+            authorized(Method::GET, "/users?limit=1000", &token, Body::empty()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Get
+        let uri = format!("/users/{}", user["id"].as_str().unwrap());
+        let (status, found) = call(&app, authorized(Method::GET, &uri, &token, Body::empty())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(found, user);
+        let uri = format!("/users/{}", Uuid::new_v4());
+        let (status, _) = call(&app, authorized(Method::GET, &uri, &token, Body::empty())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_demo_webhook() {
+        let app = demo_router(DemoConfig::default()).unwrap();
+        let body = r#"{"event":"user.created"}"#;
+        let timestamp = UtcDateTime::now().timestamp();
+
+        let request = Request::post("/webhooks")
+            .header("x-timestamp", timestamp)
+            .header(
+                "x-signature",
+                sign_request("demo-webhook-secret", timestamp, body.as_bytes()),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        let (status, response) = call(&app, request).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(response, "Webhook received (24 bytes)");
+
+        let request = Request::post("/webhooks")
+            .header("x-timestamp", timestamp)
+            .header("x-signature", sign_request("other-secret", timestamp, body.as_bytes()))
+            .body(Body::from(body))
+            .unwrap();
+        let (status, _) = call(&app, request).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
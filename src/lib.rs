@@ -9,43 +9,111 @@
 //!
 //! ## Features list
 //!
-//! | Name         | Description                       | Default |
-//! | ------------ | --------------------------------- | :-----: |
-//! | `axum`       | Enable Axum feature               |   ❌    |
-//! | `prometheus` | Enable Prometheus metrics feature |   ❌    |
-//! | `full`       | Enable all features               |   ❌    |
+//! | Name          | Description                                                                                                                                               | Default |
+//! | ------------- | --------------------------------------------------------------------------------------------------------------------------------------------------------- | :-----: |
+//! | `axum`        | Enable Axum feature (enables `std` and `tz`)                                                                                                              |   ❌    |
+//! | `bench`       | Enable the `bench` module (minimal services, requests and layers used by the criterion benches, `cargo bench --features bench`)                           |   ❌    |
+//! | `cbor`        | Enable the CBOR format of `NegotiatedResponse` and the `Negotiated` extractor (`ciborium`, enables `axum`)                                                |   ❌    |
+//! | `client`      | Enable the outbound `HttpClient` (`reqwest` HTTP client, enables `axum`)                                                                                  |   ❌    |
+//! | `config-toml` | Enable the TOML files of the `ConfigLoader` (enables `axum`)                                                                                              |   ❌    |
+//! | `config-yaml` | Enable the YAML files of the `ConfigLoader` (enables `axum`)                                                                                              |   ❌    |
+//! | `derive`      | Enable `#[derive(ApiQuery)]` macro (enables `axum`)                                                                                                       |   ❌    |
+//! | `examples`    | Enable the `demo` module (`demo_router()` assembling the layers, extractors and value objects) and the `demo` example (enables `derive` and `prometheus`) |   ❌    |
+//! | `grpc`        | Enable the conversions between `ApiError` and `tonic::Status` (gRPC, enables `axum`)                                                                      |   ❌    |
+//! | `jsonapi`     | Enable the JSON:API documents (`Document`, `Resource`) and errors (`JsonApiErrors` from `ApiError`) (enables `axum`)                                      |   ❌    |
+//! | `oidc`        | Enable OpenID Connect discovery for `Jwt` and OAuth2 token exchange (`reqwest` HTTP client, enables `axum`)                                               |   ❌    |
+//! | `openapi`     | Enable OpenAPI schemas (`utoipa`) of the error envelope and the value objects (enables `axum`)                                                            |   ❌    |
+//! | `metrics`     | Enable the exporter-agnostic `MetricsLayer` (`metrics` facade) and the host metrics collector (enables `axum`)                                            |   ❌    |
+//! | `msgpack`     | Enable the MessagePack format of `NegotiatedResponse` and the `Negotiated` extractor (`rmp-serde`, enables `axum`)                                        |   ❌    |
+//! | `otlp`        | Enable the OTLP/HTTP (JSON) push metrics exporter (enables `metrics`)                                                                                     |   ❌    |
+//! | `password`    | Enable password hashing (Argon2id) and strength checking (enables `axum`)                                                                                 |   ❌    |
+//! | `pool-redis`  | Enable the `CheckedResource` adapter of the Redis `ConnectionManager` (`redis`, enables `axum`)                                                           |   ❌    |
+//! | `pool-sqlx`   | Enable the `CheckedResource` adapter of `sqlx::Pool` (`sqlx`, enables `axum`)                                                                             |   ❌    |
+//! | `prometheus`  | Enable Prometheus metrics feature (enables `metrics`)                                                                                                     |   ❌    |
+//! | `query-qs`    | Enable the `QueryQs` extractor (sequences and nested structs in query strings, `serde_qs`, enables `axum`)                                                |   ❌    |
+//! | `statsd`      | Enable the StatsD (DogStatsD tags) UDP metrics exporter (enables `metrics`)                                                                               |   ❌    |
+//! | `std`         | Enable the value objects which need the standard library (`UtcDateTime::now`, `QueryFilters::parse`). Without it, the crate is `no_std` (with `alloc`)    |   ✅    |
+//! | `testing`     | Enable testing helpers (`TestClient` and response assertions, `MockJwtIssuer`, response snapshots for contract tests, router fuzzing, enables `axum`)     |   ❌    |
+//! | `typed-id`    | Enable the strongly-typed `Id<T>` value object (prefixed UUIDs, `uuid`)                                                                                   |   ❌    |
+//! | `tz`          | Enable `Timezone` and the time zone conversions of `UtcDateTime` (`chrono-tz`)                                                                            |   ✅    |
+//! | `full`        | Enable all features                                                                                                                                       |   ❌    |
 //!
 //! ## Components
 //!
 //! ### Value objects
 //!
-//! | Name          | Description                                                                                |
-//! | ------------- | ------------------------------------------------------------------------------------------ |
-//! | `UtcDateTime` | A wrapper around `chrono::DateTime` to handle date and time values in UTC                  |
-//! | `Timezone`    | A wrapper around `chrono_tz::Tz` to handle time zones                                      |
-//! | `Pagination`  | A struct to handle pagination parameters, including page number, page size and total count |
-//! | `QuerySort`   | A struct to handle sorting query parameters, including field and direction                 |
+//! | Name            | Description                                                                                                                           |
+//! | --------------- | ------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `UtcDateTime`   | A wrapper around `chrono::DateTime` to handle date and time values in UTC (arithmetic, truncation, start/end of day)                  |
+//! | `Timezone`      | A wrapper around `chrono_tz::Tz` to handle time zones                                                                                 |
+//! | `Pagination`    | A struct to handle pagination parameters, including page number, page size and total count                                            |
+//! | `QuerySort`     | A struct to handle sorting query parameters, including field and direction                                                            |
+//! | `QueryFilter`   | A struct to handle filtering query parameters (`filter[field][operator]=value`) checked against an allow-list of fields and operators |
+//! | `Email`         | A validated and lowercased email address, redacted in `Display` and `Debug` (`j***@example.com`)                                      |
+//! | `Money`         | A fixed-point amount with an ISO-4217 `Currency`, rounding modes and arithmetic that refuses to mix currencies                        |
+//! | `DateTimeRange` | Half-open range of `UtcDateTime` with `contains`, `overlaps`, `intersection`, `duration` and daily splitting                          |
+//! | `Id<T>`         | UUID-backed ID of a resource kind with a prefixed string form (`usr_9f8e...`), serde and `FromStr` (`typed-id` feature)               |
+//!
+//! ### Client
+//!
+//! | Name         | Description                                                                                                                                                                                                                                                         |
+//! | ------------ | ------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `HttpClient` | Outbound HTTP client (`client` feature) with timeouts, retries of idempotent requests, `x-request-id`/trace headers propagation, optional circuit breaker, adaptive throttle, retry budget and per-host concurrency cap, and non-2xx responses mapped to `ApiError` |
 //!
 //! ### Axum
 //!
 //! #### Security
 //!
-//! | Name          | Description                      |
-//! |---------------|----------------------------------|
-//! | `Jwt` | A wrapper for JWT generation and parsing |
+//! | Name                  | Description                                                                                                                                                                                                                                                                                                                              |
+//! | --------------------- | ---------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `Jwt`                 | A wrapper for JWT generation and parsing (JWKS keys, rotating keys selected by `kid` with `JwtKey` and `Jwt::init_with_keys`, `JwtValidation` of the issuer, audience, subject, leeway and required claims with per-call overrides, OpenID Connect discovery with the `oidc` feature, revocation by `jti` with a `TokenRevocationStore`) |
+//! | `TokenExchangeClient` | OAuth2 token exchange (RFC 8693) client to downscope tokens before calling internal services (`oidc` feature)                                                                                                                                                                                                                            |
+//! | `Grants`              | Roles, permissions and scopes of the caller, checked against a `Requirement` (`all_of` / `any_of`) by the `AuthorizeLayer`                                                                                                                                                                                                               |
+//! | `hash_password`       | Argon2id password hashing, `verify_password` rehashes when the parameters change, `PasswordPolicy` checks the strength (`password` feature)                                                                                                                                                                                              |
+//! | `RedactionPolicy`     | Sensitive headers, fields and patterns redacted in the logs, audit diffs and error messages (`set_redaction_policy`)                                                                                                                                                                                                                     |
+//! | `MockJwtIssuer`       | Generates an RSA or EC key pair, signs test tokens and serves its JWKS and discovery document in process (`testing` feature)                                                                                                                                                                                                             |
 //!
 //! #### Layers
 //!
-//! | Name                   | Description                                                                                                                              |
-//! | ---------------------- | ---------------------------------------------------------------------------------------------------------------------------------------- |
-//! | `BasicAuthLayer`       | Provides HTTP Basic Authentication middleware for protecting routes with username and password                                           |
-//! | `CorsLayer`            | Adds Cross-Origin Resource Sharing (CORS) headers to responses, allowing or restricting resource sharing between different origins       |
-//! | `HttpErrorsLayer`      | Middleware for intercepting and customizing HTTP error responses, enabling standardized error handling across your API                   |
-//! | `LoggerLayer`          | Logs incoming requests and outgoing responses, useful for debugging and monitoring API activity                                          |
-//! | `RequestId`            | Middleware that generates and attaches a unique request identifier (UUID) to each incoming request for traceability                      |
-//! | `TimeLimiterLayer`     | Middleware that restricts API usage to specific time slots. Outside of these allowed periods, it returns a 503 Service Unavailable error |
-//! | `PrometheusLayer`      | Middleware that collects and exposes Prometheus-compatible metrics for monitoring API performance and usage                              |
-//! | `SecurityHeadersLayer` | Middleware add security headers like (CSP, etc.)                                                                                         |
+//! | Name                      | Description                                                                                                                                                                                                                                                                                                                                                                                                                                                  |
+//! | ------------------------- | ------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------ |
+//! | `BasicAuthLayer`          | Provides HTTP Basic Authentication middleware for protecting routes with a set of users (constant-time comparison) or an async validator, and a configurable realm                                                                                                                                                                                                                                                                                           |
+//! | `CorsLayer`               | Adds Cross-Origin Resource Sharing (CORS) headers to responses, allowing or restricting resource sharing between different origins. `CorsBuilder` adds exposed headers, preflight caching, wildcard subdomains and named profiles (`CorsProfiles`)                                                                                                                                                                                                           |
+//! | `HttpErrorsLayer`         | Middleware for intercepting and customizing HTTP error responses, enabling standardized error handling across your API. The rewritten status codes, their messages and the passthrough content-types and paths are configurable                                                                                                                                                                                                                              |
+//! | `LoggerLayer`             | Logs incoming requests and outgoing responses, useful for debugging and monitoring API activity. `configure_logger(LoggerConfig)` selects the `LogFormat` (`tracing` fields, JSON or logfmt lines, HTTP fields optionally nested under `http.*`) and the `LogSamplingRule`s of the info logs (ratio and per-second cap by route pattern, method and status)                                                                                                  |
+//! | `RequestIdLayer`          | Keeps the incoming request identifier (optionally validated as a UUID) or generates one, adds it to the tracing span and copies it to the response headers. `MakeRequestUuid` generates request IDs for `tower-http`. `RequestIdScopeLayer` makes it available through `current_request_id()` for outbound requests                                                                                                                                          |
+//! | `TimeLimiterLayer`        | Middleware that restricts API usage to specific time slots. Outside of these allowed periods, it returns a 503 Service Unavailable error. Time slots can be set per route pattern with `RouteConfig`                                                                                                                                                                                                                                                         |
+//! | `MetricsLayer`            | Middleware that collects metrics (exported by Prometheus, StatsD or OTLP, see `MetricsExporter`) for monitoring API performance and usage. An optional `tenant` label (`enable_tenant_labels`) is bounded to the top tenants plus an `other` bucket. `configure_http_metrics(HttpMetricsConfig)` excludes routes and adds static or dynamic (from the request extensions) labels; the `SkipMetrics` extension excludes a single request                      |
+//! | `SecurityHeadersLayer`    | Sets the security headers (CSP, HSTS, `X-Content-Type-Options`, `X-Frame-Options`, `X-XSS-Protection`, `Referrer-Policy`, `Permissions-Policy`, `Cross-Origin-Opener-Policy`, `Cross-Origin-Embedder-Policy`, `Cross-Origin-Resource-Policy`) on the responses, replacing or keeping (`with_if_not_present`) the values of the handlers. Presets (`api_strict`, `browser_app`, `disabled`) and `ContentSecurityPolicy` builder with a per-request `CspNonce` |
+//! | `HeaderPolicyLayer`       | Applies a policy to the response headers: removes the identifying headers (`Server`, `X-Powered-By`) and the headers never echoed back (`Authorization`, `Cookie`), overrides headers, adds default headers for all routes or a route pattern and copies the `X-Request-Id`                                                                                                                                                                                  |
+//! | `MapResponseLayer`        | Runs async response transformation hooks (`ResponseHook`) matched by route pattern, status range and content-type prefix, e.g. to wrap legacy upstream errors into the standard envelope or strip internal headers. The hooks run in order (`with_hook_first`, `with_hook_before`, `with_hook_after`)                                                                                                                                                        |
+//! | `BotMitigationLayer`      | Rejects the scrapers and scanners with `403 Forbidden`: blocked `User-Agent` patterns, required headers, honeypot form field and optional JS-free proof of work (`X-Proof-Of-Work`), with a tarpit delay for the flagged clients and the `bot_mitigation_blocked_total` counter                                                                                                                                                                              |
+//! | `SlowRequestLayer`        | Logs the requests slower than a threshold (method, route pattern, path, status, duration, principal and request ID) and keeps rolling p50/p95/p99 latencies per route, served in JSON by `LatencyTracker::router`                                                                                                                                                                                                                                            |
+//! | `InjectorLayer`           | Middleware that attaches an `Injector` (typed map of singleton and per-request services) to each request, `try_new` checks the required services at startup                                                                                                                                                                                                                                                                                                  |
+//! | `HmacSignatureLayer`      | Verifies an HMAC-SHA256 signature of the request body (timestamp tolerance, secrets rotation) and returns a 401 error if it is invalid                                                                                                                                                                                                                                                                                                                       |
+//! | `ChecksumLayer`           | Validates the `Content-MD5` and `x-amz-content-sha256` checksum headers against the body (hashed while read, size-capped) with a `400` error on mismatch, and optionally sets the digest headers of the responses                                                                                                                                                                                                                                            |
+//! | `NonceLayer`              | Replay protection: requires a unique `X-Nonce` and a recent `X-Timestamp` (`401` otherwise) and rejects the replayed nonces with `409 Conflict`. The seen nonces are kept in a pluggable `NonceStore` (`MemoryNonceStore`) for twice the timestamp tolerance                                                                                                                                                                                                 |
+//! | `PerPrincipalQuotaLayer`  | Daily or monthly quotas per authenticated `Principal` (API key user, JWT subject) with per-plan limits, counted in a pluggable `QuotaStore` (`MemoryQuotaStore`). `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers on every response, `429 Too Many Requests` over the quota                                                                                                                                                     |
+//! | `MeteringLayer`           | Records the requests and the bytes transferred per `Principal` in a `Meter`, which sends them in batches (`UsageReport`) to a pluggable `MeteringSink` periodically (`spawn_flush`) or on demand (`flush`), keeping the usage on sink failure                                                                                                                                                                                                                |
+//! | `CircuitBreakerLayer`     | Fails fast with a 503 error while a `CircuitBreaker` (closed/open/half-open states, failure rate threshold) is open. The same breaker can protect the `HttpClient`, its state is exposed by the `circuit_breaker_state` gauge (`metrics` feature)                                                                                                                                                                                                            |
+//! | `ContextLayer`            | Inserts a `RequestContext` (request ID, start time, principal, client IP, matched route) in the request extensions, used by the `LoggerLayer` and the `Ctx` extractor                                                                                                                                                                                                                                                                                        |
+//! | `DeadlineLayer`           | Stores the `Deadline` of the request (now + timeout) in the `RequestStore`, added by the `ApiRouterBuilder` with its timeout                                                                                                                                                                                                                                                                                                                                 |
+//! | `IpFilterLayer`           | Allows or denies requests by client IP against CIDR allow/deny lists (403 error), rules reloadable at runtime with a `watch` channel. The decision is logged by the `LoggerLayer`                                                                                                                                                                                                                                                                            |
+//! | `AdaptiveThrottleLayer`   | Limits the requests per key (client IP by default) with a 429 error and a `Retry-After` header. The `AdaptiveThrottle` limit decreases when the upstream returns 429/503 responses and recovers on success. The same throttle can protect the `HttpClient` (per host), its limit is exposed by the `adaptive_throttle_limit` gauge (`metrics` feature)                                                                                                       |
+//! | `JwtAuthLayer`            | Authenticates requests with a JWT bearer token, or with the httpOnly `AuthCookie` set by `with_cookie` (401 error if it is missing, invalid or expired). The claims (`JwtClaims`) are added to the request extensions, their subject and grants to the `RequestStore`                                                                                                                                                                                        |
+//! | `AuthorizeLayer`          | Checks the roles, permissions and scopes (`Grants`) of the caller against a route `Requirement` (all of / any of), returns a 403 error listing the missing grants                                                                                                                                                                                                                                                                                            |
+//! | `ShutdownLayer`           | Counts the in-flight requests for the `Shutdown` tracker, which emits a structured `ShutdownReport` (requests drained, duration, jobs cancelled, stores flushed, last errors) via `tracing` and an optional JSON file on graceful shutdown                                                                                                                                                                                                                   |
+//! | `FaultInjectionLayer`     | Injects delays and errors in the inbound routes for game-day drills. The same `FaultInjector` can target the outbound dependencies of the `HttpClient` (`with_fault_injection`)                                                                                                                                                                                                                                                                              |
+//! | `OwnershipLayer`          | Declares the team owning each route, exported as an `owner` field in the request logs (5xx errors included) and as an `owner` label of the Prometheus metrics for on-call routing                                                                                                                                                                                                                                                                            |
+//! | `CatchPanicLayer`         | Converts handler panics into the standard JSON 500 error (with the trace ID), logs the panic and its backtrace and counts them in the `http_panics_total` metric (`metrics` feature)                                                                                                                                                                                                                                                                         |
+//! | `ContentNegotiationLayer` | Rejects requests with a missing or unsupported `Content-Type` (`415`) or an unacceptable `Accept` header (`406`) with the JSON error format. JSON by default, configurable per route group with `with_route(pattern, MediaTypes)` (wildcards like `image/*` are supported)                                                                                                                                                                                   |
+//! | `ApiRouterBuilder`        | Wraps a `Router` with the standard layers in the right order (request ID, logger, errors, CORS, compression, timeout, metrics) from a single `ApiRouterConfig`                                                                                                                                                                                                                                                                                               |
+//! | `SingleflightLayer`       | Coalesces concurrent identical `GET` requests (same path, query and vary headers) into one execution of the inner service and shares the response. Per-key timeout, `singleflight_requests_total` metric                                                                                                                                                                                                                                                     |
+//! | `LoadSheddingLayer`       | Rejects a fraction of the requests with a `503` error and a `Retry-After` header while the moving average latency exceeds a target or an external load signal reports an overload. Priority routes (`/health` by default) are never shed                                                                                                                                                                                                                     |
+//! | `CanaryLayer`             | Routes a percentage of the traffic to an alternate service or, with the `client` feature, an upstream URL. The routing is sticky (hash of a cookie, a header or the principal) and the `canary_requests_total` counter is split by variant (`metrics` feature)                                                                                                                                                                                               |
+//! | `LocaleLayer`             | Negotiates the locale from `Accept-Language` among the locales of a `MessageCatalog`, stores it in the `RequestStore` and translates the JSON error messages (fallback: locale, language, default locale)                                                                                                                                                                                                                                                    |
+//! | `DeprecationLayer`        | Adds the `Deprecation`, `Sunset` and `Link` (successor, documentation) headers to the responses of deprecated routes and logs their consumers (`deprecated_requests_total` metric)                                                                                                                                                                                                                                                                           |
+//! | `OpenApiValidationLayer`  | Validates requests (query, JSON body) and responses against an OpenAPI document, rejecting invalid requests with a 400 error or only logging them (`openapi` feature)                                                                                                                                                                                                                                                                                        |
 //!
 //! ##### Utility functions
 //!
@@ -56,29 +124,97 @@
 //!
 //! #### Extractors
 //!
-//! | Name               | Description                                                            |
-//! | ------------------ | ---------------------------------------------------------------------- |
-//! | `ExtractRequestId` | Extracts the unique request identifier (UUID) from the request headers |
-//! | `Path`             | Extracts and deserializes path parameters from the request URL         |
-//! | `Query`            | Extracts and deserializes query string parameters from the request URL |
+//! | Name               | Description                                                                                                                                                         |
+//! | ------------------ | ------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `ExtractRequestId` | Extracts the unique request identifier (UUID) from the request headers                                                                                              |
+//! | `Path`             | Extracts and deserializes path parameters from the request URL                                                                                                      |
+//! | `Query`            | Extracts and deserializes query string parameters from the request URL                                                                                              |
+//! | `QueryQs`          | Extracts query string parameters with `serde_qs` (`query-qs` feature): sequences and nested structs, strict mode                                                    |
+//! | `Json`             | Extracts and deserializes the JSON body, errors returned with the JSON error format (`400` with line and column, `422`, `415` or `413`)                             |
+//! | `Negotiated`       | Extracts and deserializes the body in the format of its `Content-Type` (JSON, MessagePack or CBOR), with the same JSON errors as `Json`                             |
+//! | `Dep`              | Resolves a service registered in the `InjectorLayer`, possibly a trait object (`Dep<dyn Mailer>`)                                                                   |
+//! | `Inject`           | Resolves a trait-object service (`Inject<dyn MailSender>`) of the `ServiceRegistry` added by `registry.layer()`, replaceable in tests                               |
+//! | `State`            | Gets a singleton of the `AppState` (an `Injector` of singletons validated by `AppState::into_router`), `500` JSON error if it is missing                            |
+//! | `RequestStore`     | Typed map of request-scoped values (`Principal`, `Tenant`, `Locale`, `Deadline`) shared by layers and handlers                                                      |
+//! | `ApiQuery`         | Derive macro (`derive` feature) generating a query string extractor with `min`, `max`, `regex` and `default` field validations, errors aggregated in a 422 response |
+//! | `Dto`              | Deserializes the JSON body into the DTO of a domain type and converts it with `FromDto`/`TryIntoDomain`, conversion errors returned as a 422 response               |
+//! | `Ctx`              | Gets the `RequestContext` inserted by the `ContextLayer`                                                                                                            |
+//! | `Localizer`        | Translates messages in the locale negotiated by the `LocaleLayer`, with `{name}` placeholders                                                                       |
+//! | `OidcIdentity`     | Gets the standard OpenID Connect claims of the token validated by the `JwtAuthLayer<OidcIdentity>` (`oidc` feature), 401 error if missing                           |
 //!
 //! #### Response helpers
 //!
-//! | Name               | Description                                                                                                 |
-//! | ------------------ | ----------------------------------------------------------------------------------------------------------- |
-//! | `ApiSuccess`       | Represents a successful API response (Status code and data in JSON). It implements the `IntoResponse` trait |
-//! | `ApiError`         | Represents a list of HTTP errors                                                                            |
-//! | `ApiErrorResponse` | Encapsulates the details of an API error response, including the status code and the error message          |
+//! | Name                  | Description                                                                                                                                                                                                                                                       |
+//! | --------------------- | ----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `ApiSuccess`          | Represents a successful API response (Status code and data in JSON). It implements the `IntoResponse` trait. `enveloped()`, `with_pagination`, `with_timing` and `with_extra` serialize it as `{ "data": ..., "meta": { "request_id": ..., "pagination": ... } }` |
+//! | `ApiError`            | Represents a list of HTTP errors                                                                                                                                                                                                                                  |
+//! | `ApiErrorResponse`    | Encapsulates the details of an API error response, including the status code and the error message                                                                                                                                                                |
+//! | `ValidationErrors`    | Aggregated field errors returned as a `422 Unprocessable Entity` response                                                                                                                                                                                         |
+//! | `MultiStatusResponse` | `207 Multi-Status` response for bulk operations, failed items use the `ApiErrorResponse` shape                                                                                                                                                                    |
+//! | `NotModified`         | Conditional `GET` helper: `NotModified::check` compares the `If-None-Match` and `If-Modified-Since` headers with the `ETag` and modification date of a resource, returning a `304` response or the `CacheValidators` headers of the full response                 |
+//! | `NegotiatedResponse`  | Response serialized as JSON, MessagePack (`msgpack` feature) or CBOR (`cbor` feature) depending on the `Accept` header (`BodyFormat` extractor), falling back to JSON                                                                                             |
+//! | `Document`            | JSON:API document with resource objects, relationships, included resources and links, and `JsonApiErrors` built from `ApiError` and `ValidationErrors`, sent as `application/vnd.api+json` (`jsonapi` feature)                                                    |
+//! | `HalResponse`         | HAL resource with `_links` and `_embedded` resources, sent as `application/hal+json`. `Links::paginated` generates the `self`, `first`, `prev`, `next` and `last` links from the request URI and a `PaginationResponse`                                           |
+//! | `ApiErrorDoc`         | OpenAPI schema of the error envelope and `ApiErrorResponses` documenting the common error responses, value objects implement `ToSchema` (`openapi` feature)                                                                                                       |
+//! | `tonic::Status`       | Conversions from and to `ApiError` with the request ID and trace ID in the metadata (`grpc` feature)                                                                                                                                                              |
 //!
 //! #### Handlers
 //!
-//! | Name                | Description                                                                                       |
-//! | ------------------- | ------------------------------------------------------------------------------------------------- |
-//! | `PrometheusHandler` | Handler that exposes Prometheus metrics endpoint, allowing metrics scraping by Prometheus servers |
+//! | Name                 | Description                                                                                                                                                                                                                                                                                                                                                |
+//! | -------------------- | ---------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `PrometheusHandler`  | Handler that exposes Prometheus metrics endpoint, allowing metrics scraping by Prometheus servers (with trace exemplars in the OpenMetrics format)                                                                                                                                                                                                         |
+//! | `OpenApiDocs`        | Serves an OpenAPI document (`OpenApiDocs::json`, `OpenApiDocs::yaml` or `OpenApiDocs::from_openapi` with the `openapi` feature) and a Swagger UI or RapiDoc page (`with_ui(DocsUi::RapiDoc)`) at configurable paths (`with_spec_path`, `with_ui_path`). `with_basic_auth(BasicAuthLayer)` protects both routes. Merge `router()` in the application router |
+//! | `MetricsExporter`    | Installs the global metrics recorder: `PrometheusExporter` (`prometheus` feature, returns the `PrometheusHandle`), `StatsdExporter` (`statsd` feature, UDP with DogStatsD tags) or `OtlpExporter` (`otlp` feature, periodic OTLP/HTTP JSON push to an OpenTelemetry collector). The `MetricsLayer` instrumentation is the same for all of them             |
+//! | `StaticFilesHandler` | Serves a directory with its `Content-Type`, `ETag`/`Last-Modified` (`304 Not Modified`), a `Cache-Control` policy per extension and the precompressed `.br`/`.gz` sidecars (`with_precompressed`). `with_spa_fallback()` serves `index.html` for the unmatched paths while the API prefixes (`with_api_prefix`) keep returning JSON 404 errors             |
+//! | `WellKnownHandler`   | Serves `/robots.txt` (disallows indexing by default), `/.well-known/security.txt` (`SecurityTxt`, RFC 9116) and redirects `/.well-known/change-password` (`with_change_password`). Merge `router()` in the application router                                                                                                                              |
+//! | `TokenAuth`          | `POST /login`, `/refresh` and `/logout` handlers (`login_handler`, `refresh_handler`, `logout_handler`) issuing and rotating access and refresh tokens (`TokenPair`, `AuthClaims`) with a `Jwt`, the credentials checked by a `CredentialsValidator`. Nest `router()` in the application router                                                            |
+//! | `AdminHandler`       | Opt-in admin endpoints protected by a `BasicAuthLayer`, in the standard JSON envelope: redacted configuration snapshot, feature flag rules, adaptive throttle limits, circuit breaker states, route latencies and Tokio runtime metrics. Nest `router()` in the application router                                                                         |
+//! | `BatchHandler`       | Executes an array of sub-requests (method, path, headers, JSON body) sent in one `POST` against a router, with maximum items, concurrency, per-item timeout and body size limits. The responses are returned in order in the standard JSON envelope                                                                                                        |
+//!
+//! #### Configuration
+//!
+//! | Name            | Description                                                                                                                                                                                                          |
+//! | --------------- | -------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------- |
+//! | `ConfigWatcher` | Reloads a configuration from a JSON file or environment variables, logs a redacted diff (`ConfigDiff`) as an audit event and publishes it in a `watch` channel used by the layers (e.g. `IpFilterLayer::from_watch`) |
+//! | `FeatureFlags`  | Static flags with runtime overrides (watch channel) and percentage rollouts, `Flags`/`Flag` extractors and `FeatureFlagLayer` gating routes                                                                          |
+//! | `JobScheduler`  | Background jobs with interval or cron triggers, timeouts, overlap policies, graceful shutdown and run metrics                                                                                                        |
+//! | `TaskQueue`     | Typed background tasks with workers, retries with backoff and dead-letter queue, behind a `TaskBackend` (in-memory by default)                                                                                       |
+//! | `JobManager`    | Long-running jobs (e.g. exports) with status, progress and result location, `POST`/`GET` polling routes and cleanup of the finished jobs                                                                             |
+//! | `ConfigLoader`  | Typed configuration from defaults, JSON/TOML/YAML files and environment variables, validated at startup, with `Secret` values and `JwtSettings`, `CorsSettings` and `PaginationSettings` sections                    |
+//! | `CheckedPool`   | Wraps a connection pool (`CheckedResource`) with periodic health checks and size, in-use and wait time gauges, adapters for `sqlx` and Redis                                                                         |
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
+#[cfg(feature = "axum")]
 #[allow(unused_imports)]
 #[macro_use]
 extern crate tracing;
 
+// Allow the code generated by `api-tools-derive` to refer to `::api_tools` inside this crate
+#[cfg(feature = "derive")]
+extern crate self as api_tools;
+
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "examples")]
+pub mod demo;
 pub mod server;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod value_objects;
+
+#[cfg(feature = "derive")]
+pub use api_tools_derive::ApiQuery;
+
+/// Items used by the code generated by `api-tools-derive`, not part of the public API
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use axum::extract::FromRequestParts;
+    pub use axum::http::request::Parts;
+    pub use regex::Regex;
+}
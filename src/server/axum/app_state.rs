@@ -0,0 +1,133 @@
+//! Typed application state
+//!
+//! [`AppState`] registers the singletons shared by the handlers (`Jwt`, pools, configuration,
+//! etc.). It is a thin wrapper over an [`Injector`] of singletons: the entries needed by the
+//! handlers are declared with [`AppState::require`] and checked when the router is built
+//! ([`AppState::into_router`]), so that a missing entry fails at startup instead of at the
+//! first request.
+//!
+//! Handlers read the entries with the [`State`](crate::server::axum::extractors::State)
+//! extractor, which returns a `500` JSON error instead of panicking if an entry is missing.
+//!
+//! ```rust
+//! use api_tools::server::axum::app_state::AppState;
+//! use api_tools::server::axum::extractors::State;
+//! use axum::{Router, routing::get};
+//!
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! let app: Router = AppState::new()
+//!     .with(Config { name: "users-api".to_string() })
+//!     .require::<Config>()
+//!     .into_router(Router::new().route("/", get(|State(config): State<Config>| async move { config.name.clone() })))
+//!     .unwrap();
+//! ```
+
+use crate::server::axum::layers::injector::{Injector, InjectorError, InjectorLayer};
+use axum::Router;
+use std::any::type_name;
+use std::sync::Arc;
+
+/// Typed map of the application singletons, backed by an [`Injector`]
+#[derive(Debug, Clone, Default)]
+pub struct AppState {
+    injector: Injector,
+}
+
+impl AppState {
+    /// Create an empty state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a singleton (replacing the previous one of the same type)
+    pub fn with<T: Send + Sync + 'static>(self, value: T) -> Self {
+        self.with_arc(Arc::new(value))
+    }
+
+    /// Register a shared singleton, possibly behind a trait
+    pub fn with_arc<T: ?Sized + Send + Sync + 'static>(self, value: Arc<T>) -> Self {
+        Self {
+            injector: self.injector.singleton_arc(value),
+        }
+    }
+
+    /// Declare an entry required by the handlers, checked by [`AppState::validate`]
+    pub fn require<T: ?Sized + 'static>(self) -> Self {
+        Self {
+            injector: self.injector.require::<T>(),
+        }
+    }
+
+    /// Get a singleton
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.injector.get::<T>()
+    }
+
+    /// Get a singleton, or the `NotRegistered` error
+    pub fn try_get<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<T>, InjectorError> {
+        self.get::<T>().ok_or(InjectorError::NotRegistered(type_name::<T>()))
+    }
+
+    /// Return true if a singleton of type `T` is registered
+    pub fn contains<T: ?Sized + 'static>(&self) -> bool {
+        self.injector.lifetime::<T>().is_some()
+    }
+
+    /// Check that the required entries are registered
+    pub fn validate(&self) -> Result<(), InjectorError> {
+        self.injector.validate()
+    }
+
+    /// Get the underlying injector
+    pub fn injector(&self) -> &Injector {
+        &self.injector
+    }
+
+    /// Validate the state and attach it to a router with an [`InjectorLayer`]
+    pub fn into_router(self, router: Router) -> Result<Router, InjectorError> {
+        Ok(router.layer(InjectorLayer::try_new(self.injector)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Config(&'static str);
+
+    struct Pool;
+
+    #[test]
+    fn test_app_state_entries() {
+        let state = AppState::new().with(Config("v1")).with_arc(Arc::new(Config("v2")));
+        assert_eq!(state.get::<Config>().as_deref(), Some(&Config("v2")));
+        assert!(state.contains::<Config>());
+        assert!(state.get::<Pool>().is_none());
+        assert!(matches!(state.try_get::<Pool>(), Err(InjectorError::NotRegistered(_))));
+
+        // Clones share the entries registered before
+        let clone = state.clone().with(Pool);
+        assert!(clone.contains::<Pool>());
+        assert!(!state.contains::<Pool>());
+    }
+
+    #[test]
+    fn test_app_state_validate() {
+        let state = AppState::new()
+            .with(Config("v1"))
+            .require::<Config>()
+            .require::<Config>();
+        assert_eq!(state.validate(), Ok(()));
+
+        let state = state.require::<Pool>();
+        let err = state.clone().into_router(Router::new()).unwrap_err();
+        assert_eq!(err, InjectorError::Missing(vec![type_name::<Pool>()]));
+        assert!(err.to_string().ends_with("app_state::tests::Pool"));
+
+        assert!(state.with(Pool).into_router(Router::new()).is_ok());
+    }
+}
@@ -0,0 +1,737 @@
+//! Typed configuration loader
+//!
+//! [`ConfigLoader`] builds a configuration struct from layered sources, each one overriding the
+//! previous ones:
+//! 1. the default values ([`ConfigLoader::with_defaults`])
+//! 2. the files, in the order they are added: JSON, TOML (`config-toml` feature) or YAML
+//!    (`config-yaml` feature), selected by the file extension
+//! 3. the environment variables with a prefix (`APP_JWT__SECRET` with the `APP_` prefix sets
+//!    `jwt.secret`, values which are not valid JSON are strings)
+//!
+//! The configuration is then validated with [`ValidateConfig`], reporting all the invalid fields
+//! at once. [`Secret`] hides a value in the `Debug` output and in the serialized configuration.
+//!
+//! The usual sections are provided, with the conventional environment variables
+//! (`JWT_*`, `CORS_*`, `PAGINATION_*`) read by their `from_env` constructors:
+//! - [`JwtSettings`] builds a `Jwt`
+//! - [`CorsSettings`] builds a `CorsBuilder`
+//! - [`PaginationSettings`] builds a `Pagination`
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::config::{ConfigLoader, CorsSettings, JwtSettings, ValidateConfig, validate_section};
+//! use api_tools::server::axum::response::ValidationErrors;
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     port: u16,
+//!     jwt: JwtSettings,
+//!     #[serde(default)]
+//!     cors: CorsSettings,
+//! }
+//!
+//! impl ValidateConfig for Config {
+//!     fn validate(&self, errors: &mut ValidationErrors) {
+//!         if self.port == 0 {
+//!             errors.add("port", "must be greater than 0");
+//!         }
+//!         validate_section(errors, "jwt", &self.jwt);
+//!         validate_section(errors, "cors", &self.cors);
+//!     }
+//! }
+//!
+//! # fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = ConfigLoader::new()
+//!     .with_optional_file("config.json")
+//!     .with_env_prefix("APP_")
+//!     .load::<Config>()?;
+//!
+//! let jwt = config.jwt.jwt()?;
+//! let cors = config.cors.builder()?.build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::axum::config_watcher::env_to_json;
+use crate::server::axum::layers::cors::CorsBuilder;
+use crate::server::axum::redaction::REDACTED_VALUE;
+use crate::server::axum::response::ValidationErrors;
+use crate::server::axum::security::jwt::keys::JwtKey;
+use crate::server::axum::security::jwt::{Jwt, JwtError};
+use crate::value_objects::pagination::{
+    PAGINATION_DEFAULT_LIMIT, PAGINATION_MAX_LIMIT, PAGINATION_MIN_LIMIT, Pagination,
+};
+use axum::http::{HeaderName, Method};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConfigLoadError {
+    #[error("Configuration read error: {0}")]
+    Read(String),
+
+    #[error("Configuration parse error: {0}")]
+    Parse(String),
+
+    #[error("Unsupported configuration file format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid configuration: {}", format_errors(.0))]
+    Invalid(ValidationErrors),
+}
+
+/// Format the validation errors (`field: message, ...`)
+fn format_errors(errors: &ValidationErrors) -> String {
+    errors
+        .0
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Validation of a configuration, at startup
+pub trait ValidateConfig {
+    /// Add the errors of the invalid fields
+    fn validate(&self, errors: &mut ValidationErrors);
+}
+
+/// Validate a section of a configuration, prefixing the fields of its errors with `section.`
+pub fn validate_section(errors: &mut ValidationErrors, section: &str, config: &impl ValidateConfig) {
+    let mut section_errors = ValidationErrors::new();
+    config.validate(&mut section_errors);
+
+    for error in section_errors.0 {
+        errors.add(&format!("{section}.{}", error.field), &error.message);
+    }
+}
+
+/// Secret value, hidden in the `Debug` output and serialized as `***`
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Create a new secret
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Get the secret value
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{REDACTED_VALUE}")
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED_VALUE)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Configuration source of a [`ConfigLoader`]
+#[derive(Debug, Clone, PartialEq)]
+enum Layer {
+    Value(Value),
+    File { path: PathBuf, required: bool },
+    Env(String),
+}
+
+/// Layered configuration loader
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigLoader {
+    layers: Vec<Layer>,
+}
+
+impl ConfigLoader {
+    /// Create a new loader without sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add default values
+    pub fn with_defaults<T: Serialize>(mut self, defaults: &T) -> Result<Self, ConfigLoadError> {
+        let value = serde_json::to_value(defaults).map_err(|err| ConfigLoadError::Parse(err.to_string()))?;
+        self.layers.push(Layer::Value(value));
+
+        Ok(self)
+    }
+
+    /// Add a required file
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.as_ref().to_path_buf(),
+            required: true,
+        });
+        self
+    }
+
+    /// Add a file, ignored if it does not exist
+    pub fn with_optional_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.as_ref().to_path_buf(),
+            required: false,
+        });
+        self
+    }
+
+    /// Add the environment variables starting with a prefix (`__` separates the nested keys)
+    pub fn with_env_prefix(mut self, prefix: &str) -> Self {
+        self.layers.push(Layer::Env(prefix.to_string()));
+        self
+    }
+
+    /// Merge the sources into a JSON value
+    pub fn merge(&self) -> Result<Value, ConfigLoadError> {
+        let mut config = Value::Object(Map::new());
+        for layer in &self.layers {
+            let value = match layer {
+                Layer::Value(value) => value.clone(),
+                Layer::File { path, required } => match read_file(path) {
+                    Err(ConfigLoadError::Read(_)) if !required && !path.exists() => continue,
+                    result => result?,
+                },
+                Layer::Env(prefix) => env_to_json(prefix, std::env::vars()),
+            };
+            merge_values(&mut config, value);
+        }
+
+        Ok(config)
+    }
+
+    /// Load and validate the configuration
+    pub fn load<C: DeserializeOwned + ValidateConfig>(&self) -> Result<C, ConfigLoadError> {
+        let config =
+            serde_json::from_value::<C>(self.merge()?).map_err(|err| ConfigLoadError::Parse(err.to_string()))?;
+
+        let mut errors = ValidationErrors::new();
+        config.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(ConfigLoadError::Invalid(errors));
+        }
+
+        Ok(config)
+    }
+}
+
+/// Read a configuration file as a JSON value
+fn read_file(path: &Path) -> Result<Value, ConfigLoadError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|err| ConfigLoadError::Read(format!("{}: {err}", path.display())))?;
+    let parse_error = |err: String| ConfigLoadError::Parse(format!("{}: {err}", path.display()));
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&content).map_err(|err| parse_error(err.to_string())),
+        #[cfg(feature = "config-toml")]
+        Some("toml") => toml::from_str(&content).map_err(|err| parse_error(err.to_string())),
+        #[cfg(feature = "config-yaml")]
+        Some("yaml" | "yml") => serde_yaml_ng::from_str(&content).map_err(|err| parse_error(err.to_string())),
+        _ => Err(ConfigLoadError::UnsupportedFormat(path.display().to_string())),
+    }
+}
+
+/// Deep merge of two JSON values, the objects are merged and the other values replaced
+fn merge_values(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(base) => merge_values(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Split a comma-separated list
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn default_jwt_algorithm() -> String {
+    "HS512".to_string()
+}
+
+fn default_jwt_access_lifetime() -> i64 {
+    15
+}
+
+fn default_jwt_refresh_lifetime() -> i64 {
+    7 * 24
+}
+
+/// JWT settings (`JWT_*` environment variables)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JwtSettings {
+    /// Algorithm (default: `HS512`)
+    #[serde(default = "default_jwt_algorithm")]
+    pub algorithm: String,
+
+    /// Access token lifetime (default: 15)
+    #[serde(default = "default_jwt_access_lifetime")]
+    pub access_lifetime: i64,
+
+    /// Refresh token lifetime (default: 168)
+    #[serde(default = "default_jwt_refresh_lifetime")]
+    pub refresh_lifetime: i64,
+
+    /// Secret of the `HS*` algorithms
+    #[serde(default)]
+    pub secret: Option<Secret<String>>,
+
+    /// Private key (PEM) of the other algorithms
+    #[serde(default)]
+    pub private_key: Option<Secret<String>>,
+
+    /// Public key (PEM) of the other algorithms
+    #[serde(default)]
+    pub public_key: Option<String>,
+
+    /// Expected issuer of the tokens
+    #[serde(default)]
+    pub issuer: Option<String>,
+
+    /// Expected audiences of the tokens (comma-separated)
+    #[serde(default)]
+    pub audience: Option<String>,
+
+    /// Leeway of the time-based claims, in seconds (default: 60)
+    #[serde(default)]
+    pub leeway: Option<u64>,
+
+    /// Claims which must be present in the tokens (comma-separated, default: `exp`)
+    #[serde(default)]
+    pub required_claims: Option<String>,
+
+    /// Rotating keys identified by their key ID, used instead of `secret`, `private_key` and `public_key`
+    #[serde(default)]
+    pub keys: Vec<JwtKey>,
+
+    /// Directory of rotating keys (see `JwtKey::load_dir`), added to `keys`
+    #[serde(default)]
+    pub keys_dir: Option<String>,
+
+    /// Key ID of the key signing the tokens, required with `keys` or `keys_dir`
+    #[serde(default)]
+    pub primary_kid: Option<String>,
+}
+
+impl JwtSettings {
+    /// Load the settings from the `JWT_*` environment variables
+    pub fn from_env() -> Result<Self, ConfigLoadError> {
+        ConfigLoader::new().with_env_prefix("JWT_").load()
+    }
+
+    /// Build the `Jwt`
+    pub fn jwt(&self) -> Result<Jwt, JwtError> {
+        let mut jwt = if self.keys.is_empty() && self.keys_dir.is_none() {
+            Jwt::init(
+                &self.algorithm,
+                self.access_lifetime,
+                self.refresh_lifetime,
+                self.secret.as_ref().map(|secret| secret.expose().as_str()),
+                self.private_key.as_ref().map(|key| key.expose().as_str()),
+                self.public_key.as_deref(),
+            )?
+        } else {
+            let mut keys = self.keys.clone();
+            if let Some(dir) = &self.keys_dir {
+                keys.extend(JwtKey::load_dir(dir)?);
+            }
+            let primary_kid = self
+                .primary_kid
+                .as_deref()
+                .ok_or_else(|| JwtError::KeySetError("missing primary key ID".to_owned()))?;
+
+            Jwt::init_with_keys(
+                &self.algorithm,
+                self.access_lifetime,
+                self.refresh_lifetime,
+                &keys,
+                primary_kid,
+            )?
+        };
+
+        if let Some(issuer) = &self.issuer {
+            jwt.set_issuer(issuer);
+        }
+        if let Some(audience) = &self.audience {
+            jwt.set_audience(&split_list(audience).collect::<Vec<_>>());
+        }
+        if let Some(leeway) = self.leeway {
+            jwt.set_leeway(leeway);
+        }
+        if let Some(required_claims) = &self.required_claims {
+            jwt.set_required_claims(&split_list(required_claims).collect::<Vec<_>>());
+        }
+
+        Ok(jwt)
+    }
+}
+
+impl ValidateConfig for JwtSettings {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if self.access_lifetime <= 0 {
+            errors.add("access_lifetime", "must be greater than 0");
+        }
+        if self.refresh_lifetime <= 0 {
+            errors.add("refresh_lifetime", "must be greater than 0");
+        }
+        if let Err(err) = self.jwt() {
+            let uses_secret = self.algorithm.starts_with("HS");
+            let uses_keys = !self.keys.is_empty() || self.keys_dir.is_some();
+            let field = match err {
+                JwtError::InvalidAlgorithm(_) => "algorithm",
+                JwtError::UnknownKeyId(_) => "primary_kid",
+                _ if uses_keys && self.primary_kid.is_none() => "primary_kid",
+                _ if uses_keys => "keys",
+                JwtError::EncodingKeyError(_) if !uses_secret => "private_key",
+                JwtError::DecodingKeyError(_) if !uses_secret => "public_key",
+                JwtError::EncodingKeyError(_) | JwtError::DecodingKeyError(_) => "secret",
+                _ => "algorithm",
+            };
+            errors.add(field, &err.to_string());
+        }
+    }
+}
+
+fn default_cors_origins() -> String {
+    "*".to_string()
+}
+
+fn default_cors_methods() -> String {
+    "GET,POST,PUT,PATCH,DELETE".to_string()
+}
+
+fn default_cors_headers() -> String {
+    "authorization,accept,content-type,origin".to_string()
+}
+
+/// CORS settings (`CORS_*` environment variables)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorsSettings {
+    /// Allowed origins, comma-separated (default: `*`)
+    #[serde(default = "default_cors_origins")]
+    pub origins: String,
+
+    /// Allowed methods, comma-separated (default: `GET,POST,PUT,PATCH,DELETE`)
+    #[serde(default = "default_cors_methods")]
+    pub methods: String,
+
+    /// Allowed headers, comma-separated (default: `authorization,accept,content-type,origin`)
+    #[serde(default = "default_cors_headers")]
+    pub headers: String,
+
+    /// Exposed headers, comma-separated
+    #[serde(default)]
+    pub exposed_headers: String,
+
+    /// Preflight cache duration in seconds
+    #[serde(default)]
+    pub max_age: Option<u64>,
+
+    /// Allow credentials
+    #[serde(default)]
+    pub credentials: Option<bool>,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            origins: default_cors_origins(),
+            methods: default_cors_methods(),
+            headers: default_cors_headers(),
+            exposed_headers: String::new(),
+            max_age: None,
+            credentials: None,
+        }
+    }
+}
+
+impl CorsSettings {
+    /// Load the settings from the `CORS_*` environment variables
+    pub fn from_env() -> Result<Self, ConfigLoadError> {
+        ConfigLoader::new().with_env_prefix("CORS_").load()
+    }
+
+    /// Build the `CorsBuilder`
+    pub fn builder(&self) -> Result<CorsBuilder, ConfigLoadError> {
+        let methods = split_list(&self.methods)
+            .map(|method| Method::from_str(&method.to_uppercase()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| ConfigLoadError::Parse(format!("CORS methods: {err}")))?;
+        let headers = |headers: &str| {
+            split_list(headers)
+                .map(HeaderName::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| ConfigLoadError::Parse(format!("CORS headers: {err}")))
+        };
+
+        let mut builder = CorsBuilder::new()
+            .with_origins(&self.origins)
+            .with_methods(methods)
+            .with_headers(headers(&self.headers)?)
+            .with_exposed_headers(headers(&self.exposed_headers)?);
+        if let Some(max_age) = self.max_age {
+            builder = builder.with_max_age(Duration::from_secs(max_age));
+        }
+        if let Some(credentials) = self.credentials {
+            builder = builder.with_credentials(credentials);
+        }
+
+        Ok(builder)
+    }
+}
+
+impl ValidateConfig for CorsSettings {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if let Some(method) = split_list(&self.methods).find(|method| Method::from_str(&method.to_uppercase()).is_err())
+        {
+            errors.add("methods", &format!("invalid method: {method}"));
+        }
+        if let Some(header) = split_list(&self.headers)
+            .chain(split_list(&self.exposed_headers))
+            .find(|header| HeaderName::from_str(header).is_err())
+        {
+            errors.add("headers", &format!("invalid header: {header}"));
+        }
+        if let Ok(builder) = self.builder()
+            && let Err(err) = builder.build()
+        {
+            errors.add("origins", &err.to_string());
+        }
+    }
+}
+
+fn default_pagination_limit() -> u32 {
+    PAGINATION_DEFAULT_LIMIT
+}
+
+fn default_pagination_max_limit() -> u32 {
+    PAGINATION_MAX_LIMIT
+}
+
+/// Pagination settings (`PAGINATION_*` environment variables)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaginationSettings {
+    /// Limit when the request has none (default: `PAGINATION_DEFAULT_LIMIT`)
+    #[serde(default = "default_pagination_limit")]
+    pub default_limit: u32,
+
+    /// Maximum limit (default: `PAGINATION_MAX_LIMIT`)
+    #[serde(default = "default_pagination_max_limit")]
+    pub max_limit: u32,
+}
+
+impl Default for PaginationSettings {
+    fn default() -> Self {
+        Self {
+            default_limit: PAGINATION_DEFAULT_LIMIT,
+            max_limit: PAGINATION_MAX_LIMIT,
+        }
+    }
+}
+
+impl PaginationSettings {
+    /// Load the settings from the `PAGINATION_*` environment variables
+    pub fn from_env() -> Result<Self, ConfigLoadError> {
+        ConfigLoader::new().with_env_prefix("PAGINATION_").load()
+    }
+
+    /// Build the `Pagination` of a request
+    pub fn pagination(&self, page: Option<u32>, limit: Option<u32>) -> Pagination {
+        Pagination::new(
+            page.unwrap_or(1),
+            limit.unwrap_or(self.default_limit),
+            Some(self.max_limit),
+        )
+    }
+}
+
+impl ValidateConfig for PaginationSettings {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if !(PAGINATION_MIN_LIMIT..=PAGINATION_MAX_LIMIT).contains(&self.max_limit) {
+            errors.add(
+                "max_limit",
+                &format!("must be between {PAGINATION_MIN_LIMIT} and {PAGINATION_MAX_LIMIT}"),
+            );
+        }
+        if !(PAGINATION_MIN_LIMIT..=self.max_limit).contains(&self.default_limit) {
+            errors.add(
+                "default_limit",
+                &format!("must be between {PAGINATION_MIN_LIMIT} and the maximum limit"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        jwt: JwtSettings,
+        #[serde(default)]
+        cors: CorsSettings,
+        #[serde(default)]
+        pagination: PaginationSettings,
+    }
+
+    impl ValidateConfig for Config {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            if self.port == 0 {
+                errors.add("port", "must be greater than 0");
+            }
+            validate_section(errors, "jwt", &self.jwt);
+            validate_section(errors, "cors", &self.cors);
+            validate_section(errors, "pagination", &self.pagination);
+        }
+    }
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("config-loader-{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_config_loader_layers() {
+        let file = temp_file(
+            "layers.json",
+            r#"{"name": "file", "port": 8080, "jwt": {"secret": "file-secret", "access_lifetime": 30, "leeway": 5, "required_claims": "exp,sub"}}"#,
+        );
+        // SAFETY: the variables are only used by this test
+        unsafe {
+            std::env::set_var("CONFIG_LOADER_TEST_PORT", "9090");
+            std::env::set_var("CONFIG_LOADER_TEST_JWT__SECRET", "env-secret");
+        }
+
+        let config = ConfigLoader::new()
+            .with_defaults(&serde_json::json!({"name": "default", "port": 3000}))
+            .unwrap()
+            .with_file(&file)
+            .with_optional_file("missing.json")
+            .with_env_prefix("CONFIG_LOADER_TEST_")
+            .load::<Config>()
+            .unwrap();
+        std::fs::remove_file(file).unwrap();
+
+        assert_eq!(config.name, "file");
+        assert_eq!(config.port, 9090);
+        assert_eq!(config.jwt.access_lifetime, 30);
+        assert_eq!(config.jwt.secret.as_ref().unwrap().expose(), "env-secret");
+        assert_eq!(
+            config.pagination.pagination(None, Some(1_000)).limit(),
+            PAGINATION_MAX_LIMIT
+        );
+        let jwt = config.jwt.jwt().unwrap();
+        assert_eq!(jwt.validation().leeway, 5);
+        assert_eq!(jwt.validation().required_claims, vec!["exp", "sub"]);
+        assert!(config.cors.builder().unwrap().build().is_ok());
+
+        // Secrets are hidden
+        assert!(!format!("{config:?}").contains("env-secret"));
+        assert_eq!(
+            serde_json::to_value(&config.jwt).unwrap()["secret"],
+            serde_json::json!("***")
+        );
+
+        assert!(matches!(
+            ConfigLoader::new().with_file("missing.json").merge(),
+            Err(ConfigLoadError::Read(_))
+        ));
+        assert!(matches!(
+            ConfigLoader::new().with_file(temp_file("config.ini", "")).merge(),
+            Err(ConfigLoadError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_jwt_settings_with_keys() {
+        let mut settings: JwtSettings = serde_json::from_value(serde_json::json!({
+            "algorithm": "HS256",
+            "keys": [{"kid": "2026-10", "secret": "new-secret"}, {"kid": "2026-04", "secret": "old-secret"}],
+            "primary_kid": "2026-10"
+        }))
+        .unwrap();
+        let jwt = settings.jwt().unwrap();
+        assert_eq!(jwt.primary_kid(), Some("2026-10"));
+        assert_eq!(jwt.kids(), vec!["2026-04", "2026-10"]);
+
+        settings.primary_kid = None;
+        let mut errors = ValidationErrors::new();
+        settings.validate(&mut errors);
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].field, "primary_kid");
+    }
+
+    #[test]
+    fn test_config_loader_validation() {
+        let file = temp_file(
+            "invalid.json",
+            r#"{
+                "name": "api",
+                "port": 0,
+                "jwt": {"algorithm": "HS1"},
+                "cors": {"origins": "example.com"},
+                "pagination": {"default_limit": 800}
+            }"#,
+        );
+        let err = ConfigLoader::new().with_file(&file).load::<Config>().unwrap_err();
+        std::fs::remove_file(file).unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid configuration: port: must be greater than 0, \
+             jwt.algorithm: Invalid or unsupported algorithm: HS1, \
+             cors.origins: Invalid CORS origin: example.com, \
+             pagination.default_limit: must be between 10 and the maximum limit"
+        );
+    }
+
+    #[cfg(all(feature = "config-toml", feature = "config-yaml"))]
+    #[test]
+    fn test_config_loader_toml_and_yaml() {
+        let toml = temp_file(
+            "config.toml",
+            "name = \"toml\"\nport = 8080\n\n[jwt]\nsecret = \"secret\"\n",
+        );
+        let yaml = temp_file("config.yaml", "name: yaml\ncors:\n  origins: https://example.com\n");
+
+        let config = ConfigLoader::new()
+            .with_file(&toml)
+            .with_file(&yaml)
+            .load::<Config>()
+            .unwrap();
+        std::fs::remove_file(toml).unwrap();
+        std::fs::remove_file(yaml).unwrap();
+
+        assert_eq!(config.name, "yaml");
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.cors.origins, "https://example.com");
+    }
+}
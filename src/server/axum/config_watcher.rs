@@ -0,0 +1,454 @@
+//! Configuration hot-reload
+//!
+//! [`ConfigWatcher`] reloads a configuration from a JSON file or from environment variables,
+//! computes a redacted [`ConfigDiff`] with the current configuration, logs it as an audit event
+//! (with the `trace_id` and `span_id` of the current OpenTelemetry context) and publishes the new
+//! configuration in a `tokio::sync::watch` channel.
+//!
+//! Layers supporting runtime updates (e.g. `IpFilterLayer::from_watch`) get their part of the
+//! configuration with [`ConfigWatcher::map`]:
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::config_watcher::{ConfigSource, ConfigWatcher};
+//! use api_tools::server::axum::layers::ip_filter::{IpFilterLayer, IpFilterRules};
+//! use serde::{Deserialize, Serialize};
+//! use std::time::Duration;
+//!
+//! #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     allowed_ips: Vec<String>,
+//!     api_secret: String,
+//! }
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let watcher = ConfigWatcher::<Config>::load(ConfigSource::File("config.json".into()))?;
+//! let rules = watcher.map(|config| {
+//!     let allowed_ips = config.allowed_ips.iter().map(String::as_str).collect::<Vec<_>>();
+//!     IpFilterRules::new(&allowed_ips, &[])
+//! })?;
+//! let layer = IpFilterLayer::from_watch(rules);
+//!
+//! // Reload the file every 30 seconds
+//! let _handle = watcher.spawn(Duration::from_secs(30));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::{current_span_id, current_trace_id};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Value of the redacted fields in the diff
+pub use crate::server::axum::redaction::REDACTED_VALUE;
+
+/// Separator of the nested keys in the environment variables names
+const ENV_NESTED_SEPARATOR: &str = "__";
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConfigError {
+    #[error("Configuration read error: {0}")]
+    Read(String),
+
+    #[error("Configuration parse error: {0}")]
+    Parse(String),
+
+    #[error("Configuration mapping error: {0}")]
+    Map(String),
+}
+
+/// Source of the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// JSON file
+    File(PathBuf),
+
+    /// Environment variables with a prefix
+    ///
+    /// `APP_IP_FILTER__ALLOW=["10.0.0.0/8"]` with the `APP_` prefix gives
+    /// `{ "ip_filter": { "allow": ["10.0.0.0/8"] } }`. Values which are not valid JSON are strings.
+    Env(String),
+}
+
+impl ConfigSource {
+    /// Read the configuration as a JSON value
+    fn read(&self) -> Result<Value, ConfigError> {
+        match self {
+            Self::File(path) => {
+                let content =
+                    std::fs::read(path).map_err(|err| ConfigError::Read(format!("{}: {err}", path.display())))?;
+                serde_json::from_slice(&content).map_err(|err| ConfigError::Parse(err.to_string()))
+            }
+            Self::Env(prefix) => Ok(env_to_json(prefix, std::env::vars())),
+        }
+    }
+}
+
+/// Build a JSON object from the environment variables starting with `prefix`
+pub(crate) fn env_to_json(prefix: &str, vars: impl Iterator<Item = (String, String)>) -> Value {
+    let mut root = Map::new();
+    for (name, value) in vars {
+        let Some(name) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        let keys = name
+            .to_lowercase()
+            .split(ENV_NESTED_SEPARATOR)
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+
+        let mut object = &mut root;
+        for key in &keys[..keys.len() - 1] {
+            let entry = object.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            object = entry.as_object_mut().expect("object just inserted");
+        }
+        object.insert(keys[keys.len() - 1].clone(), value);
+    }
+
+    Value::Object(root)
+}
+
+/// Change of a configuration field
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    /// Field path (e.g. `ip_filter.allow`)
+    pub path: String,
+
+    /// Previous value (`None` if the field is added)
+    pub old: Option<Value>,
+
+    /// New value (`None` if the field is removed)
+    pub new: Option<Value>,
+}
+
+/// Redacted differences between two configurations
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigDiff {
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigDiff {
+    /// Compute the differences between two configurations, redacting the fields whose name
+    /// contains one of `redacted_keys` (case-insensitive)
+    pub fn new<C: Serialize>(old: &C, new: &C, redacted_keys: &[String]) -> Result<Self, ConfigError> {
+        let old = serde_json::to_value(old).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        let new = serde_json::to_value(new).map_err(|err| ConfigError::Parse(err.to_string()))?;
+
+        let mut diff = Self::default();
+        diff.compare("", Some(&old), Some(&new), false, redacted_keys);
+
+        Ok(diff)
+    }
+
+    /// Return true if the configurations are equal
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn compare(&mut self, path: &str, old: Option<&Value>, new: Option<&Value>, redacted: bool, keys: &[String]) {
+        if let (Some(Value::Object(old)), Some(Value::Object(new))) = (old, new) {
+            let mut fields = old.keys().chain(new.keys()).collect::<Vec<_>>();
+            fields.sort();
+            fields.dedup();
+
+            for field in fields {
+                let field_path = if path.is_empty() {
+                    field.clone()
+                } else {
+                    format!("{path}.{field}")
+                };
+                let field_lowercase = field.to_lowercase();
+                let redacted = redacted || keys.iter().any(|key| field_lowercase.contains(key.as_str()));
+                self.compare(&field_path, old.get(field), new.get(field), redacted, keys);
+            }
+        } else if old != new {
+            let redact = |value: Option<&Value>| {
+                value.map(|value| match redacted {
+                    true => Value::String(REDACTED_VALUE.to_string()),
+                    false => value.clone(),
+                })
+            };
+            self.changes.push(ConfigChange {
+                path: path.to_string(),
+                old: redact(old),
+                new: redact(new),
+            });
+        }
+    }
+}
+
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "(none)".to_string(),
+        };
+        let changes = self
+            .changes
+            .iter()
+            .map(|change| format!("{}: {} -> {}", change.path, value(&change.old), value(&change.new)))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+/// Configuration watcher, cheap to clone
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher<C> {
+    source: ConfigSource,
+    sender: Arc<watch::Sender<C>>,
+    redacted_keys: Vec<String>,
+}
+
+impl<C> ConfigWatcher<C>
+where
+    C: Clone + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Create a watcher with an initial configuration
+    pub fn new(source: ConfigSource, config: C) -> Self {
+        Self {
+            source,
+            sender: Arc::new(watch::Sender::new(config)),
+            redacted_keys: redaction_policy().fields().to_vec(),
+        }
+    }
+
+    /// Create a watcher, loading the initial configuration from the source
+    pub fn load(source: ConfigSource) -> Result<Self, ConfigError> {
+        let config = Self::read(&source)?;
+        Ok(Self::new(source, config))
+    }
+
+    /// Redact the fields whose name contains one of these keys in the diffs
+    /// (default: the fields of the shared `RedactionPolicy`)
+    pub fn with_redacted_keys(mut self, keys: &[&str]) -> Self {
+        self.redacted_keys = keys.iter().map(|key| key.to_lowercase()).collect();
+        self
+    }
+
+    /// Current configuration
+    pub fn current(&self) -> C {
+        self.sender.borrow().clone()
+    }
+
+    /// Subscribe to the configuration updates
+    pub fn subscribe(&self) -> watch::Receiver<C> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribe to a part of the configuration (e.g. the rules of a layer)
+    ///
+    /// The mapping is applied on each reload in a background task. If it fails, an error is
+    /// logged and the previous value is kept.
+    pub fn map<T, E, F>(&self, f: F) -> Result<watch::Receiver<T>, ConfigError>
+    where
+        T: Send + Sync + 'static,
+        E: Display,
+        F: Fn(&C) -> Result<T, E> + Send + 'static,
+    {
+        let initial = f(&self.sender.borrow()).map_err(|err| ConfigError::Map(err.to_string()))?;
+        let (sender, receiver) = watch::channel(initial);
+
+        let mut config = self.sender.subscribe();
+        tokio::spawn(async move {
+            while config.changed().await.is_ok() {
+                let value = f(&config.borrow_and_update());
+                match value {
+                    Ok(value) => {
+                        if sender.send(value).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => warn!("Configuration mapping failed, the previous value is kept: {err}"),
+                }
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    /// Reload the configuration from the source
+    ///
+    /// If it has changed, the redacted diff is logged and the new configuration is published.
+    pub fn reload(&self) -> Result<ConfigDiff, ConfigError> {
+        let config = Self::read(&self.source)?;
+        self.update(config)
+    }
+
+    /// Replace the configuration
+    ///
+    /// If it has changed, the redacted diff is logged and the new configuration is published.
+    pub fn update(&self, config: C) -> Result<ConfigDiff, ConfigError> {
+        let diff = ConfigDiff::new(&*self.sender.borrow(), &config, &self.redacted_keys)?;
+        if !diff.is_empty() {
+            info!(
+                target: "audit",
+                changes = %serde_json::to_string(&diff.changes).unwrap_or_default(),
+                trace_id = %current_trace_id().unwrap_or_default(),
+                span_id = %current_span_id().unwrap_or_default(),
+                "Configuration reloaded: {diff}"
+            );
+            self.sender.send_replace(config);
+        }
+
+        Ok(diff)
+    }
+
+    /// Reload the configuration periodically in a background task
+    ///
+    /// Errors are logged and the current configuration is kept.
+    pub fn spawn(&self, interval: Duration) -> JoinHandle<()> {
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(err) = watcher.reload() {
+                    warn!("Configuration reload failed, the current configuration is kept: {err}");
+                }
+            }
+        })
+    }
+
+    fn read(source: &ConfigSource) -> Result<C, ConfigError> {
+        serde_json::from_value(source.read()?).map_err(|err| ConfigError::Parse(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        allowed_ips: Vec<String>,
+        database: Database,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Database {
+        url: String,
+        password: String,
+    }
+
+    fn config(allowed_ips: &[&str], password: &str) -> Config {
+        Config {
+            allowed_ips: allowed_ips.iter().map(|ip| ip.to_string()).collect(),
+            database: Database {
+                url: "postgres://localhost".to_string(),
+                password: password.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_config_diff_is_redacted() {
+        let keys = vec!["password".to_string()];
+        let diff = ConfigDiff::new(
+            &config(&["10.0.0.0/8"], "old"),
+            &config(&["10.0.0.0/8", "192.168.0.0/16"], "new"),
+            &keys,
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                ConfigChange {
+                    path: "allowed_ips".to_string(),
+                    old: Some(json!(["10.0.0.0/8"])),
+                    new: Some(json!(["10.0.0.0/8", "192.168.0.0/16"])),
+                },
+                ConfigChange {
+                    path: "database.password".to_string(),
+                    old: Some(json!(REDACTED_VALUE)),
+                    new: Some(json!(REDACTED_VALUE)),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            r#"allowed_ips: ["10.0.0.0/8"] -> ["10.0.0.0/8","192.168.0.0/16"], database.password: "***" -> "***""#
+        );
+        assert!(
+            ConfigDiff::new(&config(&[], "a"), &config(&[], "a"), &keys)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_env_to_json() {
+        let vars = [
+            ("APP_ALLOWED_IPS", r#"["10.0.0.0/8"]"#),
+            ("APP_DATABASE__URL", "postgres://localhost"),
+            ("APP_DATABASE__PASSWORD", "secret"),
+            ("OTHER_VAR", "ignored"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let value = env_to_json("APP_", vars);
+        assert_eq!(
+            serde_json::from_value::<Config>(value).unwrap(),
+            config(&["10.0.0.0/8"], "secret")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher_reload() {
+        let path = std::env::temp_dir().join(format!("config-watcher-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&config(&["10.0.0.0/8"], "old")).unwrap()).unwrap();
+
+        let watcher = ConfigWatcher::<Config>::load(ConfigSource::File(path.clone())).unwrap();
+        let mut allowed_ips = watcher
+            .map(|config| match config.allowed_ips.is_empty() {
+                true => Err("empty allow-list"),
+                false => Ok(config.allowed_ips.len()),
+            })
+            .unwrap();
+        assert_eq!(*allowed_ips.borrow(), 1);
+
+        std::fs::write(
+            &path,
+            serde_json::to_vec(&config(&["10.0.0.0/8", "192.168.0.0/16"], "new")).unwrap(),
+        )
+        .unwrap();
+        let diff = watcher.reload().unwrap();
+        assert_eq!(diff.changes.len(), 2);
+        allowed_ips.changed().await.unwrap();
+        assert_eq!(*allowed_ips.borrow_and_update(), 2);
+
+        // Unchanged configuration
+        assert!(watcher.reload().unwrap().is_empty());
+
+        // Invalid mapping: the previous value is kept
+        watcher.update(config(&[], "new")).unwrap();
+        assert_eq!(watcher.current().allowed_ips.len(), 0);
+        tokio::task::yield_now().await;
+        assert_eq!(*allowed_ips.borrow(), 2);
+
+        // Invalid file: the current configuration is kept
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(watcher.reload(), Err(ConfigError::Parse(_))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(watcher.reload(), Err(ConfigError::Read(_))));
+        assert_eq!(watcher.current(), config(&[], "new"));
+    }
+}
@@ -0,0 +1,84 @@
+//! Metrics exporters
+//!
+//! The `MetricsLayer` (and the other layers of the crate) record their metrics with the
+//! [`metrics`] facade. A [`MetricsExporter`] installs the global recorder exporting them:
+//! - [`PrometheusExporter`] (`prometheus` feature): pull model, rendered by the `/metrics` route
+//! - [`statsd::StatsdExporter`] (`statsd` feature): push to a StatsD / DogStatsD agent over UDP
+//! - [`otlp::OtlpExporter`] (`otlp` feature): periodic push to an OpenTelemetry collector
+//!   (OTLP/HTTP with the JSON encoding)
+//!
+//! Only one recorder can be installed per process.
+
+#[cfg(feature = "otlp")]
+pub mod otlp;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+
+use crate::server::axum::response::ApiError;
+
+/// Exporter installing the global `metrics` recorder
+pub trait MetricsExporter {
+    /// Value returned by the installation (e.g. the `PrometheusHandle` rendering the metrics)
+    type Handle;
+
+    /// Install the global recorder
+    ///
+    /// Returns an error if a recorder is already installed.
+    fn install(self) -> Result<Self::Handle, ApiError>;
+}
+
+/// Prometheus exporter (see `PrometheusHandler`)
+#[cfg(feature = "prometheus")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrometheusExporter {
+    /// Buckets of the `http_requests_duration_seconds` histogram
+    pub buckets: Vec<f64>,
+
+    /// Enable the exemplars (trace IDs) of `http_requests_duration_seconds`
+    pub exemplars: bool,
+}
+
+#[cfg(feature = "prometheus")]
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        Self {
+            buckets: crate::server::axum::handlers::prometheus::DEFAULT_DURATION_BUCKETS.to_vec(),
+            exemplars: false,
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl PrometheusExporter {
+    /// Create a new `PrometheusExporter` with the default buckets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the buckets of the `http_requests_duration_seconds` histogram (in seconds)
+    pub fn with_buckets(mut self, buckets: &[f64]) -> Self {
+        self.buckets = buckets.to_vec();
+        self
+    }
+
+    /// Enable the exemplars
+    pub fn with_exemplars(mut self) -> Self {
+        self.exemplars = true;
+        self
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl MetricsExporter for PrometheusExporter {
+    type Handle = metrics_exporter_prometheus::PrometheusHandle;
+
+    fn install(self) -> Result<Self::Handle, ApiError> {
+        use crate::server::axum::handlers::prometheus::PrometheusHandler;
+
+        if self.exemplars {
+            PrometheusHandler::get_handle_with_exemplars(&self.buckets)
+        } else {
+            PrometheusHandler::get_handle_with_buckets(&self.buckets)
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! OTLP metrics exporter
+//!
+//! [`OtlpExporter`] aggregates the metrics in memory and pushes them periodically to an
+//! OpenTelemetry collector with OTLP/HTTP and the JSON encoding (`POST /v1/metrics`):
+//! - counters: cumulative monotonic sums
+//! - gauges: gauges
+//! - histograms: cumulative histograms with explicit bucket bounds
+//!
+//! The labels are exported as data point attributes and the service name as the `service.name`
+//! resource attribute. A failed push is logged and retried with the next one (the values are
+//! cumulative).
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::exporters::MetricsExporter;
+//! use api_tools::server::axum::exporters::otlp::OtlpExporter;
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let push_task = OtlpExporter::new("http://otel-collector:4318/v1/metrics", "myapp")
+//!     .with_interval(Duration::from_secs(15))
+//!     .with_header("authorization", "Bearer token")
+//!     .install()
+//!     .unwrap();
+//! # }
+//! ```
+
+use super::MetricsExporter;
+use crate::server::axum::response::ApiError;
+use metrics::atomics::AtomicU64;
+use metrics::{Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Default push interval
+pub const OTLP_DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default explicit bounds of the histograms (in seconds, suitable for HTTP latencies)
+pub const OTLP_DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// OTLP exporter
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtlpExporter {
+    /// URL of the OTLP/HTTP metrics endpoint (e.g. `http://localhost:4318/v1/metrics`)
+    pub endpoint: String,
+
+    /// Service name (`service.name` resource attribute)
+    pub service_name: String,
+
+    /// Push interval
+    pub interval: Duration,
+
+    /// Headers of the push requests (e.g. authentication)
+    pub headers: Vec<(String, String)>,
+
+    /// Explicit bounds of the histograms
+    pub buckets: Vec<f64>,
+}
+
+impl OtlpExporter {
+    /// Create a new `OtlpExporter`
+    pub fn new(endpoint: &str, service_name: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            service_name: service_name.to_string(),
+            interval: OTLP_DEFAULT_INTERVAL,
+            headers: Vec::new(),
+            buckets: OTLP_DEFAULT_BUCKETS.to_vec(),
+        }
+    }
+
+    /// Set the push interval
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Add a header to the push requests
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the explicit bounds of the histograms
+    pub fn with_buckets(mut self, buckets: &[f64]) -> Self {
+        self.buckets = buckets.to_vec();
+        self
+    }
+
+    /// Build the recorder without installing it
+    pub fn build_recorder(&self) -> OtlpRecorder {
+        OtlpRecorder {
+            state: Arc::new(OtlpState {
+                service_name: self.service_name.clone(),
+                start_time: unix_nanos(),
+                buckets: self.buckets.clone().into(),
+                counters: RwLock::new(HashMap::new()),
+                gauges: RwLock::new(HashMap::new()),
+                histograms: RwLock::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+impl MetricsExporter for OtlpExporter {
+    /// Push task, to abort at shutdown
+    type Handle = JoinHandle<()>;
+
+    /// Install the global recorder and spawn the push task (requires a Tokio runtime)
+    fn install(self) -> Result<Self::Handle, ApiError> {
+        let recorder = self.build_recorder();
+        metrics::set_global_recorder(recorder.clone()).map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+
+        let client = reqwest::Client::new();
+        Ok(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            // The first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                let mut request = client
+                    .post(&self.endpoint)
+                    .header(reqwest::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(recorder.payload().to_string());
+                for (name, value) in &self.headers {
+                    request = request.header(name, value);
+                }
+
+                match request.send().await {
+                    Ok(response) if !response.status().is_success() => {
+                        warn!(endpoint = %self.endpoint, status = %response.status(), "OTLP metrics push rejected");
+                    }
+                    Err(err) => warn!(endpoint = %self.endpoint, error = %err, "OTLP metrics push error"),
+                    _ => {}
+                }
+            }
+        }))
+    }
+}
+
+/// OTLP recorder aggregating the metrics in memory
+#[derive(Debug, Clone)]
+pub struct OtlpRecorder {
+    state: Arc<OtlpState>,
+}
+
+#[derive(Debug)]
+struct OtlpState {
+    service_name: String,
+    start_time: u128,
+    buckets: Arc<[f64]>,
+    counters: RwLock<HashMap<Key, Arc<AtomicU64>>>,
+    gauges: RwLock<HashMap<Key, Arc<AtomicU64>>>,
+    histograms: RwLock<HashMap<Key, Arc<OtlpHistogram>>>,
+}
+
+impl OtlpRecorder {
+    /// OTLP/JSON `ExportMetricsServiceRequest` of the current (cumulative) values
+    pub fn payload(&self) -> Value {
+        let state = &self.state;
+        let start_time = state.start_time.to_string();
+        let time = unix_nanos().to_string();
+        let mut metrics = BTreeMap::<String, Value>::new();
+
+        for (key, counter) in snapshot(&state.counters) {
+            let data_point = json!({
+                "attributes": attributes(&key),
+                "startTimeUnixNano": start_time,
+                "timeUnixNano": time,
+                "asInt": counter.load(Ordering::Acquire).to_string(),
+            });
+            push_data_point(
+                &mut metrics,
+                &key,
+                "sum",
+                data_point,
+                || json!({ "aggregationTemporality": 2, "isMonotonic": true, "dataPoints": [] }),
+            );
+        }
+
+        for (key, gauge) in snapshot(&state.gauges) {
+            let data_point = json!({
+                "attributes": attributes(&key),
+                "timeUnixNano": time,
+                "asDouble": f64::from_bits(gauge.load(Ordering::Acquire)),
+            });
+            push_data_point(&mut metrics, &key, "gauge", data_point, || json!({ "dataPoints": [] }));
+        }
+
+        for (key, histogram) in snapshot(&state.histograms) {
+            let Ok(data) = histogram.data.lock() else {
+                continue;
+            };
+            let data_point = json!({
+                "attributes": attributes(&key),
+                "startTimeUnixNano": start_time,
+                "timeUnixNano": time,
+                "count": data.count.to_string(),
+                "sum": data.sum,
+                "bucketCounts": data.bucket_counts.iter().map(u64::to_string).collect::<Vec<_>>(),
+                "explicitBounds": histogram.buckets.as_ref(),
+            });
+            push_data_point(
+                &mut metrics,
+                &key,
+                "histogram",
+                data_point,
+                || json!({ "aggregationTemporality": 2, "dataPoints": [] }),
+            );
+        }
+
+        json!({
+            "resourceMetrics": [{
+                "resource": {
+                    "attributes": [{ "key": "service.name", "value": { "stringValue": state.service_name } }],
+                },
+                "scopeMetrics": [{
+                    "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                    "metrics": metrics.into_values().collect::<Vec<_>>(),
+                }],
+            }],
+        })
+    }
+}
+
+impl Recorder for OtlpRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(get_or_insert(&self.state.counters, key, || AtomicU64::new(0)))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(get_or_insert(&self.state.gauges, key, || {
+            AtomicU64::new(0.0f64.to_bits())
+        }))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(get_or_insert(&self.state.histograms, key, || {
+            OtlpHistogram::new(self.state.buckets.clone())
+        }))
+    }
+}
+
+/// Cumulative histogram with explicit bucket bounds
+#[derive(Debug)]
+struct OtlpHistogram {
+    buckets: Arc<[f64]>,
+    data: Mutex<HistogramData>,
+}
+
+#[derive(Debug)]
+struct HistogramData {
+    bucket_counts: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl OtlpHistogram {
+    fn new(buckets: Arc<[f64]>) -> Self {
+        Self {
+            data: Mutex::new(HistogramData {
+                bucket_counts: vec![0; buckets.len() + 1],
+                count: 0,
+                sum: 0.0,
+            }),
+            buckets,
+        }
+    }
+}
+
+impl HistogramFn for OtlpHistogram {
+    fn record(&self, value: f64) {
+        let bucket = self
+            .buckets
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.buckets.len());
+
+        if let Ok(mut data) = self.data.lock() {
+            data.bucket_counts[bucket] += 1;
+            data.count += 1;
+            data.sum += value;
+        }
+    }
+}
+
+/// Get or register the storage of a metric
+fn get_or_insert<T>(metrics: &RwLock<HashMap<Key, Arc<T>>>, key: &Key, init: impl FnOnce() -> T) -> Arc<T> {
+    if let Some(metric) = metrics.read().ok().and_then(|metrics| metrics.get(key).cloned()) {
+        return metric;
+    }
+
+    match metrics.write() {
+        Ok(mut metrics) => metrics.entry(key.clone()).or_insert_with(|| Arc::new(init())).clone(),
+        Err(_) => Arc::new(init()),
+    }
+}
+
+/// Copy of the registered metrics, to release the lock before reading them
+fn snapshot<K: Clone + Eq + Hash, T>(metrics: &RwLock<HashMap<K, Arc<T>>>) -> Vec<(K, Arc<T>)> {
+    metrics
+        .read()
+        .map(|metrics| {
+            metrics
+                .iter()
+                .map(|(key, metric)| (key.clone(), metric.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Add a data point to the metric of a key
+fn push_data_point(
+    metrics: &mut BTreeMap<String, Value>,
+    key: &Key,
+    kind: &str,
+    data_point: Value,
+    init: impl FnOnce() -> Value,
+) {
+    let metric = metrics
+        .entry(key.name().to_string())
+        .or_insert_with(|| json!({ "name": key.name(), kind: init() }));
+    if let Some(data_points) = metric[kind]["dataPoints"].as_array_mut() {
+        data_points.push(data_point);
+    }
+}
+
+/// OTLP attributes of the labels of a key
+fn attributes(key: &Key) -> Vec<Value> {
+    key.labels()
+        .map(|label| json!({ "key": label.key(), "value": { "stringValue": label.value() } }))
+        .collect()
+}
+
+/// Current Unix time in nanoseconds
+fn unix_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otlp_recorder_payload() {
+        let recorder = OtlpExporter::new("http://localhost:4318/v1/metrics", "myapp")
+            .with_buckets(&[0.1, 0.5])
+            .build_recorder();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("http_requests_total", "path" => "/users").increment(2);
+            metrics::counter!("http_requests_total", "path" => "/users").increment(1);
+            metrics::gauge!("queue_size").set(4.0);
+            for value in [0.05, 0.3, 2.0] {
+                metrics::histogram!("http_requests_duration_seconds").record(value);
+            }
+        });
+
+        let payload = recorder.payload();
+        let resource = &payload["resourceMetrics"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "myapp");
+
+        let metrics = resource["scopeMetrics"][0]["metrics"].as_array().unwrap();
+        let metric = |name: &str| metrics.iter().find(|metric| metric["name"] == name).unwrap();
+
+        let counter = &metric("http_requests_total")["sum"];
+        assert_eq!(counter["isMonotonic"], true);
+        assert_eq!(counter["dataPoints"][0]["asInt"], "3");
+        assert_eq!(
+            counter["dataPoints"][0]["attributes"],
+            json!([{ "key": "path", "value": { "stringValue": "/users" } }])
+        );
+
+        assert_eq!(metric("queue_size")["gauge"]["dataPoints"][0]["asDouble"], 4.0);
+
+        let histogram = &metric("http_requests_duration_seconds")["histogram"]["dataPoints"][0];
+        assert_eq!(histogram["count"], "3");
+        assert_eq!(histogram["sum"], 2.35);
+        assert_eq!(histogram["bucketCounts"], json!(["1", "1", "1"]));
+        assert_eq!(histogram["explicitBounds"], json!([0.1, 0.5]));
+    }
+}
@@ -0,0 +1,293 @@
+//! StatsD metrics exporter
+//!
+//! [`StatsdExporter`] sends every metric update to a StatsD agent over UDP, with the labels as
+//! DogStatsD tags (`name:value|type|#key:value,...`), so that it can be used with the Datadog
+//! agent or any StatsD server supporting tags:
+//! - counters: `|c`
+//! - gauges: `|g` (`+value` / `-value` for increments and decrements)
+//! - histograms: `|h` (or `|ms` with [`StatsdExporter::with_timer_histograms`], values in
+//!   milliseconds)
+//!
+//! The packets are sent without blocking and the errors are ignored (UDP is lossy anyway).
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::exporters::MetricsExporter;
+//! use api_tools::server::axum::exporters::statsd::StatsdExporter;
+//!
+//! StatsdExporter::new("127.0.0.1:8125")
+//!     .with_prefix("myapp")
+//!     .with_tag("env", "prod")
+//!     .install()
+//!     .unwrap();
+//! ```
+
+use super::MetricsExporter;
+use crate::server::axum::response::ApiError;
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+use std::collections::HashMap;
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, RwLock};
+
+/// StatsD exporter
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsdExporter {
+    /// Address of the StatsD agent (e.g. `127.0.0.1:8125`)
+    pub address: String,
+
+    /// Prefix of the metric names (`prefix.name`)
+    pub prefix: Option<String>,
+
+    /// Tags added to every metric
+    pub tags: Vec<(String, String)>,
+
+    /// Send the histograms as timers (`|ms`, values converted from seconds to milliseconds)
+    pub timer_histograms: bool,
+}
+
+impl StatsdExporter {
+    /// Create a new `StatsdExporter`
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            prefix: None,
+            tags: Vec::new(),
+            timer_histograms: false,
+        }
+    }
+
+    /// Set the prefix of the metric names
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Add a tag to every metric
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Send the histograms as timers (`|ms`), for StatsD servers without histograms
+    pub fn with_timer_histograms(mut self) -> Self {
+        self.timer_histograms = true;
+        self
+    }
+
+    /// Build the recorder without installing it
+    pub fn build_recorder(self) -> Result<StatsdRecorder, ApiError> {
+        let socket = self
+            .address
+            .to_socket_addrs()
+            .and_then(|mut addresses| {
+                let address = addresses
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address"))?;
+                let socket = UdpSocket::bind(if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+                socket.connect(address)?;
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            })
+            .map_err(|err| ApiError::InternalServerError(format!("StatsD socket error: {err}")))?;
+
+        Ok(StatsdRecorder {
+            socket: Arc::new(socket),
+            exporter: self,
+            metrics: RwLock::new(HashMap::new()),
+        })
+    }
+}
+
+impl MetricsExporter for StatsdExporter {
+    type Handle = ();
+
+    fn install(self) -> Result<Self::Handle, ApiError> {
+        metrics::set_global_recorder(self.build_recorder()?)
+            .map_err(|err| ApiError::InternalServerError(err.to_string()))
+    }
+}
+
+/// Kind of a StatsD metric, used as the cache key with the metric key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+type MetricCacheKey = (MetricKind, Key);
+
+/// StatsD recorder sending the metric updates over UDP
+#[derive(Debug)]
+pub struct StatsdRecorder {
+    socket: Arc<UdpSocket>,
+    exporter: StatsdExporter,
+    metrics: RwLock<HashMap<MetricCacheKey, Arc<StatsdMetric>>>,
+}
+
+impl StatsdRecorder {
+    /// Metric of a key, created and cached on first use
+    fn metric(&self, kind: MetricKind, key: &Key) -> Arc<StatsdMetric> {
+        let cache_key = (kind, key.clone());
+        if let Some(metric) = self
+            .metrics
+            .read()
+            .ok()
+            .and_then(|metrics| metrics.get(&cache_key).cloned())
+        {
+            return metric;
+        }
+
+        let name = match &self.exporter.prefix {
+            Some(prefix) => format!("{prefix}.{}", key.name()),
+            None => key.name().to_string(),
+        };
+        let tags = self
+            .exporter
+            .tags
+            .iter()
+            .map(|(key, value)| format!("{key}:{value}"))
+            .chain(key.labels().map(|label| format!("{}:{}", label.key(), label.value())))
+            .collect::<Vec<_>>();
+        let tags = if tags.is_empty() {
+            String::new()
+        } else {
+            format!("|#{}", tags.join(","))
+        };
+        let metric = Arc::new(StatsdMetric {
+            socket: self.socket.clone(),
+            name,
+            tags,
+            timer: self.exporter.timer_histograms,
+        });
+
+        if let Ok(mut metrics) = self.metrics.write() {
+            metrics.insert(cache_key, metric.clone());
+        }
+
+        metric
+    }
+}
+
+impl Recorder for StatsdRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.metric(MetricKind::Counter, key))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.metric(MetricKind::Gauge, key))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.metric(MetricKind::Histogram, key))
+    }
+}
+
+/// StatsD metric with its formatted name and tags
+#[derive(Debug)]
+struct StatsdMetric {
+    socket: Arc<UdpSocket>,
+    name: String,
+    tags: String,
+    timer: bool,
+}
+
+impl StatsdMetric {
+    fn send(&self, value: &str, kind: &str) {
+        let packet = format!("{}:{value}|{kind}{}", self.name, self.tags);
+        let _ = self.socket.send(packet.as_bytes());
+    }
+}
+
+impl CounterFn for StatsdMetric {
+    fn increment(&self, value: u64) {
+        self.send(&value.to_string(), "c");
+    }
+
+    fn absolute(&self, value: u64) {
+        self.send(&value.to_string(), "g");
+    }
+}
+
+impl GaugeFn for StatsdMetric {
+    fn increment(&self, value: f64) {
+        self.send(&format!("+{value}"), "g");
+    }
+
+    fn decrement(&self, value: f64) {
+        self.send(&format!("-{value}"), "g");
+    }
+
+    fn set(&self, value: f64) {
+        // A negative value would be read as a decrement
+        if value < 0.0 {
+            self.send("0", "g");
+        }
+        self.send(&value.to_string(), "g");
+    }
+}
+
+impl HistogramFn for StatsdMetric {
+    fn record(&self, value: f64) {
+        if self.timer {
+            self.send(&(value * 1_000.0).to_string(), "ms");
+        } else {
+            self.send(&value.to_string(), "h");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(socket: &UdpSocket) -> String {
+        let mut buffer = [0; 512];
+        let size = socket.recv(&mut buffer).unwrap();
+        String::from_utf8(buffer[..size].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_statsd_recorder() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+        let recorder = StatsdExporter::new(&agent.local_addr().unwrap().to_string())
+            .with_prefix("myapp")
+            .with_tag("env", "prod")
+            .build_recorder()
+            .unwrap();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("http_requests_total", "method" => "GET", "status" => "200").increment(1);
+            metrics::gauge!("queue_size").set(-3.0);
+            metrics::gauge!("queue_size").increment(2.5);
+            metrics::histogram!("http_requests_duration_seconds").record(0.25);
+        });
+
+        assert_eq!(
+            receive(&agent),
+            "myapp.http_requests_total:1|c|#env:prod,method:GET,status:200"
+        );
+        assert_eq!(receive(&agent), "myapp.queue_size:0|g|#env:prod");
+        assert_eq!(receive(&agent), "myapp.queue_size:-3|g|#env:prod");
+        assert_eq!(receive(&agent), "myapp.queue_size:+2.5|g|#env:prod");
+        assert_eq!(receive(&agent), "myapp.http_requests_duration_seconds:0.25|h|#env:prod");
+
+        let recorder = StatsdExporter::new(&agent.local_addr().unwrap().to_string())
+            .with_timer_histograms()
+            .build_recorder()
+            .unwrap();
+        metrics::with_local_recorder(&recorder, || {
+            metrics::histogram!("latency").record(0.25);
+        });
+        assert_eq!(receive(&agent), "latency:250|ms");
+    }
+}
@@ -1,13 +1,25 @@
 //! Extractor modules for Axum
 
+use crate::server::axum::layers::context::RequestContext;
+use crate::server::axum::layers::injector::{InjectorError, RequestScope};
 use crate::server::axum::layers::request_id::REQUEST_ID_HEADER;
-use crate::server::axum::response::ApiError;
-use axum::extract::FromRequestParts;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use crate::server::axum::response::{ApiError, ApiErrorResponse, BodyFormat, ValidationErrors, current_trace_id};
 use axum::extract::path::ErrorKind;
+use axum::extract::rejection::JsonRejection;
 use axum::extract::rejection::PathRejection;
+use axum::extract::{FromRequest, FromRequestParts, Request};
 use axum::http::request::Parts;
 use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use serde::Serialize;
 use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
 
 /// Request ID extractor from HTTP headers
 pub struct RequestId(pub HeaderValue);
@@ -98,14 +110,556 @@ where
     }
 }
 
+/// Configuration of the [`QueryQs`] extractor, read from the request extensions
+///
+/// # Example
+/// ```rust
+/// use api_tools::server::axum::extractors::{QueryQs, QueryQsConfig};
+/// use axum::{Extension, Router, routing::get};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Filters {
+///     ids: Vec<u32>,
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/users", get(|QueryQs(filters): QueryQs<Filters>| async move { filters.ids.len().to_string() }))
+///     .layer(Extension(QueryQsConfig::new().with_strict(true)));
+/// ```
+#[cfg(feature = "query-qs")]
+#[derive(Debug, Clone, Copy)]
+pub struct QueryQsConfig {
+    pub strict: bool,
+    pub max_depth: usize,
+}
+
+#[cfg(feature = "query-qs")]
+impl Default for QueryQsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "query-qs")]
+impl QueryQsConfig {
+    /// Create a new lenient configuration (unknown parameters are ignored, maximum depth of 5)
+    pub fn new() -> Self {
+        Self {
+            strict: false,
+            max_depth: 5,
+        }
+    }
+
+    /// Reject the requests with unknown parameters
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the maximum nesting depth of the parameters
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+/// `QueryQs` extractor deserializes the query string with `serde_qs` (`query-qs` feature)
+///
+/// Unlike [`Query`], it supports sequences (`ids=1&ids=2`, `ids[]=1&ids[]=2` or
+/// `ids[0]=1&ids[1]=2`) and nested structs (`filter[status]=active`).
+/// Errors and, in strict mode (see [`QueryQsConfig`]), unknown parameters are returned as a
+/// `400 Bad Request` error.
+#[cfg(feature = "query-qs")]
+pub struct QueryQs<T>(pub T);
+
+#[cfg(feature = "query-qs")]
+impl<T, S> FromRequestParts<S> for QueryQs<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, ApiError);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let config = parts.extensions.get::<QueryQsConfig>().copied().unwrap_or_default();
+        let query = parts.uri.query().unwrap_or_default();
+        let bad_request = |message: String| (StatusCode::BAD_REQUEST, ApiError::BadRequest(message));
+
+        let deserializer =
+            serde_qs::Deserializer::with_config(serde_qs::Config::new().max_depth(config.max_depth), query.as_bytes())
+                .map_err(|err| bad_request(err.to_string()))?;
+
+        let mut unknown = Vec::new();
+        let value = serde_ignored::deserialize(deserializer, |path| unknown.push(query_param_name(&path)))
+            .map_err(|err| bad_request(err.to_string()))?;
+
+        if config.strict && !unknown.is_empty() {
+            unknown.sort();
+            return Err(bad_request(format!("Unknown query parameters: {}", unknown.join(", "))));
+        }
+
+        Ok(QueryQs(value))
+    }
+}
+
+/// Name of a query parameter in the bracket notation (`filter[status]`)
+#[cfg(feature = "query-qs")]
+fn query_param_name(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{index}]", query_param_name(parent)),
+        Path::Map { parent, key } => match query_param_name(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{parent}[{key}]"),
+        },
+        Path::Some { parent } | Path::NewtypeStruct { parent } | Path::NewtypeVariant { parent } => {
+            query_param_name(parent)
+        }
+    }
+}
+
+/// `Json` extractor customizes the error from `axum::Json`
+///
+/// Rejections are returned with the JSON error format:
+/// - syntax errors: `400 Bad Request`, with the line and the column of the error
+/// - data errors (missing field, wrong type): `422 Unprocessable Entity`, with the path of the
+///   field and the line and the column of the error
+/// - missing or invalid `Content-Type`: `415 Unsupported Media Type`
+/// - body larger than the limit: `413 Payload Too Large`
+///
+/// It can also be used as a response.
+pub struct Json<T>(pub T);
+
+impl<S, T> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::<T>::from_request(request, state)
+            .await
+            .map(|value| Self(value.0))
+            .map_err(Self::rejection_response)
+    }
+}
+
+impl<T> Json<T> {
+    /// Build the response of a rejection of the JSON body
+    fn rejection_response(rejection: JsonRejection) -> Response {
+        // The source contains the path of the field, the line and the column
+        let detail = std::error::Error::source(&rejection)
+            .map(ToString::to_string)
+            .unwrap_or_else(|| rejection.body_text());
+
+        match rejection {
+            JsonRejection::JsonSyntaxError(_) => {
+                ApiError::BadRequest(format!("Invalid JSON: {detail}")).into_response()
+            }
+            JsonRejection::JsonDataError(_) => {
+                ApiError::UnprocessableEntity(format!("Invalid JSON body: {detail}")).into_response()
+            }
+            JsonRejection::MissingJsonContentType(_) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                axum::Json(ApiErrorResponse::new(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "Expected request with `Content-Type: application/json`",
+                    current_trace_id(),
+                )),
+            )
+                .into_response(),
+            JsonRejection::BytesRejection(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                ApiError::PayloadTooLarge.into_response()
+            }
+            _ => ApiError::BadRequest(rejection.body_text()).into_response(),
+        }
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// `Negotiated` extractor decodes the body in the format of the `Content-Type` header (see
+/// [`BodyFormat`]): JSON, MessagePack (`msgpack` feature) or CBOR (`cbor` feature)
+///
+/// JSON bodies are decoded by the [`Json`] extractor. Rejections use the JSON error format:
+/// - unsupported `Content-Type`: `415 Unsupported Media Type`
+/// - invalid binary body: `422 Unprocessable Entity`
+/// - body larger than the limit: `413 Payload Too Large`
+pub struct Negotiated<T>(pub T);
+
+impl<S, T> FromRequest<S> for Negotiated<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(format) = BodyFormat::from_content_type(request.headers()) else {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                axum::Json(ApiErrorResponse::new(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "Unsupported `Content-Type`",
+                    current_trace_id(),
+                )),
+            )
+                .into_response());
+        };
+        if format == BodyFormat::Json {
+            return Json::<T>::from_request(request, state)
+                .await
+                .map(|Json(value)| Self(value));
+        }
+
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|rejection| match rejection.status() {
+                StatusCode::PAYLOAD_TOO_LARGE => ApiError::PayloadTooLarge.into_response(),
+                _ => ApiError::BadRequest(rejection.body_text()).into_response(),
+            })?;
+
+        format.deserialize(&bytes).map(Self).map_err(|err| {
+            ApiError::UnprocessableEntity(format!("Invalid {} body: {err}", format.media_type())).into_response()
+        })
+    }
+}
+
+/// `Dep` extractor resolves a service registered in the `InjectorLayer`
+/// (possibly a trait object, e.g. `Dep<dyn Mailer>`)
+pub struct Dep<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> Deref for Dep<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S, T> FromRequestParts<S> for Dep<T>
+where
+    T: ?Sized + Send + Sync + 'static,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let service = parts
+            .extensions
+            .get::<RequestScope>()
+            .ok_or(InjectorError::MissingLayer)
+            .and_then(RequestScope::resolve::<T>)
+            .inspect_err(|err| error!("{err}"))?;
+
+        Ok(Dep(service))
+    }
+}
+
+/// `Inject` extractor resolves a service of the [`ServiceRegistry`](crate::server::axum::service_registry::ServiceRegistry)
+/// (e.g. `Inject<dyn MailSender>`)
+///
+/// It resolves the service like [`Dep`]: a missing registry or service returns a `500` error.
+pub struct Inject<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> Deref for Inject<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S, T> FromRequestParts<S> for Inject<T>
+where
+    T: ?Sized + Send + Sync + 'static,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Dep(service) = Dep::<T>::from_request_parts(parts, state).await?;
+
+        Ok(Inject(service))
+    }
+}
+
+/// `State` extractor gets a singleton registered in the [`AppState`](crate::server::axum::app_state::AppState)
+///
+/// It resolves the entry like [`Dep`]: a missing entry returns a `500` JSON error instead of
+/// panicking.
+pub struct State<T: ?Sized>(pub Arc<T>);
+
+impl<T: ?Sized> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S, T> FromRequestParts<S> for State<T>
+where
+    T: ?Sized + Send + Sync + 'static,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Dep(entry) = Dep::<T>::from_request_parts(parts, state).await?;
+
+        Ok(State(entry))
+    }
+}
+
+/// `Ctx` extractor gets the [`RequestContext`] inserted by the `ContextLayer`
+///
+/// The principal is read from the `RequestStore` when the extractor runs, so that it is set
+/// even if the authentication layer runs after the `ContextLayer`.
+pub struct Ctx(pub RequestContext);
+
+impl Deref for Ctx {
+    type Target = RequestContext;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S> FromRequestParts<S> for Ctx
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let mut context = parts
+            .extensions
+            .get::<RequestContext>()
+            .cloned()
+            .ok_or_else(|| ApiError::InternalServerError("ContextLayer is missing".to_string()))?;
+
+        if let Some(principal) =
+            RequestStore::from_extensions(&parts.extensions).and_then(|store| store.get::<Principal>())
+        {
+            context.principal = Some(principal.0.clone());
+        }
+
+        Ok(Ctx(context))
+    }
+}
+
+/// Conversion of a domain type from its request DTO
+///
+/// Implementations validate the DTO (typically by building value objects) and report
+/// every invalid field at once.
+pub trait FromDto: Sized {
+    /// Request DTO, deserialized from the JSON body
+    type Dto: DeserializeOwned;
+
+    fn from_dto(dto: Self::Dto) -> Result<Self, ValidationErrors>;
+}
+
+/// Conversion of a request DTO into the domain type `T`
+///
+/// Implemented for the DTO of every [`FromDto`] type, so that a DTO can also be converted
+/// outside of the [`Dto`] extractor (e.g. `dto.try_into_domain()` in a service or a test).
+pub trait TryIntoDomain<T> {
+    fn try_into_domain(self) -> Result<T, ValidationErrors>;
+}
+
+impl<T: FromDto> TryIntoDomain<T> for T::Dto {
+    fn try_into_domain(self) -> Result<T, ValidationErrors> {
+        T::from_dto(self)
+    }
+}
+
+/// `Dto` extractor deserializes the JSON body into the DTO of `T` and converts it
+/// into `T` with `FromDto`
+///
+/// The body is read like the [`Json`] extractor (`400` for invalid JSON, `413` for a body too
+/// large, `415` for a missing or wrong content type), except for:
+///
+/// - Invalid JSON data or conversion errors: `422 Unprocessable Entity` with the list of field errors
+///
+/// # Example
+///
+/// ```rust
+/// use api_tools::server::axum::extractors::{Dto, FromDto};
+/// use api_tools::server::axum::response::ValidationErrors;
+/// use api_tools::value_objects::email::Email;
+/// use serde::Deserialize;
+///
+/// struct User {
+///     email: Email,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct CreateUserDto {
+///     email: String,
+/// }
+///
+/// impl FromDto for User {
+///     type Dto = CreateUserDto;
+///
+///     fn from_dto(dto: CreateUserDto) -> Result<Self, ValidationErrors> {
+///         let mut errors = ValidationErrors::new();
+///         let email = Email::new(&dto.email).map_err(|err| errors.add("email", &err.to_string()));
+///
+///         match email {
+///             Ok(email) if errors.is_empty() => Ok(User { email }),
+///             _ => Err(errors),
+///         }
+///     }
+/// }
+///
+/// async fn create_user(Dto(user): Dto<User>) -> String {
+///     user.email.value().to_string()
+/// }
+/// ```
+pub struct Dto<T>(pub T);
+
+impl<S, T> FromRequest<S> for Dto<T>
+where
+    T: FromDto,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(dto) = axum::Json::<T::Dto>::from_request(request, state)
+            .await
+            .map_err(|rejection| match rejection {
+                JsonRejection::JsonDataError(err) => {
+                    let mut errors = ValidationErrors::new();
+                    errors.add("body", &err.body_text());
+                    errors.into_response()
+                }
+                _ => Json::<T::Dto>::rejection_response(rejection),
+            })?;
+
+        let domain = dto.try_into_domain().map_err(IntoResponse::into_response)?;
+
+        Ok(Self(domain))
+    }
+}
+
+/// Query string parameters parsed field by field, aggregating errors
+///
+/// Used by the code generated by `#[derive(ApiQuery)]` (`derive` feature).
+#[derive(Debug, Default)]
+pub struct QueryParams {
+    params: HashMap<String, String>,
+    errors: ValidationErrors,
+}
+
+impl QueryParams {
+    /// Parse the query string of a request
+    pub fn from_parts(parts: &Parts) -> Result<Self, ValidationErrors> {
+        Self::parse(parts.uri.query().unwrap_or_default())
+    }
+
+    /// Parse a query string
+    pub fn parse(query: &str) -> Result<Self, ValidationErrors> {
+        let params = serde_urlencoded::from_str::<Vec<(String, String)>>(query).map_err(|err| {
+            let mut errors = ValidationErrors::new();
+            errors.add("query", &err.to_string());
+            errors
+        })?;
+
+        Ok(Self {
+            params: params.into_iter().collect(),
+            errors: ValidationErrors::new(),
+        })
+    }
+
+    /// Get and parse a required parameter, using `default` if it is missing
+    pub fn required<T: FromStr>(&mut self, field: &str, default: Option<&str>) -> Option<T> {
+        match self.params.get(field).cloned().or(default.map(str::to_string)) {
+            Some(value) => self.parse_value(field, &value),
+            None => {
+                self.errors.add(field, "is required");
+                None
+            }
+        }
+    }
+
+    /// Get and parse an optional parameter
+    pub fn optional<T: FromStr>(&mut self, field: &str) -> Option<T> {
+        let value = self.params.get(field)?.clone();
+        self.parse_value(field, &value)
+    }
+
+    /// Check that a value is greater than or equal to `min`
+    pub fn check_min<T: PartialOrd + Display>(&mut self, field: &str, value: &T, min: &T) {
+        if value < min {
+            self.errors.add(field, &format!("must be at least {min}"));
+        }
+    }
+
+    /// Check that a value is less than or equal to `max`
+    pub fn check_max<T: PartialOrd + Display>(&mut self, field: &str, value: &T, max: &T) {
+        if value > max {
+            self.errors.add(field, &format!("must be at most {max}"));
+        }
+    }
+
+    /// Check that a value matches a regular expression
+    #[cfg(feature = "derive")]
+    pub fn check_regex<T: Display>(&mut self, field: &str, value: &T, regex: &regex::Regex) {
+        if !regex.is_match(&value.to_string()) {
+            self.errors.add(field, &format!("must match {}", regex.as_str()));
+        }
+    }
+
+    /// Return all the errors found
+    pub fn finish(self) -> Result<(), ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn parse_value<T: FromStr>(&mut self, field: &str, value: &str) -> Option<T> {
+        match value.parse::<T>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.errors.add(field, "has an invalid format");
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "derive")]
+    use crate::ApiQuery;
+    use crate::server::axum::app_state::AppState;
+    use crate::server::axum::layers::injector::{Injector, InjectorLayer};
+    use crate::server::axum::response::FieldError;
+    use crate::server::axum::service_registry::ServiceRegistry;
+    #[cfg(feature = "query-qs")]
+    use axum::Extension;
     use axum::Router;
     use axum::body::Body;
-    use axum::http::Request;
-    use axum::routing::get;
-    use serde::Deserialize;
+    use axum::http::{Request, header};
+    use axum::routing::{get, post};
+    use serde::{Deserialize, Serialize};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tower::ServiceExt;
 
     async fn read_body(response: axum::response::Response) -> String {
@@ -246,4 +800,639 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    // ---------------- Json ----------------
+
+    #[derive(Deserialize, Serialize)]
+    struct UserDto {
+        name: String,
+        age: u8,
+    }
+
+    async fn call_json(content_type: &str, body: &'static str) -> (StatusCode, String) {
+        let app: Router = Router::new()
+            .route(
+                "/",
+                axum::routing::post(|Json(user): Json<UserDto>| async move { Json(user) }),
+            )
+            .layer(axum::extract::DefaultBodyLimit::max(64));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header(axum::http::header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+
+        (status, read_body(response).await)
+    }
+
+    #[tokio::test]
+    async fn json_extractor_success() {
+        let (status, body) = call_json("application/json", r#"{"name":"alice","age":30}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"name":"alice","age":30}"#);
+    }
+
+    #[tokio::test]
+    async fn json_extractor_rejections() {
+        let (status, body) = call_json("application/json", r#"{"name":"alice","#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            r#"{"code":400,"message":"Invalid JSON: EOF while parsing a value at line 1 column 16"}"#
+        );
+
+        let (status, body) = call_json("application/json", r#"{"name":"alice","age":"30"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body,
+            r#"{"code":422,"message":"Invalid JSON body: age: invalid type: string \"30\", expected u8 at line 1 column 26"}"#
+        );
+
+        let (status, body) = call_json("text/plain", r#"{"name":"alice","age":30}"#).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(body.contains("Content-Type: application/json"), "body was: {body}");
+
+        let body = r#"{"name":"a very long name exceeding the body limit of the router","age":30}"#;
+        let (status, _) = call_json("application/json", body).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    // ---------------- Negotiated ----------------
+
+    async fn call_negotiated(content_type: &str, accept: &str, body: Vec<u8>) -> Response {
+        let app: Router = Router::new().route(
+            "/",
+            axum::routing::post(|format: BodyFormat, Negotiated(user): Negotiated<UserDto>| async move {
+                crate::server::axum::response::NegotiatedResponse::new(format, user)
+            }),
+        );
+
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header(axum::http::header::CONTENT_TYPE, content_type)
+                .header(axum::http::header::ACCEPT, accept)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn negotiated_extractor_json() {
+        let response = call_negotiated(
+            "application/json",
+            "text/html",
+            br#"{"name":"alice","age":30}"#.to_vec(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(read_body(response).await, r#"{"name":"alice","age":30}"#);
+
+        let response = call_negotiated("text/plain", "*/*", b"alice".to_vec()).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[cfg(all(feature = "msgpack", feature = "cbor"))]
+    #[tokio::test]
+    async fn negotiated_extractor_binary_formats() {
+        let user = UserDto {
+            name: "alice".to_string(),
+            age: 30,
+        };
+        let body = BodyFormat::MessagePack.serialize(&user).unwrap();
+        let response = call_negotiated("application/msgpack", "application/cbor", body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "application/cbor");
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        let decoded: UserDto = BodyFormat::Cbor.deserialize(&body).unwrap();
+        assert_eq!((decoded.name.as_str(), decoded.age), ("alice", 30));
+
+        let response = call_negotiated("application/cbor", "*/*", vec![0xff, 0x00]).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // ---------------- Dep ----------------
+
+    #[tokio::test]
+    async fn dep_extractor_resolves_registered_services() {
+        let injector = Injector::new()
+            .singleton("api".to_string())
+            .per_request(|| AtomicUsize::new(0));
+        let app: Router = Router::new()
+            .route(
+                "/",
+                get(
+                    |Dep(name): Dep<String>, Dep(a): Dep<AtomicUsize>, Dep(b): Dep<AtomicUsize>| async move {
+                        a.fetch_add(1, Ordering::SeqCst);
+                        b.fetch_add(1, Ordering::SeqCst);
+                        format!("{name}-{}", a.load(Ordering::SeqCst))
+                    },
+                ),
+            )
+            .layer(InjectorLayer::new(injector));
+
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            // Both `Dep<AtomicUsize>` share the same per-request instance
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(read_body(response).await, "api-2");
+        }
+    }
+
+    #[tokio::test]
+    async fn dep_extractor_returns_500_when_service_is_missing() {
+        let app: Router = Router::new()
+            .route("/", get(|Dep(name): Dep<String>| async move { name.to_string() }))
+            .layer(InjectorLayer::new(Injector::new()));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = read_body(response).await;
+        assert!(body.contains("Dependency not registered"), "body was: {body}");
+    }
+
+    #[tokio::test]
+    async fn dep_extractor_returns_500_without_layer() {
+        let app: Router = Router::new().route("/", get(|Dep(name): Dep<String>| async move { name.to_string() }));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = read_body(response).await;
+        assert!(body.contains("Injector layer is missing"), "body was: {body}");
+    }
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> String {
+            "Hello".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn dep_extractor_resolves_trait_objects() {
+        let injector = Injector::new().singleton_arc::<dyn Greeter>(Arc::new(English));
+        let app: Router = Router::new()
+            .route(
+                "/",
+                get(|Dep(greeter): Dep<dyn Greeter>| async move { greeter.greet() }),
+            )
+            .layer(InjectorLayer::new(injector));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(response).await, "Hello");
+    }
+
+    // ---------------- Inject ----------------
+
+    #[tokio::test]
+    async fn inject_extractor_resolves_registered_services() {
+        let registry = ServiceRegistry::new().register::<dyn Greeter>(Arc::new(English));
+        let app: Router = Router::new()
+            .route(
+                "/",
+                get(|Inject(greeter): Inject<dyn Greeter>| async move { greeter.greet() }),
+            )
+            .route(
+                "/missing",
+                get(|Inject(name): Inject<String>| async move { name.to_string() }),
+            )
+            .layer(registry.layer());
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(response).await, "Hello");
+
+        let response = app
+            .oneshot(Request::builder().uri("/missing").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = read_body(response).await;
+        assert!(body.contains("Dependency not registered"), "body was: {body}");
+    }
+
+    // ---------------- State ----------------
+
+    #[tokio::test]
+    async fn state_extractor_gets_registered_entries() {
+        let app = AppState::new()
+            .with("api".to_string())
+            .into_router(
+                Router::new()
+                    .route("/", get(|State(name): State<String>| async move { name.to_string() }))
+                    .route(
+                        "/missing",
+                        get(|State(count): State<AtomicUsize>| async move { count.load(Ordering::SeqCst).to_string() }),
+                    ),
+            )
+            .unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(response).await, "api");
+
+        let response = app
+            .oneshot(Request::builder().uri("/missing").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = read_body(response).await;
+        assert!(body.contains("Dependency not registered"), "body was: {body}");
+    }
+
+    // ---------------- Ctx ----------------
+
+    #[tokio::test]
+    async fn ctx_extractor_returns_context_with_route_and_principal() {
+        use crate::server::axum::layers::context::ContextLayer;
+
+        let app: Router = Router::new()
+            .route(
+                "/users/{id}",
+                get(|Ctx(ctx): Ctx| async move { format!("{:?}-{:?}-{:?}", ctx.request_id, ctx.route, ctx.principal) }),
+            )
+            // Authentication layer running after the `ContextLayer`
+            .layer(axum::middleware::from_fn(
+                |mut request: Request<Body>, next: axum::middleware::Next| async move {
+                    RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal("bob".to_string()));
+                    next.run(request).await
+                },
+            ))
+            .layer(ContextLayer::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/1")
+                    .header("x-request-id", "abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(response).await,
+            r#"Some("abc")-Some("/users/{id}")-Some("bob")"#
+        );
+    }
+
+    #[tokio::test]
+    async fn ctx_extractor_returns_500_without_layer() {
+        let app: Router = Router::new().route("/", get(|Ctx(ctx): Ctx| async move { format!("{:?}", ctx.route) }));
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(read_body(response).await.contains("ContextLayer is missing"));
+    }
+
+    // ---------------- QueryParams ----------------
+
+    #[test]
+    fn query_params_aggregate_errors() {
+        let mut params = QueryParams::parse("page=0&limit=abc&name=bob").unwrap();
+
+        let page = params.required::<u32>("page", None).unwrap();
+        params.check_min("page", &page, &1);
+        assert!(params.required::<u32>("limit", None).is_none());
+        assert!(params.required::<String>("sort", None).is_none());
+        assert_eq!(params.required::<u32>("size", Some("20")), Some(20));
+        assert_eq!(params.optional::<String>("name"), Some("bob".to_string()));
+        assert_eq!(params.optional::<String>("missing"), None);
+
+        let errors = params.finish().unwrap_err();
+        assert_eq!(
+            errors.0,
+            vec![
+                FieldError::new("page", "must be at least 1"),
+                FieldError::new("limit", "has an invalid format"),
+                FieldError::new("sort", "is required"),
+            ]
+        );
+    }
+
+    #[test]
+    fn query_params_check_max() {
+        let mut params = QueryParams::default();
+        params.check_max("limit", &100, &100);
+        assert!(params.errors.is_empty());
+
+        params.check_max("limit", &101, &100);
+        assert_eq!(
+            params.finish().unwrap_err().0,
+            vec![FieldError::new("limit", "must be at most 100")]
+        );
+    }
+
+    // ---------------- Dto ----------------
+
+    #[derive(Debug)]
+    struct Product {
+        name: String,
+        price: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct ProductDto {
+        name: String,
+        price: i64,
+    }
+
+    impl FromDto for Product {
+        type Dto = ProductDto;
+
+        fn from_dto(dto: ProductDto) -> Result<Self, ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            if dto.name.trim().is_empty() {
+                errors.add("name", "must not be empty");
+            }
+            let price = u32::try_from(dto.price).map_err(|_| errors.add("price", "must be positive"));
+
+            match price {
+                Ok(price) if errors.is_empty() => Ok(Product { name: dto.name, price }),
+                _ => Err(errors),
+            }
+        }
+    }
+
+    async fn call_dto(body: &'static str) -> (StatusCode, String) {
+        call_dto_with("application/json", body).await
+    }
+
+    async fn call_dto_with(content_type: &str, body: &'static str) -> (StatusCode, String) {
+        let app: Router = Router::new()
+            .route(
+                "/",
+                post(|Dto(product): Dto<Product>| async move { format!("{}-{}", product.name, product.price) }),
+            )
+            .layer(axum::extract::DefaultBodyLimit::max(64));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 4096).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn dto_extractor_converts_valid_body() {
+        assert_eq!(
+            call_dto(r#"{"name": "book", "price": 12}"#).await,
+            (StatusCode::OK, "book-12".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn dto_extractor_returns_422_with_conversion_errors() {
+        let (status, body) = call_dto(r#"{"name": " ", "price": -1}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let expected = ValidationErrors(vec![
+            FieldError::new("name", "must not be empty"),
+            FieldError::new("price", "must be positive"),
+        ]);
+        assert_eq!(
+            body,
+            serde_json::json!({ "code": 422, "message": expected.0 }).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn dto_extractor_returns_422_on_invalid_data() {
+        let (status, body) = call_dto(r#"{"name": "book"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.contains("\"field\":\"body\""), "body was: {body}");
+    }
+
+    #[tokio::test]
+    async fn dto_extractor_returns_400_on_invalid_json() {
+        let (status, body) = call_dto("{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("\"code\":400"), "body was: {body}");
+    }
+
+    #[tokio::test]
+    async fn dto_extractor_returns_the_json_extractor_rejections() {
+        let (status, body) = call_dto_with("text/plain", r#"{"name": "book", "price": 12}"#).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(body.contains("Content-Type: application/json"), "body was: {body}");
+
+        let body = r#"{"name": "a very long name exceeding the body limit", "price": 12}"#;
+        let (status, _) = call_dto_with("application/json", body).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn dto_try_into_domain() {
+        let product: Product = ProductDto {
+            name: "book".to_string(),
+            price: 12,
+        }
+        .try_into_domain()
+        .unwrap();
+        assert_eq!((product.name.as_str(), product.price), ("book", 12));
+
+        let result: Result<Product, _> = ProductDto {
+            name: "book".to_string(),
+            price: -1,
+        }
+        .try_into_domain();
+        assert!(result.is_err());
+    }
+
+    // ---------------- ApiQuery ----------------
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, ApiQuery)]
+    struct ApiQuerySearch {
+        #[api_query(regex = "^[a-z]+$")]
+        name: String,
+        #[api_query(min = 1, default = 1)]
+        page: u32,
+        #[api_query(min = 10, max = 100)]
+        limit: Option<u32>,
+        r#type: Option<String>,
+    }
+
+    #[cfg(feature = "derive")]
+    async fn api_query_handler(search: ApiQuerySearch) -> String {
+        format!("{}-{}-{:?}-{:?}", search.name, search.page, search.limit, search.r#type)
+    }
+
+    #[cfg(feature = "derive")]
+    async fn call_api_query(uri: &str) -> (StatusCode, String) {
+        let app: Router = Router::new().route("/", get(api_query_handler));
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 4096).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[cfg(feature = "derive")]
+    #[tokio::test]
+    async fn derive_api_query_parses_valid_query() {
+        assert_eq!(
+            call_api_query("/?name=bob").await,
+            (StatusCode::OK, "bob-1-None-None".to_string())
+        );
+        assert_eq!(
+            call_api_query("/?name=bob&page=3&limit=50&type=admin").await,
+            (StatusCode::OK, "bob-3-Some(50)-Some(\"admin\")".to_string())
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[tokio::test]
+    async fn derive_api_query_aggregates_errors_in_422() {
+        let (status, body) = call_api_query("/?name=Bob1&page=0&limit=500").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 422,
+                "message": [
+                    { "field": "name", "message": "must match ^[a-z]+$" },
+                    { "field": "page", "message": "must be at least 1" },
+                    { "field": "limit", "message": "must be at most 100" },
+                ]
+            })
+            .to_string()
+        );
+
+        let (status, body) = call_api_query("/?limit=x").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.contains("is required"), "body was: {body}");
+        assert!(body.contains("has an invalid format"), "body was: {body}");
+    }
+
+    // ---------------- QueryQs ----------------
+
+    #[cfg(feature = "query-qs")]
+    #[derive(Debug, Deserialize)]
+    struct Filter {
+        status: String,
+    }
+
+    #[cfg(feature = "query-qs")]
+    #[derive(Debug, Deserialize)]
+    struct QsSearch {
+        ids: Vec<u32>,
+        filter: Option<Filter>,
+    }
+
+    #[cfg(feature = "query-qs")]
+    async fn call_query_qs(config: QueryQsConfig, uri: &str) -> (StatusCode, String) {
+        let app: Router = Router::new()
+            .route(
+                "/",
+                get(|QueryQs(search): QueryQs<QsSearch>| async move {
+                    format!("{:?}-{:?}", search.ids, search.filter.map(|filter| filter.status))
+                }),
+            )
+            .layer(Extension(config));
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 4096).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[cfg(feature = "query-qs")]
+    #[tokio::test]
+    async fn query_qs_extractor_supports_sequences_and_nested_structs() {
+        let config = QueryQsConfig::new();
+        assert_eq!(
+            call_query_qs(config, "/?ids=1&ids=2").await,
+            (StatusCode::OK, "[1, 2]-None".to_string())
+        );
+        assert_eq!(
+            call_query_qs(config, "/?ids[]=1&ids[]=2&filter[status]=active").await,
+            (StatusCode::OK, r#"[1, 2]-Some("active")"#.to_string())
+        );
+        assert_eq!(
+            call_query_qs(config, "/?ids[0]=3&unknown=1").await,
+            (StatusCode::OK, "[3]-None".to_string())
+        );
+
+        let (status, body) = call_query_qs(config, "/?ids=abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains(r#""code":400"#), "body was: {body}");
+    }
+
+    #[cfg(feature = "query-qs")]
+    #[tokio::test]
+    async fn query_qs_extractor_strict_mode_rejects_unknown_parameters() {
+        let config = QueryQsConfig::new().with_strict(true);
+        assert_eq!(call_query_qs(config, "/?ids=1").await.0, StatusCode::OK);
+
+        let (status, body) = call_query_qs(config, "/?ids=1&unknown=1&filter[status]=active&filter[name]=a").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            r#"{"code":400,"message":"Unknown query parameters: filter[name], unknown"}"#
+        );
+    }
 }
@@ -0,0 +1,598 @@
+//! Feature flags
+//!
+//! [`FeatureFlags`] is a store of static flags with runtime overrides published in a
+//! `tokio::sync::watch` channel (e.g. with `ConfigWatcher::map`). A flag can be enabled for a
+//! percentage of the users or tenants only: the rollout bucket is computed from the flag name and
+//! a stable ID, so that a user keeps the same result between requests and between instances.
+//!
+//! The [`FeatureFlagLayer`] inserts the store in the requests for the [`Flags`] and [`Flag`]
+//! extractors and can gate entire routes (`404 Not Found` or `503 Service Unavailable` when the
+//! flag is disabled). The stable ID is the `Principal` (or the `Tenant`) of the `RequestStore`,
+//! so the gates must run after the authentication layer.
+//!
+//! ```rust
+//! use api_tools::server::axum::feature_flags::{FeatureFlagLayer, FeatureFlags, FlagRule, Flags};
+//! use axum::{Router, routing::get};
+//! use std::collections::{BTreeMap, HashMap};
+//! use tokio::sync::watch;
+//!
+//! let (sender, overrides) = watch::channel(HashMap::new());
+//! let flags = FeatureFlags::new()
+//!     .with_flag("beta", false)
+//!     .with_rollout("new_checkout", 20)
+//!     .with_overrides(overrides);
+//!
+//! let app: Router = Router::new()
+//!     .route("/checkout", get(|flags: Flags| async move {
+//!         if flags.is_enabled("new_checkout") { "new" } else { "old" }
+//!     }))
+//!     .route("/beta/{*path}", get(|| async { "beta" }))
+//!     .layer(FeatureFlagLayer::new(flags).with_route("/beta/{*path}", "beta"));
+//!
+//! // Later, enable the beta routes without restarting
+//! sender.send_modify(|overrides| {
+//!     overrides.insert("beta".to_string(), FlagRule::enabled());
+//! });
+//! ```
+
+use crate::server::axum::layers::{body_from_parts, route_matches};
+use crate::server::axum::request_store::{Principal, RequestStore, Tenant};
+use crate::server::axum::response::ApiError;
+use axum::body::Body;
+use axum::extract::{FromRequestParts, MatchedPath};
+use axum::http::request::Parts;
+use axum::http::{Extensions, Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// Feature flags possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum FeatureFlagError {
+    #[error("Feature flag layer is missing")]
+    MissingLayer,
+}
+
+impl From<FeatureFlagError> for ApiError {
+    fn from(value: FeatureFlagError) -> Self {
+        Self::InternalServerError(value.to_string())
+    }
+}
+
+/// Rule of a flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagRule {
+    /// Flag enabled
+    pub enabled: bool,
+
+    /// Percentage (0 to 100) of the stable IDs for which the flag is enabled
+    ///
+    /// `None` enables the flag for every request. A partial rollout is disabled for the requests
+    /// without stable ID.
+    #[serde(default)]
+    pub rollout: Option<u8>,
+}
+
+impl FlagRule {
+    /// Flag enabled for every request
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            rollout: None,
+        }
+    }
+
+    /// Flag disabled
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            rollout: None,
+        }
+    }
+
+    /// Flag enabled for a percentage of the stable IDs (capped at 100)
+    pub fn rollout(percentage: u8) -> Self {
+        Self {
+            enabled: true,
+            rollout: Some(percentage.min(100)),
+        }
+    }
+
+    /// Check if the flag is enabled for a stable ID
+    fn is_enabled(&self, name: &str, stable_id: Option<&str>) -> bool {
+        match (self.enabled, self.rollout) {
+            (false, _) => false,
+            (true, None) => true,
+            (true, Some(percentage)) if percentage >= 100 => true,
+            (true, Some(percentage)) => stable_id.is_some_and(|id| rollout_bucket(name, id) < percentage),
+        }
+    }
+}
+
+/// Bucket (0 to 99) of a stable ID for a flag
+///
+/// FNV-1a is used instead of the `std` hasher, whose output may change between Rust versions.
+pub(crate) fn rollout_bucket(name: &str, stable_id: &str) -> u8 {
+    let hash = name
+        .bytes()
+        .chain([b':'])
+        .chain(stable_id.bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    (hash % 100) as u8
+}
+
+/// Feature flags store, cheap to clone
+///
+/// The runtime overrides take precedence over the static flags. Unknown flags are disabled.
+#[derive(Debug, Clone)]
+pub struct FeatureFlags {
+    flags: Arc<HashMap<String, FlagRule>>,
+    overrides: watch::Receiver<HashMap<String, FlagRule>>,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        let (_sender, overrides) = watch::channel(HashMap::new());
+
+        Self {
+            flags: Arc::new(HashMap::new()),
+            overrides,
+        }
+    }
+}
+
+impl FeatureFlags {
+    /// Create a new store without flags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a static flag
+    pub fn with_flag(self, name: &str, enabled: bool) -> Self {
+        let rule = if enabled {
+            FlagRule::enabled()
+        } else {
+            FlagRule::disabled()
+        };
+
+        self.with_rule(name, rule)
+    }
+
+    /// Add a static flag enabled for a percentage of the stable IDs
+    pub fn with_rollout(self, name: &str, percentage: u8) -> Self {
+        self.with_rule(name, FlagRule::rollout(percentage))
+    }
+
+    /// Add a static flag rule
+    pub fn with_rule(mut self, name: &str, rule: FlagRule) -> Self {
+        Arc::make_mut(&mut self.flags).insert(name.to_string(), rule);
+        self
+    }
+
+    /// Set the channel of the runtime overrides
+    pub fn with_overrides(mut self, overrides: watch::Receiver<HashMap<String, FlagRule>>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Current rule of a flag
+    pub fn rule(&self, name: &str) -> Option<FlagRule> {
+        self.overrides
+            .borrow()
+            .get(name)
+            .or_else(|| self.flags.get(name))
+            .copied()
+    }
+
+    /// Current rules of all the flags (static flags and runtime overrides), sorted by name
+    pub fn rules(&self) -> BTreeMap<String, FlagRule> {
+        let mut rules = self
+            .flags
+            .iter()
+            .map(|(name, rule)| (name.clone(), *rule))
+            .collect::<BTreeMap<_, _>>();
+        rules.extend(self.overrides.borrow().iter().map(|(name, rule)| (name.clone(), *rule)));
+
+        rules
+    }
+
+    /// Check if a flag is enabled for a stable ID (user or tenant)
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::feature_flags::FeatureFlags;
+    ///
+    /// let flags = FeatureFlags::new().with_flag("beta", true).with_rollout("new_ui", 0);
+    ///
+    /// assert!(flags.is_enabled("beta", None));
+    /// assert!(!flags.is_enabled("new_ui", Some("user-1")));
+    /// assert!(!flags.is_enabled("unknown", None));
+    /// ```
+    pub fn is_enabled(&self, name: &str, stable_id: Option<&str>) -> bool {
+        self.rule(name).is_some_and(|rule| rule.is_enabled(name, stable_id))
+    }
+}
+
+/// Source of the stable ID of the percentage rollouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RolloutKey {
+    /// `Principal` of the `RequestStore`
+    #[default]
+    Principal,
+
+    /// `Tenant` of the `RequestStore`
+    Tenant,
+}
+
+impl RolloutKey {
+    /// Stable ID of a request
+    fn stable_id(&self, extensions: &Extensions) -> Option<String> {
+        let store = RequestStore::from_extensions(extensions)?;
+
+        match self {
+            Self::Principal => store.get::<Principal>().map(|principal| principal.0.clone()),
+            Self::Tenant => store.get::<Tenant>().map(|tenant| tenant.0.clone()),
+        }
+    }
+}
+
+/// Flags of a request, inserted by the `FeatureFlagLayer`
+#[derive(Debug, Clone)]
+struct RequestFlags {
+    flags: FeatureFlags,
+    rollout_key: RolloutKey,
+}
+
+/// `Flags` extractor checks the flags for the stable ID of the request
+///
+/// The stable ID is read when the extractor runs, so that it is set even if the authentication
+/// layer runs after the `FeatureFlagLayer`.
+pub struct Flags {
+    flags: FeatureFlags,
+    stable_id: Option<String>,
+}
+
+impl Flags {
+    /// Check if a flag is enabled for the request
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.flags.is_enabled(name, self.stable_id.as_deref())
+    }
+
+    /// Stable ID of the request used by the percentage rollouts
+    pub fn stable_id(&self) -> Option<&str> {
+        self.stable_id.as_deref()
+    }
+}
+
+impl<S> FromRequestParts<S> for Flags
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let request_flags = parts
+            .extensions
+            .get::<RequestFlags>()
+            .ok_or(FeatureFlagError::MissingLayer)?;
+
+        Ok(Self {
+            flags: request_flags.flags.clone(),
+            stable_id: request_flags.rollout_key.stable_id(&parts.extensions),
+        })
+    }
+}
+
+/// Name of a flag checked by the [`Flag`] extractor
+pub trait FlagName {
+    const NAME: &'static str;
+}
+
+/// `Flag` extractor checks a single flag, named by a [`FlagName`] type
+///
+/// # Example
+///
+/// ```rust
+/// use api_tools::server::axum::feature_flags::{Flag, FlagName};
+///
+/// struct NewCheckout;
+///
+/// impl FlagName for NewCheckout {
+///     const NAME: &'static str = "new_checkout";
+/// }
+///
+/// async fn checkout(flag: Flag<NewCheckout>) -> &'static str {
+///     if flag.is_enabled() { "new" } else { "old" }
+/// }
+/// ```
+pub struct Flag<F: FlagName> {
+    enabled: bool,
+    flag: PhantomData<F>,
+}
+
+impl<F: FlagName> Flag<F> {
+    /// Check if the flag is enabled for the request
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl<S, F> FromRequestParts<S> for Flag<F>
+where
+    F: FlagName,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let flags = Flags::from_request_parts(parts, state).await?;
+
+        Ok(Self {
+            enabled: flags.is_enabled(F::NAME),
+            flag: PhantomData,
+        })
+    }
+}
+
+/// Flag required by a route pattern
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteFlag {
+    pub pattern: String,
+    pub flag: String,
+}
+
+/// Feature flags layer
+///
+/// The disabled routes return `404 Not Found` by default, as if they did not exist.
+#[derive(Clone)]
+pub struct FeatureFlagLayer {
+    pub flags: FeatureFlags,
+    pub routes: Vec<RouteFlag>,
+    pub rollout_key: RolloutKey,
+    pub disabled_status: StatusCode,
+}
+
+impl FeatureFlagLayer {
+    /// Create a new `FeatureFlagLayer`
+    pub fn new(flags: FeatureFlags) -> Self {
+        Self {
+            flags,
+            routes: Vec::new(),
+            rollout_key: RolloutKey::default(),
+            disabled_status: StatusCode::NOT_FOUND,
+        }
+    }
+
+    /// Gate a route pattern (`*` for every route) with a flag
+    pub fn with_route(mut self, pattern: &str, flag: &str) -> Self {
+        self.routes.push(RouteFlag {
+            pattern: pattern.to_string(),
+            flag: flag.to_string(),
+        });
+        self
+    }
+
+    /// Set the source of the stable ID of the percentage rollouts
+    pub fn with_rollout_key(mut self, rollout_key: RolloutKey) -> Self {
+        self.rollout_key = rollout_key;
+        self
+    }
+
+    /// Return `503 Service Unavailable` instead of `404 Not Found` for the disabled routes
+    pub fn with_unavailable_status(mut self) -> Self {
+        self.disabled_status = StatusCode::SERVICE_UNAVAILABLE;
+        self
+    }
+
+    /// First disabled flag of a request path (or a matched route)
+    fn disabled_flag(&self, path: &str, extensions: &Extensions) -> Option<&str> {
+        let mut routes = self
+            .routes
+            .iter()
+            .filter(|route| route_matches(&route.pattern, path))
+            .peekable();
+        routes.peek()?;
+
+        let stable_id = self.rollout_key.stable_id(extensions);
+        routes
+            .find(|route| !self.flags.is_enabled(&route.flag, stable_id.as_deref()))
+            .map(|route| route.flag.as_str())
+    }
+}
+
+impl<S> Layer<S> for FeatureFlagLayer {
+    type Service = FeatureFlagMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FeatureFlagMiddleware {
+            inner,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FeatureFlagMiddleware<S> {
+    inner: S,
+    layer: Arc<FeatureFlagLayer>,
+}
+
+impl<S> Service<Request<Body>> for FeatureFlagMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let path = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str(),
+            None => request.uri().path(),
+        };
+
+        if let Some(flag) = self.layer.disabled_flag(path, request.extensions()) {
+            let status = self.layer.disabled_status;
+            let message = match status {
+                StatusCode::NOT_FOUND => "Not Found".to_string(),
+                _ => format!("Feature '{flag}' is disabled"),
+            };
+
+            return Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let msg = body_from_parts(&mut parts, status, &message, None);
+
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            });
+        }
+
+        request.extensions_mut().insert(RequestFlags {
+            flags: self.layer.flags.clone(),
+            rollout_key: self.layer.rollout_key,
+        });
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    struct Beta;
+
+    impl FlagName for Beta {
+        const NAME: &'static str = "beta";
+    }
+
+    async fn call(app: &Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn test_feature_flags_rollout() {
+        let flags = FeatureFlags::new()
+            .with_rollout("half", 50)
+            .with_rollout("all", 150)
+            .with_rule(
+                "off",
+                FlagRule {
+                    enabled: false,
+                    rollout: Some(100),
+                },
+            );
+
+        let enabled = (0..1_000)
+            .filter(|id| flags.is_enabled("half", Some(&id.to_string())))
+            .count();
+        assert!((400..600).contains(&enabled), "{enabled} users of 1000");
+        assert_eq!(
+            flags.is_enabled("half", Some("user-1")),
+            flags.is_enabled("half", Some("user-1"))
+        );
+        assert!(!flags.is_enabled("half", None));
+        assert!(flags.is_enabled("all", None));
+        assert!(!flags.is_enabled("off", Some("user-1")));
+        assert_eq!(rollout_bucket("half", "user-1"), 75);
+    }
+
+    #[test]
+    fn test_feature_flags_overrides() {
+        let (sender, overrides) = watch::channel(HashMap::new());
+        let flags = FeatureFlags::new().with_flag("beta", false).with_overrides(overrides);
+        assert!(!flags.is_enabled("beta", None));
+
+        sender.send_modify(|overrides| {
+            overrides.insert("beta".to_string(), FlagRule::enabled());
+            overrides.insert("new".to_string(), FlagRule::enabled());
+        });
+        assert!(flags.is_enabled("beta", None));
+        assert!(flags.clone().is_enabled("new", None));
+
+        let rule: FlagRule = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+        assert_eq!(rule, FlagRule::enabled());
+    }
+
+    #[tokio::test]
+    async fn test_feature_flag_layer() {
+        let (sender, overrides) = watch::channel(HashMap::new());
+        let flags = FeatureFlags::new()
+            .with_flag("beta", false)
+            .with_flag("maintenance", true)
+            .with_overrides(overrides);
+        let app = Router::new()
+            .route(
+                "/flags",
+                get(|flags: Flags, beta: Flag<Beta>| async move {
+                    format!("{} {}", flags.is_enabled("maintenance"), beta.is_enabled())
+                }),
+            )
+            .route("/beta/{id}", get(|| async { "beta" }))
+            .layer(FeatureFlagLayer::new(flags.clone()).with_route("/beta/{*path}", "beta"));
+
+        assert_eq!(call(&app, "/flags").await, (StatusCode::OK, "true false".to_string()));
+        assert_eq!(
+            call(&app, "/beta/1").await,
+            (
+                StatusCode::NOT_FOUND,
+                r#"{"code":404,"message":"Not Found"}"#.to_string()
+            )
+        );
+
+        sender.send_modify(|overrides| {
+            overrides.insert("beta".to_string(), FlagRule::enabled());
+        });
+        assert_eq!(call(&app, "/flags").await, (StatusCode::OK, "true true".to_string()));
+        assert_eq!(call(&app, "/beta/1").await, (StatusCode::OK, "beta".to_string()));
+
+        let app = Router::new().route("/beta", get(|| async { "beta" })).route_layer(
+            FeatureFlagLayer::new(flags)
+                .with_route("*", "unknown")
+                .with_unavailable_status(),
+        );
+        assert_eq!(
+            call(&app, "/beta").await,
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                r#"{"code":503,"message":"Feature 'unknown' is disabled"}"#.to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flags_extractor_without_layer() {
+        let app = Router::new().route("/", get(|_flags: Flags| async { "ok" }));
+
+        assert_eq!(call(&app, "/").await.0, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,227 @@
+//! gRPC status mapping (`grpc` feature)
+//!
+//! Conversions between [`ApiError`] and [`tonic::Status`], so that services exposing both REST and
+//! gRPC APIs keep the same error semantics. The mapping follows the usual HTTP/gRPC equivalences
+//! (see [`grpc_code`] and [`http_status`]):
+//!
+//! | `ApiError`            | gRPC code             |
+//! |-----------------------|-----------------------|
+//! | `BadRequest`          | `INVALID_ARGUMENT`    |
+//! | `Unauthorized`        | `UNAUTHENTICATED`     |
+//! | `Forbidden`           | `PERMISSION_DENIED`   |
+//! | `NotFound`            | `NOT_FOUND`           |
+//! | `UnprocessableEntity` | `FAILED_PRECONDITION` |
+//! | `InternalServerError` | `INTERNAL`            |
+//! | `Timeout`             | `DEADLINE_EXCEEDED`   |
+//! | `TooManyRequests`     | `RESOURCE_EXHAUSTED`  |
+//! | `MethodNotAllowed`    | `UNIMPLEMENTED`       |
+//! | `PayloadTooLarge`     | `OUT_OF_RANGE`        |
+//! | `ServiceUnavailable`  | `UNAVAILABLE`         |
+//!
+//! The request ID and the trace ID of the current request are added to the metadata of the
+//! statuses (`x-request-id` and `x-trace-id` keys), and can be read back from the statuses
+//! received from other services with [`status_request_id`] and [`status_trace_id`].
+//!
+//! ```rust
+//! use api_tools::server::axum::response::ApiError;
+//! use tonic::{Code, Status};
+//!
+//! let status = Status::from(ApiError::NotFound("User not found".to_string()));
+//! assert_eq!(status.code(), Code::NotFound);
+//! assert_eq!(status.message(), "User not found");
+//!
+//! let error = ApiError::from(Status::unavailable("Database unavailable"));
+//! assert_eq!(error, ApiError::ServiceUnavailable);
+//! ```
+
+use crate::server::axum::layers::request_id::current_request_id;
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::{ApiError, current_trace_id};
+use axum::http::StatusCode;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+
+/// Metadata key of the request ID
+pub const GRPC_REQUEST_ID_KEY: &str = "x-request-id";
+
+/// Metadata key of the trace ID
+pub const GRPC_TRACE_ID_KEY: &str = "x-trace-id";
+
+/// gRPC code equivalent to an HTTP status
+///
+/// # Example
+/// ```rust
+/// use api_tools::server::axum::grpc::grpc_code;
+/// use axum::http::StatusCode;
+/// use tonic::Code;
+///
+/// assert_eq!(grpc_code(StatusCode::CONFLICT), Code::Aborted);
+/// assert_eq!(grpc_code(StatusCode::IM_A_TEAPOT), Code::FailedPrecondition);
+/// assert_eq!(grpc_code(StatusCode::BAD_GATEWAY), Code::Unavailable);
+/// ```
+pub fn grpc_code(status: StatusCode) -> Code {
+    match status.as_u16() {
+        200..=299 => Code::Ok,
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        405 | 501 => Code::Unimplemented,
+        408 | 504 => Code::DeadlineExceeded,
+        409 => Code::Aborted,
+        413 | 416 => Code::OutOfRange,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        502 | 503 => Code::Unavailable,
+        _ if status.is_client_error() => Code::FailedPrecondition,
+        _ if status.is_server_error() => Code::Internal,
+        _ => Code::Unknown,
+    }
+}
+
+/// HTTP status equivalent to a gRPC code
+///
+/// # Example
+/// ```rust
+/// use api_tools::server::axum::grpc::http_status;
+/// use axum::http::StatusCode;
+/// use tonic::Code;
+///
+/// assert_eq!(http_status(Code::AlreadyExists), StatusCode::CONFLICT);
+/// assert_eq!(http_status(Code::DataLoss), StatusCode::INTERNAL_SERVER_ERROR);
+/// ```
+pub fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::FailedPrecondition => StatusCode::UNPROCESSABLE_ENTITY,
+        Code::OutOfRange => StatusCode::PAYLOAD_TOO_LARGE,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::REQUEST_TIMEOUT,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Request ID of a status metadata
+pub fn status_request_id(status: &Status) -> Option<&str> {
+    metadata_value(status.metadata(), GRPC_REQUEST_ID_KEY)
+}
+
+/// Trace ID of a status metadata
+pub fn status_trace_id(status: &Status) -> Option<&str> {
+    metadata_value(status.metadata(), GRPC_TRACE_ID_KEY)
+}
+
+/// ASCII value of a metadata key
+fn metadata_value<'a>(metadata: &'a MetadataMap, key: &str) -> Option<&'a str> {
+    metadata.get(key).and_then(|value| value.to_str().ok())
+}
+
+impl From<ApiError> for Status {
+    /// Convert an error into a status, with the request ID and the trace ID of the current request
+    /// in its metadata
+    fn from(error: ApiError) -> Self {
+        let code = grpc_code(error.status_code());
+        let message = redaction_policy().redact_str(error.message());
+
+        let mut metadata = MetadataMap::new();
+        let ids = [
+            (GRPC_REQUEST_ID_KEY, current_request_id()),
+            (GRPC_TRACE_ID_KEY, current_trace_id()),
+        ];
+        for (key, id) in ids {
+            if let Some(value) = id.and_then(|id| MetadataValue::try_from(id).ok()) {
+                metadata.insert(key, value);
+            }
+        }
+
+        Status::with_metadata(code, message, metadata)
+    }
+}
+
+impl From<Status> for ApiError {
+    /// Convert a status into an error (the codes without equivalent error are internal errors)
+    fn from(status: Status) -> Self {
+        let message = status.message().to_string();
+        match status.code() {
+            Code::InvalidArgument => ApiError::BadRequest(message),
+            Code::Unauthenticated => ApiError::Unauthorized(message),
+            Code::PermissionDenied => ApiError::Forbidden(message),
+            Code::NotFound => ApiError::NotFound(message),
+            Code::AlreadyExists | Code::Aborted | Code::FailedPrecondition => ApiError::UnprocessableEntity(message),
+            Code::Cancelled | Code::DeadlineExceeded => ApiError::Timeout,
+            Code::ResourceExhausted => ApiError::TooManyRequests,
+            Code::Unimplemented => ApiError::MethodNotAllowed,
+            Code::OutOfRange => ApiError::PayloadTooLarge,
+            Code::Unavailable => ApiError::ServiceUnavailable,
+            Code::Ok | Code::Unknown | Code::Internal | Code::DataLoss => ApiError::InternalServerError(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::layers::request_id::scope_request_id;
+
+    #[test]
+    fn test_api_error_round_trip() {
+        let errors = [
+            ApiError::BadRequest("Invalid name".to_string()),
+            ApiError::Unauthorized("Invalid token".to_string()),
+            ApiError::Forbidden("Access denied".to_string()),
+            ApiError::NotFound("User not found".to_string()),
+            ApiError::UnprocessableEntity("Invalid state".to_string()),
+            ApiError::InternalServerError("Database error".to_string()),
+            ApiError::Timeout,
+            ApiError::TooManyRequests,
+            ApiError::MethodNotAllowed,
+            ApiError::PayloadTooLarge,
+            ApiError::ServiceUnavailable,
+        ];
+
+        for error in errors {
+            let status = Status::from(error.clone());
+            assert_eq!(status.message(), error.message());
+            assert_eq!(ApiError::from(status), error);
+        }
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(
+            Status::from(ApiError::UnprocessableEntity(String::new())).code(),
+            Code::FailedPrecondition
+        );
+        assert_eq!(Status::from(ApiError::Timeout).code(), Code::DeadlineExceeded);
+        assert_eq!(Status::from(ApiError::PayloadTooLarge).code(), Code::OutOfRange);
+        assert_eq!(
+            ApiError::from(Status::already_exists("User already exists")),
+            ApiError::UnprocessableEntity("User already exists".to_string())
+        );
+        assert_eq!(
+            ApiError::from(Status::data_loss("Corrupted")),
+            ApiError::InternalServerError("Corrupted".to_string())
+        );
+        assert_eq!(grpc_code(StatusCode::OK), Code::Ok);
+        assert_eq!(grpc_code(StatusCode::GONE), Code::FailedPrecondition);
+        assert_eq!(http_status(Code::DeadlineExceeded), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_status_metadata() {
+        let status = scope_request_id("abc".to_string(), async { Status::from(ApiError::TooManyRequests) }).await;
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status_request_id(&status), Some("abc"));
+        assert_eq!(status_trace_id(&status), None);
+
+        assert_eq!(status_request_id(&Status::from(ApiError::TooManyRequests)), None);
+    }
+}
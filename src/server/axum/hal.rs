@@ -0,0 +1,346 @@
+//! HAL hypermedia links
+//!
+//! [`HalResponse`] serializes a resource with its `_links` (and `_embedded` resources) following
+//! the [HAL](https://stateless.group/hal_specification.html) format, sent as
+//! `application/hal+json`:
+//!
+//! ```json
+//! {
+//!     "id": 1,
+//!     "name": "John",
+//!     "_links": {
+//!         "self": { "href": "/users/1" },
+//!         "related": { "href": "/users/1/orders" }
+//!     }
+//! }
+//! ```
+//!
+//! [`Links::paginated`] generates the `self`, `first`, `prev`, `next` and `last` links of a
+//! paginated collection from the current request URI, keeping its other query parameters:
+//!
+//! ```rust
+//! use api_tools::server::axum::hal::{HalResponse, Links};
+//! use api_tools::server::axum::response::ApiError;
+//! use api_tools::value_objects::pagination::PaginationResponse;
+//! use axum::http::Uri;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     id: u32,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Users {
+//!     total: i64,
+//! }
+//!
+//! async fn list_users(uri: Uri) -> Result<HalResponse<Users>, ApiError> {
+//!     let pagination = PaginationResponse::new(2, 10, 42);
+//!     let users = vec![User { id: 11 }, User { id: 12 }];
+//!
+//!     HalResponse::new(Users { total: pagination.total })
+//!         .with_links(Links::paginated(&uri, &pagination))
+//!         .with_embedded("users", &users)
+//!         .map_err(|err| ApiError::InternalServerError(err.to_string()))
+//! }
+//! ```
+
+use crate::server::axum::response::ApiError;
+use crate::value_objects::pagination::PaginationResponse;
+use axum::http::{HeaderValue, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// HAL media type
+pub const HAL_MEDIA_TYPE: &str = "application/hal+json";
+
+/// Link object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub href: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub templated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Link {
+    /// Create a new link
+    pub fn new(href: &str) -> Self {
+        Self {
+            href: href.to_string(),
+            templated: false,
+            title: None,
+        }
+    }
+
+    /// Link with a URI template (e.g. `/users{?page,limit}`)
+    pub fn templated(href: &str) -> Self {
+        Self {
+            templated: true,
+            ..Self::new(href)
+        }
+    }
+
+    /// Set the title
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+}
+
+/// Links of a resource, by relation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Links(BTreeMap<String, Link>);
+
+impl Links {
+    /// Create an empty list of links
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paging links (`self`, `first`, `prev`, `next` and `last`) of the current request URI
+    ///
+    /// The `page` and `limit` query parameters are replaced, the other ones are kept.
+    pub fn paginated(uri: &Uri, pagination: &PaginationResponse) -> Self {
+        let limit = pagination.limit.max(1);
+        let last = u32::try_from((pagination.total.max(0) as u64).div_ceil(u64::from(limit)))
+            .unwrap_or(u32::MAX)
+            .max(1);
+        let page = pagination.page.max(1);
+
+        let mut links = Self::new()
+            .with_self(&page_uri(uri, page, limit))
+            .with("first", &page_uri(uri, 1, limit))
+            .with("last", &page_uri(uri, last, limit));
+        if page > 1 {
+            links = links.with_prev(&page_uri(uri, page.min(last + 1) - 1, limit));
+        }
+        if page < last {
+            links = links.with_next(&page_uri(uri, page + 1, limit));
+        }
+
+        links
+    }
+
+    /// Add a link
+    pub fn with(self, rel: &str, href: &str) -> Self {
+        self.with_link(rel, Link::new(href))
+    }
+
+    /// Add a link object (e.g. a templated link)
+    pub fn with_link(mut self, rel: &str, link: Link) -> Self {
+        self.0.insert(rel.to_string(), link);
+        self
+    }
+
+    /// Add the `self` link
+    pub fn with_self(self, href: &str) -> Self {
+        self.with("self", href)
+    }
+
+    /// Add the `next` link
+    pub fn with_next(self, href: &str) -> Self {
+        self.with("next", href)
+    }
+
+    /// Add the `prev` link
+    pub fn with_prev(self, href: &str) -> Self {
+        self.with("prev", href)
+    }
+
+    /// Add the `related` link
+    pub fn with_related(self, href: &str) -> Self {
+        self.with("related", href)
+    }
+
+    /// Link of a relation
+    pub fn get(&self, rel: &str) -> Option<&Link> {
+        self.0.get(rel)
+    }
+
+    /// Return true if there is no link
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Extend with the links of another list (replacing the same relations)
+    pub fn merge(mut self, links: Links) -> Self {
+        self.0.extend(links.0);
+        self
+    }
+}
+
+/// URI of a page, with the other query parameters of the URI
+fn page_uri(uri: &Uri, page: u32, limit: u32) -> String {
+    let mut query = serde_urlencoded::from_str::<Vec<(String, String)>>(uri.query().unwrap_or_default())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(name, _)| name != "page" && name != "limit")
+        .collect::<Vec<_>>();
+    query.push(("page".to_string(), page.to_string()));
+    query.push(("limit".to_string(), limit.to_string()));
+
+    format!(
+        "{}?{}",
+        uri.path(),
+        serde_urlencoded::to_string(&query).unwrap_or_default()
+    )
+}
+
+#[derive(Serialize)]
+struct HalBody<'a, T> {
+    #[serde(flatten)]
+    resource: &'a T,
+    #[serde(rename = "_links", skip_serializing_if = "Links::is_empty")]
+    links: &'a Links,
+    #[serde(rename = "_embedded", skip_serializing_if = "BTreeMap::is_empty")]
+    embedded: &'a BTreeMap<String, Value>,
+}
+
+/// HAL resource response (`200 OK` by default)
+///
+/// The resource must be serialized as a JSON object, its fields are merged with `_links` and
+/// `_embedded`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HalResponse<T: Serialize> {
+    status: StatusCode,
+    resource: T,
+    links: Links,
+    embedded: BTreeMap<String, Value>,
+}
+
+impl<T: Serialize> HalResponse<T> {
+    /// Create a new HAL response
+    pub fn new(resource: T) -> Self {
+        Self {
+            status: StatusCode::OK,
+            resource,
+            links: Links::new(),
+            embedded: BTreeMap::new(),
+        }
+    }
+
+    /// Set the response status (e.g. `201 Created`)
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add links
+    pub fn with_links(mut self, links: Links) -> Self {
+        self.links = self.links.merge(links);
+        self
+    }
+
+    /// Add a link
+    pub fn with_link(mut self, rel: &str, href: &str) -> Self {
+        self.links = self.links.with(rel, href);
+        self
+    }
+
+    /// Add embedded resources (e.g. the items of a collection)
+    pub fn with_embedded<E: Serialize + ?Sized>(
+        mut self,
+        name: &str,
+        resources: &E,
+    ) -> Result<Self, serde_json::Error> {
+        self.embedded.insert(name.to_string(), serde_json::to_value(resources)?);
+        Ok(self)
+    }
+
+    /// Links of the response
+    pub fn links(&self) -> &Links {
+        &self.links
+    }
+}
+
+impl<T: Serialize> IntoResponse for HalResponse<T> {
+    fn into_response(self) -> Response {
+        let body = HalBody {
+            resource: &self.resource,
+            links: &self.links,
+            embedded: &self.embedded,
+        };
+
+        match serde_json::to_vec(&body) {
+            Ok(body) => (
+                self.status,
+                [(header::CONTENT_TYPE, HeaderValue::from_static(HAL_MEDIA_TYPE))],
+                body,
+            )
+                .into_response(),
+            Err(err) => ApiError::InternalServerError(err.to_string()).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn href<'a>(links: &'a Links, rel: &str) -> Option<&'a str> {
+        links.get(rel).map(|link| link.href.as_str())
+    }
+
+    #[test]
+    fn test_links_paginated() {
+        let uri = Uri::from_static("/users?sort=name&page=2&limit=10&q=a%20b");
+        let links = Links::paginated(&uri, &PaginationResponse::new(2, 10, 42));
+        assert_eq!(href(&links, "self"), Some("/users?sort=name&q=a+b&page=2&limit=10"));
+        assert_eq!(href(&links, "first"), Some("/users?sort=name&q=a+b&page=1&limit=10"));
+        assert_eq!(href(&links, "prev"), Some("/users?sort=name&q=a+b&page=1&limit=10"));
+        assert_eq!(href(&links, "next"), Some("/users?sort=name&q=a+b&page=3&limit=10"));
+        assert_eq!(href(&links, "last"), Some("/users?sort=name&q=a+b&page=5&limit=10"));
+
+        let uri = Uri::from_static("/users");
+        let links = Links::paginated(&uri, &PaginationResponse::new(1, 10, 0));
+        assert_eq!(href(&links, "last"), Some("/users?page=1&limit=10"));
+        assert!(links.get("prev").is_none());
+        assert!(links.get("next").is_none());
+
+        // Page after the last one
+        let links = Links::paginated(&uri, &PaginationResponse::new(9, 10, 42));
+        assert_eq!(href(&links, "prev"), Some("/users?page=5&limit=10"));
+        assert!(links.get("next").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hal_response() {
+        let response = HalResponse::new(json!({ "id": 1, "name": "John" }))
+            .with_links(Links::new().with_self("/users/1").with_related("/users/1/orders"))
+            .with_link("edit", "/users/1")
+            .with_embedded("orders", &[json!({ "id": 7 })])
+            .unwrap()
+            .with_status(StatusCode::CREATED)
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], HAL_MEDIA_TYPE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({
+                "id": 1,
+                "name": "John",
+                "_links": {
+                    "self": { "href": "/users/1" },
+                    "related": { "href": "/users/1/orders" },
+                    "edit": { "href": "/users/1" }
+                },
+                "_embedded": { "orders": [{ "id": 7 }] }
+            })
+        );
+
+        let link = Link::templated("/users{?page,limit}").with_title("Users");
+        assert_eq!(
+            serde_json::to_value(link).unwrap(),
+            json!({ "href": "/users{?page,limit}", "templated": true, "title": "Users" })
+        );
+    }
+}
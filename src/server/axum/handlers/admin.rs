@@ -0,0 +1,320 @@
+//! Admin introspection handler for Axum
+//!
+//! [`AdminHandler`] is an opt-in router exposing the runtime state of the service to the
+//! operators, protected by a [`BasicAuthLayer`]. Each endpoint answers with the standard JSON
+//! envelope (`{ "data": ..., "meta": { ... } }`):
+//!
+//! - `/config`: configuration snapshot, redacted with the global `RedactionPolicy` (the `Secret`
+//!   values are already serialized as `***`), if set
+//! - `/feature-flags`: rules of the feature flags, if set
+//! - `/rate-limiters`: limits and tracked keys of the adaptive throttles
+//! - `/circuit-breakers`: states of the circuit breakers
+//! - `/latencies`: per-route latency percentiles of a `LatencyTracker`, if set
+//! - `/runtime`: Tokio runtime metrics
+//!
+//! ```rust
+//! use api_tools::server::axum::handlers::admin::AdminHandler;
+//! use api_tools::server::axum::layers::basic_auth::BasicAuthLayer;
+//! use api_tools::server::axum::layers::circuit_breaker::CircuitBreaker;
+//! use axum::{Router, routing::get};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Config {
+//!     port: u16,
+//!     database_password: String,
+//! }
+//!
+//! let config = Config { port: 8080, database_password: "secret".to_string() };
+//! let admin = AdminHandler::new(BasicAuthLayer::new("admin", "password"))
+//!     .with_config(&config)
+//!     .unwrap()
+//!     .with_circuit_breaker(CircuitBreaker::new("users-service"));
+//!
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .nest("/admin", admin.router());
+//! ```
+
+use crate::server::axum::feature_flags::{FeatureFlags, FlagRule};
+use crate::server::axum::layers::adaptive_throttle::AdaptiveThrottle;
+use crate::server::axum::layers::basic_auth::BasicAuthLayer;
+use crate::server::axum::layers::circuit_breaker::CircuitBreaker;
+use crate::server::axum::layers::slow_request::{LatencyTracker, RouteLatency};
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::ApiSuccess;
+use axum::Router;
+use axum::http::StatusCode;
+use axum::routing::get;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// State of an adaptive throttle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RateLimiterSnapshot {
+    pub name: String,
+    pub limit: u32,
+    pub current_limit: u32,
+    pub tracked_keys: usize,
+}
+
+impl From<&AdaptiveThrottle> for RateLimiterSnapshot {
+    fn from(throttle: &AdaptiveThrottle) -> Self {
+        Self {
+            name: throttle.name().to_string(),
+            limit: throttle.limit(),
+            current_limit: throttle.current_limit(),
+            tracked_keys: throttle.tracked_keys(),
+        }
+    }
+}
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitBreakerSnapshot {
+    pub name: String,
+    pub state: String,
+}
+
+impl From<&CircuitBreaker> for CircuitBreakerSnapshot {
+    fn from(breaker: &CircuitBreaker) -> Self {
+        Self {
+            name: breaker.name().to_string(),
+            state: breaker.state().to_string(),
+        }
+    }
+}
+
+/// Tokio runtime metrics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RuntimeSnapshot {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+}
+
+impl RuntimeSnapshot {
+    /// Metrics of the current runtime (default values outside of a runtime)
+    pub fn current() -> Self {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let metrics = handle.metrics();
+                Self {
+                    workers: metrics.num_workers(),
+                    alive_tasks: metrics.num_alive_tasks(),
+                    global_queue_depth: metrics.global_queue_depth(),
+                }
+            }
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Admin introspection router
+#[derive(Clone)]
+pub struct AdminHandler {
+    pub auth: BasicAuthLayer,
+    pub config: Option<Value>,
+    pub feature_flags: Option<FeatureFlags>,
+    pub rate_limiters: Vec<AdaptiveThrottle>,
+    pub circuit_breakers: Vec<CircuitBreaker>,
+    pub latencies: Option<LatencyTracker>,
+}
+
+impl AdminHandler {
+    /// Create a new `AdminHandler` protected by Basic authentication
+    pub fn new(auth: BasicAuthLayer) -> Self {
+        Self {
+            auth,
+            config: None,
+            feature_flags: None,
+            rate_limiters: Vec::new(),
+            circuit_breakers: Vec::new(),
+            latencies: None,
+        }
+    }
+
+    /// Expose a configuration snapshot, redacted with the global `RedactionPolicy`
+    pub fn with_config<T: Serialize>(mut self, config: &T) -> Result<Self, serde_json::Error> {
+        let mut config = serde_json::to_value(config)?;
+        redaction_policy().redact_json(&mut config);
+        self.config = Some(config);
+
+        Ok(self)
+    }
+
+    /// Expose the rules of the feature flags
+    pub fn with_feature_flags(mut self, feature_flags: FeatureFlags) -> Self {
+        self.feature_flags = Some(feature_flags);
+        self
+    }
+
+    /// Expose the state of an adaptive throttle
+    pub fn with_rate_limiter(mut self, throttle: AdaptiveThrottle) -> Self {
+        self.rate_limiters.push(throttle);
+        self
+    }
+
+    /// Expose the state of a circuit breaker
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breakers.push(breaker);
+        self
+    }
+
+    /// Expose the per-route latency percentiles
+    pub fn with_latencies(mut self, tracker: LatencyTracker) -> Self {
+        self.latencies = Some(tracker);
+        self
+    }
+
+    /// Router of the admin endpoints, to nest under a prefix (e.g. `/admin`)
+    pub fn router<S>(self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let mut router = Router::new().route("/runtime", get(runtime_handler));
+
+        if let Some(config) = self.config {
+            router = router.route("/config", get(move || async move { enveloped(config) }));
+        }
+        if let Some(feature_flags) = self.feature_flags {
+            router = router.route(
+                "/feature-flags",
+                get(move || async move { enveloped::<BTreeMap<String, FlagRule>>(feature_flags.rules()) }),
+            );
+        }
+        let rate_limiters = self.rate_limiters;
+        router = router.route(
+            "/rate-limiters",
+            get(move || async move {
+                enveloped::<Vec<RateLimiterSnapshot>>(rate_limiters.iter().map(Into::into).collect())
+            }),
+        );
+        let circuit_breakers = self.circuit_breakers;
+        router = router.route(
+            "/circuit-breakers",
+            get(move || async move {
+                enveloped::<Vec<CircuitBreakerSnapshot>>(circuit_breakers.iter().map(Into::into).collect())
+            }),
+        );
+        if let Some(tracker) = self.latencies {
+            router = router.route(
+                "/latencies",
+                get(move || async move { enveloped::<Vec<RouteLatency>>(tracker.snapshot()) }),
+            );
+        }
+
+        router.layer(self.auth)
+    }
+}
+
+/// Standard JSON envelope of the admin responses
+fn enveloped<T: Serialize + PartialEq>(data: T) -> ApiSuccess<T> {
+    ApiSuccess::new(StatusCode::OK, data).enveloped()
+}
+
+/// Tokio runtime metrics handler
+async fn runtime_handler() -> ApiSuccess<RuntimeSnapshot> {
+    enveloped(RuntimeSnapshot::current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::response::Response;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::json;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn get_json(router: Router, uri: &str) -> (StatusCode, Value) {
+        let request = Request::get(uri)
+            .header(
+                header::AUTHORIZATION,
+                format!("Basic {}", STANDARD.encode("admin:password")),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response: Response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 100_000).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    fn admin() -> AdminHandler {
+        AdminHandler::new(BasicAuthLayer::new("admin", "password"))
+    }
+
+    #[tokio::test]
+    async fn test_admin_handler_requires_authentication() {
+        let response = admin()
+            .router::<()>()
+            .oneshot(Request::get("/runtime").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_handler_config_is_redacted() {
+        let config = json!({ "port": 8080, "database": { "password": "secret" } });
+        let router = admin().with_config(&config).unwrap().router();
+
+        let (status, body) = get_json(router, "/config").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["port"], 8080);
+        assert_ne!(body["data"]["database"]["password"], "secret");
+    }
+
+    #[tokio::test]
+    async fn test_admin_handler_unset_sections() {
+        let (status, _) = get_json(admin().router(), "/config").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = get_json(admin().router(), "/circuit-breakers").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_admin_handler_states() {
+        let throttle = AdaptiveThrottle::new("payments", 10, Duration::from_secs(60));
+        throttle.try_acquire("203.0.113.1").unwrap();
+        let breaker = CircuitBreaker::new("users-service")
+            .with_window_size(2)
+            .with_minimum_calls(1);
+        breaker.record_failure();
+        let tracker = LatencyTracker::default();
+        tracker.record("/users", Duration::from_millis(10), false);
+        let router = admin()
+            .with_feature_flags(FeatureFlags::new().with_flag("beta", true))
+            .with_rate_limiter(throttle)
+            .with_circuit_breaker(breaker)
+            .with_latencies(tracker)
+            .router::<()>();
+
+        let (_, body) = get_json(router.clone(), "/feature-flags").await;
+        assert_eq!(body["data"]["beta"]["enabled"], true);
+
+        let (_, body) = get_json(router.clone(), "/rate-limiters").await;
+        assert_eq!(
+            body["data"],
+            json!([{ "name": "payments", "limit": 10, "current_limit": 10, "tracked_keys": 1 }])
+        );
+
+        let (_, body) = get_json(router.clone(), "/circuit-breakers").await;
+        assert_eq!(body["data"], json!([{ "name": "users-service", "state": "open" }]));
+
+        let (_, body) = get_json(router.clone(), "/latencies").await;
+        assert_eq!(body["data"][0]["route"], "/users");
+
+        let (status, body) = get_json(router, "/runtime").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["data"]["workers"].as_u64().unwrap() >= 1);
+    }
+}
@@ -0,0 +1,460 @@
+//! Login, refresh and logout handlers for Axum
+//!
+//! The handlers issue and rotate the pairs of access and refresh tokens signed by a [`Jwt`].
+//! The application only implements the [`CredentialsValidator`] checking the credentials of the
+//! login request:
+//!
+//! - `POST /login` ([`login_handler`]): credentials → [`TokenPair`]
+//! - `POST /refresh` ([`refresh_handler`]): refresh token → new [`TokenPair`]
+//! - `POST /logout` ([`logout_handler`]): refresh token → `204 No Content`
+//!
+//! The tokens carry the [`AuthClaims`]: use `JwtAuthLayer<AuthClaims>` to protect the other routes
+//! (the refresh tokens are rejected by the layer).
+//!
+//! With a revocation store on the `Jwt` (`Jwt::set_revocation_store`), the refresh tokens are
+//! rotated: a refresh token can only be used once, and the logout revokes the refresh token and
+//! the access token of the request. With an [`AuthCookie`], the access token is also set in the
+//! cookie, and cleared on logout.
+//!
+//! ```rust
+//! use api_tools::server::axum::handlers::auth::{AuthClaims, CredentialsValidator, TokenAuth};
+//! use api_tools::server::axum::layers::jwt_auth::JwtAuthLayer;
+//! use api_tools::server::axum::response::ApiError;
+//! use api_tools::server::axum::security::jwt::Jwt;
+//! use axum::{Router, routing::get};
+//! use futures::future::BoxFuture;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Credentials {
+//!     login: String,
+//!     password: String,
+//! }
+//!
+//! struct Users;
+//!
+//! impl CredentialsValidator for Users {
+//!     type Credentials = Credentials;
+//!
+//!     fn validate<'a>(&'a self, credentials: &'a Credentials) -> BoxFuture<'a, Result<Option<String>, ApiError>> {
+//!         Box::pin(async move {
+//!             let valid = credentials.login == "bob" && credentials.password == "secret";
+//!             Ok(valid.then(|| "user-42".to_string()))
+//!         })
+//!     }
+//! }
+//!
+//! let jwt = Jwt::init("HS256", 15, 24, Some("secret"), Some("secret"), None).unwrap();
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .layer(JwtAuthLayer::<AuthClaims>::new(jwt.clone()))
+//!     .nest("/auth", TokenAuth::new(jwt, Users).router());
+//! ```
+
+use crate::server::axum::extractors::Json;
+use crate::server::axum::layers::jwt_auth::JwtClaims;
+use crate::server::axum::response::{ApiError, ApiSuccess};
+use crate::server::axum::security::jwt::access_token::AccessToken;
+use crate::server::axum::security::jwt::cookie::AuthCookie;
+use crate::server::axum::security::jwt::{Jwt, JwtError};
+use crate::value_objects::datetime::UtcDateTime;
+use axum::Router;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use chrono::{DateTime, TimeDelta};
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Check of the credentials of the login requests
+pub trait CredentialsValidator: Send + Sync + 'static {
+    /// Body of the login request
+    type Credentials: DeserializeOwned + Send + 'static;
+
+    /// Return the subject (user ID) of valid credentials, `None` otherwise
+    fn validate<'a>(&'a self, credentials: &'a Self::Credentials) -> BoxFuture<'a, Result<Option<String>, ApiError>>;
+
+    /// Return true if the subject of a refresh token can still get new tokens (disabled user)
+    fn is_active<'a>(&'a self, _subject: &'a str) -> BoxFuture<'a, Result<bool, ApiError>> {
+        Box::pin(async { Ok(true) })
+    }
+}
+
+/// Type of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+/// Claims of the tokens issued by the handlers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthClaims {
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+    pub typ: TokenType,
+}
+
+impl JwtClaims for AuthClaims {
+    fn subject(&self) -> Option<&str> {
+        Some(&self.sub)
+    }
+
+    fn token_id(&self) -> Option<&str> {
+        Some(&self.jti)
+    }
+
+    fn is_refresh_token(&self) -> bool {
+        self.typ == TokenType::Refresh
+    }
+}
+
+/// Access and refresh tokens returned by the login and refresh handlers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub token_type: String,
+    pub access_token: String,
+    pub access_token_expired_at: UtcDateTime,
+    pub refresh_token: String,
+    pub refresh_token_expired_at: UtcDateTime,
+}
+
+/// Body of the refresh and logout requests
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// State of the handlers
+pub struct TokenAuth<V> {
+    pub jwt: Jwt,
+    pub validator: Arc<V>,
+    pub cookie: Option<AuthCookie>,
+}
+
+impl<V> Clone for TokenAuth<V> {
+    fn clone(&self) -> Self {
+        Self {
+            jwt: self.jwt.clone(),
+            validator: Arc::clone(&self.validator),
+            cookie: self.cookie.clone(),
+        }
+    }
+}
+
+impl<V: CredentialsValidator> TokenAuth<V> {
+    /// Create a new `TokenAuth`
+    pub fn new(jwt: Jwt, validator: V) -> Self {
+        Self {
+            jwt,
+            validator: Arc::new(validator),
+            cookie: None,
+        }
+    }
+
+    /// Also set the access token in a cookie (cleared on logout)
+    pub fn with_cookie(mut self, cookie: AuthCookie) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+
+    /// `POST /login`, `POST /refresh` and `POST /logout` routes, to nest in the application router
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/login", post(login_handler::<V>))
+            .route("/refresh", post(refresh_handler::<V>))
+            .route("/logout", post(logout_handler::<V>))
+            .with_state(self.clone())
+    }
+
+    /// Issue a new pair of tokens
+    fn issue(&self, subject: &str) -> Result<Response, ApiError> {
+        let now = UtcDateTime::now();
+        let access_expired_at = now.add(TimeDelta::minutes(self.jwt.access_lifetime()));
+        let refresh_expired_at = now.add(TimeDelta::hours(self.jwt.refresh_lifetime()));
+
+        let access_token = self.generate(subject, TokenType::Access, &now, access_expired_at)?;
+        let refresh_token = self.generate(subject, TokenType::Refresh, &now, refresh_expired_at)?;
+        let cookie = self.cookie.as_ref().map(|cookie| cookie.set(&access_token));
+        let pair = TokenPair {
+            token_type: "Bearer".to_string(),
+            access_token: access_token.token,
+            access_token_expired_at: access_token.expired_at,
+            refresh_token: refresh_token.token,
+            refresh_token_expired_at: refresh_token.expired_at,
+        };
+
+        Ok((
+            cookie.into_iter().collect::<HeaderMap>(),
+            ApiSuccess::new(StatusCode::OK, pair),
+        )
+            .into_response())
+    }
+
+    /// Generate a token
+    fn generate(
+        &self,
+        subject: &str,
+        typ: TokenType,
+        now: &UtcDateTime,
+        expired_at: UtcDateTime,
+    ) -> Result<AccessToken, ApiError> {
+        let claims = AuthClaims {
+            sub: subject.to_string(),
+            iat: now.timestamp(),
+            exp: expired_at.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            typ,
+        };
+
+        Ok(self.jwt.generate(claims, expired_at)?)
+    }
+
+    /// Parse a refresh token
+    fn parse_refresh_token(&self, token: &str) -> Result<AuthClaims, ApiError> {
+        let token = AccessToken::new(token.to_string(), UtcDateTime::now());
+
+        match self.jwt.parse::<AuthClaims>(&token) {
+            Ok(claims) if claims.typ == TokenType::Refresh => Ok(claims),
+            Err(JwtError::ExpiredToken) => Err(ApiError::Unauthorized("Expired refresh token".to_string())),
+            _ => Err(ApiError::Unauthorized("Invalid refresh token".to_string())),
+        }
+    }
+
+    /// Revoke a token until its expiration, if the `Jwt` has a revocation store
+    async fn revoke(&self, claims: &AuthClaims) -> Result<(), ApiError> {
+        if !self.jwt.has_revocation_store() {
+            return Ok(());
+        }
+
+        let expires_at = DateTime::from_timestamp(claims.exp, 0)
+            .map(UtcDateTime::new)
+            .unwrap_or_else(UtcDateTime::now);
+        self.jwt.revoke(&claims.jti, expires_at).await.map_err(store_error)
+    }
+}
+
+/// Issue a pair of tokens for valid credentials (`401 Unauthorized` otherwise)
+pub async fn login_handler<V: CredentialsValidator>(
+    State(auth): State<TokenAuth<V>>,
+    Json(credentials): Json<V::Credentials>,
+) -> Result<Response, ApiError> {
+    match auth.validator.validate(&credentials).await? {
+        Some(subject) => auth.issue(&subject),
+        None => Err(ApiError::Unauthorized("Invalid credentials".to_string())),
+    }
+}
+
+/// Issue a new pair of tokens for a valid refresh token, revoked if the `Jwt` has a revocation store
+pub async fn refresh_handler<V: CredentialsValidator>(
+    State(auth): State<TokenAuth<V>>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Response, ApiError> {
+    let claims = auth.parse_refresh_token(&request.refresh_token)?;
+
+    if auth.jwt.is_revoked(&claims.jti).await.map_err(store_error)? {
+        return Err(ApiError::Unauthorized("Revoked refresh token".to_string()));
+    }
+    if !auth.validator.is_active(&claims.sub).await? {
+        return Err(ApiError::Unauthorized("Inactive subject".to_string()));
+    }
+
+    auth.revoke(&claims).await?;
+    auth.issue(&claims.sub)
+}
+
+/// Revoke the refresh token and the access token of the request, and clear the cookie
+pub async fn logout_handler<V: CredentialsValidator>(
+    State(auth): State<TokenAuth<V>>,
+    headers: HeaderMap,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Response, ApiError> {
+    let claims = auth.parse_refresh_token(&request.refresh_token)?;
+    auth.revoke(&claims).await?;
+
+    let access_token = AccessToken::extract_bearer_token_from_headers(&headers).or_else(|| {
+        auth.cookie
+            .as_ref()
+            .and_then(|cookie| cookie.token_from_headers(&headers))
+    });
+    if let Some(access_claims) = access_token.and_then(|token| auth.jwt.parse::<AuthClaims>(&token).ok()) {
+        auth.revoke(&access_claims).await?;
+    }
+
+    let cookie = auth.cookie.as_ref().map(AuthCookie::clear);
+
+    Ok((StatusCode::NO_CONTENT, cookie.into_iter().collect::<HeaderMap>()).into_response())
+}
+
+/// Error of the revocation store
+fn store_error(err: JwtError) -> ApiError {
+    tracing::error!(error = %err, "token revocation store failure");
+    ApiError::ServiceUnavailable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::security::jwt::revocation::MemoryRevocationStore;
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Credentials {
+        login: String,
+        password: String,
+    }
+
+    struct Users;
+
+    impl CredentialsValidator for Users {
+        type Credentials = Credentials;
+
+        fn validate<'a>(&'a self, credentials: &'a Credentials) -> BoxFuture<'a, Result<Option<String>, ApiError>> {
+            Box::pin(async move {
+                let valid = credentials.login == "bob" && credentials.password == "secret";
+                Ok(valid.then(|| "user-42".to_string()))
+            })
+        }
+
+        fn is_active<'a>(&'a self, subject: &'a str) -> BoxFuture<'a, Result<bool, ApiError>> {
+            Box::pin(async move { Ok(subject != "disabled") })
+        }
+    }
+
+    fn auth() -> TokenAuth<Users> {
+        let mut jwt = Jwt::init("HS256", 15, 24, Some("secret"), Some("secret"), None).unwrap();
+        jwt.set_revocation_store(MemoryRevocationStore::new());
+
+        TokenAuth::new(jwt, Users)
+    }
+
+    async fn post(router: &Router, uri: &str, body: &str) -> Response {
+        router
+            .clone()
+            .oneshot(
+                Request::post(uri)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn token_pair(response: Response) -> TokenPair {
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_login_handler() {
+        let auth = auth();
+        let router: Router = auth.router();
+
+        let response = post(&router, "/login", r#"{"login": "bob", "password": "wrong"}"#).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = post(&router, "/login", r#"{"login": "bob", "password": "secret"}"#).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let pair = token_pair(response).await;
+        assert_eq!(pair.token_type, "Bearer");
+
+        let claims: AuthClaims = auth
+            .jwt
+            .parse(&AccessToken::new(pair.access_token, UtcDateTime::now()))
+            .unwrap();
+        assert_eq!(claims.sub, "user-42");
+        assert_eq!(claims.typ, TokenType::Access);
+        assert!(!claims.is_refresh_token());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_handler_rotates_the_refresh_token() {
+        let router: Router = auth().router();
+        let response = post(&router, "/login", r#"{"login": "bob", "password": "secret"}"#).await;
+        let pair = token_pair(response).await;
+
+        // An access token is not a refresh token
+        let body = format!(r#"{{"refresh_token": "{}"}}"#, pair.access_token);
+        let response = post(&router, "/refresh", &body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let body = format!(r#"{{"refresh_token": "{}"}}"#, pair.refresh_token);
+        let response = post(&router, "/refresh", &body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let new_pair = token_pair(response).await;
+        assert_ne!(new_pair.refresh_token, pair.refresh_token);
+
+        // The previous refresh token can only be used once
+        let response = post(&router, "/refresh", &body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_handler_rejects_inactive_subjects() {
+        let auth = auth();
+        let router: Router = auth.router();
+        let response = auth.issue("disabled").unwrap();
+        let pair = token_pair(response).await;
+
+        let body = format!(r#"{{"refresh_token": "{}"}}"#, pair.refresh_token);
+        let response = post(&router, "/refresh", &body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_logout_handler() {
+        let auth = auth().with_cookie(AuthCookie::new());
+        let router: Router = auth.router();
+
+        let response = post(&router, "/login", r#"{"login": "bob", "password": "secret"}"#).await;
+        assert!(
+            response.headers()[header::SET_COOKIE]
+                .to_str()
+                .unwrap()
+                .starts_with("access_token=")
+        );
+        let pair = token_pair(response).await;
+
+        let body = format!(r#"{{"refresh_token": "{}"}}"#, pair.refresh_token);
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post("/logout")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", pair.access_token))
+                    .body(Body::from(body.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(
+            response.headers()[header::SET_COOKIE]
+                .to_str()
+                .unwrap()
+                .starts_with("access_token=; Path=/; Max-Age=0")
+        );
+
+        let access_claims: AuthClaims = auth
+            .jwt
+            .parse(&AccessToken::new(pair.access_token, UtcDateTime::now()))
+            .unwrap();
+        assert!(auth.jwt.is_revoked(&access_claims.jti).await.unwrap());
+
+        let response = post(&router, "/refresh", &body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
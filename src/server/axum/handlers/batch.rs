@@ -0,0 +1,352 @@
+//! Batch requests handler for Axum
+//!
+//! [`BatchHandler`] executes several sub-requests sent in a single `POST` against a router, to
+//! reduce the round-trips of the mobile clients. The body is an array of sub-requests:
+//!
+//! ```json
+//! [
+//!     { "method": "GET", "path": "/users/1" },
+//!     { "method": "POST", "path": "/users", "headers": { "x-tenant": "acme" }, "body": { "name": "John" } }
+//! ]
+//! ```
+//!
+//! The sub-requests inherit the headers of the batch request (e.g. `Authorization`), overridden
+//! by their own headers, and their bodies are sent as JSON. The responses are returned in the
+//! order of the sub-requests, in the standard JSON envelope:
+//!
+//! ```json
+//! {
+//!     "data": [
+//!         { "status": 200, "headers": { "content-type": "application/json" }, "body": { "id": 1 } },
+//!         { "status": 201, "headers": { "content-type": "application/json" }, "body": { "id": 2 } }
+//!     ],
+//!     "meta": { ... }
+//! }
+//! ```
+//!
+//! The bodies which are not JSON are returned as strings (`null` if empty). A failed sub-request
+//! (invalid method or path, timeout, too large body) only fails its own item, with the standard
+//! JSON error as body. The batch fails with a `400 Bad Request` if it has too many sub-requests.
+//!
+//! ```rust
+//! use api_tools::server::axum::handlers::batch::BatchHandler;
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let api: Router = Router::new().route("/users/{id}", get(|| async { "user" }));
+//!
+//! let app: Router = api.clone().merge(
+//!     BatchHandler::new(api)
+//!         .with_max_items(10)
+//!         .with_max_concurrency(4)
+//!         .with_timeout(Duration::from_secs(5))
+//!         .router("/batch"),
+//! );
+//! ```
+
+use crate::server::axum::extractors::Json;
+use crate::server::axum::response::{ApiError, ApiSuccess};
+use axum::Router;
+use axum::body::Body;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tower::ServiceExt;
+
+/// Default maximum number of sub-requests in a batch
+pub const BATCH_DEFAULT_MAX_ITEMS: usize = 20;
+
+/// Default maximum number of sub-requests executed concurrently
+pub const BATCH_DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+/// Default maximum size of the body of a sub-response (1 MiB)
+pub const BATCH_DEFAULT_MAX_BODY_SIZE: usize = 1_024 * 1_024;
+
+/// Sub-request of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// Response of a sub-request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+}
+
+/// Batch requests router
+#[derive(Clone)]
+pub struct BatchHandler {
+    router: Router,
+    pub max_items: usize,
+    pub max_concurrency: usize,
+    pub max_body_size: usize,
+    pub timeout: Option<Duration>,
+}
+
+impl BatchHandler {
+    /// Create a new `BatchHandler` executing the sub-requests against a router
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            max_items: BATCH_DEFAULT_MAX_ITEMS,
+            max_concurrency: BATCH_DEFAULT_MAX_CONCURRENCY,
+            max_body_size: BATCH_DEFAULT_MAX_BODY_SIZE,
+            timeout: None,
+        }
+    }
+
+    /// Set the maximum number of sub-requests in a batch
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Set the maximum number of sub-requests executed concurrently
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Set the maximum size of the body of a sub-response
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Set the timeout of each sub-request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Router answering the batches on `POST {path}`
+    pub fn router<S>(&self, path: &str) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let handler = self.clone();
+
+        Router::new().route(
+            path,
+            post(
+                move |headers: HeaderMap, Json(requests): Json<Vec<BatchRequest>>| async move {
+                    handler.execute(&headers, requests).await
+                },
+            ),
+        )
+    }
+
+    /// Execute the sub-requests of a batch
+    pub async fn execute(&self, headers: &HeaderMap, requests: Vec<BatchRequest>) -> Response {
+        if requests.len() > self.max_items {
+            return ApiError::BadRequest(format!("Too many requests in the batch (max: {})", self.max_items))
+                .into_response();
+        }
+
+        let responses = futures::stream::iter(requests)
+            .map(|request| self.execute_one(headers, request))
+            .buffered(self.max_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        ApiSuccess::new(StatusCode::OK, responses).enveloped().into_response()
+    }
+
+    /// Execute a sub-request
+    async fn execute_one(&self, headers: &HeaderMap, request: BatchRequest) -> BatchResponse {
+        let response = match self.build_request(headers, request) {
+            Ok(request) => {
+                let call = self.router.clone().oneshot(request);
+                match self.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, call).await.map_or_else(
+                        |_| ApiError::Timeout.into_response(),
+                        |response| response.into_response(),
+                    ),
+                    None => call.await.into_response(),
+                }
+            }
+            Err(err) => err.into_response(),
+        };
+
+        self.batch_response(response).await
+    }
+
+    /// Build the request of a sub-request
+    fn build_request(&self, headers: &HeaderMap, request: BatchRequest) -> Result<Request<Body>, ApiError> {
+        let method = Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|_| ApiError::BadRequest(format!("Invalid method: {}", request.method)))?;
+        if !request.path.starts_with('/') {
+            return Err(ApiError::BadRequest(format!("Invalid path: {}", request.path)));
+        }
+
+        let mut sub_headers = headers.clone();
+        sub_headers.remove(CONTENT_LENGTH);
+        sub_headers.remove(CONTENT_TYPE);
+        for (name, value) in &request.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ApiError::BadRequest(format!("Invalid header name: {name}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| ApiError::BadRequest(format!("Invalid header value: {name}")))?;
+            sub_headers.insert(name, value);
+        }
+
+        let body = match request.body {
+            Some(body) => {
+                if !sub_headers.contains_key(CONTENT_TYPE) {
+                    sub_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                }
+                Body::from(serde_json::to_vec(&body).map_err(|err| ApiError::BadRequest(err.to_string()))?)
+            }
+            None => Body::empty(),
+        };
+
+        let mut sub_request = Request::builder()
+            .method(method)
+            .uri(&request.path)
+            .body(body)
+            .map_err(|_| ApiError::BadRequest(format!("Invalid path: {}", request.path)))?;
+        *sub_request.headers_mut() = sub_headers;
+
+        Ok(sub_request)
+    }
+
+    /// Convert the response of a sub-request
+    async fn batch_response(&self, response: Response) -> BatchResponse {
+        let (parts, body) = response.into_parts();
+        let (status, headers, body) = match axum::body::to_bytes(body, self.max_body_size).await {
+            Ok(body) => (parts.status, parts.headers, body),
+            Err(_) => {
+                let (parts, body) = ApiError::PayloadTooLarge.into_response().into_parts();
+                let body = axum::body::to_bytes(body, usize::MAX).await.unwrap_or_default();
+                (parts.status, parts.headers, body)
+            }
+        };
+
+        let body = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()))
+        };
+
+        BatchResponse {
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use serde_json::json;
+
+    fn api() -> Router {
+        Router::new()
+            .route(
+                "/users/{id}",
+                get(|axum::extract::Path(id): axum::extract::Path<u32>| async move { axum::Json(json!({ "id": id })) }),
+            )
+            .route(
+                "/users",
+                post(|headers: HeaderMap, axum::Json(body): axum::Json<Value>| async move {
+                    let tenant = headers
+                        .get("x-tenant")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default();
+                    (
+                        StatusCode::CREATED,
+                        axum::Json(json!({ "name": body["name"], "tenant": tenant })),
+                    )
+                }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    "slow"
+                }),
+            )
+            .route("/text", get(|| async { "plain text" }))
+    }
+
+    async fn send(handler: BatchHandler, body: Value) -> (StatusCode, Value) {
+        let request = Request::post("/batch")
+            .header(CONTENT_TYPE, "application/json")
+            .header("x-tenant", "acme")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = handler.router::<()>("/batch").oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_batch_handler() {
+        let handler = BatchHandler::new(api()).with_timeout(Duration::from_millis(50));
+        let (status, body) = send(
+            handler,
+            json!([
+                { "method": "GET", "path": "/users/1" },
+                { "method": "post", "path": "/users", "body": { "name": "John" } },
+                { "method": "POST", "path": "/users", "headers": { "x-tenant": "other" }, "body": { "name": "Jane" } },
+                { "method": "GET", "path": "/text" },
+                { "method": "GET", "path": "/slow" },
+                { "method": "GET", "path": "/unknown" },
+                { "method": "GET", "path": "users" },
+            ]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let items = body["data"].as_array().unwrap();
+        assert_eq!(items.len(), 7);
+        assert_eq!(items[0]["status"], 200);
+        assert_eq!(items[0]["body"], json!({ "id": 1 }));
+        assert_eq!(items[0]["headers"]["content-type"], "application/json");
+        assert_eq!(items[1]["status"], 201);
+        assert_eq!(items[1]["body"], json!({ "name": "John", "tenant": "acme" }));
+        assert_eq!(items[2]["body"], json!({ "name": "Jane", "tenant": "other" }));
+        assert_eq!(items[3]["body"], "plain text");
+        assert_eq!(items[4]["status"], ApiError::Timeout.into_response().status().as_u16());
+        assert_eq!(items[5]["status"], 404);
+        assert_eq!(items[6]["status"], 400);
+    }
+
+    #[tokio::test]
+    async fn test_batch_handler_limits() {
+        let handler = BatchHandler::new(api()).with_max_items(2).with_max_body_size(5);
+
+        let (status, body) = send(
+            handler.clone(),
+            json!([{ "method": "GET", "path": "/users/1" }, { "method": "GET", "path": "/text" }]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["status"], 413);
+
+        let item = json!({ "method": "GET", "path": "/users/1" });
+        let (status, _) = send(handler, json!([item, item, item])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,4 +1,10 @@
 //! Axum handlers
 
+pub mod admin;
+pub mod auth;
+pub mod batch;
+pub mod openapi;
 #[cfg(feature = "prometheus")]
 pub mod prometheus;
+pub mod static_files;
+pub mod well_known;
@@ -0,0 +1,302 @@
+//! OpenAPI documentation handler for Axum
+//!
+//! [`OpenApiDocs`] serves an OpenAPI document (JSON or YAML) and a Swagger UI (or RapiDoc) page
+//! loading it. The UI assets are loaded from a CDN (configurable with
+//! [`OpenApiDocs::with_assets_url`]). The routes can be protected with a `BasicAuthLayer`.
+//!
+//! ```rust
+//! use api_tools::server::axum::handlers::openapi::OpenApiDocs;
+//! use api_tools::server::axum::layers::basic_auth::BasicAuthLayer;
+//! use axum::{Router, routing::get};
+//!
+//! let spec = r#"{"openapi":"3.1.0","info":{"title":"Users","version":"1.0.0"},"paths":{}}"#;
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .merge(OpenApiDocs::json(spec).with_basic_auth(BasicAuthLayer::new("docs", "secret")).router());
+//! ```
+
+use crate::server::axum::layers::basic_auth::BasicAuthLayer;
+use axum::Router;
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use bytes::Bytes;
+
+/// Default path of the OpenAPI document
+pub const OPENAPI_DEFAULT_SPEC_PATH: &str = "/openapi.json";
+
+/// Default path of the documentation UI
+pub const OPENAPI_DEFAULT_UI_PATH: &str = "/docs";
+
+/// Default URL of the Swagger UI assets
+pub const SWAGGER_UI_DEFAULT_ASSETS_URL: &str = "https://unpkg.com/swagger-ui-dist@5";
+
+/// Default URL of the RapiDoc assets
+pub const RAPIDOC_DEFAULT_ASSETS_URL: &str = "https://unpkg.com/rapidoc@9";
+
+/// Documentation UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocsUi {
+    /// Swagger UI
+    #[default]
+    SwaggerUi,
+
+    /// RapiDoc
+    RapiDoc,
+}
+
+/// Format of the OpenAPI document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+impl SpecFormat {
+    /// Content type of the document
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Yaml => "application/yaml",
+        }
+    }
+}
+
+/// OpenAPI document and documentation UI routes
+#[derive(Clone)]
+pub struct OpenApiDocs {
+    pub spec: Bytes,
+    pub format: SpecFormat,
+    pub spec_path: String,
+    pub ui_path: String,
+    pub ui: DocsUi,
+    pub title: String,
+    pub assets_url: Option<String>,
+    pub basic_auth: Option<BasicAuthLayer>,
+}
+
+impl OpenApiDocs {
+    /// Serve a JSON OpenAPI document (at `/openapi.json` by default)
+    pub fn json(spec: impl Into<Bytes>) -> Self {
+        Self::new(spec.into(), SpecFormat::Json, OPENAPI_DEFAULT_SPEC_PATH)
+    }
+
+    /// Serve a YAML OpenAPI document (at `/openapi.yaml` by default)
+    pub fn yaml(spec: impl Into<Bytes>) -> Self {
+        Self::new(spec.into(), SpecFormat::Yaml, "/openapi.yaml")
+    }
+
+    /// Serve the OpenAPI document generated by `utoipa`
+    #[cfg(feature = "openapi")]
+    pub fn from_openapi(openapi: &utoipa::openapi::OpenApi) -> Result<Self, crate::server::axum::response::ApiError> {
+        let spec = openapi
+            .to_json()
+            .map_err(|err| crate::server::axum::response::ApiError::InternalServerError(err.to_string()))?;
+
+        Ok(Self::json(spec))
+    }
+
+    fn new(spec: Bytes, format: SpecFormat, spec_path: &str) -> Self {
+        Self {
+            spec,
+            format,
+            spec_path: spec_path.to_string(),
+            ui_path: OPENAPI_DEFAULT_UI_PATH.to_string(),
+            ui: DocsUi::default(),
+            title: "API documentation".to_string(),
+            assets_url: None,
+            basic_auth: None,
+        }
+    }
+
+    /// Set the path of the OpenAPI document
+    pub fn with_spec_path(mut self, path: &str) -> Self {
+        self.spec_path = path.to_string();
+        self
+    }
+
+    /// Set the path of the documentation UI
+    pub fn with_ui_path(mut self, path: &str) -> Self {
+        self.ui_path = path.to_string();
+        self
+    }
+
+    /// Set the documentation UI (Swagger UI by default)
+    pub fn with_ui(mut self, ui: DocsUi) -> Self {
+        self.ui = ui;
+        self
+    }
+
+    /// Set the title of the documentation page
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Set the URL of the UI assets (e.g. a self-hosted copy of `swagger-ui-dist`)
+    pub fn with_assets_url(mut self, url: &str) -> Self {
+        self.assets_url = Some(url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Protect the document and the UI with Basic Auth
+    pub fn with_basic_auth(mut self, layer: BasicAuthLayer) -> Self {
+        self.basic_auth = Some(layer);
+        self
+    }
+
+    /// HTML page of the documentation UI
+    pub fn html(&self) -> String {
+        let title = escape_html(&self.title);
+        let spec_url = escape_html(&self.spec_path);
+        match self.ui {
+            DocsUi::SwaggerUi => {
+                let assets = escape_html(self.assets_url.as_deref().unwrap_or(SWAGGER_UI_DEFAULT_ASSETS_URL));
+                format!(
+                    r##"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>{title}</title>
+  <link rel="stylesheet" href="{assets}/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="{assets}/swagger-ui-bundle.js"></script>
+  <script>
+    window.onload = () => {{
+      window.ui = SwaggerUIBundle({{ url: "{spec_url}", dom_id: "#swagger-ui" }});
+    }};
+  </script>
+</body>
+</html>
+"##
+                )
+            }
+            DocsUi::RapiDoc => {
+                let assets = escape_html(self.assets_url.as_deref().unwrap_or(RAPIDOC_DEFAULT_ASSETS_URL));
+                format!(
+                    r##"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>{title}</title>
+  <script type="module" src="{assets}/dist/rapidoc-min.js"></script>
+</head>
+<body>
+  <rapi-doc spec-url="{spec_url}" render-style="read"></rapi-doc>
+</body>
+</html>
+"##
+                )
+            }
+        }
+    }
+
+    /// Routes of the OpenAPI document and the documentation UI, to merge in the application router
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let spec = self.spec.clone();
+        let content_type = self.format.content_type();
+        let html = self.html();
+
+        let router = Router::new()
+            .route(
+                &self.spec_path,
+                get(move || async move { ([(header::CONTENT_TYPE, content_type)], spec).into_response() }),
+            )
+            .route(&self.ui_path, get(move || async move { Html(html) }));
+
+        match &self.basic_auth {
+            Some(layer) => router.route_layer(layer.clone()),
+            None => router,
+        }
+    }
+}
+
+/// Escape the HTML special characters
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use base64::{Engine as _, engine::general_purpose};
+    use tower::ServiceExt;
+
+    const SPEC: &str = r#"{"openapi":"3.1.0","info":{"title":"Users","version":"1.0.0"},"paths":{}}"#;
+
+    async fn get(router: &Router, uri: &str, authorization: Option<&str>) -> (StatusCode, String, String) {
+        let mut request = Request::get(uri);
+        if let Some(authorization) = authorization {
+            request = request.header(
+                header::AUTHORIZATION,
+                format!("Basic {}", general_purpose::STANDARD.encode(authorization)),
+            );
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+
+        (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_openapi_docs_router() {
+        let router: Router = OpenApiDocs::json(SPEC).with_title("Users <API>").router();
+
+        let (status, content_type, body) = get(&router, "/openapi.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(body, SPEC);
+
+        let (status, content_type, body) = get(&router, "/docs", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/html"));
+        assert!(body.contains("SwaggerUIBundle({ url: \"/openapi.json\""));
+        assert!(body.contains("<title>Users &lt;API&gt;</title>"));
+    }
+
+    #[tokio::test]
+    async fn test_openapi_docs_rapidoc_yaml_with_basic_auth() {
+        let router: Router = OpenApiDocs::yaml("openapi: 3.1.0")
+            .with_ui(DocsUi::RapiDoc)
+            .with_ui_path("/api/docs")
+            .with_spec_path("/api/openapi.yaml")
+            .with_assets_url("https://assets.example.com/rapidoc/")
+            .with_basic_auth(BasicAuthLayer::new("docs", "secret"))
+            .router();
+
+        let (status, _, _) = get(&router, "/api/docs", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _, body) = get(&router, "/api/docs", Some("docs:secret")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#"<rapi-doc spec-url="/api/openapi.yaml""#));
+        assert!(body.contains("https://assets.example.com/rapidoc/dist/rapidoc-min.js"));
+
+        let (status, content_type, body) = get(&router, "/api/openapi.yaml", Some("docs:secret")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/yaml");
+        assert_eq!(body, "openapi: 3.1.0");
+    }
+}
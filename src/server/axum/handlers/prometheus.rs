@@ -1,12 +1,54 @@
 //! Prometheus metrics handler for Axum
 
+use crate::server::axum::layers::logger::SkipLogging;
+use crate::server::axum::layers::metrics::{enable_exemplars, exemplars, render_openmetrics};
 use crate::server::axum::response::ApiError;
+use axum::Router;
+use axum::response::IntoResponse;
+use axum::routing::get;
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Default buckets for the `http_requests_duration_seconds` histogram, in
 /// seconds. Suitable for typical HTTP API latency distributions.
 pub const DEFAULT_DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
 
+/// Default buckets for the `http_request_size_bytes` and
+/// `http_response_size_bytes` histograms, in bytes (100 B to 10 MB).
+pub const DEFAULT_SIZE_BUCKETS: &[f64] = &[100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0, 10_000_000.0];
+
+/// Content type of the OpenMetrics format
+pub const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Registration time of the process metrics, used to compute `process_uptime_seconds`
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+/// Build information exposed by the `build_info` gauge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub service: String,
+    pub version: String,
+    pub git_sha: Option<String>,
+}
+
+impl BuildInfo {
+    /// Create a new `BuildInfo`
+    pub fn new(service: &str, version: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            version: version.to_string(),
+            git_sha: None,
+        }
+    }
+
+    /// Set the Git commit SHA
+    pub fn with_git_sha(mut self, git_sha: &str) -> Self {
+        self.git_sha = Some(git_sha.to_string());
+        self
+    }
+}
+
 /// Prometheus metrics handler for Axum
 pub struct PrometheusHandler {}
 
@@ -22,12 +64,136 @@ impl PrometheusHandler {
     /// default bucket distribution does not match your service's latency
     /// profile.
     pub fn get_handle_with_buckets(buckets: &[f64]) -> Result<PrometheusHandle, ApiError> {
-        PrometheusBuilder::new()
-            .set_buckets_for_metric(Matcher::Full("http_requests_duration_seconds".to_string()), buckets)
-            .map_err(|err| ApiError::InternalServerError(err.to_string()))?
+        Self::get_handle_with_size_buckets(buckets, DEFAULT_SIZE_BUCKETS)
+    }
+
+    /// Install the global Prometheus recorder with custom histogram buckets
+    /// for `http_requests_duration_seconds` (in seconds) and for
+    /// `http_request_size_bytes` and `http_response_size_bytes` (in bytes).
+    pub fn get_handle_with_size_buckets(
+        duration_buckets: &[f64],
+        size_buckets: &[f64],
+    ) -> Result<PrometheusHandle, ApiError> {
+        Self::builder(duration_buckets, size_buckets)?
             .install_recorder()
             .map_err(|err| ApiError::InternalServerError(err.to_string()))
     }
+
+    /// Prometheus builder with the histogram buckets of the HTTP metrics
+    fn builder(duration_buckets: &[f64], size_buckets: &[f64]) -> Result<PrometheusBuilder, ApiError> {
+        let mut builder = PrometheusBuilder::new()
+            .set_buckets_for_metric(
+                Matcher::Full("http_requests_duration_seconds".to_string()),
+                duration_buckets,
+            )
+            .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+        for metric in ["http_request_size_bytes", "http_response_size_bytes"] {
+            builder = builder
+                .set_buckets_for_metric(Matcher::Full(metric.to_string()), size_buckets)
+                .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+        }
+
+        Ok(builder)
+    }
+
+    /// Install the global Prometheus recorder with custom histogram buckets
+    /// and enable exemplars (trace IDs) on `http_requests_duration_seconds`.
+    ///
+    /// Exemplars are only exposed by [`PrometheusHandler::render_openmetrics`].
+    pub fn get_handle_with_exemplars(buckets: &[f64]) -> Result<PrometheusHandle, ApiError> {
+        let handle = Self::get_handle_with_buckets(buckets)?;
+        enable_exemplars(buckets);
+
+        Ok(handle)
+    }
+
+    /// Render metrics in the OpenMetrics format (with exemplars if enabled).
+    /// The response must use the [`OPENMETRICS_CONTENT_TYPE`] content type.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use api_tools::server::axum::handlers::prometheus::{OPENMETRICS_CONTENT_TYPE, PrometheusHandler};
+    /// use axum::http::header;
+    ///
+    /// let handle = PrometheusHandler::get_handle_with_exemplars(DEFAULT_DURATION_BUCKETS)?;
+    /// let metrics = move || async move {
+    ///     ([(header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)], PrometheusHandler::render_openmetrics(&handle))
+    /// };
+    /// ```
+    pub fn render_openmetrics(handle: &PrometheusHandle) -> String {
+        Self::update_process_metrics();
+        render_openmetrics(&handle.render(), exemplars())
+    }
+
+    /// Register the `process_start_time_seconds` (Unix timestamp of the registration, which should
+    /// happen at startup) and `process_uptime_seconds` gauges.
+    ///
+    /// The uptime is updated when the metrics are rendered by [`metrics_router`] or
+    /// [`PrometheusHandler::render_openmetrics`].
+    pub fn register_process_metrics() {
+        PROCESS_START.get_or_init(|| {
+            let start_time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            metrics::gauge!("process_start_time_seconds").set(start_time);
+
+            Instant::now()
+        });
+        Self::update_process_metrics();
+    }
+
+    /// Register the `build_info` gauge (always `1`) with the `service`, `version` and `git_sha`
+    /// labels
+    pub fn register_build_info(info: &BuildInfo) {
+        metrics::gauge!(
+            "build_info",
+            "service" => info.service.clone(),
+            "version" => info.version.clone(),
+            "git_sha" => info.git_sha.clone().unwrap_or_default(),
+        )
+        .set(1.0);
+    }
+
+    /// Update `process_uptime_seconds` if the process metrics are registered
+    fn update_process_metrics() {
+        if let Some(start) = PROCESS_START.get() {
+            metrics::gauge!("process_uptime_seconds").set(start.elapsed().as_secs_f64());
+        }
+    }
+}
+
+/// Router exposing the metrics (Prometheus text format) on `/metrics`
+///
+/// The requests are excluded from the `LoggerLayer` info logs. The router can be protected (e.g.
+/// with `BasicAuthLayer`) before being merged in the application router.
+///
+/// # Example
+///
+/// ```ignore
+/// use api_tools::server::axum::handlers::prometheus::{BuildInfo, PrometheusHandler, metrics_router};
+///
+/// let handle = PrometheusHandler::get_handle()?;
+/// PrometheusHandler::register_process_metrics();
+/// PrometheusHandler::register_build_info(&BuildInfo::new("myapp", env!("CARGO_PKG_VERSION")));
+///
+/// let app = Router::new().route("/health", get(health)).merge(metrics_router(handle));
+/// ```
+pub fn metrics_router<S>(handle: PrometheusHandle) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route(
+        "/metrics",
+        get(move || async move {
+            PrometheusHandler::update_process_metrics();
+            let mut response = handle.render().into_response();
+            response.extensions_mut().insert(SkipLogging);
+
+            response
+        }),
+    )
 }
 
 #[cfg(test)]
@@ -42,6 +208,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn default_size_buckets_are_monotonically_increasing() {
+        assert!(!DEFAULT_SIZE_BUCKETS.is_empty());
+        for pair in DEFAULT_SIZE_BUCKETS.windows(2) {
+            assert!(pair[0] < pair[1], "buckets must be strictly increasing: {pair:?}");
+        }
+    }
+
+    #[test]
+    fn size_histograms_use_the_size_buckets() {
+        use crate::server::axum::layers::metrics::MetricsLayer;
+        use axum::body::Body;
+        use axum::http::{Request, Response};
+        use std::convert::Infallible;
+        use tower::{Layer, ServiceExt};
+
+        let recorder = PrometheusHandler::builder(DEFAULT_DURATION_BUCKETS, &[10.0, 100.0])
+            .unwrap()
+            .build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            let service = MetricsLayer {
+                service_name: "test".into(),
+            }
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("x".repeat(50))))
+            }));
+            futures::executor::block_on(service.oneshot(Request::post("/users").body(Body::from("hello")).unwrap()))
+                .unwrap();
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains(
+            r#"http_request_size_bytes_bucket{method="POST",path="/users",service="test",status="200",le="10"} 1"#
+        ));
+        assert!(rendered.contains(
+            r#"http_response_size_bytes_bucket{method="POST",path="/users",service="test",status="200",le="10"} 0"#
+        ));
+        assert!(rendered.contains(
+            r#"http_response_size_bytes_bucket{method="POST",path="/users",service="test",status="200",le="100"} 1"#
+        ));
+    }
+
     /// Single combined test for the whole handler lifecycle. `install_recorder`
     /// mutates a process-wide global, so we cannot run multiple tests against
     /// it in parallel — tarpaulin / `cargo test` would race. Keeping the
@@ -62,5 +271,40 @@ mod tests {
 
         let err = PrometheusHandler::get_handle_with_buckets(&[0.5, 1.0]).expect_err("third install must fail");
         assert!(matches!(err, ApiError::InternalServerError(_)));
+
+        let err = PrometheusHandler::get_handle_with_exemplars(&[0.5, 1.0]).expect_err("fourth install must fail");
+        assert!(matches!(err, ApiError::InternalServerError(_)));
+
+        let rendered = PrometheusHandler::render_openmetrics(&handle);
+        assert!(rendered.ends_with("# EOF\n"));
+
+        PrometheusHandler::register_process_metrics();
+        let rendered = handle.render();
+        assert!(rendered.contains("process_start_time_seconds"));
+        assert!(rendered.contains("process_uptime_seconds"));
+    }
+
+    #[tokio::test]
+    async fn metrics_router_renders_build_info() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            PrometheusHandler::register_build_info(&BuildInfo::new("myapp", "1.2.0").with_git_sha("abc123"));
+        });
+
+        let response = metrics_router::<()>(handle)
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.extensions().get::<SkipLogging>().is_some());
+
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#"build_info{service="myapp",version="1.2.0",git_sha="abc123"} 1"#));
     }
 }
@@ -0,0 +1,672 @@
+//! Static files handler for Axum
+//!
+//! [`StaticFilesHandler`] serves the files of a directory with their `Content-Type`, an `ETag`
+//! and a `Last-Modified` header (answering `304 Not Modified` to conditional requests) and a
+//! `Cache-Control` policy per extension. With [`StaticFilesHandler::with_precompressed`], the
+//! `.br` and `.gz` sidecar files are served to the clients accepting these encodings.
+//!
+//! In SPA mode ([`StaticFilesHandler::with_spa_fallback`]), the unmatched paths without extension
+//! return the index file so that the client-side router can handle them. The paths under an API
+//! prefix ([`StaticFilesHandler::with_api_prefix`]) keep returning the standard JSON 404 error.
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::handlers::static_files::StaticFilesHandler;
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/api/users", get(|| async { "users" }))
+//!     .merge(
+//!         StaticFilesHandler::new("./dist")
+//!             .with_spa_fallback()
+//!             .with_api_prefix("/api")
+//!             .with_precompressed()
+//!             .with_cache_control(&["js", "css", "woff2"], "public, max-age=31536000, immutable")
+//!             .router(),
+//!     );
+//! ```
+
+use crate::server::axum::response::{ApiError, HTTP_DATE_FORMAT, is_not_modified};
+use axum::Router;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_util::io::ReaderStream;
+
+/// Default index file
+pub const STATIC_FILES_DEFAULT_INDEX: &str = "index.html";
+
+/// Default `Cache-Control` of the files without a specific policy
+pub const STATIC_FILES_DEFAULT_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// Precompressed sidecar encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// `Content-Encoding` value
+    fn name(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// Extension of the sidecar file
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gz",
+        }
+    }
+}
+
+/// Static files handler
+#[derive(Debug, Clone)]
+pub struct StaticFilesHandler {
+    pub root: PathBuf,
+    pub index_file: String,
+    pub spa_fallback: bool,
+    pub api_prefixes: Vec<String>,
+    pub precompressed: bool,
+    pub cache_control: HashMap<String, String>,
+    pub default_cache_control: String,
+}
+
+impl StaticFilesHandler {
+    /// Serve the files of the `root` directory
+    ///
+    /// HTML files are served with `Cache-Control: no-cache` (revalidated with their `ETag`),
+    /// the other files with [`STATIC_FILES_DEFAULT_CACHE_CONTROL`].
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index_file: STATIC_FILES_DEFAULT_INDEX.to_string(),
+            spa_fallback: false,
+            api_prefixes: Vec::new(),
+            precompressed: false,
+            cache_control: HashMap::from([
+                ("html".to_string(), "no-cache".to_string()),
+                ("htm".to_string(), "no-cache".to_string()),
+            ]),
+            default_cache_control: STATIC_FILES_DEFAULT_CACHE_CONTROL.to_string(),
+        }
+    }
+
+    /// Set the index file served for the directories (`index.html` by default)
+    pub fn with_index_file(mut self, index_file: &str) -> Self {
+        self.index_file = index_file.to_string();
+        self
+    }
+
+    /// Serve the index file for the unmatched paths without extension (single-page application)
+    pub fn with_spa_fallback(mut self) -> Self {
+        self.spa_fallback = true;
+        self
+    }
+
+    /// Return the JSON 404 error instead of a file for the paths under this prefix (e.g. `/api`)
+    ///
+    /// The root prefix (`/` or an empty prefix) is ignored, as it would match every path.
+    pub fn with_api_prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        if !prefix.is_empty() {
+            self.api_prefixes.push(format!("/{prefix}"));
+        }
+        self
+    }
+
+    /// Serve the precompressed `.br` and `.gz` sidecar files when the client accepts them
+    pub fn with_precompressed(mut self) -> Self {
+        self.precompressed = true;
+        self
+    }
+
+    /// Set the `Cache-Control` of the files with one of these extensions
+    pub fn with_cache_control(mut self, extensions: &[&str], value: &str) -> Self {
+        for extension in extensions {
+            self.cache_control.insert(
+                extension.trim_start_matches('.').to_ascii_lowercase(),
+                value.to_string(),
+            );
+        }
+        self
+    }
+
+    /// Set the `Cache-Control` of the files without a specific policy
+    pub fn with_default_cache_control(mut self, value: &str) -> Self {
+        self.default_cache_control = value.to_string();
+        self
+    }
+
+    /// Router serving the files as fallback, to merge in the application router
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let handler = Arc::new(self.clone());
+
+        Router::new().fallback(move |request: Request| {
+            let handler = handler.clone();
+            async move { handler.serve(request).await }
+        })
+    }
+
+    /// Serve the file matching the request path
+    pub async fn serve(&self, request: Request) -> Response {
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return ApiError::MethodNotAllowed.into_response();
+        }
+
+        let path = request.uri().path();
+        if self.is_api_path(path) {
+            return ApiError::NotFound("Resource not found".to_string()).into_response();
+        }
+
+        let Some(file) = self.resolve(path).await else {
+            return ApiError::NotFound("Resource not found".to_string()).into_response();
+        };
+
+        match self.file_response(&file, request.method(), request.headers()).await {
+            Ok(response) => response,
+            Err(err) => err.into_response(),
+        }
+    }
+
+    /// Return true if the path is under an API prefix
+    fn is_api_path(&self, path: &str) -> bool {
+        self.api_prefixes.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// File to serve for the request path
+    async fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let relative = sanitize_path(path)?;
+        let mut file = self.root.join(&relative);
+
+        match tokio::fs::metadata(&file).await {
+            Ok(metadata) if metadata.is_dir() => {
+                file.push(&self.index_file);
+                if tokio::fs::metadata(&file)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    return Some(file);
+                }
+            }
+            Ok(metadata) if metadata.is_file() => return Some(file),
+            _ => {}
+        }
+
+        if self.spa_fallback && relative.extension().is_none() {
+            let index = self.root.join(&self.index_file);
+            if tokio::fs::metadata(&index)
+                .await
+                .is_ok_and(|metadata| metadata.is_file())
+            {
+                return Some(index);
+            }
+        }
+
+        None
+    }
+
+    /// Response of a file, or `304 Not Modified` if the client copy is fresh
+    async fn file_response(&self, file: &Path, method: &Method, headers: &HeaderMap) -> Result<Response, ApiError> {
+        let extension = file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+
+        let (served, encoding) = self.select_encoding(file, headers).await;
+        let metadata = tokio::fs::metadata(&served)
+            .await
+            .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+        let etag = entity_tag(metadata.len(), metadata.modified().ok(), encoding);
+
+        let mut response_headers = HeaderMap::new();
+        let cache_control = self
+            .cache_control
+            .get(&extension)
+            .unwrap_or(&self.default_cache_control);
+        insert_header(&mut response_headers, header::CACHE_CONTROL, cache_control);
+        insert_header(&mut response_headers, header::ETAG, &etag);
+        if let Some(modified) = modified {
+            insert_header(
+                &mut response_headers,
+                header::LAST_MODIFIED,
+                &modified.format(HTTP_DATE_FORMAT).to_string(),
+            );
+        }
+        if self.precompressed {
+            response_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+
+        if is_not_modified(headers, Some(&etag), modified) {
+            return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+        }
+
+        response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type(&extension)));
+        if let Some(encoding) = encoding {
+            response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+        }
+        response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(metadata.len()));
+
+        if method == Method::HEAD {
+            return Ok((StatusCode::OK, response_headers, Body::empty()).into_response());
+        }
+
+        // The file is streamed, not loaded in memory
+        let content = tokio::fs::File::open(&served)
+            .await
+            .map_err(|err| ApiError::InternalServerError(err.to_string()))?;
+
+        Ok((
+            StatusCode::OK,
+            response_headers,
+            Body::from_stream(ReaderStream::new(content)),
+        )
+            .into_response())
+    }
+
+    /// Precompressed sidecar accepted by the client (Brotli first), or the file itself
+    async fn select_encoding(&self, file: &Path, headers: &HeaderMap) -> (PathBuf, Option<Encoding>) {
+        if self.precompressed {
+            let accepted = headers
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+
+            for encoding in [Encoding::Brotli, Encoding::Gzip] {
+                if !accepts_encoding(accepted, encoding.name()) {
+                    continue;
+                }
+
+                let mut sidecar = file.as_os_str().to_owned();
+                sidecar.push(".");
+                sidecar.push(encoding.extension());
+                let sidecar = PathBuf::from(sidecar);
+                if tokio::fs::metadata(&sidecar)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    return (sidecar, Some(encoding));
+                }
+            }
+        }
+
+        (file.to_path_buf(), None)
+    }
+}
+
+/// Decode the request path and reject the parent directory components
+///
+/// The path is decoded once: a double-encoded `%252e%252e` stays a literal file name. Backslashes
+/// are rejected, as they are separators on Windows.
+fn sanitize_path(path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(path)?;
+    if decoded.contains('\\') {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(segment) => relative.push(segment),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    Some(relative)
+}
+
+/// Decode the percent-encoded bytes of a path
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            let byte = u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    let decoded = String::from_utf8(decoded).ok()?;
+    (!decoded.contains('\0')).then_some(decoded)
+}
+
+/// Return true if the `Accept-Encoding` header accepts the encoding (q > 0)
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        name.eq_ignore_ascii_case(encoding) && quality > 0.0
+    })
+}
+
+/// Entity tag built from the size, the modification time and the encoding of the file
+fn entity_tag(len: u64, modified: Option<SystemTime>, encoding: Option<Encoding>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+
+    match encoding {
+        Some(encoding) => format!("\"{len:x}-{modified:x}-{}\"", encoding.extension()),
+        None => format!("\"{len:x}-{modified:x}\""),
+    }
+}
+
+/// Insert a header, ignoring the invalid values
+fn insert_header(headers: &mut HeaderMap, name: header::HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+/// Content type of a file extension
+fn content_type(extension: &str) -> &'static str {
+    match extension {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    fn fixtures(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("static-files-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("assets")).unwrap();
+        std::fs::write(root.join("index.html"), "<h1>App</h1>").unwrap();
+        std::fs::write(root.join("assets/app.js"), "console.log('app');").unwrap();
+        std::fs::write(root.join("assets/app.js.br"), "brotli").unwrap();
+        std::fs::write(root.join("assets/app.js.gz"), "gzip").unwrap();
+        std::fs::write(root.join("secret.txt"), "secret").unwrap();
+        root
+    }
+
+    async fn send(router: &Router, method: Method, uri: &str, headers: &[(&str, &str)]) -> Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_static_files_headers_and_conditional_requests() {
+        let root = fixtures("headers");
+        let router: Router = StaticFilesHandler::new(&root)
+            .with_cache_control(&["js"], "public, max-age=31536000, immutable")
+            .router();
+
+        let response = send(&router, Method::GET, "/", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        assert!(response.headers().contains_key(header::LAST_MODIFIED));
+        assert_eq!(body(response).await, "<h1>App</h1>");
+
+        let response = send(&router, Method::GET, "/assets/app.js", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let last_modified = response.headers()[header::LAST_MODIFIED].to_str().unwrap().to_string();
+
+        let response = send(&router, Method::GET, "/assets/app.js", &[("if-none-match", &etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+        let response = send(
+            &router,
+            Method::GET,
+            "/assets/app.js",
+            &[("if-modified-since", &last_modified)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = send(
+            &router,
+            Method::GET,
+            "/assets/app.js",
+            &[("if-none-match", "\"other\"")],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&router, Method::HEAD, "/secret.txt", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "6");
+        assert_eq!(body(response).await, "");
+
+        let response = send(&router, Method::POST, "/secret.txt", &[]).await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_static_files_streams_large_files() {
+        let root = fixtures("large");
+        let content = "0123456789".repeat(10_000);
+        std::fs::write(root.join("large.txt"), &content).unwrap();
+        let router: Router = StaticFilesHandler::new(&root).router();
+
+        let response = send(&router, Method::GET, "/large.txt", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "100000");
+        let body = axum::body::to_bytes(response.into_body(), 200_000).await.unwrap();
+        assert_eq!(body, content.as_bytes());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_static_files_precompressed() {
+        let root = fixtures("precompressed");
+        let router: Router = StaticFilesHandler::new(&root).with_precompressed().router();
+
+        let response = send(
+            &router,
+            Method::GET,
+            "/assets/app.js",
+            &[("accept-encoding", "gzip, br")],
+        )
+        .await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(body(response).await, "brotli");
+
+        let response = send(
+            &router,
+            Method::GET,
+            "/assets/app.js",
+            &[("accept-encoding", "gzip, br;q=0")],
+        )
+        .await;
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(body(response).await, "gzip");
+
+        let response = send(&router, Method::GET, "/assets/app.js", &[]).await;
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(body(response).await, "console.log('app');");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_static_files_spa_fallback_and_api_prefix() {
+        let root = fixtures("spa");
+        let router: Router = Router::new()
+            .route("/api/users", axum::routing::get(|| async { "users" }))
+            .merge(
+                StaticFilesHandler::new(&root)
+                    .with_spa_fallback()
+                    .with_api_prefix("/api/")
+                    .router(),
+            );
+
+        let response = send(&router, Method::GET, "/users/42/edit", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "<h1>App</h1>");
+
+        let response = send(&router, Method::GET, "/assets/missing.js", &[]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&router, Method::GET, "/api/users", &[]).await;
+        assert_eq!(body(response).await, "users");
+
+        let response = send(&router, Method::GET, "/api/unknown", &[]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert!(body(response).await.contains("\"code\":404"));
+
+        let response = send(&router, Method::GET, "/apiary", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_sanitize_path() {
+        assert_eq!(sanitize_path("/assets/app.js"), Some(PathBuf::from("assets/app.js")));
+        assert_eq!(sanitize_path("/my%20file.txt"), Some(PathBuf::from("my file.txt")));
+        assert_eq!(sanitize_path("/./index.html"), Some(PathBuf::from("index.html")));
+        assert_eq!(sanitize_path("/../etc/passwd"), None);
+        assert_eq!(sanitize_path("/assets/%2e%2e/%2e%2e/etc/passwd"), None);
+        assert_eq!(sanitize_path("/bad%zz"), None);
+        assert_eq!(sanitize_path("/nul%00"), None);
+        assert_eq!(sanitize_path("/nul%00.html"), None);
+        assert_eq!(sanitize_path("/%2e%2e%2fetc%2fpasswd"), None);
+        assert_eq!(sanitize_path("/%2E%2E%2Fetc%2Fpasswd"), None);
+        assert_eq!(sanitize_path("/..%5cetc%5cpasswd"), None);
+        assert_eq!(sanitize_path("/assets\\..\\secret.txt"), None);
+        assert_eq!(sanitize_path("/%2e%2e"), None);
+
+        // Decoded once: a double-encoded path is a literal file name
+        assert_eq!(
+            sanitize_path("/%252e%252e%252fsecret.txt"),
+            Some(PathBuf::from("%2e%2e%2fsecret.txt"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_files_rejects_encoded_traversal() {
+        let root = fixtures("traversal");
+        // `secret.txt` is outside of the served directory
+        let router: Router = StaticFilesHandler::new(root.join("assets")).router();
+
+        let response = send(&router, Method::GET, "/app.js", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for uri in [
+            "/../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/%2e%2e%2fsecret.txt",
+            "/..%2fsecret.txt",
+            "/..%5csecret.txt",
+            "/%252e%252e%252fsecret.txt",
+            "/app.js%00.html",
+        ] {
+            let response = send(&router, Method::GET, uri, &[]).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "uri: {uri}");
+        }
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_static_files_ignores_the_root_api_prefix() {
+        let root = fixtures("root-prefix");
+        let router: Router = StaticFilesHandler::new(&root)
+            .with_spa_fallback()
+            .with_api_prefix("/")
+            .with_api_prefix("")
+            .with_api_prefix("api")
+            .router();
+
+        let response = send(&router, Method::GET, "/users/42", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "<h1>App</h1>");
+
+        let response = send(&router, Method::GET, "/api/users", &[]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
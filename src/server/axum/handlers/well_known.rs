@@ -0,0 +1,303 @@
+//! `robots.txt` and well-known endpoints handler for Axum
+//!
+//! [`WellKnownHandler`] answers the requests of the crawlers and the security scanners:
+//!
+//! - `/robots.txt`: disallows indexing by default ([`ROBOTS_TXT_DISALLOW_ALL`])
+//! - `/.well-known/security.txt`: [`SecurityTxt`] contact information (RFC 9116), if set
+//! - `/.well-known/change-password`: redirection to the password change page, if set
+//!
+//! ```rust
+//! use api_tools::server::axum::handlers::well_known::{SecurityTxt, WellKnownHandler};
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use axum::{Router, routing::get};
+//!
+//! let expires = UtcDateTime::from_rfc3339("2030-01-01T00:00:00Z").unwrap();
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .merge(
+//!         WellKnownHandler::new()
+//!             .with_security_txt(SecurityTxt::new("mailto:security@example.com", expires))
+//!             .with_change_password("https://example.com/account/password")
+//!             .router(),
+//!     );
+//! ```
+
+use crate::value_objects::datetime::UtcDateTime;
+use axum::Router;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use std::fmt;
+
+/// `robots.txt` disallowing the indexing of the whole site
+pub const ROBOTS_TXT_DISALLOW_ALL: &str = "User-agent: *\nDisallow: /\n";
+
+/// `robots.txt` allowing the indexing of the whole site
+pub const ROBOTS_TXT_ALLOW_ALL: &str = "User-agent: *\nAllow: /\n";
+
+/// Content type of the text files
+const TEXT_PLAIN_UTF_8: &str = "text/plain; charset=utf-8";
+
+/// `security.txt` file (RFC 9116)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityTxt {
+    pub contacts: Vec<String>,
+    pub expires: UtcDateTime,
+    pub encryption: Vec<String>,
+    pub acknowledgments: Vec<String>,
+    pub preferred_languages: Vec<String>,
+    pub canonical: Vec<String>,
+    pub policy: Vec<String>,
+    pub hiring: Vec<String>,
+}
+
+impl SecurityTxt {
+    /// Create a `security.txt` with its two required fields
+    ///
+    /// The contact is a URI (`mailto:security@example.com`, `https://example.com/security`).
+    pub fn new(contact: &str, expires: UtcDateTime) -> Self {
+        Self {
+            contacts: vec![contact.to_string()],
+            expires,
+            encryption: Vec::new(),
+            acknowledgments: Vec::new(),
+            preferred_languages: Vec::new(),
+            canonical: Vec::new(),
+            policy: Vec::new(),
+            hiring: Vec::new(),
+        }
+    }
+
+    /// Add a contact
+    pub fn with_contact(mut self, contact: &str) -> Self {
+        self.contacts.push(contact.to_string());
+        self
+    }
+
+    /// Add the URI of an encryption key
+    pub fn with_encryption(mut self, uri: &str) -> Self {
+        self.encryption.push(uri.to_string());
+        self
+    }
+
+    /// Add the URI of an acknowledgments page
+    pub fn with_acknowledgments(mut self, uri: &str) -> Self {
+        self.acknowledgments.push(uri.to_string());
+        self
+    }
+
+    /// Add a preferred language (e.g. `en`)
+    pub fn with_preferred_language(mut self, language: &str) -> Self {
+        self.preferred_languages.push(language.to_string());
+        self
+    }
+
+    /// Add the canonical URI of the file
+    pub fn with_canonical(mut self, uri: &str) -> Self {
+        self.canonical.push(uri.to_string());
+        self
+    }
+
+    /// Add the URI of the vulnerability disclosure policy
+    pub fn with_policy(mut self, uri: &str) -> Self {
+        self.policy.push(uri.to_string());
+        self
+    }
+
+    /// Add the URI of a security-related job page
+    pub fn with_hiring(mut self, uri: &str) -> Self {
+        self.hiring.push(uri.to_string());
+        self
+    }
+}
+
+impl fmt::Display for SecurityTxt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for contact in &self.contacts {
+            writeln!(f, "Contact: {contact}")?;
+        }
+        writeln!(f, "Expires: {}", self.expires.value().format("%Y-%m-%dT%H:%M:%SZ"))?;
+        for uri in &self.encryption {
+            writeln!(f, "Encryption: {uri}")?;
+        }
+        for uri in &self.acknowledgments {
+            writeln!(f, "Acknowledgments: {uri}")?;
+        }
+        if !self.preferred_languages.is_empty() {
+            writeln!(f, "Preferred-Languages: {}", self.preferred_languages.join(", "))?;
+        }
+        for uri in &self.canonical {
+            writeln!(f, "Canonical: {uri}")?;
+        }
+        for uri in &self.policy {
+            writeln!(f, "Policy: {uri}")?;
+        }
+        for uri in &self.hiring {
+            writeln!(f, "Hiring: {uri}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `robots.txt` and well-known endpoints
+#[derive(Debug, Clone)]
+pub struct WellKnownHandler {
+    pub robots_txt: String,
+    pub security_txt: Option<SecurityTxt>,
+    pub change_password_url: Option<String>,
+}
+
+impl Default for WellKnownHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WellKnownHandler {
+    /// Serve a `robots.txt` disallowing indexing, without `security.txt` nor `change-password`
+    pub fn new() -> Self {
+        Self {
+            robots_txt: ROBOTS_TXT_DISALLOW_ALL.to_string(),
+            security_txt: None,
+            change_password_url: None,
+        }
+    }
+
+    /// Set the content of `/robots.txt`
+    pub fn with_robots_txt(mut self, content: &str) -> Self {
+        self.robots_txt = content.to_string();
+        self
+    }
+
+    /// Serve `/.well-known/security.txt`
+    pub fn with_security_txt(mut self, security_txt: SecurityTxt) -> Self {
+        self.security_txt = Some(security_txt);
+        self
+    }
+
+    /// Redirect `/.well-known/change-password` to this URL
+    pub fn with_change_password(mut self, url: &str) -> Self {
+        self.change_password_url = Some(url.to_string());
+        self
+    }
+
+    /// Routes of the well-known endpoints, to merge in the application router
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let robots_txt = self.robots_txt.clone();
+        let mut router = Router::new().route(
+            "/robots.txt",
+            get(move || async move { ([(header::CONTENT_TYPE, TEXT_PLAIN_UTF_8)], robots_txt) }),
+        );
+
+        if let Some(security_txt) = &self.security_txt {
+            let security_txt = security_txt.to_string();
+            router = router.route(
+                "/.well-known/security.txt",
+                get(move || async move { ([(header::CONTENT_TYPE, TEXT_PLAIN_UTF_8)], security_txt) }),
+            );
+        }
+
+        if let Some(url) = &self.change_password_url {
+            let url = url.clone();
+            router = router.route(
+                "/.well-known/change-password",
+                get(move || async move { (StatusCode::FOUND, [(header::LOCATION, url)]).into_response() }),
+            );
+        }
+
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::response::Response;
+    use tower::ServiceExt;
+
+    async fn get(router: &Router, uri: &str) -> Response {
+        router
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_security_txt_display() {
+        let expires = UtcDateTime::from_rfc3339("2030-01-01T12:00:00Z").unwrap();
+        let security_txt = SecurityTxt::new("mailto:security@example.com", expires)
+            .with_contact("https://example.com/security")
+            .with_encryption("https://example.com/pgp-key.txt")
+            .with_preferred_language("en")
+            .with_preferred_language("fr")
+            .with_canonical("https://example.com/.well-known/security.txt")
+            .with_policy("https://example.com/security-policy");
+
+        assert_eq!(
+            security_txt.to_string(),
+            "Contact: mailto:security@example.com\n\
+             Contact: https://example.com/security\n\
+             Expires: 2030-01-01T12:00:00Z\n\
+             Encryption: https://example.com/pgp-key.txt\n\
+             Preferred-Languages: en, fr\n\
+             Canonical: https://example.com/.well-known/security.txt\n\
+             Policy: https://example.com/security-policy\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_well_known_default_router() {
+        let router: Router = WellKnownHandler::new().router();
+
+        let response = get(&router, "/robots.txt").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], TEXT_PLAIN_UTF_8);
+        assert_eq!(body(response).await, ROBOTS_TXT_DISALLOW_ALL);
+
+        let response = get(&router, "/.well-known/security.txt").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get(&router, "/.well-known/change-password").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_well_known_router() {
+        let expires = UtcDateTime::from_rfc3339("2030-01-01T00:00:00Z").unwrap();
+        let router: Router = WellKnownHandler::new()
+            .with_robots_txt(ROBOTS_TXT_ALLOW_ALL)
+            .with_security_txt(SecurityTxt::new("mailto:security@example.com", expires))
+            .with_change_password("https://example.com/account/password")
+            .router();
+
+        let response = get(&router, "/robots.txt").await;
+        assert_eq!(body(response).await, ROBOTS_TXT_ALLOW_ALL);
+
+        let response = get(&router, "/.well-known/security.txt").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], TEXT_PLAIN_UTF_8);
+        assert_eq!(
+            body(response).await,
+            "Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+        );
+
+        let response = get(&router, "/.well-known/change-password").await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://example.com/account/password"
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Localization of the error messages
+//!
+//! A [`MessageCatalog`] holds the translations of messages (key/value maps) per locale. The keys
+//! of the error messages are the English messages themselves (e.g. `Too many requests`), so that
+//! the errors of the crate and of the applications are translated without changing them.
+//!
+//! [`LocaleLayer`] negotiates the locale of each request from its `Accept-Language` header among
+//! the locales of the catalog, stores it in the [`RequestStore`] ([`Locale`]) and translates the
+//! message of the JSON error responses (`{ "code": ..., "message": ... }`), setting the
+//! `Content-Language` header. Handlers translate their own messages with the [`Localizer`]
+//! extractor.
+//!
+//! Translations are resolved with a fallback chain: negotiated locale (`fr-CA`), its language
+//! (`fr`), default locale, and finally the key itself.
+//!
+//! ```rust
+//! use api_tools::server::axum::i18n::{LocaleLayer, Localizer, MessageCatalog};
+//! use axum::{Router, routing::get};
+//!
+//! let catalog = MessageCatalog::new("en")
+//!     .with_messages("en", [("welcome", "Welcome {name}!")])
+//!     .with_messages(
+//!         "fr",
+//!         [("welcome", "Bienvenue {name} !"), ("Resource Not Found", "Ressource introuvable")],
+//!     );
+//!
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/",
+//!         get(|localizer: Localizer| async move { localizer.format("welcome", &[("name", "Alice")]) }),
+//!     )
+//!     .layer(LocaleLayer::new(catalog));
+//! ```
+
+use crate::server::axum::request_store::{Locale, RequestStore};
+use crate::server::axum::response::ApiError;
+use axum::body::{Body, HttpBody};
+use axum::extract::FromRequestParts;
+use axum::http::header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Default maximum size of the translated error bodies (64 KiB)
+pub const I18N_DEFAULT_BODY_MAX_SIZE: usize = 64 * 1_024;
+
+/// Messages per locale
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    default_locale: String,
+    messages: HashMap<String, HashMap<String, String>>,
+}
+
+impl MessageCatalog {
+    /// Create an empty catalog with a default locale (e.g. `en`)
+    pub fn new(default_locale: &str) -> Self {
+        Self {
+            default_locale: default_locale.to_string(),
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Add messages to a locale
+    pub fn with_messages<K, V>(mut self, locale: &str, messages: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.messages
+            .entry(normalize(locale))
+            .or_default()
+            .extend(messages.into_iter().map(|(key, value)| (key.into(), value.into())));
+        self
+    }
+
+    /// Default locale
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Return true if the catalog has messages for a locale
+    pub fn has_locale(&self, locale: &str) -> bool {
+        self.messages.contains_key(&normalize(locale))
+    }
+
+    /// Negotiate the best locale of the catalog from an `Accept-Language` header value
+    ///
+    /// Languages are tried by decreasing quality: exact locale (`fr-CA`), then its language
+    /// (`fr`), then any locale of the same language (`fr-FR`). The default locale is returned if
+    /// none matches.
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::i18n::MessageCatalog;
+    ///
+    /// let catalog = MessageCatalog::new("en")
+    ///     .with_messages("en", [("hello", "Hello")])
+    ///     .with_messages("fr", [("hello", "Bonjour")]);
+    ///
+    /// assert_eq!(catalog.negotiate("de-DE, fr-CA;q=0.8, en;q=0.5"), "fr");
+    /// assert_eq!(catalog.negotiate("de"), "en");
+    /// ```
+    pub fn negotiate(&self, accept_language: &str) -> String {
+        let mut ranges = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let tag = params.next()?.trim();
+                let quality = params
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |quality| quality.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && tag != "*" && quality > 0.0).then(|| (normalize(tag), quality))
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (tag, _) in ranges {
+            if self.messages.contains_key(&tag) {
+                return tag;
+            }
+            let language = language(&tag);
+            if self.messages.contains_key(language) {
+                return language.to_string();
+            }
+            let mut same_language = self
+                .messages
+                .keys()
+                .filter(|locale| self::language(locale) == language)
+                .collect::<Vec<_>>();
+            same_language.sort();
+            if let Some(locale) = same_language.first() {
+                return locale.to_string();
+            }
+        }
+
+        self.default_locale.clone()
+    }
+
+    /// Translate a message, following the fallback chain of the locale
+    pub fn translate(&self, locale: &str, key: &str) -> Option<&str> {
+        let locale = normalize(locale);
+        let default_locale = normalize(&self.default_locale);
+
+        [locale.as_str(), language(&locale), default_locale.as_str()]
+            .into_iter()
+            .find_map(|locale| self.messages.get(locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// Translate a message and replace its `{name}` placeholders, or return the key if there is no
+    /// translation
+    pub fn format(&self, locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+        let mut message = self.translate(locale, key).unwrap_or(key).to_string();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{name}}}"), value);
+        }
+
+        message
+    }
+}
+
+/// Normalize a language tag (`fr_ca` => `fr-CA`)
+fn normalize(tag: &str) -> String {
+    let mut subtags = tag.trim().split(['-', '_']);
+    let mut normalized = subtags.next().unwrap_or_default().to_ascii_lowercase();
+    for subtag in subtags {
+        normalized.push('-');
+        if subtag.len() == 2 {
+            normalized.push_str(&subtag.to_ascii_uppercase());
+        } else {
+            normalized.push_str(subtag);
+        }
+    }
+
+    normalized
+}
+
+/// Language of a normalized tag (`fr-CA` => `fr`)
+fn language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// `Localizer` extractor translates messages in the locale negotiated by the [`LocaleLayer`]
+///
+/// The rejection is a `500` error if the `LocaleLayer` is missing.
+#[derive(Debug, Clone)]
+pub struct Localizer {
+    catalog: Arc<MessageCatalog>,
+    locale: String,
+}
+
+impl Localizer {
+    /// Negotiated locale
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Translate a message, or return the key if there is no translation
+    pub fn translate(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// Translate a message and replace its `{name}` placeholders
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        self.catalog.format(&self.locale, key, args)
+    }
+}
+
+impl<S> FromRequestParts<S> for Localizer
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Localizer>()
+            .cloned()
+            .ok_or_else(|| ApiError::InternalServerError("LocaleLayer is missing".to_string()))
+    }
+}
+
+/// Layer negotiating the locale of the requests and translating the JSON error messages
+#[derive(Clone, Debug)]
+pub struct LocaleLayer {
+    pub catalog: Arc<MessageCatalog>,
+    pub body_max_size: usize,
+}
+
+impl LocaleLayer {
+    /// Create a new `LocaleLayer`
+    pub fn new(catalog: MessageCatalog) -> Self {
+        Self {
+            catalog: Arc::new(catalog),
+            body_max_size: I18N_DEFAULT_BODY_MAX_SIZE,
+        }
+    }
+
+    /// Set the maximum size of the error bodies to translate (larger ones are kept)
+    pub fn with_body_max_size(mut self, body_max_size: usize) -> Self {
+        self.body_max_size = body_max_size;
+        self
+    }
+}
+
+impl<S> Layer<S> for LocaleLayer {
+    type Service = LocaleMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LocaleMiddleware {
+            inner,
+            catalog: self.catalog.clone(),
+            body_max_size: self.body_max_size,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LocaleMiddleware<S> {
+    inner: S,
+    catalog: Arc<MessageCatalog>,
+    body_max_size: usize,
+}
+
+impl<S> Service<Request<Body>> for LocaleMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let accept_language = request
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let locale = self.catalog.negotiate(accept_language);
+
+        RequestStore::from_extensions_mut(request.extensions_mut()).insert(Locale(locale.clone()));
+        request.extensions_mut().insert(Localizer {
+            catalog: self.catalog.clone(),
+            locale: locale.clone(),
+        });
+
+        let catalog = self.catalog.clone();
+        let body_max_size = self.body_max_size;
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            if let Ok(value) = HeaderValue::from_str(&locale) {
+                response.headers_mut().insert(CONTENT_LANGUAGE, value);
+            }
+
+            Ok(translate_error(response, &catalog, &locale, body_max_size).await)
+        })
+    }
+}
+
+/// Translate the message of a JSON error response
+async fn translate_error(response: Response, catalog: &MessageCatalog, locale: &str, body_max_size: usize) -> Response {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(mime::APPLICATION_JSON.as_ref()));
+    let size = response.body().size_hint().exact();
+    if !(response.status().is_client_error() || response.status().is_server_error())
+        || !is_json
+        || size.is_none_or(|size| size > body_max_size as u64)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, body_max_size).await {
+        Ok(body) => body,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    let translated = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|mut value| {
+            let message = value.get("message")?.as_str()?;
+            let translation = catalog.translate(locale, message)?.to_string();
+            value["message"] = serde_json::Value::String(translation);
+            serde_json::to_vec(&value).ok()
+        });
+
+    match translated {
+        Some(body) => {
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(body))
+        }
+        None => Response::from_parts(parts, Body::from(body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::extractors::Path;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn catalog() -> MessageCatalog {
+        MessageCatalog::new("en")
+            .with_messages("en", [("welcome", "Welcome {name}!")])
+            .with_messages(
+                "fr",
+                [
+                    ("welcome", "Bienvenue {name} !"),
+                    ("Too many requests", "Trop de requêtes"),
+                ],
+            )
+            .with_messages("fr_ca", [("welcome", "Bienvenue {name}!")])
+            .with_messages("de-DE", [("welcome", "Willkommen {name}!")])
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let catalog = catalog();
+        assert!(catalog.has_locale("fr-CA"));
+        assert_eq!(catalog.negotiate(""), "en");
+        assert_eq!(catalog.negotiate("fr-CA"), "fr-CA");
+        assert_eq!(catalog.negotiate("fr-BE, en;q=0.9"), "fr");
+        assert_eq!(catalog.negotiate("en;q=0.5, de"), "de-DE");
+        assert_eq!(catalog.negotiate("it, *;q=0.1"), "en");
+        assert_eq!(catalog.negotiate("fr;q=0, en"), "en");
+    }
+
+    #[test]
+    fn test_translate_fallback_chain() {
+        let catalog = catalog();
+        assert_eq!(catalog.translate("fr-CA", "welcome"), Some("Bienvenue {name}!"));
+        assert_eq!(
+            catalog.translate("fr-CA", "Too many requests"),
+            Some("Trop de requêtes")
+        );
+        assert_eq!(catalog.translate("it", "welcome"), Some("Welcome {name}!"));
+        assert_eq!(catalog.translate("fr", "unknown"), None);
+        assert_eq!(
+            catalog.format("fr", "welcome", &[("name", "Alice")]),
+            "Bienvenue Alice !"
+        );
+        assert_eq!(
+            catalog.format("fr", "unknown {name}", &[("name", "Alice")]),
+            "unknown Alice"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_locale_layer() {
+        let app = Router::new()
+            .route(
+                "/welcome",
+                get(|localizer: Localizer, store: RequestStore| async move {
+                    format!(
+                        "{} ({:?})",
+                        localizer.format("welcome", &[("name", "Alice")]),
+                        store.get::<Locale>()
+                    )
+                }),
+            )
+            .route("/limited", get(|| async { ApiError::TooManyRequests }))
+            .route("/users/{id}", get(|Path(id): Path<u32>| async move { id.to_string() }))
+            .layer(LocaleLayer::new(catalog()));
+        let call = |uri: &'static str| {
+            app.clone().oneshot(
+                Request::get(uri)
+                    .header(ACCEPT_LANGUAGE, "fr-FR,fr;q=0.9")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = call("/welcome").await.unwrap();
+        assert_eq!(response.headers().get(CONTENT_LANGUAGE).unwrap(), "fr");
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], r#"Bienvenue Alice ! (Some(Locale("fr")))"#.as_bytes());
+
+        let response = call("/limited").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], r#"{"code":429,"message":"Trop de requêtes"}"#.as_bytes());
+
+        // Messages without translation are kept
+        let response = call("/users/abc").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("Cannot parse"));
+    }
+}
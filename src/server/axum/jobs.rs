@@ -0,0 +1,449 @@
+//! Long-running jobs (e.g. large exports)
+//!
+//! [`JobManager`] stores the state of the jobs (status, progress, result location and error) and
+//! runs them with the workers of a [`TaskQueue`]. Its router exposes the polling pattern:
+//!
+//! - `POST {path}`: submits a job with the parameters of the JSON body and answers
+//!   `202 Accepted`, with the job state and its URL in the `Location` header
+//! - `GET {path}/{id}`: state of the job in the standard JSON envelope, `404` if it is unknown
+//!
+//! The states are kept in memory. The cleanup policy removes the finished jobs after a retention
+//! period (see [`JobManager::cleanup_job`], to run with the `JobScheduler`).
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::jobs::JobManager;
+//! use api_tools::server::axum::scheduler::{JobScheduler, Trigger};
+//! use api_tools::server::axum::shutdown::Shutdown;
+//! use api_tools::server::axum::tasks::{Task, TaskQueue};
+//! use axum::Router;
+//! use serde::{Deserialize, Serialize};
+//! use std::time::Duration;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct UsersExport {
+//!     format: String,
+//! }
+//!
+//! impl Task for UsersExport {
+//!     const QUEUE: &'static str = "users-exports";
+//! }
+//!
+//! let shutdown = Shutdown::new();
+//! let jobs = JobManager::new(TaskQueue::memory()).with_retention(Duration::from_secs(24 * 3_600));
+//!
+//! jobs.worker(|export: UsersExport, progress| async move {
+//!     progress.set(50);
+//!     Ok::<_, String>(format!("https://files.example.com/exports/users.{}", export.format))
+//! })
+//! .with_shutdown(shutdown.clone())
+//! .spawn();
+//!
+//! JobScheduler::new().with_shutdown(shutdown).spawn(Trigger::every(Duration::from_secs(3_600)), jobs.cleanup_job());
+//!
+//! let app: Router = Router::new().merge(jobs.router::<UsersExport, ()>("/exports"));
+//! ```
+
+use crate::server::axum::extractors::{Json, Path};
+use crate::server::axum::response::{ApiError, ApiSuccess};
+use crate::server::axum::scheduler::Job;
+use crate::server::axum::tasks::{MemoryBackend, Task, TaskBackend, TaskError, TaskQueue, Worker};
+use axum::Router;
+use axum::http::header::LOCATION;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Default retention of the finished jobs
+pub const JOB_DEFAULT_RETENTION: Duration = Duration::from_secs(24 * 3_600);
+
+/// Status of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    /// Return true if the job is finished
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// State of a job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobState {
+    pub id: Uuid,
+    pub status: JobStatus,
+
+    /// Progress, in percent
+    pub progress: u8,
+
+    /// Location of the result (e.g. the URL of the exported file)
+    pub result_location: Option<String>,
+
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Task wrapping the parameters of a job, in the queue of the parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobTask<T> {
+    pub job_id: Uuid,
+    pub params: T,
+}
+
+impl<T: Task> Task for JobTask<T> {
+    const QUEUE: &'static str = T::QUEUE;
+}
+
+type JobStates = Arc<Mutex<HashMap<Uuid, JobState>>>;
+
+/// Progress reporter of a running job
+#[derive(Debug, Clone)]
+pub struct JobProgress {
+    id: Uuid,
+    jobs: JobStates,
+}
+
+impl JobProgress {
+    /// Job ID
+    pub fn job_id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Set the progress (capped at 100%)
+    pub fn set(&self, progress: u8) {
+        update(&self.jobs, self.id, |state| state.progress = progress.min(100));
+    }
+}
+
+/// Jobs manager, cheap to clone
+pub struct JobManager<B: TaskBackend = MemoryBackend> {
+    queue: TaskQueue<B>,
+    jobs: JobStates,
+    retention: Duration,
+}
+
+impl<B: TaskBackend> Clone for JobManager<B> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+            jobs: self.jobs.clone(),
+            retention: self.retention,
+        }
+    }
+}
+
+impl<B: TaskBackend> JobManager<B> {
+    /// Create a new `JobManager` running the jobs with the workers of a task queue
+    pub fn new(queue: TaskQueue<B>) -> Self {
+        Self {
+            queue,
+            jobs: Arc::default(),
+            retention: JOB_DEFAULT_RETENTION,
+        }
+    }
+
+    /// Set the retention of the finished jobs
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Submit a job
+    pub async fn submit<T: Task>(&self, params: T) -> Result<JobState, TaskError> {
+        let now = Utc::now();
+        let state = JobState {
+            id: Uuid::new_v4(),
+            status: JobStatus::Pending,
+            progress: 0,
+            result_location: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.jobs
+            .lock()
+            .map_err(|err| TaskError::Backend(err.to_string()))?
+            .insert(state.id, state.clone());
+
+        let task = JobTask {
+            job_id: state.id,
+            params,
+        };
+        if let Err(err) = self.queue.enqueue(&task).await {
+            self.remove(state.id);
+            return Err(err);
+        }
+
+        Ok(state)
+    }
+
+    /// State of a job
+    pub fn get(&self, id: Uuid) -> Option<JobState> {
+        self.jobs.lock().ok()?.get(&id).cloned()
+    }
+
+    /// Remove a job
+    pub fn remove(&self, id: Uuid) -> Option<JobState> {
+        self.jobs.lock().ok()?.remove(&id)
+    }
+
+    /// Remove the jobs finished for longer than the retention, returning the number of removed
+    /// jobs
+    pub fn cleanup(&self) -> usize {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return 0;
+        };
+        let retention = chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+        let expiration = Utc::now()
+            .checked_sub_signed(retention)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let count = jobs.len();
+        jobs.retain(|_, state| !state.status.is_finished() || state.updated_at > expiration);
+
+        count - jobs.len()
+    }
+
+    /// Scheduler job running the cleanup
+    pub fn cleanup_job(&self) -> Job {
+        let manager = self.clone();
+
+        Job::new("jobs-cleanup", move || {
+            let removed = manager.cleanup();
+            async move {
+                if removed > 0 {
+                    info!(removed, "Finished jobs removed");
+                }
+                Ok::<_, String>(())
+            }
+        })
+    }
+
+    /// Create a worker running the jobs with parameters of type `T`
+    ///
+    /// The handler returns the location of the result. A failed attempt is retried by the task
+    /// queue and the job stays `failed` after the last retry.
+    pub fn worker<T, F, Fut, E>(&self, handler: F) -> Worker<JobTask<T>, B>
+    where
+        T: Task,
+        F: Fn(T, JobProgress) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: Display,
+    {
+        let jobs = self.jobs.clone();
+
+        self.queue.worker(move |task: JobTask<T>| {
+            let jobs = jobs.clone();
+            let id = task.job_id;
+            update(&jobs, id, |state| state.status = JobStatus::Running);
+            let future = handler(task.params, JobProgress { id, jobs: jobs.clone() });
+
+            async move {
+                let result = future.await.map_err(|err| err.to_string());
+                update(&jobs, id, |state| match &result {
+                    Ok(location) => {
+                        state.status = JobStatus::Succeeded;
+                        state.progress = 100;
+                        state.result_location = Some(location.clone());
+                        state.error = None;
+                    }
+                    Err(err) => {
+                        state.status = JobStatus::Failed;
+                        state.error = Some(err.clone());
+                    }
+                });
+
+                result.map(|_| ())
+            }
+        })
+    }
+
+    /// Router submitting the jobs (`POST {path}`) and serving their states (`GET {path}/{id}`)
+    pub fn router<T, S>(&self, path: &str) -> Router<S>
+    where
+        T: Task + Sync,
+        S: Clone + Send + Sync + 'static,
+    {
+        let path = path.trim_end_matches('/').to_string();
+        let submit_manager = self.clone();
+        let get_manager = self.clone();
+        let location_path = path.clone();
+
+        Router::new()
+            .route(
+                &path,
+                post(move |Json(params): Json<T>| async move { submit(submit_manager, params, &location_path).await }),
+            )
+            .route(
+                &format!("{path}/{{id}}"),
+                get(move |Path(id): Path<Uuid>| async move {
+                    get_manager
+                        .get(id)
+                        .map(|state| ApiSuccess::new(StatusCode::OK, state).enveloped())
+                        .ok_or_else(|| ApiError::NotFound("Job not found".to_string()))
+                }),
+            )
+    }
+}
+
+/// Submit a job, answering `202 Accepted` with the URL of the job in the `Location` header
+async fn submit<T: Task, B: TaskBackend>(manager: JobManager<B>, params: T, path: &str) -> Response {
+    match manager.submit(params).await {
+        Ok(state) => {
+            let location = HeaderValue::from_str(&format!("{path}/{}", state.id)).ok();
+            let mut response = ApiSuccess::new(StatusCode::ACCEPTED, state).enveloped().into_response();
+            if let Some(location) = location {
+                response.headers_mut().insert(LOCATION, location);
+            }
+
+            response
+        }
+        Err(err) => ApiError::InternalServerError(err.to_string()).into_response(),
+    }
+}
+
+/// Update the state of a job
+fn update(jobs: &JobStates, id: Uuid, f: impl FnOnce(&mut JobState)) {
+    if let Ok(mut jobs) = jobs.lock()
+        && let Some(state) = jobs.get_mut(&id)
+    {
+        f(state);
+        state.updated_at = Utc::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::shutdown::Shutdown;
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Export {
+        format: String,
+    }
+
+    impl Task for Export {
+        const QUEUE: &'static str = "exports";
+    }
+
+    async fn wait_for(manager: &JobManager, id: Uuid, status: JobStatus) -> JobState {
+        for _ in 0..100 {
+            if let Some(state) = manager.get(id).filter(|state| state.status == status) {
+                return state;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("job {id} is not {status:?}: {:?}", manager.get(id));
+    }
+
+    async fn send(router: Router, request: Request<Body>) -> (StatusCode, Option<String>, Value) {
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+
+        (status, location, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_job_manager_router() {
+        let shutdown = Shutdown::new();
+        let manager = JobManager::new(TaskQueue::memory());
+        manager
+            .worker(|export: Export, progress| async move {
+                progress.set(50);
+                match export.format.as_str() {
+                    "csv" => Ok(format!("/files/{}.csv", progress.job_id())),
+                    _ => Err("unsupported format"),
+                }
+            })
+            .with_retries(0, Duration::from_millis(1))
+            .with_shutdown(shutdown.clone())
+            .spawn();
+        let router = manager.router::<Export, ()>("/exports");
+
+        let request = Request::post("/exports")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"format":"csv"}"#))
+            .unwrap();
+        let (status, location, body) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let id = body["data"]["id"].as_str().unwrap().parse::<Uuid>().unwrap();
+        assert_eq!(location.unwrap(), format!("/exports/{id}"));
+
+        let state = wait_for(&manager, id, JobStatus::Succeeded).await;
+        assert_eq!(state.progress, 100);
+        assert_eq!(state.result_location, Some(format!("/files/{id}.csv")));
+
+        let request = Request::get(format!("/exports/{id}")).body(Body::empty()).unwrap();
+        let (status, _, body) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["status"], "succeeded");
+        assert_eq!(body["data"]["result_location"], format!("/files/{id}.csv"));
+
+        // Failed job
+        let state = manager
+            .submit(Export {
+                format: "pdf".to_string(),
+            })
+            .await
+            .unwrap();
+        let state = wait_for(&manager, state.id, JobStatus::Failed).await;
+        assert_eq!(state.progress, 50);
+        assert_eq!(state.error.as_deref(), Some("unsupported format"));
+
+        let request = Request::get(format!("/exports/{}", Uuid::new_v4()))
+            .body(Body::empty())
+            .unwrap();
+        let (status, _, _) = send(router, request).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_job_manager_cleanup() {
+        let manager = JobManager::new(TaskQueue::memory()).with_retention(Duration::ZERO);
+        let pending = manager
+            .submit(Export {
+                format: "csv".to_string(),
+            })
+            .await
+            .unwrap();
+        let finished = manager
+            .submit(Export {
+                format: "csv".to_string(),
+            })
+            .await
+            .unwrap();
+        update(&manager.jobs, finished.id, |state| state.status = JobStatus::Succeeded);
+
+        assert_eq!(manager.cleanup(), 1);
+        assert!(manager.get(pending.id).is_some());
+        assert!(manager.get(finished.id).is_none());
+
+        let manager = manager.with_retention(Duration::from_secs(60));
+        update(&manager.jobs, pending.id, |state| state.status = JobStatus::Failed);
+        assert_eq!(manager.cleanup(), 0);
+    }
+}
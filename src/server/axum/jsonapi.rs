@@ -0,0 +1,593 @@
+//! JSON:API serialization helpers (`jsonapi` feature)
+//!
+//! Types of the [JSON:API](https://jsonapi.org/format/) documents, for the teams required to
+//! follow the specification: [`Resource`] objects with their [`Relationship`]s, [`Document`]s
+//! with the included resources, and [`JsonApiErrors`] built from the `ApiError`s and the
+//! `ValidationErrors`. The responses are sent with the `application/vnd.api+json` content type.
+//!
+//! ```rust
+//! use api_tools::server::axum::jsonapi::{Document, JsonApiErrors, Relationship, Resource, ResourceIdentifier};
+//! use api_tools::server::axum::response::ApiError;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Article {
+//!     title: String,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Person {
+//!     name: String,
+//! }
+//!
+//! async fn get_article() -> Result<Document<Resource<Article>>, JsonApiErrors> {
+//!     let article = Resource::new("articles", "1", Article { title: "JSON:API".to_string() })
+//!         .with_relationship("author", Relationship::one(ResourceIdentifier::new("people", "9")))
+//!         .with_link("self", "/articles/1");
+//!     let author = Resource::new("people", "9", Person { name: "John".to_string() });
+//!
+//!     Document::new(article)
+//!         .with_included(author)
+//!         .map_err(|err| ApiError::InternalServerError(err.to_string()).into())
+//! }
+//! ```
+
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::{ApiError, ValidationErrors, current_trace_id};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// JSON:API media type
+pub const JSONAPI_MEDIA_TYPE: &str = "application/vnd.api+json";
+
+/// JSON:API version of the documents
+pub const JSONAPI_VERSION: &str = "1.1";
+
+/// Links object (name → URL)
+pub type Links = BTreeMap<String, String>;
+
+/// Resource identifier object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceIdentifier {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+impl ResourceIdentifier {
+    /// Create a new resource identifier
+    pub fn new(kind: &str, id: impl ToString) -> Self {
+        Self {
+            kind: kind.to_string(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// Resource linkage of a relationship
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RelationshipData {
+    /// To-one relationship (`null` if empty)
+    One(Option<ResourceIdentifier>),
+
+    /// To-many relationship
+    Many(Vec<ResourceIdentifier>),
+}
+
+/// Relationship object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relationship {
+    pub data: RelationshipData,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: Links,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl Relationship {
+    /// To-one relationship
+    pub fn one(resource: ResourceIdentifier) -> Self {
+        Self::from(RelationshipData::One(Some(resource)))
+    }
+
+    /// Empty to-one relationship
+    pub fn none() -> Self {
+        Self::from(RelationshipData::One(None))
+    }
+
+    /// To-many relationship
+    pub fn many(resources: Vec<ResourceIdentifier>) -> Self {
+        Self::from(RelationshipData::Many(resources))
+    }
+
+    /// Add a link (e.g. `self` or `related`)
+    pub fn with_link(mut self, name: &str, href: &str) -> Self {
+        self.links.insert(name.to_string(), href.to_string());
+        self
+    }
+
+    /// Set the meta information
+    pub fn with_meta(mut self, meta: Value) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+}
+
+impl From<RelationshipData> for Relationship {
+    fn from(data: RelationshipData) -> Self {
+        Self {
+            data,
+            links: Links::new(),
+            meta: None,
+        }
+    }
+}
+
+/// Resource object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource<A> {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub attributes: A,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub relationships: BTreeMap<String, Relationship>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: Links,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl<A> Resource<A> {
+    /// Create a new resource
+    pub fn new(kind: &str, id: impl ToString, attributes: A) -> Self {
+        Self {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            attributes,
+            relationships: BTreeMap::new(),
+            links: Links::new(),
+            meta: None,
+        }
+    }
+
+    /// Identifier of the resource
+    pub fn identifier(&self) -> ResourceIdentifier {
+        ResourceIdentifier::new(&self.kind, &self.id)
+    }
+
+    /// Add a relationship
+    pub fn with_relationship(mut self, name: &str, relationship: Relationship) -> Self {
+        self.relationships.insert(name.to_string(), relationship);
+        self
+    }
+
+    /// Add a link (e.g. `self`)
+    pub fn with_link(mut self, name: &str, href: &str) -> Self {
+        self.links.insert(name.to_string(), href.to_string());
+        self
+    }
+
+    /// Set the meta information
+    pub fn with_meta(mut self, meta: Value) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+}
+
+impl<A: Serialize> Resource<A> {
+    /// Resource with its attributes serialized as JSON (e.g. to mix types in the included
+    /// resources)
+    pub fn into_value(self) -> Result<Resource<Value>, serde_json::Error> {
+        Ok(Resource {
+            kind: self.kind,
+            id: self.id,
+            attributes: serde_json::to_value(self.attributes)?,
+            relationships: self.relationships,
+            links: self.links,
+            meta: self.meta,
+        })
+    }
+}
+
+/// JSON:API object of the documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonApiObject {
+    pub version: String,
+}
+
+impl Default for JsonApiObject {
+    fn default() -> Self {
+        Self {
+            version: JSONAPI_VERSION.to_string(),
+        }
+    }
+}
+
+/// Top-level document, whose primary data is a resource (`Resource<A>`), a collection
+/// (`Vec<Resource<A>>`) or resource identifiers
+///
+/// Sent with a `200 OK` status by default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document<D> {
+    pub data: D,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<Resource<Value>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: Links,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    #[serde(default)]
+    pub jsonapi: JsonApiObject,
+    #[serde(skip, default = "default_status")]
+    status: StatusCode,
+}
+
+fn default_status() -> StatusCode {
+    StatusCode::OK
+}
+
+impl<D> Document<D> {
+    /// Create a new document
+    pub fn new(data: D) -> Self {
+        Self {
+            data,
+            included: Vec::new(),
+            links: Links::new(),
+            meta: None,
+            jsonapi: JsonApiObject::default(),
+            status: StatusCode::OK,
+        }
+    }
+
+    /// Set the response status (e.g. `201 Created`)
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add an included resource (compound document)
+    ///
+    /// A resource already included (same type and ID) is not added twice.
+    pub fn with_included<A: Serialize>(mut self, resource: Resource<A>) -> Result<Self, serde_json::Error> {
+        let resource = resource.into_value()?;
+        if !self
+            .included
+            .iter()
+            .any(|included| included.kind == resource.kind && included.id == resource.id)
+        {
+            self.included.push(resource);
+        }
+
+        Ok(self)
+    }
+
+    /// Add a link (e.g. `self`, `next`)
+    pub fn with_link(mut self, name: &str, href: &str) -> Self {
+        self.links.insert(name.to_string(), href.to_string());
+        self
+    }
+
+    /// Set the meta information
+    pub fn with_meta(mut self, meta: Value) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+}
+
+impl<D: Serialize> IntoResponse for Document<D> {
+    fn into_response(self) -> Response {
+        json_api_response(self.status, &self)
+    }
+}
+
+/// Source of an error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorSource {
+    /// JSON pointer to the value in the request document (e.g. `/data/attributes/title`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+
+    /// Query parameter which caused the error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<String>,
+}
+
+/// Error object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonApiError {
+    /// HTTP status code, as a string
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ErrorSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+impl JsonApiError {
+    /// Create a new error, titled with the reason of the status code
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status: status.as_u16().to_string(),
+            code: None,
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            detail: None,
+            source: None,
+            meta: None,
+        }
+    }
+
+    /// Set the application-specific error code
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Set the detail of the error
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    /// Set the JSON pointer to the invalid value of the request document
+    pub fn with_pointer(mut self, pointer: &str) -> Self {
+        self.source
+            .get_or_insert(ErrorSource {
+                pointer: None,
+                parameter: None,
+            })
+            .pointer = Some(pointer.to_string());
+        self
+    }
+
+    /// Set the invalid query parameter
+    pub fn with_parameter(mut self, parameter: &str) -> Self {
+        self.source
+            .get_or_insert(ErrorSource {
+                pointer: None,
+                parameter: None,
+            })
+            .parameter = Some(parameter.to_string());
+        self
+    }
+
+    /// Set the meta information
+    pub fn with_meta(mut self, meta: Value) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Status code of the error (`500` if invalid)
+    pub fn status_code(&self) -> StatusCode {
+        self.status
+            .parse::<u16>()
+            .ok()
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl From<ApiError> for JsonApiError {
+    fn from(error: ApiError) -> Self {
+        let status = error.status_code();
+        let mut json_api_error = Self::new(status).with_detail(&redaction_policy().redact_str(error.message()));
+        if status != StatusCode::UNAUTHORIZED
+            && let Some(trace_id) = current_trace_id()
+        {
+            json_api_error.meta = Some(serde_json::json!({ "trace_id": trace_id }));
+        }
+
+        json_api_error
+    }
+}
+
+/// Errors document
+///
+/// The response status is the status of the errors if they all have the same, `500` if one of
+/// them is a server error and `400` otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsonApiErrors {
+    pub errors: Vec<JsonApiError>,
+    #[serde(default)]
+    pub jsonapi: JsonApiObject,
+}
+
+impl JsonApiErrors {
+    /// Create a new errors document
+    pub fn new(errors: Vec<JsonApiError>) -> Self {
+        Self {
+            errors,
+            jsonapi: JsonApiObject::default(),
+        }
+    }
+
+    /// Status code of the response
+    pub fn status_code(&self) -> StatusCode {
+        let mut statuses = self.errors.iter().map(JsonApiError::status_code);
+        let Some(first) = statuses.next() else {
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        };
+        if statuses.clone().all(|status| status == first) {
+            first
+        } else if std::iter::once(first)
+            .chain(statuses)
+            .any(|status| status.is_server_error())
+        {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        }
+    }
+}
+
+impl From<JsonApiError> for JsonApiErrors {
+    fn from(error: JsonApiError) -> Self {
+        Self::new(vec![error])
+    }
+}
+
+impl From<ApiError> for JsonApiErrors {
+    fn from(error: ApiError) -> Self {
+        JsonApiError::from(error).into()
+    }
+}
+
+impl From<ValidationErrors> for JsonApiErrors {
+    /// One `422` error per field, pointing to the attribute (`/data/attributes/{field}`)
+    fn from(errors: ValidationErrors) -> Self {
+        Self::new(
+            errors
+                .0
+                .into_iter()
+                .map(|error| {
+                    JsonApiError::new(StatusCode::UNPROCESSABLE_ENTITY)
+                        .with_detail(&error.message)
+                        .with_pointer(&format!("/data/attributes/{}", error.field.replace('.', "/")))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl IntoResponse for JsonApiErrors {
+    fn into_response(self) -> Response {
+        json_api_response(self.status_code(), &self)
+    }
+}
+
+/// JSON:API response
+fn json_api_response<T: Serialize>(status: StatusCode, document: &T) -> Response {
+    match serde_json::to_vec(document) {
+        Ok(body) => (
+            status,
+            [(header::CONTENT_TYPE, HeaderValue::from_static(JSONAPI_MEDIA_TYPE))],
+            body,
+        )
+            .into_response(),
+        Err(err) => JsonApiErrors::from(ApiError::InternalServerError(err.to_string())).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize)]
+    struct Article {
+        title: String,
+    }
+
+    async fn body(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_document_into_response() {
+        let article = Resource::new(
+            "articles",
+            1,
+            Article {
+                title: "JSON:API".to_string(),
+            },
+        )
+        .with_relationship("author", Relationship::one(ResourceIdentifier::new("people", 9)))
+        .with_relationship(
+            "tags",
+            Relationship::many(vec![]).with_link("related", "/articles/1/tags"),
+        )
+        .with_relationship("editor", Relationship::none())
+        .with_link("self", "/articles/1");
+        let author = Resource::new("people", 9, json!({ "name": "John" }));
+
+        let response = Document::new(vec![article])
+            .with_included(author.clone())
+            .unwrap()
+            .with_included(author)
+            .unwrap()
+            .with_link("self", "/articles")
+            .with_meta(json!({ "total": 1 }))
+            .with_status(StatusCode::CREATED)
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], JSONAPI_MEDIA_TYPE);
+        assert_eq!(
+            body(response).await,
+            json!({
+                "data": [{
+                    "type": "articles",
+                    "id": "1",
+                    "attributes": { "title": "JSON:API" },
+                    "relationships": {
+                        "author": { "data": { "type": "people", "id": "9" } },
+                        "editor": { "data": null },
+                        "tags": { "data": [], "links": { "related": "/articles/1/tags" } }
+                    },
+                    "links": { "self": "/articles/1" }
+                }],
+                "included": [{ "type": "people", "id": "9", "attributes": { "name": "John" } }],
+                "links": { "self": "/articles" },
+                "meta": { "total": 1 },
+                "jsonapi": { "version": "1.1" }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_into_response() {
+        let response = JsonApiErrors::from(ApiError::NotFound("Article not found".to_string())).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], JSONAPI_MEDIA_TYPE);
+        assert_eq!(
+            body(response).await,
+            json!({
+                "errors": [{ "status": "404", "title": "Not Found", "detail": "Article not found" }],
+                "jsonapi": { "version": "1.1" }
+            })
+        );
+
+        let mut validation = ValidationErrors::new();
+        validation.add("title", "must not be empty");
+        validation.add("author.name", "is too long");
+        let errors = JsonApiErrors::from(validation);
+        assert_eq!(errors.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            errors.errors[1].source.as_ref().unwrap().pointer.as_deref(),
+            Some("/data/attributes/author/name")
+        );
+    }
+
+    #[test]
+    fn test_errors_status_code() {
+        let errors = |statuses: &[StatusCode]| {
+            JsonApiErrors::new(statuses.iter().map(|status| JsonApiError::new(*status)).collect()).status_code()
+        };
+        assert_eq!(errors(&[]), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(errors(&[StatusCode::FORBIDDEN]), StatusCode::FORBIDDEN);
+        assert_eq!(
+            errors(&[StatusCode::FORBIDDEN, StatusCode::NOT_FOUND]),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            errors(&[StatusCode::NOT_FOUND, StatusCode::SERVICE_UNAVAILABLE]),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            JsonApiError::new(StatusCode::CONFLICT).with_parameter("sort").source,
+            Some(ErrorSource {
+                pointer: None,
+                parameter: Some("sort".to_string()),
+            })
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! Adaptive throttle
+//!
+//! An [`AdaptiveThrottle`] limits the number of calls per key (client IP, upstream host, etc.)
+//! over a fixed window, and adapts the limit to the pressure reported by a protected upstream:
+//!
+//! - a `429 Too Many Requests` or `503 Service Unavailable` response multiplies the limit by the
+//!   decrease factor (at most once per window, down to the minimum limit)
+//! - any other response (except 5xx) adds the increase step to the limit, up to the configured one
+//!
+//! The same throttle can protect inbound routes ([`AdaptiveThrottleLayer`], 429 response with a
+//! `Retry-After` header when the limit is reached) and outbound requests
+//! (`HttpClient::with_throttle` with the `client` feature, keyed by host).
+//!
+//! With the `prometheus` feature, the `adaptive_throttle_limit` gauge (label `name`) exposes the
+//! current limit.
+
+use crate::server::axum::layers::body_from_parts;
+use crate::server::axum::layers::context::RequestContext;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderValue, Request, StatusCode, header};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Default limit decrease factor on upstream pressure
+pub const ADAPTIVE_THROTTLE_DEFAULT_DECREASE_FACTOR: f64 = 0.5;
+
+/// Default limit increase step (in percent of the configured limit) on success
+pub const ADAPTIVE_THROTTLE_DEFAULT_INCREASE_STEP: f64 = 0.05;
+
+/// Default minimum limit
+pub const ADAPTIVE_THROTTLE_DEFAULT_MIN_LIMIT: u32 = 1;
+
+/// Key used when the client IP is unknown
+const UNKNOWN_KEY: &str = "unknown";
+
+/// Mutable state shared by the clones of a throttle
+#[derive(Debug)]
+struct AdaptiveThrottleState {
+    /// Current limit in percent of the configured limit
+    ratio: f64,
+    last_decrease: Option<Instant>,
+    /// Start and number of calls of the current window by key
+    windows: HashMap<String, (Instant, u32)>,
+    last_cleanup: Instant,
+}
+
+/// Adaptive throttle (clones share the same state)
+#[derive(Debug, Clone)]
+pub struct AdaptiveThrottle {
+    name: Arc<str>,
+    limit: u32,
+    period: Duration,
+    min_limit: u32,
+    decrease_factor: f64,
+    increase_step: f64,
+    state: Arc<Mutex<AdaptiveThrottleState>>,
+}
+
+impl AdaptiveThrottle {
+    /// Create a new throttle allowing `limit` calls per key and per `period`
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::layers::adaptive_throttle::AdaptiveThrottle;
+    /// use axum::http::StatusCode;
+    /// use std::time::Duration;
+    ///
+    /// let throttle = AdaptiveThrottle::new("payments", 100, Duration::from_secs(1));
+    /// assert!(throttle.try_acquire("203.0.113.1").is_ok());
+    ///
+    /// // The upstream is overloaded
+    /// throttle.record_status(StatusCode::TOO_MANY_REQUESTS);
+    /// assert_eq!(throttle.current_limit(), 50);
+    /// ```
+    pub fn new(name: &str, limit: u32, period: Duration) -> Self {
+        let throttle = Self {
+            name: Arc::from(name),
+            limit: limit.max(1),
+            period,
+            min_limit: ADAPTIVE_THROTTLE_DEFAULT_MIN_LIMIT,
+            decrease_factor: ADAPTIVE_THROTTLE_DEFAULT_DECREASE_FACTOR,
+            increase_step: ADAPTIVE_THROTTLE_DEFAULT_INCREASE_STEP,
+            state: Arc::new(Mutex::new(AdaptiveThrottleState {
+                ratio: 1.0,
+                last_decrease: None,
+                windows: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+        };
+        throttle.publish(throttle.limit);
+
+        throttle
+    }
+
+    /// Set the minimum limit
+    pub fn with_min_limit(mut self, min_limit: u32) -> Self {
+        self.min_limit = min_limit.clamp(1, self.limit);
+        self
+    }
+
+    /// Set the factor (between 0 and 1) applied to the limit on upstream pressure
+    pub fn with_decrease_factor(mut self, decrease_factor: f64) -> Self {
+        self.decrease_factor = decrease_factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the step (in percent of the configured limit, between 0 and 1) added to the limit on success
+    pub fn with_increase_step(mut self, increase_step: f64) -> Self {
+        self.increase_step = increase_step.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Throttle name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Configured limit per key and per period
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Current limit per key and per period
+    pub fn current_limit(&self) -> u32 {
+        match self.state.lock() {
+            Ok(state) => self.limit_for(state.ratio),
+            Err(_) => self.limit,
+        }
+    }
+
+    /// Number of keys with a current window
+    pub fn tracked_keys(&self) -> usize {
+        let now = Instant::now();
+
+        self.state
+            .lock()
+            .map(|state| {
+                state
+                    .windows
+                    .values()
+                    .filter(|(started_at, _)| now.duration_since(*started_at) < self.period)
+                    .count()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Count a call for a key, or return the time to wait if the limit is reached
+    pub fn try_acquire(&self, key: &str) -> Result<(), Duration> {
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let limit = self.limit_for(state.ratio);
+
+        // Remove the expired windows
+        if now.duration_since(state.last_cleanup) >= self.period {
+            let period = self.period;
+            state
+                .windows
+                .retain(|_, (started_at, _)| now.duration_since(*started_at) < period);
+            state.last_cleanup = now;
+        }
+
+        let window = state.windows.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(window.0) >= self.period {
+            *window = (now, 0);
+        }
+        if window.1 >= limit {
+            return Err(self.period.saturating_sub(now.duration_since(window.0)));
+        }
+        window.1 += 1;
+
+        Ok(())
+    }
+
+    /// Record an upstream response status: `429` and `503` decrease the limit, other non-5xx
+    /// statuses increase it
+    pub fn record_status(&self, status: StatusCode) {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => self.record_pressure(),
+            status if !status.is_server_error() => self.record_success(),
+            _ => {}
+        }
+    }
+
+    /// Decrease the limit (at most once per period)
+    pub fn record_pressure(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state
+            .last_decrease
+            .is_some_and(|last_decrease| last_decrease.elapsed() < self.period)
+        {
+            return;
+        }
+
+        let min_ratio = f64::from(self.min_limit) / f64::from(self.limit);
+        let ratio = (state.ratio * self.decrease_factor).max(min_ratio);
+        state.last_decrease = Some(Instant::now());
+        self.set_ratio(&mut state, ratio);
+    }
+
+    /// Increase the limit
+    pub fn record_success(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.ratio < 1.0 {
+            let ratio = (state.ratio + self.increase_step).min(1.0);
+            self.set_ratio(&mut state, ratio);
+        }
+    }
+
+    fn limit_for(&self, ratio: f64) -> u32 {
+        ((f64::from(self.limit) * ratio).round() as u32).clamp(self.min_limit, self.limit)
+    }
+
+    fn set_ratio(&self, state: &mut AdaptiveThrottleState, ratio: f64) {
+        let (from, to) = (self.limit_for(state.ratio), self.limit_for(ratio));
+        state.ratio = ratio;
+
+        if from != to {
+            if to < from {
+                warn!(name = %self.name, from, to, "Adaptive throttle limit decreased");
+            } else {
+                debug!(name = %self.name, from, to, "Adaptive throttle limit increased");
+            }
+            self.publish(to);
+        }
+    }
+
+    /// Publish the limit gauge
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn publish(&self, limit: u32) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("adaptive_throttle_limit", "name" => self.name.to_string()).set(f64::from(limit));
+    }
+}
+
+/// Key of a request
+pub type ThrottleKeyFn = Arc<dyn Fn(&Request<Body>) -> String + Send + Sync>;
+
+/// Client IP of the request (from the `RequestContext` if the `ContextLayer` is set, else from the
+/// peer address)
+pub fn client_ip_key(request: &Request<Body>) -> String {
+    let client_ip = match RequestContext::from_request(request) {
+        Some(context) => context.client_ip,
+        None => request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+    };
+
+    client_ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| UNKNOWN_KEY.to_string())
+}
+
+/// Layer rejecting requests with a 429 error when the limit of their key is reached
+///
+/// `429` and `503` responses of the inner service decrease the limit.
+#[derive(Clone)]
+pub struct AdaptiveThrottleLayer {
+    pub throttle: AdaptiveThrottle,
+    pub key: ThrottleKeyFn,
+}
+
+impl AdaptiveThrottleLayer {
+    /// Create a new `AdaptiveThrottleLayer` keyed by client IP
+    pub fn new(throttle: AdaptiveThrottle) -> Self {
+        Self {
+            throttle,
+            key: Arc::new(client_ip_key),
+        }
+    }
+
+    /// Set the function returning the key of a request (e.g. a tenant or an API key)
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Request<Body>) -> String + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+}
+
+impl<S> Layer<S> for AdaptiveThrottleLayer {
+    type Service = AdaptiveThrottleMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdaptiveThrottleMiddleware {
+            inner,
+            throttle: self.throttle.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AdaptiveThrottleMiddleware<S> {
+    inner: S,
+    throttle: AdaptiveThrottle,
+    key: ThrottleKeyFn,
+}
+
+impl<S> Service<Request<Body>> for AdaptiveThrottleMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let throttle = self.throttle.clone();
+        if let Err(retry_after) = throttle.try_acquire(&(self.key)(&request)) {
+            return Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                let msg = body_from_parts(
+                    &mut parts,
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many requests",
+                    Some(vec![(header::RETRY_AFTER, HeaderValue::from(retry_after))]),
+                );
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            });
+        }
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            throttle.record_status(response.status());
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_try_acquire_per_key() {
+        let throttle = AdaptiveThrottle::new("test", 2, Duration::from_secs(60));
+        assert!(throttle.try_acquire("a").is_ok());
+        assert!(throttle.try_acquire("a").is_ok());
+        let retry_after = throttle.try_acquire("a").unwrap_err();
+        assert!(retry_after > Duration::from_secs(59));
+        assert!(throttle.try_acquire("b").is_ok());
+    }
+
+    #[test]
+    fn test_window_is_reset() {
+        let throttle = AdaptiveThrottle::new("test", 1, Duration::from_millis(20));
+        assert!(throttle.try_acquire("a").is_ok());
+        assert!(throttle.try_acquire("a").is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(throttle.try_acquire("a").is_ok());
+    }
+
+    #[test]
+    fn test_limit_adapts_to_upstream_pressure() {
+        let throttle = AdaptiveThrottle::new("test", 100, Duration::from_millis(20))
+            .with_min_limit(20)
+            .with_increase_step(0.1);
+
+        throttle.record_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(throttle.current_limit(), 50);
+        throttle.record_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(throttle.current_limit(), 50, "at most one decrease per period");
+
+        std::thread::sleep(Duration::from_millis(30));
+        throttle.record_pressure();
+        assert_eq!(throttle.current_limit(), 25);
+        std::thread::sleep(Duration::from_millis(30));
+        throttle.record_pressure();
+        assert_eq!(throttle.current_limit(), 20, "minimum limit");
+
+        throttle.record_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(throttle.current_limit(), 20);
+        throttle.record_status(StatusCode::OK);
+        assert_eq!(throttle.current_limit(), 30);
+        for _ in 0..10 {
+            throttle.record_success();
+        }
+        assert_eq!(throttle.current_limit(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_throttle_layer() {
+        let status = Arc::new(AtomicU16::new(503));
+        let throttle = AdaptiveThrottle::new("test", 2, Duration::from_secs(60));
+        let layer = AdaptiveThrottleLayer::new(throttle.clone()).with_key(|request| request.uri().path().to_string());
+        let service = layer.layer(tower::service_fn({
+            let status = status.clone();
+            move |_: Request<Body>| {
+                let status = StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap();
+                async move { Ok::<_, Infallible>(Response::builder().status(status).body(Body::empty()).unwrap()) }
+            }
+        }));
+        let call = |path: &'static str| service.clone().oneshot(Request::get(path).body(Body::empty()).unwrap());
+
+        // The 503 response halves the limit
+        assert_eq!(call("/a").await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(throttle.current_limit(), 1);
+
+        let response = call("/a").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], br#"{"code":429,"message":"Too many requests"}"#);
+
+        status.store(200, Ordering::SeqCst);
+        assert_eq!(call("/b").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(throttle.current_limit(), 1);
+    }
+
+    #[test]
+    fn test_client_ip_key() {
+        let mut request = Request::new(Body::empty());
+        assert_eq!(client_ip_key(&request), "unknown");
+
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 1234))));
+        assert_eq!(client_ip_key(&request), "10.0.0.1");
+    }
+}
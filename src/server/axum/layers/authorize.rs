@@ -0,0 +1,142 @@
+//! Authorization layer
+//!
+//! [`AuthorizeLayer`] checks the [`Grants`] stored in the `RequestStore` by the authentication
+//! layer (e.g. `JwtAuthLayer`) against the [`Requirement`] of a route:
+//!
+//! - `401 Unauthorized` if the request is not authenticated (no grants in the store)
+//! - `403 Forbidden` with the list of missing grants if the requirement is not satisfied
+//!
+//! It is usually added to a route (`MethodRouter::layer`) and must be wrapped by the authentication
+//! layer.
+
+use super::body_from_parts;
+use crate::server::axum::request_store::RequestStore;
+use crate::server::axum::security::authorization::{Grants, Requirement};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Layer checking the grants of the caller against a requirement
+#[derive(Clone)]
+pub struct AuthorizeLayer {
+    pub requirement: Arc<Requirement>,
+}
+
+impl AuthorizeLayer {
+    /// Create a new `AuthorizeLayer`
+    pub fn new(requirement: Requirement) -> Self {
+        Self {
+            requirement: Arc::new(requirement),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthorizeLayer {
+    type Service = AuthorizeMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthorizeMiddleware {
+            inner,
+            requirement: self.requirement.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthorizeMiddleware<S> {
+    inner: S,
+    requirement: Arc<Requirement>,
+}
+
+impl<S> Service<Request<Body>> for AuthorizeMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let grants = RequestStore::from_extensions(request.extensions()).and_then(|store| store.get::<Grants>());
+
+        match grants.map(|grants| grants.check(&self.requirement)) {
+            Some(Ok(())) => Box::pin(self.inner.call(request)),
+            Some(Err(missing)) => {
+                warn!(missing = ?missing.missing, path = %request.uri().path(), "Missing grants");
+                Box::pin(async move { Ok(missing.into_response()) })
+            }
+            None => Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let msg = body_from_parts(&mut parts, StatusCode::UNAUTHORIZED, "Unauthorized", None);
+
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::security::authorization::Grant;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn call(requirement: Requirement, grants: Option<Grants>) -> (StatusCode, String) {
+        let mut request = Request::new(Body::empty());
+        if let Some(grants) = grants {
+            RequestStore::from_extensions_mut(request.extensions_mut()).insert(grants);
+        }
+
+        let response = AuthorizeLayer::new(requirement)
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("ok")))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_authorize_layer() {
+        let grants = Grants::new().with_roles(&["editor"]).with_scopes(&["users:read"]);
+
+        let requirement = Requirement::all_of(&[Grant::role("editor"), Grant::scope("users:read")]);
+        assert_eq!(
+            call(requirement, Some(grants.clone())).await,
+            (StatusCode::OK, "ok".to_string())
+        );
+
+        let requirement = Requirement::all_of(&[Grant::role("editor"), Grant::scope("users:write")]);
+        assert_eq!(
+            call(requirement, Some(grants)).await,
+            (
+                StatusCode::FORBIDDEN,
+                r#"{"code":403,"message":[{"type":"scope","value":"users:write"}]}"#.to_string()
+            )
+        );
+
+        let requirement = Requirement::any_of(&[Grant::role("admin")]);
+        assert_eq!(
+            call(requirement, None).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                r#"{"code":401,"message":"Unauthorized"}"#.to_string()
+            )
+        );
+    }
+}
@@ -1,6 +1,24 @@
 //! Basic Auth layer
+//!
+//! [`BasicAuthLayer`] checks the credentials of the `Authorization` header with a
+//! [`BasicAuthValidator`]:
+//! - [`BasicAuthCredentials`], a set of usernames and passwords compared in constant time
+//! - any custom (async) validator, e.g. checking the credentials in a database
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::basic_auth::{BasicAuthCredentials, BasicAuthLayer};
+//! use axum::{Router, routing::get};
+//!
+//! let credentials = BasicAuthCredentials::new()
+//!     .with_user("admin", "admin-password")
+//!     .with_user("monitoring", "monitoring-password");
+//! let app: Router = Router::new()
+//!     .route("/metrics", get(|| async { "metrics" }))
+//!     .layer(BasicAuthLayer::from_validator(credentials).with_realm("Metrics"));
+//! ```
 
 use super::body_from_parts;
+use crate::server::axum::request_store::{Principal, RequestStore};
 use axum::{
     body::Body,
     http::{HeaderValue, Request, header},
@@ -9,23 +27,92 @@ use axum::{
 use futures::future::BoxFuture;
 use http_auth_basic::Credentials;
 use hyper::StatusCode;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use subtle::ConstantTimeEq;
 use tower::{Layer, Service};
 
+/// Default realm of the `WWW-Authenticate` header
+pub const BASIC_AUTH_DEFAULT_REALM: &str = "RESTRICTED";
+
+/// Validator of the Basic Auth credentials
+pub trait BasicAuthValidator: Send + Sync + 'static {
+    /// Return `true` if the credentials are valid
+    fn validate<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool>;
+}
+
+/// Set of Basic Auth credentials
+///
+/// Usernames and passwords are compared in constant time, and all the users are checked, so that
+/// the response time does not reveal which part of the credentials is wrong.
+#[derive(Clone, Default)]
+pub struct BasicAuthCredentials {
+    users: Vec<(String, String)>,
+}
+
+impl BasicAuthCredentials {
+    /// Create an empty set of credentials
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a user
+    pub fn with_user(mut self, username: &str, password: &str) -> Self {
+        self.users.push((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Check the credentials in constant time
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::basic_auth::BasicAuthCredentials;
+    ///
+    /// let credentials = BasicAuthCredentials::new().with_user("user", "pass");
+    /// assert!(credentials.contains("user", "pass"));
+    /// assert!(!credentials.contains("user", "wrong"));
+    /// ```
+    pub fn contains(&self, username: &str, password: &str) -> bool {
+        self.users
+            .iter()
+            .fold(subtle::Choice::from(0), |found, (user, pass)| {
+                found | (user.as_bytes().ct_eq(username.as_bytes()) & pass.as_bytes().ct_eq(password.as_bytes()))
+            })
+            .into()
+    }
+}
+
+impl BasicAuthValidator for BasicAuthCredentials {
+    fn validate<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool> {
+        Box::pin(async move { self.contains(username, password) })
+    }
+}
+
 #[derive(Clone)]
 pub struct BasicAuthLayer {
-    pub username: String,
-    pub password: String,
+    pub validator: Arc<dyn BasicAuthValidator>,
+    pub realm: String,
 }
 
 impl BasicAuthLayer {
-    /// Create a new `BasicAuthLayer`
+    /// Create a new `BasicAuthLayer` with a single user
     pub fn new(username: &str, password: &str) -> Self {
+        Self::from_validator(BasicAuthCredentials::new().with_user(username, password))
+    }
+
+    /// Create a new `BasicAuthLayer` with a validator (e.g. [`BasicAuthCredentials`])
+    pub fn from_validator(validator: impl BasicAuthValidator) -> Self {
         Self {
-            username: username.to_string(),
-            password: password.to_string(),
+            validator: Arc::new(validator),
+            realm: BASIC_AUTH_DEFAULT_REALM.to_string(),
         }
     }
+
+    /// Set the realm of the `WWW-Authenticate` header
+    pub fn with_realm(mut self, realm: &str) -> Self {
+        self.realm = realm.to_string();
+        self
+    }
 }
 
 impl<S> Layer<S> for BasicAuthLayer {
@@ -34,8 +121,8 @@ impl<S> Layer<S> for BasicAuthLayer {
     fn layer(&self, inner: S) -> Self::Service {
         BasicAuthMiddleware {
             inner,
-            username: self.username.clone(),
-            password: self.password.clone(),
+            validator: self.validator.clone(),
+            challenge: challenge(&self.realm),
         }
     }
 }
@@ -43,13 +130,13 @@ impl<S> Layer<S> for BasicAuthLayer {
 #[derive(Clone)]
 pub struct BasicAuthMiddleware<S> {
     inner: S,
-    username: String,
-    password: String,
+    validator: Arc<dyn BasicAuthValidator>,
+    challenge: HeaderValue,
 }
 
 impl<S> Service<Request<Body>> for BasicAuthMiddleware<S>
 where
-    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
     S::Future: Send + 'static,
 {
     type Response = S::Response;
@@ -61,48 +148,61 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: Request<Body>) -> Self::Future {
-        let auth = request
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let credentials = request
             .headers()
             .get(header::AUTHORIZATION)
             .and_then(|h| h.to_str().ok())
-            .map(str::to_string);
-        let username = self.username.clone();
-        let password = self.password.clone();
+            .and_then(|auth| Credentials::from_header(auth.to_string()).ok());
 
-        let future = self.inner.call(request);
+        // The service which was ready is taken, a clone is left in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let validator = self.validator.clone();
+        let challenge = self.challenge.clone();
         Box::pin(async move {
-            let mut response = Response::default();
-
-            let ok = match auth {
+            let ok = match &credentials {
+                Some(credentials) => validator.validate(&credentials.user_id, &credentials.password).await,
                 None => false,
-                Some(auth) => match Credentials::from_header(auth) {
-                    Err(_) => false,
-                    Ok(cred) => cred.user_id == username && cred.password == password,
-                },
             };
-            response = match ok {
-                true => future.await?,
-                false => {
-                    let (mut parts, _body) = response.into_parts();
+
+            match (ok, credentials) {
+                (true, Some(credentials)) => {
+                    // Share the authenticated user with the next layers and handlers
+                    RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal(credentials.user_id));
+
+                    inner.call(request).await
+                }
+                _ => {
+                    let (mut parts, _body) = Response::<Body>::default().into_parts();
                     let msg = body_from_parts(
                         &mut parts,
                         StatusCode::UNAUTHORIZED,
                         "Unauthorized",
-                        Some(vec![(
-                            header::WWW_AUTHENTICATE,
-                            HeaderValue::from_static("basic realm=RESTRICTED"),
-                        )]),
+                        Some(vec![(header::WWW_AUTHENTICATE, challenge)]),
                     );
-                    Response::from_parts(parts, Body::from(msg))
-                }
-            };
 
-            Ok(response)
+                    Ok(Response::from_parts(parts, Body::from(msg)))
+                }
+            }
         })
     }
 }
 
+/// `WWW-Authenticate` header value of a realm (quoted if it is not a token)
+fn challenge(realm: &str) -> HeaderValue {
+    let is_token = !realm.is_empty()
+        && realm
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte));
+    let value = match is_token {
+        true => format!("basic realm={realm}"),
+        false => format!("basic realm=\"{}\"", realm.replace('\\', "\\\\").replace('"', "\\\"")),
+    };
+
+    HeaderValue::from_str(&value).unwrap_or_else(|_| HeaderValue::from_static("basic realm=RESTRICTED"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,4 +352,81 @@ mod tests {
         let resp = service.oneshot(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn correct_credentials_store_the_principal() {
+        let svc = BasicAuthLayer::new("user", "pass").layer(tower::service_fn(|req: Request<Body>| async move {
+            let principal = RequestStore::from_extensions(req.extensions())
+                .and_then(|store| store.get::<Principal>())
+                .map(|principal| principal.0.clone())
+                .unwrap_or_default();
+            Ok::<_, Infallible>(Response::new(Body::from(principal)))
+        }));
+        let req = Request::builder()
+            .uri("/")
+            .header(header::AUTHORIZATION, auth_header("user:pass"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = svc.oneshot(req).await.unwrap();
+
+        let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"user");
+    }
+
+    #[tokio::test]
+    async fn multiple_users_and_custom_realm() {
+        let layer = BasicAuthLayer::from_validator(
+            BasicAuthCredentials::new()
+                .with_user("admin", "secret")
+                .with_user("monitoring", "metrics"),
+        )
+        .with_realm("Admin area");
+        let svc = layer.layer(tower::service_fn(dummy_service));
+
+        for (credentials, status) in [
+            ("admin:secret", StatusCode::OK),
+            ("monitoring:metrics", StatusCode::OK),
+            ("admin:metrics", StatusCode::UNAUTHORIZED),
+        ] {
+            let req = Request::builder()
+                .uri("/")
+                .header(header::AUTHORIZATION, auth_header(credentials))
+                .body(Body::empty())
+                .unwrap();
+            let resp = svc.clone().oneshot(req).await.unwrap();
+            assert_eq!(resp.status(), status, "credentials: {credentials}");
+            if status == StatusCode::UNAUTHORIZED {
+                assert_eq!(
+                    resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+                    "basic realm=\"Admin area\"",
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_validator() {
+        struct PrefixValidator;
+
+        impl BasicAuthValidator for PrefixValidator {
+            fn validate<'a>(&'a self, username: &'a str, password: &'a str) -> BoxFuture<'a, bool> {
+                Box::pin(async move { password == format!("{username}-token") })
+            }
+        }
+
+        let svc = BasicAuthLayer::from_validator(PrefixValidator).layer(tower::service_fn(dummy_service));
+        let req = Request::builder()
+            .uri("/")
+            .header(header::AUTHORIZATION, auth_header("bob:bob-token"))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(svc.clone().oneshot(req).await.unwrap().status(), StatusCode::OK);
+
+        let req = Request::builder()
+            .uri("/")
+            .header(header::AUTHORIZATION, auth_header("bob:alice-token"))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(svc.oneshot(req).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
 }
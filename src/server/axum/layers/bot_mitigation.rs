@@ -0,0 +1,452 @@
+//! Bot mitigation layer
+//!
+//! [`BotMitigationLayer`] rejects the requests of the scrapers and the vulnerability scanners on
+//! public endpoints with a `403 Forbidden` response. A request is flagged if:
+//!
+//! - its `User-Agent` contains a blocked pattern ([`BOT_MITIGATION_DEFAULT_USER_AGENTS`] by
+//!   default, case-insensitive)
+//! - one of the required headers is missing (`User-Agent` and `Accept` by default)
+//! - the honeypot field of a form (hidden to humans by CSS) is filled
+//!   ([`BotMitigationLayer::with_honeypot_field`])
+//! - the proof of work is missing or invalid ([`BotMitigationLayer::with_proof_of_work`])
+//!
+//! The response to a flagged client can be delayed (tarpit) to slow down the scraping. With the
+//! `metrics` feature, the `bot_mitigation_blocked_total` counter (label `reason`) counts the
+//! rejected requests.
+//!
+//! The proof of work does not need JavaScript: the client sends the `X-Proof-Of-Work:
+//! {timestamp}:{nonce}` header, where the SHA-256 hash of `{METHOD} {path}:{timestamp}:{nonce}`
+//! starts with `difficulty` zero bits ([`solve_proof_of_work`]).
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::bot_mitigation::BotMitigationLayer;
+//! use axum::{Router, routing::post};
+//! use std::time::Duration;
+//!
+//! let app: Router = Router::new()
+//!     .route("/contact", post(|| async { "sent" }))
+//!     .layer(
+//!         BotMitigationLayer::new()
+//!             .with_honeypot_field("website")
+//!             .with_tarpit(Duration::from_secs(5)),
+//!     );
+//! ```
+
+use super::body_from_parts;
+use axum::body::{Body, Bytes};
+use axum::http::{HeaderName, Request, StatusCode, header};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+/// Default blocked `User-Agent` patterns (vulnerability scanners)
+pub const BOT_MITIGATION_DEFAULT_USER_AGENTS: &[&str] = &[
+    "acunetix",
+    "dirbuster",
+    "gobuster",
+    "masscan",
+    "nikto",
+    "nmap",
+    "nuclei",
+    "sqlmap",
+    "wpscan",
+    "zgrab",
+];
+
+/// Default required headers
+pub const BOT_MITIGATION_DEFAULT_REQUIRED_HEADERS: &[&str] = &["user-agent", "accept"];
+
+/// Proof of work header
+pub const PROOF_OF_WORK_HEADER: &str = "x-proof-of-work";
+
+/// Accepted difference between the timestamp of a proof of work and now
+pub const PROOF_OF_WORK_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Max size of the form bodies checked for the honeypot field
+pub const BOT_MITIGATION_MAX_FORM_SIZE: usize = 64 * 1024;
+
+/// Reason of a rejection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotReason {
+    UserAgent,
+    MissingHeader,
+    Honeypot,
+    ProofOfWork,
+}
+
+impl fmt::Display for BotReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserAgent => write!(f, "user_agent"),
+            Self::MissingHeader => write!(f, "missing_header"),
+            Self::Honeypot => write!(f, "honeypot"),
+            Self::ProofOfWork => write!(f, "proof_of_work"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BotMitigationLayer {
+    pub blocked_user_agents: Vec<String>,
+    pub required_headers: Vec<HeaderName>,
+    pub honeypot_field: Option<String>,
+    pub proof_of_work_difficulty: Option<u8>,
+    pub tarpit: Duration,
+}
+
+impl Default for BotMitigationLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BotMitigationLayer {
+    /// Create a new `BotMitigationLayer` with the default blocked user agents and required headers
+    pub fn new() -> Self {
+        Self {
+            blocked_user_agents: BOT_MITIGATION_DEFAULT_USER_AGENTS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            required_headers: BOT_MITIGATION_DEFAULT_REQUIRED_HEADERS
+                .iter()
+                .map(|name| HeaderName::from_static(name))
+                .collect(),
+            honeypot_field: None,
+            proof_of_work_difficulty: None,
+            tarpit: Duration::ZERO,
+        }
+    }
+
+    /// Block the user agents containing a pattern (case-insensitive)
+    pub fn with_blocked_user_agent(mut self, pattern: &str) -> Self {
+        self.blocked_user_agents.push(pattern.to_lowercase());
+        self
+    }
+
+    /// Set the required headers
+    pub fn with_required_headers(mut self, headers: &[HeaderName]) -> Self {
+        self.required_headers = headers.to_vec();
+        self
+    }
+
+    /// Reject the forms (`application/x-www-form-urlencoded`) with this field filled
+    pub fn with_honeypot_field(mut self, field: &str) -> Self {
+        self.honeypot_field = Some(field.to_string());
+        self
+    }
+
+    /// Require a proof of work with a number of leading zero bits (20 takes about a second)
+    pub fn with_proof_of_work(mut self, difficulty: u8) -> Self {
+        self.proof_of_work_difficulty = Some(difficulty);
+        self
+    }
+
+    /// Delay the responses to the flagged clients
+    pub fn with_tarpit(mut self, delay: Duration) -> Self {
+        self.tarpit = delay;
+        self
+    }
+
+    /// Check the headers of a request
+    fn check_headers<B>(&self, request: &Request<B>) -> Result<(), BotReason> {
+        if self
+            .required_headers
+            .iter()
+            .any(|name| !request.headers().contains_key(name))
+        {
+            return Err(BotReason::MissingHeader);
+        }
+
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        if self
+            .blocked_user_agents
+            .iter()
+            .any(|pattern| user_agent.contains(pattern.as_str()))
+        {
+            return Err(BotReason::UserAgent);
+        }
+
+        if let Some(difficulty) = self.proof_of_work_difficulty {
+            let proof = request
+                .headers()
+                .get(PROOF_OF_WORK_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            let now = chrono::Utc::now().timestamp();
+            if !verify_proof_of_work(request.method().as_str(), request.uri().path(), proof, difficulty, now) {
+                return Err(BotReason::ProofOfWork);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return true if the request has a form body which can contain the honeypot field
+    fn has_form<B>(&self, request: &Request<B>) -> bool {
+        let is_form = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+        let length = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+
+        self.honeypot_field.is_some() && is_form && length.is_some_and(|length| length <= BOT_MITIGATION_MAX_FORM_SIZE)
+    }
+
+    /// Check the honeypot field of a form body
+    fn check_form(&self, body: &Bytes) -> Result<(), BotReason> {
+        let Some(field) = &self.honeypot_field else {
+            return Ok(());
+        };
+        let fields = serde_urlencoded::from_bytes::<HashMap<String, String>>(body).unwrap_or_default();
+
+        match fields.get(field) {
+            Some(value) if !value.is_empty() => Err(BotReason::Honeypot),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejection of a flagged request
+    async fn reject(&self, reason: BotReason, path: &str) -> Response {
+        tracing::info!(reason = %reason, path, "Bot request blocked");
+        record(reason);
+        if !self.tarpit.is_zero() {
+            tokio::time::sleep(self.tarpit).await;
+        }
+
+        error_response(StatusCode::FORBIDDEN, "Forbidden")
+    }
+}
+
+/// Count a blocked request
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn record(reason: BotReason) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("bot_mitigation_blocked_total", "reason" => reason.to_string()).increment(1);
+}
+
+/// Number of leading zero bits of a hash
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Hash of a proof of work
+fn proof_of_work_hash(method: &str, path: &str, timestamp: i64, nonce: u64) -> Vec<u8> {
+    Sha256::digest(format!("{method} {path}:{timestamp}:{nonce}")).to_vec()
+}
+
+/// Verify a `{timestamp}:{nonce}` proof of work
+fn verify_proof_of_work(method: &str, path: &str, proof: &str, difficulty: u8, now: i64) -> bool {
+    let Some((timestamp, nonce)) = proof.split_once(':') else {
+        return false;
+    };
+    let (Ok(timestamp), Ok(nonce)) = (timestamp.parse::<i64>(), nonce.parse::<u64>()) else {
+        return false;
+    };
+
+    now.abs_diff(timestamp) <= PROOF_OF_WORK_TOLERANCE.as_secs()
+        && leading_zero_bits(&proof_of_work_hash(method, path, timestamp, nonce)) >= u32::from(difficulty)
+}
+
+/// Compute the `X-Proof-Of-Work` header value of a request (client side)
+pub fn solve_proof_of_work(method: &str, path: &str, timestamp: i64, difficulty: u8) -> String {
+    let nonce = (0..u64::MAX)
+        .find(|nonce| leading_zero_bits(&proof_of_work_hash(method, path, timestamp, *nonce)) >= u32::from(difficulty))
+        .unwrap_or_default();
+
+    format!("{timestamp}:{nonce}")
+}
+
+impl<S> Layer<S> for BotMitigationLayer {
+    type Service = BotMitigationMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BotMitigationMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BotMitigationMiddleware<S> {
+    inner: S,
+    config: BotMitigationLayer,
+}
+
+impl<S> Service<Request<Body>> for BotMitigationMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let path = request.uri().path().to_string();
+            if let Err(reason) = config.check_headers(&request) {
+                return Ok(config.reject(reason, &path).await);
+            }
+            if !config.has_form(&request) {
+                return inner.call(request).await;
+            }
+
+            // The form body is buffered to look for the honeypot field
+            let (parts, body) = request.into_parts();
+            let Ok(body) = axum::body::to_bytes(body, BOT_MITIGATION_MAX_FORM_SIZE).await else {
+                return Ok(error_response(StatusCode::BAD_REQUEST, "Invalid body"));
+            };
+            if let Err(reason) = config.check_form(&body) {
+                return Ok(config.reject(reason, &path).await);
+            }
+
+            inner.call(Request::from_parts(parts, Body::from(body))).await
+        })
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, None);
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn call(layer: &BotMitigationLayer, request: Request<Body>) -> (StatusCode, String) {
+        let response = layer
+            .layer(tower::service_fn(|request: Request<Body>| async {
+                let body = axum::body::to_bytes(request.into_body(), 1_024).await.unwrap();
+                Ok::<_, Infallible>(Response::new(Body::from(body)))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn request(user_agent: Option<&str>) -> axum::http::request::Builder {
+        let mut request = Request::post("/contact").header(header::ACCEPT, "*/*");
+        if let Some(user_agent) = user_agent {
+            request = request.header(header::USER_AGENT, user_agent);
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_bot_mitigation_layer_headers() {
+        let layer = BotMitigationLayer::new().with_blocked_user_agent("BadBot");
+
+        let (status, _) = call(&layer, request(Some("Mozilla/5.0")).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = call(&layer, request(Some("sqlmap/1.7")).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body, r#"{"code":403,"message":"Forbidden"}"#);
+
+        let (status, _) = call(&layer, request(Some("badbot/2.0")).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = call(&layer, request(None).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_bot_mitigation_layer_honeypot() {
+        let layer = BotMitigationLayer::new().with_honeypot_field("website");
+        let form = |body: &'static str| {
+            request(Some("Mozilla/5.0"))
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let (status, body) = call(&layer, form("name=Bob&website=")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "name=Bob&website=");
+
+        let (status, _) = call(&layer, form("name=Bob&website=https%3A%2F%2Fspam.example.com")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_bot_mitigation_layer_tarpit() {
+        let layer = BotMitigationLayer::new().with_tarpit(Duration::from_millis(50));
+        let start = std::time::Instant::now();
+
+        let (status, _) = call(&layer, request(Some("nikto")).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_proof_of_work() {
+        let now = 1_800_000_000;
+        let proof = solve_proof_of_work("POST", "/contact", now, 8);
+
+        assert!(verify_proof_of_work("POST", "/contact", &proof, 8, now));
+        assert!(!verify_proof_of_work("POST", "/other", &proof, 8, now));
+        assert!(!verify_proof_of_work("POST", "/contact", &proof, 8, now + 3_600));
+        assert!(!verify_proof_of_work("POST", "/contact", "invalid", 8, now));
+        assert_eq!(leading_zero_bits(&[0, 0b0001_0000, 0xff]), 11);
+    }
+
+    #[tokio::test]
+    async fn test_bot_mitigation_layer_proof_of_work() {
+        let layer = BotMitigationLayer::new().with_proof_of_work(8);
+
+        let (status, _) = call(&layer, request(Some("Mozilla/5.0")).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let proof = solve_proof_of_work("POST", "/contact", chrono::Utc::now().timestamp(), 8);
+        let request = request(Some("Mozilla/5.0"))
+            .header(PROOF_OF_WORK_HEADER, proof)
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = call(&layer, request).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,389 @@
+//! Sticky canary routing
+//!
+//! [`CanaryLayer`] routes a percentage of the traffic to an alternate service (e.g. a `Router`
+//! with the new version of the handlers) or, with the `client` feature, to an upstream URL. The
+//! other requests go to the inner service.
+//!
+//! The routing is sticky: the variant depends on a hash of a stable key of the request (see
+//! [`CanaryKey`]), so a user always hits the same variant while the percentage is unchanged. With
+//! the cookie key, a random cookie is set on the first response of a client without one. Requests
+//! without a key are routed to the primary variant.
+//!
+//! The [`CanaryVariant`] is inserted in the request extensions and, with the `metrics` feature,
+//! the `canary_requests_total` counter (labels `canary` and `variant`) counts the requests.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::canary::{CanaryKey, CanaryLayer};
+//! use axum::{Router, routing::get};
+//!
+//! let canary: Router = Router::new().route("/users", get(|| async { "users v2" }));
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users v1" }))
+//!     .layer(CanaryLayer::service("users-v2", 10, canary).with_key(CanaryKey::Header("x-user-id".parse().unwrap())));
+//! ```
+
+use crate::server::axum::feature_flags::rollout_bucket;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use axum::body::Body;
+use axum::http::header::{COOKIE, SET_COOKIE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "client")]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::util::BoxCloneSyncService;
+use tower::{Layer, Service, ServiceExt};
+use uuid::Uuid;
+
+/// Default name of the stickiness cookie
+pub const CANARY_DEFAULT_COOKIE: &str = "canary_id";
+
+/// Default maximum size of the bodies forwarded to an upstream URL
+#[cfg(feature = "client")]
+pub const CANARY_DEFAULT_BODY_MAX_SIZE: usize = 10 * 1_024 * 1_024;
+
+/// Stable key of the canary routing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanaryKey {
+    /// Cookie set with a random value when it is missing
+    Cookie(String),
+
+    /// Request header
+    Header(HeaderName),
+
+    /// `Principal` of the `RequestStore` (put the layer inside the authentication layer)
+    Principal,
+}
+
+impl Default for CanaryKey {
+    fn default() -> Self {
+        Self::Cookie(CANARY_DEFAULT_COOKIE.to_string())
+    }
+}
+
+impl CanaryKey {
+    /// Stable key of a request
+    fn stable_id(&self, request: &Request<Body>) -> Option<String> {
+        match self {
+            Self::Cookie(name) => cookie(request.headers(), name),
+            Self::Header(name) => request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string),
+            Self::Principal => RequestStore::from_extensions(request.extensions())
+                .and_then(|store| store.get::<Principal>())
+                .map(|principal| principal.0.clone()),
+        }
+    }
+}
+
+/// Variant serving a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanaryVariant {
+    Primary,
+    Canary,
+}
+
+impl Display for CanaryVariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Primary => write!(f, "primary"),
+            Self::Canary => write!(f, "canary"),
+        }
+    }
+}
+
+type CanaryService = BoxCloneSyncService<Request<Body>, Response, Infallible>;
+
+#[derive(Clone)]
+pub struct CanaryLayer {
+    pub name: String,
+    pub percentage: u8,
+    pub key: CanaryKey,
+    canary: CanaryService,
+}
+
+impl CanaryLayer {
+    /// Route `percentage` (capped at 100) of the traffic to an alternate service
+    pub fn service<T>(name: &str, percentage: u8, canary: T) -> Self
+    where
+        T: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + Sync + 'static,
+        T::Future: Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            percentage: percentage.min(100),
+            key: CanaryKey::default(),
+            canary: BoxCloneSyncService::new(canary),
+        }
+    }
+
+    /// Route `percentage` (capped at 100) of the traffic to an upstream URL (e.g.
+    /// `http://users-v2:8080`), the request path and query being appended to the URL
+    ///
+    /// The request bodies are buffered (up to [`CANARY_DEFAULT_BODY_MAX_SIZE`]) and the upstream
+    /// errors are returned as `503` errors.
+    #[cfg(feature = "client")]
+    pub fn upstream(name: &str, percentage: u8, client: reqwest::Client, url: &str) -> Self {
+        let url: Arc<str> = Arc::from(url.trim_end_matches('/'));
+        let proxy = tower::service_fn(move |request: Request<Body>| {
+            let client = client.clone();
+            let url = url.clone();
+            async move { Ok::<_, Infallible>(proxy(&client, &url, request).await) }
+        });
+
+        Self::service(name, percentage, proxy)
+    }
+
+    /// Set the stable key of the routing
+    pub fn with_key(mut self, key: CanaryKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Variant of a stable key
+    pub fn variant(&self, stable_id: Option<&str>) -> CanaryVariant {
+        match stable_id {
+            Some(id) if rollout_bucket(&self.name, id) < self.percentage => CanaryVariant::Canary,
+            _ => CanaryVariant::Primary,
+        }
+    }
+}
+
+impl<S> Layer<S> for CanaryLayer {
+    type Service = CanaryMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CanaryMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CanaryMiddleware<S> {
+    inner: S,
+    config: CanaryLayer,
+}
+
+impl<S> Service<Request<Body>> for CanaryMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let mut stable_id = self.config.key.stable_id(&request);
+
+        // New clients get a random cookie, also used to route their first request
+        let mut set_cookie = None;
+        if let (CanaryKey::Cookie(name), None) = (&self.config.key, &stable_id) {
+            let id = Uuid::new_v4().to_string();
+            set_cookie = HeaderValue::from_str(&format!("{name}={id}; Path=/; HttpOnly; SameSite=Lax")).ok();
+            stable_id = Some(id);
+        }
+
+        let variant = self.config.variant(stable_id.as_deref());
+        request.extensions_mut().insert(variant);
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!(
+            "canary_requests_total",
+            "canary" => self.config.name.clone(),
+            "variant" => variant.to_string(),
+        )
+        .increment(1);
+
+        let future: BoxFuture<'static, Result<Response, S::Error>> = match variant {
+            CanaryVariant::Primary => Box::pin(self.inner.call(request)),
+            CanaryVariant::Canary => {
+                let canary = self.config.canary.clone();
+                Box::pin(async move {
+                    match canary.oneshot(request).await {
+                        Ok(response) => Ok(response),
+                        Err(err) => match err {},
+                    }
+                })
+            }
+        };
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            if let Some(set_cookie) = set_cookie {
+                response.headers_mut().append(SET_COOKIE, set_cookie);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Value of a cookie
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(cookie, value)| *cookie == name && !value.is_empty())
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+/// Forward a request to an upstream URL
+#[cfg(feature = "client")]
+async fn proxy(client: &reqwest::Client, url: &str, request: Request<Body>) -> Response {
+    use crate::server::axum::response::ApiError;
+    use axum::http::header::HOST;
+    use axum::response::IntoResponse;
+
+    let (mut parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, CANARY_DEFAULT_BODY_MAX_SIZE).await else {
+        return ApiError::PayloadTooLarge.into_response();
+    };
+    let path = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+    parts.headers.remove(HOST);
+
+    match client
+        .request(parts.method, format!("{url}{path}"))
+        .headers(parts.headers)
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(response) => axum::http::Response::from(response).map(Body::new),
+        Err(err) => {
+            warn!(%url, "Canary upstream request failed: {err}");
+            ApiError::ServiceUnavailable.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+
+    fn app(layer: CanaryLayer) -> Router {
+        Router::new().route("/version", get(|| async { "v1" })).layer(layer)
+    }
+
+    fn canary() -> Router {
+        Router::new().route("/version", get(|| async { "v2" }))
+    }
+
+    async fn version(app: Router, request: Request<Body>) -> (String, Option<HeaderValue>) {
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let set_cookie = response.headers().get(SET_COOKIE).cloned();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (String::from_utf8(body.to_vec()).unwrap(), set_cookie)
+    }
+
+    #[test]
+    fn test_canary_layer_variant() {
+        let layer = CanaryLayer::service("v2", 30, canary());
+        assert_eq!(layer.variant(None), CanaryVariant::Primary);
+
+        let canaries = (0..1_000)
+            .filter(|id| layer.variant(Some(&format!("user-{id}"))) == CanaryVariant::Canary)
+            .count();
+        assert!((250..350).contains(&canaries), "{canaries} canaries");
+
+        // Sticky
+        let variant = layer.variant(Some("user-1"));
+        assert!((0..10).all(|_| layer.variant(Some("user-1")) == variant));
+
+        assert_eq!(
+            CanaryLayer::service("v2", 0, canary()).variant(Some("user-1")),
+            CanaryVariant::Primary
+        );
+        assert_eq!(
+            CanaryLayer::service("v2", 200, canary()).variant(Some("user-1")),
+            CanaryVariant::Canary
+        );
+    }
+
+    #[tokio::test]
+    async fn test_canary_layer_header_key() {
+        let key = CanaryKey::Header(HeaderName::from_static("x-user-id"));
+        let app = app(CanaryLayer::service("v2", 100, canary()).with_key(key.clone()));
+
+        let request = Request::get("/version")
+            .header("x-user-id", "user-1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(version(app.clone(), request).await, ("v2".to_string(), None));
+
+        // Without key
+        let request = Request::get("/version").body(Body::empty()).unwrap();
+        assert_eq!(version(app, request).await, ("v1".to_string(), None));
+
+        let app = self::app(CanaryLayer::service("v2", 0, canary()).with_key(key));
+        let request = Request::get("/version")
+            .header("x-user-id", "user-1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(version(app, request).await.0, "v1");
+    }
+
+    #[tokio::test]
+    async fn test_canary_layer_cookie_key() {
+        let app = app(CanaryLayer::service("v2", 100, canary()));
+
+        let (body, set_cookie) = version(app.clone(), Request::get("/version").body(Body::empty()).unwrap()).await;
+        assert_eq!(body, "v2");
+        let set_cookie = set_cookie.unwrap();
+        assert!(set_cookie.to_str().unwrap().starts_with("canary_id="));
+
+        let request = Request::get("/version")
+            .header(COOKIE, "theme=dark; canary_id=abc")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(version(app, request).await, ("v2".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn test_canary_layer_principal_key() {
+        let app = app(CanaryLayer::service("v2", 100, canary()).with_key(CanaryKey::Principal));
+        let mut request = Request::get("/version").body(Body::empty()).unwrap();
+        RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal("alice".to_string()));
+        assert_eq!(version(app.clone(), request).await.0, "v2");
+
+        let request = Request::get("/version").body(Body::empty()).unwrap();
+        assert_eq!(version(app, request).await.0, "v1");
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn test_canary_layer_upstream() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, canary()).await.unwrap() });
+
+        let layer = CanaryLayer::upstream("v2", 100, reqwest::Client::new(), &url)
+            .with_key(CanaryKey::Header(HeaderName::from_static("x-user-id")));
+        let request = Request::get("/version")
+            .header("x-user-id", "user-1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(version(app(layer), request).await.0, "v2");
+    }
+}
@@ -0,0 +1,205 @@
+//! Panic-catching layer
+//!
+//! [`CatchPanicLayer`] converts the panics of the inner services (handlers and layers) into the
+//! standard `500 Internal Server Error` JSON response (with the `trace_id` when available). The
+//! panic payload and its backtrace (if enabled with `RUST_BACKTRACE`) are logged, and the
+//! `http_panics_total` counter is incremented (`prometheus` feature).
+//!
+//! The panic message is never sent to the client.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::catch_panic::CatchPanicLayer;
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(|| async { "ok" }))
+//!     .layer(CatchPanicLayer::new());
+//! ```
+
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::ApiError;
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::panic::AssertUnwindSafe;
+use std::sync::Once;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Message of the panic responses
+pub const PANIC_RESPONSE_MESSAGE: &str = "Internal server error";
+
+thread_local! {
+    /// Backtrace of the last panic of the thread, captured by the panic hook
+    static LAST_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// Install (once) a panic hook capturing the backtrace of the panics, before calling the previous hook
+fn install_panic_hook() {
+    static HOOK: Once = Once::new();
+
+    HOOK.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            LAST_BACKTRACE.with(|backtrace| *backtrace.borrow_mut() = Some(Backtrace::capture()));
+            previous_hook(info);
+        }));
+    });
+}
+
+/// Layer converting panics into `500 Internal Server Error` responses
+#[derive(Clone, Default)]
+pub struct CatchPanicLayer;
+
+impl CatchPanicLayer {
+    /// Create a new `CatchPanicLayer`
+    pub fn new() -> Self {
+        install_panic_hook();
+        Self
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanicMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        install_panic_hook();
+        CatchPanicMiddleware { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct CatchPanicMiddleware<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for CatchPanicMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let method = request.method().to_string();
+        let path = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str().to_string(),
+            None => request.uri().path().to_string(),
+        };
+
+        // The inner service can panic when it is called or when its future is polled
+        match std::panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(request))) {
+            Ok(future) => Box::pin(async move {
+                match AssertUnwindSafe(future).catch_unwind().await {
+                    Ok(result) => result,
+                    Err(payload) => Ok(panic_response(&method, &path, payload)),
+                }
+            }),
+            Err(payload) => {
+                let response = panic_response(&method, &path, payload);
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// Log a panic and return the `500 Internal Server Error` response
+fn panic_response(method: &str, path: &str, payload: Box<dyn Any + Send>) -> Response {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string());
+    let backtrace = LAST_BACKTRACE
+        .with(|backtrace| backtrace.borrow_mut().take())
+        .filter(|backtrace| backtrace.status() == BacktraceStatus::Captured)
+        .map(|backtrace| backtrace.to_string())
+        .unwrap_or_default();
+    let message = redaction_policy().redact_str(&message);
+    error!(method, path, panic = %message, backtrace = %backtrace, "Request handler panicked");
+
+    #[cfg(feature = "metrics")]
+    metrics::counter!("http_panics_total", "method" => method.to_string(), "path" => path.to_string()).increment(1);
+
+    ApiError::InternalServerError(PANIC_RESPONSE_MESSAGE.to_string()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_catch_panic_layer() {
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route(
+                "/panic",
+                get(|| async {
+                    if true {
+                        panic!("secret database password");
+                    }
+                    "unreachable"
+                }),
+            )
+            .layer(CatchPanicLayer::new());
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(Request::get("/panic").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(body, r#"{"code":500,"message":"Internal server error"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic_in_service_call() {
+        #[derive(Clone)]
+        struct PanickingService;
+
+        impl Service<Request<Body>> for PanickingService {
+            type Response = Response;
+            type Error = std::convert::Infallible;
+            type Future = std::future::Ready<Result<Response, Self::Error>>;
+
+            fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, _request: Request<Body>) -> Self::Future {
+                panic!("panic in call");
+            }
+        }
+
+        let response = CatchPanicLayer::new()
+            .layer(PanickingService)
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
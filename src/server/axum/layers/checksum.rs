@@ -0,0 +1,379 @@
+//! Body checksum validation layer
+//!
+//! [`ChecksumLayer`] validates the checksum headers of the requests against the received body:
+//!
+//! - `Content-MD5`: base64 encoded MD5 digest
+//! - `x-amz-content-sha256` (configurable): hex encoded SHA-256 digest (`UNSIGNED-PAYLOAD` and the
+//!   `STREAMING-*` values are not checked)
+//!
+//! The body is hashed while it is read, up to `max_body_size` (larger bodies are rejected with a
+//! `413 Payload Too Large`), and the mismatches are rejected with a `400 Bad Request`. Requests
+//! without checksum header are accepted, unless a checksum is required.
+//!
+//! The layer can also set the digest headers of the responses whose size is known and lower than
+//! `max_body_size` (streamed responses are untouched).
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::checksum::{ChecksumAlgorithm, ChecksumLayer, content_md5};
+//!
+//! let layer = ChecksumLayer::new()
+//!     .with_max_body_size(1_024 * 1_024)
+//!     .with_response_digest(ChecksumAlgorithm::Sha256);
+//!
+//! // Client side
+//! assert_eq!(content_md5(b"hello"), "XUFAKrxLKna5cZ2REBfFkg==");
+//! ```
+
+use super::body_from_parts;
+use axum::body::{Body, HttpBody};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode};
+use axum::response::Response;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::BytesMut;
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// `Content-MD5` header
+pub const CONTENT_MD5_HEADER: &str = "content-md5";
+
+/// Default SHA-256 checksum header
+pub const CONTENT_SHA256_HEADER: &str = "x-amz-content-sha256";
+
+/// Default max body size (10 MiB)
+pub const CHECKSUM_DEFAULT_MAX_BODY_SIZE: usize = 10 * 1_024 * 1_024;
+
+/// Values of the SHA-256 header meaning that the payload is not checked
+const UNSIGNED_PAYLOADS: &[&str] = &["UNSIGNED-PAYLOAD", "STREAMING-"];
+
+/// Checksum algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// MD5 digest, base64 encoded (`Content-MD5` header)
+    Md5,
+
+    /// SHA-256 digest, hex encoded
+    Sha256,
+}
+
+/// `Content-MD5` header value of a body
+pub fn content_md5(body: &[u8]) -> String {
+    STANDARD.encode(Md5::digest(body))
+}
+
+/// SHA-256 header value of a body
+pub fn content_sha256(body: &[u8]) -> String {
+    encode_hex(&Sha256::digest(body))
+}
+
+/// Encode bytes in hexadecimal
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Expected checksums of a request and their hashers
+struct Checksums {
+    md5: Option<(String, Md5)>,
+    sha256: Option<(String, Sha256)>,
+}
+
+impl Checksums {
+    fn from_headers(headers: &HeaderMap, sha256_header: &HeaderName) -> Self {
+        let header = |name| headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok());
+
+        Self {
+            md5: header(&HeaderName::from_static(CONTENT_MD5_HEADER))
+                .map(|value| (value.trim().to_string(), Md5::new())),
+            sha256: header(sha256_header)
+                .filter(|value| !UNSIGNED_PAYLOADS.iter().any(|unsigned| value.starts_with(unsigned)))
+                .map(|value| (value.trim().to_ascii_lowercase(), Sha256::new())),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.md5.is_none() && self.sha256.is_none()
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        if let Some((_, hasher)) = &mut self.md5 {
+            hasher.update(chunk);
+        }
+        if let Some((_, hasher)) = &mut self.sha256 {
+            hasher.update(chunk);
+        }
+    }
+
+    /// Return true if all the checksums match
+    fn verify(self) -> bool {
+        let md5 = self
+            .md5
+            .is_none_or(|(expected, hasher)| STANDARD.encode(hasher.finalize()) == expected);
+        let sha256 = self
+            .sha256
+            .is_none_or(|(expected, hasher)| encode_hex(&hasher.finalize()) == expected);
+
+        md5 && sha256
+    }
+}
+
+#[derive(Clone)]
+pub struct ChecksumLayer {
+    pub max_body_size: usize,
+    pub sha256_header: HeaderName,
+    pub required: bool,
+    pub response_digests: Vec<ChecksumAlgorithm>,
+}
+
+impl Default for ChecksumLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChecksumLayer {
+    /// Create a new `ChecksumLayer`
+    pub fn new() -> Self {
+        Self {
+            max_body_size: CHECKSUM_DEFAULT_MAX_BODY_SIZE,
+            sha256_header: HeaderName::from_static(CONTENT_SHA256_HEADER),
+            required: false,
+            response_digests: Vec::new(),
+        }
+    }
+
+    /// Update the max body size (larger bodies are rejected with a `413 Payload Too Large`)
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Update the SHA-256 checksum header name
+    pub fn with_sha256_header(mut self, header: HeaderName) -> Self {
+        self.sha256_header = header;
+        self
+    }
+
+    /// Reject the requests without checksum header with a `400 Bad Request`
+    pub fn with_required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Set a digest header on the responses
+    pub fn with_response_digest(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        if !self.response_digests.contains(&algorithm) {
+            self.response_digests.push(algorithm);
+        }
+        self
+    }
+
+    /// Set the digest headers of a response whose size is known and lower than `max_body_size`
+    async fn digest_response(&self, response: Response) -> Response {
+        let size = response.body().size_hint().exact();
+        if self.response_digests.is_empty() || size.is_none_or(|size| size > self.max_body_size as u64) {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, self.max_body_size).await else {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Invalid response body");
+        };
+        for algorithm in &self.response_digests {
+            let (name, value) = match algorithm {
+                ChecksumAlgorithm::Md5 => (HeaderName::from_static(CONTENT_MD5_HEADER), content_md5(&bytes)),
+                ChecksumAlgorithm::Sha256 => (self.sha256_header.clone(), content_sha256(&bytes)),
+            };
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                parts.headers.insert(name, value);
+            }
+        }
+
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+impl<S> Layer<S> for ChecksumLayer {
+    type Service = ChecksumMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ChecksumMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ChecksumMiddleware<S> {
+    inner: S,
+    config: ChecksumLayer,
+}
+
+impl<S> Service<Request<Body>> for ChecksumMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // The body must be read before calling the inner service
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let mut checksums = Checksums::from_headers(request.headers(), &config.sha256_header);
+            let request = if checksums.is_empty() {
+                if config.required {
+                    return Ok(error_response(StatusCode::BAD_REQUEST, "Missing checksum"));
+                }
+                request
+            } else {
+                let (parts, body) = request.into_parts();
+                let mut bytes = BytesMut::new();
+                let mut stream = body.into_data_stream();
+                while let Some(chunk) = stream.next().await {
+                    let Ok(chunk) = chunk else {
+                        return Ok(error_response(StatusCode::BAD_REQUEST, "Invalid body"));
+                    };
+                    if bytes.len() + chunk.len() > config.max_body_size {
+                        return Ok(error_response(StatusCode::PAYLOAD_TOO_LARGE, "Payload too large"));
+                    }
+                    checksums.update(&chunk);
+                    bytes.extend_from_slice(&chunk);
+                }
+                if !checksums.verify() {
+                    return Ok(error_response(StatusCode::BAD_REQUEST, "Checksum mismatch"));
+                }
+
+                Request::from_parts(parts, Body::from(bytes.freeze()))
+            };
+
+            let response = inner.call(request).await?;
+
+            Ok(config.digest_response(response).await)
+        })
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, None);
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn echo_service(request: Request<Body>) -> Result<Response, Infallible> {
+        let body = axum::body::to_bytes(request.into_body(), 1_024).await.unwrap();
+        Ok(Response::new(Body::from(body)))
+    }
+
+    async fn call(layer: ChecksumLayer, request: Request<Body>) -> Response {
+        layer
+            .layer(tower::service_fn(echo_service))
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    fn request(headers: &[(&str, &str)], body: &'static str) -> Request<Body> {
+        let mut request = Request::post("/upload");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(Body::from(body)).unwrap()
+    }
+
+    #[test]
+    fn test_checksums() {
+        // echo -n hello | openssl dgst -md5 -binary | base64
+        assert_eq!(content_md5(b"hello"), "XUFAKrxLKna5cZ2REBfFkg==");
+        // echo -n hello | sha256sum
+        assert_eq!(
+            content_sha256(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_checksum_layer_validation() {
+        let layer = ChecksumLayer::new();
+        let md5 = content_md5(b"hello");
+        let sha256 = content_sha256(b"hello").to_uppercase();
+
+        let response = call(layer.clone(), request(&[(CONTENT_MD5_HEADER, &md5)], "hello")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, "hello");
+
+        let headers = [(CONTENT_MD5_HEADER, md5.as_str()), (CONTENT_SHA256_HEADER, &sha256)];
+        let response = call(layer.clone(), request(&headers, "hello")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = call(layer.clone(), request(&[(CONTENT_MD5_HEADER, &md5)], "hellO")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = call(layer.clone(), request(&[(CONTENT_SHA256_HEADER, &sha256)], "hellO")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = call(
+            layer.clone(),
+            request(&[(CONTENT_SHA256_HEADER, "UNSIGNED-PAYLOAD")], "hellO"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Without checksum
+        let response = call(layer.clone(), request(&[], "hello")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = call(layer.with_required(), request(&[], "hello")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_layer_max_body_size() {
+        let layer = ChecksumLayer::new().with_max_body_size(4);
+        let md5 = content_md5(b"hello");
+
+        let response = call(layer, request(&[(CONTENT_MD5_HEADER, &md5)], "hello")).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_layer_response_digests() {
+        let layer = ChecksumLayer::new()
+            .with_response_digest(ChecksumAlgorithm::Md5)
+            .with_response_digest(ChecksumAlgorithm::Sha256);
+
+        let response = call(layer, request(&[], "hello")).await;
+        assert_eq!(
+            response.headers().get(CONTENT_MD5_HEADER).unwrap(),
+            "XUFAKrxLKna5cZ2REBfFkg=="
+        );
+        assert_eq!(
+            response.headers().get(CONTENT_SHA256_HEADER).unwrap(),
+            &content_sha256(b"hello")
+        );
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, "hello");
+    }
+}
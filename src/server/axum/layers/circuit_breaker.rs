@@ -0,0 +1,483 @@
+//! Circuit breaker
+//!
+//! A [`CircuitBreaker`] stops calling a flapping dependency for a while once too many calls fail:
+//!
+//! - **Closed**: calls are allowed. The outcome of the last `window_size` calls is recorded and
+//!   the circuit opens when the failure rate reaches the threshold (after `minimum_calls` calls).
+//! - **Open**: calls are rejected until `open_duration` has elapsed.
+//! - **Half-open**: `half_open_calls` trial calls are allowed. The circuit closes if they all
+//!   succeed and opens again at the first failure.
+//!
+//! The same breaker can protect inbound routes ([`CircuitBreakerLayer`], 503 response when the
+//! circuit is open, 5xx responses count as failures) and outbound requests
+//! (`HttpClient::with_circuit_breaker` with the `client` feature).
+//!
+//! With the `prometheus` feature, the `circuit_breaker_state` gauge (label `name`) exposes the
+//! state: 0 (closed), 1 (open) or 2 (half-open).
+
+use crate::server::axum::layers::body_from_parts;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Default failure rate opening the circuit
+pub const CIRCUIT_BREAKER_DEFAULT_FAILURE_RATE: f64 = 0.5;
+
+/// Default number of calls used to compute the failure rate
+pub const CIRCUIT_BREAKER_DEFAULT_WINDOW_SIZE: usize = 20;
+
+/// Default minimum number of calls before computing the failure rate
+pub const CIRCUIT_BREAKER_DEFAULT_MINIMUM_CALLS: usize = 10;
+
+/// Default duration of the open state
+pub const CIRCUIT_BREAKER_DEFAULT_OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// Default number of trial calls in the half-open state
+pub const CIRCUIT_BREAKER_DEFAULT_HALF_OPEN_CALLS: usize = 1;
+
+/// Circuit state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Gauge value of the state
+    #[cfg(feature = "metrics")]
+    fn gauge_value(&self) -> f64 {
+        match self {
+            Self::Closed => 0.0,
+            Self::Open => 1.0,
+            Self::HalfOpen => 2.0,
+        }
+    }
+}
+
+impl Display for CircuitState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "closed"),
+            Self::Open => write!(f, "open"),
+            Self::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+/// Mutable state shared by the clones of a circuit breaker
+#[derive(Debug)]
+struct CircuitBreakerState {
+    state: CircuitState,
+    /// Outcomes of the last calls in the closed state (`true` for a failure)
+    outcomes: VecDeque<bool>,
+    opened_at: Option<Instant>,
+    half_open_started: usize,
+    half_open_succeeded: usize,
+}
+
+/// Circuit breaker (clones share the same state)
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    name: Arc<str>,
+    failure_rate: f64,
+    window_size: usize,
+    minimum_calls: usize,
+    open_duration: Duration,
+    half_open_calls: usize,
+    state: Arc<Mutex<CircuitBreakerState>>,
+}
+
+impl CircuitBreaker {
+    /// Create a new circuit breaker with default thresholds
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::layers::circuit_breaker::{CircuitBreaker, CircuitState};
+    /// use std::time::Duration;
+    ///
+    /// let breaker = CircuitBreaker::new("users-service")
+    ///     .with_failure_rate(0.5)
+    ///     .with_window_size(4)
+    ///     .with_minimum_calls(2)
+    ///     .with_open_duration(Duration::from_secs(10));
+    ///
+    /// breaker.record_failure();
+    /// breaker.record_failure();
+    /// assert_eq!(breaker.state(), CircuitState::Open);
+    /// assert!(!breaker.try_acquire());
+    /// ```
+    pub fn new(name: &str) -> Self {
+        let breaker = Self {
+            name: Arc::from(name),
+            failure_rate: CIRCUIT_BREAKER_DEFAULT_FAILURE_RATE,
+            window_size: CIRCUIT_BREAKER_DEFAULT_WINDOW_SIZE,
+            minimum_calls: CIRCUIT_BREAKER_DEFAULT_MINIMUM_CALLS,
+            open_duration: CIRCUIT_BREAKER_DEFAULT_OPEN_DURATION,
+            half_open_calls: CIRCUIT_BREAKER_DEFAULT_HALF_OPEN_CALLS,
+            state: Arc::new(Mutex::new(CircuitBreakerState {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: None,
+                half_open_started: 0,
+                half_open_succeeded: 0,
+            })),
+        };
+        breaker.publish(CircuitState::Closed);
+
+        breaker
+    }
+
+    /// Set the failure rate (between 0 and 1) opening the circuit
+    pub fn with_failure_rate(mut self, failure_rate: f64) -> Self {
+        self.failure_rate = failure_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the number of calls used to compute the failure rate
+    pub fn with_window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size.max(1);
+        self
+    }
+
+    /// Set the minimum number of calls before computing the failure rate
+    pub fn with_minimum_calls(mut self, minimum_calls: usize) -> Self {
+        self.minimum_calls = minimum_calls.max(1);
+        self
+    }
+
+    /// Set the duration of the open state
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    /// Set the number of trial calls in the half-open state
+    pub fn with_half_open_calls(mut self, half_open_calls: usize) -> Self {
+        self.half_open_calls = half_open_calls.max(1);
+        self
+    }
+
+    /// Circuit breaker name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Current state
+    pub fn state(&self) -> CircuitState {
+        match self.state.lock() {
+            Ok(mut state) => {
+                self.refresh(&mut state);
+                state.state
+            }
+            Err(_) => CircuitState::Closed,
+        }
+    }
+
+    /// Return true if a call is allowed
+    ///
+    /// In the half-open state, each allowed call is a trial call and its outcome must be recorded.
+    pub fn try_acquire(&self) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return true;
+        };
+        self.refresh(&mut state);
+
+        match state.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if state.half_open_started < self.half_open_calls => {
+                state.half_open_started += 1;
+                true
+            }
+            CircuitState::HalfOpen => false,
+        }
+    }
+
+    /// Record a successful call
+    pub fn record_success(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        match state.state {
+            CircuitState::Closed => self.push_outcome(&mut state, false),
+            CircuitState::HalfOpen => {
+                state.half_open_succeeded += 1;
+                if state.half_open_succeeded >= self.half_open_calls {
+                    self.transition(&mut state, CircuitState::Closed);
+                }
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Record a failed call
+    pub fn record_failure(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        match state.state {
+            CircuitState::Closed => {
+                self.push_outcome(&mut state, true);
+
+                let calls = state.outcomes.len();
+                let failures = state.outcomes.iter().filter(|failure| **failure).count();
+                if calls >= self.minimum_calls && failures as f64 >= self.failure_rate * calls as f64 {
+                    self.transition(&mut state, CircuitState::Open);
+                }
+            }
+            CircuitState::HalfOpen => self.transition(&mut state, CircuitState::Open),
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Switch from open to half-open once the open duration has elapsed
+    fn refresh(&self, state: &mut CircuitBreakerState) {
+        if state.state == CircuitState::Open
+            && state
+                .opened_at
+                .is_none_or(|opened_at| opened_at.elapsed() >= self.open_duration)
+        {
+            self.transition(state, CircuitState::HalfOpen);
+        }
+    }
+
+    fn push_outcome(&self, state: &mut CircuitBreakerState, failure: bool) {
+        state.outcomes.push_back(failure);
+        while state.outcomes.len() > self.window_size {
+            state.outcomes.pop_front();
+        }
+    }
+
+    fn transition(&self, state: &mut CircuitBreakerState, to: CircuitState) {
+        warn!(name = %self.name, from = %state.state, to = %to, "Circuit breaker state changed");
+
+        state.state = to;
+        state.outcomes.clear();
+        state.half_open_started = 0;
+        state.half_open_succeeded = 0;
+        state.opened_at = (to == CircuitState::Open).then(Instant::now);
+
+        self.publish(to);
+    }
+
+    /// Publish the state gauge
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn publish(&self, state: CircuitState) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("circuit_breaker_state", "name" => self.name.to_string()).set(state.gauge_value());
+    }
+}
+
+/// Layer rejecting requests with a 503 error while the circuit is open
+#[derive(Clone)]
+pub struct CircuitBreakerLayer {
+    pub circuit_breaker: CircuitBreaker,
+}
+
+impl CircuitBreakerLayer {
+    /// Create a new `CircuitBreakerLayer`
+    pub fn new(circuit_breaker: CircuitBreaker) -> Self {
+        Self { circuit_breaker }
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreakerMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CircuitBreakerMiddleware {
+            inner,
+            circuit_breaker: self.circuit_breaker.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CircuitBreakerMiddleware<S> {
+    inner: S,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl<S> Service<Request<Body>> for CircuitBreakerMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let circuit_breaker = self.circuit_breaker.clone();
+        if !circuit_breaker.try_acquire() {
+            return Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let msg = body_from_parts(
+                    &mut parts,
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("Service unavailable: {} circuit is open", circuit_breaker.name()).as_str(),
+                    None,
+                );
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            });
+        }
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            match future.await {
+                Ok(response) => {
+                    if response.status().is_server_error() {
+                        circuit_breaker.record_failure();
+                    } else {
+                        circuit_breaker.record_success();
+                    }
+                    Ok(response)
+                }
+                Err(err) => {
+                    circuit_breaker.record_failure();
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new("test")
+            .with_failure_rate(0.5)
+            .with_window_size(4)
+            .with_minimum_calls(4)
+            .with_open_duration(Duration::from_millis(50))
+            .with_half_open_calls(2)
+    }
+
+    #[test]
+    fn test_closed_to_open_on_failure_rate() {
+        let breaker = breaker();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed, "minimum calls not reached");
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed, "25% of the last 4 calls failed");
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn test_window_keeps_last_calls() {
+        let breaker = breaker();
+        breaker.record_failure();
+        for _ in 0..4 {
+            breaker.record_success();
+        }
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_to_closed() {
+        let breaker = breaker();
+        for _ in 0..4 {
+            breaker.record_failure();
+        }
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        assert!(breaker.try_acquire());
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire(), "only 2 trial calls");
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn test_half_open_to_open() {
+        let breaker = breaker();
+        for _ in 0..4 {
+            breaker.record_failure();
+        }
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let breaker = breaker();
+        let clone = breaker.clone();
+        for _ in 0..4 {
+            clone.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.name(), "test");
+        assert_eq!(breaker.state().to_string(), "open");
+    }
+
+    #[tokio::test]
+    async fn test_layer_fails_fast_when_open() {
+        let status = Arc::new(AtomicU16::new(500));
+        let inner_status = status.clone();
+        let svc = ServiceBuilder::new()
+            .layer(CircuitBreakerLayer::new(breaker()))
+            .service(tower::service_fn(move |_req: Request<Body>| {
+                let status = StatusCode::from_u16(inner_status.load(Ordering::SeqCst)).unwrap();
+                async move { Ok::<_, Infallible>(Response::builder().status(status).body(Body::empty()).unwrap()) }
+            }));
+
+        for _ in 0..4 {
+            let response = svc.clone().oneshot(Request::new(Body::empty())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        // Open: the inner service is not called
+        status.store(200, Ordering::SeqCst);
+        let response = svc.clone().oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(
+            body,
+            r#"{"code":503,"message":"Service unavailable: test circuit is open"}"#
+        );
+
+        // Half-open then closed
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        for _ in 0..2 {
+            let response = svc.clone().oneshot(Request::new(Body::empty())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = svc.oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,354 @@
+//! Content negotiation layer
+//!
+//! [`ContentNegotiationLayer`] checks the media types of the requests before they reach the
+//! handlers:
+//! - `415 Unsupported Media Type` if a request with a body has a missing or unsupported
+//!   `Content-Type`
+//! - `406 Not Acceptable` if the `Accept` header does not accept any of the produced media types
+//!
+//! The errors use the JSON error format of the crate. The accepted media types can be configured
+//! per route group (the first matching route is used, or else the default media types).
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::content_negotiation::{ContentNegotiationLayer, MediaTypes};
+//! use axum::{Router, routing::post};
+//!
+//! let app: Router = Router::new()
+//!     .route("/users", post(|| async { "user" }))
+//!     .route("/files/{id}", post(|| async { "file" }))
+//!     .layer(ContentNegotiationLayer::new().with_route(
+//!         "/files/{*path}",
+//!         MediaTypes::new()
+//!             .with_consumes(&["image/*", "application/pdf"])
+//!             .with_produces(&["application/json"]),
+//!     ));
+//! ```
+
+use super::{body_from_parts, route_matches};
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use mime::Mime;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Media types consumed and produced by a group of routes
+///
+/// Media types can use wildcards (`image/*` or `*/*`). An empty list disables the check.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaTypes {
+    /// Accepted request `Content-Type`
+    pub consumes: Vec<String>,
+
+    /// Media types of the responses, checked against the request `Accept` header
+    pub produces: Vec<String>,
+}
+
+impl MediaTypes {
+    /// Create new `MediaTypes` without restrictions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// JSON requests and responses (`application/json`)
+    pub fn json() -> Self {
+        Self::new()
+            .with_consumes(&[mime::APPLICATION_JSON.as_ref()])
+            .with_produces(&[mime::APPLICATION_JSON.as_ref()])
+    }
+
+    /// Set the accepted request `Content-Type`
+    pub fn with_consumes(mut self, media_types: &[&str]) -> Self {
+        self.consumes = media_types.iter().map(|media_type| media_type.to_string()).collect();
+        self
+    }
+
+    /// Set the media types of the responses
+    pub fn with_produces(mut self, media_types: &[&str]) -> Self {
+        self.produces = media_types.iter().map(|media_type| media_type.to_string()).collect();
+        self
+    }
+
+    /// Check the request headers
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::content_negotiation::MediaTypes;
+    /// use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(header::ACCEPT, HeaderValue::from_static("text/html"));
+    ///
+    /// assert_eq!(MediaTypes::json().check(&headers).unwrap_err().0, StatusCode::NOT_ACCEPTABLE);
+    /// ```
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        if !self.consumes.is_empty() && has_body(headers) {
+            let content_type = headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<Mime>().ok());
+            let supported = content_type.is_some_and(|content_type| {
+                self.consumes
+                    .iter()
+                    .any(|media_type| media_type_matches(media_type, &content_type))
+            });
+
+            if !supported {
+                return Err((
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("Unsupported media type, expected: {}", self.consumes.join(", ")),
+                ));
+            }
+        }
+
+        if !self.produces.is_empty()
+            && let Some(accept) = headers.get(ACCEPT).and_then(|value| value.to_str().ok())
+        {
+            let acceptable = accept
+                .split(',')
+                .filter_map(|range| range.trim().parse::<Mime>().ok())
+                .filter(|range| {
+                    range
+                        .get_param("q")
+                        .is_none_or(|q| q.as_str().parse::<f32>().unwrap_or(1.0) > 0.0)
+                })
+                .any(|range| {
+                    self.produces.iter().any(|media_type| {
+                        media_type
+                            .parse::<Mime>()
+                            .is_ok_and(|media_type| media_type_matches(range.essence_str(), &media_type))
+                    })
+                });
+
+            if !acceptable {
+                return Err((
+                    StatusCode::NOT_ACCEPTABLE,
+                    format!("Not acceptable, available: {}", self.produces.join(", ")),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Media types of a route pattern
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteMediaTypes {
+    pub pattern: String,
+    pub media_types: MediaTypes,
+}
+
+/// Content negotiation layer
+///
+/// The default media types are JSON (`application/json`) requests and responses.
+#[derive(Clone)]
+pub struct ContentNegotiationLayer {
+    pub routes: Vec<RouteMediaTypes>,
+    pub default: MediaTypes,
+}
+
+impl Default for ContentNegotiationLayer {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            default: MediaTypes::json(),
+        }
+    }
+}
+
+impl ContentNegotiationLayer {
+    /// Create a new `ContentNegotiationLayer` accepting JSON requests and responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the media types of the routes without specific media types
+    pub fn with_default(mut self, media_types: MediaTypes) -> Self {
+        self.default = media_types;
+        self
+    }
+
+    /// Set the media types of a route pattern
+    pub fn with_route(mut self, pattern: &str, media_types: MediaTypes) -> Self {
+        self.routes.push(RouteMediaTypes {
+            pattern: pattern.to_string(),
+            media_types,
+        });
+        self
+    }
+
+    /// Media types of a request path (or a matched route)
+    pub fn media_types_of(&self, path: &str) -> &MediaTypes {
+        self.routes
+            .iter()
+            .find(|route| route_matches(&route.pattern, path))
+            .map(|route| &route.media_types)
+            .unwrap_or(&self.default)
+    }
+}
+
+impl<S> Layer<S> for ContentNegotiationLayer {
+    type Service = ContentNegotiationMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ContentNegotiationMiddleware {
+            inner,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ContentNegotiationMiddleware<S> {
+    inner: S,
+    layer: Arc<ContentNegotiationLayer>,
+}
+
+impl<S> Service<Request<Body>> for ContentNegotiationMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let path = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str(),
+            None => request.uri().path(),
+        };
+
+        match self.layer.media_types_of(path).check(request.headers()) {
+            Ok(()) => Box::pin(self.inner.call(request)),
+            Err((status, message)) => Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let msg = body_from_parts(&mut parts, status, &message, None);
+
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            }),
+        }
+    }
+}
+
+/// Check if the request has a body
+fn has_body(headers: &HeaderMap) -> bool {
+    headers.contains_key(TRANSFER_ENCODING)
+        || headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .is_some_and(|length| length > 0)
+}
+
+/// Check if a media type (or a media range with wildcards) matches a media type
+fn media_type_matches(pattern: &str, media_type: &Mime) -> bool {
+    match pattern.split_once('/') {
+        Some(("*", "*")) => true,
+        Some((type_, "*")) => type_.eq_ignore_ascii_case(media_type.type_().as_str()),
+        _ => pattern.eq_ignore_ascii_case(media_type.essence_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    async fn call(app: &Router, uri: &str, headers: &[(&str, &str)], body: &'static str) -> (StatusCode, String) {
+        let mut request = Request::post(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        if !body.is_empty() {
+            request = request.header(CONTENT_LENGTH, body.len());
+        }
+
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_content_negotiation_layer() {
+        let app = Router::new()
+            .route("/users", post(|| async { "ok" }))
+            .route("/files/{id}", post(|| async { "ok" }))
+            .layer(
+                ContentNegotiationLayer::new()
+                    .with_route("/files/{*path}", MediaTypes::new().with_consumes(&["image/*"])),
+            );
+
+        let json = ("content-type", "application/json; charset=utf-8");
+        assert_eq!(call(&app, "/users", &[json], "{}").await.0, StatusCode::OK);
+        assert_eq!(call(&app, "/users", &[], "").await.0, StatusCode::OK);
+        assert_eq!(
+            call(&app, "/users", &[("content-type", "text/plain")], "test").await,
+            (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                r#"{"code":415,"message":"Unsupported media type, expected: application/json"}"#.to_string()
+            )
+        );
+        assert_eq!(
+            call(&app, "/users", &[], "{}").await.0,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        assert_eq!(
+            call(
+                &app,
+                "/users",
+                &[json, ("accept", "text/html, application/*;q=0.5")],
+                "{}"
+            )
+            .await
+            .0,
+            StatusCode::OK
+        );
+        assert_eq!(call(&app, "/users", &[("accept", "*/*")], "").await.0, StatusCode::OK);
+        assert_eq!(
+            call(&app, "/users", &[("accept", "text/html, application/json;q=0")], "").await,
+            (
+                StatusCode::NOT_ACCEPTABLE,
+                r#"{"code":406,"message":"Not acceptable, available: application/json"}"#.to_string()
+            )
+        );
+
+        assert_eq!(
+            call(
+                &app,
+                "/files/1",
+                &[("content-type", "image/png"), ("accept", "text/html")],
+                "png"
+            )
+            .await
+            .0,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(&app, "/files/1", &[json], "{}").await.0,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+}
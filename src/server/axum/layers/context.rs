@@ -0,0 +1,243 @@
+//! Request context layer
+//!
+//! [`ContextLayer`] inserts a [`RequestContext`] in the request extensions, so that handlers
+//! (with the [`Ctx`](crate::server::axum::extractors::Ctx) extractor) and the other layers (e.g.
+//! `LoggerLayer`) read the request ID, the client IP or the matched route from one place.
+//!
+//! It must be added with `Router::layer` (so that the matched route is known), wrapped by the
+//! `RequestIdLayer` (so that the request ID is set) and wrapping the `LoggerLayer`.
+//!
+//! ```rust
+//! use api_tools::server::axum::extractors::Ctx;
+//! use api_tools::server::axum::layers::context::ContextLayer;
+//! use api_tools::server::axum::layers::logger::LoggerLayer;
+//! use api_tools::server::axum::layers::request_id::RequestIdLayer;
+//! use axum::{Router, routing::get};
+//!
+//! async fn handler(Ctx(ctx): Ctx) -> String {
+//!     format!("{:?} {:?}", ctx.request_id, ctx.route)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/users/{id}", get(handler))
+//!     .layer(LoggerLayer)
+//!     .layer(ContextLayer::new())
+//!     .layer(RequestIdLayer::new());
+//! ```
+
+use crate::server::axum::layers::request_id::REQUEST_ID_HEADER;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use crate::value_objects::datetime::UtcDateTime;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, MatchedPath};
+use axum::http::{HeaderMap, HeaderName, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::net::{IpAddr, SocketAddr};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// `X-Forwarded-For` header
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// `X-Real-IP` header
+static X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+/// Request context
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    /// Request ID (`x-request-id` header)
+    pub request_id: Option<String>,
+    /// Instant at which the request was received (to compute latencies)
+    pub started_at: Instant,
+    /// Date at which the request was received
+    pub received_at: UtcDateTime,
+    /// Authenticated principal (read from the `RequestStore` by the `Ctx` extractor)
+    pub principal: Option<String>,
+    /// Client IP address
+    pub client_ip: Option<IpAddr>,
+    /// Matched route (e.g. `/users/{id}`)
+    pub route: Option<String>,
+}
+
+impl RequestContext {
+    /// Elapsed time since the request was received
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Get the context from the request extensions
+    pub fn from_request<B>(request: &Request<B>) -> Option<&Self> {
+        request.extensions().get::<Self>()
+    }
+}
+
+/// Layer inserting a [`RequestContext`] in the request extensions
+#[derive(Clone, Default)]
+pub struct ContextLayer {
+    pub trust_proxy_headers: bool,
+}
+
+impl ContextLayer {
+    /// Create a new `ContextLayer`
+    ///
+    /// The client IP is the peer address (`ConnectInfo<SocketAddr>`), proxy headers are ignored.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the client IP from the `X-Forwarded-For` (first address) and `X-Real-IP` headers
+    ///
+    /// Only enable it behind a proxy which overrides these headers.
+    pub fn with_trust_proxy_headers(mut self, trust_proxy_headers: bool) -> Self {
+        self.trust_proxy_headers = trust_proxy_headers;
+        self
+    }
+}
+
+impl<S> Layer<S> for ContextLayer {
+    type Service = ContextMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ContextMiddleware {
+            inner,
+            trust_proxy_headers: self.trust_proxy_headers,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ContextMiddleware<S> {
+    inner: S,
+    trust_proxy_headers: bool,
+}
+
+impl<S> Service<Request<Body>> for ContextMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let headers = request.headers();
+        let client_ip = self
+            .trust_proxy_headers
+            .then(|| proxy_client_ip(headers))
+            .flatten()
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            });
+
+        let context = RequestContext {
+            request_id: headers
+                .get(REQUEST_ID_HEADER.clone())
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+            started_at: Instant::now(),
+            received_at: UtcDateTime::now(),
+            principal: RequestStore::from_extensions(request.extensions())
+                .and_then(|store| store.get::<Principal>())
+                .map(|principal| principal.0.clone()),
+            client_ip,
+            route: request
+                .extensions()
+                .get::<MatchedPath>()
+                .map(|path| path.as_str().to_string()),
+        };
+        request.extensions_mut().insert(context);
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Client IP from the proxy headers
+fn proxy_client_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    let forwarded_for = headers
+        .get(&X_FORWARDED_FOR)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+
+    forwarded_for.or_else(|| {
+        headers
+            .get(&X_REAL_IP)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|ip| ip.trim().parse().ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::{ServiceBuilder, ServiceExt};
+
+    async fn context(layer: ContextLayer, request: Request<Body>) -> RequestContext {
+        let svc = ServiceBuilder::new()
+            .layer(layer)
+            .service(tower::service_fn(|request: Request<Body>| async move {
+                let context = RequestContext::from_request(&request).cloned().unwrap();
+                let mut response = Response::new(Body::empty());
+                response.extensions_mut().insert(context);
+                Ok::<_, Infallible>(response)
+            }));
+
+        let response = svc.oneshot(request).await.unwrap();
+        response.extensions().get::<RequestContext>().cloned().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_context_layer() {
+        let mut request = Request::builder()
+            .header("x-request-id", "abc")
+            .header("x-forwarded-for", "203.0.113.1, 10.0.0.1")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 2], 1234))));
+        RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal("bob".to_string()));
+
+        let ctx = context(ContextLayer::new(), request).await;
+        assert_eq!(ctx.request_id, Some("abc".to_string()));
+        assert_eq!(ctx.principal, Some("bob".to_string()));
+        assert_eq!(ctx.client_ip, Some(IpAddr::from([10, 0, 0, 2])));
+        assert_eq!(ctx.route, None);
+        assert!(ctx.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_context_layer_proxy_headers() {
+        let layer = ContextLayer::new().with_trust_proxy_headers(true);
+
+        let request = Request::builder()
+            .header("x-forwarded-for", "203.0.113.1, 10.0.0.1")
+            .body(Body::empty())
+            .unwrap();
+        let ctx = context(layer.clone(), request).await;
+        assert_eq!(ctx.client_ip, Some(IpAddr::from([203, 0, 113, 1])));
+
+        let request = Request::builder()
+            .header("x-real-ip", "2001:db8::1")
+            .body(Body::empty())
+            .unwrap();
+        let ctx = context(layer.clone(), request).await;
+        assert_eq!(ctx.client_ip, Some("2001:db8::1".parse().unwrap()));
+
+        let ctx = context(layer, Request::new(Body::empty())).await;
+        assert_eq!(ctx.client_ip, None);
+        assert_eq!(ctx.request_id, None);
+    }
+}
@@ -1,8 +1,56 @@
 //! CORS layer for Axum
+//!
+//! [`cors`] creates a layer from a single [`CorsConfig`]. [`CorsBuilder`] adds exposed headers,
+//! preflight caching (`max_age`), wildcard subdomain origins (`https://*.example.com`) and returns
+//! an error for an invalid origin. [`CorsProfiles`] names several configurations (e.g. `public`,
+//! `internal`) to apply to different routers:
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::cors::{CorsBuilder, CorsProfiles};
+//! use axum::http::{Method, header};
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let profiles = CorsProfiles::new()
+//!     .with_profile("public", CorsBuilder::new().with_origins("*").with_methods(vec![Method::GET]))
+//!     .with_profile(
+//!         "internal",
+//!         CorsBuilder::new()
+//!             .with_origins("https://*.example.com,https://admin.example.org")
+//!             .with_methods(vec![Method::GET, Method::POST])
+//!             .with_headers(vec![header::AUTHORIZATION, header::CONTENT_TYPE])
+//!             .with_exposed_headers(vec![header::HeaderName::from_static("x-request-id")])
+//!             .with_max_age(Duration::from_secs(600)),
+//!     );
+//!
+//! let public: Router = Router::new()
+//!     .route("/status", get(|| async { "ok" }))
+//!     .layer(profiles.layer("public").unwrap());
+//! let internal: Router = Router::new()
+//!     .route("/admin", get(|| async { "admin" }))
+//!     .layer(profiles.layer("internal").unwrap());
+//! let app = public.merge(internal);
+//! ```
 
-use axum::http::{HeaderName, HeaderValue, Method};
+use axum::http::{HeaderName, HeaderValue, Method, Uri};
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
+/// CORS configuration errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CorsError {
+    #[error("Invalid CORS origin: {0}")]
+    InvalidOrigin(String),
+
+    #[error("CORS credentials cannot be allowed for any origin")]
+    CredentialsWithAnyOrigin,
+
+    #[error("Unknown CORS profile: {0}")]
+    UnknownProfile(String),
+}
+
 /// CORS configuration
 ///
 /// # Example
@@ -54,10 +102,17 @@ pub fn cors(config: CorsConfig) -> CorsLayer {
         let origins = allow_origin
             .split(',')
             .filter(|url| *url != "*" && !url.is_empty())
-            .filter_map(|url| url.parse().ok())
+            .filter_map(|url| match url.parse() {
+                Ok(origin) => Some(origin),
+                Err(_) => {
+                    warn!(origin = url, "Invalid CORS origin ignored");
+                    None
+                }
+            })
             .collect::<Vec<HeaderValue>>();
 
         if origins.is_empty() {
+            warn!("No valid CORS origin, any origin is allowed (use `CorsBuilder` to get an error instead)");
             layer.allow_origin(Any)
         } else {
             layer
@@ -69,6 +124,201 @@ pub fn cors(config: CorsConfig) -> CorsLayer {
     }
 }
 
+/// Allowed origin
+#[derive(Debug, Clone, PartialEq)]
+enum OriginPattern {
+    /// Exact origin (e.g. `https://example.com`)
+    Exact(HeaderValue),
+
+    /// Any subdomain of a domain (e.g. `https://*.example.com`, stored as `https://` and `.example.com`)
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    /// Parse an origin (`scheme://host[:port]`), the host may start with a `*.` wildcard
+    fn parse(origin: &str) -> Result<Self, CorsError> {
+        let invalid = || CorsError::InvalidOrigin(origin.to_string());
+        let (scheme, host) = origin.split_once("://").ok_or_else(invalid)?;
+        let (wildcard, host) = match host.strip_prefix("*.") {
+            Some(host) => (true, host),
+            None => (false, host),
+        };
+
+        // The origin must be a valid URI without path, query or wildcard in the host
+        let uri = format!("{scheme}://{host}").parse::<Uri>().map_err(|_| invalid())?;
+        if !matches!(scheme, "http" | "https")
+            || host.is_empty()
+            || host.contains(['*', '/', '?', '#'])
+            || uri.authority().is_none_or(|authority| authority.as_str() != host)
+        {
+            return Err(invalid());
+        }
+
+        match wildcard {
+            true => Ok(Self::Subdomain {
+                scheme: format!("{scheme}://"),
+                suffix: format!(".{host}"),
+            }),
+            false => Ok(Self::Exact(HeaderValue::from_str(origin).map_err(|_| invalid())?)),
+        }
+    }
+
+    /// Check if an `Origin` header matches the pattern
+    fn matches(&self, origin: &HeaderValue) -> bool {
+        match self {
+            Self::Exact(value) => value == origin,
+            Self::Subdomain { scheme, suffix } => origin
+                .to_str()
+                .ok()
+                .and_then(|origin| origin.strip_prefix(scheme.as_str()))
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain
+                            .bytes()
+                            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.')
+                }),
+        }
+    }
+}
+
+/// CORS layer builder
+///
+/// Origins are a comma separated list of `scheme://host[:port]` (`*` for any origin). Credentials
+/// are allowed for explicit origins, unless [`CorsBuilder::with_credentials`] is used.
+#[derive(Debug, Clone, Default)]
+pub struct CorsBuilder {
+    pub origins: String,
+    pub methods: Vec<Method>,
+    pub headers: Vec<HeaderName>,
+    pub exposed_headers: Vec<HeaderName>,
+    pub max_age: Option<Duration>,
+    pub credentials: Option<bool>,
+}
+
+impl CorsBuilder {
+    /// Create a new `CorsBuilder` (no origin allowed)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the allowed origins (comma separated, `*` for any origin)
+    pub fn with_origins(mut self, origins: &str) -> Self {
+        self.origins = origins.to_string();
+        self
+    }
+
+    /// Set the allowed methods
+    pub fn with_methods(mut self, methods: Vec<Method>) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Set the allowed request headers
+    pub fn with_headers(mut self, headers: Vec<HeaderName>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set the response headers exposed to the browser (`Access-Control-Expose-Headers`)
+    pub fn with_exposed_headers(mut self, headers: Vec<HeaderName>) -> Self {
+        self.exposed_headers = headers;
+        self
+    }
+
+    /// Set how long the preflight responses can be cached (`Access-Control-Max-Age`)
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Allow or not the credentials (`Access-Control-Allow-Credentials`)
+    pub fn with_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Build the CORS layer
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::cors::{CorsBuilder, CorsError};
+    ///
+    /// assert!(CorsBuilder::new().with_origins("https://*.example.com").build().is_ok());
+    /// assert_eq!(
+    ///     CorsBuilder::new().with_origins("example.com").build().unwrap_err(),
+    ///     CorsError::InvalidOrigin("example.com".to_string())
+    /// );
+    /// ```
+    pub fn build(&self) -> Result<CorsLayer, CorsError> {
+        let mut layer = CorsLayer::new()
+            .allow_methods(self.methods.clone())
+            .allow_headers(self.headers.clone())
+            .expose_headers(self.exposed_headers.clone());
+        if let Some(max_age) = self.max_age {
+            layer = layer.max_age(max_age);
+        }
+
+        let origins = self
+            .origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .collect::<Vec<_>>();
+        if origins.contains(&"*") {
+            if self.credentials == Some(true) {
+                return Err(CorsError::CredentialsWithAnyOrigin);
+            }
+            return Ok(layer.allow_origin(Any));
+        }
+
+        let patterns = origins
+            .into_iter()
+            .map(OriginPattern::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(layer
+            .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+                patterns.iter().any(|pattern| pattern.matches(origin))
+            }))
+            .allow_credentials(self.credentials.unwrap_or(true)))
+    }
+}
+
+/// Named CORS configurations (e.g. `public`, `internal`) applied to different routers
+#[derive(Debug, Clone, Default)]
+pub struct CorsProfiles {
+    pub profiles: HashMap<String, CorsBuilder>,
+}
+
+impl CorsProfiles {
+    /// Create an empty set of profiles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a profile
+    pub fn with_profile(mut self, name: &str, builder: CorsBuilder) -> Self {
+        self.profiles.insert(name.to_string(), builder);
+        self
+    }
+
+    /// Check that all the profiles are valid (e.g. at startup)
+    pub fn validate(&self) -> Result<(), CorsError> {
+        self.profiles
+            .values()
+            .try_for_each(|builder| builder.build().map(|_| ()))
+    }
+
+    /// Build the CORS layer of a profile
+    pub fn layer(&self, name: &str) -> Result<CorsLayer, CorsError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| CorsError::UnknownProfile(name.to_string()))?
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,4 +446,99 @@ mod tests {
 
         assert_eq!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
     }
+
+    async fn preflight(layer: CorsLayer, origin: &str) -> Response {
+        let svc = ServiceBuilder::new()
+            .layer(layer)
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(ok_response())
+            }));
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+
+        svc.oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn cors_builder_wildcard_subdomain_and_max_age() {
+        let builder = CorsBuilder::new()
+            .with_origins("https://*.example.com")
+            .with_methods(default_methods())
+            .with_headers(default_headers())
+            .with_exposed_headers(vec![HeaderName::from_static("x-request-id")])
+            .with_max_age(Duration::from_secs(600));
+
+        let resp = preflight(builder.build().unwrap(), "https://api.example.com").await;
+        assert_eq!(
+            resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://api.example.com"
+        );
+        assert_eq!(resp.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+        assert_eq!(
+            resp.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(),
+            "true"
+        );
+
+        for origin in [
+            "https://example.com",
+            "http://api.example.com",
+            "https://api.example.com.evil.com",
+            "https://evil.com/.example.com",
+        ] {
+            let resp = preflight(builder.build().unwrap(), origin).await;
+            assert!(
+                resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none(),
+                "origin: {origin}"
+            );
+        }
+    }
+
+    #[test]
+    fn cors_builder_invalid_config() {
+        for origin in [
+            "example.com",
+            "ftp://example.com",
+            "https://example.com/path",
+            "https://*",
+        ] {
+            assert_eq!(
+                CorsBuilder::new().with_origins(origin).build().unwrap_err(),
+                CorsError::InvalidOrigin(origin.to_string())
+            );
+        }
+        assert_eq!(
+            CorsBuilder::new()
+                .with_origins("*")
+                .with_credentials(true)
+                .build()
+                .unwrap_err(),
+            CorsError::CredentialsWithAnyOrigin
+        );
+    }
+
+    #[tokio::test]
+    async fn cors_profiles() {
+        let profiles = CorsProfiles::new()
+            .with_profile("public", CorsBuilder::new().with_origins("*"))
+            .with_profile("internal", CorsBuilder::new().with_origins("https://admin.example.com"));
+        assert!(profiles.validate().is_ok());
+
+        let resp = preflight(profiles.layer("public").unwrap(), "https://anywhere.com").await;
+        assert_eq!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+
+        let resp = preflight(profiles.layer("internal").unwrap(), "https://anywhere.com").await;
+        assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        assert_eq!(
+            profiles.layer("partners").unwrap_err(),
+            CorsError::UnknownProfile("partners".to_string())
+        );
+        let profiles = profiles.with_profile("partners", CorsBuilder::new().with_origins("partner.com"));
+        assert!(profiles.validate().is_err());
+    }
 }
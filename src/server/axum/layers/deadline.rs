@@ -0,0 +1,134 @@
+//! Deadline layer
+//!
+//! [`DeadlineLayer`] stores the [`Deadline`] of the request (now + timeout) in the
+//! `RequestStore`, so that handlers and clients can stop working on a request which is about to
+//! time out. If a deadline is already stored by an outer layer, the earliest one is kept.
+//!
+//! The `ApiRouterBuilder` adds it with its `TimeoutLayer`.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::deadline::DeadlineLayer;
+//! use api_tools::server::axum::request_store::{Deadline, RequestStore};
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/",
+//!         get(|store: RequestStore| async move {
+//!             store.get::<Deadline>().is_some_and(|deadline| !deadline.is_exceeded()).to_string()
+//!         }),
+//!     )
+//!     .layer(DeadlineLayer::new(Duration::from_secs(30)));
+//! ```
+
+use crate::server::axum::request_store::{Deadline, RequestStore};
+use axum::body::Body;
+use axum::http::Request;
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Layer storing the [`Deadline`] of the request in the `RequestStore`
+#[derive(Debug, Clone, Copy)]
+pub struct DeadlineLayer {
+    timeout: Duration,
+}
+
+impl DeadlineLayer {
+    /// Create a new `DeadlineLayer`
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl<S> Layer<S> for DeadlineLayer {
+    type Service = DeadlineMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlineMiddleware {
+            inner,
+            timeout: self.timeout,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DeadlineMiddleware<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S> Service<Request<Body>> for DeadlineMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let store = RequestStore::from_extensions_mut(request.extensions_mut());
+        let deadline = Instant::now() + self.timeout;
+        let deadline = store
+            .get::<Deadline>()
+            .map_or(deadline, |previous| previous.0.min(deadline));
+        store.insert(Deadline(deadline));
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn deadline(layer: DeadlineLayer, request: Request<Body>) -> Instant {
+        let response = layer
+            .layer(tower::service_fn(|request: Request<Body>| async move {
+                let mut response = Response::default();
+                let store = RequestStore::from_extensions(request.extensions()).unwrap();
+                response.extensions_mut().insert(*store.get::<Deadline>().unwrap());
+                Ok::<_, Infallible>(response)
+            }))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        response.extensions().get::<Deadline>().unwrap().0
+    }
+
+    #[tokio::test]
+    async fn test_deadline_layer_stores_the_deadline() {
+        let before = Instant::now();
+        let deadline = deadline(DeadlineLayer::new(Duration::from_secs(30)), Request::new(Body::empty())).await;
+        assert!(deadline >= before + Duration::from_secs(30));
+        assert!(deadline <= Instant::now() + Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_layer_keeps_the_earliest_deadline() {
+        let earliest = Instant::now() + Duration::from_secs(5);
+        let mut request = Request::new(Body::empty());
+        RequestStore::from_extensions_mut(request.extensions_mut()).insert(Deadline(earliest));
+        assert_eq!(
+            deadline(DeadlineLayer::new(Duration::from_secs(30)), request).await,
+            earliest
+        );
+
+        let mut request = Request::new(Body::empty());
+        RequestStore::from_extensions_mut(request.extensions_mut())
+            .insert(Deadline(Instant::now() + Duration::from_secs(60)));
+        assert!(deadline(DeadlineLayer::new(Duration::from_secs(1)), request).await < earliest);
+    }
+}
@@ -0,0 +1,322 @@
+//! Deprecation of endpoints
+//!
+//! [`DeprecationLayer`] adds the deprecation headers to the responses of the deprecated routes
+//! (see [`DeprecatedRoute`]):
+//!
+//! - `Deprecation` (RFC 9745): `@<timestamp>` of the deprecation date, or `true`
+//! - `Sunset` (RFC 8594): HTTP date after which the endpoint may be removed
+//! - `Link`: `rel="successor-version"` and `rel="deprecation"` (documentation) links
+//!
+//! Each call to a deprecated route is logged with the principal of the request (set by an
+//! authentication layer running before this one), so that API owners can track the remaining
+//! consumers before the removal. With the `metrics` feature, the `deprecated_requests_total`
+//! counter (labels `route` and `method`) counts them.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::deprecation::{DeprecatedRoute, DeprecationLayer};
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/v1/users/{id}", get(|| async { "user" }))
+//!     .layer(
+//!         DeprecationLayer::new().with_route(
+//!             DeprecatedRoute::new("/v1/users/{id}")
+//!                 .with_sunset(UtcDateTime::from_rfc3339("2027-01-01T00:00:00Z").unwrap())
+//!                 .with_successor("/v2/users/{id}"),
+//!         ),
+//!     );
+//! ```
+
+use crate::server::axum::layers::route_matches;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use crate::value_objects::datetime::UtcDateTime;
+use axum::body::Body;
+use axum::http::{HeaderName, HeaderValue, Method, Request, header};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// `Deprecation` header
+pub static DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+
+/// `Sunset` header
+pub static SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+/// Deprecated route
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeprecatedRoute {
+    pub pattern: String,
+    pub methods: Vec<Method>,
+    pub deprecated_at: Option<UtcDateTime>,
+    pub sunset: Option<UtcDateTime>,
+    pub successor: Option<String>,
+    pub documentation: Option<String>,
+}
+
+impl DeprecatedRoute {
+    /// Create a new deprecated route (all methods)
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            methods: Vec::new(),
+            deprecated_at: None,
+            sunset: None,
+            successor: None,
+            documentation: None,
+        }
+    }
+
+    /// Only deprecate a method of the route
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.methods.push(method);
+        self
+    }
+
+    /// Set the deprecation date (`Deprecation: true` if not set)
+    pub fn with_deprecation_date(mut self, deprecated_at: UtcDateTime) -> Self {
+        self.deprecated_at = Some(deprecated_at);
+        self
+    }
+
+    /// Set the sunset date
+    pub fn with_sunset(mut self, sunset: UtcDateTime) -> Self {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    /// Set the URL of the successor endpoint
+    pub fn with_successor(mut self, successor: &str) -> Self {
+        self.successor = Some(successor.to_string());
+        self
+    }
+
+    /// Set the URL of the deprecation documentation
+    pub fn with_documentation(mut self, documentation: &str) -> Self {
+        self.documentation = Some(documentation.to_string());
+        self
+    }
+
+    /// Check if a request matches the route
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::layers::deprecation::DeprecatedRoute;
+    /// use axum::http::Method;
+    ///
+    /// let route = DeprecatedRoute::new("/v1/users/{id}").with_method(Method::DELETE);
+    /// assert!(route.matches(&Method::DELETE, "/v1/users/1"));
+    /// assert!(!route.matches(&Method::GET, "/v1/users/1"));
+    /// ```
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        (self.methods.is_empty() || self.methods.contains(method)) && route_matches(&self.pattern, path)
+    }
+
+    /// Deprecation headers of the route
+    fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let mut headers = Vec::new();
+
+        let deprecation = match &self.deprecated_at {
+            Some(deprecated_at) => format!("@{}", deprecated_at.timestamp()),
+            None => "true".to_string(),
+        };
+        headers.extend(HeaderValue::from_str(&deprecation).map(|value| (DEPRECATION_HEADER.clone(), value)));
+
+        if let Some(sunset) = &self.sunset {
+            let sunset = sunset.value().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            headers.extend(HeaderValue::from_str(&sunset).map(|value| (SUNSET_HEADER.clone(), value)));
+        }
+
+        let links = [
+            (&self.successor, "successor-version"),
+            (&self.documentation, "deprecation"),
+        ];
+        for (url, rel) in links {
+            if let Some(url) = url {
+                let link = format!("<{url}>; rel=\"{rel}\"");
+                headers.extend(HeaderValue::from_str(&link).map(|value| (header::LINK, value)));
+            }
+        }
+
+        headers
+    }
+}
+
+/// Layer adding the deprecation headers to the responses of the deprecated routes
+#[derive(Clone, Debug, Default)]
+pub struct DeprecationLayer {
+    pub routes: Vec<DeprecatedRoute>,
+}
+
+impl DeprecationLayer {
+    /// Create a new `DeprecationLayer` without deprecated routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a deprecated route (the first matching route is used)
+    pub fn with_route(mut self, route: DeprecatedRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+}
+
+impl<S> Layer<S> for DeprecationLayer {
+    type Service = DeprecationMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeprecationMiddleware {
+            inner,
+            routes: Arc::from(self.routes.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DeprecationMiddleware<S> {
+    inner: S,
+    routes: Arc<[DeprecatedRoute]>,
+}
+
+/// Count a call to a deprecated route
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn record(route: &DeprecatedRoute, method: &Method) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(
+        "deprecated_requests_total",
+        "route" => route.pattern.clone(),
+        "method" => method.to_string()
+    )
+    .increment(1);
+}
+
+impl<S> Service<Request<Body>> for DeprecationMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let Some(route) = self
+            .routes
+            .iter()
+            .find(|route| route.matches(request.method(), request.uri().path()))
+            .cloned()
+        else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        let principal = RequestStore::from_extensions(request.extensions())
+            .and_then(|store| store.get::<Principal>())
+            .map(|principal| principal.0.clone());
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+
+            warn!(
+                route = %route.pattern,
+                %method,
+                %path,
+                principal = principal.as_deref().unwrap_or("-"),
+                sunset = route.sunset.as_ref().map(ToString::to_string).unwrap_or_default(),
+                "Deprecated endpoint called"
+            );
+            record(&route, &method);
+
+            let headers = response.headers_mut();
+            for (name, value) in route.headers() {
+                headers.append(name, value);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_deprecated_route_headers() {
+        let route = DeprecatedRoute::new("/v1/users")
+            .with_deprecation_date(UtcDateTime::from_rfc3339("2026-01-01T00:00:00Z").unwrap())
+            .with_sunset(UtcDateTime::from_rfc3339("2026-11-06T08:49:37Z").unwrap())
+            .with_successor("https://api.example.com/v2/users")
+            .with_documentation("https://docs.example.com/deprecations");
+
+        assert_eq!(
+            route.headers(),
+            vec![
+                (DEPRECATION_HEADER.clone(), HeaderValue::from_static("@1767225600")),
+                (
+                    SUNSET_HEADER.clone(),
+                    HeaderValue::from_static("Fri, 06 Nov 2026 08:49:37 GMT")
+                ),
+                (
+                    header::LINK,
+                    HeaderValue::from_static(r#"<https://api.example.com/v2/users>; rel="successor-version""#)
+                ),
+                (
+                    header::LINK,
+                    HeaderValue::from_static(r#"<https://docs.example.com/deprecations>; rel="deprecation""#)
+                ),
+            ]
+        );
+        assert_eq!(
+            DeprecatedRoute::new("/v1").headers(),
+            vec![(DEPRECATION_HEADER.clone(), HeaderValue::from_static("true"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deprecation_layer() {
+        let app = Router::new()
+            .route("/v1/users/{id}", get(|| async { "v1" }).delete(|| async { "deleted" }))
+            .route("/v2/users/{id}", get(|| async { "v2" }))
+            .layer(
+                DeprecationLayer::new().with_route(
+                    DeprecatedRoute::new("/v1/users/{id}")
+                        .with_method(Method::GET)
+                        .with_successor("/v2/users/{id}"),
+                ),
+            );
+        let call = |method: Method, uri: &'static str| {
+            app.clone()
+                .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = call(Method::GET, "/v1/users/1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(&DEPRECATION_HEADER).unwrap(), "true");
+        assert_eq!(
+            response.headers().get(header::LINK).unwrap(),
+            r#"</v2/users/{id}>; rel="successor-version""#
+        );
+
+        let response = call(Method::DELETE, "/v1/users/1").await.unwrap();
+        assert!(response.headers().get(&DEPRECATION_HEADER).is_none());
+        let response = call(Method::GET, "/v2/users/1").await.unwrap();
+        assert!(response.headers().get(&DEPRECATION_HEADER).is_none());
+    }
+}
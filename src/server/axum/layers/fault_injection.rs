@@ -0,0 +1,326 @@
+//! Fault injection for game-day drills
+//!
+//! A [`FaultInjector`] holds [`Fault`]s (delay and/or error, with a probability) per
+//! [`FaultTarget`]:
+//!
+//! - **Route**: inbound requests whose matched route (or path) starts with the target, injected
+//!   by the [`FaultInjectionLayer`]
+//! - **Dependency**: outbound requests of a named dependency, injected by
+//!   `HttpClient::with_fault_injection` (`client` feature)
+//!
+//! Faults can be added and removed at runtime (clones share the same faults), so that a drill can
+//! verify the circuit breakers and the timeout budgets end to end:
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::fault_injection::{Fault, FaultInjector, FaultTarget};
+//! use axum::http::StatusCode;
+//! use std::time::Duration;
+//!
+//! let injector = FaultInjector::new();
+//!
+//! // Half of the calls to the users service are slowed down by 2 seconds
+//! injector.set(
+//!     FaultTarget::dependency("users-service"),
+//!     Fault::delay(Duration::from_secs(2)).with_probability(0.5),
+//! );
+//!
+//! // All the requests to `/orders` fail
+//! injector.set(FaultTarget::route("/orders"), Fault::error(StatusCode::SERVICE_UNAVAILABLE));
+//!
+//! // End of the drill
+//! injector.clear();
+//! ```
+
+use crate::server::axum::layers::body_from_parts;
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+/// Message of the injected error responses
+pub const INJECTED_FAULT_MESSAGE: &str = "Injected fault";
+
+/// Fault target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FaultTarget {
+    /// Inbound route (prefix of the matched route or of the path)
+    Route(String),
+
+    /// Outbound dependency name
+    Dependency(String),
+}
+
+impl FaultTarget {
+    /// Inbound route target
+    pub fn route(route: &str) -> Self {
+        Self::Route(route.to_string())
+    }
+
+    /// Outbound dependency target
+    pub fn dependency(name: &str) -> Self {
+        Self::Dependency(name.to_string())
+    }
+}
+
+/// Injected fault
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fault {
+    /// Delay added before the request
+    pub delay: Option<Duration>,
+
+    /// Error status returned instead of calling the service
+    pub error: Option<StatusCode>,
+
+    /// Probability (between 0 and 1) of injecting the fault
+    pub probability: f64,
+}
+
+impl Fault {
+    /// Delay each request
+    pub fn delay(delay: Duration) -> Self {
+        Self {
+            delay: Some(delay),
+            error: None,
+            probability: 1.0,
+        }
+    }
+
+    /// Return an error instead of calling the service
+    pub fn error(status: StatusCode) -> Self {
+        Self {
+            delay: None,
+            error: Some(status),
+            probability: 1.0,
+        }
+    }
+
+    /// Also return an error after the delay
+    pub fn with_error(mut self, status: StatusCode) -> Self {
+        self.error = Some(status);
+        self
+    }
+
+    /// Set the probability (between 0 and 1) of injecting the fault
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// Faults per target (clones share the same faults)
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    faults: Arc<RwLock<HashMap<FaultTarget, Fault>>>,
+}
+
+impl FaultInjector {
+    /// Create an injector without faults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the fault of a target
+    pub fn set(&self, target: FaultTarget, fault: Fault) {
+        info!(?target, ?fault, "Fault injection enabled");
+        if let Ok(mut faults) = self.faults.write() {
+            faults.insert(target, fault);
+        }
+    }
+
+    /// Remove the fault of a target
+    pub fn remove(&self, target: &FaultTarget) {
+        info!(?target, "Fault injection disabled");
+        if let Ok(mut faults) = self.faults.write() {
+            faults.remove(target);
+        }
+    }
+
+    /// Remove all the faults
+    pub fn clear(&self) {
+        info!("Fault injection disabled for all targets");
+        if let Ok(mut faults) = self.faults.write() {
+            faults.clear();
+        }
+    }
+
+    /// Fault to inject for an inbound route, if any (the longest matching prefix wins)
+    pub fn route_fault(&self, route: &str) -> Option<Fault> {
+        let faults = self.faults.read().ok()?;
+        let fault = faults
+            .iter()
+            .filter_map(|(target, fault)| match target {
+                FaultTarget::Route(prefix) if route.starts_with(prefix.as_str()) => Some((prefix.len(), *fault)),
+                _ => None,
+            })
+            .max_by_key(|(length, _)| *length)
+            .map(|(_, fault)| fault)?;
+
+        draw(fault)
+    }
+
+    /// Fault to inject for an outbound dependency, if any
+    pub fn dependency_fault(&self, name: &str) -> Option<Fault> {
+        let faults = self.faults.read().ok()?;
+        let fault = *faults.get(&FaultTarget::Dependency(name.to_string()))?;
+
+        draw(fault)
+    }
+}
+
+/// Return the fault according to its probability
+fn draw(fault: Fault) -> Option<Fault> {
+    if fault.probability >= 1.0 {
+        return Some(fault);
+    }
+    let (random, _) = Uuid::new_v4().as_u64_pair();
+
+    ((random as f64 / u64::MAX as f64) < fault.probability).then_some(fault)
+}
+
+/// Layer injecting the faults of the inbound routes
+#[derive(Clone)]
+pub struct FaultInjectionLayer {
+    pub injector: FaultInjector,
+}
+
+impl FaultInjectionLayer {
+    /// Create a new `FaultInjectionLayer`
+    pub fn new(injector: FaultInjector) -> Self {
+        Self { injector }
+    }
+}
+
+impl<S> Layer<S> for FaultInjectionLayer {
+    type Service = FaultInjectionMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FaultInjectionMiddleware {
+            inner,
+            injector: self.injector.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FaultInjectionMiddleware<S> {
+    inner: S,
+    injector: FaultInjector,
+}
+
+impl<S> Service<Request<Body>> for FaultInjectionMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let route = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str(),
+            None => request.uri().path(),
+        };
+        let Some(fault) = self.injector.route_fault(route) else {
+            return Box::pin(self.inner.call(request));
+        };
+        warn!(route, ?fault, "Fault injected");
+
+        let future = fault.error.is_none().then(|| self.inner.call(request));
+        Box::pin(async move {
+            if let Some(delay) = fault.delay {
+                tokio::time::sleep(delay).await;
+            }
+
+            match (future, fault.error) {
+                (Some(future), _) => future.await,
+                (None, status) => {
+                    let (mut parts, _body) = Response::<Body>::default().into_parts();
+                    let status = status.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                    let msg = body_from_parts(&mut parts, status, INJECTED_FAULT_MESSAGE, None);
+
+                    Ok(Response::from_parts(parts, Body::from(msg)))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::time::Instant;
+    use tower::ServiceExt;
+
+    async fn call(injector: &FaultInjector, path: &str) -> StatusCode {
+        FaultInjectionLayer::new(injector.clone())
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }))
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection_layer() {
+        let injector = FaultInjector::new();
+        assert_eq!(call(&injector, "/orders/1").await, StatusCode::OK);
+
+        injector.set(
+            FaultTarget::route("/orders"),
+            Fault::error(StatusCode::SERVICE_UNAVAILABLE),
+        );
+        injector.set(
+            FaultTarget::route("/orders/slow"),
+            Fault::delay(Duration::from_millis(50)),
+        );
+        assert_eq!(call(&injector, "/orders/1").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(call(&injector, "/users").await, StatusCode::OK);
+
+        let start = Instant::now();
+        assert_eq!(call(&injector, "/orders/slow").await, StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        injector.clear();
+        assert_eq!(call(&injector, "/orders/1").await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_fault_probability() {
+        let injector = FaultInjector::new();
+        injector.set(
+            FaultTarget::dependency("users-service"),
+            Fault::error(StatusCode::BAD_GATEWAY).with_probability(0.0),
+        );
+        assert!(injector.dependency_fault("users-service").is_none());
+
+        injector.set(
+            FaultTarget::dependency("users-service"),
+            Fault::delay(Duration::from_secs(1)).with_error(StatusCode::BAD_GATEWAY),
+        );
+        assert_eq!(
+            injector.dependency_fault("users-service").unwrap().error,
+            Some(StatusCode::BAD_GATEWAY)
+        );
+        assert!(injector.dependency_fault("orders-service").is_none());
+        assert!(injector.route_fault("/users-service").is_none());
+
+        injector.remove(&FaultTarget::dependency("users-service"));
+        assert!(injector.dependency_fault("users-service").is_none());
+    }
+}
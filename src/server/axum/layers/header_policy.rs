@@ -0,0 +1,265 @@
+//! Response header policy layer
+//!
+//! [`HeaderPolicyLayer`] applies a policy to the headers of every response, whatever the handler
+//! or the layer which built it:
+//!
+//! - removes the headers identifying the stack (`Server` and `X-Powered-By` by default)
+//! - removes the headers which must never be echoed back to the client (`Authorization`,
+//!   `Proxy-Authorization` and `Cookie` by default), with a warning as it reveals a bug
+//! - overrides headers (e.g. a generic `Server` value)
+//! - adds default headers, if they are not set by the handler, for all the routes or for a route
+//!   pattern (e.g. `Cache-Control: no-store` for `/api/*`)
+//! - copies the `X-Request-Id` request header to the response, if it is not set
+//!
+//! It complements the `SecurityHeadersLayer`, which only sets the security headers.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::header_policy::HeaderPolicyLayer;
+//! use axum::http::{HeaderValue, header};
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/api/users", get(|| async { "users" }))
+//!     .layer(
+//!         HeaderPolicyLayer::new()
+//!             .with_override(header::SERVER, HeaderValue::from_static("api"))
+//!             .with_route_default("/api/{*path}", header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+//!             .with_request_id(),
+//!     );
+//! ```
+
+use super::request_id::REQUEST_ID_HEADER;
+use super::route_matches;
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Headers identifying the stack, removed by default
+pub const HEADER_POLICY_DEFAULT_REMOVED: &[&str] = &["server", "x-powered-by"];
+
+/// Headers never echoed back, removed by default
+pub const HEADER_POLICY_DEFAULT_NEVER_ECHOED: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+#[derive(Clone, Debug)]
+pub struct HeaderPolicyLayer {
+    pub removed: Vec<HeaderName>,
+    pub never_echoed: Vec<HeaderName>,
+    pub overrides: Vec<(HeaderName, HeaderValue)>,
+    pub defaults: Vec<(HeaderName, HeaderValue)>,
+    pub route_defaults: Vec<(String, HeaderName, HeaderValue)>,
+    pub request_id: bool,
+}
+
+impl Default for HeaderPolicyLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeaderPolicyLayer {
+    /// Create a new `HeaderPolicyLayer` removing the default identifying and never echoed headers
+    pub fn new() -> Self {
+        Self {
+            removed: HEADER_POLICY_DEFAULT_REMOVED
+                .iter()
+                .map(|name| HeaderName::from_static(name))
+                .collect(),
+            never_echoed: HEADER_POLICY_DEFAULT_NEVER_ECHOED
+                .iter()
+                .map(|name| HeaderName::from_static(name))
+                .collect(),
+            overrides: Vec::new(),
+            defaults: Vec::new(),
+            route_defaults: Vec::new(),
+            request_id: false,
+        }
+    }
+
+    /// Remove a header from the responses
+    pub fn with_removed(mut self, name: HeaderName) -> Self {
+        self.removed.push(name);
+        self
+    }
+
+    /// Never echo a header back (removed with a warning)
+    pub fn with_never_echoed(mut self, name: HeaderName) -> Self {
+        self.never_echoed.push(name);
+        self
+    }
+
+    /// Always set a header, replacing the value of the handler
+    pub fn with_override(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.removed.retain(|removed| removed != name);
+        self.overrides.push((name, value));
+        self
+    }
+
+    /// Set a header if the handler did not set it
+    pub fn with_default(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.defaults.push((name, value));
+        self
+    }
+
+    /// Set a header if the handler did not set it, for the paths matching a route pattern
+    /// (`/api/{*path}`, `/users/{id}`)
+    pub fn with_route_default(mut self, pattern: &str, name: HeaderName, value: HeaderValue) -> Self {
+        self.route_defaults.push((pattern.to_string(), name, value));
+        self
+    }
+
+    /// Copy the `X-Request-Id` request header to the response, if it is not set
+    pub fn with_request_id(mut self) -> Self {
+        self.request_id = true;
+        self
+    }
+
+    /// Apply the policy to the headers of a response
+    fn apply(&self, path: &str, request_id: Option<HeaderValue>, headers: &mut HeaderMap) {
+        for name in &self.removed {
+            headers.remove(name);
+        }
+        for name in &self.never_echoed {
+            if headers.remove(name).is_some() {
+                tracing::warn!(header = %name, path, "header removed from the response");
+            }
+        }
+        for (name, value) in &self.overrides {
+            headers.insert(name, value.clone());
+        }
+
+        let defaults = self.defaults.iter().map(|(name, value)| (name, value)).chain(
+            self.route_defaults
+                .iter()
+                .filter(|(pattern, _, _)| route_matches(pattern, path))
+                .map(|(_, name, value)| (name, value)),
+        );
+        for (name, value) in defaults {
+            if !headers.contains_key(name) {
+                headers.insert(name, value.clone());
+            }
+        }
+
+        if let Some(request_id) = request_id {
+            headers.entry(REQUEST_ID_HEADER.clone()).or_insert(request_id);
+        }
+    }
+}
+
+impl<S> Layer<S> for HeaderPolicyLayer {
+    type Service = HeaderPolicyMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HeaderPolicyMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HeaderPolicyMiddleware<S> {
+    inner: S,
+    config: HeaderPolicyLayer,
+}
+
+impl<S> Service<Request<Body>> for HeaderPolicyMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let config = self.config.clone();
+        let path = request.uri().path().to_string();
+        let request_id = config
+            .request_id
+            .then(|| request.headers().get(REQUEST_ID_HEADER.clone()).cloned())
+            .flatten();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            config.apply(&path, request_id, response.headers_mut());
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn call(layer: HeaderPolicyLayer, request: Request<Body>) -> Response {
+        layer
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .header(header::SERVER, "hyper")
+                        .header("x-powered-by", "axum")
+                        .header(header::AUTHORIZATION, "Bearer secret")
+                        .header(header::ETAG, "\"v1\"")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+            }))
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_header_policy_layer_removes_headers() {
+        let response = call(HeaderPolicyLayer::new(), Request::new(Body::empty())).await;
+
+        let headers = response.headers();
+        assert!(!headers.contains_key(header::SERVER));
+        assert!(!headers.contains_key("x-powered-by"));
+        assert!(!headers.contains_key(header::AUTHORIZATION));
+        assert_eq!(headers[header::ETAG], "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn test_header_policy_layer_sets_headers() {
+        let layer = HeaderPolicyLayer::new()
+            .with_override(header::SERVER, HeaderValue::from_static("api"))
+            .with_default(header::ETAG, HeaderValue::from_static("\"default\""))
+            .with_default(header::VARY, HeaderValue::from_static("accept"))
+            .with_route_default(
+                "/api/{*path}",
+                header::CACHE_CONTROL,
+                HeaderValue::from_static("no-store"),
+            )
+            .with_request_id();
+
+        let request = Request::get("/api/users")
+            .header(REQUEST_ID_HEADER.clone(), "req-1")
+            .body(Body::empty())
+            .unwrap();
+        let response = call(layer.clone(), request).await;
+        let headers = response.headers();
+        assert_eq!(headers[header::SERVER], "api");
+        assert_eq!(headers[header::ETAG], "\"v1\"");
+        assert_eq!(headers[header::VARY], "accept");
+        assert_eq!(headers[header::CACHE_CONTROL], "no-store");
+        assert_eq!(headers[REQUEST_ID_HEADER.clone()], "req-1");
+
+        let response = call(layer, Request::get("/health").body(Body::empty()).unwrap()).await;
+        assert!(!response.headers().contains_key(header::CACHE_CONTROL));
+        assert!(!response.headers().contains_key(REQUEST_ID_HEADER.clone()));
+    }
+}
@@ -0,0 +1,361 @@
+//! HMAC signature layer for webhook-style authentication
+//!
+//! The client signs `{timestamp}.{body}` with HMAC-SHA256 and sends two headers:
+//!
+//! - `X-Timestamp`: Unix timestamp (in seconds) of the signature
+//! - `X-Signature`: `sha256=<hex encoded HMAC>`
+//!
+//! The layer rejects requests with a missing or invalid signature, or with a timestamp
+//! outside the tolerance (replay protection), with a `401 Unauthorized` response.
+//! Several secrets can be configured to rotate them without downtime.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::hmac_signature::{HmacSignatureLayer, sign_request};
+//! use std::time::Duration;
+//!
+//! let layer = HmacSignatureLayer::new(&["new-secret", "old-secret"]).with_tolerance(Duration::from_secs(60));
+//!
+//! // Client side
+//! let timestamp = chrono::Utc::now().timestamp();
+//! let signature = sign_request("new-secret", timestamp, br#"{"event":"paid"}"#);
+//! assert!(signature.starts_with("sha256="));
+//! ```
+
+use super::body_from_parts;
+use axum::body::Body;
+use axum::http::{HeaderName, HeaderValue, Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+/// Default signature header
+pub const HMAC_SIGNATURE_HEADER: &str = "x-signature";
+
+/// Default timestamp header
+pub const HMAC_TIMESTAMP_HEADER: &str = "x-timestamp";
+
+/// Default timestamp tolerance (5 minutes)
+pub const HMAC_DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Default max body size (1 MiB)
+pub const HMAC_DEFAULT_MAX_BODY_SIZE: usize = 1_024 * 1_024;
+
+/// Signature prefix
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sign a request body, returning the signature header value
+pub fn sign_request(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let tag = signed_payload_mac(secret, timestamp, body).finalize().into_bytes();
+    let hex = tag.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+
+    format!("{SIGNATURE_PREFIX}{hex}")
+}
+
+/// HMAC of `{timestamp}.{body}`
+fn signed_payload_mac(secret: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Decode an hexadecimal string
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct HmacSignatureLayer {
+    pub secrets: Vec<String>,
+    pub signature_header: HeaderName,
+    pub timestamp_header: HeaderName,
+    pub tolerance: Duration,
+    pub max_body_size: usize,
+}
+
+impl HmacSignatureLayer {
+    /// Create a new `HmacSignatureLayer`
+    ///
+    /// A signature is valid if it matches one of the secrets.
+    pub fn new(secrets: &[&str]) -> Self {
+        Self {
+            secrets: secrets.iter().map(|secret| secret.to_string()).collect(),
+            signature_header: HeaderName::from_static(HMAC_SIGNATURE_HEADER),
+            timestamp_header: HeaderName::from_static(HMAC_TIMESTAMP_HEADER),
+            tolerance: HMAC_DEFAULT_TOLERANCE,
+            max_body_size: HMAC_DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Update the signature header name
+    pub fn with_signature_header(mut self, header: HeaderName) -> Self {
+        self.signature_header = header;
+        self
+    }
+
+    /// Update the timestamp header name
+    pub fn with_timestamp_header(mut self, header: HeaderName) -> Self {
+        self.timestamp_header = header;
+        self
+    }
+
+    /// Update the accepted difference between the timestamp and now
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Update the max body size (larger bodies are rejected with a `413 Payload Too Large`)
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Check the signature of a body
+    fn verify(&self, timestamp: Option<&HeaderValue>, signature: Option<&HeaderValue>, body: &[u8]) -> bool {
+        let Some(timestamp) = timestamp
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok())
+        else {
+            return false;
+        };
+        let now = chrono::Utc::now().timestamp();
+        if now.abs_diff(timestamp) > self.tolerance.as_secs() {
+            return false;
+        }
+
+        let Some(signature) = signature
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.strip_prefix(SIGNATURE_PREFIX).unwrap_or(value))
+            .and_then(decode_hex)
+        else {
+            return false;
+        };
+
+        self.secrets.iter().any(|secret| {
+            signed_payload_mac(secret, timestamp, body)
+                .verify_slice(&signature)
+                .is_ok()
+        })
+    }
+}
+
+impl<S> Layer<S> for HmacSignatureLayer {
+    type Service = HmacSignatureMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HmacSignatureMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HmacSignatureMiddleware<S> {
+    inner: S,
+    config: HmacSignatureLayer,
+}
+
+impl<S> Service<Request<Body>> for HmacSignatureMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // The body must be read before calling the inner service
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+
+            let Ok(bytes) = axum::body::to_bytes(body, config.max_body_size).await else {
+                return Ok(error_response(StatusCode::PAYLOAD_TOO_LARGE, "Payload too large"));
+            };
+
+            let is_valid = config.verify(
+                parts.headers.get(&config.timestamp_header),
+                parts.headers.get(&config.signature_header),
+                &bytes,
+            );
+            if !is_valid {
+                return Ok(error_response(StatusCode::UNAUTHORIZED, "Invalid signature"));
+            }
+
+            inner.call(Request::from_parts(parts, Body::from(bytes))).await
+        })
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, None);
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn echo_service(request: Request<Body>) -> Result<Response, Infallible> {
+        let body = axum::body::to_bytes(request.into_body(), 1_024).await.unwrap();
+        Ok(Response::new(Body::from(body)))
+    }
+
+    fn signed_request(secret: &str, timestamp: i64, body: &'static str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/webhooks")
+            .header(HMAC_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(HMAC_SIGNATURE_HEADER, sign_request(secret, timestamp, body.as_bytes()))
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn call(layer: HmacSignatureLayer, request: Request<Body>) -> (StatusCode, String) {
+        let response = layer
+            .layer(tower::service_fn(echo_service))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn test_sign_request() {
+        // echo -n '1700000000.hello' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign_request("secret", 1_700_000_000, b"hello"),
+            "sha256=47b1df0ab12338b2685470b0d2b37033add7c3b2bc8172f313e77413f1bb78c8"
+        );
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00ff10"), Some(vec![0, 255, 16]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("é0"), None);
+    }
+
+    #[tokio::test]
+    async fn valid_signature_reaches_the_handler_with_the_body() {
+        let layer = HmacSignatureLayer::new(&["secret"]);
+        let (status, body) = call(layer, signed_request("secret", now(), r#"{"event":"paid"}"#)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"event":"paid"}"#);
+    }
+
+    #[tokio::test]
+    async fn rotated_secrets_are_accepted() {
+        let layer = HmacSignatureLayer::new(&["new-secret", "old-secret"]);
+        let (status, _) = call(layer.clone(), signed_request("old-secret", now(), "body")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = call(layer, signed_request("unknown", now(), "body")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, r#"{"code":401,"message":"Invalid signature"}"#);
+    }
+
+    #[tokio::test]
+    async fn expired_or_future_timestamps_are_rejected() {
+        let layer = HmacSignatureLayer::new(&["secret"]).with_tolerance(Duration::from_secs(60));
+        let (status, _) = call(layer.clone(), signed_request("secret", now() - 120, "body")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = call(layer.clone(), signed_request("secret", now() + 120, "body")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = call(layer, signed_request("secret", now() - 30, "body")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn tampered_body_or_missing_headers_are_rejected() {
+        let layer = HmacSignatureLayer::new(&["secret"]);
+        let timestamp = now();
+
+        let request = Request::builder()
+            .method("POST")
+            .header(HMAC_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(HMAC_SIGNATURE_HEADER, sign_request("secret", timestamp, b"original"))
+            .body(Body::from("tampered"))
+            .unwrap();
+        assert_eq!(call(layer.clone(), request).await.0, StatusCode::UNAUTHORIZED);
+
+        let request = Request::builder()
+            .method("POST")
+            .header(HMAC_SIGNATURE_HEADER, sign_request("secret", timestamp, b"body"))
+            .body(Body::from("body"))
+            .unwrap();
+        assert_eq!(call(layer.clone(), request).await.0, StatusCode::UNAUTHORIZED);
+
+        let request = Request::builder()
+            .method("POST")
+            .header(HMAC_TIMESTAMP_HEADER, timestamp.to_string())
+            .body(Body::from("body"))
+            .unwrap();
+        assert_eq!(call(layer, request).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn custom_headers_and_unprefixed_signature() {
+        let layer = HmacSignatureLayer::new(&["secret"])
+            .with_signature_header(HeaderName::from_static("x-hub-signature"))
+            .with_timestamp_header(HeaderName::from_static("x-hub-timestamp"));
+        let timestamp = now();
+        let signature = sign_request("secret", timestamp, b"body");
+
+        let request = Request::builder()
+            .method("POST")
+            .header("x-hub-timestamp", timestamp.to_string())
+            .header("x-hub-signature", signature.trim_start_matches(SIGNATURE_PREFIX))
+            .body(Body::from("body"))
+            .unwrap();
+        assert_eq!(call(layer, request).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn too_large_body_is_rejected() {
+        let layer = HmacSignatureLayer::new(&["secret"]).with_max_body_size(4);
+        let (status, body) = call(layer, signed_request("secret", now(), "too large")).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body, r#"{"code":413,"message":"Payload too large"}"#);
+    }
+}
@@ -1,13 +1,51 @@
 //! Override some HTTP errors
-
-use crate::server::axum::response::ApiError;
+//!
+//! [`HttpErrorsLayer`] rewrites the error responses of some status codes (see [`ErrorRewrite`]) as
+//! JSON errors, e.g. the empty `404` responses of the router. The rewrite decision only depends on
+//! the status and the headers: the other responses are streamed untouched, and a body is only
+//! buffered (up to `body_max_size`) when it becomes the message of the error.
+//!
+//! The rewritten status codes and the passthrough rules are set with [`HttpErrorsRules`].
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::http_errors::{ErrorRewrite, HttpErrorsConfig, HttpErrorsLayer, HttpErrorsRules};
+//! use axum::http::StatusCode;
+//!
+//! let rules = HttpErrorsRules::new()
+//!     .with_rewrite(StatusCode::FORBIDDEN, ErrorRewrite::EmptyBody("Access denied".to_string()))
+//!     .with_passthrough_path("/files");
+//! let layer = HttpErrorsLayer::new(&HttpErrorsConfig { body_max_size: 1_024 * 1_024 }).with_rules(rules);
+//! ```
+
+use crate::server::axum::response::{ApiError, ApiErrorResponse, current_trace_id};
+use axum::Json;
 use axum::body::Body;
-use axum::http::{Request, StatusCode};
+use axum::body::HttpBody;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, Request, StatusCode};
 use axum::response::{IntoResponse, Response};
 use futures::future::BoxFuture;
+use futures::{StreamExt, stream};
+use std::collections::HashMap;
 use std::task::{Context, Poll};
 use tower::{Layer, Service};
 
+/// Rewrite of the responses of a status code
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorRewrite {
+    /// Replace the body with a JSON error with this message
+    Message(String),
+
+    /// Replace an empty body with a JSON error with this message
+    EmptyBody(String),
+
+    /// Use the body as the message of a JSON error (JSON bodies, e.g. `ValidationErrors`, are kept)
+    WrapBody,
+}
+
+/// Default maximum size of the bodies read by the `HttpErrorsLayer` (1 MiB)
+pub const HTTP_ERRORS_DEFAULT_BODY_MAX_SIZE: usize = 1_024 * 1_024;
+
 /// Configuration for the `HttpErrorsLayer`
 #[derive(Clone, Debug)]
 pub struct HttpErrorsConfig {
@@ -15,15 +53,137 @@ pub struct HttpErrorsConfig {
     pub body_max_size: usize,
 }
 
+impl HttpErrorsConfig {
+    /// Create a configuration
+    pub fn new(body_max_size: usize) -> Self {
+        Self { body_max_size }
+    }
+
+    /// Set the maximum size of the bodies in bytes
+    pub fn with_body_max_size(mut self, body_max_size: usize) -> Self {
+        self.body_max_size = body_max_size;
+        self
+    }
+}
+
+impl Default for HttpErrorsConfig {
+    /// 1 MiB body limit ([`HTTP_ERRORS_DEFAULT_BODY_MAX_SIZE`])
+    fn default() -> Self {
+        Self::new(HTTP_ERRORS_DEFAULT_BODY_MAX_SIZE)
+    }
+}
+
+/// Rewritten status codes and passthrough rules of the `HttpErrorsLayer`
+#[derive(Clone, Debug)]
+pub struct HttpErrorsRules {
+    rewrites: HashMap<StatusCode, ErrorRewrite>,
+    passthrough_content_types: Vec<String>,
+    passthrough_paths: Vec<String>,
+}
+
+impl HttpErrorsRules {
+    /// Create the default rules:
+    /// - `404`: empty bodies are replaced by `Resource Not Found`
+    /// - `405`: bodies are replaced by `Method not allowed`
+    /// - `422`: non-JSON bodies are used as the message
+    ///
+    /// Images, audios, videos and server-sent events are never rewritten.
+    pub fn new() -> Self {
+        Self {
+            rewrites: HashMap::from([
+                (
+                    StatusCode::NOT_FOUND,
+                    ErrorRewrite::EmptyBody("Resource Not Found".to_string()),
+                ),
+                (
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    ErrorRewrite::Message(ApiError::MethodNotAllowed.message().to_string()),
+                ),
+                (StatusCode::UNPROCESSABLE_ENTITY, ErrorRewrite::WrapBody),
+            ]),
+            passthrough_content_types: vec![
+                "image/".to_string(),
+                "audio/".to_string(),
+                "video/".to_string(),
+                mime::TEXT_EVENT_STREAM.to_string(),
+            ],
+            passthrough_paths: Vec::new(),
+        }
+    }
+
+    /// Return true if the responses of a status code are rewritten
+    pub fn is_rewritten(&self, status: StatusCode) -> bool {
+        self.rewrites.contains_key(&status)
+    }
+
+    /// Add or replace the rewrite of a status code
+    pub fn with_rewrite(mut self, status: StatusCode, rewrite: ErrorRewrite) -> Self {
+        self.rewrites.insert(status, rewrite);
+        self
+    }
+
+    /// Do not rewrite the responses of a status code
+    pub fn without_rewrite(mut self, status: StatusCode) -> Self {
+        self.rewrites.remove(&status);
+        self
+    }
+
+    /// Never rewrite the responses with a content-type starting with this prefix
+    pub fn with_passthrough_content_type(mut self, content_type: &str) -> Self {
+        self.passthrough_content_types.push(content_type.to_string());
+        self
+    }
+
+    /// Never rewrite the responses of the requests with a path starting with this prefix
+    pub fn with_passthrough_path(mut self, path: &str) -> Self {
+        self.passthrough_paths.push(path.to_string());
+        self
+    }
+
+    /// Rewrite applying to a response, if any
+    fn rewrite_for(&self, status: StatusCode, headers: &HeaderMap) -> Option<&ErrorRewrite> {
+        let rewrite = self.rewrites.get(&status)?;
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if self
+            .passthrough_content_types
+            .iter()
+            .any(|prefix| content_type.starts_with(prefix.as_str()))
+        {
+            return None;
+        }
+
+        Some(rewrite)
+    }
+}
+
+impl Default for HttpErrorsRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone)]
 pub struct HttpErrorsLayer {
     pub config: HttpErrorsConfig,
+    rules: HttpErrorsRules,
 }
 
 impl HttpErrorsLayer {
-    /// Create a new `HttpErrorsLayer`
+    /// Create a new `HttpErrorsLayer` with the default rules
     pub fn new(config: &HttpErrorsConfig) -> Self {
-        Self { config: config.clone() }
+        Self {
+            config: config.clone(),
+            rules: HttpErrorsRules::new(),
+        }
+    }
+
+    /// Replace the rewritten status codes and the passthrough rules
+    pub fn with_rules(mut self, rules: HttpErrorsRules) -> Self {
+        self.rules = rules;
+        self
     }
 }
 
@@ -34,6 +194,7 @@ impl<S> Layer<S> for HttpErrorsLayer {
         HttpErrorsMiddleware {
             inner,
             config: self.config.clone(),
+            rules: self.rules.clone(),
         }
     }
 }
@@ -42,6 +203,7 @@ impl<S> Layer<S> for HttpErrorsLayer {
 pub struct HttpErrorsMiddleware<S> {
     inner: S,
     config: HttpErrorsConfig,
+    rules: HttpErrorsRules,
 }
 
 impl<S> Service<Request<Body>> for HttpErrorsMiddleware<S>
@@ -59,43 +221,84 @@ where
     }
 
     fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let path = request.uri().path();
+        if self
+            .rules
+            .passthrough_paths
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+        {
+            return Box::pin(self.inner.call(request));
+        }
+
         let mut inner = self.inner.clone();
         let config = self.config.clone();
+        let rules = self.rules.clone();
 
         Box::pin(async move {
             let response: Response = inner.call(request).await?;
 
-            // Check the content-type
-            let headers = response.headers();
-            if let Some(content_type) = headers.get("content-type") {
-                let content_type = content_type.to_str().unwrap_or_default();
-                if content_type.starts_with("image/")
-                    || content_type.starts_with("audio/")
-                    || content_type.starts_with("video/")
-                {
-                    return Ok(response);
-                }
-            }
+            // The rewrite decision only depends on the status and the headers
+            let Some(rewrite) = rules.rewrite_for(response.status(), response.headers()) else {
+                return Ok(response);
+            };
 
             let (parts, body) = response.into_parts();
-            match axum::body::to_bytes(body, config.body_max_size).await {
-                Ok(body) => match String::from_utf8(body.to_vec()) {
-                    Ok(body) => match parts.status {
-                        StatusCode::METHOD_NOT_ALLOWED => Ok(ApiError::MethodNotAllowed.into_response()),
-                        StatusCode::UNPROCESSABLE_ENTITY => Ok(ApiError::UnprocessableEntity(body).into_response()),
-                        StatusCode::NOT_FOUND if body.is_empty() => {
-                            Ok(ApiError::NotFound("Resource Not Found".to_owned()).into_response())
-                        }
-                        _ => Ok(Response::from_parts(parts, Body::from(body))),
+            match rewrite {
+                // The body is dropped without being read
+                ErrorRewrite::Message(message) => Ok(error_response(parts.status, message)),
+                ErrorRewrite::EmptyBody(message) => match first_chunk(body).await {
+                    None => Ok(error_response(parts.status, message)),
+                    Some(body) => Ok(Response::from_parts(parts, body)),
+                },
+                // Already formatted errors (e.g. `ValidationErrors`) are kept
+                ErrorRewrite::WrapBody if is_json(&parts.headers) => Ok(Response::from_parts(parts, body)),
+                ErrorRewrite::WrapBody => match axum::body::to_bytes(body, config.body_max_size).await {
+                    Ok(body) => match String::from_utf8(body.to_vec()) {
+                        Ok(body) => Ok(error_response(parts.status, &body)),
+                        Err(err) => Ok(ApiError::InternalServerError(err.to_string()).into_response()),
                     },
-                    Err(err) => Ok(ApiError::InternalServerError(err.to_string()).into_response()),
+                    Err(_) => Ok(ApiError::PayloadTooLarge.into_response()),
                 },
-                Err(_) => Ok(ApiError::PayloadTooLarge.into_response()),
             }
         })
     }
 }
 
+/// Read the first chunk of a body, returning `None` if the body is empty, or else a body streaming
+/// the first chunk and the rest of the body (so that streamed responses are not buffered)
+async fn first_chunk(body: Body) -> Option<Body> {
+    let size_hint = body.size_hint();
+    if size_hint.upper() == Some(0) {
+        return None;
+    }
+    if size_hint.lower() > 0 {
+        return Some(body);
+    }
+
+    let mut stream = body.into_data_stream();
+    loop {
+        match stream.next().await? {
+            // Empty chunks are skipped
+            Ok(chunk) if chunk.is_empty() => continue,
+            first => return Some(Body::from_stream(stream::once(async move { first }).chain(stream))),
+        }
+    }
+}
+
+/// JSON error response
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(ApiErrorResponse::new(status, message, current_trace_id()))).into_response()
+}
+
+/// Return true if the content type is JSON
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(mime::APPLICATION_JSON.as_ref()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,7 +307,7 @@ mod tests {
     use tower::{ServiceBuilder, ServiceExt};
 
     fn layer() -> HttpErrorsLayer {
-        HttpErrorsLayer::new(&HttpErrorsConfig { body_max_size: 1024 })
+        HttpErrorsLayer::new(&HttpErrorsConfig::new(1024))
     }
 
     async fn read_body(response: Response) -> String {
@@ -241,6 +444,28 @@ mod tests {
         assert!(body.contains("validation failed"), "body was: {body}");
     }
 
+    #[tokio::test]
+    async fn json_unprocessable_entity_is_kept() {
+        let svc = ServiceBuilder::new()
+            .layer(layer())
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                let mut errors = crate::server::axum::response::ValidationErrors::new();
+                errors.add("email", "invalid email");
+                Ok::<_, Infallible>(errors.into_response())
+            }));
+
+        let response = svc
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"code":422,"message":[{"field":"email","message":"invalid email"}]}"#
+        );
+    }
+
     /// Binary content-types (image/audio/video) must short-circuit before any
     /// body reading: rewriting them as JSON would corrupt the payload.
     #[tokio::test]
@@ -275,13 +500,13 @@ mod tests {
 
     #[tokio::test]
     async fn body_exceeding_max_size_returns_payload_too_large() {
-        let small_layer = HttpErrorsLayer::new(&HttpErrorsConfig { body_max_size: 4 });
+        let small_layer = HttpErrorsLayer::new(&HttpErrorsConfig::new(4));
         let svc = ServiceBuilder::new()
             .layer(small_layer)
             .service(tower::service_fn(|_req: Request<Body>| async {
                 Ok::<_, Infallible>(
                     Response::builder()
-                        .status(StatusCode::OK)
+                        .status(StatusCode::UNPROCESSABLE_ENTITY)
                         .body(Body::from("hello world"))
                         .unwrap(),
                 )
@@ -296,4 +521,135 @@ mod tests {
         let body = read_body(response).await;
         assert!(body.contains("\"code\":413"), "body was: {body}");
     }
+
+    /// Responses which are not rewritten are not buffered, whatever their size
+    #[tokio::test]
+    async fn large_ok_response_is_not_buffered() {
+        let small_layer = HttpErrorsLayer::new(&HttpErrorsConfig::new(4));
+        let svc = ServiceBuilder::new()
+            .layer(small_layer)
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(StatusCode::OK)
+                        .body(Body::from("hello world"))
+                        .unwrap(),
+                )
+            }));
+
+        let response = svc
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(response).await, "hello world");
+    }
+
+    #[test]
+    fn default_config_and_rules() {
+        let config = HttpErrorsConfig::default();
+        assert_eq!(config.body_max_size, HTTP_ERRORS_DEFAULT_BODY_MAX_SIZE);
+        assert_eq!(config.with_body_max_size(1_024).body_max_size, 1_024);
+
+        // The configuration is still constructible with a struct literal
+        let config = HttpErrorsConfig { body_max_size: 1_024 };
+        assert_eq!(config.body_max_size, 1_024);
+
+        let rules = HttpErrorsRules::default();
+        assert_eq!(rules.rewrites.len(), 3);
+        assert!(rules.is_rewritten(StatusCode::NOT_FOUND));
+        assert!(!rules.is_rewritten(StatusCode::FORBIDDEN));
+        assert!(rules.passthrough_paths.is_empty());
+    }
+
+    #[tokio::test]
+    async fn custom_rewrites_and_passthrough_rules() {
+        let rules = HttpErrorsRules::new()
+            .with_rewrite(
+                StatusCode::FORBIDDEN,
+                ErrorRewrite::Message("Access denied".to_string()),
+            )
+            .without_rewrite(StatusCode::METHOD_NOT_ALLOWED)
+            .with_passthrough_content_type("text/csv")
+            .with_passthrough_path("/files");
+        let svc = ServiceBuilder::new()
+            .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1024)).with_rules(rules))
+            .service(tower::service_fn(|req: Request<Body>| async move {
+                let (status, content_type) = match req.uri().path() {
+                    "/csv" => (StatusCode::FORBIDDEN, "text/csv"),
+                    "/not-allowed" => (StatusCode::METHOD_NOT_ALLOWED, "text/plain"),
+                    _ => (StatusCode::FORBIDDEN, "text/plain"),
+                };
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(status)
+                        .header(header::CONTENT_TYPE, content_type)
+                        .body(Body::from("raw"))
+                        .unwrap(),
+                )
+            }));
+
+        let call = |uri: &'static str| {
+            svc.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let body = read_body(call("/admin").await.unwrap()).await;
+        assert_eq!(body, r#"{"code":403,"message":"Access denied"}"#);
+        assert_eq!(read_body(call("/files/report").await.unwrap()).await, "raw");
+        assert_eq!(read_body(call("/csv").await.unwrap()).await, "raw");
+        assert_eq!(read_body(call("/not-allowed").await.unwrap()).await, "raw");
+    }
+
+    /// A streamed body must not be buffered: only its first chunk is read to know if it is empty
+    #[tokio::test]
+    async fn streamed_404_is_not_buffered() {
+        let svc = ServiceBuilder::new()
+            .layer(layer())
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                let chunks =
+                    futures::stream::once(async { Ok::<_, Infallible>("first") }).chain(futures::stream::pending());
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from_stream(chunks))
+                        .unwrap(),
+                )
+            }));
+
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            svc.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap()),
+        )
+        .await
+        .expect("the body must not be buffered")
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut stream = response.into_body().into_data_stream();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "first");
+    }
+
+    #[tokio::test]
+    async fn empty_streamed_404_is_rewritten() {
+        let svc = ServiceBuilder::new()
+            .layer(layer())
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                let chunks = futures::stream::iter([Ok::<_, Infallible>("")]);
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from_stream(chunks))
+                        .unwrap(),
+                )
+            }));
+
+        let response = svc
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = read_body(response).await;
+        assert!(body.contains("Resource Not Found"), "body was: {body}");
+    }
 }
@@ -0,0 +1,505 @@
+//! Dependency injection layer
+//!
+//! [`Injector`] is a small typed map of services. Each service is registered
+//! with a [`Lifetime`]:
+//!
+//! - [`Lifetime::Singleton`]: one instance shared by every request
+//! - [`Lifetime::PerRequest`]: built once per request, then shared by every
+//!   extractor of that request
+//!
+//! [`InjectorLayer`] attaches the injector to each request and handlers use the
+//! [`Dep`](crate::server::axum::extractors::Dep) extractor to resolve services,
+//! instead of growing nested `State` tuples.
+//!
+//! The services needed by the handlers can be declared with [`Injector::require`]:
+//! [`InjectorLayer::try_new`] checks them when the router is built, so that a missing
+//! service fails at startup instead of at the first request.
+//!
+//! Services behind a trait are registered with [`Injector::singleton_arc`] and resolved with
+//! `Dep<dyn Trait>`. In tests, [`Injector::replace`] swaps a singleton for a fake one, even
+//! after the layer has been built from a clone of the injector.
+//!
+//! # Example
+//!
+//! ```rust
+//! use api_tools::server::axum::extractors::Dep;
+//! use api_tools::server::axum::layers::injector::{Injector, InjectorLayer};
+//! use axum::{Router, routing::get};
+//!
+//! #[derive(Clone)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! let injector = Injector::new()
+//!     .singleton(Config { name: "api".to_string() })
+//!     .per_request(|| std::time::Instant::now())
+//!     .require::<Config>();
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(|Dep(config): Dep<Config>| async move { config.name.clone() }))
+//!     .layer(InjectorLayer::try_new(injector).expect("missing dependencies"));
+//! ```
+
+use crate::server::axum::response::ApiError;
+use axum::body::Body;
+use axum::http::Request;
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// `Arc<T>` of a service, `T` being possibly a trait object
+type Instance = Arc<dyn Any + Send + Sync>;
+type Constructor = Arc<dyn Fn() -> Instance + Send + Sync>;
+
+/// Service lifetime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifetime {
+    /// One instance shared by all requests
+    Singleton,
+
+    /// One instance per request
+    PerRequest,
+}
+
+/// Service registration
+#[derive(Clone)]
+struct Registration {
+    lifetime: Lifetime,
+    constructor: Constructor,
+    /// Singleton instance, shared by the clones of the injector
+    instance: Arc<RwLock<Option<Instance>>>,
+}
+
+impl Registration {
+    /// Get the singleton instance, building it on first use
+    fn singleton(&self) -> Instance {
+        if let Some(instance) = self.instance.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return instance.clone();
+        }
+
+        self.instance
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(|| (self.constructor)())
+            .clone()
+    }
+}
+
+/// Typed map of services with their lifetimes
+#[derive(Clone, Default)]
+pub struct Injector {
+    registrations: HashMap<TypeId, Registration>,
+    required: Vec<(TypeId, &'static str)>,
+}
+
+impl Debug for Injector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Injector => services: {}", self.registrations.len())
+    }
+}
+
+impl Injector {
+    /// Create an empty injector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an already built singleton
+    pub fn singleton<T: Send + Sync + 'static>(self, value: T) -> Self {
+        self.singleton_arc(Arc::new(value))
+    }
+
+    /// Register a shared singleton, possibly behind a trait
+    /// (e.g. `singleton_arc::<dyn Mailer>(Arc::new(SmtpMailer))`)
+    pub fn singleton_arc<T: ?Sized + Send + Sync + 'static>(self, value: Arc<T>) -> Self {
+        let value: Instance = Arc::new(value);
+        let instance = value.clone();
+
+        self.register::<T>(Lifetime::Singleton, Arc::new(move || value.clone()), Some(instance))
+    }
+
+    /// Register a singleton built lazily on first resolution
+    pub fn singleton_with<T, F>(self, constructor: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        self.register::<T>(
+            Lifetime::Singleton,
+            Arc::new(move || Arc::new(Arc::new(constructor()))),
+            None,
+        )
+    }
+
+    /// Register a service built once per request
+    pub fn per_request<T, F>(self, constructor: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        self.register::<T>(
+            Lifetime::PerRequest,
+            Arc::new(move || Arc::new(Arc::new(constructor()))),
+            None,
+        )
+    }
+
+    /// Declare a service required by the handlers, checked by [`Injector::validate`]
+    pub fn require<T: ?Sized + 'static>(mut self) -> Self {
+        let type_id = TypeId::of::<T>();
+        if !self.required.iter().any(|(id, _)| *id == type_id) {
+            self.required.push((type_id, type_name::<T>()));
+        }
+        self
+    }
+
+    /// Check that the required services are registered
+    pub fn validate(&self) -> Result<(), InjectorError> {
+        let missing = self
+            .required
+            .iter()
+            .filter(|(type_id, _)| !self.registrations.contains_key(type_id))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(InjectorError::Missing(missing))
+        }
+    }
+
+    /// Get the lifetime of a registered service
+    pub fn lifetime<T: ?Sized + 'static>(&self) -> Option<Lifetime> {
+        self.registrations.get(&TypeId::of::<T>()).map(|r| r.lifetime)
+    }
+
+    /// Return true if no service is registered
+    pub fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+
+    /// Resolve a singleton outside of any request
+    ///
+    /// Returns `None` if `T` is not registered or is registered per request.
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let registration = self.registrations.get(&TypeId::of::<T>())?;
+        match registration.lifetime {
+            Lifetime::Singleton => Self::downcast(&registration.singleton()),
+            Lifetime::PerRequest => None,
+        }
+    }
+
+    /// Replace the instance of a registered singleton (e.g. by a fake one in a test), returning
+    /// the previous one if it was already built
+    ///
+    /// The instance is shared by the clones of the injector, including the one of an
+    /// [`InjectorLayer`] built before.
+    pub fn replace<T: ?Sized + Send + Sync + 'static>(&self, value: Arc<T>) -> Result<Option<Arc<T>>, InjectorError> {
+        let registration = self
+            .registrations
+            .get(&TypeId::of::<T>())
+            .filter(|registration| registration.lifetime == Lifetime::Singleton)
+            .ok_or(InjectorError::NotRegistered(type_name::<T>()))?;
+
+        let previous = registration
+            .instance
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(Arc::new(value));
+
+        Ok(previous.and_then(|previous| Self::downcast(&previous)))
+    }
+
+    fn register<T: ?Sized + 'static>(
+        mut self,
+        lifetime: Lifetime,
+        constructor: Constructor,
+        instance: Option<Instance>,
+    ) -> Self {
+        self.registrations.insert(
+            TypeId::of::<T>(),
+            Registration {
+                lifetime,
+                constructor,
+                instance: Arc::new(RwLock::new(instance)),
+            },
+        );
+        self
+    }
+
+    fn downcast<T: ?Sized + Send + Sync + 'static>(instance: &Instance) -> Option<Arc<T>> {
+        instance.downcast_ref::<Arc<T>>().cloned()
+    }
+}
+
+/// Injector errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum InjectorError {
+    #[error("Injector layer is missing")]
+    MissingLayer,
+
+    #[error("Dependency not registered: {0}")]
+    NotRegistered(&'static str),
+
+    #[error("Missing dependencies: {}", .0.join(", "))]
+    Missing(Vec<&'static str>),
+}
+
+/// Injector error
+impl From<InjectorError> for ApiError {
+    fn from(value: InjectorError) -> Self {
+        Self::InternalServerError(value.to_string())
+    }
+}
+
+/// Injector attached to a request, holding per-request instances
+#[derive(Clone)]
+pub struct RequestScope {
+    injector: Arc<Injector>,
+    instances: Arc<Mutex<HashMap<TypeId, Instance>>>,
+}
+
+impl RequestScope {
+    /// Create a new request scope
+    pub fn new(injector: Arc<Injector>) -> Self {
+        Self {
+            injector,
+            instances: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Resolve a service for the current request
+    pub fn resolve<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<T>, InjectorError> {
+        let type_id = TypeId::of::<T>();
+        let registration = self
+            .injector
+            .registrations
+            .get(&type_id)
+            .ok_or(InjectorError::NotRegistered(type_name::<T>()))?;
+
+        let instance = match registration.lifetime {
+            Lifetime::Singleton => registration.singleton(),
+            Lifetime::PerRequest => {
+                let mut instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+                instances
+                    .entry(type_id)
+                    .or_insert_with(|| (registration.constructor)())
+                    .clone()
+            }
+        };
+
+        Injector::downcast(&instance).ok_or(InjectorError::NotRegistered(type_name::<T>()))
+    }
+}
+
+/// Layer attaching a [`RequestScope`] of the [`Injector`] to each request, read by the
+/// [`Dep`](crate::server::axum::extractors::Dep) extractor
+#[derive(Clone)]
+pub struct InjectorLayer {
+    injector: Arc<Injector>,
+}
+
+impl InjectorLayer {
+    /// Create a new `InjectorLayer`
+    pub fn new(injector: Injector) -> Self {
+        Self {
+            injector: Arc::new(injector),
+        }
+    }
+
+    /// Create a new `InjectorLayer`, checking the required services first
+    pub fn try_new(injector: Injector) -> Result<Self, InjectorError> {
+        injector.validate()?;
+
+        Ok(Self::new(injector))
+    }
+}
+
+impl<S> Layer<S> for InjectorLayer {
+    type Service = InjectorMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InjectorMiddleware {
+            inner,
+            injector: self.injector.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InjectorMiddleware<S> {
+    inner: S,
+    injector: Arc<Injector>,
+}
+
+impl<S> Service<Request<Body>> for InjectorMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        request
+            .extensions_mut()
+            .insert(RequestScope::new(self.injector.clone()));
+
+        let future = self.inner.call(request);
+        Box::pin(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, PartialEq)]
+    struct Config(&'static str);
+
+    #[test]
+    fn singleton_is_resolved_outside_of_a_request() {
+        let injector = Injector::new().singleton(Config("api"));
+
+        assert_eq!(injector.lifetime::<Config>(), Some(Lifetime::Singleton));
+        assert_eq!(*injector.get::<Config>().unwrap(), Config("api"));
+        assert!(injector.get::<String>().is_none());
+    }
+
+    #[test]
+    fn singleton_with_is_built_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let injector = Injector::new().singleton_with(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Config("lazy")
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let scope_a = RequestScope::new(Arc::new(injector.clone()));
+        let scope_b = RequestScope::new(Arc::new(injector));
+        let a = scope_a.resolve::<Config>().unwrap();
+        let b = scope_b.resolve::<Config>().unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn per_request_is_shared_inside_a_scope_only() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let injector = Arc::new(Injector::new().per_request(move || counter.fetch_add(1, Ordering::SeqCst)));
+        assert!(injector.get::<usize>().is_none(), "per-request services need a scope");
+
+        let scope = RequestScope::new(injector.clone());
+        assert_eq!(*scope.resolve::<usize>().unwrap(), 0);
+        assert_eq!(*scope.resolve::<usize>().unwrap(), 0);
+
+        let other_scope = RequestScope::new(injector);
+        assert_eq!(*other_scope.resolve::<usize>().unwrap(), 1);
+    }
+
+    #[test]
+    fn resolve_unknown_type_returns_not_registered() {
+        let scope = RequestScope::new(Arc::new(Injector::new()));
+        let err = scope.resolve::<Config>().unwrap_err();
+        assert!(matches!(err, InjectorError::NotRegistered(name) if name.ends_with("Config")));
+    }
+
+    #[test]
+    fn required_services_are_validated() {
+        struct Pool;
+
+        let injector = Injector::new()
+            .singleton(Config("api"))
+            .require::<Config>()
+            .require::<Config>();
+        assert_eq!(injector.validate(), Ok(()));
+
+        let injector = injector.require::<Pool>();
+        let err = InjectorLayer::try_new(injector.clone()).err().unwrap();
+        assert_eq!(err, InjectorError::Missing(vec![type_name::<Pool>()]));
+        assert!(err.to_string().starts_with("Missing dependencies: "));
+
+        assert!(InjectorLayer::try_new(injector.singleton(Pool)).is_ok());
+    }
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> &'static str;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> &'static str {
+            "Hello"
+        }
+    }
+
+    struct French;
+
+    impl Greeter for French {
+        fn greet(&self) -> &'static str {
+            "Bonjour"
+        }
+    }
+
+    #[test]
+    fn trait_objects_are_resolved() {
+        let injector = Injector::new()
+            .singleton_arc::<dyn Greeter>(Arc::new(English))
+            .require::<dyn Greeter>();
+        assert_eq!(injector.validate(), Ok(()));
+        assert_eq!(injector.lifetime::<dyn Greeter>(), Some(Lifetime::Singleton));
+        assert_eq!(injector.get::<dyn Greeter>().unwrap().greet(), "Hello");
+
+        let scope = RequestScope::new(Arc::new(injector));
+        assert_eq!(scope.resolve::<dyn Greeter>().unwrap().greet(), "Hello");
+        assert!(scope.resolve::<English>().is_err());
+    }
+
+    #[test]
+    fn replace_is_shared_by_clones() {
+        let injector = Injector::new()
+            .singleton_arc::<dyn Greeter>(Arc::new(English))
+            .per_request(|| Config("request"));
+        let scope = RequestScope::new(Arc::new(injector.clone()));
+
+        let previous = injector.replace::<dyn Greeter>(Arc::new(French)).unwrap();
+        assert_eq!(previous.map(|greeter| greeter.greet()), Some("Hello"));
+        assert_eq!(scope.resolve::<dyn Greeter>().unwrap().greet(), "Bonjour");
+
+        // Only the registered singletons can be replaced
+        assert_eq!(
+            injector.replace(Arc::new(Config("fake"))),
+            Err(InjectorError::NotRegistered(type_name::<Config>()))
+        );
+        assert_eq!(
+            injector.replace::<String>(Arc::new("api".to_string())),
+            Err(InjectorError::NotRegistered(type_name::<String>()))
+        );
+    }
+
+    #[test]
+    fn test_injector_debug() {
+        let injector = Injector::new().singleton(Config("api"));
+        assert_eq!(format!("{injector:?}"), "Injector => services: 1");
+        assert!(Injector::new().is_empty());
+    }
+}
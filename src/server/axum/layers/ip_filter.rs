@@ -0,0 +1,400 @@
+//! IP filter layer
+//!
+//! [`IpFilterLayer`] allows or denies requests by client IP against CIDR ranges:
+//!
+//! - an IP in the deny-list is always rejected
+//! - if the allow-list is not empty, an IP which is not in it is rejected
+//! - an unknown IP is rejected as soon as a rule is configured (the filter fails closed)
+//!
+//! Rejected requests get a `403 Forbidden` response. The decision ([`IpFilterMatch`]) is added to
+//! the request and response extensions, so that handlers and the `LoggerLayer` can read it.
+//!
+//! The client IP comes from the [`RequestContext`] when the `ContextLayer` is set (which handles
+//! the proxy headers), or else from the peer address (`ConnectInfo<SocketAddr>`). Without any of
+//! them, the IP is unknown and the requests are rejected, unless both lists are empty.
+//!
+//! The rules can be reloaded at runtime with a `tokio::sync::watch` channel:
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::ip_filter::{IpFilterLayer, IpFilterRules};
+//! use tokio::sync::watch;
+//!
+//! let rules = IpFilterRules::new(&["10.0.0.0/8"], &["10.0.0.13/32"]).unwrap();
+//! let (sender, receiver) = watch::channel(rules);
+//! let layer = IpFilterLayer::from_watch(receiver);
+//!
+//! // Later, e.g. when the configuration file changes
+//! sender.send_replace(IpFilterRules::new(&["10.0.0.0/8", "192.168.0.0/16"], &[]).unwrap());
+//! ```
+
+use super::body_from_parts;
+use super::context::RequestContext;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// IP filter possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum IpFilterError {
+    #[error("Invalid CIDR: {0}")]
+    InvalidCidr(String),
+}
+
+/// IP network in the CIDR notation (`192.168.0.0/16`, `2001:db8::/32`)
+///
+/// An address without prefix length is a single host (`/32` or `/128`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_length: u8,
+}
+
+impl Cidr {
+    /// Network address
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// Prefix length
+    pub fn prefix_length(&self) -> u8 {
+        self.prefix_length
+    }
+
+    /// Check if an IP is in the network
+    ///
+    /// IPv4-mapped IPv6 addresses (`::ffff:10.0.0.1`) are compared as IPv4 addresses.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_length)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_length)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = IpFilterError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || IpFilterError::InvalidCidr(value.to_string());
+
+        let (address, prefix_length) = match value.trim().split_once('/') {
+            Some((address, prefix_length)) => (address, Some(prefix_length)),
+            None => (value.trim(), None),
+        };
+        let address = IpAddr::from_str(address).map_err(|_| invalid())?;
+        let max_prefix_length = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_length = match prefix_length {
+            Some(prefix_length) => prefix_length.parse::<u8>().map_err(|_| invalid())?,
+            None => max_prefix_length,
+        };
+        if prefix_length > max_prefix_length {
+            return Err(invalid());
+        }
+
+        Ok(Self { address, prefix_length })
+    }
+}
+
+impl TryFrom<&str> for Cidr {
+    type Error = IpFilterError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str(value)
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_length)
+    }
+}
+
+/// Allow-list and deny-list of CIDR ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilterRules {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl IpFilterRules {
+    /// Create rules from an allow-list and a deny-list
+    ///
+    /// An empty allow-list allows every IP which is not in the deny-list.
+    pub fn new(allow: &[&str], deny: &[&str]) -> Result<Self, IpFilterError> {
+        Ok(Self {
+            allow: allow.iter().map(|cidr| cidr.parse()).collect::<Result<_, _>>()?,
+            deny: deny.iter().map(|cidr| cidr.parse()).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Return true if there is no rule (every request is allowed)
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check a client IP against the rules
+    ///
+    /// An unknown IP (`None`) is denied, unless there is no rule at all.
+    pub fn check(&self, client_ip: Option<IpAddr>) -> IpFilterMatch {
+        let Some(ip) = client_ip else {
+            return IpFilterMatch {
+                client_ip,
+                allowed: self.is_empty(),
+                rule: None,
+            };
+        };
+
+        if let Some(rule) = self.deny.iter().find(|cidr| cidr.contains(ip)) {
+            return IpFilterMatch {
+                client_ip,
+                allowed: false,
+                rule: Some(*rule),
+            };
+        }
+
+        let rule = self.allow.iter().find(|cidr| cidr.contains(ip)).copied();
+        IpFilterMatch {
+            client_ip,
+            allowed: self.allow.is_empty() || rule.is_some(),
+            rule,
+        }
+    }
+}
+
+/// Result of the IP filter for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpFilterMatch {
+    /// Client IP (`None` if it is unknown)
+    pub client_ip: Option<IpAddr>,
+    /// The request is allowed
+    pub allowed: bool,
+    /// Matching rule (from the deny-list if the request is denied, else from the allow-list)
+    pub rule: Option<Cidr>,
+}
+
+impl Display for IpFilterMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let decision = if self.allowed { "allowed" } else { "denied" };
+        match self.rule {
+            Some(rule) => write!(f, "{decision} ({rule})"),
+            None => write!(f, "{decision}"),
+        }
+    }
+}
+
+/// Layer filtering requests by client IP
+#[derive(Clone)]
+pub struct IpFilterLayer {
+    pub rules: watch::Receiver<IpFilterRules>,
+}
+
+impl IpFilterLayer {
+    /// Create a new `IpFilterLayer` with static rules
+    pub fn new(rules: IpFilterRules) -> Self {
+        let (_sender, receiver) = watch::channel(rules);
+        Self::from_watch(receiver)
+    }
+
+    /// Create a new `IpFilterLayer` whose rules are reloaded when the channel value changes
+    pub fn from_watch(rules: watch::Receiver<IpFilterRules>) -> Self {
+        Self { rules }
+    }
+}
+
+impl<S> Layer<S> for IpFilterLayer {
+    type Service = IpFilterMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IpFilterMiddleware {
+            inner,
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IpFilterMiddleware<S> {
+    inner: S,
+    rules: watch::Receiver<IpFilterRules>,
+}
+
+impl<S> Service<Request<Body>> for IpFilterMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let client_ip = match RequestContext::from_request(&request) {
+            Some(context) => context.client_ip,
+            None => request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip()),
+        };
+        let ip_match = self.rules.borrow().check(client_ip);
+
+        if !ip_match.allowed {
+            let (mut parts, _body) = Response::<Body>::default().into_parts();
+            let msg = body_from_parts(
+                &mut parts,
+                StatusCode::FORBIDDEN,
+                "Forbidden: client IP not allowed",
+                None,
+            );
+            parts.extensions.insert(ip_match);
+
+            return Box::pin(async move { Ok(Response::from_parts(parts, Body::from(msg))) });
+        }
+
+        request.extensions_mut().insert(ip_match);
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            response.extensions_mut().insert(ip_match);
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    async fn call(layer: &IpFilterLayer, ip: Option<[u8; 4]>) -> Response {
+        let mut request = Request::new(Body::empty());
+        if let Some(ip) = ip {
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((ip, 1234))));
+        }
+
+        layer
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cidr_parse_and_contains() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert_eq!(cidr.to_string(), "10.1.0.0/16");
+        assert!(cidr.contains(IpAddr::from([10, 1, 200, 3])));
+        assert!(!cidr.contains(IpAddr::from([10, 2, 0, 1])));
+        assert!(cidr.contains("::ffff:10.1.0.1".parse().unwrap()));
+
+        let cidr = Cidr::try_from("2001:db8::/32").unwrap();
+        assert!(cidr.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!cidr.contains(IpAddr::from([10, 1, 0, 1])));
+
+        assert_eq!(Cidr::try_from("192.0.2.1").unwrap().prefix_length(), 32);
+        assert!(
+            Cidr::try_from("0.0.0.0/0")
+                .unwrap()
+                .contains(IpAddr::from([1, 2, 3, 4]))
+        );
+        for invalid in ["10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/x", ""] {
+            assert_eq!(
+                Cidr::try_from(invalid),
+                Err(IpFilterError::InvalidCidr(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ip_filter_rules_check() {
+        let rules = IpFilterRules::new(&["10.0.0.0/8"], &["10.0.0.13"]).unwrap();
+
+        let ip_match = rules.check(Some(IpAddr::from([10, 0, 0, 1])));
+        assert!(ip_match.allowed);
+        assert_eq!(ip_match.to_string(), "allowed (10.0.0.0/8)");
+
+        let ip_match = rules.check(Some(IpAddr::from([10, 0, 0, 13])));
+        assert!(!ip_match.allowed);
+        assert_eq!(ip_match.to_string(), "denied (10.0.0.13/32)");
+
+        assert_eq!(rules.check(Some(IpAddr::from([192, 0, 2, 1]))).to_string(), "denied");
+        assert!(!rules.check(None).allowed);
+
+        let rules = IpFilterRules::new(&[], &["192.0.2.0/24"]).unwrap();
+        assert!(rules.check(Some(IpAddr::from([10, 0, 0, 1]))).allowed);
+        assert!(!rules.check(Some(IpAddr::from([192, 0, 2, 1]))).allowed);
+        assert!(!rules.check(None).allowed, "unknown IPs are denied by a deny-list");
+
+        let rules = IpFilterRules::default();
+        assert!(rules.is_empty());
+        assert!(rules.check(None).allowed);
+
+        assert!(IpFilterRules::new(&["invalid"], &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter_layer() {
+        let layer = IpFilterLayer::new(IpFilterRules::new(&["10.0.0.0/8"], &[]).unwrap());
+
+        let response = call(&layer, Some([10, 0, 0, 1])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.extensions().get::<IpFilterMatch>().unwrap().allowed);
+
+        let response = call(&layer, Some([192, 0, 2, 1])).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!response.extensions().get::<IpFilterMatch>().unwrap().allowed);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(
+            &body[..],
+            br#"{"code":403,"message":"Forbidden: client IP not allowed"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter_layer_denies_unknown_ips() {
+        let layer = IpFilterLayer::new(IpFilterRules::new(&[], &["192.0.2.0/24"]).unwrap());
+        let response = call(&layer, None).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.extensions().get::<IpFilterMatch>().unwrap().client_ip, None);
+
+        let layer = IpFilterLayer::new(IpFilterRules::default());
+        assert_eq!(call(&layer, None).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter_layer_reload() {
+        let (sender, receiver) = watch::channel(IpFilterRules::new(&["10.0.0.0/8"], &[]).unwrap());
+        let layer = IpFilterLayer::from_watch(receiver);
+        assert_eq!(call(&layer, Some([192, 0, 2, 1])).await.status(), StatusCode::FORBIDDEN);
+
+        sender.send_replace(IpFilterRules::new(&["192.0.2.0/24"], &[]).unwrap());
+        assert_eq!(call(&layer, Some([192, 0, 2, 1])).await.status(), StatusCode::OK);
+        assert_eq!(call(&layer, Some([10, 0, 0, 1])).await.status(), StatusCode::FORBIDDEN);
+    }
+}
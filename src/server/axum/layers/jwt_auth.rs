@@ -0,0 +1,427 @@
+//! JWT authentication layer
+//!
+//! [`JwtAuthLayer`] parses the bearer token of the `Authorization` header with a [`Jwt`] and
+//! rejects the request with a `401 Unauthorized` response if it is missing or invalid.
+//!
+//! For a valid token, the claims are added to the request extensions (use the `Extension`
+//! extractor to get them), and the [`JwtClaims::subject`] and [`JwtClaims::grants`] are stored in
+//! the `RequestStore` as the `Principal` and the `Grants` checked by the `AuthorizeLayer`. The
+//! [`JwtClaims::tenant`] is stored as the `Tenant`.
+//!
+//! With [`JwtAuthLayer::with_cookie`], the token is read from the [`AuthCookie`] when there is no
+//! `Authorization` header (browser clients).
+//!
+//! If the [`Jwt`] has a revocation store (`Jwt::set_revocation_store`), the tokens whose
+//! [`JwtClaims::token_id`] is revoked are rejected with a `401 Unauthorized` response.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::authorize::AuthorizeLayer;
+//! use api_tools::server::axum::layers::jwt_auth::{JwtAuthLayer, JwtClaims};
+//! use api_tools::server::axum::security::authorization::{Grant, Grants, Requirement};
+//! use api_tools::server::axum::security::jwt::Jwt;
+//! use axum::{Extension, Router, routing::get};
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Clone, Deserialize)]
+//! struct Claims {
+//!     sub: String,
+//!     roles: Vec<String>,
+//!     scope: String,
+//! }
+//!
+//! impl JwtClaims for Claims {
+//!     fn subject(&self) -> Option<&str> {
+//!         Some(&self.sub)
+//!     }
+//!
+//!     fn grants(&self) -> Grants {
+//!         let roles = self.roles.iter().map(String::as_str).collect::<Vec<_>>();
+//!         Grants::new().with_roles(&roles).with_scope_claim(&self.scope)
+//!     }
+//! }
+//!
+//! async fn delete_user(Extension(claims): Extension<Claims>) -> String {
+//!     format!("deleted by {}", claims.sub)
+//! }
+//!
+//! let jwt = Jwt::init("HS256", 15, 24, Some("secret"), Some("secret"), None).unwrap();
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/users/{id}",
+//!         get(delete_user).layer(AuthorizeLayer::new(Requirement::any_of(&[
+//!             Grant::role("admin"),
+//!             Grant::scope("users:delete"),
+//!         ]))),
+//!     )
+//!     .layer(JwtAuthLayer::<Claims>::new(jwt));
+//! ```
+
+use super::body_from_parts;
+use crate::server::axum::request_store::{Principal, RequestStore, Tenant};
+use crate::server::axum::security::authorization::Grants;
+use crate::server::axum::security::jwt::access_token::AccessToken;
+use crate::server::axum::security::jwt::cookie::AuthCookie;
+use crate::server::axum::security::jwt::{Jwt, JwtError};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Claims parsed by the [`JwtAuthLayer`]
+pub trait JwtClaims: Clone + Debug + DeserializeOwned + Send + Sync + 'static {
+    /// Subject of the token, stored as the `Principal`
+    fn subject(&self) -> Option<&str> {
+        None
+    }
+
+    /// Tenant of the token, stored as the `Tenant`
+    fn tenant(&self) -> Option<&str> {
+        None
+    }
+
+    /// Roles, permissions and scopes of the token, checked by the `AuthorizeLayer`
+    fn grants(&self) -> Grants {
+        Grants::default()
+    }
+
+    /// ID of the token (`jti` claim), checked against the revocation store of the `Jwt`
+    fn token_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Return true for a refresh token, rejected by the [`JwtAuthLayer`]
+    fn is_refresh_token(&self) -> bool {
+        false
+    }
+}
+
+/// Layer authenticating requests with a JWT bearer token
+pub struct JwtAuthLayer<P> {
+    pub jwt: Jwt,
+    pub cookie: Option<AuthCookie>,
+    claims: PhantomData<fn() -> P>,
+}
+
+impl<P> Clone for JwtAuthLayer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            jwt: self.jwt.clone(),
+            cookie: self.cookie.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<P: JwtClaims> JwtAuthLayer<P> {
+    /// Create a new `JwtAuthLayer`
+    pub fn new(jwt: Jwt) -> Self {
+        Self {
+            jwt,
+            cookie: None,
+            claims: PhantomData,
+        }
+    }
+
+    /// Also read the token from a cookie, if the `Authorization` header is missing
+    ///
+    /// The cookie is added to the request extensions for the `AccessToken` extractor.
+    pub fn with_cookie(mut self, cookie: AuthCookie) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+}
+
+impl<S, P> Layer<S> for JwtAuthLayer<P> {
+    type Service = JwtAuthMiddleware<S, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuthMiddleware {
+            inner,
+            jwt: self.jwt.clone(),
+            cookie: self.cookie.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+pub struct JwtAuthMiddleware<S, P> {
+    inner: S,
+    jwt: Jwt,
+    cookie: Option<AuthCookie>,
+    claims: PhantomData<fn() -> P>,
+}
+
+impl<S: Clone, P> Clone for JwtAuthMiddleware<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            jwt: self.jwt.clone(),
+            cookie: self.cookie.clone(),
+            claims: PhantomData,
+        }
+    }
+}
+
+impl<S, P> Service<Request<Body>> for JwtAuthMiddleware<S, P>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    P: JwtClaims,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let token = AccessToken::extract_bearer_token_from_headers(request.headers()).or_else(|| {
+            self.cookie
+                .as_ref()
+                .and_then(|cookie| cookie.token_from_headers(request.headers()))
+        });
+        let claims = match token {
+            Some(token) => self.jwt.parse::<P>(&token).map_err(|err| match err {
+                JwtError::ExpiredToken => "Expired token",
+                _ => "Invalid token",
+            }),
+            None => Err("Missing or invalid token"),
+        };
+
+        let claims = match claims {
+            Ok(claims) if claims.is_refresh_token() => {
+                return Box::pin(async move { Ok(error_response(StatusCode::UNAUTHORIZED, "Invalid token")) });
+            }
+            Ok(claims) => claims,
+            Err(message) => return Box::pin(async move { Ok(error_response(StatusCode::UNAUTHORIZED, message)) }),
+        };
+
+        let jti = claims
+            .token_id()
+            .filter(|_| self.jwt.has_revocation_store())
+            .map(str::to_string);
+        let store = RequestStore::from_extensions_mut(request.extensions_mut());
+        if let Some(subject) = claims.subject() {
+            store.insert(Principal(subject.to_string()));
+        }
+        if let Some(tenant) = claims.tenant() {
+            store.insert(Tenant(tenant.to_string()));
+        }
+        store.insert(claims.grants());
+        request.extensions_mut().insert(claims);
+        if let Some(cookie) = &self.cookie {
+            request.extensions_mut().insert(cookie.clone());
+        }
+
+        let Some(jti) = jti else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        // The revocation store is asynchronous: the inner service is called in the future
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let jwt = self.jwt.clone();
+
+        Box::pin(async move {
+            match jwt.is_revoked(&jti).await {
+                Ok(false) => inner.call(request).await,
+                Ok(true) => Ok(error_response(StatusCode::UNAUTHORIZED, "Revoked token")),
+                Err(err) => {
+                    tracing::error!(error = %err, "token revocation store failure");
+                    Ok(error_response(StatusCode::SERVICE_UNAVAILABLE, "Service unavailable"))
+                }
+            }
+        })
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, None);
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::security::authorization::Grant;
+    use crate::server::axum::security::jwt::revocation::MemoryRevocationStore;
+    use crate::value_objects::datetime::UtcDateTime;
+    use axum::http::header;
+    use chrono::TimeDelta;
+    use serde::{Deserialize, Serialize};
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Claims {
+        sub: String,
+        exp: i64,
+        scope: String,
+        jti: String,
+        tid: String,
+    }
+
+    impl JwtClaims for Claims {
+        fn subject(&self) -> Option<&str> {
+            Some(&self.sub)
+        }
+
+        fn tenant(&self) -> Option<&str> {
+            Some(&self.tid)
+        }
+
+        fn grants(&self) -> Grants {
+            Grants::new().with_scope_claim(&self.scope)
+        }
+
+        fn token_id(&self) -> Option<&str> {
+            Some(&self.jti)
+        }
+    }
+
+    fn jwt() -> Jwt {
+        Jwt::init("HS256", 15, 24, Some("secret"), Some("secret"), None).unwrap()
+    }
+
+    fn token(exp: UtcDateTime) -> String {
+        let claims = Claims {
+            sub: "bob".to_string(),
+            exp: exp.timestamp(),
+            scope: "users:read".to_string(),
+            jti: "token-1".to_string(),
+            tid: "acme".to_string(),
+        };
+        jwt().generate(claims, exp).unwrap().token
+    }
+
+    async fn call(authorization: Option<String>) -> Response {
+        call_with_jwt(jwt(), authorization).await
+    }
+
+    async fn call_with_jwt(jwt: Jwt, authorization: Option<String>) -> Response {
+        let mut request = Request::new(Body::empty());
+        if let Some(authorization) = authorization {
+            request
+                .headers_mut()
+                .insert(header::AUTHORIZATION, authorization.parse().unwrap());
+        }
+
+        call_layer(JwtAuthLayer::<Claims>::new(jwt), request).await
+    }
+
+    async fn call_layer(layer: JwtAuthLayer<Claims>, request: Request<Body>) -> Response {
+        layer
+            .layer(tower::service_fn(|request: Request<Body>| async move {
+                let store = RequestStore::from_extensions(request.extensions()).unwrap();
+                let body = format!(
+                    "{} {} {} {}",
+                    request.extensions().get::<Claims>().unwrap().sub,
+                    store.get::<Principal>().unwrap().0,
+                    store.get::<Grants>().unwrap().contains(&Grant::scope("users:read")),
+                    store.get::<Tenant>().unwrap().0,
+                );
+                Ok::<_, Infallible>(Response::new(Body::from(body)))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer() {
+        let response = call(Some(format!(
+            "Bearer {}",
+            token(UtcDateTime::now().add(TimeDelta::hours(1)))
+        )))
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "bob bob true acme");
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer_rejects_invalid_tokens() {
+        let response = call(None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            body(response).await,
+            r#"{"code":401,"message":"Missing or invalid token"}"#
+        );
+
+        let response = call(Some("Bearer invalid".to_string())).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response).await, r#"{"code":401,"message":"Invalid token"}"#);
+
+        let response = call(Some(format!(
+            "Bearer {}",
+            token(UtcDateTime::now().sub(TimeDelta::hours(1)))
+        )))
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response).await, r#"{"code":401,"message":"Expired token"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer_rejects_revoked_tokens() {
+        let mut jwt = jwt();
+        jwt.set_revocation_store(MemoryRevocationStore::new());
+        let exp = UtcDateTime::now().add(TimeDelta::hours(1));
+
+        let response = call_with_jwt(jwt.clone(), Some(format!("Bearer {}", token(exp.clone())))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        jwt.revoke("token-1", exp.clone()).await.unwrap();
+        let response = call_with_jwt(jwt, Some(format!("Bearer {}", token(exp)))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response).await, r#"{"code":401,"message":"Revoked token"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer_with_cookie() {
+        let layer = JwtAuthLayer::<Claims>::new(jwt()).with_cookie(AuthCookie::new().with_name("session"));
+        let cookie = format!(
+            "theme=dark; session={}",
+            token(UtcDateTime::now().add(TimeDelta::hours(1)))
+        );
+
+        let request = Request::get("/")
+            .header(header::COOKIE, &cookie)
+            .body(Body::empty())
+            .unwrap();
+        let response = call_layer(layer.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "bob bob true acme");
+
+        // The `Authorization` header takes precedence over the cookie
+        let request = Request::get("/")
+            .header(header::COOKIE, &cookie)
+            .header(header::AUTHORIZATION, "Bearer invalid")
+            .body(Body::empty())
+            .unwrap();
+        let response = call_layer(layer, request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // The cookie is ignored without `with_cookie`
+        let request = Request::get("/")
+            .header(header::COOKIE, &cookie)
+            .body(Body::empty())
+            .unwrap();
+        let response = call_layer(JwtAuthLayer::<Claims>::new(jwt()), request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,356 @@
+//! Load shedding
+//!
+//! A [`LoadShedder`] tracks an exponential moving average of the latency of the requests and
+//! considers the service overloaded when it exceeds the target latency, or when an external load
+//! signal (queue depth, CPU, etc.) reports it. While overloaded, [`LoadSheddingLayer`] rejects a
+//! configurable fraction of the requests with a `503 Service Unavailable` error and a
+//! `Retry-After` header, instead of letting queues grow until every request times out.
+//!
+//! Requests matching a priority route (`/health` by default) are never shed, so that the
+//! orchestrator does not restart an overloaded but alive instance.
+//!
+//! With the `metrics` feature, the `load_shedding_latency_seconds` gauge and the
+//! `load_shedding_rejected_total` counter (label `name`) are published.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::load_shedding::{LoadShedder, LoadSheddingLayer};
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let shedder = LoadShedder::new("api", Duration::from_millis(500)).with_shed_fraction(0.3);
+//! let app: Router = Router::new()
+//!     .route("/health", get(|| async { "ok" }))
+//!     .route("/reports", get(|| async { "report" }))
+//!     .layer(LoadSheddingLayer::new(shedder).with_priority_route("/internal/{*path}"));
+//! ```
+
+use crate::server::axum::layers::{body_from_parts, route_matches};
+use axum::body::Body;
+use axum::http::{HeaderValue, Request, StatusCode, header};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Default fraction of the requests rejected while overloaded
+pub const LOAD_SHEDDING_DEFAULT_SHED_FRACTION: f64 = 0.5;
+
+/// Default weight of the last latency in the moving average
+pub const LOAD_SHEDDING_DEFAULT_SMOOTHING: f64 = 0.1;
+
+/// Default `Retry-After` value
+pub const LOAD_SHEDDING_DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// External load signal, `true` if the service is overloaded
+pub type LoadSignalFn = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Load shedder (clones share the same state)
+#[derive(Clone)]
+pub struct LoadShedder {
+    name: Arc<str>,
+    target_latency: Duration,
+    shed_fraction: f64,
+    smoothing: f64,
+    retry_after: Duration,
+    signal: Option<LoadSignalFn>,
+    /// Moving average of the latency (in seconds)
+    latency: Arc<Mutex<Option<f64>>>,
+    /// Number of requests received while overloaded
+    overloaded_requests: Arc<AtomicU64>,
+}
+
+impl Debug for LoadShedder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadShedder")
+            .field("name", &self.name)
+            .field("target_latency", &self.target_latency)
+            .field("shed_fraction", &self.shed_fraction)
+            .field("smoothing", &self.smoothing)
+            .field("retry_after", &self.retry_after)
+            .field("latency", &self.average_latency())
+            .finish()
+    }
+}
+
+impl LoadShedder {
+    /// Create a new load shedder considering the service overloaded when the average latency
+    /// exceeds `target_latency`
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::layers::load_shedding::LoadShedder;
+    /// use std::time::Duration;
+    ///
+    /// let shedder = LoadShedder::new("api", Duration::from_millis(100)).with_smoothing(1.0);
+    /// assert!(!shedder.is_overloaded());
+    ///
+    /// shedder.record_latency(Duration::from_millis(250));
+    /// assert!(shedder.is_overloaded());
+    /// ```
+    pub fn new(name: &str, target_latency: Duration) -> Self {
+        Self {
+            name: Arc::from(name),
+            target_latency,
+            shed_fraction: LOAD_SHEDDING_DEFAULT_SHED_FRACTION,
+            smoothing: LOAD_SHEDDING_DEFAULT_SMOOTHING,
+            retry_after: LOAD_SHEDDING_DEFAULT_RETRY_AFTER,
+            signal: None,
+            latency: Arc::new(Mutex::new(None)),
+            overloaded_requests: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Set the fraction (between 0 and 1) of the requests rejected while overloaded
+    pub fn with_shed_fraction(mut self, shed_fraction: f64) -> Self {
+        self.shed_fraction = shed_fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the weight (between 0 and 1) of the last latency in the moving average
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the `Retry-After` value of the rejected requests
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Set an external load signal, checked in addition to the latency
+    pub fn with_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.signal = Some(Arc::new(signal));
+        self
+    }
+
+    /// Load shedder name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Moving average of the latency
+    pub fn average_latency(&self) -> Option<Duration> {
+        self.latency
+            .lock()
+            .ok()
+            .and_then(|latency| latency.map(Duration::from_secs_f64))
+    }
+
+    /// Check if the service is overloaded
+    pub fn is_overloaded(&self) -> bool {
+        self.signal.as_ref().is_some_and(|signal| signal())
+            || self
+                .average_latency()
+                .is_some_and(|latency| latency > self.target_latency)
+    }
+
+    /// Record the latency of a request
+    pub fn record_latency(&self, latency: Duration) {
+        let Ok(mut average) = self.latency.lock() else {
+            return;
+        };
+        let latency = latency.as_secs_f64();
+        let value = match *average {
+            Some(average) => average + self.smoothing * (latency - average),
+            None => latency,
+        };
+        *average = Some(value);
+        self.publish_latency(value);
+    }
+
+    /// Admit a request, or return the `Retry-After` delay if it must be rejected
+    ///
+    /// While overloaded, the requests are rejected evenly: with a fraction of 0.25, one request
+    /// out of four is rejected.
+    pub fn try_admit(&self) -> Result<(), Duration> {
+        if !self.is_overloaded() {
+            return Ok(());
+        }
+
+        let n = self.overloaded_requests.fetch_add(1, Ordering::Relaxed) as f64;
+        if ((n + 1.0) * self.shed_fraction).floor() > (n * self.shed_fraction).floor() {
+            self.publish_rejection();
+            return Err(self.retry_after);
+        }
+
+        Ok(())
+    }
+
+    /// Publish the latency gauge
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn publish_latency(&self, latency: f64) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("load_shedding_latency_seconds", "name" => self.name.to_string()).set(latency);
+    }
+
+    /// Count a rejected request
+    fn publish_rejection(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("load_shedding_rejected_total", "name" => self.name.to_string()).increment(1);
+    }
+}
+
+/// Layer rejecting a fraction of the requests with a 503 error while the service is overloaded
+#[derive(Clone, Debug)]
+pub struct LoadSheddingLayer {
+    pub shedder: LoadShedder,
+    pub priority_routes: Vec<String>,
+}
+
+impl LoadSheddingLayer {
+    /// Create a new `LoadSheddingLayer` (the `/health` route is never shed)
+    pub fn new(shedder: LoadShedder) -> Self {
+        Self {
+            shedder,
+            priority_routes: vec!["/health".to_string()],
+        }
+    }
+
+    /// Add a route pattern (e.g. `/ready` or `/internal/{*path}`) which is never shed
+    pub fn with_priority_route(mut self, pattern: &str) -> Self {
+        self.priority_routes.push(pattern.to_string());
+        self
+    }
+}
+
+impl<S> Layer<S> for LoadSheddingLayer {
+    type Service = LoadSheddingMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadSheddingMiddleware {
+            inner,
+            shedder: self.shedder.clone(),
+            priority_routes: Arc::from(self.priority_routes.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LoadSheddingMiddleware<S> {
+    inner: S,
+    shedder: LoadShedder,
+    priority_routes: Arc<[String]>,
+}
+
+impl<S> Service<Request<Body>> for LoadSheddingMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let path = request.uri().path();
+        if self.priority_routes.iter().any(|pattern| route_matches(pattern, path)) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        if let Err(retry_after) = self.shedder.try_admit() {
+            return Box::pin(async move {
+                let (mut parts, _body) = Response::<Body>::default().into_parts();
+                let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                let msg = body_from_parts(
+                    &mut parts,
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Service overloaded",
+                    Some(vec![(header::RETRY_AFTER, HeaderValue::from(retry_after))]),
+                );
+                Ok(Response::from_parts(parts, Body::from(msg)))
+            });
+        }
+
+        let shedder = self.shedder.clone();
+        let started_at = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            shedder.record_latency(started_at.elapsed());
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::AtomicBool;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_average_latency() {
+        let shedder = LoadShedder::new("test", Duration::from_millis(100)).with_smoothing(0.5);
+        assert_eq!(shedder.average_latency(), None);
+
+        shedder.record_latency(Duration::from_millis(100));
+        shedder.record_latency(Duration::from_millis(300));
+        assert_eq!(shedder.average_latency(), Some(Duration::from_millis(200)));
+        assert!(shedder.is_overloaded());
+
+        shedder.record_latency(Duration::from_millis(0));
+        assert_eq!(shedder.average_latency(), Some(Duration::from_millis(100)));
+        assert!(!shedder.is_overloaded());
+    }
+
+    #[test]
+    fn test_try_admit_sheds_a_fraction() {
+        let overloaded = Arc::new(AtomicBool::new(false));
+        let shedder = LoadShedder::new("test", Duration::from_secs(1))
+            .with_shed_fraction(0.25)
+            .with_signal({
+                let overloaded = overloaded.clone();
+                move || overloaded.load(Ordering::SeqCst)
+            });
+
+        assert!((0..100).all(|_| shedder.try_admit().is_ok()));
+
+        overloaded.store(true, Ordering::SeqCst);
+        let rejected = (0..100).filter(|_| shedder.try_admit().is_err()).count();
+        assert_eq!(rejected, 25);
+
+        let shedder = shedder.with_shed_fraction(1.0);
+        assert_eq!(shedder.try_admit(), Err(LOAD_SHEDDING_DEFAULT_RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn test_load_shedding_layer() {
+        let shedder = LoadShedder::new("test", Duration::from_millis(10))
+            .with_shed_fraction(1.0)
+            .with_smoothing(1.0)
+            .with_retry_after(Duration::from_secs(30));
+        let service = LoadSheddingLayer::new(shedder.clone()).layer(tower::service_fn(|_: Request<Body>| async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        let call = |path: &'static str| service.clone().oneshot(Request::get(path).body(Body::empty()).unwrap());
+
+        // The slow response makes the service overloaded
+        assert_eq!(call("/reports").await.unwrap().status(), StatusCode::OK);
+        assert!(shedder.is_overloaded());
+
+        let response = call("/reports").await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "30");
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], br#"{"code":503,"message":"Service overloaded"}"#);
+
+        // Health checks are never shed
+        assert_eq!(call("/health").await.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -1,18 +1,256 @@
 //! Logger layer
+//!
+//! [`LoggerLayer`] logs each request with the `tracing` key-value fields by default. Once, at
+//! startup, [`configure_logger`] can select another [`LogFormat`] for the log pipelines:
+//!
+//! - [`LogFormat::Json`]: one JSON object per line, with stable field names
+//! - [`LogFormat::Logfmt`]: one `key=value` line
+//!
+//! The line is the message of the `tracing` event, so the subscriber should only print the
+//! message (e.g. `tracing_subscriber::fmt().without_time().with_level(false).with_target(false)`).
+//! The `trace_id` and `span_id` fields of the current OpenTelemetry context (empty without
+//! context) correlate the logs with the traces and the error bodies (`ApiErrorResponse`).
+//!
+//! With [`LoggerConfig::with_nested_http_fields`], the HTTP fields are nested under `http.*`
+//! (`http.method`, `http.status_code`, etc.), as in the ECS and OpenTelemetry conventions.
+//!
+//! The info logs can be sampled with [`LogSamplingRule`]s, matched by route pattern (`/health`,
+//! `/users/{id}`, `/api/*`), method and status: the first matching rule keeps a ratio of the
+//! requests and/or a maximum number of logs per second and per route. The requests matching no
+//! rule and the error logs (`5xx` responses) are always logged.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::logger::{
+//!     LogFormat, LogSamplingRule, LoggerConfig, LoggerLayer, configure_logger,
+//! };
+//! use axum::http::Method;
+//! use axum::{Router, routing::get};
+//!
+//! configure_logger(
+//!     LoggerConfig::new()
+//!         .with_format(LogFormat::Json)
+//!         .with_nested_http_fields()
+//!         // 100% of the non-2xx responses
+//!         .with_sampling_rule(LogSamplingRule::new("*").with_statuses(300..=599))
+//!         // 1% of `GET /health`
+//!         .with_sampling_rule(LogSamplingRule::new("/health").with_method(Method::GET).with_rate(0.01))
+//!         // At most 100 logs per second for each `/api` route
+//!         .with_sampling_rule(LogSamplingRule::new("/api/*").with_max_per_second(100)),
+//! );
+//!
+//! let app: Router = Router::new().route("/users", get(|| async { "users" })).layer(LoggerLayer);
+//! ```
 
+use super::context::RequestContext;
 use super::header_value_to_str;
+use super::ip_filter::IpFilterMatch;
+use super::ownership::Owner;
+use super::route_matches;
+use crate::server::axum::redaction::redaction_policy;
+use crate::server::axum::response::{current_span_id, current_trace_id};
+use crate::value_objects::datetime::UtcDateTime;
 use axum::body::HttpBody;
+use axum::extract::MatchedPath;
 use axum::http::{Method, StatusCode};
 use axum::{body::Body, http::Request, response::Response};
 use bytesize::ByteSize;
+use chrono::SecondsFormat;
 use futures::future::BoxFuture;
+use serde_json::{Map, Value, json};
 use std::{
-    fmt::Display,
+    collections::HashMap,
+    fmt::{Display, Write},
+    ops::RangeInclusive,
+    sync::{Arc, LazyLock, Mutex, OnceLock},
     task::{Context, Poll},
     time::{Duration, Instant},
 };
 use tower::{Layer, Service};
 
+/// Response extension disabling the info log of a request (e.g. the metrics route)
+///
+/// Errors (`5xx` responses) are still logged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkipLogging;
+
+/// Output format of the request logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `tracing` key-value fields
+    #[default]
+    Fields,
+    /// One JSON object per line
+    Json,
+    /// One logfmt (`key=value`) line
+    Logfmt,
+}
+
+/// Maximum number of per-second windows kept by a sampling rule
+const LOG_SAMPLING_MAX_WINDOWS: usize = 1_024;
+
+/// Sampling rule of the info request logs
+#[derive(Debug, Clone)]
+pub struct LogSamplingRule {
+    /// Route pattern (`{id}` and trailing `*` or `{*rest}` segments are supported)
+    pub route: String,
+    pub method: Option<Method>,
+    pub statuses: Option<RangeInclusive<u16>>,
+    /// Ratio (between 0 and 1) of the matching requests which are logged
+    pub rate: f64,
+    /// Maximum number of logs per second and per route
+    pub max_per_second: Option<u32>,
+    state: Arc<Mutex<LogSamplingState>>,
+}
+
+#[derive(Debug, Default)]
+struct LogSamplingState {
+    matched: u64,
+    /// Start and number of logs of the current window by route
+    windows: HashMap<String, (Instant, u32)>,
+}
+
+impl LogSamplingRule {
+    /// Create a new rule logging all the requests matching a route pattern
+    pub fn new(route: &str) -> Self {
+        Self {
+            route: route.to_string(),
+            method: None,
+            statuses: None,
+            rate: 1.0,
+            max_per_second: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Only match a method
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Only match a range of statuses (e.g. `200..=299`)
+    pub fn with_statuses(mut self, statuses: RangeInclusive<u16>) -> Self {
+        self.statuses = Some(statuses);
+        self
+    }
+
+    /// Set the ratio (between 0 and 1) of the logged requests
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the maximum number of logs per second and per route
+    pub fn with_max_per_second(mut self, max_per_second: u32) -> Self {
+        self.max_per_second = Some(max_per_second);
+        self
+    }
+
+    /// Check if the rule applies to a request
+    fn matches(&self, method: &str, route: &str, status: u16) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|rule_method| rule_method.as_str() == method)
+            && self.statuses.as_ref().is_none_or(|statuses| statuses.contains(&status))
+            && route_matches(&self.route, route)
+    }
+
+    /// Check if a matching request is logged
+    ///
+    /// The ratio is applied deterministically (e.g. 1 request out of 100 for `0.01`).
+    fn sample(&self, route: &str) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return true;
+        };
+        state.matched += 1;
+        let matched = state.matched as f64;
+        if (matched * self.rate).floor() == ((matched - 1.0) * self.rate).floor() {
+            return false;
+        }
+
+        let Some(max_per_second) = self.max_per_second else {
+            return true;
+        };
+        let now = Instant::now();
+        if state.windows.len() >= LOG_SAMPLING_MAX_WINDOWS && !state.windows.contains_key(route) {
+            state
+                .windows
+                .retain(|_, (started_at, _)| now.duration_since(*started_at) < Duration::from_secs(1));
+        }
+        let window = state.windows.entry(route.to_string()).or_insert((now, 0));
+        if now.duration_since(window.0) >= Duration::from_secs(1) {
+            *window = (now, 0);
+        }
+        if window.1 >= max_per_second {
+            return false;
+        }
+        window.1 += 1;
+
+        true
+    }
+}
+
+/// Configuration of the [`LoggerLayer`]
+#[derive(Debug, Clone, Default)]
+pub struct LoggerConfig {
+    pub format: LogFormat,
+    /// Nest the HTTP fields under `http.*` (JSON and logfmt formats)
+    pub nest_http_fields: bool,
+    /// Sampling rules of the info logs, the first matching rule applies
+    pub sampling_rules: Vec<LogSamplingRule>,
+}
+
+impl LoggerConfig {
+    /// Create a new `LoggerConfig` with the default format
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the output format
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Nest the HTTP fields under `http.*`
+    pub fn with_nested_http_fields(mut self) -> Self {
+        self.nest_http_fields = true;
+        self
+    }
+
+    /// Add a sampling rule of the info logs
+    pub fn with_sampling_rule(mut self, rule: LogSamplingRule) -> Self {
+        self.sampling_rules.push(rule);
+        self
+    }
+
+    /// Check if the info log of a request is kept by the sampling rules
+    fn is_sampled(&self, method: &str, route: &str, status: u16) -> bool {
+        self.sampling_rules
+            .iter()
+            .find(|rule| rule.matches(method, route, status))
+            .is_none_or(|rule| rule.sample(route))
+    }
+}
+
+/// Global logger configuration, like the `tracing` subscriber
+static LOGGER_CONFIG: OnceLock<LoggerConfig> = OnceLock::new();
+
+/// Configure the output format of the [`LoggerLayer`]
+///
+/// Only the first call configures the logger.
+pub fn configure_logger(config: LoggerConfig) -> &'static LoggerConfig {
+    LOGGER_CONFIG.get_or_init(|| config)
+}
+
+/// Default logger configuration
+static DEFAULT_LOGGER_CONFIG: LazyLock<LoggerConfig> = LazyLock::new(LoggerConfig::default);
+
+/// Get the logger configuration (the default one if it is not set)
+pub fn logger_config() -> &'static LoggerConfig {
+    LOGGER_CONFIG.get().unwrap_or(&DEFAULT_LOGGER_CONFIG)
+}
+
 #[derive(Debug, Default)]
 struct LoggerMessage {
     method: String,
@@ -21,6 +259,12 @@ struct LoggerMessage {
     path: String,
     uri: String,
     user_agent: String,
+    client_ip: String,
+    route: String,
+    ip_filter: String,
+    owner: String,
+    trace_id: String,
+    span_id: String,
     status_code: u16,
     version: String,
     latency: Duration,
@@ -31,7 +275,7 @@ impl Display for LoggerMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "status_code: {}, method: {}, path: {}, uri: {}, host: {}, request_id: {}, user_agent: {}, version: {}, latency: {:?}, body_size: {}",
+            "status_code: {}, method: {}, path: {}, uri: {}, host: {}, request_id: {}, user_agent: {}, client_ip: {}, route: {}, ip_filter: {}, owner: {}, trace_id: {}, span_id: {}, version: {}, latency: {:?}, body_size: {}",
             self.status_code,
             self.method,
             self.path,
@@ -39,6 +283,12 @@ impl Display for LoggerMessage {
             self.host,
             self.request_id,
             self.user_agent,
+            self.client_ip,
+            self.route,
+            self.ip_filter,
+            self.owner,
+            self.trace_id,
+            self.span_id,
             self.version,
             self.latency,
             ByteSize::b(self.body_size),
@@ -46,6 +296,106 @@ impl Display for LoggerMessage {
     }
 }
 
+/// Named values of a log line
+type LogFields = Vec<(&'static str, Value)>;
+
+impl LoggerMessage {
+    /// HTTP fields (nested under `http.*` if requested) and other fields
+    fn fields(&self, level: &str, nest_http_fields: bool) -> (LogFields, LogFields) {
+        let http = vec![
+            ("method", json!(self.method)),
+            ("status_code", json!(self.status_code)),
+            ("path", json!(self.path)),
+            ("uri", json!(self.uri)),
+            ("host", json!(self.host)),
+            ("user_agent", json!(self.user_agent)),
+            ("route", json!(self.route)),
+            ("version", json!(self.version)),
+            ("body_size", json!(self.body_size)),
+        ];
+        let mut others = vec![
+            (
+                "timestamp",
+                json!(UtcDateTime::now().value().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            ),
+            ("level", json!(level)),
+            ("request_id", json!(self.request_id)),
+            ("client_ip", json!(self.client_ip)),
+            ("ip_filter", json!(self.ip_filter)),
+            ("owner", json!(self.owner)),
+            ("trace_id", json!(self.trace_id)),
+            ("span_id", json!(self.span_id)),
+            ("latency_ms", json!(self.latency.as_secs_f64() * 1_000.0)),
+        ];
+        if nest_http_fields {
+            (http, others)
+        } else {
+            others.splice(2..2, http);
+            (Vec::new(), others)
+        }
+    }
+
+    /// JSON line
+    fn to_json(&self, level: &str, nest_http_fields: bool) -> String {
+        let (http, others) = self.fields(level, nest_http_fields);
+        let mut object = others
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<Map<_, _>>();
+        if nest_http_fields {
+            let http = http
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect::<Map<_, _>>();
+            object.insert("http".to_string(), Value::Object(http));
+        }
+
+        Value::Object(object).to_string()
+    }
+
+    /// logfmt line
+    fn to_logfmt(&self, level: &str, nest_http_fields: bool) -> String {
+        let (http, others) = self.fields(level, nest_http_fields);
+        let http = http.into_iter().map(|(key, value)| (format!("http.{key}"), value));
+        let others = others.into_iter().map(|(key, value)| (key.to_string(), value));
+
+        let mut line = String::new();
+        for (key, value) in others.chain(http) {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            let value = match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            let _ = write!(line, "{key}={}", logfmt_value(&value));
+        }
+
+        line
+    }
+}
+
+/// Quote a logfmt value if needed
+fn logfmt_value(value: &str) -> String {
+    if !value.is_empty() && !value.contains([' ', '=', '"', '\\']) && !value.contains(char::is_control) {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
 #[derive(Clone)]
 pub struct LoggerLayer;
 
@@ -79,16 +429,34 @@ where
     fn call(&mut self, request: Request<Body>) -> Self::Future {
         let now = Instant::now();
         let request_headers = request.headers();
+        // The `RequestContext` (`ContextLayer`) is used when it is set
+        let context = RequestContext::from_request(&request);
 
-        let message = LoggerMessage {
+        let mut message = LoggerMessage {
             method: request.method().to_string(),
             path: request.uri().path().to_string(),
-            uri: request.uri().to_string(),
+            uri: redaction_policy().redact_uri(&request.uri().to_string()).into_owned(),
             host: header_value_to_str(request_headers.get("host")).to_string(),
             request_id: header_value_to_str(request_headers.get("x-request-id")).to_string(),
             user_agent: header_value_to_str(request_headers.get("user-agent")).to_string(),
+            trace_id: current_trace_id().unwrap_or_default(),
+            span_id: current_span_id().unwrap_or_default(),
             ..Default::default()
         };
+        if let Some(context) = context {
+            if let Some(request_id) = &context.request_id {
+                message.request_id = request_id.clone();
+            }
+            message.client_ip = context.client_ip.map(|ip| ip.to_string()).unwrap_or_default();
+            message.route = context.route.clone().unwrap_or_default();
+        }
+        let sampling_route = if !message.route.is_empty() {
+            message.route.clone()
+        } else if let Some(matched_path) = request.extensions().get::<MatchedPath>() {
+            matched_path.as_str().to_string()
+        } else {
+            message.path.clone()
+        };
 
         let future = self.inner.call(request);
         Box::pin(async move {
@@ -99,25 +467,56 @@ where
                 return Ok(response);
             }
 
-            let status_code = response.status().as_u16();
-            let version = format!("{:?}", response.version());
-            let latency = now.elapsed();
-            let body_size = response.body().size_hint().lower();
+            // The span may have been entered by an inner layer
+            if message.trace_id.is_empty() {
+                message.trace_id = current_trace_id().unwrap_or_default();
+                message.span_id = current_span_id().unwrap_or_default();
+            }
+
+            message.status_code = response.status().as_u16();
+            message.version = format!("{:?}", response.version());
+            message.latency = now.elapsed();
+            message.body_size = response.body().size_hint().lower();
+            // The `IpFilterMatch` (`IpFilterLayer`) is used when it is set
+            if let Some(ip_match) = response.extensions().get::<IpFilterMatch>() {
+                message.ip_filter = ip_match.to_string();
+                if message.client_ip.is_empty() {
+                    message.client_ip = ip_match.client_ip.map(|ip| ip.to_string()).unwrap_or_default();
+                }
+            }
 
+            // The `Owner` (`OwnershipLayer`) is used when it is set
+            if let Some(owner) = response.extensions().get::<Owner>() {
+                message.owner = owner.to_string();
+            }
+
+            let config = logger_config();
             macro_rules! log_request {
                 ($level:ident) => {
-                    $level!(
-                        status_code = %status_code,
-                        method = %message.method,
-                        path = %message.path,
-                        uri = %message.uri,
-                        host = %message.host,
-                        request_id = %message.request_id,
-                        user_agent = %message.user_agent,
-                        version = %version,
-                        latency = %format!("{:?}", latency),
-                        body_size = %ByteSize::b(body_size),
-                    );
+                    match config.format {
+                        LogFormat::Fields => $level!(
+                            status_code = %message.status_code,
+                            method = %message.method,
+                            path = %message.path,
+                            uri = %message.uri,
+                            host = %message.host,
+                            request_id = %message.request_id,
+                            user_agent = %message.user_agent,
+                            client_ip = %message.client_ip,
+                            route = %message.route,
+                            ip_filter = %message.ip_filter,
+                            owner = %message.owner,
+                            trace_id = %message.trace_id,
+                            span_id = %message.span_id,
+                            version = %message.version,
+                            latency = %format!("{:?}", message.latency),
+                            body_size = %ByteSize::b(message.body_size),
+                        ),
+                        LogFormat::Json => $level!("{}", message.to_json(stringify!($level), config.nest_http_fields)),
+                        LogFormat::Logfmt => {
+                            $level!("{}", message.to_logfmt(stringify!($level), config.nest_http_fields))
+                        }
+                    }
                 };
             }
 
@@ -125,7 +524,10 @@ where
                 && response.status() != StatusCode::SERVICE_UNAVAILABLE
             {
                 log_request!(error);
-            } else if !message.path.starts_with("/metrics") {
+            } else if !message.path.starts_with("/metrics")
+                && response.extensions().get::<SkipLogging>().is_none()
+                && config.is_sampled(&message.method, &sampling_route, message.status_code)
+            {
                 log_request!(info);
             }
 
@@ -241,15 +643,99 @@ mod tests {
             path: "/test".to_string(),
             uri: "/test?query=1".to_string(),
             user_agent: "TestAgent/1.0".to_string(),
+            client_ip: "203.0.113.1".to_string(),
+            route: "/test".to_string(),
+            ip_filter: "allowed (203.0.113.0/24)".to_string(),
+            owner: "checkout".to_string(),
+            trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+            span_id: "00f067aa0ba902b7".to_string(),
             status_code: 200,
             version: "HTTP/1.1".to_string(),
             latency: Duration::from_millis(42),
             body_size: 1_524,
         };
         let expected = String::from(
-            "status_code: 200, method: GET, path: /test, uri: /test?query=1, host: localhost, request_id: abc-123, user_agent: TestAgent/1.0, version: HTTP/1.1, latency: 42ms, body_size: 1.5 KiB",
+            "status_code: 200, method: GET, path: /test, uri: /test?query=1, host: localhost, request_id: abc-123, user_agent: TestAgent/1.0, client_ip: 203.0.113.1, route: /test, ip_filter: allowed (203.0.113.0/24), owner: checkout, trace_id: 4bf92f3577b34da6a3ce929d0e0e4736, span_id: 00f067aa0ba902b7, version: HTTP/1.1, latency: 42ms, body_size: 1.5 KiB",
         );
 
         assert_eq!(message.to_string(), expected);
     }
+
+    fn message() -> LoggerMessage {
+        LoggerMessage {
+            method: "GET".to_string(),
+            request_id: "abc-123".to_string(),
+            path: "/test".to_string(),
+            uri: "/test?query=1".to_string(),
+            user_agent: "Test Agent/1.0".to_string(),
+            status_code: 200,
+            version: "HTTP/1.1".to_string(),
+            latency: Duration::from_millis(42),
+            body_size: 1_524,
+            trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+            span_id: "00f067aa0ba902b7".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_logger_message_to_json() {
+        let line: Value = serde_json::from_str(&message().to_json("info", false)).unwrap();
+        assert_eq!(line["level"], "info");
+        assert_eq!(line["method"], "GET");
+        assert_eq!(line["status_code"], 200);
+        assert_eq!(line["body_size"], 1_524);
+        assert_eq!(line["latency_ms"], 42.0);
+        assert_eq!(line["request_id"], "abc-123");
+        assert_eq!(line["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(line["span_id"], "00f067aa0ba902b7");
+        assert!(line["timestamp"].is_string());
+
+        let line: Value = serde_json::from_str(&message().to_json("error", true)).unwrap();
+        assert_eq!(line["http"]["method"], "GET");
+        assert_eq!(line["http"]["status_code"], 200);
+        assert!(line.get("method").is_none());
+        assert_eq!(line["request_id"], "abc-123");
+    }
+
+    #[test]
+    fn test_logger_message_to_logfmt() {
+        let line = message().to_logfmt("info", false);
+        assert!(line.contains(" level=info method=GET status_code=200 path=/test uri=\"/test?query=1\" host=\"\" "));
+        assert!(line.contains(" user_agent=\"Test Agent/1.0\" "));
+        assert!(line.ends_with(" latency_ms=42.0"));
+
+        let line = message().to_logfmt("info", true);
+        assert!(line.contains(" request_id=abc-123 "));
+        assert!(line.contains(" trace_id=4bf92f3577b34da6a3ce929d0e0e4736 span_id=00f067aa0ba902b7 "));
+        assert!(line.contains(" http.method=GET http.status_code=200 "));
+    }
+
+    #[test]
+    fn test_log_sampling_rules() {
+        let config = LoggerConfig::new()
+            .with_sampling_rule(LogSamplingRule::new("*").with_statuses(300..=599))
+            .with_sampling_rule(LogSamplingRule::new("/health").with_method(Method::GET).with_rate(0.01))
+            .with_sampling_rule(LogSamplingRule::new("/api/*").with_max_per_second(2));
+
+        let logged = (0..200).filter(|_| config.is_sampled("GET", "/health", 200)).count();
+        assert_eq!(logged, 2);
+        assert!(config.is_sampled("GET", "/health", 500));
+        assert!(config.is_sampled("POST", "/health", 200));
+
+        assert!(config.is_sampled("GET", "/api/users", 200));
+        assert!(config.is_sampled("GET", "/api/users", 200));
+        assert!(!config.is_sampled("GET", "/api/users", 200));
+        assert!(config.is_sampled("GET", "/api/orders", 200));
+
+        assert!(config.is_sampled("GET", "/users", 200));
+    }
+
+    #[test]
+    fn test_logfmt_value() {
+        assert_eq!(logfmt_value("abc"), "abc");
+        assert_eq!(logfmt_value(""), "\"\"");
+        assert_eq!(logfmt_value("a=b"), "\"a=b\"");
+        assert_eq!(logfmt_value("say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+    }
 }
@@ -0,0 +1,327 @@
+//! Response transformation hooks
+//!
+//! [`MapResponseLayer`] runs async [`ResponseHook`]s on the responses matching a route pattern, a
+//! range of status codes and a content-type prefix (all optional), e.g. to wrap the errors of a
+//! legacy upstream service into the standard envelope or to strip internal headers, without
+//! writing a tower middleware each time.
+//!
+//! The hooks run in their order in the layer (see [`MapResponseLayer::with_hook_first`],
+//! [`MapResponseLayer::with_hook_before`] and [`MapResponseLayer::with_hook_after`]), each one
+//! matching the response returned by the previous one.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::map_response::{MapResponseLayer, ResponseHook};
+//! use axum::http::HeaderName;
+//! use axum::response::IntoResponse;
+//! use axum::{Json, Router, routing::get};
+//!
+//! let layer = MapResponseLayer::new()
+//!     .with_hook(
+//!         ResponseHook::new("legacy-errors", |response: axum::response::Response| async move {
+//!             let status = response.status();
+//!             let body = axum::body::to_bytes(response.into_body(), 64 * 1_024).await.unwrap_or_default();
+//!             let message = String::from_utf8_lossy(&body).into_owned();
+//!             (status, Json(serde_json::json!({ "code": status.as_u16(), "message": message }))).into_response()
+//!         })
+//!         .with_route("/legacy/{*path}")
+//!         .with_statuses(400..=599)
+//!         .with_content_type("text/plain"),
+//!     )
+//!     .with_hook_first(ResponseHook::remove_headers(
+//!         "internal-headers",
+//!         vec![HeaderName::from_static("x-internal-node")],
+//!     ));
+//!
+//! let app: Router = Router::new()
+//!     .route("/legacy/users", get(|| async { "users" }))
+//!     .layer(layer);
+//! ```
+
+use super::route_matches;
+use axum::body::Body;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderName, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+type ResponseHookFn = Arc<dyn Fn(Response) -> BoxFuture<'static, Response> + Send + Sync>;
+
+/// Async transformation of the matching responses
+#[derive(Clone)]
+pub struct ResponseHook {
+    pub name: String,
+    pub route: Option<String>,
+    pub statuses: Option<RangeInclusive<u16>>,
+    pub content_type: Option<String>,
+    hook: ResponseHookFn,
+}
+
+impl ResponseHook {
+    /// Create a new hook matching all the responses
+    pub fn new<F, Fut>(name: &str, hook: F) -> Self
+    where
+        F: Fn(Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            route: None,
+            statuses: None,
+            content_type: None,
+            hook: Arc::new(move |response| Box::pin(hook(response))),
+        }
+    }
+
+    /// Hook removing headers from the responses
+    pub fn remove_headers(name: &str, headers: Vec<HeaderName>) -> Self {
+        Self::new(name, move |mut response: Response| {
+            for header in &headers {
+                response.headers_mut().remove(header);
+            }
+            async move { response }
+        })
+    }
+
+    /// Only match the paths matching a route pattern (`/legacy/{*path}`, `/users/{id}`)
+    pub fn with_route(mut self, pattern: &str) -> Self {
+        self.route = Some(pattern.to_string());
+        self
+    }
+
+    /// Only match a range of status codes (e.g. `500..=599`)
+    pub fn with_statuses(mut self, statuses: RangeInclusive<u16>) -> Self {
+        self.statuses = Some(statuses);
+        self
+    }
+
+    /// Only match the content-types starting with this prefix
+    pub fn with_content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Return true if the hook applies to the response of a request path
+    fn matches(&self, path: &str, response: &Response) -> bool {
+        if let Some(route) = &self.route
+            && !route_matches(route, path)
+        {
+            return false;
+        }
+        if let Some(statuses) = &self.statuses
+            && !statuses.contains(&response.status().as_u16())
+        {
+            return false;
+        }
+        if let Some(content_type) = &self.content_type {
+            return response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with(content_type.as_str()));
+        }
+
+        true
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MapResponseLayer {
+    pub hooks: Vec<ResponseHook>,
+}
+
+impl MapResponseLayer {
+    /// Create a new `MapResponseLayer` without hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run a hook after the other hooks
+    pub fn with_hook(mut self, hook: ResponseHook) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Run a hook before the other hooks
+    pub fn with_hook_first(mut self, hook: ResponseHook) -> Self {
+        self.hooks.insert(0, hook);
+        self
+    }
+
+    /// Run a hook just before the hook named `name` (after the other hooks if there is none)
+    pub fn with_hook_before(mut self, name: &str, hook: ResponseHook) -> Self {
+        let index = self.position(name).unwrap_or(self.hooks.len());
+        self.hooks.insert(index, hook);
+        self
+    }
+
+    /// Run a hook just after the hook named `name` (after the other hooks if there is none)
+    pub fn with_hook_after(mut self, name: &str, hook: ResponseHook) -> Self {
+        let index = self.position(name).map_or(self.hooks.len(), |index| index + 1);
+        self.hooks.insert(index, hook);
+        self
+    }
+
+    /// Names of the hooks, in their running order
+    pub fn hook_names(&self) -> Vec<&str> {
+        self.hooks.iter().map(|hook| hook.name.as_str()).collect()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.hooks.iter().position(|hook| hook.name == name)
+    }
+}
+
+impl<S> Layer<S> for MapResponseLayer {
+    type Service = MapResponseMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MapResponseMiddleware {
+            inner,
+            hooks: self.hooks.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MapResponseMiddleware<S> {
+    inner: S,
+    hooks: Vec<ResponseHook>,
+}
+
+impl<S> Service<Request<Body>> for MapResponseMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if self.hooks.is_empty() {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let hooks = self.hooks.clone();
+        let path = request.uri().path().to_string();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            for hook in &hooks {
+                if hook.matches(&path, &response) {
+                    response = (hook.hook)(response).await;
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::{HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn tag(name: &str) -> ResponseHook {
+        let value = HeaderValue::from_str(name).unwrap();
+        ResponseHook::new(name, move |mut response: Response| {
+            response.headers_mut().append("x-hooks", value.clone());
+            async move { response }
+        })
+    }
+
+    async fn call(layer: MapResponseLayer, uri: &str) -> Response {
+        let app: Router = Router::new()
+            .route("/users", get(|| async { ([("x-internal", "node-1")], "users") }))
+            .route(
+                "/legacy/error",
+                get(|| async { (StatusCode::BAD_GATEWAY, "upstream down") }),
+            )
+            .layer(layer);
+
+        app.oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn hooks(response: &Response) -> Vec<&str> {
+        response
+            .headers()
+            .get_all("x-hooks")
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_map_response_layer_ordering() {
+        let layer = MapResponseLayer::new()
+            .with_hook(tag("b"))
+            .with_hook(tag("d"))
+            .with_hook_first(tag("a"))
+            .with_hook_before("d", tag("c"))
+            .with_hook_after("d", tag("e"))
+            .with_hook_after("unknown", tag("f"));
+        assert_eq!(layer.hook_names(), vec!["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[tokio::test]
+    async fn test_map_response_layer_matchers() {
+        let layer = MapResponseLayer::new()
+            .with_hook(tag("all"))
+            .with_hook(tag("legacy").with_route("/legacy/{*path}"))
+            .with_hook(tag("errors").with_statuses(500..=599))
+            .with_hook(tag("text").with_content_type("text/plain"))
+            .with_hook(tag("json").with_content_type("application/json"))
+            .with_hook(ResponseHook::remove_headers(
+                "internal",
+                vec![HeaderName::from_static("x-internal")],
+            ));
+
+        let response = call(layer.clone(), "/users").await;
+        assert_eq!(hooks(&response), vec!["all", "text"]);
+        assert!(response.headers().get("x-internal").is_none());
+
+        let response = call(layer, "/legacy/error").await;
+        assert_eq!(hooks(&response), vec!["all", "legacy", "errors", "text"]);
+    }
+
+    #[tokio::test]
+    async fn test_map_response_layer_chained_hooks() {
+        // The second hook matches the response of the first one
+        let layer = MapResponseLayer::new()
+            .with_hook(
+                ResponseHook::new("wrap", |response: Response| async move {
+                    let status = response.status();
+                    let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+                    let body =
+                        serde_json::json!({ "code": status.as_u16(), "message": String::from_utf8_lossy(&body) });
+                    (status, axum::Json(body)).into_response()
+                })
+                .with_statuses(500..=599),
+            )
+            .with_hook(tag("json").with_content_type("application/json"));
+
+        let response = call(layer, "/legacy/error").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(hooks(&response), vec!["json"]);
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "upstream down");
+    }
+}
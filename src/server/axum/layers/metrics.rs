@@ -0,0 +1,1224 @@
+//! Metrics layer
+//!
+//! The metrics are recorded with the [`metrics`] facade, so they are exported by
+//! the installed recorder: Prometheus (`prometheus` feature), StatsD (`statsd`
+//! feature) or OTLP (`otlp` feature), see `server::axum::exporters`.
+//!
+//! This module provides:
+//!
+//! 1. [`MetricsLayer`] — a tower [`Layer`] that records per-request HTTP
+//!    metrics (`http_requests_total`, `http_requests_duration_seconds`,
+//!    `http_request_size_bytes`, `http_response_size_bytes`). The middleware
+//!    overhead is in the microsecond range.
+//!
+//! 2. [`spawn_system_metrics_collector`] — a helper that spawns a background
+//!    Tokio task to collect host-level metrics (CPU, memory, swap, disk
+//!    usage). System metrics are intentionally **not** collected from the
+//!    request path: they do not change at request granularity, and collecting
+//!    them inline would add hundreds of milliseconds to every response (see
+//!    `sysinfo::MINIMUM_CPU_UPDATE_INTERVAL`).
+//!
+//! 3. [`Exemplars`] — optional store of the latest trace ID of each
+//!    `http_requests_duration_seconds` bucket, enabled with [`enable_exemplars`]
+//!    (or `PrometheusHandler::get_handle_with_exemplars`). The exporter does not
+//!    support exemplars, so they are added when rendering the metrics in the
+//!    OpenMetrics format (`PrometheusHandler::render_openmetrics`).
+//!
+//! 4. [`TenantLabels`] — optional `tenant` label on the HTTP metrics, enabled
+//!    with [`enable_tenant_labels`]. Only the first `max_tenants` tenants
+//!    reaching `min_requests` requests get their own label value, the others
+//!    are aggregated in the [`OTHER_TENANT_LABEL`] bucket to bound the
+//!    cardinality.
+//!
+//! 5. An `owner` label on the HTTP metrics of the routes owned by a team (see
+//!    `OwnershipLayer`), so that alerts are routed to the owning team.
+//!
+//! 6. [`HttpMetricsConfig`] — optional excluded routes and custom labels
+//!    (static or derived from the request extensions), enabled with
+//!    [`configure_http_metrics`]. A request can also be excluded with the
+//!    [`SkipMetrics`] request or response extension.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use std::path::PathBuf;
+//! use std::time::Duration;
+//! use api_tools::server::axum::layers::metrics::{
+//!     MetricsLayer, spawn_system_metrics_collector,
+//! };
+//!
+//! let layer = MetricsLayer { service_name: "myapp".into() };
+//!
+//! // Once, at application startup:
+//! let _collector = spawn_system_metrics_collector(
+//!     "myapp".into(),
+//!     vec![PathBuf::from("/")],
+//!     Duration::from_secs(10),
+//! );
+//! ```
+
+use crate::server::axum::layers::ownership::Owner;
+use crate::server::axum::layers::route_matches;
+use crate::server::axum::request_store::{RequestStore, Tenant};
+use crate::server::axum::response::current_trace_id;
+use axum::body::{Body, HttpBody};
+use axum::extract::MatchedPath;
+use axum::http::{Extensions, HeaderMap, Method, Request, header};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use metrics::{Label, counter, gauge, histogram};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::{
+    CpuRefreshKind, Disks, MemoryRefreshKind, Networks, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System,
+};
+use tokio::task::JoinHandle;
+use tower::{Layer, Service};
+
+/// Metrics layer for Axum.
+///
+/// Records `http_requests_total` (counter) and
+/// `http_requests_duration_seconds` (histogram) for every request, labeled
+/// by `method`, `path` (the matched route — bounded cardinality), `service`
+/// and `status`. The `http_request_size_bytes` and `http_response_size_bytes`
+/// histograms, with the same labels, record the body sizes known from the
+/// `Content-Length` header or the body itself (streamed bodies of unknown
+/// size are not recorded). Requests to `/metrics` and with the [`SkipMetrics`] extension
+/// (request or response) are excluded.
+///
+/// If [`enable_tenant_labels`] is called, a `tenant` label is added. The tenant
+/// is the `Tenant` of the `RequestStore`, written by a layer wrapping this one,
+/// or else the `Tenant` of the response extensions.
+///
+/// An `owner` label is added to the routes owned by a team (the `Owner` of the
+/// response extensions, inserted by the `OwnershipLayer`).
+///
+/// If [`configure_http_metrics`] is called, the excluded routes are skipped and
+/// the custom labels are added after the standard ones.
+///
+/// System metrics (CPU, memory, swap, disks) are **not** collected here.
+/// Use [`spawn_system_metrics_collector`] at startup instead.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    /// Service name used as a label on every metric.
+    pub service_name: String,
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsMiddleware {
+            inner,
+            // One-time conversion: subsequent per-request clones bump the
+            // refcount only.
+            service_name: Arc::from(self.service_name.as_str()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsMiddleware<S> {
+    inner: S,
+    service_name: Arc<str>,
+}
+
+/// Map standard HTTP methods to a `&'static str` to avoid an allocation on
+/// every request. Falls back to an owned string for non-standard methods.
+fn method_label(method: &Method) -> Cow<'static, str> {
+    match *method {
+        Method::GET => Cow::Borrowed("GET"),
+        Method::POST => Cow::Borrowed("POST"),
+        Method::PUT => Cow::Borrowed("PUT"),
+        Method::DELETE => Cow::Borrowed("DELETE"),
+        Method::PATCH => Cow::Borrowed("PATCH"),
+        Method::HEAD => Cow::Borrowed("HEAD"),
+        Method::OPTIONS => Cow::Borrowed("OPTIONS"),
+        Method::CONNECT => Cow::Borrowed("CONNECT"),
+        Method::TRACE => Cow::Borrowed("TRACE"),
+        _ => Cow::Owned(method.as_str().to_owned()),
+    }
+}
+
+impl<S> Service<Request<Body>> for MetricsMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let path = if let Some(matched_path) = request.extensions().get::<MatchedPath>() {
+            matched_path.as_str().to_owned()
+        } else {
+            request.uri().path().to_owned()
+        };
+        let config = http_metrics_config();
+        if request.extensions().get::<SkipMetrics>().is_some() || config.is_some_and(|config| config.is_excluded(&path))
+        {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let method = method_label(request.method());
+        let service_name = Arc::clone(&self.service_name);
+        let custom_labels = config
+            .map(|config| config.labels(request.extensions()))
+            .unwrap_or_default();
+        // The trace ID is only looked up if exemplars are enabled
+        let exemplar = exemplars().and_then(|exemplars| Some((exemplars, current_trace_id()?)));
+        let request_tenant = tenant_labels().and_then(|_| {
+            RequestStore::from_extensions(request.extensions())
+                .and_then(|store| store.get::<Tenant>())
+                .map(|tenant| tenant.0.clone())
+        });
+
+        let request_size = body_size(request.headers(), request.body());
+
+        let start = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+
+            // Exclude metrics endpoint
+            if path != "/metrics" && response.extensions().get::<SkipMetrics>().is_none() {
+                let latency = start.elapsed().as_secs_f64();
+                let status = status_label(response.status().as_u16());
+                let tenant = tenant_labels().map(|tenant_labels| {
+                    let tenant = request_tenant
+                        .as_deref()
+                        .or_else(|| response.extensions().get::<Tenant>().map(|tenant| tenant.0.as_str()));
+                    tenant.map(|tenant| tenant_labels.label(tenant)).unwrap_or_default()
+                });
+                let owner = response.extensions().get::<Owner>().cloned();
+                if let Some((exemplars, trace_id)) = exemplar {
+                    let mut labels = vec![
+                        ("method", method.as_ref()),
+                        ("path", path.as_str()),
+                        ("service", service_name.as_ref()),
+                        ("status", status.as_ref()),
+                    ];
+                    if let Some(tenant) = &tenant {
+                        labels.push(("tenant", tenant));
+                    }
+                    if let Some(owner) = &owner {
+                        labels.push(("owner", owner.team.as_str()));
+                    }
+                    labels.extend(custom_labels.iter().map(|label| (label.key(), label.value())));
+                    exemplars.record(&labels, latency, &trace_id);
+                }
+                let mut labels = vec![
+                    Label::new("method", method),
+                    Label::new("path", path),
+                    Label::new("service", service_name),
+                    Label::new("status", status),
+                ];
+                if let Some(tenant) = tenant {
+                    labels.push(Label::new("tenant", tenant));
+                }
+                if let Some(owner) = owner {
+                    labels.push(Label::new("owner", owner.team));
+                }
+                labels.extend(custom_labels);
+
+                if let Some(request_size) = request_size {
+                    histogram!("http_request_size_bytes", labels.clone()).record(request_size as f64);
+                }
+                if let Some(response_size) = body_size(response.headers(), response.body()) {
+                    histogram!("http_response_size_bytes", labels.clone()).record(response_size as f64);
+                }
+                counter!("http_requests_total", labels.clone()).increment(1);
+                histogram!("http_requests_duration_seconds", labels).record(latency);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Size of a body from its `Content-Length` header, or else from its exact size hint
+fn body_size(headers: &HeaderMap, body: &Body) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| body.size_hint().exact())
+}
+
+/// Map common HTTP status codes to a `&'static str` to avoid formatting an
+/// integer on every response. Falls back to an owned string for uncommon
+/// codes.
+fn status_label(code: u16) -> Cow<'static, str> {
+    match code {
+        200 => Cow::Borrowed("200"),
+        201 => Cow::Borrowed("201"),
+        204 => Cow::Borrowed("204"),
+        301 => Cow::Borrowed("301"),
+        302 => Cow::Borrowed("302"),
+        304 => Cow::Borrowed("304"),
+        400 => Cow::Borrowed("400"),
+        401 => Cow::Borrowed("401"),
+        403 => Cow::Borrowed("403"),
+        404 => Cow::Borrowed("404"),
+        409 => Cow::Borrowed("409"),
+        422 => Cow::Borrowed("422"),
+        500 => Cow::Borrowed("500"),
+        502 => Cow::Borrowed("502"),
+        503 => Cow::Borrowed("503"),
+        504 => Cow::Borrowed("504"),
+        _ => Cow::Owned(code.to_string()),
+    }
+}
+
+/// Label value of the tenants over the cardinality limit
+pub const OTHER_TENANT_LABEL: &str = "other";
+
+/// Maximum number of tenants counted before reaching `min_requests`
+const MAX_TENANT_CANDIDATES: usize = 10_000;
+
+/// Global tenant labels, like the `metrics` recorder
+static TENANT_LABELS: OnceLock<TenantLabels> = OnceLock::new();
+
+/// Enable the `tenant` label on the HTTP metrics
+///
+/// Only the first call configures the cardinality limit.
+pub fn enable_tenant_labels(tenant_labels: TenantLabels) -> &'static TenantLabels {
+    TENANT_LABELS.get_or_init(|| tenant_labels)
+}
+
+/// Get the tenant labels if they are enabled
+pub fn tenant_labels() -> Option<&'static TenantLabels> {
+    TENANT_LABELS.get()
+}
+
+/// Cardinality limiter of the `tenant` label
+///
+/// The first `max_tenants` tenants reaching `min_requests` requests keep their
+/// own label value; all the other tenants share the [`OTHER_TENANT_LABEL`] value.
+/// Requiring a minimum of requests keeps the slots for the busiest tenants.
+#[derive(Debug)]
+pub struct TenantLabels {
+    max_tenants: usize,
+    min_requests: u64,
+    state: Mutex<TenantLabelsState>,
+}
+
+#[derive(Debug, Default)]
+struct TenantLabelsState {
+    labeled: HashSet<String>,
+    candidates: HashMap<String, u64>,
+}
+
+impl TenantLabels {
+    /// Create a limiter keeping at most `max_tenants` label values (plus `other`)
+    pub fn new(max_tenants: usize) -> Self {
+        Self {
+            max_tenants,
+            min_requests: 1,
+            state: Mutex::new(TenantLabelsState::default()),
+        }
+    }
+
+    /// Number of requests before a tenant gets its own label value (1 by default)
+    pub fn with_min_requests(mut self, min_requests: u64) -> Self {
+        self.min_requests = min_requests.max(1);
+        self
+    }
+
+    /// Label value of a tenant
+    pub fn label(&self, tenant: &str) -> String {
+        let Ok(mut state) = self.state.lock() else {
+            return OTHER_TENANT_LABEL.to_string();
+        };
+        if state.labeled.contains(tenant) {
+            return tenant.to_string();
+        }
+        if state.labeled.len() >= self.max_tenants {
+            return OTHER_TENANT_LABEL.to_string();
+        }
+
+        if !state.candidates.contains_key(tenant) && state.candidates.len() >= MAX_TENANT_CANDIDATES {
+            return OTHER_TENANT_LABEL.to_string();
+        }
+        let requests = state.candidates.entry(tenant.to_string()).or_default();
+        *requests += 1;
+        if *requests < self.min_requests {
+            return OTHER_TENANT_LABEL.to_string();
+        }
+
+        state.candidates.remove(tenant);
+        state.labeled.insert(tenant.to_string());
+        if state.labeled.len() >= self.max_tenants {
+            state.candidates = HashMap::new();
+        }
+
+        tenant.to_string()
+    }
+}
+
+/// Request or response extension excluding a request from the HTTP metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkipMetrics;
+
+/// Global HTTP metrics configuration, like the `metrics` recorder
+static HTTP_METRICS_CONFIG: OnceLock<HttpMetricsConfig> = OnceLock::new();
+
+/// Configure the excluded routes and the custom labels of the HTTP metrics
+///
+/// Only the first call configures the HTTP metrics.
+pub fn configure_http_metrics(config: HttpMetricsConfig) -> &'static HttpMetricsConfig {
+    HTTP_METRICS_CONFIG.get_or_init(|| config)
+}
+
+/// Get the HTTP metrics configuration if it is set
+pub fn http_metrics_config() -> Option<&'static HttpMetricsConfig> {
+    HTTP_METRICS_CONFIG.get()
+}
+
+/// Label computed from the request extensions
+type LabelExtractor = Arc<dyn Fn(&Extensions) -> Option<String> + Send + Sync>;
+
+/// Excluded routes and custom labels of the HTTP metrics
+///
+/// The values of the custom labels must have a bounded cardinality. A dynamic
+/// label is empty if the extractor returns `None`.
+#[derive(Clone, Default)]
+pub struct HttpMetricsConfig {
+    excluded_routes: Vec<String>,
+    labels: Vec<(String, String)>,
+    dynamic_labels: Vec<(String, LabelExtractor)>,
+}
+
+impl HttpMetricsConfig {
+    /// Create a new `HttpMetricsConfig`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exclude a route pattern (`{id}` and trailing `{*rest}` segments are supported)
+    pub fn with_excluded_route(mut self, pattern: &str) -> Self {
+        self.excluded_routes.push(pattern.to_string());
+        self
+    }
+
+    /// Add a static label (e.g. `region`)
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a label computed from the request extensions
+    pub fn with_dynamic_label<F>(mut self, name: &str, extractor: F) -> Self
+    where
+        F: Fn(&Extensions) -> Option<String> + Send + Sync + 'static,
+    {
+        self.dynamic_labels.push((name.to_string(), Arc::new(extractor)));
+        self
+    }
+
+    /// Check if a request path (or a matched route) is excluded
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.excluded_routes.iter().any(|pattern| route_matches(pattern, path))
+    }
+
+    /// Custom labels of a request
+    fn labels(&self, extensions: &Extensions) -> Vec<Label> {
+        self.labels
+            .iter()
+            .map(|(name, value)| Label::new(name.clone(), value.clone()))
+            .chain(
+                self.dynamic_labels
+                    .iter()
+                    .map(|(name, extractor)| Label::new(name.clone(), extractor(extensions).unwrap_or_default())),
+            )
+            .collect()
+    }
+}
+
+/// Name of the histogram with exemplars
+const EXEMPLARS_METRIC: &str = "http_requests_duration_seconds";
+
+/// Global exemplars store, like the `metrics` recorder
+static EXEMPLARS: OnceLock<Exemplars> = OnceLock::new();
+
+/// Enable exemplars on `http_requests_duration_seconds`
+///
+/// `buckets` must be the buckets of the histogram. Only the first call configures the store.
+pub fn enable_exemplars(buckets: &[f64]) -> &'static Exemplars {
+    EXEMPLARS.get_or_init(|| Exemplars::new(buckets))
+}
+
+/// Get the exemplars store if exemplars are enabled
+pub fn exemplars() -> Option<&'static Exemplars> {
+    EXEMPLARS.get()
+}
+
+/// Sample linked to a trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exemplar {
+    pub trace_id: String,
+    pub value: f64,
+    /// Unix timestamp in seconds
+    pub timestamp: f64,
+}
+
+/// Label set, sorted by label name
+type LabelSet = Vec<(String, String)>;
+
+/// Latest exemplar of each `http_requests_duration_seconds` bucket, per label set
+#[derive(Debug)]
+pub struct Exemplars {
+    buckets: Vec<f64>,
+    values: Mutex<HashMap<LabelSet, Vec<Option<Exemplar>>>>,
+}
+
+impl Exemplars {
+    /// Create a new store for histogram buckets
+    pub fn new(buckets: &[f64]) -> Self {
+        Self {
+            buckets: buckets.to_vec(),
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a sample as the exemplar of its bucket
+    pub fn record(&self, labels: &[(&str, &str)], value: f64, trace_id: &str) {
+        let bucket = self
+            .buckets
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.buckets.len());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        if let Ok(mut values) = self.values.lock() {
+            let exemplars = values
+                .entry(Self::key(labels.iter().map(|(k, v)| (k.to_string(), v.to_string()))))
+                .or_insert_with(|| vec![None; self.buckets.len() + 1]);
+            exemplars[bucket] = Some(Exemplar {
+                trace_id: trace_id.to_string(),
+                value,
+                timestamp,
+            });
+        }
+    }
+
+    /// Get the exemplar of a bucket (`le` bound, `f64::INFINITY` for `+Inf`)
+    pub fn get(&self, labels: &[(&str, &str)], le: f64) -> Option<Exemplar> {
+        self.get_by_key(
+            &Self::key(labels.iter().map(|(k, v)| (k.to_string(), v.to_string()))),
+            le,
+        )
+    }
+
+    /// Convert the Prometheus text output of the exporter to the OpenMetrics
+    /// format, adding exemplars to the `http_requests_duration_seconds` buckets
+    pub fn render_openmetrics(&self, text: &str) -> String {
+        render_openmetrics(text, Some(self))
+    }
+
+    fn get_by_key(&self, key: &[(String, String)], le: f64) -> Option<Exemplar> {
+        let bucket = if le.is_infinite() {
+            self.buckets.len()
+        } else {
+            self.buckets.iter().position(|bound| *bound == le)?
+        };
+        let values = self.values.lock().ok()?;
+
+        values.get(key)?.get(bucket)?.clone()
+    }
+
+    /// Label set key, sorted by label name
+    fn key(labels: impl Iterator<Item = (String, String)>) -> LabelSet {
+        let mut key = labels.collect::<Vec<_>>();
+        key.sort();
+        key
+    }
+}
+
+/// Convert the Prometheus text output of the exporter to the OpenMetrics format
+///
+/// - Counter families are named without the `_total` suffix
+/// - Empty lines are removed and the output ends with `# EOF`
+/// - Exemplars are added to the `http_requests_duration_seconds` buckets
+pub(crate) fn render_openmetrics(text: &str, exemplars: Option<&Exemplars>) -> String {
+    let counters = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("# TYPE "))
+        .filter_map(|line| line.strip_suffix(" counter"))
+        .filter(|name| name.ends_with("_total"))
+        .collect::<HashSet<_>>();
+
+    let mut output = String::with_capacity(text.len() + 64);
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        if let Some(rest) = line.strip_prefix("# TYPE ").or_else(|| line.strip_prefix("# HELP ")) {
+            let name = rest.split(' ').next().unwrap_or_default();
+            if counters.contains(name) {
+                output.push_str(&line[..7]);
+                output.push_str(name.trim_end_matches("_total"));
+                output.push_str(&rest[name.len()..]);
+                output.push('\n');
+                continue;
+            }
+        }
+
+        output.push_str(line);
+        if let Some(exemplar) = exemplars.and_then(|exemplars| bucket_exemplar(line, exemplars)) {
+            output.push_str(&format!(
+                " # {{trace_id=\"{}\"}} {} {:.3}",
+                exemplar.trace_id, exemplar.value, exemplar.timestamp
+            ));
+        }
+        output.push('\n');
+    }
+    output.push_str("# EOF\n");
+
+    output
+}
+
+/// Exemplar of a `http_requests_duration_seconds_bucket` line
+fn bucket_exemplar(line: &str, exemplars: &Exemplars) -> Option<Exemplar> {
+    let labels = line
+        .strip_prefix(EXEMPLARS_METRIC)?
+        .strip_prefix("_bucket{")?
+        .rsplit_once('}')?
+        .0;
+    let mut labels = parse_labels(labels)?;
+    let le_position = labels.iter().position(|(name, _)| name == "le")?;
+    let (_, le) = labels.remove(le_position);
+    let le = match le.as_str() {
+        "+Inf" => f64::INFINITY,
+        le => le.parse().ok()?,
+    };
+
+    exemplars.get_by_key(&Exemplars::key(labels.into_iter()), le)
+}
+
+/// Parse `name="value",...` labels (values are unescaped)
+fn parse_labels(labels: &str) -> Option<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    let mut chars = labels.chars().peekable();
+
+    while chars.peek().is_some() {
+        let name = chars.by_ref().take_while(|c| *c != '=').collect::<String>();
+        if chars.next() != Some('"') {
+            return None;
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                '\\' => match chars.next()? {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                '"' => break,
+                c => value.push(c),
+            }
+        }
+        result.push((name.trim_start_matches(',').to_string(), value));
+
+        if chars.peek() == Some(&',') {
+            chars.next();
+        }
+    }
+
+    Some(result)
+}
+
+/// Naming scheme of the system metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SystemMetricsNaming {
+    /// `system_<resource>_<total|used>_bytes` names, one disk gauge per mount point (label
+    /// `mount_point`)
+    #[default]
+    Standard,
+    /// Names of the previous versions (`system_total_memory`, `system_used_disks_space`, etc.),
+    /// disk space summed over the mount points
+    Legacy,
+}
+
+/// Names of the memory, swap and disk gauges
+struct SystemMetricNames {
+    total_memory: &'static str,
+    used_memory: &'static str,
+    total_swap: &'static str,
+    used_swap: &'static str,
+    total_disk: &'static str,
+    used_disk: &'static str,
+}
+
+impl SystemMetricsNaming {
+    fn names(&self) -> SystemMetricNames {
+        match self {
+            Self::Standard => SystemMetricNames {
+                total_memory: "system_memory_total_bytes",
+                used_memory: "system_memory_used_bytes",
+                total_swap: "system_swap_total_bytes",
+                used_swap: "system_swap_used_bytes",
+                total_disk: "system_disk_total_bytes",
+                used_disk: "system_disk_used_bytes",
+            },
+            Self::Legacy => SystemMetricNames {
+                total_memory: "system_total_memory",
+                used_memory: "system_used_memory",
+                total_swap: "system_total_swap",
+                used_swap: "system_used_swap",
+                total_disk: "system_total_disks_space",
+                used_disk: "system_used_disks_space",
+            },
+        }
+    }
+}
+
+/// Options of the system metrics collector
+///
+/// Each optional metric has its own sampling cost, so they are all disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemMetricsConfig {
+    /// Naming scheme of the memory, swap and disk gauges
+    pub naming: SystemMetricsNaming,
+    /// `system_cpu_core_usage` gauge (label `core`)
+    pub per_core_cpu: bool,
+    /// `system_load_average_1m`, `system_load_average_5m` and `system_load_average_15m` gauges
+    pub load_average: bool,
+    /// `system_open_file_descriptors` and `system_max_file_descriptors` gauges of the process
+    pub file_descriptors: bool,
+    /// `system_network_received_bytes` and `system_network_transmitted_bytes` gauges (label `interface`)
+    pub network: bool,
+}
+
+impl SystemMetricsConfig {
+    /// Create a new config without optional metrics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable all the optional metrics
+    pub fn all() -> Self {
+        Self {
+            naming: SystemMetricsNaming::default(),
+            per_core_cpu: true,
+            load_average: true,
+            file_descriptors: true,
+            network: true,
+        }
+    }
+
+    /// Set the naming scheme
+    pub fn with_naming(mut self, naming: SystemMetricsNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Enable the per-core CPU usage
+    pub fn with_per_core_cpu(mut self) -> Self {
+        self.per_core_cpu = true;
+        self
+    }
+
+    /// Enable the load averages
+    pub fn with_load_average(mut self) -> Self {
+        self.load_average = true;
+        self
+    }
+
+    /// Enable the open file descriptors count
+    pub fn with_file_descriptors(mut self) -> Self {
+        self.file_descriptors = true;
+        self
+    }
+
+    /// Enable the network bytes per interface
+    pub fn with_network(mut self) -> Self {
+        self.network = true;
+        self
+    }
+}
+
+/// Spawn a background task that periodically refreshes host metrics and
+/// publishes them as Prometheus gauges.
+///
+/// Emitted gauges (all labeled by `service`):
+///
+/// - `system_cpu_usage` — average global CPU usage in percent
+/// - `system_memory_total_bytes` / `system_memory_used_bytes`
+/// - `system_swap_total_bytes` / `system_swap_used_bytes`
+/// - `system_disk_total_bytes` / `system_disk_used_bytes` — for each of the
+///   `disk_mount_points` (label `mount_point`)
+///
+/// The names of the previous versions are available with
+/// [`SystemMetricsNaming::Legacy`].
+///
+/// The first tick reports `system_cpu_usage = 0.0` because `sysinfo` needs
+/// two snapshots to compute a delta. Subsequent ticks report the real value.
+///
+/// The returned [`JoinHandle`] can be aborted at shutdown; if dropped, the
+/// task continues running for the lifetime of the Tokio runtime.
+pub fn spawn_system_metrics_collector(
+    service_name: String,
+    disk_mount_points: Vec<PathBuf>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    spawn_system_metrics_collector_with_config(service_name, disk_mount_points, interval, SystemMetricsConfig::new())
+}
+
+/// Spawn the system metrics collector (see [`spawn_system_metrics_collector`])
+/// with the optional metrics enabled in `config`:
+///
+/// - `system_cpu_core_usage` — CPU usage of each core in percent (label `core`)
+/// - `system_load_average_1m` / `_5m` / `_15m` — load averages (not on Windows)
+/// - `system_open_file_descriptors` / `system_max_file_descriptors` — open
+///   file descriptors of the process and their limit (Linux only)
+/// - `system_network_received_bytes` / `system_network_transmitted_bytes` —
+///   bytes since boot (label `interface`)
+pub fn spawn_system_metrics_collector_with_config(
+    service_name: String,
+    disk_mount_points: Vec<PathBuf>,
+    interval: Duration,
+    config: SystemMetricsConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let refresh_kind = RefreshKind::nothing()
+            .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+            .with_memory(MemoryRefreshKind::everything());
+        let mut sys = System::new_with_specifics(refresh_kind);
+        let mut networks = config.network.then(Networks::new_with_refreshed_list);
+        let pid = sysinfo::get_current_pid().ok();
+        let names = config.naming.names();
+
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            sys.refresh_cpu_usage();
+            sys.refresh_memory();
+
+            let cpu_usage = sys.global_cpu_usage();
+            let total_memory = sys.total_memory();
+            let used_memory = sys.used_memory();
+            let total_swap = sys.total_swap();
+            let used_swap = sys.used_swap();
+
+            gauge!("system_cpu_usage", "service" => service_name.clone()).set(cpu_usage);
+            gauge!(names.total_memory, "service" => service_name.clone()).set(total_memory as f64);
+            gauge!(names.used_memory, "service" => service_name.clone()).set(used_memory as f64);
+            gauge!(names.total_swap, "service" => service_name.clone()).set(total_swap as f64);
+            gauge!(names.used_swap, "service" => service_name.clone()).set(used_swap as f64);
+
+            let disks = Disks::new_with_refreshed_list();
+            let disks = disks.iter().filter(|disk| {
+                disk_mount_points
+                    .iter()
+                    .any(|mount_point| mount_point == disk.mount_point())
+            });
+            match config.naming {
+                SystemMetricsNaming::Standard => {
+                    for disk in disks {
+                        let mount_point = disk.mount_point().display().to_string();
+                        let used_space = disk.total_space().saturating_sub(disk.available_space());
+                        gauge!(names.total_disk, "service" => service_name.clone(), "mount_point" => mount_point.clone())
+                            .set(disk.total_space() as f64);
+                        gauge!(names.used_disk, "service" => service_name.clone(), "mount_point" => mount_point)
+                            .set(used_space as f64);
+                    }
+                }
+                SystemMetricsNaming::Legacy => {
+                    let (total_disks_space, used_disks_space) = disks.fold((0_u64, 0_u64), |(total, used), disk| {
+                        (
+                            total + disk.total_space(),
+                            used + disk.total_space().saturating_sub(disk.available_space()),
+                        )
+                    });
+                    gauge!(names.total_disk, "service" => service_name.clone()).set(total_disks_space as f64);
+                    gauge!(names.used_disk, "service" => service_name.clone()).set(used_disks_space as f64);
+                }
+            }
+
+            if config.per_core_cpu {
+                for (core, cpu) in sys.cpus().iter().enumerate() {
+                    gauge!("system_cpu_core_usage", "service" => service_name.clone(), "core" => core.to_string())
+                        .set(cpu.cpu_usage());
+                }
+            }
+
+            if config.load_average {
+                let load_average = System::load_average();
+                gauge!("system_load_average_1m", "service" => service_name.clone()).set(load_average.one);
+                gauge!("system_load_average_5m", "service" => service_name.clone()).set(load_average.five);
+                gauge!("system_load_average_15m", "service" => service_name.clone()).set(load_average.fifteen);
+            }
+
+            if config.file_descriptors
+                && let Some(pid) = pid
+            {
+                sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), false, ProcessRefreshKind::nothing());
+                if let Some(process) = sys.process(pid) {
+                    if let Some(open_files) = process.open_files() {
+                        gauge!("system_open_file_descriptors", "service" => service_name.clone())
+                            .set(open_files as f64);
+                    }
+                    if let Some(open_files_limit) = process.open_files_limit() {
+                        gauge!("system_max_file_descriptors", "service" => service_name.clone())
+                            .set(open_files_limit as f64);
+                    }
+                }
+            }
+
+            if let Some(networks) = networks.as_mut() {
+                networks.refresh(true);
+                for (interface, network) in networks.iter() {
+                    gauge!(
+                        "system_network_received_bytes",
+                        "service" => service_name.clone(),
+                        "interface" => interface.clone()
+                    )
+                    .set(network.total_received() as f64);
+                    gauge!(
+                        "system_network_transmitted_bytes",
+                        "service" => service_name.clone(),
+                        "interface" => interface.clone()
+                    )
+                    .set(network.total_transmitted() as f64);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, Response, StatusCode};
+    use std::convert::Infallible;
+    use tower::{ServiceBuilder, ServiceExt};
+
+    /// Sentinel test: the middleware must not block on host-metrics collection.
+    /// In 0.7.x, this call took ~200 ms because of an in-path
+    /// `tokio::time::sleep(MINIMUM_CPU_UPDATE_INTERVAL)`. After the refactor,
+    /// it should complete in well under 10 ms.
+    #[tokio::test]
+    async fn middleware_does_not_block_on_system_metrics() {
+        let svc = ServiceBuilder::new()
+            .layer(MetricsLayer {
+                service_name: "test".into(),
+            })
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+            }));
+
+        let start = Instant::now();
+        let response = svc
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            elapsed < Duration::from_millis(50),
+            "middleware took {elapsed:?}, expected < 50 ms",
+        );
+    }
+
+    /// Smoke test: the collector must start, tick at least twice without
+    /// panicking, and remain alive until aborted.
+    #[tokio::test]
+    async fn collector_ticks_without_panicking() {
+        let handle = spawn_system_metrics_collector("test".into(), vec![PathBuf::from("/")], Duration::from_millis(50));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(!handle.is_finished(), "collector ended prematurely");
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn collector_with_all_optional_metrics_ticks_without_panicking() {
+        let handle = spawn_system_metrics_collector_with_config(
+            "test".into(),
+            vec![PathBuf::from("/")],
+            Duration::from_millis(50),
+            SystemMetricsConfig::all(),
+        );
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(!handle.is_finished(), "collector ended prematurely");
+        handle.abort();
+    }
+
+    #[test]
+    fn test_system_metrics_config() {
+        assert_eq!(SystemMetricsConfig::new(), SystemMetricsConfig::default());
+        assert_eq!(
+            SystemMetricsConfig::new()
+                .with_per_core_cpu()
+                .with_load_average()
+                .with_file_descriptors()
+                .with_network(),
+            SystemMetricsConfig::all()
+        );
+        assert_eq!(
+            SystemMetricsConfig::new()
+                .with_naming(SystemMetricsNaming::Legacy)
+                .naming
+                .names()
+                .used_disk,
+            "system_used_disks_space"
+        );
+    }
+
+    #[tokio::test]
+    async fn collector_with_legacy_names_ticks_without_panicking() {
+        let handle = spawn_system_metrics_collector_with_config(
+            "test".into(),
+            vec![PathBuf::from("/")],
+            Duration::from_millis(50),
+            SystemMetricsConfig::new().with_naming(SystemMetricsNaming::Legacy),
+        );
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(!handle.is_finished(), "collector ended prematurely");
+        handle.abort();
+    }
+
+    #[test]
+    fn test_method_label_standard_methods_are_borrowed() {
+        for (method, expected) in [
+            (Method::GET, "GET"),
+            (Method::POST, "POST"),
+            (Method::PUT, "PUT"),
+            (Method::DELETE, "DELETE"),
+            (Method::PATCH, "PATCH"),
+            (Method::HEAD, "HEAD"),
+            (Method::OPTIONS, "OPTIONS"),
+            (Method::CONNECT, "CONNECT"),
+            (Method::TRACE, "TRACE"),
+        ] {
+            let label = method_label(&method);
+            assert_eq!(label, expected);
+            assert!(
+                matches!(label, Cow::Borrowed(_)),
+                "{method} should be Borrowed, got Owned (allocation in hot path)",
+            );
+        }
+    }
+
+    #[test]
+    fn test_method_label_custom_method_is_owned() {
+        let custom = Method::from_bytes(b"PURGE").unwrap();
+        let label = method_label(&custom);
+        assert_eq!(label, "PURGE");
+        assert!(matches!(label, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_status_label_common_codes_are_borrowed() {
+        for code in [
+            200, 201, 204, 301, 302, 304, 400, 401, 403, 404, 409, 422, 500, 502, 503, 504,
+        ] {
+            let label = status_label(code);
+            assert_eq!(label, code.to_string());
+            assert!(
+                matches!(label, Cow::Borrowed(_)),
+                "{code} should be Borrowed, got Owned (allocation in hot path)",
+            );
+        }
+    }
+
+    #[test]
+    fn test_status_label_uncommon_code_is_owned() {
+        let label = status_label(418);
+        assert_eq!(label, "418");
+        assert!(matches!(label, Cow::Owned(_)));
+    }
+
+    /// The middleware must short-circuit on `/metrics` requests (avoiding
+    /// observation loops). We can't easily inspect the global recorder, but
+    /// we can at least verify the path is exercised without panicking.
+    #[tokio::test]
+    async fn middleware_handles_metrics_path() {
+        let svc = ServiceBuilder::new()
+            .layer(MetricsLayer {
+                service_name: "test".into(),
+            })
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+            }));
+
+        let response = svc
+            .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_http_metrics_config() {
+        #[derive(Clone)]
+        struct Region(&'static str);
+
+        let config = HttpMetricsConfig::new()
+            .with_excluded_route("/health")
+            .with_excluded_route("/internal/{*path}")
+            .with_label("env", "prod")
+            .with_dynamic_label("region", |extensions| {
+                extensions.get::<Region>().map(|region| region.0.to_string())
+            });
+        assert!(config.is_excluded("/health"));
+        assert!(config.is_excluded("/internal/jobs/1"));
+        assert!(!config.is_excluded("/users"));
+
+        let mut extensions = Extensions::new();
+        assert_eq!(
+            config.labels(&extensions),
+            vec![Label::new("env", "prod"), Label::new("region", "")]
+        );
+        extensions.insert(Region("eu"));
+        assert_eq!(config.labels(&extensions)[1], Label::new("region", "eu"));
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn middleware_skips_requests_with_skip_metrics() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let svc = ServiceBuilder::new()
+            .layer(MetricsLayer {
+                service_name: "test".into(),
+            })
+            .service(tower::service_fn(|req: Request<Body>| async move {
+                let mut response = Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap();
+                if req.uri().path() == "/skip-response" {
+                    response.extensions_mut().insert(SkipMetrics);
+                }
+                Ok::<_, Infallible>(response)
+            }));
+
+        metrics::with_local_recorder(&recorder, || {
+            futures::executor::block_on(async {
+                for uri in ["/users", "/skip-response"] {
+                    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+                    svc.clone().oneshot(request).await.unwrap();
+                }
+                let mut request = Request::builder().uri("/skip-request").body(Body::empty()).unwrap();
+                request.extensions_mut().insert(SkipMetrics);
+                svc.clone().oneshot(request).await.unwrap();
+            })
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains(r#"http_requests_total{method="GET",path="/users",service="test",status="200"} 1"#));
+        assert!(!rendered.contains("/skip"));
+    }
+
+    #[test]
+    fn test_exemplars_record_in_bucket() {
+        let exemplars = Exemplars::new(&[0.1, 0.5]);
+        let labels = [("path", "/users"), ("method", "GET")];
+        exemplars.record(&labels, 0.05, "trace-1");
+        exemplars.record(&labels, 0.3, "trace-2");
+        exemplars.record(&labels, 0.08, "trace-3");
+        exemplars.record(&labels, 2.0, "trace-4");
+
+        assert_eq!(exemplars.get(&labels, 0.1).unwrap().trace_id, "trace-3");
+        assert_eq!(
+            exemplars
+                .get(&[("method", "GET"), ("path", "/users")], 0.5)
+                .unwrap()
+                .trace_id,
+            "trace-2"
+        );
+        assert_eq!(exemplars.get(&labels, f64::INFINITY).unwrap().value, 2.0);
+        assert!(exemplars.get(&labels, 0.2).is_none());
+        assert!(exemplars.get(&[("path", "/other")], 0.1).is_none());
+    }
+
+    #[test]
+    fn test_tenant_labels_cardinality() {
+        let tenant_labels = TenantLabels::new(2).with_min_requests(2);
+        assert_eq!(tenant_labels.label("acme"), OTHER_TENANT_LABEL);
+        assert_eq!(tenant_labels.label("globex"), OTHER_TENANT_LABEL);
+        assert_eq!(tenant_labels.label("acme"), "acme");
+        assert_eq!(tenant_labels.label("acme"), "acme");
+        assert_eq!(tenant_labels.label("initech"), OTHER_TENANT_LABEL);
+        assert_eq!(tenant_labels.label("initech"), "initech");
+
+        // Limit reached
+        assert_eq!(tenant_labels.label("globex"), OTHER_TENANT_LABEL);
+        assert_eq!(tenant_labels.label("globex"), OTHER_TENANT_LABEL);
+        assert_eq!(tenant_labels.label("acme"), "acme");
+        assert_eq!(tenant_labels.label("initech"), "initech");
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(
+            parse_labels(r#"method="GET",path="/a\"b\\c",le="0.1""#),
+            Some(vec![
+                ("method".to_string(), "GET".to_string()),
+                ("path".to_string(), r#"/a"b\c"#.to_string()),
+                ("le".to_string(), "0.1".to_string()),
+            ])
+        );
+        assert_eq!(parse_labels(""), Some(vec![]));
+        assert_eq!(parse_labels("method=GET"), None);
+        assert_eq!(parse_labels(r#"method="GET"#), None);
+    }
+
+    #[test]
+    fn test_render_openmetrics_with_exemplars() {
+        let exemplars = Exemplars::new(&[0.1, 1.0]);
+        let labels = [
+            ("method", "GET"),
+            ("path", "/users"),
+            ("service", "api"),
+            ("status", "200"),
+        ];
+        exemplars.record(&labels, 0.05, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        let text = "# TYPE http_requests_total counter
+http_requests_total{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\"} 1
+
+# TYPE http_requests_duration_seconds histogram
+http_requests_duration_seconds_bucket{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\",le=\"0.1\"} 1
+http_requests_duration_seconds_bucket{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\",le=\"1\"} 1
+http_requests_duration_seconds_bucket{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\",le=\"+Inf\"} 1
+http_requests_duration_seconds_sum{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\"} 0.05
+http_requests_duration_seconds_count{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\"} 1
+
+";
+        let output = exemplars.render_openmetrics(text);
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "# TYPE http_requests counter");
+        assert_eq!(lines[1], text.lines().nth(1).unwrap());
+        assert_eq!(lines[2], "# TYPE http_requests_duration_seconds histogram");
+        assert!(
+            lines[3].starts_with(
+                "http_requests_duration_seconds_bucket{method=\"GET\",path=\"/users\",service=\"api\",status=\"200\",le=\"0.1\"} 1 # {trace_id=\"4bf92f3577b34da6a3ce929d0e0e4736\"} 0.05 "
+            ),
+            "{}",
+            lines[3]
+        );
+        assert!(!lines[4].contains('#'));
+        assert!(!lines[5].contains('#'));
+        assert_eq!(lines.last(), Some(&"# EOF"));
+        assert_eq!(lines.len(), 9);
+    }
+}
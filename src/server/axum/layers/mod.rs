@@ -1,13 +1,41 @@
 //! Axum layers
 
+pub mod adaptive_throttle;
+pub mod authorize;
 pub mod basic_auth;
+pub mod bot_mitigation;
+pub mod canary;
+pub mod catch_panic;
+pub mod checksum;
+pub mod circuit_breaker;
+pub mod content_negotiation;
+pub mod context;
 pub mod cors;
+pub mod deadline;
+pub mod deprecation;
+pub mod fault_injection;
+pub mod header_policy;
+pub mod hmac_signature;
 pub mod http_errors;
+pub mod injector;
+pub mod ip_filter;
+pub mod jwt_auth;
+pub mod load_shedding;
 pub mod logger;
+pub mod map_response;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod nonce;
+#[cfg(feature = "openapi")]
+pub mod openapi_validation;
+pub mod ownership;
 #[cfg(feature = "prometheus")]
 pub mod prometheus;
+pub mod quota;
 pub mod request_id;
 pub mod security_headers;
+pub mod singleflight;
+pub mod slow_request;
 pub mod time_limiter;
 
 use crate::server::axum::response::ApiErrorResponse;
@@ -43,6 +71,32 @@ pub fn body_from_parts(
     Bytes::from(msg.to_string())
 }
 
+/// Check if a request path (or a matched route) matches a route pattern
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+pub(crate) fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut path_segments = path.trim_end_matches('/').split('/');
+
+    for pattern_segment in pattern.trim_end_matches('/').split('/') {
+        if pattern_segment == "*" || pattern_segment.starts_with("{*") {
+            return true;
+        }
+
+        match path_segments.next() {
+            Some(segment) if pattern_segment.starts_with('{') && pattern_segment.ends_with('}') => {
+                if segment.is_empty() {
+                    return false;
+                }
+            }
+            Some(segment) if segment == pattern_segment => {}
+            _ => return false,
+        }
+    }
+
+    path_segments.next().is_none()
+}
+
 /// Convert `HeaderValue` to `&str`
 pub fn header_value_to_str(value: Option<&HeaderValue>) -> &str {
     match value {
@@ -0,0 +1,399 @@
+//! Replay protection layer (nonce)
+//!
+//! The client sends a unique nonce and the Unix timestamp (in seconds) of the request:
+//!
+//! - `X-Nonce`: 16 to 128 visible ASCII characters (e.g. a UUID)
+//! - `X-Timestamp`: Unix timestamp of the request (the header of the `HmacSignatureLayer`)
+//!
+//! The layer rejects the requests with a missing or invalid nonce, or with a timestamp outside the
+//! tolerance, with a `401 Unauthorized` response. The seen nonces are kept in a [`NonceStore`]
+//! for twice the tolerance, so a replayed request is rejected with a `409 Conflict` response
+//! until its timestamp is too old anyway. A store failure returns a `503 Service Unavailable`.
+//!
+//! Put it inside the `HmacSignatureLayer` (which authenticates the timestamp) and include the
+//! nonce in the signed body for high-security integrations.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::hmac_signature::HmacSignatureLayer;
+//! use api_tools::server::axum::layers::nonce::{MemoryNonceStore, NonceLayer};
+//! use axum::{Router, routing::post};
+//! use std::time::Duration;
+//!
+//! let app: Router = Router::new()
+//!     .route("/webhooks", post(|| async { "ok" }))
+//!     .layer(NonceLayer::new(MemoryNonceStore::new()).with_tolerance(Duration::from_secs(60)))
+//!     .layer(HmacSignatureLayer::new(&["secret"]).with_tolerance(Duration::from_secs(60)));
+//! ```
+
+use super::body_from_parts;
+use super::hmac_signature::HMAC_TIMESTAMP_HEADER;
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderName, Request, StatusCode};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// Default nonce header
+pub const NONCE_HEADER: &str = "x-nonce";
+
+/// Default timestamp header (same as the `HmacSignatureLayer`)
+pub const NONCE_TIMESTAMP_HEADER: &str = HMAC_TIMESTAMP_HEADER;
+
+/// Default timestamp tolerance (5 minutes)
+pub const NONCE_DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Min length of a nonce
+pub const NONCE_MIN_LENGTH: usize = 16;
+
+/// Max length of a nonce
+pub const NONCE_MAX_LENGTH: usize = 128;
+
+/// Interval between two purges of the expired nonces of the [`MemoryNonceStore`]
+const MEMORY_STORE_PURGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Nonce store error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum NonceStoreError {
+    #[error("Nonce store error: {0}")]
+    Backend(String),
+}
+
+/// Store of the seen nonces
+pub trait NonceStore: Send + Sync + 'static {
+    /// Record a nonce for `ttl`, returning `false` if it has already been seen and is not expired
+    fn insert<'a>(&'a self, nonce: &'a str, ttl: Duration) -> BoxFuture<'a, Result<bool, NonceStoreError>>;
+}
+
+#[derive(Debug)]
+struct MemoryNonces {
+    expirations: HashMap<String, Instant>,
+    last_purge: Instant,
+}
+
+/// In-memory nonce store, not shared between the instances of the service
+#[derive(Debug)]
+pub struct MemoryNonceStore {
+    nonces: Mutex<MemoryNonces>,
+}
+
+impl Default for MemoryNonceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryNonceStore {
+    /// Create a new `MemoryNonceStore`
+    pub fn new() -> Self {
+        Self {
+            nonces: Mutex::new(MemoryNonces {
+                expirations: HashMap::new(),
+                last_purge: Instant::now(),
+            }),
+        }
+    }
+
+    /// Number of nonces in the store (including the expired ones not purged yet)
+    pub fn len(&self) -> usize {
+        self.nonces
+            .lock()
+            .map(|nonces| nonces.expirations.len())
+            .unwrap_or_default()
+    }
+
+    /// Return true if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert_sync(&self, nonce: &str, ttl: Duration) -> Result<bool, NonceStoreError> {
+        let mut nonces = self
+            .nonces
+            .lock()
+            .map_err(|err| NonceStoreError::Backend(err.to_string()))?;
+        let now = Instant::now();
+
+        if now.duration_since(nonces.last_purge) >= MEMORY_STORE_PURGE_INTERVAL {
+            nonces.expirations.retain(|_, expiration| *expiration > now);
+            nonces.last_purge = now;
+        }
+
+        match nonces.expirations.get(nonce) {
+            Some(expiration) if *expiration > now => Ok(false),
+            _ => {
+                nonces.expirations.insert(nonce.to_string(), now + ttl);
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn insert<'a>(&'a self, nonce: &'a str, ttl: Duration) -> BoxFuture<'a, Result<bool, NonceStoreError>> {
+        Box::pin(async move { self.insert_sync(nonce, ttl) })
+    }
+}
+
+/// Rejection of a request by the [`NonceLayer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NonceRejection {
+    Invalid(&'static str),
+    Replayed,
+    Store,
+}
+
+#[derive(Clone)]
+pub struct NonceLayer {
+    pub store: Arc<dyn NonceStore>,
+    pub nonce_header: HeaderName,
+    pub timestamp_header: HeaderName,
+    pub tolerance: Duration,
+}
+
+impl NonceLayer {
+    /// Create a new `NonceLayer`
+    pub fn new(store: impl NonceStore) -> Self {
+        Self::from_arc(Arc::new(store))
+    }
+
+    /// Create a new `NonceLayer` from a shared store
+    pub fn from_arc(store: Arc<dyn NonceStore>) -> Self {
+        Self {
+            store,
+            nonce_header: HeaderName::from_static(NONCE_HEADER),
+            timestamp_header: HeaderName::from_static(NONCE_TIMESTAMP_HEADER),
+            tolerance: NONCE_DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Update the nonce header name
+    pub fn with_nonce_header(mut self, header: HeaderName) -> Self {
+        self.nonce_header = header;
+        self
+    }
+
+    /// Update the timestamp header name
+    pub fn with_timestamp_header(mut self, header: HeaderName) -> Self {
+        self.timestamp_header = header;
+        self
+    }
+
+    /// Update the accepted difference between the timestamp and now
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Check the timestamp and record the nonce
+    async fn check(&self, headers: &HeaderMap) -> Result<(), NonceRejection> {
+        let nonce = headers
+            .get(&self.nonce_header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid_nonce(value))
+            .ok_or(NonceRejection::Invalid("Invalid nonce"))?;
+
+        let timestamp = headers
+            .get(&self.timestamp_header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok())
+            .ok_or(NonceRejection::Invalid("Invalid timestamp"))?;
+        if chrono::Utc::now().timestamp().abs_diff(timestamp) > self.tolerance.as_secs() {
+            return Err(NonceRejection::Invalid("Expired timestamp"));
+        }
+
+        match self.store.insert(nonce, self.tolerance * 2).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(NonceRejection::Replayed),
+            Err(err) => {
+                tracing::error!(error = %err, "nonce store failure");
+                Err(NonceRejection::Store)
+            }
+        }
+    }
+}
+
+/// Return true if the nonce has a valid length and only visible ASCII characters
+fn is_valid_nonce(nonce: &str) -> bool {
+    (NONCE_MIN_LENGTH..=NONCE_MAX_LENGTH).contains(&nonce.len()) && nonce.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+impl<S> Layer<S> for NonceLayer {
+    type Service = NonceMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        NonceMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NonceMiddleware<S> {
+    inner: S,
+    config: NonceLayer,
+}
+
+impl<S> Service<Request<Body>> for NonceMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            match config.check(request.headers()).await {
+                Ok(()) => inner.call(request).await,
+                Err(NonceRejection::Invalid(message)) => Ok(error_response(StatusCode::UNAUTHORIZED, message)),
+                Err(NonceRejection::Replayed) => Ok(error_response(StatusCode::CONFLICT, "Replayed request")),
+                Err(NonceRejection::Store) => {
+                    Ok(error_response(StatusCode::SERVICE_UNAVAILABLE, "Service unavailable"))
+                }
+            }
+        })
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, None);
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    struct FailingStore;
+
+    impl NonceStore for FailingStore {
+        fn insert<'a>(&'a self, _nonce: &'a str, _ttl: Duration) -> BoxFuture<'a, Result<bool, NonceStoreError>> {
+            Box::pin(async { Err(NonceStoreError::Backend("connection refused".to_string())) })
+        }
+    }
+
+    fn request(nonce: Option<&str>, timestamp: Option<i64>) -> Request<Body> {
+        let mut request = Request::builder().method("POST").uri("/webhooks");
+        if let Some(nonce) = nonce {
+            request = request.header(NONCE_HEADER, nonce);
+        }
+        if let Some(timestamp) = timestamp {
+            request = request.header(NONCE_TIMESTAMP_HEADER, timestamp.to_string());
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    async fn call(layer: &NonceLayer, request: Request<Body>) -> (StatusCode, String) {
+        let response = layer
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("ok")))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn test_is_valid_nonce() {
+        assert!(is_valid_nonce("0f8fad5b-d9cb-469f-a165-70867728950e"));
+        assert!(!is_valid_nonce("too-short"));
+        assert!(!is_valid_nonce(&"a".repeat(NONCE_MAX_LENGTH + 1)));
+        assert!(!is_valid_nonce("with some spaces inside"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_nonce_store() {
+        let store = MemoryNonceStore::new();
+        assert!(store.is_empty());
+
+        assert_eq!(store.insert("nonce-1", Duration::from_secs(60)).await, Ok(true));
+        assert_eq!(store.insert("nonce-1", Duration::from_secs(60)).await, Ok(false));
+        assert_eq!(store.insert("nonce-2", Duration::ZERO).await, Ok(true));
+        assert_eq!(store.insert("nonce-2", Duration::ZERO).await, Ok(true));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_layer_rejects_replays() {
+        let layer = NonceLayer::new(MemoryNonceStore::new());
+        let nonce = "0f8fad5b-d9cb-469f-a165-70867728950e";
+
+        let (status, body) = call(&layer, request(Some(nonce), Some(now()))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "ok");
+
+        let (status, body) = call(&layer, request(Some(nonce), Some(now()))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body, r#"{"code":409,"message":"Replayed request"}"#);
+
+        let (status, _) = call(
+            &layer,
+            request(Some("1b4e28ba-2fa1-11d2-883f-0016d3cca427"), Some(now())),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_layer_rejects_invalid_requests() {
+        let layer = NonceLayer::new(MemoryNonceStore::new()).with_tolerance(Duration::from_secs(60));
+        let nonce = "0f8fad5b-d9cb-469f-a165-70867728950e";
+
+        let (status, body) = call(&layer, request(None, Some(now()))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, r#"{"code":401,"message":"Invalid nonce"}"#);
+
+        let (status, body) = call(&layer, request(Some(nonce), None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, r#"{"code":401,"message":"Invalid timestamp"}"#);
+
+        let (status, body) = call(&layer, request(Some(nonce), Some(now() - 120))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, r#"{"code":401,"message":"Expired timestamp"}"#);
+
+        // The rejected requests do not consume the nonce
+        let (status, _) = call(&layer, request(Some(nonce), Some(now()))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_layer_store_failure() {
+        let layer = NonceLayer::new(FailingStore);
+
+        let (status, _) = call(
+            &layer,
+            request(Some("0f8fad5b-d9cb-469f-a165-70867728950e"), Some(now())),
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
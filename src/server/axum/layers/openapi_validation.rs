@@ -0,0 +1,927 @@
+//! Runtime validation against an OpenAPI document (`openapi` feature)
+//!
+//! [`OpenApiValidator`] validates the query parameters and the JSON bodies of the requests, and the
+//! status and the JSON bodies of the responses, against the operations of an OpenAPI 3 document.
+//! [`OpenApiValidationLayer`] applies it to every request, to catch contract drift early in
+//! development or staging environments:
+//!
+//! - [`ValidationMode::Enforce`]: invalid requests are rejected with a `400 Bad Request` error
+//!   whose message is the list of field errors (same envelope as `ValidationErrors`)
+//! - [`ValidationMode::Report`]: invalid requests are only logged
+//!
+//! Invalid responses are always logged, never modified. Requests of undocumented operations are
+//! not validated.
+//!
+//! The supported schema keywords are `$ref` (local), `type`, `nullable`, `enum`, `required`,
+//! `properties`, `additionalProperties`, `items`, `allOf`, `anyOf`, `oneOf`, `minimum`, `maximum`,
+//! `minLength`, `maxLength`, `pattern`, `minItems` and `maxItems`.
+//!
+//! With the `metrics` feature, the `openapi_violations_total` counter (label `kind`: `request` or
+//! `response`) counts the invalid requests and responses.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::openapi_validation::{
+//!     OpenApiValidationLayer, OpenApiValidator, ValidationMode,
+//! };
+//! use axum::{Router, routing::get};
+//! use serde_json::json;
+//!
+//! let document = json!({
+//!     "openapi": "3.1.0",
+//!     "paths": {
+//!         "/users": {
+//!             "get": {
+//!                 "parameters": [
+//!                     { "name": "page", "in": "query", "required": true, "schema": { "type": "integer", "minimum": 1 } }
+//!                 ],
+//!                 "responses": { "200": { "description": "Users" } }
+//!             }
+//!         }
+//!     }
+//! });
+//! let validator = OpenApiValidator::new(&document).unwrap();
+//!
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .layer(OpenApiValidationLayer::new(validator).with_mode(ValidationMode::Report));
+//! ```
+
+use crate::server::axum::layers::route_matches;
+use crate::server::axum::response::{ApiErrorResponse, FieldError, ValidationErrors, current_trace_id};
+use axum::Json;
+use axum::body::{Body, HttpBody};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// Default maximum size of the validated bodies (1 MiB)
+pub const OPENAPI_VALIDATION_DEFAULT_BODY_MAX_SIZE: usize = 1_024 * 1_024;
+
+/// Maximum depth of the validated schemas (recursive schemas)
+const MAX_DEPTH: usize = 32;
+
+/// OpenAPI validator error
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum OpenApiValidatorError {
+    #[error("Invalid OpenAPI document: {0}")]
+    InvalidDocument(String),
+}
+
+/// Behavior of the layer for the invalid requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Reject the invalid requests with a `400 Bad Request` error
+    #[default]
+    Enforce,
+
+    /// Log the invalid requests
+    Report,
+}
+
+/// Query parameter of an operation
+#[derive(Debug, Clone)]
+struct QueryParameter {
+    name: String,
+    required: bool,
+    schema: Value,
+}
+
+/// Documented operation
+#[derive(Debug, Clone)]
+struct Operation {
+    method: Method,
+    pattern: String,
+    parameters: Vec<QueryParameter>,
+    /// Request body: required and JSON schema
+    body: Option<(bool, Option<Value>)>,
+    /// JSON schemas of the responses by status (`200`, `2XX` or `default`)
+    responses: Vec<(String, Option<Value>)>,
+}
+
+/// Validator of the requests and the responses against an OpenAPI document
+#[derive(Debug, Clone)]
+pub struct OpenApiValidator {
+    document: Arc<Value>,
+    operations: Arc<[Operation]>,
+}
+
+impl OpenApiValidator {
+    /// Create a validator from an OpenAPI 3 document in JSON
+    pub fn new(document: &Value) -> Result<Self, OpenApiValidatorError> {
+        let paths = document
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| OpenApiValidatorError::InvalidDocument("missing `paths`".to_string()))?;
+
+        let mut validator = Self {
+            document: Arc::new(document.clone()),
+            operations: Arc::from([]),
+        };
+        let mut operations = Vec::new();
+        for (pattern, item) in paths {
+            let item = validator.resolve(item);
+            let common_parameters = item
+                .get("parameters")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+
+            for method in ["get", "put", "post", "delete", "options", "head", "patch", "trace"] {
+                let Some(operation) = item.get(method) else {
+                    continue;
+                };
+                let parameters = common_parameters
+                    .iter()
+                    .chain(
+                        operation
+                            .get("parameters")
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten(),
+                    )
+                    .map(|parameter| validator.resolve(parameter))
+                    .filter(|parameter| parameter.get("in").and_then(Value::as_str) == Some("query"))
+                    .filter_map(|parameter| {
+                        Some(QueryParameter {
+                            name: parameter.get("name")?.as_str()?.to_string(),
+                            required: parameter.get("required").and_then(Value::as_bool).unwrap_or(false),
+                            schema: parameter.get("schema").cloned().unwrap_or(Value::Null),
+                        })
+                    })
+                    .collect();
+                let body = operation.get("requestBody").map(|body| {
+                    let body = validator.resolve(body);
+                    (
+                        body.get("required").and_then(Value::as_bool).unwrap_or(false),
+                        json_schema(body),
+                    )
+                });
+                let responses = operation
+                    .get("responses")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .map(|(status, response)| (status.to_ascii_uppercase(), json_schema(validator.resolve(response))))
+                    .collect();
+
+                operations.push(Operation {
+                    method: Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                        .map_err(|err| OpenApiValidatorError::InvalidDocument(err.to_string()))?,
+                    pattern: pattern.clone(),
+                    parameters,
+                    body,
+                    responses,
+                });
+            }
+        }
+
+        // Static segments take precedence over parameters (`/users/me` before `/users/{id}`)
+        operations.sort_by_key(|operation| operation.pattern.matches('{').count());
+        validator.operations = Arc::from(operations);
+
+        Ok(validator)
+    }
+
+    /// Create a validator from a `utoipa` OpenAPI document
+    pub fn from_openapi(openapi: &utoipa::openapi::OpenApi) -> Result<Self, OpenApiValidatorError> {
+        let document =
+            serde_json::to_value(openapi).map_err(|err| OpenApiValidatorError::InvalidDocument(err.to_string()))?;
+        Self::new(&document)
+    }
+
+    /// Return true if an operation is documented
+    pub fn is_documented(&self, method: &Method, path: &str) -> bool {
+        self.operation(method, path).is_some()
+    }
+
+    /// Validate the query string and the JSON body of a request (undocumented operations are valid)
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::server::axum::layers::openapi_validation::OpenApiValidator;
+    /// use api_tools::server::axum::response::FieldError;
+    /// use axum::http::Method;
+    /// use serde_json::json;
+    ///
+    /// let validator = OpenApiValidator::new(&json!({
+    ///     "paths": {
+    ///         "/users": {
+    ///             "post": {
+    ///                 "requestBody": {
+    ///                     "required": true,
+    ///                     "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User" } } }
+    ///                 },
+    ///                 "responses": { "201": { "description": "Created" } }
+    ///             }
+    ///         }
+    ///     },
+    ///     "components": {
+    ///         "schemas": {
+    ///             "User": {
+    ///                 "type": "object",
+    ///                 "required": ["name"],
+    ///                 "properties": { "name": { "type": "string", "minLength": 1 } }
+    ///             }
+    ///         }
+    ///     }
+    /// }))
+    /// .unwrap();
+    ///
+    /// let errors = validator
+    ///     .validate_request(&Method::POST, "/users", None, Some(&json!({ "name": 1 })))
+    ///     .unwrap_err();
+    /// assert_eq!(errors.0, vec![FieldError::new("body.name", "must be of type string")]);
+    /// ```
+    pub fn validate_request(
+        &self,
+        method: &Method,
+        path: &str,
+        query: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<(), ValidationErrors> {
+        let Some(operation) = self.operation(method, path) else {
+            return Ok(());
+        };
+        let mut errors = ValidationErrors::new();
+
+        // Query parameters
+        let query = serde_urlencoded::from_str::<Vec<(String, String)>>(query.unwrap_or_default()).unwrap_or_default();
+        for parameter in &operation.parameters {
+            let field = format!("query.{}", parameter.name);
+            let values = query
+                .iter()
+                .filter(|(name, _)| *name == parameter.name || *name == format!("{}[]", parameter.name))
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>();
+
+            if values.is_empty() {
+                if parameter.required {
+                    errors.add(&field, "is required");
+                }
+                continue;
+            }
+
+            match self.coerce(&parameter.schema, &values) {
+                Some(value) => self.check(&parameter.schema, &value, &field, &mut errors, 0),
+                None => errors.add(&field, "has an invalid format"),
+            }
+        }
+
+        // Body
+        match (&operation.body, body) {
+            (Some((_, Some(schema))), Some(body)) => self.check(schema, body, "body", &mut errors, 0),
+            (Some((true, _)), None) => errors.add("body", "is required"),
+            _ => {}
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Validate the status and the JSON body of a response (undocumented operations are valid)
+    pub fn validate_response(
+        &self,
+        method: &Method,
+        path: &str,
+        status: StatusCode,
+        body: Option<&Value>,
+    ) -> Result<(), ValidationErrors> {
+        let Some(operation) = self.operation(method, path) else {
+            return Ok(());
+        };
+        let mut errors = ValidationErrors::new();
+
+        let range = format!("{}XX", status.as_u16() / 100);
+        let response = [status.as_str(), range.as_str(), "DEFAULT"]
+            .into_iter()
+            .find_map(|key| operation.responses.iter().find(|(status, _)| status == key));
+        match (response, body) {
+            (None, _) => errors.add("status", &format!("{} is not documented", status.as_u16())),
+            (Some((_, Some(schema))), Some(body)) => self.check(schema, body, "response", &mut errors, 0),
+            _ => {}
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Documented operation of a request
+    fn operation(&self, method: &Method, path: &str) -> Option<&Operation> {
+        self.operations
+            .iter()
+            .find(|operation| operation.method == method && route_matches(&operation.pattern, path))
+    }
+
+    /// Follow the local references (`#/components/schemas/User`)
+    fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_DEPTH {
+            let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            match reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.document.pointer(pointer))
+            {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+
+        value
+    }
+
+    /// Convert query parameter values to the type of their schema
+    fn coerce(&self, schema: &Value, values: &[&str]) -> Option<Value> {
+        let schema = self.resolve(schema);
+        if schema_types(schema).contains(&"array") {
+            let items = schema.get("items").unwrap_or(&Value::Null);
+            return values
+                .iter()
+                .map(|value| self.coerce(items, &[value]))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array);
+        }
+
+        let value = values.last()?;
+        let types = schema_types(schema);
+        if types.contains(&"integer") {
+            value.parse::<i64>().ok().map(Value::from)
+        } else if types.contains(&"number") {
+            value.parse::<f64>().ok().map(Value::from)
+        } else if types.contains(&"boolean") {
+            value.parse::<bool>().ok().map(Value::from)
+        } else {
+            Some(Value::from(*value))
+        }
+    }
+
+    /// Validate a value against a schema
+    fn check(&self, schema: &Value, value: &Value, path: &str, errors: &mut ValidationErrors, depth: usize) {
+        let schema = self.resolve(schema);
+        if depth > MAX_DEPTH || !schema.is_object() {
+            return;
+        }
+        if value.is_null() && schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+            return;
+        }
+
+        // Compositions
+        for sub_schema in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(sub_schema, value, path, errors, depth + 1);
+        }
+        for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+            if let Some(sub_schemas) = schema.get(keyword).and_then(Value::as_array) {
+                let matches = sub_schemas
+                    .iter()
+                    .filter(|sub_schema| {
+                        let mut sub_errors = ValidationErrors::new();
+                        self.check(sub_schema, value, path, &mut sub_errors, depth + 1);
+                        sub_errors.is_empty()
+                    })
+                    .count();
+                if matches == 0 {
+                    errors.add(path, "does not match any of the allowed schemas");
+                } else if exactly_one && matches > 1 {
+                    errors.add(path, "matches more than one schema");
+                }
+            }
+        }
+
+        if let Some(values) = schema.get("enum").and_then(Value::as_array)
+            && !values.contains(value)
+        {
+            let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+            errors.add(path, &format!("must be one of {}", values.join(", ")));
+            return;
+        }
+
+        let types = schema_types(schema);
+        if !types.is_empty() && !types.iter().any(|kind| is_type(kind, value)) {
+            errors.add(path, &format!("must be of type {}", types.join(" or ")));
+            return;
+        }
+
+        let number = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+        match value {
+            Value::String(value) => {
+                let length = value.chars().count() as f64;
+                if number("minLength").is_some_and(|min| length < min) {
+                    errors.add(
+                        path,
+                        &format!(
+                            "must be at least {} characters long",
+                            number("minLength").unwrap_or_default()
+                        ),
+                    );
+                }
+                if number("maxLength").is_some_and(|max| length > max) {
+                    errors.add(
+                        path,
+                        &format!(
+                            "must be at most {} characters long",
+                            number("maxLength").unwrap_or_default()
+                        ),
+                    );
+                }
+                if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+                    && regex::Regex::new(pattern).is_ok_and(|regex| !regex.is_match(value))
+                {
+                    errors.add(path, &format!("must match {pattern}"));
+                }
+            }
+            Value::Number(value) => {
+                let value = value.as_f64().unwrap_or_default();
+                if let Some(min) = number("minimum").filter(|min| value < *min) {
+                    errors.add(path, &format!("must be at least {min}"));
+                }
+                if let Some(max) = number("maximum").filter(|max| value > *max) {
+                    errors.add(path, &format!("must be at most {max}"));
+                }
+            }
+            Value::Array(items) => {
+                let length = items.len() as f64;
+                if let Some(min) = number("minItems").filter(|min| length < *min) {
+                    errors.add(path, &format!("must contain at least {min} items"));
+                }
+                if let Some(max) = number("maxItems").filter(|max| length > *max) {
+                    errors.add(path, &format!("must contain at most {max} items"));
+                }
+                if let Some(items_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(items_schema, item, &format!("{path}[{index}]"), errors, depth + 1);
+                    }
+                }
+            }
+            Value::Object(object) => {
+                for field in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                    if let Some(field) = field.as_str()
+                        && !object.contains_key(field)
+                    {
+                        errors.add(&format!("{path}.{field}"), "is required");
+                    }
+                }
+
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (field, value) in object {
+                    let field_path = format!("{path}.{field}");
+                    match (
+                        properties.and_then(|properties| properties.get(field)),
+                        schema.get("additionalProperties"),
+                    ) {
+                        (Some(property), _) => self.check(property, value, &field_path, errors, depth + 1),
+                        (None, Some(Value::Bool(false))) => errors.add(&field_path, "is not allowed"),
+                        (None, Some(additional)) => self.check(additional, value, &field_path, errors, depth + 1),
+                        (None, None) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// JSON schema of a request body or a response, if any
+fn json_schema(item: &Value) -> Option<Value> {
+    item.get("content")?
+        .as_object()?
+        .iter()
+        .find(|(content_type, _)| {
+            content_type.starts_with(mime::APPLICATION_JSON.as_ref()) || content_type.ends_with("+json")
+        })
+        .and_then(|(_, media_type)| media_type.get("schema").cloned())
+}
+
+/// Types of a schema (`type` is a string in OpenAPI 3.0 and can be an array in OpenAPI 3.1)
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check the type of a value
+fn is_type(kind: &str, value: &Value) -> bool {
+    match kind {
+        "integer" => {
+            value.as_i64().is_some()
+                || value.as_u64().is_some()
+                || value.as_f64().is_some_and(|value| value.fract() == 0.0)
+        }
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Return true if the content type is JSON
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(mime::APPLICATION_JSON.as_ref()))
+}
+
+/// Count an invalid request or response
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn record(kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("openapi_violations_total", "kind" => kind).increment(1);
+}
+
+/// Layer validating the requests and the responses against an OpenAPI document
+#[derive(Clone, Debug)]
+pub struct OpenApiValidationLayer {
+    pub validator: OpenApiValidator,
+    pub mode: ValidationMode,
+    pub validate_responses: bool,
+    pub body_max_size: usize,
+}
+
+impl OpenApiValidationLayer {
+    /// Create a new `OpenApiValidationLayer` rejecting the invalid requests and logging the invalid
+    /// responses
+    pub fn new(validator: OpenApiValidator) -> Self {
+        Self {
+            validator,
+            mode: ValidationMode::default(),
+            validate_responses: true,
+            body_max_size: OPENAPI_VALIDATION_DEFAULT_BODY_MAX_SIZE,
+        }
+    }
+
+    /// Set the behavior for the invalid requests
+    pub fn with_mode(mut self, mode: ValidationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Enable or disable the validation of the responses
+    pub fn with_response_validation(mut self, validate_responses: bool) -> Self {
+        self.validate_responses = validate_responses;
+        self
+    }
+
+    /// Set the maximum size of the validated bodies (larger response bodies are not validated)
+    pub fn with_body_max_size(mut self, body_max_size: usize) -> Self {
+        self.body_max_size = body_max_size;
+        self
+    }
+}
+
+impl<S> Layer<S> for OpenApiValidationLayer {
+    type Service = OpenApiValidationMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OpenApiValidationMiddleware {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OpenApiValidationMiddleware<S> {
+    inner: S,
+    layer: OpenApiValidationLayer,
+}
+
+impl<S> Service<Request<Body>> for OpenApiValidationMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        if !self.layer.validator.is_documented(&method, &path) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let mut inner = self.inner.clone();
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            // Request
+            let (parts, body) = request.into_parts();
+            let body = match axum::body::to_bytes(body, layer.body_max_size).await {
+                Ok(body) => body,
+                Err(_) => return Ok(crate::server::axum::response::ApiError::PayloadTooLarge.into_response()),
+            };
+            let mut errors = ValidationErrors::new();
+            let json = match serde_json::from_slice::<Value>(&body) {
+                _ if body.is_empty() => None,
+                Ok(json) => Some(json),
+                Err(err) => {
+                    errors.add("body", &format!("is not valid JSON: {err}"));
+                    None
+                }
+            };
+            if errors.is_empty()
+                && let Err(request_errors) =
+                    layer
+                        .validator
+                        .validate_request(&method, &path, parts.uri.query(), json.as_ref())
+            {
+                errors = request_errors;
+            }
+
+            if !errors.is_empty() {
+                record("request");
+                warn!(%method, %path, errors = ?errors.0, "Request does not match the OpenAPI document");
+                if layer.mode == ValidationMode::Enforce {
+                    return Ok(bad_request(errors.0));
+                }
+            }
+
+            let response = inner.call(Request::from_parts(parts, Body::from(body))).await?;
+            if !layer.validate_responses {
+                return Ok(response);
+            }
+
+            // Response (streamed or large bodies are not buffered)
+            let size = response.body().size_hint().exact();
+            let (parts, body) = response.into_parts();
+            let (json, body) = if is_json(&parts.headers) && size.is_some_and(|size| size <= layer.body_max_size as u64)
+            {
+                match axum::body::to_bytes(body, layer.body_max_size).await {
+                    Ok(bytes) => (serde_json::from_slice::<Value>(&bytes).ok(), Body::from(bytes)),
+                    Err(_) => (None, Body::empty()),
+                }
+            } else {
+                (None, body)
+            };
+
+            if let Err(errors) = layer
+                .validator
+                .validate_response(&method, &path, parts.status, json.as_ref())
+            {
+                record("response");
+                warn!(%method, %path, status = %parts.status, errors = ?errors.0, "Response does not match the OpenAPI document");
+            }
+
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// `400 Bad Request` response with the field errors
+fn bad_request(errors: Vec<FieldError>) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            errors,
+            current_trace_id(),
+        )),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::get;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn validator() -> OpenApiValidator {
+        OpenApiValidator::new(&json!({
+            "openapi": "3.1.0",
+            "paths": {
+                "/users": {
+                    "get": {
+                        "parameters": [
+                            { "name": "page", "in": "query", "required": true, "schema": { "type": "integer", "minimum": 1 } },
+                            { "name": "ids", "in": "query", "schema": { "type": "array", "items": { "type": "integer" } } },
+                            { "name": "status", "in": "query", "schema": { "$ref": "#/components/schemas/Status" } }
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "Users",
+                                "content": {
+                                    "application/json": {
+                                        "schema": { "type": "array", "items": { "$ref": "#/components/schemas/User" } }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "required": true,
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User" } } }
+                        },
+                        "responses": { "201": { "description": "Created" }, "4XX": { "description": "Error" } }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Status": { "type": "string", "enum": ["active", "disabled"] },
+                    "User": {
+                        "type": "object",
+                        "required": ["name", "email"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "name": { "type": "string", "minLength": 2, "maxLength": 20 },
+                            "email": { "type": "string", "pattern": "^[^@]+@[^@]+$" },
+                            "age": { "type": ["integer", "null"], "minimum": 0 },
+                            "roles": { "type": "array", "maxItems": 2, "items": { "$ref": "#/components/schemas/Role" } }
+                        }
+                    },
+                    "Role": { "oneOf": [{ "type": "string" }, { "type": "object", "required": ["name"] }] }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn fields(errors: ValidationErrors) -> Vec<(String, String)> {
+        errors.0.into_iter().map(|error| (error.field, error.message)).collect()
+    }
+
+    #[test]
+    fn test_validate_request_query() {
+        let validator = validator();
+        assert!(
+            validator
+                .validate_request(&Method::GET, "/users", Some("page=1&ids=1&ids=2&status=active"), None)
+                .is_ok()
+        );
+        assert!(validator.validate_request(&Method::GET, "/unknown", None, None).is_ok());
+
+        let errors = validator
+            .validate_request(&Method::GET, "/users", Some("ids=1&ids=a&status=deleted"), None)
+            .unwrap_err();
+        assert_eq!(
+            fields(errors),
+            vec![
+                ("query.page".to_string(), "is required".to_string()),
+                ("query.ids".to_string(), "has an invalid format".to_string()),
+                (
+                    "query.status".to_string(),
+                    r#"must be one of "active", "disabled""#.to_string()
+                ),
+            ]
+        );
+
+        let errors = validator
+            .validate_request(&Method::GET, "/users", Some("page=0"), None)
+            .unwrap_err();
+        assert_eq!(
+            fields(errors),
+            vec![("query.page".to_string(), "must be at least 1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_validate_request_body() {
+        let validator = validator();
+        let user = json!({ "name": "Alice", "email": "alice@example.com", "age": null, "roles": ["admin", { "name": "user" }] });
+        assert!(
+            validator
+                .validate_request(&Method::POST, "/users", None, Some(&user))
+                .is_ok()
+        );
+
+        let errors = validator
+            .validate_request(&Method::POST, "/users", None, None)
+            .unwrap_err();
+        assert_eq!(fields(errors), vec![("body".to_string(), "is required".to_string())]);
+
+        let user = json!({ "name": "A", "email": "alice", "age": -1, "roles": [1, {}, "user"], "admin": true });
+        let errors = validator
+            .validate_request(&Method::POST, "/users", None, Some(&user))
+            .unwrap_err();
+        assert_eq!(
+            fields(errors),
+            vec![
+                ("body.admin".to_string(), "is not allowed".to_string()),
+                ("body.age".to_string(), "must be at least 0".to_string()),
+                ("body.email".to_string(), "must match ^[^@]+@[^@]+$".to_string()),
+                (
+                    "body.name".to_string(),
+                    "must be at least 2 characters long".to_string()
+                ),
+                ("body.roles".to_string(), "must contain at most 2 items".to_string()),
+                (
+                    "body.roles[0]".to_string(),
+                    "does not match any of the allowed schemas".to_string()
+                ),
+                (
+                    "body.roles[1]".to_string(),
+                    "does not match any of the allowed schemas".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_response() {
+        let validator = validator();
+        let users = json!([{ "name": "Alice", "email": "alice@example.com" }]);
+        assert!(
+            validator
+                .validate_response(&Method::GET, "/users", StatusCode::OK, Some(&users))
+                .is_ok()
+        );
+        assert!(
+            validator
+                .validate_response(&Method::POST, "/users", StatusCode::CONFLICT, None)
+                .is_ok()
+        );
+
+        let errors = validator
+            .validate_response(
+                &Method::GET,
+                "/users",
+                StatusCode::OK,
+                Some(&json!([{ "name": "Alice" }])),
+            )
+            .unwrap_err();
+        assert_eq!(
+            fields(errors),
+            vec![("response[0].email".to_string(), "is required".to_string())]
+        );
+
+        let errors = validator
+            .validate_response(&Method::GET, "/users", StatusCode::NOT_FOUND, None)
+            .unwrap_err();
+        assert_eq!(
+            fields(errors),
+            vec![("status".to_string(), "404 is not documented".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_openapi_validation_layer() {
+        let app = Router::new()
+            .route(
+                "/users",
+                get(|| async { Json(json!([{ "name": "Alice" }])) })
+                    .post(|Json(user): Json<Value>| async move { (StatusCode::CREATED, Json(user)) }),
+            )
+            .layer(OpenApiValidationLayer::new(validator()));
+        let call = |method: Method, uri: &'static str, body: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        // The invalid response is only logged
+        let response = call(Method::GET, "/users?page=1", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], br#"[{"name":"Alice"}]"#);
+
+        let response = call(Method::GET, "/users", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(
+            &body[..],
+            br#"{"code":400,"message":[{"field":"query.page","message":"is required"}]}"#
+        );
+
+        let response = call(Method::POST, "/users", r#"{"name":"Alice","email":"a@b"}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = call(Method::POST, "/users", "{").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Report mode
+        let app = Router::new()
+            .route("/users", get(|| async { "users" }))
+            .layer(OpenApiValidationLayer::new(validator()).with_mode(ValidationMode::Report));
+        let response = app
+            .oneshot(Request::get("/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
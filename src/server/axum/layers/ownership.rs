@@ -0,0 +1,257 @@
+//! Endpoint ownership layer
+//!
+//! [`OwnershipLayer`] declares the team owning each route, so that alerts and 5xx reports are
+//! routed to the right on-call team. The [`Owner`] of the matched route is inserted in the request
+//! extensions (for the handlers and the inner layers) and in the response extensions, where it is
+//! read by:
+//! - the `LoggerLayer`, adding the `owner` field to the request logs (5xx responses are logged as
+//!   errors)
+//! - the `MetricsLayer` (`metrics` feature), adding an `owner` label to the HTTP metrics
+//!
+//! It must be added with `Router::layer` (so that the matched route is known) and wrapped by the
+//! `LoggerLayer` and the `MetricsLayer`.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::logger::LoggerLayer;
+//! use api_tools::server::axum::layers::ownership::{OwnershipLayer, Owner, RouteOwner};
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/orders/{id}", get(|| async { "order" }))
+//!     .route("/users/{id}", get(|| async { "user" }))
+//!     .layer(
+//!         OwnershipLayer::new()
+//!             .with_route(RouteOwner::new(
+//!                 "/orders/{*path}",
+//!                 Owner::new("checkout").with_channel("#checkout-oncall"),
+//!             ))
+//!             .with_default(Owner::new("platform")),
+//!     )
+//!     .layer(LoggerLayer);
+//! ```
+
+use crate::server::axum::layers::route_matches;
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Team owning an endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Owner {
+    /// Team name (e.g. `checkout`)
+    pub team: String,
+
+    /// On-call channel of the team (e.g. `#checkout-oncall`)
+    pub channel: Option<String>,
+}
+
+impl Owner {
+    /// Create a new `Owner`
+    pub fn new(team: &str) -> Self {
+        Self {
+            team: team.to_string(),
+            channel: None,
+        }
+    }
+
+    /// Set the on-call channel of the team
+    pub fn with_channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.to_string());
+        self
+    }
+}
+
+impl Display for Owner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.team)
+    }
+}
+
+/// Owner of a route pattern
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteOwner {
+    pub pattern: String,
+    pub owner: Owner,
+}
+
+impl RouteOwner {
+    /// Create a new `RouteOwner`
+    pub fn new(pattern: &str, owner: Owner) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            owner,
+        }
+    }
+
+    /// Check if a request path (or a matched route) matches the route pattern
+    pub fn matches(&self, path: &str) -> bool {
+        route_matches(&self.pattern, path)
+    }
+}
+
+/// Endpoint ownership layer
+///
+/// The first matching route is used, or else the default owner.
+#[derive(Clone, Default)]
+pub struct OwnershipLayer {
+    pub routes: Vec<RouteOwner>,
+    pub default: Option<Owner>,
+}
+
+impl OwnershipLayer {
+    /// Create a new `OwnershipLayer` without owners
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the owner of a route pattern
+    pub fn with_route(mut self, route: RouteOwner) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// Set the owner of the routes without a specific owner
+    pub fn with_default(mut self, owner: Owner) -> Self {
+        self.default = Some(owner);
+        self
+    }
+
+    /// Owner of a request path (or a matched route)
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::ownership::{OwnershipLayer, Owner, RouteOwner};
+    ///
+    /// let layer = OwnershipLayer::new().with_route(RouteOwner::new("/orders/{*path}", Owner::new("checkout")));
+    /// assert_eq!(layer.owner_of("/orders/{id}"), Some(&Owner::new("checkout")));
+    /// assert_eq!(layer.owner_of("/users/{id}"), None);
+    /// ```
+    pub fn owner_of(&self, path: &str) -> Option<&Owner> {
+        self.routes
+            .iter()
+            .find(|route| route.matches(path))
+            .map(|route| &route.owner)
+            .or(self.default.as_ref())
+    }
+}
+
+impl<S> Layer<S> for OwnershipLayer {
+    type Service = OwnershipMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OwnershipMiddleware {
+            inner,
+            layer: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OwnershipMiddleware<S> {
+    inner: S,
+    layer: Arc<OwnershipLayer>,
+}
+
+impl<S> Service<Request<Body>> for OwnershipMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let path = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str(),
+            None => request.uri().path(),
+        };
+        let Some(owner) = self.layer.owner_of(path).cloned() else {
+            return Box::pin(self.inner.call(request));
+        };
+        request.extensions_mut().insert(owner.clone());
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            response.extensions_mut().insert(owner);
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::Extension;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_ownership_layer() {
+        let app = Router::new()
+            .route(
+                "/orders/{id}",
+                get(|Extension(owner): Extension<Owner>| async move { owner.team }),
+            )
+            .route("/users/{id}", get(|| async { "user" }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(
+                OwnershipLayer::new()
+                    .with_route(RouteOwner::new(
+                        "/orders/{*path}",
+                        Owner::new("checkout").with_channel("#checkout-oncall"),
+                    ))
+                    .with_route(RouteOwner::new("/users/{id}", Owner::new("identity"))),
+            );
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/orders/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let owner = response.extensions().get::<Owner>().cloned().unwrap();
+        assert_eq!(owner.channel.as_deref(), Some("#checkout-oncall"));
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(body, "checkout");
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/users/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.extensions().get::<Owner>(), Some(&Owner::new("identity")));
+
+        let response = app
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.extensions().get::<Owner>(), None);
+    }
+
+    #[test]
+    fn test_owner_of_default() {
+        let layer = OwnershipLayer::new()
+            .with_route(RouteOwner::new("/orders/{*path}", Owner::new("checkout")))
+            .with_default(Owner::new("platform"));
+
+        assert_eq!(layer.owner_of("/orders/{id}/items"), Some(&Owner::new("checkout")));
+        assert_eq!(layer.owner_of("/users"), Some(&Owner::new("platform")));
+    }
+}
@@ -0,0 +1,454 @@
+//! Quotas per authenticated principal
+//!
+//! [`PerPrincipalQuotaLayer`] counts the requests of each [`Principal`] (API key user, JWT
+//! subject) of the `RequestStore` over a calendar period (UTC day or month) and rejects the
+//! requests over the quota with a `429 Too Many Requests` response. The counters are kept in a
+//! pluggable [`QuotaStore`] shared by the instances of the service (e.g. Redis), or in a
+//! [`MemoryQuotaStore`].
+//!
+//! Every response carries the state of the quota:
+//!
+//! - `X-RateLimit-Limit`: quota of the period
+//! - `X-RateLimit-Remaining`: remaining requests in the period
+//! - `X-RateLimit-Reset`: Unix timestamp of the end of the period
+//!
+//! The quota of a principal can depend on its plan ([`PerPrincipalQuotaLayer::with_limits`]).
+//! The requests without principal are not counted: put the layer inside the authentication layer.
+//! A store failure returns a `503 Service Unavailable`.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::jwt_auth::{JwtAuthLayer, JwtClaims};
+//! use api_tools::server::axum::layers::quota::{MemoryQuotaStore, PerPrincipalQuotaLayer, QuotaPeriod};
+//! use api_tools::server::axum::security::jwt::Jwt;
+//! use axum::{Router, routing::get};
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Clone, Deserialize)]
+//! struct Claims {
+//!     sub: String,
+//! }
+//!
+//! impl JwtClaims for Claims {
+//!     fn subject(&self) -> Option<&str> {
+//!         Some(&self.sub)
+//!     }
+//! }
+//!
+//! let jwt = Jwt::init("HS256", 15, 24, Some("secret"), Some("secret"), None).unwrap();
+//! let app: Router = Router::new()
+//!     .route("/search", get(|| async { "results" }))
+//!     .layer(
+//!         PerPrincipalQuotaLayer::new(MemoryQuotaStore::new(), QuotaPeriod::Monthly, 10_000)
+//!             .with_limits(|principal| (principal == "premium-customer").then_some(1_000_000)),
+//!     )
+//!     .layer(JwtAuthLayer::<Claims>::new(jwt));
+//! ```
+
+use super::body_from_parts;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode, header};
+use axum::response::Response;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tower::{Layer, Service};
+
+/// Quota header
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+
+/// Remaining requests header
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+/// End of the period header (Unix timestamp)
+pub const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Interval between two purges of the expired counters of the [`MemoryQuotaStore`]
+const MEMORY_STORE_PURGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Quota store error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QuotaStoreError {
+    #[error("Quota store error: {0}")]
+    Backend(String),
+}
+
+/// Store of the request counters
+pub trait QuotaStore: Send + Sync + 'static {
+    /// Increment the counter of a key, kept until `expires_at` (Unix timestamp), and return it
+    fn increment<'a>(&'a self, key: &'a str, expires_at: i64) -> BoxFuture<'a, Result<u64, QuotaStoreError>>;
+}
+
+#[derive(Debug)]
+struct MemoryCounters {
+    counters: HashMap<String, (u64, i64)>,
+    last_purge: Instant,
+}
+
+/// In-memory quota store, not shared between the instances of the service
+#[derive(Debug)]
+pub struct MemoryQuotaStore {
+    counters: Mutex<MemoryCounters>,
+}
+
+impl Default for MemoryQuotaStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryQuotaStore {
+    /// Create a new `MemoryQuotaStore`
+    pub fn new() -> Self {
+        Self {
+            counters: Mutex::new(MemoryCounters {
+                counters: HashMap::new(),
+                last_purge: Instant::now(),
+            }),
+        }
+    }
+
+    /// Number of counters in the store (including the expired ones not purged yet)
+    pub fn len(&self) -> usize {
+        self.counters
+            .lock()
+            .map(|counters| counters.counters.len())
+            .unwrap_or_default()
+    }
+
+    /// Return true if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn increment_sync(&self, key: &str, expires_at: i64) -> Result<u64, QuotaStoreError> {
+        let mut counters = self
+            .counters
+            .lock()
+            .map_err(|err| QuotaStoreError::Backend(err.to_string()))?;
+        let now = Instant::now();
+
+        if now.duration_since(counters.last_purge) >= MEMORY_STORE_PURGE_INTERVAL {
+            let timestamp = Utc::now().timestamp();
+            counters.counters.retain(|_, (_, expiration)| *expiration > timestamp);
+            counters.last_purge = now;
+        }
+
+        let counter = counters.counters.entry(key.to_string()).or_insert((0, expires_at));
+        counter.0 += 1;
+
+        Ok(counter.0)
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn increment<'a>(&'a self, key: &'a str, expires_at: i64) -> BoxFuture<'a, Result<u64, QuotaStoreError>> {
+        Box::pin(async move { self.increment_sync(key, expires_at) })
+    }
+}
+
+/// Calendar period of a quota (UTC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    /// ID of the period containing `now` (`2026-10-17` or `2026-10`) and Unix timestamp of its end
+    fn window(&self, now: DateTime<Utc>) -> (String, i64) {
+        let today = now.date_naive();
+        let (id, end) = match self {
+            Self::Daily => (today.format("%Y-%m-%d").to_string(), today.succ_opt()),
+            Self::Monthly => {
+                let (year, month) = match today.month() {
+                    12 => (today.year() + 1, 1),
+                    month => (today.year(), month + 1),
+                };
+                (
+                    today.format("%Y-%m").to_string(),
+                    NaiveDate::from_ymd_opt(year, month, 1),
+                )
+            }
+        };
+        let end = end
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc().timestamp())
+            .unwrap_or(i64::MAX);
+
+        (id, end)
+    }
+}
+
+/// Quota of a principal, `None` for the default quota
+pub type QuotaLimits = Arc<dyn Fn(&str) -> Option<u64> + Send + Sync>;
+
+/// State of the quota of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct QuotaState {
+    limit: u64,
+    count: u64,
+    reset: i64,
+}
+
+impl QuotaState {
+    /// `X-RateLimit-*` headers
+    fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        vec![
+            (HeaderName::from_static(RATE_LIMIT_LIMIT_HEADER), self.limit.into()),
+            (
+                HeaderName::from_static(RATE_LIMIT_REMAINING_HEADER),
+                self.limit.saturating_sub(self.count).into(),
+            ),
+            (HeaderName::from_static(RATE_LIMIT_RESET_HEADER), self.reset.into()),
+        ]
+    }
+}
+
+/// Rejection of a request by the [`PerPrincipalQuotaLayer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QuotaRejection {
+    Exceeded(QuotaState),
+    Store,
+}
+
+#[derive(Clone)]
+pub struct PerPrincipalQuotaLayer {
+    pub store: Arc<dyn QuotaStore>,
+    pub period: QuotaPeriod,
+    pub limit: u64,
+    pub limits: Option<QuotaLimits>,
+    pub key_prefix: String,
+}
+
+impl PerPrincipalQuotaLayer {
+    /// Create a new `PerPrincipalQuotaLayer` with a default quota per period
+    pub fn new(store: impl QuotaStore, period: QuotaPeriod, limit: u64) -> Self {
+        Self::from_arc(Arc::new(store), period, limit)
+    }
+
+    /// Create a new `PerPrincipalQuotaLayer` from a shared store
+    pub fn from_arc(store: Arc<dyn QuotaStore>, period: QuotaPeriod, limit: u64) -> Self {
+        Self {
+            store,
+            period,
+            limit,
+            limits: None,
+            key_prefix: "quota".to_string(),
+        }
+    }
+
+    /// Quota of each principal (e.g. from its plan), the default quota is used for `None`
+    pub fn with_limits(mut self, limits: impl Fn(&str) -> Option<u64> + Send + Sync + 'static) -> Self {
+        self.limits = Some(Arc::new(limits));
+        self
+    }
+
+    /// Prefix of the counter keys, to use several quotas with the same store
+    pub fn with_key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+
+    /// Count the request of a principal
+    async fn check(&self, principal: &str) -> Result<QuotaState, QuotaRejection> {
+        let limit = self
+            .limits
+            .as_ref()
+            .and_then(|limits| limits(principal))
+            .unwrap_or(self.limit);
+        let (period, reset) = self.period.window(Utc::now());
+        let key = format!("{}:{principal}:{period}", self.key_prefix);
+
+        match self.store.increment(&key, reset).await {
+            Ok(count) if count > limit => Err(QuotaRejection::Exceeded(QuotaState { limit, count, reset })),
+            Ok(count) => Ok(QuotaState { limit, count, reset }),
+            Err(err) => {
+                tracing::error!(error = %err, "quota store failure");
+                Err(QuotaRejection::Store)
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for PerPrincipalQuotaLayer {
+    type Service = PerPrincipalQuotaMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PerPrincipalQuotaMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PerPrincipalQuotaMiddleware<S> {
+    inner: S,
+    config: PerPrincipalQuotaLayer,
+}
+
+impl<S> Service<Request<Body>> for PerPrincipalQuotaMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let principal = RequestStore::from_extensions(request.extensions())
+            .and_then(|store| store.get::<Principal>())
+            .map(|principal| principal.0.clone());
+        let Some(principal) = principal else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            match config.check(&principal).await {
+                Ok(state) => {
+                    let mut response = inner.call(request).await?;
+                    insert_headers(response.headers_mut(), state.headers());
+                    Ok(response)
+                }
+                Err(QuotaRejection::Exceeded(state)) => {
+                    let retry_after = (state.reset - Utc::now().timestamp()).max(0);
+                    let mut headers = state.headers();
+                    headers.push((header::RETRY_AFTER, retry_after.into()));
+
+                    Ok(error_response(StatusCode::TOO_MANY_REQUESTS, "Quota exceeded", headers))
+                }
+                Err(QuotaRejection::Store) => Ok(error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Service unavailable",
+                    Vec::new(),
+                )),
+            }
+        })
+    }
+}
+
+/// Insert headers in a response
+fn insert_headers(headers: &mut HeaderMap, values: Vec<(HeaderName, HeaderValue)>) {
+    for (name, value) in values {
+        headers.insert(name, value);
+    }
+}
+
+/// JSON error response
+fn error_response(status_code: StatusCode, message: &str, headers: Vec<(HeaderName, HeaderValue)>) -> Response {
+    let (mut parts, _body) = Response::<Body>::default().into_parts();
+    let msg = body_from_parts(&mut parts, status_code, message, Some(headers));
+
+    Response::from_parts(parts, Body::from(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    struct FailingStore;
+
+    impl QuotaStore for FailingStore {
+        fn increment<'a>(&'a self, _key: &'a str, _expires_at: i64) -> BoxFuture<'a, Result<u64, QuotaStoreError>> {
+            Box::pin(async { Err(QuotaStoreError::Backend("connection refused".to_string())) })
+        }
+    }
+
+    fn request(principal: Option<&str>) -> Request<Body> {
+        let mut request = Request::new(Body::empty());
+        if let Some(principal) = principal {
+            RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal(principal.to_string()));
+        }
+        request
+    }
+
+    async fn call(layer: &PerPrincipalQuotaLayer, principal: Option<&str>) -> Response {
+        layer
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("ok")))
+            }))
+            .oneshot(request(principal))
+            .await
+            .unwrap()
+    }
+
+    fn date(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_quota_period_window() {
+        assert_eq!(
+            QuotaPeriod::Daily.window(date("2026-10-17T13:45:00Z")),
+            ("2026-10-17".to_string(), date("2026-10-18T00:00:00Z").timestamp())
+        );
+        assert_eq!(
+            QuotaPeriod::Monthly.window(date("2026-10-17T13:45:00Z")),
+            ("2026-10".to_string(), date("2026-11-01T00:00:00Z").timestamp())
+        );
+        assert_eq!(
+            QuotaPeriod::Monthly.window(date("2026-12-31T23:59:59Z")),
+            ("2026-12".to_string(), date("2027-01-01T00:00:00Z").timestamp())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_per_principal_quota_layer() {
+        let layer = PerPrincipalQuotaLayer::new(MemoryQuotaStore::new(), QuotaPeriod::Daily, 2)
+            .with_limits(|principal| (principal == "premium").then_some(100));
+        let (_, reset) = QuotaPeriod::Daily.window(Utc::now());
+
+        let response = call(&layer, Some("alice")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "2");
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "1");
+        assert_eq!(response.headers()[RATE_LIMIT_RESET_HEADER], reset.to_string().as_str());
+
+        let response = call(&layer, Some("alice")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "0");
+
+        let response = call(&layer, Some("alice")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "0");
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, r#"{"code":429,"message":"Quota exceeded"}"#);
+
+        // Quotas are counted per principal
+        let response = call(&layer, Some("premium")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "100");
+        assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], "99");
+
+        // Anonymous requests are not counted
+        let response = call(&layer, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(RATE_LIMIT_LIMIT_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_per_principal_quota_layer_store_failure() {
+        let layer = PerPrincipalQuotaLayer::new(FailingStore, QuotaPeriod::Monthly, 10);
+
+        let response = call(&layer, Some("alice")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -1,10 +1,41 @@
 //! Request ID middleware
+//!
+//! [`RequestIdLayer`] keeps the incoming request ID (or generates a UUID if it is absent or
+//! invalid), runs the request in a `request` tracing span with a `request_id` field, makes the ID
+//! available through [`current_request_id`] and copies it to the response headers.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::request_id::RequestIdLayer;
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(|| async { "OK" }))
+//!     .layer(RequestIdLayer::new().with_uuid_validation(true));
+//! ```
+//!
+//! [`RequestIdScopeLayer`] makes the request ID available to the code running in the
+//! request task with [`current_request_id`] (e.g. to propagate it to outbound requests).
+//! It must be added after `SetRequestIdLayer` (so that it wraps the inner services).
+//! The request ID is not available in tasks spawned with `tokio::spawn`.
 
-use axum::http::{HeaderName, Request};
+use axum::body::Body;
+use axum::http::{HeaderName, HeaderValue, Request};
+use axum::response::Response;
+use futures::future::BoxFuture;
 use std::sync::LazyLock;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
 use tower_http::request_id::{MakeRequestId, RequestId};
+use tracing::Instrument;
 use uuid::Uuid;
 
+/// Maximum length of an incoming request ID
+const REQUEST_ID_MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
 #[derive(Clone, Copy)]
 pub struct MakeRequestUuid;
 
@@ -21,10 +52,191 @@ impl MakeRequestId for MakeRequestUuid {
     }
 }
 
+/// Get the request ID of the current request
+///
+/// Returns `None` outside of a [`RequestIdScopeLayer`] or [`scope_request_id`] scope.
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run a future with a request ID available through [`current_request_id`]
+pub async fn scope_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    CURRENT_REQUEST_ID.scope(request_id, future).await
+}
+
+/// Layer accepting or generating the request ID and copying it to the response
+#[derive(Clone)]
+pub struct RequestIdLayer {
+    pub header: HeaderName,
+    pub trust_incoming: bool,
+    pub validate_uuid: bool,
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIdLayer {
+    /// Create a new `RequestIdLayer` using the `x-request-id` header and trusting incoming IDs
+    pub fn new() -> Self {
+        Self {
+            header: REQUEST_ID_HEADER.clone(),
+            trust_incoming: true,
+            validate_uuid: false,
+        }
+    }
+
+    /// Set the request ID header
+    pub fn with_header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Keep (`true`) or always replace (`false`) incoming request IDs
+    pub fn with_trust_incoming(mut self, trust_incoming: bool) -> Self {
+        self.trust_incoming = trust_incoming;
+        self
+    }
+
+    /// Replace incoming request IDs which are not UUIDs
+    pub fn with_uuid_validation(mut self, validate_uuid: bool) -> Self {
+        self.validate_uuid = validate_uuid;
+        self
+    }
+
+    /// Incoming request ID if it can be kept
+    ///
+    /// IDs longer than 128 characters or with non-visible ASCII characters are never kept.
+    fn incoming_id(&self, value: Option<&HeaderValue>) -> Option<String> {
+        let value = value?.to_str().ok()?;
+        let is_valid = self.trust_incoming
+            && !value.is_empty()
+            && value.len() <= REQUEST_ID_MAX_LENGTH
+            && value.chars().all(|c| c.is_ascii_graphic())
+            && (!self.validate_uuid || Uuid::parse_str(value).is_ok());
+
+        is_valid.then(|| value.to_string())
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdMiddleware<S> {
+    inner: S,
+    config: RequestIdLayer,
+}
+
+impl<S> Service<Request<Body>> for RequestIdMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let header = self.config.header.clone();
+        let request_id = self
+            .config
+            .incoming_id(request.headers().get(&header))
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        // The ID only contains visible ASCII characters
+        let value = HeaderValue::from_str(&request_id).unwrap_or_else(|_| HeaderValue::from_static(""));
+        request.headers_mut().insert(header.clone(), value.clone());
+        request.extensions_mut().insert(RequestId::new(value.clone()));
+
+        let span = info_span!("request", request_id = %request_id);
+        let future = self.inner.call(request);
+
+        Box::pin(
+            async move {
+                let mut response = scope_request_id(request_id, future).await?;
+                response.headers_mut().entry(header).or_insert(value);
+
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Layer making the request ID header available through [`current_request_id`]
+#[derive(Clone, Default)]
+pub struct RequestIdScopeLayer;
+
+impl RequestIdScopeLayer {
+    /// Create a new `RequestIdScopeLayer`
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for RequestIdScopeLayer {
+    type Service = RequestIdScopeMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdScopeMiddleware { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdScopeMiddleware<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for RequestIdScopeMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER.clone())
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        let future = self.inner.call(request);
+
+        match request_id {
+            Some(request_id) => Box::pin(scope_request_id(request_id, future)),
+            None => Box::pin(future),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axum::body::Body;
+    use std::convert::Infallible;
+    use tower::{ServiceBuilder, ServiceExt};
 
     #[test]
     fn test_request_id_header_name() {
@@ -58,4 +270,130 @@ mod tests {
             id_b.header_value().to_str().unwrap(),
         );
     }
+
+    #[tokio::test]
+    async fn test_request_id_scope_layer() {
+        let svc = ServiceBuilder::new()
+            .layer(RequestIdScopeLayer::new())
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from(current_request_id().unwrap_or_default())))
+            }));
+
+        let response = svc
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .header("x-request-id", "abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "abc");
+
+        let response = svc.oneshot(Request::new(Body::empty())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "");
+    }
+
+    #[tokio::test]
+    async fn test_current_request_id_outside_scope() {
+        assert_eq!(current_request_id(), None);
+        assert_eq!(
+            scope_request_id("abc".to_string(), async { current_request_id() }).await,
+            Some("abc".to_string())
+        );
+    }
+
+    async fn call_request_id_layer(layer: RequestIdLayer, request: Request<Body>) -> String {
+        let svc = ServiceBuilder::new()
+            .layer(layer)
+            .service(tower::service_fn(|request: Request<Body>| async move {
+                let header = request
+                    .headers()
+                    .get("x-request-id")
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                assert_eq!(current_request_id(), Some(header.clone()));
+                assert!(request.extensions().get::<RequestId>().is_some());
+                Ok::<_, Infallible>(Response::new(Body::from(header)))
+            }));
+
+        let response = svc.oneshot(request).await.unwrap();
+        let id = response
+            .headers()
+            .get("x-request-id")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, id, "the handler and the response must see the same ID");
+
+        id
+    }
+
+    fn request_with_id(id: &str) -> Request<Body> {
+        Request::builder()
+            .header("x-request-id", id)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_layer_generates_missing_id() {
+        let id = call_request_id_layer(RequestIdLayer::new(), Request::new(Body::empty())).await;
+        assert!(Uuid::parse_str(&id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_layer_keeps_incoming_id() {
+        let id = call_request_id_layer(RequestIdLayer::new(), request_with_id("abc-123")).await;
+        assert_eq!(id, "abc-123");
+
+        let id = call_request_id_layer(
+            RequestIdLayer::new().with_trust_incoming(false),
+            request_with_id("abc-123"),
+        )
+        .await;
+        assert_ne!(id, "abc-123");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_layer_validates_incoming_id() {
+        let layer = RequestIdLayer::new().with_uuid_validation(true);
+        let uuid = Uuid::new_v4().to_string();
+
+        let id = call_request_id_layer(layer.clone(), request_with_id(&uuid)).await;
+        assert_eq!(id, uuid);
+
+        let id = call_request_id_layer(layer, request_with_id("abc-123")).await;
+        assert!(Uuid::parse_str(&id).is_ok());
+
+        let id = call_request_id_layer(RequestIdLayer::new(), request_with_id(&"a".repeat(129))).await;
+        assert!(Uuid::parse_str(&id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_layer_custom_header() {
+        let svc = ServiceBuilder::new()
+            .layer(RequestIdLayer::new().with_header(HeaderName::from_static("x-correlation-id")))
+            .service(tower::service_fn(|_request: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }));
+
+        let response = svc
+            .oneshot(
+                Request::builder()
+                    .header("x-correlation-id", "abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get("x-correlation-id").unwrap(), "abc");
+        assert!(response.headers().get("x-request-id").is_none());
+    }
 }
@@ -1,14 +1,232 @@
 //! Security layer (standard security headers: CSP, HSTS, etc.)
+//!
+//! [`SecurityHeadersLayer`] sets the security headers of [`SecurityHeadersConfig`] on every
+//! response. By default, they replace the values set by the handlers; with `if_not_present`, a
+//! handler can set its own value (e.g. a less strict `Content-Security-Policy` for a page).
+//!
+//! The presets cover the usual cases: [`SecurityHeadersConfig::api_strict`] for JSON APIs,
+//! [`SecurityHeadersConfig::browser_app`] for the applications serving HTML pages and
+//! [`SecurityHeadersConfig::disabled`] to only set some headers. An empty value disables a header.
+//!
+//! The `Cross-Origin-Opener-Policy`, `Cross-Origin-Embedder-Policy` and
+//! `Cross-Origin-Resource-Policy` headers are opt-in: they are empty in the default configuration
+//! (`require-corp` blocks the cross-origin resources which do not allow it) and set by the presets.
+//!
+//! The `Content-Security-Policy` is built directive by directive with a [`ContentSecurityPolicy`].
+//! With `with_script_nonce` or `with_style_nonce`, a new nonce is generated for each request: the
+//! handlers get it with the `Extension<CspNonce>` extractor to allow their inline scripts. The
+//! policy is checked when it is set: a value which is not a valid header value is an error.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::security_headers::{
+//!     CSP_SELF, ContentSecurityPolicy, CspNonce, SecurityHeadersConfig, SecurityHeadersLayer,
+//! };
+//! use axum::response::Html;
+//! use axum::{Extension, Router, routing::get};
+//!
+//! async fn page(Extension(nonce): Extension<CspNonce>) -> Html<String> {
+//!     Html(format!(r#"<script nonce="{}">console.log("ok")</script>"#, nonce.0))
+//! }
+//!
+//! let csp = ContentSecurityPolicy::new()
+//!     .default_src(&[CSP_SELF])
+//!     .img_src(&[CSP_SELF, "https://cdn.example.com"])
+//!     .with_script_nonce();
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(page))
+//!     .layer(SecurityHeadersLayer::new(
+//!         SecurityHeadersConfig::browser_app()
+//!             .with_content_security_policy(csp)
+//!             .expect("invalid Content-Security-Policy"),
+//!     ));
+//! ```
 
 use axum::{
     body::Body,
     extract::Request,
-    http::{HeaderName, HeaderValue, header},
+    http::{HeaderMap, HeaderName, HeaderValue, header},
     response::Response,
 };
 use futures::future::BoxFuture;
+use std::fmt;
 use std::task::{Context, Poll};
+use thiserror::Error;
 use tower::{Layer, Service};
+use uuid::Uuid;
+
+/// `'self'` source
+pub const CSP_SELF: &str = "'self'";
+
+/// `'none'` source
+pub const CSP_NONE: &str = "'none'";
+
+/// `'unsafe-inline'` source
+pub const CSP_UNSAFE_INLINE: &str = "'unsafe-inline'";
+
+/// `'strict-dynamic'` source
+pub const CSP_STRICT_DYNAMIC: &str = "'strict-dynamic'";
+
+/// `data:` source
+pub const CSP_DATA: &str = "data:";
+
+/// `https:` source
+pub const CSP_HTTPS: &str = "https:";
+
+/// Policy sent if the policy built with the nonce of a request is not a valid header value
+const CSP_FALLBACK: &str = "default-src 'none'";
+
+/// Security headers errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SecurityHeadersError {
+    #[error("Invalid Content-Security-Policy: {0}")]
+    InvalidContentSecurityPolicy(String),
+}
+
+/// Nonce of the `Content-Security-Policy` of a request, added to the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+
+/// `Content-Security-Policy` builder
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+    script_nonce: bool,
+    style_nonce: bool,
+}
+
+impl ContentSecurityPolicy {
+    /// Create an empty policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add sources to a directive
+    pub fn directive(mut self, name: &str, sources: &[&str]) -> Self {
+        let sources = sources.iter().map(|source| source.to_string());
+        match self.directives.iter_mut().find(|(directive, _)| directive == name) {
+            Some((_, values)) => values.extend(sources),
+            None => self.directives.push((name.to_string(), sources.collect())),
+        }
+        self
+    }
+
+    /// `default-src` directive
+    pub fn default_src(self, sources: &[&str]) -> Self {
+        self.directive("default-src", sources)
+    }
+
+    /// `script-src` directive
+    pub fn script_src(self, sources: &[&str]) -> Self {
+        self.directive("script-src", sources)
+    }
+
+    /// `style-src` directive
+    pub fn style_src(self, sources: &[&str]) -> Self {
+        self.directive("style-src", sources)
+    }
+
+    /// `img-src` directive
+    pub fn img_src(self, sources: &[&str]) -> Self {
+        self.directive("img-src", sources)
+    }
+
+    /// `connect-src` directive
+    pub fn connect_src(self, sources: &[&str]) -> Self {
+        self.directive("connect-src", sources)
+    }
+
+    /// `font-src` directive
+    pub fn font_src(self, sources: &[&str]) -> Self {
+        self.directive("font-src", sources)
+    }
+
+    /// `object-src` directive
+    pub fn object_src(self, sources: &[&str]) -> Self {
+        self.directive("object-src", sources)
+    }
+
+    /// `frame-src` directive
+    pub fn frame_src(self, sources: &[&str]) -> Self {
+        self.directive("frame-src", sources)
+    }
+
+    /// `frame-ancestors` directive
+    pub fn frame_ancestors(self, sources: &[&str]) -> Self {
+        self.directive("frame-ancestors", sources)
+    }
+
+    /// `base-uri` directive
+    pub fn base_uri(self, sources: &[&str]) -> Self {
+        self.directive("base-uri", sources)
+    }
+
+    /// `form-action` directive
+    pub fn form_action(self, sources: &[&str]) -> Self {
+        self.directive("form-action", sources)
+    }
+
+    /// `upgrade-insecure-requests` directive
+    pub fn upgrade_insecure_requests(self) -> Self {
+        self.directive("upgrade-insecure-requests", &[])
+    }
+
+    /// `report-to` directive
+    pub fn report_to(self, group: &str) -> Self {
+        self.directive("report-to", &[group])
+    }
+
+    /// Add the nonce of each request to `script-src`
+    pub fn with_script_nonce(mut self) -> Self {
+        self.script_nonce = true;
+        self
+    }
+
+    /// Add the nonce of each request to `style-src`
+    pub fn with_style_nonce(mut self) -> Self {
+        self.style_nonce = true;
+        self
+    }
+
+    /// Return true if the policy needs a nonce per request
+    pub fn uses_nonce(&self) -> bool {
+        self.script_nonce || self.style_nonce
+    }
+
+    /// Header value of the policy, with the nonce of the request
+    pub fn build(&self, nonce: Option<&str>) -> String {
+        let mut directives = self.directives.clone();
+        if let Some(nonce) = nonce {
+            let nonce = format!("'nonce-{nonce}'");
+            for (name, enabled) in [("script-src", self.script_nonce), ("style-src", self.style_nonce)] {
+                if !enabled {
+                    continue;
+                }
+                match directives.iter_mut().find(|(directive, _)| directive == name) {
+                    Some((_, sources)) => sources.push(nonce.clone()),
+                    None => directives.push((name.to_string(), vec![nonce.clone()])),
+                }
+            }
+        }
+
+        directives
+            .iter()
+            .map(|(name, sources)| {
+                std::iter::once(name.as_str())
+                    .chain(sources.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl fmt::Display for ContentSecurityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.build(None))
+    }
+}
 
 /// Configuration for security headers
 #[derive(Clone, Debug)]
@@ -20,6 +238,15 @@ pub struct SecurityHeadersConfig {
     pub x_xss_protection: HeaderValue,
     pub referrer_policy: HeaderValue,
     pub permissions_policy: HeaderValue,
+    pub cross_origin_opener_policy: HeaderValue,
+    pub cross_origin_embedder_policy: HeaderValue,
+    pub cross_origin_resource_policy: HeaderValue,
+
+    /// Policy built for each request with a nonce, replaces `content_security_policy`
+    pub content_security_policy_nonce: Option<ContentSecurityPolicy>,
+
+    /// Keep the values set by the inner service instead of replacing them
+    pub if_not_present: bool,
 }
 
 impl Default for SecurityHeadersConfig {
@@ -32,6 +259,11 @@ impl Default for SecurityHeadersConfig {
             x_xss_protection: HeaderValue::from_static("1; mode=block"),
             referrer_policy: HeaderValue::from_static("no-referrer"),
             permissions_policy: HeaderValue::from_static("geolocation=(self), microphone=(), camera=()"),
+            cross_origin_opener_policy: HeaderValue::from_static(""),
+            cross_origin_embedder_policy: HeaderValue::from_static(""),
+            cross_origin_resource_policy: HeaderValue::from_static(""),
+            content_security_policy_nonce: None,
+            if_not_present: false,
         }
     }
 }
@@ -41,11 +273,129 @@ pub struct SecurityHeadersLayer {
     pub config: SecurityHeadersConfig,
 }
 
+impl SecurityHeadersConfig {
+    /// Strictest headers, for JSON APIs which never serve documents to browsers
+    pub fn api_strict() -> Self {
+        Self {
+            content_security_policy: HeaderValue::from_static("default-src 'none'; frame-ancestors 'none'"),
+            strict_transport_security: HeaderValue::from_static("max-age=63072000; includeSubDomains; preload"),
+            x_content_type_options: HeaderValue::from_static("nosniff"),
+            x_frame_options: HeaderValue::from_static("DENY"),
+            x_xss_protection: HeaderValue::from_static("0"),
+            referrer_policy: HeaderValue::from_static("no-referrer"),
+            permissions_policy: HeaderValue::from_static(
+                "accelerometer=(), camera=(), geolocation=(), gyroscope=(), microphone=(), payment=(), usb=()",
+            ),
+            cross_origin_opener_policy: HeaderValue::from_static("same-origin"),
+            cross_origin_embedder_policy: HeaderValue::from_static("require-corp"),
+            cross_origin_resource_policy: HeaderValue::from_static("same-origin"),
+            content_security_policy_nonce: None,
+            if_not_present: false,
+        }
+    }
+
+    /// Headers of an application serving HTML pages and assets from its own origin
+    pub fn browser_app() -> Self {
+        Self {
+            content_security_policy: HeaderValue::from_static(
+                "default-src 'self'; img-src 'self' data:; object-src 'none'; frame-ancestors 'self'; \
+                 base-uri 'self'; form-action 'self'",
+            ),
+            x_frame_options: HeaderValue::from_static("SAMEORIGIN"),
+            x_xss_protection: HeaderValue::from_static("0"),
+            referrer_policy: HeaderValue::from_static("strict-origin-when-cross-origin"),
+            cross_origin_opener_policy: HeaderValue::from_static("same-origin"),
+            cross_origin_embedder_policy: HeaderValue::from_static("unsafe-none"),
+            cross_origin_resource_policy: HeaderValue::from_static("same-site"),
+            ..Self::default()
+        }
+    }
+
+    /// No header, to only set some of them
+    pub fn disabled() -> Self {
+        let empty = HeaderValue::from_static("");
+
+        Self {
+            content_security_policy: empty.clone(),
+            strict_transport_security: empty.clone(),
+            x_content_type_options: empty.clone(),
+            x_frame_options: empty.clone(),
+            x_xss_protection: empty.clone(),
+            referrer_policy: empty.clone(),
+            permissions_policy: empty.clone(),
+            cross_origin_opener_policy: empty.clone(),
+            cross_origin_embedder_policy: empty.clone(),
+            cross_origin_resource_policy: empty,
+            content_security_policy_nonce: None,
+            if_not_present: false,
+        }
+    }
+
+    /// Set the `Content-Security-Policy`, built for each request if it uses a nonce
+    ///
+    /// Returns an error if the policy is not a valid header value.
+    pub fn with_content_security_policy(mut self, csp: ContentSecurityPolicy) -> Result<Self, SecurityHeadersError> {
+        // The nonces are hexadecimal strings, so that checking the policy with one of them is enough
+        let nonce = csp.uses_nonce().then(|| Uuid::nil().simple().to_string());
+        let policy = csp.build(nonce.as_deref());
+        let value = HeaderValue::from_str(&policy)
+            .map_err(|_| SecurityHeadersError::InvalidContentSecurityPolicy(policy.clone()))?;
+
+        if csp.uses_nonce() {
+            self.content_security_policy_nonce = Some(csp);
+        } else {
+            self.content_security_policy = value;
+            self.content_security_policy_nonce = None;
+        }
+        Ok(self)
+    }
+
+    /// Set the headers in a response
+    fn apply(self, headers: &mut HeaderMap) {
+        let values = [
+            (header::CONTENT_SECURITY_POLICY, self.content_security_policy),
+            (header::STRICT_TRANSPORT_SECURITY, self.strict_transport_security),
+            (header::X_CONTENT_TYPE_OPTIONS, self.x_content_type_options),
+            (header::X_FRAME_OPTIONS, self.x_frame_options),
+            (header::X_XSS_PROTECTION, self.x_xss_protection),
+            (header::REFERRER_POLICY, self.referrer_policy),
+            (HeaderName::from_static("permissions-policy"), self.permissions_policy),
+            (
+                HeaderName::from_static("cross-origin-opener-policy"),
+                self.cross_origin_opener_policy,
+            ),
+            (
+                HeaderName::from_static("cross-origin-embedder-policy"),
+                self.cross_origin_embedder_policy,
+            ),
+            (
+                HeaderName::from_static("cross-origin-resource-policy"),
+                self.cross_origin_resource_policy,
+            ),
+        ];
+
+        for (name, value) in values {
+            if value.is_empty() {
+                continue;
+            }
+            if !self.if_not_present || !headers.contains_key(&name) {
+                headers.insert(name, value);
+            }
+        }
+    }
+}
+
 impl SecurityHeadersLayer {
     /// Create a new `SecurityLayer`
     pub fn new(config: SecurityHeadersConfig) -> Self {
         Self { config }
     }
+
+    /// Keep the headers already set by the inner service (`true`) or replace them (`false`, default)
+    pub fn with_if_not_present(mut self, if_not_present: bool) -> Self {
+        self.config.if_not_present = if_not_present;
+        self
+    }
 }
 
 impl<S> Layer<S> for SecurityHeadersLayer {
@@ -79,21 +429,21 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: Request<Body>) -> Self::Future {
-        let config = self.config.clone();
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let mut config = self.config.clone();
+        if let Some(csp) = config.content_security_policy_nonce.take() {
+            let nonce = Uuid::new_v4().simple().to_string();
+            config.content_security_policy = HeaderValue::from_str(&csp.build(Some(&nonce))).unwrap_or_else(|err| {
+                error!("Invalid Content-Security-Policy, `{CSP_FALLBACK}` sent instead: {err}");
+                HeaderValue::from_static(CSP_FALLBACK)
+            });
+            request.extensions_mut().insert(CspNonce(nonce));
+        }
         let future = self.inner.call(request);
 
         Box::pin(async move {
             let mut response: Response = future.await?;
-
-            let headers = response.headers_mut();
-            headers.insert(header::CONTENT_SECURITY_POLICY, config.content_security_policy);
-            headers.insert(header::STRICT_TRANSPORT_SECURITY, config.strict_transport_security);
-            headers.insert(header::X_CONTENT_TYPE_OPTIONS, config.x_content_type_options);
-            headers.insert(header::X_FRAME_OPTIONS, config.x_frame_options);
-            headers.insert(header::X_XSS_PROTECTION, config.x_xss_protection);
-            headers.insert(header::REFERRER_POLICY, config.referrer_policy);
-            headers.insert(HeaderName::from_static("permissions-policy"), config.permissions_policy);
+            config.apply(response.headers_mut());
 
             Ok(response)
         })
@@ -138,6 +488,10 @@ mod tests {
             h.get("permissions-policy").unwrap(),
             "geolocation=(self), microphone=(), camera=()",
         );
+        // The `Cross-Origin-*` headers are opt-in
+        assert!(h.get("cross-origin-opener-policy").is_none());
+        assert!(h.get("cross-origin-embedder-policy").is_none());
+        assert!(h.get("cross-origin-resource-policy").is_none());
     }
 
     #[tokio::test]
@@ -192,4 +546,159 @@ mod tests {
 
         assert_eq!(response.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
     }
+
+    #[tokio::test]
+    async fn layer_keeps_existing_header_if_not_present() {
+        let svc = ServiceBuilder::new()
+            .layer(SecurityHeadersLayer::new(SecurityHeadersConfig::default()).with_if_not_present(true))
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .header(header::CONTENT_SECURITY_POLICY, "default-src 'self' cdn.example.com")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+            }));
+
+        let response = svc
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        // Error responses get the headers too
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let h = response.headers();
+        assert_eq!(
+            h.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self' cdn.example.com"
+        );
+        assert_eq!(h.get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(h.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    }
+
+    #[test]
+    fn content_security_policy_builder() {
+        let csp = ContentSecurityPolicy::new()
+            .default_src(&[CSP_SELF])
+            .script_src(&[CSP_SELF])
+            .script_src(&["https://cdn.example.com"])
+            .object_src(&[CSP_NONE])
+            .upgrade_insecure_requests()
+            .with_script_nonce()
+            .with_style_nonce();
+
+        assert!(csp.uses_nonce());
+        assert_eq!(
+            csp.to_string(),
+            "default-src 'self'; script-src 'self' https://cdn.example.com; object-src 'none'; upgrade-insecure-requests"
+        );
+        assert_eq!(
+            csp.build(Some("abc")),
+            "default-src 'self'; script-src 'self' https://cdn.example.com 'nonce-abc'; object-src 'none'; \
+             upgrade-insecure-requests; style-src 'nonce-abc'"
+        );
+    }
+
+    #[test]
+    fn invalid_content_security_policy_is_rejected() {
+        for csp in [
+            ContentSecurityPolicy::new().default_src(&["'self'\n"]),
+            ContentSecurityPolicy::new()
+                .script_src(&["https://cdn.example.com\r\n"])
+                .with_script_nonce(),
+        ] {
+            assert!(matches!(
+                SecurityHeadersConfig::default().with_content_security_policy(csp),
+                Err(SecurityHeadersError::InvalidContentSecurityPolicy(_))
+            ));
+        }
+
+        let config = SecurityHeadersConfig::default()
+            .with_content_security_policy(ContentSecurityPolicy::new().default_src(&[CSP_SELF]))
+            .unwrap();
+        assert_eq!(config.content_security_policy, "default-src 'self'");
+    }
+
+    #[tokio::test]
+    async fn presets_set_their_headers() {
+        let call = |config: SecurityHeadersConfig| async move {
+            ServiceBuilder::new()
+                .layer(SecurityHeadersLayer::new(config))
+                .service(tower::service_fn(|_req: Request<Body>| async {
+                    Ok::<_, Infallible>(ok_response())
+                }))
+                .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+        };
+
+        let response = call(SecurityHeadersConfig::api_strict()).await;
+        let h = response.headers();
+        assert_eq!(
+            h.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'none'; frame-ancestors 'none'"
+        );
+        assert_eq!(h.get(header::X_XSS_PROTECTION).unwrap(), "0");
+        assert_eq!(h.get("cross-origin-opener-policy").unwrap(), "same-origin");
+        assert_eq!(h.get("cross-origin-embedder-policy").unwrap(), "require-corp");
+        assert_eq!(h.get("cross-origin-resource-policy").unwrap(), "same-origin");
+
+        let response = call(SecurityHeadersConfig::browser_app()).await;
+        let h = response.headers();
+        assert_eq!(
+            h.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'; img-src 'self' data:; object-src 'none'; frame-ancestors 'self'; base-uri 'self'; \
+             form-action 'self'"
+        );
+        assert_eq!(h.get(header::X_FRAME_OPTIONS).unwrap(), "SAMEORIGIN");
+        assert_eq!(h.get("cross-origin-opener-policy").unwrap(), "same-origin");
+        assert_eq!(h.get("cross-origin-embedder-policy").unwrap(), "unsafe-none");
+        assert_eq!(h.get("cross-origin-resource-policy").unwrap(), "same-site");
+
+        let response = call(SecurityHeadersConfig {
+            x_content_type_options: HeaderValue::from_static("nosniff"),
+            ..SecurityHeadersConfig::disabled()
+        })
+        .await;
+        let h = response.headers();
+        assert_eq!(h.len(), 1);
+        assert_eq!(h.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    }
+
+    #[tokio::test]
+    async fn layer_generates_a_csp_nonce_per_request() {
+        let csp = ContentSecurityPolicy::new()
+            .default_src(&[CSP_SELF])
+            .with_script_nonce();
+        let svc = ServiceBuilder::new()
+            .layer(SecurityHeadersLayer::new(
+                SecurityHeadersConfig::disabled()
+                    .with_content_security_policy(csp)
+                    .unwrap(),
+            ))
+            .service(tower::service_fn(|req: Request<Body>| async move {
+                let nonce = req.extensions().get::<CspNonce>().unwrap().0.clone();
+                Ok::<_, Infallible>(Response::new(Body::from(nonce)))
+            }));
+
+        let mut nonces = Vec::new();
+        for _ in 0..2 {
+            let response = svc
+                .clone()
+                .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let csp = response.headers()[header::CONTENT_SECURITY_POLICY]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+            let nonce = String::from_utf8(body.to_vec()).unwrap();
+
+            assert_eq!(csp, format!("default-src 'self'; script-src 'nonce-{nonce}'"));
+            nonces.push(nonce);
+        }
+        assert_ne!(nonces[0], nonces[1]);
+    }
 }
@@ -0,0 +1,377 @@
+//! Request deduplication layer
+//!
+//! [`SingleflightLayer`] coalesces the concurrent identical `GET` requests (same path, query
+//! string and values of the vary headers) into a single execution of the inner service: the
+//! first request is executed and its response is shared with the requests received while it is in
+//! flight. It protects slow backends from thundering herds (e.g. a cache expiration).
+//!
+//! - The `Accept`, `Accept-Encoding`, `Accept-Language`, `Authorization` and `Cookie` headers are
+//!   part of the key by default, so that responses are never shared between users
+//! - A waiting request executes the inner service itself if the shared execution takes longer
+//!   than the timeout, is cancelled or returns a body larger than `body_max_size` (streamed bodies
+//!   are never shared)
+//! - With the `metrics` feature, the `singleflight_requests_total` counter (label `outcome`:
+//!   `executed`, `coalesced`, `timeout` or `fallback`) counts the requests
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::singleflight::SingleflightLayer;
+//! use axum::http::HeaderName;
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let app: Router = Router::new()
+//!     .route("/reports", get(|| async { "report" }))
+//!     .layer(
+//!         SingleflightLayer::new()
+//!             .with_vary_header(HeaderName::from_static("x-tenant-id"))
+//!             .with_timeout(Duration::from_secs(10)),
+//!     );
+//! ```
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{HeaderName, Method, Request, header, response::Parts};
+use axum::response::Response;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// Default time waited for a shared execution
+pub const SINGLEFLIGHT_DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum size of a shared body (1 MiB)
+pub const SINGLEFLIGHT_DEFAULT_BODY_MAX_SIZE: usize = 1_024 * 1_024;
+
+/// Result of a shared execution
+#[derive(Clone)]
+enum Outcome {
+    /// Response shared with the waiting requests
+    Shared(Arc<(Parts, Bytes)>),
+
+    /// Response which cannot be shared (error, streamed or too large body)
+    NotShared,
+}
+
+/// In-flight executions by key
+type InFlight = Arc<Mutex<HashMap<String, watch::Receiver<Option<Outcome>>>>>;
+
+/// Layer coalescing the concurrent identical `GET` requests
+#[derive(Clone)]
+pub struct SingleflightLayer {
+    pub vary_headers: Vec<HeaderName>,
+    pub timeout: Duration,
+    pub body_max_size: usize,
+    in_flight: InFlight,
+}
+
+impl Default for SingleflightLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SingleflightLayer {
+    /// Create a new `SingleflightLayer` with the default vary headers, timeout (30s) and maximum
+    /// body size (1 MiB)
+    pub fn new() -> Self {
+        Self {
+            vary_headers: vec![
+                header::ACCEPT,
+                header::ACCEPT_ENCODING,
+                header::ACCEPT_LANGUAGE,
+                header::AUTHORIZATION,
+                header::COOKIE,
+            ],
+            timeout: SINGLEFLIGHT_DEFAULT_TIMEOUT,
+            body_max_size: SINGLEFLIGHT_DEFAULT_BODY_MAX_SIZE,
+            in_flight: InFlight::default(),
+        }
+    }
+
+    /// Add a header to the key of the requests
+    pub fn with_vary_header(mut self, header: HeaderName) -> Self {
+        self.vary_headers.push(header);
+        self
+    }
+
+    /// Set the time a request waits for the shared execution before executing itself
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum size of a shared body
+    pub fn with_body_max_size(mut self, body_max_size: usize) -> Self {
+        self.body_max_size = body_max_size;
+        self
+    }
+
+    /// Key of a request, `None` if it cannot be coalesced
+    fn key(&self, request: &Request<Body>) -> Option<String> {
+        if request.method() != Method::GET {
+            return None;
+        }
+
+        let mut key = request
+            .uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/")
+            .to_string();
+        for name in &self.vary_headers {
+            key.push('\n');
+            key.push_str(name.as_str());
+            for value in request.headers().get_all(name) {
+                key.push(':');
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+            }
+        }
+
+        Some(key)
+    }
+}
+
+impl<S> Layer<S> for SingleflightLayer {
+    type Service = SingleflightMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SingleflightMiddleware {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SingleflightMiddleware<S> {
+    inner: S,
+    layer: SingleflightLayer,
+}
+
+/// Removes the key of an execution when it completes or is cancelled
+struct InFlightGuard {
+    in_flight: InFlight,
+    key: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Count a request in the `singleflight_requests_total` metric
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn record(outcome: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("singleflight_requests_total", "outcome" => outcome).increment(1);
+}
+
+/// Build a response from a shared one
+fn shared_response(shared: &(Parts, Bytes)) -> Response {
+    let (parts, body) = shared;
+    Response::from_parts(parts.clone(), Body::from(body.clone()))
+}
+
+impl<S> Service<Request<Body>> for SingleflightMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let Some(key) = self.layer.key(&request) else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        // Join the in-flight execution of the key or start a new one
+        let (sender, receiver) = {
+            let Ok(mut in_flight) = self.layer.in_flight.lock() else {
+                return Box::pin(self.inner.call(request));
+            };
+            match in_flight.get(&key) {
+                Some(receiver) => (None, receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    in_flight.insert(key.clone(), receiver.clone());
+                    (Some(sender), receiver)
+                }
+            }
+        };
+
+        // Executed request
+        if let Some(sender) = sender {
+            let guard = InFlightGuard {
+                in_flight: self.layer.in_flight.clone(),
+                key,
+            };
+            let body_max_size = self.layer.body_max_size;
+            let future = self.inner.call(request);
+
+            return Box::pin(async move {
+                record("executed");
+                // The key is released once the outcome is published
+                let _guard = guard;
+                let response = future.await;
+
+                let response = match response {
+                    Ok(response) => response,
+                    Err(err) => {
+                        sender.send_replace(Some(Outcome::NotShared));
+                        return Err(err);
+                    }
+                };
+
+                // Only the bodies with a known size are buffered
+                let size = response.body().size_hint().exact();
+                if size.is_none_or(|size| size > body_max_size as u64) {
+                    sender.send_replace(Some(Outcome::NotShared));
+                    return Ok(response);
+                }
+
+                let (parts, body) = response.into_parts();
+                match axum::body::to_bytes(body, body_max_size).await {
+                    Ok(body) => {
+                        let shared = Arc::new((parts, body));
+                        sender.send_replace(Some(Outcome::Shared(shared.clone())));
+                        Ok(shared_response(&shared))
+                    }
+                    Err(err) => {
+                        sender.send_replace(Some(Outcome::NotShared));
+                        Ok(Response::from_parts(
+                            parts,
+                            Body::from_stream(futures::stream::once(async move { Err::<Bytes, _>(err) })),
+                        ))
+                    }
+                }
+            });
+        }
+
+        // Waiting request
+        let mut inner = self.inner.clone();
+        let timeout = self.layer.timeout;
+        let mut receiver = receiver;
+
+        Box::pin(async move {
+            let outcome = tokio::time::timeout(timeout, async {
+                receiver.wait_for(Option::is_some).await.map(|outcome| outcome.clone())
+            })
+            .await;
+            match outcome {
+                Ok(Ok(Some(Outcome::Shared(shared)))) => {
+                    record("coalesced");
+                    return Ok(shared_response(&shared));
+                }
+                Ok(Ok(_)) => record("fallback"),
+                // The executed request was cancelled
+                Ok(Err(_)) => record("fallback"),
+                Err(_) => record("timeout"),
+            }
+
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    fn app(calls: Arc<AtomicUsize>, delay: Duration, layer: SingleflightLayer) -> Router {
+        Router::new()
+            .route(
+                "/report",
+                get(move || {
+                    let calls = calls.clone();
+                    async move {
+                        let call = calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(delay).await;
+                        format!("report {call}")
+                    }
+                }),
+            )
+            .layer(layer)
+    }
+
+    fn request(uri: &str, authorization: &str) -> Request<Body> {
+        Request::get(uri)
+            .header(header::AUTHORIZATION, authorization)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_singleflight_coalesces_identical_requests() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone(), Duration::from_millis(100), SingleflightLayer::new());
+
+        let responses =
+            futures::future::join_all((0..5).map(|_| send(app.clone(), request("/report?year=2026", "Bearer alice"))))
+                .await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(
+            responses
+                .iter()
+                .all(|response| *response == (StatusCode::OK, "report 0".to_string()))
+        );
+
+        // Different users, query strings or sequential requests are not coalesced
+        let responses = futures::future::join_all([
+            send(app.clone(), request("/report?year=2026", "Bearer alice")),
+            send(app.clone(), request("/report?year=2026", "Bearer bob")),
+            send(app.clone(), request("/report?year=2025", "Bearer alice")),
+        ])
+        .await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_ne!(responses[0].1, responses[1].1);
+        assert_ne!(responses[0].1, responses[2].1);
+
+        // Other methods are not coalesced
+        let response = app
+            .oneshot(Request::post("/report").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_singleflight_timeout() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let layer = SingleflightLayer::new().with_timeout(Duration::from_millis(20));
+        let app = app(calls.clone(), Duration::from_millis(100), layer.clone());
+
+        let responses =
+            futures::future::join_all((0..3).map(|_| send(app.clone(), request("/report", "Bearer alice")))).await;
+
+        // The waiting requests executed themselves
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(responses.iter().all(|(status, _)| *status == StatusCode::OK));
+        assert!(layer.in_flight.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Slow request log and latency percentiles
+//!
+//! [`SlowRequestLayer`] logs the details of the requests slower than a threshold (method, route
+//! pattern, path, status, duration, principal and request ID) with a `warn` level, and records
+//! the latency of every request in a [`LatencyTracker`].
+//!
+//! The tracker keeps the latest durations of each route (a rolling window) to compute the p50,
+//! p95 and p99 percentiles, exposed in JSON by [`LatencyTracker::router`] (a debug endpoint to
+//! protect or to serve on an internal port).
+//!
+//! The principal comes from the `RequestStore`: put the layer inside the authentication layer.
+//!
+//! ```rust
+//! use api_tools::server::axum::layers::slow_request::SlowRequestLayer;
+//! use axum::{Router, routing::get};
+//! use std::time::Duration;
+//!
+//! let slow_requests = SlowRequestLayer::new(Duration::from_millis(500));
+//! let app: Router = Router::new()
+//!     .route("/users", get(|| async { "users" }))
+//!     .layer(slow_requests.clone())
+//!     .merge(slow_requests.tracker().router("/debug/latencies"));
+//! ```
+
+use crate::server::axum::layers::request_id::REQUEST_ID_HEADER;
+use crate::server::axum::request_store::{Principal, RequestStore};
+use axum::body::Body;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Default slow request threshold
+pub const SLOW_REQUEST_DEFAULT_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Default number of durations kept per route
+pub const LATENCY_TRACKER_DEFAULT_WINDOW: usize = 1_024;
+
+/// Latency percentiles of a route, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteLatency {
+    pub route: String,
+    pub count: u64,
+    pub slow_count: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct RouteSamples {
+    durations: VecDeque<Duration>,
+    count: u64,
+    slow_count: u64,
+}
+
+/// Rolling latency percentiles per route
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    window: usize,
+    routes: Arc<Mutex<HashMap<String, RouteSamples>>>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(LATENCY_TRACKER_DEFAULT_WINDOW)
+    }
+}
+
+impl LatencyTracker {
+    /// Create a new `LatencyTracker` keeping the last `window` durations of each route
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            routes: Arc::default(),
+        }
+    }
+
+    /// Record the duration of a request
+    pub fn record(&self, route: &str, duration: Duration, slow: bool) {
+        if let Ok(mut routes) = self.routes.lock() {
+            let samples = routes.entry(route.to_string()).or_default();
+            if samples.durations.len() == self.window {
+                samples.durations.pop_front();
+            }
+            samples.durations.push_back(duration);
+            samples.count += 1;
+            samples.slow_count += u64::from(slow);
+        }
+    }
+
+    /// Percentiles of a route
+    pub fn route(&self, route: &str) -> Option<RouteLatency> {
+        let routes = self.routes.lock().ok()?;
+
+        routes.get(route).map(|samples| Self::latency(route, samples))
+    }
+
+    /// Percentiles of all the routes, sorted by route
+    pub fn snapshot(&self) -> Vec<RouteLatency> {
+        let mut latencies = self
+            .routes
+            .lock()
+            .map(|routes| {
+                routes
+                    .iter()
+                    .map(|(route, samples)| Self::latency(route, samples))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        latencies.sort_by(|a, b| a.route.cmp(&b.route));
+
+        latencies
+    }
+
+    /// Route serving the percentiles in JSON
+    pub fn router<S>(&self, path: &str) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let tracker = self.clone();
+
+        Router::new().route(path, get(move || async move { Json(tracker.snapshot()) }))
+    }
+
+    fn latency(route: &str, samples: &RouteSamples) -> RouteLatency {
+        let mut durations = samples.durations.iter().copied().collect::<Vec<_>>();
+        durations.sort_unstable();
+
+        RouteLatency {
+            route: route.to_string(),
+            count: samples.count,
+            slow_count: samples.slow_count,
+            p50_ms: percentile(&durations, 0.50),
+            p95_ms: percentile(&durations, 0.95),
+            p99_ms: percentile(&durations, 0.99),
+            max_ms: durations
+                .last()
+                .map(|max| max.as_secs_f64() * 1_000.0)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Percentile (nearest rank) of sorted durations, in milliseconds
+fn percentile(sorted: &[Duration], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((percentile * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+
+    sorted[rank - 1].as_secs_f64() * 1_000.0
+}
+
+#[derive(Clone)]
+pub struct SlowRequestLayer {
+    pub threshold: Duration,
+    pub tracker: LatencyTracker,
+}
+
+impl Default for SlowRequestLayer {
+    fn default() -> Self {
+        Self::new(SLOW_REQUEST_DEFAULT_THRESHOLD)
+    }
+}
+
+impl SlowRequestLayer {
+    /// Create a new `SlowRequestLayer`
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            tracker: LatencyTracker::default(),
+        }
+    }
+
+    /// Use a specific tracker (e.g. shared with other routers or with a custom window)
+    pub fn with_tracker(mut self, tracker: LatencyTracker) -> Self {
+        self.tracker = tracker;
+        self
+    }
+
+    /// Tracker of the latencies
+    pub fn tracker(&self) -> LatencyTracker {
+        self.tracker.clone()
+    }
+}
+
+impl<S> Layer<S> for SlowRequestLayer {
+    type Service = SlowRequestMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRequestMiddleware {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SlowRequestMiddleware<S> {
+    inner: S,
+    config: SlowRequestLayer,
+}
+
+impl<S> Service<Request<Body>> for SlowRequestMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let start = Instant::now();
+        let config = self.config.clone();
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        let route = match request.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str().to_string(),
+            None => path.clone(),
+        };
+        let principal = RequestStore::from_extensions(request.extensions())
+            .and_then(|store| store.get::<Principal>())
+            .map(|principal| principal.0.clone());
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER.clone())
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            let duration = start.elapsed();
+            let slow = duration >= config.threshold;
+            config.tracker.record(&route, duration, slow);
+
+            if slow {
+                tracing::warn!(
+                    method,
+                    route,
+                    path,
+                    status = response.status().as_u16(),
+                    duration_ms = duration.as_secs_f64() * 1_000.0,
+                    threshold_ms = config.threshold.as_secs_f64() * 1_000.0,
+                    principal = principal.as_deref().unwrap_or_default(),
+                    request_id = request_id.as_deref().unwrap_or_default(),
+                    "Slow request"
+                );
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_latency_tracker_percentiles() {
+        let tracker = LatencyTracker::new(100);
+        for ms in 1..=200 {
+            tracker.record("/users", Duration::from_millis(ms), ms > 190);
+        }
+
+        let latency = tracker.route("/users").unwrap();
+        assert_eq!(latency.count, 200);
+        assert_eq!(latency.slow_count, 10);
+        // Only the last 100 durations (101 to 200 ms) are kept
+        assert_eq!(latency.p50_ms, 150.0);
+        assert_eq!(latency.p95_ms, 195.0);
+        assert_eq!(latency.p99_ms, 199.0);
+        assert_eq!(latency.max_ms, 200.0);
+        assert!(tracker.route("/unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_layer() {
+        let layer = SlowRequestLayer::new(Duration::from_millis(20));
+        let app: Router = Router::new()
+            .route("/users/{id}", get(|| async { "user" }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(30)).await;
+                    "slow"
+                }),
+            )
+            .layer(layer.clone())
+            .merge(layer.tracker().router("/debug/latencies"));
+
+        for uri in ["/users/1", "/users/2", "/slow"] {
+            app.clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let snapshot = layer.tracker().snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].route, "/slow");
+        assert_eq!(snapshot[0].slow_count, 1);
+        assert_eq!(snapshot[1].route, "/users/{id}");
+        assert_eq!(snapshot[1].count, 2);
+        assert_eq!(snapshot[1].slow_count, 0);
+
+        let response = app
+            .oneshot(Request::get("/debug/latencies").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 10_000).await.unwrap();
+        let latencies: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(latencies[1]["route"], "/users/{id}");
+        assert_eq!(latencies[1]["count"], 2);
+    }
+}
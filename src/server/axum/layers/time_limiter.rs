@@ -1,6 +1,7 @@
 //! Time limiter layer
 
-use crate::server::axum::{layers::body_from_parts, response::ApiError};
+use crate::server::axum::layers::{body_from_parts, route_matches};
+use crate::server::axum::response::ApiError;
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use axum::response::Response;
@@ -113,15 +114,77 @@ impl TryFrom<&str> for TimeSlot {
     }
 }
 
+/// Time slots of a route pattern
+///
+/// Segments in braces (`{id}`) match any single segment and a final `{*rest}` or `*`
+/// segment matches the rest of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteConfig {
+    pub pattern: String,
+    pub time_slots: TimeSlots,
+}
+
+impl RouteConfig {
+    /// Create a new `RouteConfig`
+    pub fn new(pattern: &str, time_slots: TimeSlots) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            time_slots,
+        }
+    }
+
+    /// Check if a request path matches the route pattern
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::time_limiter::{RouteConfig, TimeSlots};
+    ///
+    /// let route = RouteConfig::new("/reports/{id}", TimeSlots::from("08:00-20:00"));
+    /// assert!(route.matches("/reports/42"));
+    /// assert!(!route.matches("/reports/42/export"));
+    ///
+    /// let route = RouteConfig::new("/exports/*", TimeSlots::from("08:00-20:00"));
+    /// assert!(route.matches("/exports/2024/08"));
+    /// ```
+    pub fn matches(&self, path: &str) -> bool {
+        route_matches(&self.pattern, path)
+    }
+}
+
+/// Time limiter layer
+///
+/// Requests are rejected with a `503 Service Unavailable` during the time slots.
+/// Routes configured with [`TimeLimiterLayer::with_route`] use their own time slots
+/// instead of the global ones (the first matching route is used).
 #[derive(Clone)]
 pub struct TimeLimiterLayer {
     pub time_slots: TimeSlots,
+    pub routes: Vec<RouteConfig>,
 }
 
 impl TimeLimiterLayer {
     /// Create a new `TimeLimiterLayer`
     pub fn new(time_slots: TimeSlots) -> Self {
-        Self { time_slots }
+        Self {
+            time_slots,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Add time slots for a specific route pattern
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::server::axum::layers::time_limiter::{RouteConfig, TimeLimiterLayer, TimeSlots};
+    ///
+    /// // Heavy reports are only available at night
+    /// let layer = TimeLimiterLayer::new(TimeSlots::from(""))
+    ///     .with_route(RouteConfig::new("/reports/{*path}", TimeSlots::from("06:00-22:00")));
+    /// assert_eq!(layer.routes.len(), 1);
+    /// ```
+    pub fn with_route(mut self, route: RouteConfig) -> Self {
+        self.routes.push(route);
+        self
     }
 }
 
@@ -132,6 +195,7 @@ impl<S> Layer<S> for TimeLimiterLayer {
         TimeLimiterMiddleware {
             inner,
             time_slots: self.time_slots.clone(),
+            routes: self.routes.clone(),
         }
     }
 }
@@ -140,6 +204,18 @@ impl<S> Layer<S> for TimeLimiterLayer {
 pub struct TimeLimiterMiddleware<S> {
     inner: S,
     time_slots: TimeSlots,
+    routes: Vec<RouteConfig>,
+}
+
+impl<S> TimeLimiterMiddleware<S> {
+    /// Time slots applying to a request path
+    fn time_slots_for(&self, path: &str) -> &TimeSlots {
+        self.routes
+            .iter()
+            .find(|route| route.matches(path))
+            .map(|route| &route.time_slots)
+            .unwrap_or(&self.time_slots)
+    }
 }
 
 impl<S> Service<Request<Body>> for TimeLimiterMiddleware<S>
@@ -158,8 +234,8 @@ where
 
     fn call(&mut self, request: Request<Body>) -> Self::Future {
         let now = Local::now().format("%H:%M").to_string();
-        let is_authorized = !self.time_slots.contains(&now);
-        let time_slots = self.time_slots.clone();
+        let time_slots = self.time_slots_for(request.uri().path()).clone();
+        let is_authorized = !time_slots.contains(&now);
 
         let future = self.inner.call(request);
         Box::pin(async move {
@@ -236,6 +312,26 @@ mod tests {
         assert!(!slots.contains("12:01"));
     }
 
+    #[test]
+    fn route_config_matches() {
+        let route = RouteConfig::new("/reports/{id}", TimeSlots::from(""));
+        assert!(route.matches("/reports/42"));
+        assert!(route.matches("/reports/42/"));
+        assert!(!route.matches("/reports"));
+        assert!(!route.matches("/reports/"));
+        assert!(!route.matches("/reports/42/export"));
+        assert!(!route.matches("/users/42"));
+
+        let route = RouteConfig::new("/health", TimeSlots::from(""));
+        assert!(route.matches("/health"));
+        assert!(!route.matches("/healthz"));
+
+        let route = RouteConfig::new("/exports/{*path}", TimeSlots::from(""));
+        assert!(route.matches("/exports/2024/08"));
+        assert!(route.matches("/exports"));
+        assert!(!route.matches("/export/2024"));
+    }
+
     #[test]
     fn timeslots_empty_never_contains() {
         let slots: TimeSlots = "".into();
@@ -307,4 +403,52 @@ mod middleware_tests {
         assert!(body.contains("\"code\":503"), "body was: {body}");
         assert!(body.contains("Service unavailable"), "body was: {body}");
     }
+
+    #[tokio::test]
+    async fn middleware_uses_route_time_slots() {
+        let layer = TimeLimiterLayer::new(TimeSlots::from(""))
+            .with_route(RouteConfig::new("/reports/{*path}", TimeSlots::from("00:00-23:59")));
+        let svc = ServiceBuilder::new()
+            .layer(layer)
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(ok_response())
+            }));
+
+        let resp = svc
+            .clone()
+            .oneshot(Request::builder().uri("/reports/daily").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let resp = svc
+            .oneshot(Request::builder().uri("/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn middleware_route_time_slots_override_global_ones() {
+        let layer = TimeLimiterLayer::new(TimeSlots::from("00:00-23:59"))
+            .with_route(RouteConfig::new("/health", TimeSlots::from("")));
+        let svc = ServiceBuilder::new()
+            .layer(layer)
+            .service(tower::service_fn(|_req: Request<Body>| async {
+                Ok::<_, Infallible>(ok_response())
+            }));
+
+        let resp = svc
+            .clone()
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = svc
+            .oneshot(Request::builder().uri("/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
 }
@@ -0,0 +1,376 @@
+//! Usage metering
+//!
+//! The [`MeteringLayer`] counts the requests and the bytes transferred by each [`Principal`] of
+//! the `RequestStore` (set by the authentication layers) in a [`Meter`]. The [`Meter`]
+//! aggregates the usage in memory and sends it in batches ([`UsageReport`]) to a
+//! [`MeteringSink`] (billing service, analytics database, message queue):
+//!
+//! - periodically, with [`Meter::spawn_flush`]
+//! - on demand, with [`Meter::flush`] (e.g. during the graceful shutdown)
+//!
+//! If the sink fails, the usage is kept and sent with the next batch. The sizes are the known
+//! sizes of the bodies (`Content-Length`): the streamed bodies of unknown size are not counted.
+//!
+//! ```rust
+//! use api_tools::server::axum::metering::{Meter, MeteringError, MeteringLayer, MeteringSink, UsageReport};
+//! use axum::{Router, routing::get};
+//! use futures::future::BoxFuture;
+//! use std::time::Duration;
+//!
+//! struct LogSink;
+//!
+//! impl MeteringSink for LogSink {
+//!     fn send<'a>(&'a self, report: &'a UsageReport) -> BoxFuture<'a, Result<(), MeteringError>> {
+//!         Box::pin(async move {
+//!             for usage in &report.usages {
+//!                 println!("{}: {} requests", usage.principal, usage.requests);
+//!             }
+//!             Ok(())
+//!         })
+//!     }
+//! }
+//!
+//! # async fn run() {
+//! let meter = Meter::new(LogSink);
+//! let _flush = meter.spawn_flush(Duration::from_secs(60));
+//!
+//! let app: Router = Router::new()
+//!     .route("/search", get(|| async { "results" }))
+//!     .layer(MeteringLayer::new(meter.clone()));
+//! # }
+//! ```
+
+use crate::server::axum::request_store::{Principal, RequestStore};
+use crate::value_objects::datetime::UtcDateTime;
+use axum::body::{Body, HttpBody};
+use axum::http::Request;
+use axum::response::Response;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tower::{Layer, Service};
+
+/// Metering error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MeteringError {
+    #[error("Metering sink error: {0}")]
+    Sink(String),
+}
+
+/// Usage of a principal
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub principal: String,
+    pub requests: u64,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+impl Usage {
+    /// Add the usage of the same principal
+    fn merge(&mut self, other: &Self) {
+        self.requests += other.requests;
+        self.request_bytes += other.request_bytes;
+        self.response_bytes += other.response_bytes;
+    }
+}
+
+/// Usage of the principals between two flushes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageReport {
+    pub from: UtcDateTime,
+    pub to: UtcDateTime,
+    pub usages: Vec<Usage>,
+}
+
+/// Destination of the usage reports
+pub trait MeteringSink: Send + Sync + 'static {
+    /// Send a report
+    fn send<'a>(&'a self, report: &'a UsageReport) -> BoxFuture<'a, Result<(), MeteringError>>;
+}
+
+#[derive(Debug)]
+struct MeterState {
+    since: UtcDateTime,
+    usages: HashMap<String, Usage>,
+}
+
+/// Aggregation of the usage, sent in batches to a [`MeteringSink`]
+#[derive(Clone)]
+pub struct Meter {
+    sink: Arc<dyn MeteringSink>,
+    state: Arc<Mutex<MeterState>>,
+}
+
+impl Meter {
+    /// Create a new `Meter`
+    pub fn new(sink: impl MeteringSink) -> Self {
+        Self::from_arc(Arc::new(sink))
+    }
+
+    /// Create a new `Meter` from a shared sink
+    pub fn from_arc(sink: Arc<dyn MeteringSink>) -> Self {
+        Self {
+            sink,
+            state: Arc::new(Mutex::new(MeterState {
+                since: UtcDateTime::now(),
+                usages: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Record a request of a principal
+    pub fn record(&self, principal: &str, request_bytes: u64, response_bytes: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            let usage = state.usages.entry(principal.to_string()).or_insert_with(|| Usage {
+                principal: principal.to_string(),
+                ..Usage::default()
+            });
+            usage.requests += 1;
+            usage.request_bytes += request_bytes;
+            usage.response_bytes += response_bytes;
+        }
+    }
+
+    /// Usage recorded since the last flush, sorted by principal
+    pub fn pending(&self) -> Vec<Usage> {
+        let mut usages = self
+            .state
+            .lock()
+            .map(|state| state.usages.values().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        usages.sort_by(|a, b| a.principal.cmp(&b.principal));
+
+        usages
+    }
+
+    /// Send the usage recorded since the last flush to the sink
+    ///
+    /// On failure, the usage is kept for the next flush.
+    pub async fn flush(&self) -> Result<(), MeteringError> {
+        let now = UtcDateTime::now();
+        let (since, usages) = {
+            let mut state = self.state.lock().map_err(|err| MeteringError::Sink(err.to_string()))?;
+            let since = std::mem::replace(&mut state.since, now.clone());
+            (since, std::mem::take(&mut state.usages))
+        };
+        if usages.is_empty() {
+            return Ok(());
+        }
+
+        let mut report = UsageReport {
+            from: since.clone(),
+            to: now,
+            usages: usages.into_values().collect(),
+        };
+        report.usages.sort_by(|a, b| a.principal.cmp(&b.principal));
+
+        let result = self.sink.send(&report).await;
+        if result.is_err() {
+            let mut state = self.state.lock().map_err(|err| MeteringError::Sink(err.to_string()))?;
+            state.since = since;
+            for usage in report.usages {
+                state
+                    .usages
+                    .entry(usage.principal.clone())
+                    .and_modify(|pending| pending.merge(&usage))
+                    .or_insert(usage);
+            }
+        }
+
+        result
+    }
+
+    /// Flush the usage at each interval on a background task
+    pub fn spawn_flush(&self, interval: Duration) -> JoinHandle<()> {
+        let meter = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(err) = meter.flush().await {
+                    tracing::warn!(error = %err, "Usage metering flush failed");
+                }
+            }
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct MeteringLayer {
+    pub meter: Meter,
+}
+
+impl MeteringLayer {
+    /// Create a new `MeteringLayer`
+    pub fn new(meter: Meter) -> Self {
+        Self { meter }
+    }
+}
+
+impl<S> Layer<S> for MeteringLayer {
+    type Service = MeteringMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MeteringMiddleware {
+            inner,
+            meter: self.meter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MeteringMiddleware<S> {
+    inner: S,
+    meter: Meter,
+}
+
+impl<S> Service<Request<Body>> for MeteringMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let principal = RequestStore::from_extensions(request.extensions())
+            .and_then(|store| store.get::<Principal>())
+            .map(|principal| principal.0.clone());
+        let Some(principal) = principal else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let request_bytes = request.body().size_hint().lower();
+        let meter = self.meter.clone();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            meter.record(&principal, request_bytes, response.body().size_hint().lower());
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct MemorySink {
+        reports: Mutex<Vec<UsageReport>>,
+        failing: AtomicBool,
+    }
+
+    impl MeteringSink for Arc<MemorySink> {
+        fn send<'a>(&'a self, report: &'a UsageReport) -> BoxFuture<'a, Result<(), MeteringError>> {
+            Box::pin(async move {
+                if self.failing.load(Ordering::SeqCst) {
+                    return Err(MeteringError::Sink("unavailable".to_string()));
+                }
+                self.reports.lock().unwrap().push(report.clone());
+                Ok(())
+            })
+        }
+    }
+
+    async fn call(layer: &MeteringLayer, principal: Option<&str>, body: &'static str) {
+        let mut request = Request::new(Body::from(body));
+        if let Some(principal) = principal {
+            RequestStore::from_extensions_mut(request.extensions_mut()).insert(Principal(principal.to_string()));
+        }
+
+        layer
+            .layer(tower::service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("0123456789")))
+            }))
+            .oneshot(request)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metering_layer() {
+        let sink = Arc::new(MemorySink::default());
+        let meter = Meter::new(Arc::clone(&sink));
+        let layer = MeteringLayer::new(meter.clone());
+
+        call(&layer, Some("alice"), "hello").await;
+        call(&layer, Some("alice"), "").await;
+        call(&layer, Some("bob"), "hi").await;
+        call(&layer, None, "anonymous").await;
+
+        assert_eq!(
+            meter.pending(),
+            vec![
+                Usage {
+                    principal: "alice".to_string(),
+                    requests: 2,
+                    request_bytes: 5,
+                    response_bytes: 20,
+                },
+                Usage {
+                    principal: "bob".to_string(),
+                    requests: 1,
+                    request_bytes: 2,
+                    response_bytes: 10,
+                },
+            ]
+        );
+
+        meter.flush().await.unwrap();
+        assert!(meter.pending().is_empty());
+        let reports = sink.reports.lock().unwrap().clone();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].usages.len(), 2);
+
+        // Nothing to send
+        meter.flush().await.unwrap();
+        assert_eq!(sink.reports.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_meter_keeps_the_usage_on_sink_failure() {
+        let sink = Arc::new(MemorySink::default());
+        let meter = Meter::new(Arc::clone(&sink));
+
+        meter.record("alice", 10, 100);
+        sink.failing.store(true, Ordering::SeqCst);
+        assert!(meter.flush().await.is_err());
+
+        meter.record("alice", 5, 50);
+        sink.failing.store(false, Ordering::SeqCst);
+        meter.flush().await.unwrap();
+
+        let reports = sink.reports.lock().unwrap().clone();
+        assert_eq!(
+            reports[0].usages,
+            vec![Usage {
+                principal: "alice".to_string(),
+                requests: 2,
+                request_bytes: 15,
+                response_bytes: 150,
+            }]
+        );
+    }
+}
@@ -1,7 +1,31 @@
 //! Axum server
 
+pub mod app_state;
+pub mod config;
+pub mod config_watcher;
+#[cfg(feature = "metrics")]
+pub mod exporters;
 pub mod extractors;
+pub mod feature_flags;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hal;
 pub mod handlers;
+pub mod i18n;
+pub mod jobs;
+#[cfg(feature = "jsonapi")]
+pub mod jsonapi;
 pub mod layers;
+pub mod metering;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod pool;
+pub mod redaction;
+pub mod request_store;
 pub mod response;
+pub mod router;
+pub mod scheduler;
 pub mod security;
+pub mod service_registry;
+pub mod shutdown;
+pub mod tasks;
@@ -0,0 +1,347 @@
+//! OpenAPI schemas (`utoipa`)
+//!
+//! This module documents the standard error envelope of the toolkit, so that it does not have to
+//! be declared again in the OpenAPI specifications:
+//! - [`ApiErrorDoc`]: schema of the `ApiError` responses (`{ "code": 404, "message": "...", "trace_id": "..." }`)
+//! - [`ValidationErrorsDoc`]: schema of the `ValidationErrors` responses (the message is the list of
+//!   field errors)
+//! - [`ApiErrorResponses`]: the common error responses (`400`, `401`, `403`, `404`, `422`, `429`,
+//!   `500`, `503`), to use in the `responses` of `#[utoipa::path]`
+//!
+//! The value objects also implement `ToSchema`: `UtcDateTime`, `DateTimeRange`, `Email`, `Money`,
+//! `PaginationResponse`, `QuerySort`, `FieldError` and `Id<T>` (`typed-id` feature).
+//!
+//! ```rust
+//! use api_tools::server::axum::openapi::{ApiErrorDoc, ApiErrorResponses, ValidationErrorsDoc};
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use utoipa::OpenApi;
+//!
+//! /// Get the creation date of a user
+//! #[utoipa::path(
+//!     get,
+//!     path = "/users/{id}/created-at",
+//!     responses((status = 200, body = UtcDateTime), ApiErrorResponses)
+//! )]
+//! async fn created_at() {}
+//!
+//! #[derive(OpenApi)]
+//! #[openapi(paths(created_at), components(schemas(ApiErrorDoc, ValidationErrorsDoc)))]
+//! struct ApiDoc;
+//!
+//! let spec = ApiDoc::openapi().to_json().unwrap();
+//! assert!(spec.contains("ApiErrorDoc"));
+//! ```
+
+use crate::server::axum::response::FieldError;
+use crate::value_objects::datetime::UtcDateTime;
+use crate::value_objects::datetime_range::DateTimeRange;
+use crate::value_objects::email::Email;
+use crate::value_objects::money::Money;
+#[cfg(feature = "typed-id")]
+use crate::value_objects::typed_id::{Id, IdKind};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, Schema, SchemaFormat, Type};
+use utoipa::openapi::{ContentBuilder, Ref, RefOr, Response, ResponseBuilder};
+use utoipa::{IntoResponses, PartialSchema, ToSchema};
+
+/// Standard error envelope of the `ApiError` responses
+#[derive(Debug, Clone, PartialEq, ToSchema)]
+pub struct ApiErrorDoc {
+    /// HTTP status code
+    #[schema(example = 404)]
+    pub code: u16,
+
+    /// Error message
+    #[schema(example = "User not found")]
+    pub message: String,
+
+    /// OpenTelemetry trace ID, when available
+    #[schema(example = "4bf92f3577b34da6a3ce929d0e0e4736")]
+    pub trace_id: Option<String>,
+}
+
+impl ApiErrorDoc {
+    /// OpenAPI response with the standard error envelope
+    pub fn response(description: &str) -> Response {
+        json_response::<Self>(description)
+    }
+
+    /// OpenAPI responses of some status codes with the standard error envelope (`422` uses the
+    /// [`ValidationErrorsDoc`] envelope)
+    pub fn responses(statuses: &[u16]) -> BTreeMap<String, RefOr<Response>> {
+        statuses
+            .iter()
+            .map(|status| {
+                let response = match status {
+                    422 => ValidationErrorsDoc::response(status_description(*status)),
+                    _ => Self::response(status_description(*status)),
+                };
+                (status.to_string(), response.into())
+            })
+            .collect()
+    }
+}
+
+/// Envelope of the `ValidationErrors` responses (`422 Unprocessable Entity`)
+#[derive(Debug, Clone, PartialEq, ToSchema)]
+pub struct ValidationErrorsDoc {
+    /// HTTP status code
+    #[schema(example = 422)]
+    pub code: u16,
+
+    /// Field errors
+    pub message: Vec<FieldError>,
+
+    /// OpenTelemetry trace ID, when available
+    pub trace_id: Option<String>,
+}
+
+impl ValidationErrorsDoc {
+    /// OpenAPI response with the validation errors envelope
+    pub fn response(description: &str) -> Response {
+        json_response::<Self>(description)
+    }
+}
+
+/// Common error responses (`400`, `401`, `403`, `404`, `422`, `429`, `500`, `503`)
+pub struct ApiErrorResponses;
+
+impl IntoResponses for ApiErrorResponses {
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        ApiErrorDoc::responses(&[400, 401, 403, 404, 422, 429, 500, 503])
+    }
+}
+
+/// JSON response referencing the schema of `T`
+fn json_response<T: ToSchema>(description: &str) -> Response {
+    ResponseBuilder::new()
+        .description(description)
+        .content(
+            mime::APPLICATION_JSON.as_ref(),
+            ContentBuilder::new()
+                .schema(Some(Ref::from_schema_name(T::name())))
+                .build(),
+        )
+        .build()
+}
+
+/// Description of an error status code
+fn status_description(status: u16) -> &'static str {
+    axum::http::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Error")
+}
+
+/// String schema with a format
+fn string_schema(format: SchemaFormat, description: &str) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .format(Some(format))
+        .description(Some(description))
+        .into()
+}
+
+impl PartialSchema for UtcDateTime {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::Object)
+            .description(Some("Date time in UTC"))
+            .property(
+                "value",
+                string_schema(SchemaFormat::KnownFormat(KnownFormat::DateTime), "RFC 3339 date time"),
+            )
+            .required("value")
+            .into()
+    }
+}
+
+impl ToSchema for UtcDateTime {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("UtcDateTime")
+    }
+}
+
+impl PartialSchema for DateTimeRange {
+    fn schema() -> RefOr<Schema> {
+        let date_time = || string_schema(SchemaFormat::KnownFormat(KnownFormat::DateTime), "RFC 3339 date time");
+
+        ObjectBuilder::new()
+            .schema_type(Type::Object)
+            .description(Some("Date time range (`[start, end)`) in UTC"))
+            .property("start", date_time())
+            .required("start")
+            .property("end", date_time())
+            .required("end")
+            .into()
+    }
+}
+
+impl ToSchema for DateTimeRange {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("DateTimeRange")
+    }
+}
+
+impl PartialSchema for Email {
+    fn schema() -> RefOr<Schema> {
+        string_schema(SchemaFormat::KnownFormat(KnownFormat::Email), "Email address")
+    }
+}
+
+impl ToSchema for Email {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("Email")
+    }
+}
+
+impl PartialSchema for Money {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::Object)
+            .description(Some("Amount of money in a currency"))
+            .property(
+                "amount",
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .description(Some("Decimal amount"))
+                    .examples(["12.50"]),
+            )
+            .required("amount")
+            .property(
+                "currency",
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .description(Some("ISO 4217 currency code"))
+                    .examples(["EUR"]),
+            )
+            .required("currency")
+            .into()
+    }
+}
+
+impl ToSchema for Money {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("Money")
+    }
+}
+
+#[cfg(feature = "typed-id")]
+impl<T: IdKind> PartialSchema for Id<T> {
+    fn schema() -> RefOr<Schema> {
+        let (pattern, example) = match T::PREFIX {
+            "" => (
+                "^[0-9a-f]{32}$".to_string(),
+                "9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".to_string(),
+            ),
+            prefix => (
+                format!("^{prefix}_[0-9a-f]{{32}}$"),
+                format!("{prefix}_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b"),
+            ),
+        };
+
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("Prefixed UUID"))
+            .pattern(Some(pattern))
+            .examples([example])
+            .into()
+    }
+}
+
+#[cfg(feature = "typed-id")]
+impl<T: IdKind> ToSchema for Id<T> {
+    /// Name of the kind followed by `Id` (e.g. `UserId`)
+    fn name() -> Cow<'static, str> {
+        let kind = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+        Cow::Owned(format!("{kind}Id"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value_objects::pagination::PaginationResponse;
+    use crate::value_objects::query_sort::QuerySort;
+    use serde_json::Value;
+    use utoipa::OpenApi;
+
+    #[utoipa::path(
+        get,
+        path = "/users/{id}",
+        responses((status = 200, body = Email), ApiErrorResponses)
+    )]
+    #[allow(dead_code)]
+    async fn get_user() {}
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(get_user),
+        components(schemas(
+            ApiErrorDoc,
+            ValidationErrorsDoc,
+            UtcDateTime,
+            DateTimeRange,
+            Money,
+            PaginationResponse,
+            QuerySort
+        ))
+    )]
+    struct ApiDoc;
+
+    #[test]
+    fn test_openapi_schemas() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = &spec["components"]["schemas"];
+
+        assert_eq!(
+            schemas["ApiErrorDoc"]["required"],
+            serde_json::json!(["code", "message"])
+        );
+        assert_eq!(
+            schemas["ValidationErrorsDoc"]["properties"]["message"]["items"]["$ref"],
+            "#/components/schemas/FieldError"
+        );
+        assert_eq!(schemas["FieldError"]["type"], "object");
+        assert_eq!(schemas["UtcDateTime"]["properties"]["value"]["format"], "date-time");
+        assert_eq!(schemas["Money"]["required"], serde_json::json!(["amount", "currency"]));
+        assert_eq!(schemas["PaginationResponse"]["properties"]["total"]["format"], "int64");
+        assert!(schemas["QuerySortDirection"].is_object());
+
+        let responses = &spec["paths"]["/users/{id}"]["get"]["responses"];
+        assert_eq!(
+            responses["404"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ApiErrorDoc"
+        );
+        assert_eq!(responses["404"]["description"], "Not Found");
+        assert_eq!(
+            responses["422"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ValidationErrorsDoc"
+        );
+        assert_eq!(
+            responses["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Email"
+        );
+        assert!(matches!(responses["503"], Value::Object(_)));
+    }
+
+    #[cfg(feature = "typed-id")]
+    #[test]
+    fn test_typed_id_schema() {
+        struct User;
+
+        impl IdKind for User {
+            const PREFIX: &'static str = "usr";
+        }
+
+        assert_eq!(Id::<User>::name(), "UserId");
+        let schema = serde_json::to_value(Id::<User>::schema()).unwrap();
+        assert_eq!(schema["type"], "string");
+        assert_eq!(schema["pattern"], "^usr_[0-9a-f]{32}$");
+        assert_eq!(
+            schema["examples"],
+            serde_json::json!(["usr_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b"])
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Health-checked connection pools
+//!
+//! [`CheckedPool`] wraps a pool of connections (a [`CheckedResource`]) to:
+//! - probe its health periodically ([`CheckedPool::spawn_health_probe`]): a connection is
+//!   acquired and pinged, the result is available with [`CheckedPool::health`]
+//! - measure the time waiting for a connection ([`CheckedPool::acquire`])
+//! - publish the pool gauges (`metrics` feature): `pool_connections`, `pool_connections_in_use`,
+//!   `pool_connections_max`, `pool_wait_seconds` (last acquisition) and `pool_healthy` (1 or 0),
+//!   with a `pool` label
+//!
+//! Adapters are provided for `sqlx::Pool` (`pool-sqlx` feature) and the Redis
+//! `ConnectionManager` (`pool-redis` feature).
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::pool::{CheckedPool, CheckedResource};
+//! use std::time::Duration;
+//!
+//! # async fn run(db: impl CheckedResource) {
+//! let pool = CheckedPool::new("postgres", db);
+//! let probe = pool.spawn_health_probe(Duration::from_secs(10));
+//!
+//! let mut connection = pool.acquire().await.unwrap();
+//! // ...
+//! assert!(pool.health().healthy);
+//! # }
+//! ```
+
+use crate::value_objects::datetime::UtcDateTime;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// Default timeout of a health check
+pub const POOL_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PoolError {
+    #[error("Pool acquire error: {0}")]
+    Acquire(String),
+
+    #[error("Pool health check error: {0}")]
+    Check(String),
+
+    #[error("Pool health check timeout")]
+    Timeout,
+}
+
+/// Connections of a pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PoolState {
+    /// Open connections
+    pub size: u32,
+
+    /// Idle connections
+    pub idle: u32,
+
+    /// Maximum number of connections, if known
+    pub max_size: Option<u32>,
+}
+
+impl PoolState {
+    /// Connections in use
+    pub fn in_use(&self) -> u32 {
+        self.size.saturating_sub(self.idle)
+    }
+}
+
+/// Pool of connections which can be checked
+pub trait CheckedResource: Send + Sync + 'static {
+    /// Connection acquired from the pool
+    type Connection: Send;
+
+    /// Acquire a connection
+    fn acquire(&self) -> BoxFuture<'_, Result<Self::Connection, PoolError>>;
+
+    /// Check that a connection works (e.g. `SELECT 1`, `PING`)
+    fn ping<'a>(&'a self, connection: &'a mut Self::Connection) -> BoxFuture<'a, Result<(), PoolError>>;
+
+    /// Current connections of the pool
+    fn state(&self) -> PoolState;
+}
+
+/// Health of a pool, updated by the health checks
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PoolHealth {
+    /// Result of the last check (`true` before the first check)
+    pub healthy: bool,
+
+    /// Date of the last check
+    pub checked_at: Option<UtcDateTime>,
+
+    /// Duration of the last check in milliseconds
+    pub latency_ms: Option<u64>,
+
+    /// Error of the last check
+    pub error: Option<String>,
+
+    /// Number of consecutive failed checks
+    pub consecutive_failures: u32,
+
+    /// Connections of the pool at the last check
+    pub state: PoolState,
+}
+
+/// Health-checked pool
+pub struct CheckedPool<R> {
+    name: Arc<str>,
+    resource: Arc<R>,
+    check_timeout: Duration,
+    health: Arc<RwLock<PoolHealth>>,
+}
+
+impl<R> Clone for CheckedPool<R> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            resource: self.resource.clone(),
+            check_timeout: self.check_timeout,
+            health: self.health.clone(),
+        }
+    }
+}
+
+impl<R: CheckedResource> CheckedPool<R> {
+    /// Create a new pool, healthy until the first check
+    pub fn new(name: &str, resource: R) -> Self {
+        Self {
+            name: Arc::from(name),
+            resource: Arc::new(resource),
+            check_timeout: POOL_CHECK_TIMEOUT,
+            health: Arc::new(RwLock::new(PoolHealth {
+                healthy: true,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Set the timeout of a health check (default: 5s)
+    pub fn with_check_timeout(mut self, timeout: Duration) -> Self {
+        self.check_timeout = timeout;
+        self
+    }
+
+    /// Pool name (`pool` label of the metrics)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wrapped pool
+    pub fn resource(&self) -> &R {
+        &self.resource
+    }
+
+    /// Health of the last check
+    pub fn health(&self) -> PoolHealth {
+        self.health.read().map(|health| health.clone()).unwrap_or_default()
+    }
+
+    /// Acquire a connection, publishing the time waited for it
+    pub async fn acquire(&self) -> Result<R::Connection, PoolError> {
+        let start = Instant::now();
+        let connection = self.resource.acquire().await;
+        self.publish_wait(start.elapsed());
+
+        connection
+    }
+
+    /// Check the pool health (acquire a connection and ping it) and publish the gauges
+    pub async fn check(&self) -> PoolHealth {
+        let start = Instant::now();
+        let result = tokio::time::timeout(self.check_timeout, async {
+            let mut connection = self.acquire().await?;
+            self.resource.ping(&mut connection).await
+        })
+        .await
+        .unwrap_or(Err(PoolError::Timeout));
+        let state = self.resource.state();
+
+        let health = match self.health.write() {
+            Ok(mut health) => {
+                health.healthy = result.is_ok();
+                health.checked_at = Some(UtcDateTime::now());
+                health.latency_ms = Some(start.elapsed().as_millis() as u64);
+                health.state = state;
+                match &result {
+                    Ok(()) => {
+                        health.error = None;
+                        health.consecutive_failures = 0;
+                    }
+                    Err(err) => {
+                        health.error = Some(err.to_string());
+                        health.consecutive_failures += 1;
+                    }
+                }
+                health.clone()
+            }
+            Err(_) => self.health(),
+        };
+        if let Err(err) = &result {
+            warn!(pool = %self.name, error = %err, "Pool health check failed");
+        }
+        self.publish(&health);
+
+        health
+    }
+
+    /// Check the pool health at each interval on a background task
+    pub fn spawn_health_probe(&self, interval: Duration) -> JoinHandle<()> {
+        let pool = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                pool.check().await;
+            }
+        })
+    }
+
+    /// Publish the pool gauges
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn publish(&self, health: &PoolHealth) {
+        #[cfg(feature = "metrics")]
+        {
+            let name = self.name.to_string();
+            metrics::gauge!("pool_connections", "pool" => name.clone()).set(f64::from(health.state.size));
+            metrics::gauge!("pool_connections_in_use", "pool" => name.clone()).set(f64::from(health.state.in_use()));
+            if let Some(max_size) = health.state.max_size {
+                metrics::gauge!("pool_connections_max", "pool" => name.clone()).set(f64::from(max_size));
+            }
+            metrics::gauge!("pool_healthy", "pool" => name).set(if health.healthy { 1.0 } else { 0.0 });
+        }
+    }
+
+    /// Publish the time waited for a connection
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn publish_wait(&self, wait: Duration) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("pool_wait_seconds", "pool" => self.name.to_string()).set(wait.as_secs_f64());
+    }
+}
+
+#[cfg(feature = "pool-sqlx")]
+impl<DB: sqlx::Database> CheckedResource for sqlx::Pool<DB> {
+    type Connection = sqlx::pool::PoolConnection<DB>;
+
+    fn acquire(&self) -> BoxFuture<'_, Result<Self::Connection, PoolError>> {
+        Box::pin(async move {
+            sqlx::Pool::acquire(self)
+                .await
+                .map_err(|err| PoolError::Acquire(err.to_string()))
+        })
+    }
+
+    fn ping<'a>(&'a self, connection: &'a mut Self::Connection) -> BoxFuture<'a, Result<(), PoolError>> {
+        use sqlx::Connection;
+
+        Box::pin(async move { connection.ping().await.map_err(|err| PoolError::Check(err.to_string())) })
+    }
+
+    fn state(&self) -> PoolState {
+        PoolState {
+            size: self.size(),
+            idle: self.num_idle() as u32,
+            max_size: Some(self.options().get_max_connections()),
+        }
+    }
+}
+
+/// The Redis `ConnectionManager` multiplexes the commands on a single connection
+#[cfg(feature = "pool-redis")]
+impl CheckedResource for redis::aio::ConnectionManager {
+    type Connection = redis::aio::ConnectionManager;
+
+    fn acquire(&self) -> BoxFuture<'_, Result<Self::Connection, PoolError>> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+
+    fn ping<'a>(&'a self, connection: &'a mut Self::Connection) -> BoxFuture<'a, Result<(), PoolError>> {
+        Box::pin(async move {
+            redis::cmd("PING")
+                .query_async::<String>(connection)
+                .await
+                .map(|_| ())
+                .map_err(|err| PoolError::Check(err.to_string()))
+        })
+    }
+
+    fn state(&self) -> PoolState {
+        PoolState {
+            size: 1,
+            idle: 1,
+            max_size: Some(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    #[derive(Default)]
+    struct FakePool {
+        down: AtomicBool,
+        in_use: AtomicU32,
+    }
+
+    impl CheckedResource for FakePool {
+        type Connection = ();
+
+        fn acquire(&self) -> BoxFuture<'_, Result<Self::Connection, PoolError>> {
+            Box::pin(async move {
+                self.in_use.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        }
+
+        fn ping<'a>(&'a self, _connection: &'a mut Self::Connection) -> BoxFuture<'a, Result<(), PoolError>> {
+            Box::pin(async move {
+                if self.down.load(Ordering::SeqCst) {
+                    return Err(PoolError::Check("connection refused".to_string()));
+                }
+                Ok(())
+            })
+        }
+
+        fn state(&self) -> PoolState {
+            PoolState {
+                size: 4,
+                idle: 4 - self.in_use.load(Ordering::SeqCst),
+                max_size: Some(10),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checked_pool_health() {
+        let pool = CheckedPool::new("fake", FakePool::default());
+        assert!(pool.health().healthy);
+        assert_eq!(pool.health().checked_at, None);
+
+        let health = pool.check().await;
+        assert!(health.healthy);
+        assert_eq!(health.state.in_use(), 1);
+        assert_eq!(health.state.max_size, Some(10));
+
+        pool.resource().down.store(true, Ordering::SeqCst);
+        pool.check().await;
+        let health = pool.check().await;
+        assert!(!health.healthy);
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(
+            health.error.as_deref(),
+            Some("Pool health check error: connection refused")
+        );
+        assert_eq!(pool.health(), health);
+
+        pool.resource().down.store(false, Ordering::SeqCst);
+        let health = pool.check().await;
+        assert!(health.healthy);
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_checked_pool_probe() {
+        let pool = CheckedPool::new("fake", FakePool::default());
+        let probe = pool.spawn_health_probe(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        probe.abort();
+
+        assert!(pool.health().checked_at.is_some());
+        assert!(pool.resource().in_use.load(Ordering::SeqCst) > 1);
+    }
+
+    #[cfg(feature = "pool-sqlx")]
+    #[tokio::test]
+    async fn test_checked_pool_sqlx() {
+        let db = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let pool = CheckedPool::new("sqlite", db);
+
+        let connection = pool.acquire().await.unwrap();
+        assert_eq!(pool.resource().state().in_use(), 1);
+        drop(connection);
+
+        let health = pool.check().await;
+        assert!(health.healthy, "{health:?}");
+        assert!(health.state.size >= 1);
+        assert_eq!(health.state.max_size, Some(10));
+    }
+
+    #[cfg(feature = "pool-redis")]
+    #[tokio::test]
+    async fn test_checked_pool_redis() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Fake Redis server answering `+PONG` to `PING` and `+OK` to the other commands
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0; 4_096];
+                    while let Ok(size) = socket.read(&mut buffer).await {
+                        if size == 0 {
+                            break;
+                        }
+                        let request = String::from_utf8_lossy(&buffer[..size]).to_string();
+                        let lines = request.split("\r\n").collect::<Vec<_>>();
+                        for (index, line) in lines.iter().enumerate() {
+                            if line.starts_with('*') {
+                                let reply = match lines.get(index + 2) {
+                                    Some(command) if command.eq_ignore_ascii_case("PING") => "+PONG\r\n",
+                                    _ => "+OK\r\n",
+                                };
+                                socket.write_all(reply.as_bytes()).await.unwrap();
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let client = redis::Client::open(url).unwrap();
+        let manager = redis::aio::ConnectionManager::new(client).await.unwrap();
+        let pool = CheckedPool::new("redis", manager);
+
+        let health = pool.check().await;
+        assert!(health.healthy, "{health:?}");
+        assert_eq!(health.state.in_use(), 0);
+    }
+}
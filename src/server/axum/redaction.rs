@@ -0,0 +1,321 @@
+//! Redaction of sensitive values
+//!
+//! A [`RedactionPolicy`] lists the sensitive header names, field names (case-insensitive
+//! substrings), JSON pointers and regex patterns whose values are replaced by [`REDACTED_VALUE`].
+//! The policy installed with [`set_redaction_policy`] (or the default one) is shared by the crate:
+//! - `LoggerLayer`: query parameters of the logged URI
+//! - `ConfigWatcher`: default redacted fields of the audit diffs
+//! - `ApiError` responses and `CatchPanicLayer` logs: patterns in the error messages
+//! - `HttpClient` (`client` feature): query parameters of the logged URLs
+//!
+//! ```rust
+//! use api_tools::server::axum::redaction::{RedactionPolicy, set_redaction_policy};
+//! use serde_json::json;
+//!
+//! let policy = set_redaction_policy(
+//!     RedactionPolicy::new()
+//!         .with_header("x-internal-key")
+//!         .with_field("ssn")
+//!         .with_pointer("/card/number")
+//!         .with_pattern(r"\b\d{4}-\d{4}-\d{4}-\d{4}\b")
+//!         .unwrap(),
+//! );
+//!
+//! let mut body = json!({"name": "John", "password": "secret", "card": {"number": "4242"}});
+//! policy.redact_json(&mut body);
+//! assert_eq!(body, json!({"name": "John", "password": "***", "card": {"number": "***"}}));
+//! assert_eq!(policy.redact_uri("/login?user=john&token=abc"), "/login?user=john&token=***");
+//! ```
+
+use axum::http::HeaderMap;
+use regex::Regex;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Value of the redacted fields
+pub const REDACTED_VALUE: &str = "***";
+
+/// Default sensitive headers
+const DEFAULT_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Default sensitive fields (case-insensitive substrings of the field names)
+const DEFAULT_FIELDS: [&str; 5] = ["password", "secret", "token", "api_key", "apikey"];
+
+/// Default sensitive patterns (bearer tokens)
+const DEFAULT_PATTERNS: [&str; 1] = [r"(?i)\bbearer\s+[a-z0-9\-._~+/]+=*"];
+
+/// Shared redaction policy
+static REDACTION_POLICY: OnceLock<RedactionPolicy> = OnceLock::new();
+
+/// Install the redaction policy shared by the layers
+///
+/// Only the first call (before any use of the policy) configures it, so it must be called at
+/// startup.
+pub fn set_redaction_policy(policy: RedactionPolicy) -> &'static RedactionPolicy {
+    REDACTION_POLICY.get_or_init(|| policy)
+}
+
+/// Get the shared redaction policy ([`RedactionPolicy::new`] if it is not set)
+pub fn redaction_policy() -> &'static RedactionPolicy {
+    REDACTION_POLICY.get_or_init(RedactionPolicy::new)
+}
+
+/// Redaction error
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RedactionError {
+    #[error("Invalid redaction pattern: {0}")]
+    InvalidPattern(String),
+}
+
+/// Redaction rules
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    headers: Vec<String>,
+    fields: Vec<String>,
+    pointers: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_HEADERS.iter().map(|header| header.to_string()).collect(),
+            fields: DEFAULT_FIELDS.iter().map(|field| field.to_string()).collect(),
+            pointers: Vec::new(),
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .filter_map(|pattern| Regex::new(pattern).ok())
+                .collect(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// Create a new policy with the default rules (`Authorization`, `Cookie`, `Set-Cookie` and
+    /// `X-Api-Key` headers, `password`, `secret`, `token` and `api_key` fields, bearer tokens)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new policy without rules
+    pub fn empty() -> Self {
+        Self {
+            headers: Vec::new(),
+            fields: Vec::new(),
+            pointers: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Add a sensitive header
+    pub fn with_header(mut self, name: &str) -> Self {
+        self.headers.push(name.to_lowercase());
+        self
+    }
+
+    /// Add a sensitive field (case-insensitive substring of the field names)
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.fields.push(field.to_lowercase());
+        self
+    }
+
+    /// Add a sensitive JSON pointer (e.g. `/card/number`)
+    pub fn with_pointer(mut self, pointer: &str) -> Self {
+        self.pointers.push(pointer.to_string());
+        self
+    }
+
+    /// Add a sensitive regex pattern, redacted in the strings
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, RedactionError> {
+        let regex = Regex::new(pattern).map_err(|err| RedactionError::InvalidPattern(err.to_string()))?;
+        self.patterns.push(regex);
+
+        Ok(self)
+    }
+
+    /// Sensitive fields
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Check if a header is sensitive
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|header| header.eq_ignore_ascii_case(name))
+    }
+
+    /// Check if a field is sensitive
+    pub fn is_sensitive_field(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.fields.iter().any(|field| name.contains(field.as_str()))
+    }
+
+    /// Redact the patterns of a string
+    pub fn redact_str<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(value);
+        for pattern in &self.patterns {
+            if let Cow::Owned(redacted) = pattern.replace_all(&value, REDACTED_VALUE) {
+                value = Cow::Owned(redacted);
+            }
+        }
+
+        value
+    }
+
+    /// Header values, with the sensitive headers redacted
+    pub fn redact_headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match self.is_sensitive_header(name.as_str()) {
+                    true => REDACTED_VALUE.to_string(),
+                    false => self.redact_str(value.to_str().unwrap_or_default()).into_owned(),
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Redact the sensitive query parameters and the patterns of a URI
+    pub fn redact_uri<'a>(&self, uri: &'a str) -> Cow<'a, str> {
+        let uri = match uri.split_once('?') {
+            Some((path, query)) if query.split('&').any(|param| self.is_sensitive_param(param)) => {
+                let query = query
+                    .split('&')
+                    .map(|param| match self.is_sensitive_param(param) {
+                        true => Cow::Owned(format!(
+                            "{}={REDACTED_VALUE}",
+                            param.split('=').next().unwrap_or_default()
+                        )),
+                        false => Cow::Borrowed(param),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&");
+                Cow::Owned(format!("{path}?{query}"))
+            }
+            _ => Cow::Borrowed(uri),
+        };
+
+        match self.redact_str(&uri) {
+            Cow::Owned(redacted) => Cow::Owned(redacted),
+            Cow::Borrowed(_) => uri,
+        }
+    }
+
+    /// Redact the sensitive fields, the JSON pointers and the patterns of a JSON value
+    pub fn redact_json(&self, value: &mut Value) {
+        for pointer in &self.pointers {
+            if let Some(value) = value.pointer_mut(pointer) {
+                *value = Value::String(REDACTED_VALUE.to_string());
+            }
+        }
+        self.redact_json_value(value);
+    }
+
+    fn redact_json_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (name, value) in fields.iter_mut() {
+                    if self.is_sensitive_field(name) {
+                        *value = Value::String(REDACTED_VALUE.to_string());
+                    } else {
+                        self.redact_json_value(value);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact_json_value(value)),
+            Value::String(string) => {
+                if let Cow::Owned(redacted) = self.redact_str(string) {
+                    *string = redacted;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Check if a query parameter (`name=value`) is sensitive
+    fn is_sensitive_param(&self, param: &str) -> bool {
+        param
+            .split_once('=')
+            .is_some_and(|(name, value)| !value.is_empty() && self.is_sensitive_field(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_redaction_policy_default_rules() {
+        let policy = RedactionPolicy::new();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Basic dXNlcjpwYXNz"));
+        headers.insert("set-cookie", HeaderValue::from_static("session=abc"));
+        headers.insert("x-trace", HeaderValue::from_static("Bearer abc.def"));
+        headers.insert("accept", HeaderValue::from_static("application/json"));
+        let mut headers = policy.redact_headers(&headers);
+        headers.sort();
+        assert_eq!(
+            headers,
+            vec![
+                ("accept".to_string(), "application/json".to_string()),
+                ("authorization".to_string(), "***".to_string()),
+                ("set-cookie".to_string(), "***".to_string()),
+                ("x-trace".to_string(), "***".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            policy.redact_uri("/users?page=1&access_token=abc&Api_Key=def&token="),
+            "/users?page=1&access_token=***&Api_Key=***&token="
+        );
+        assert!(matches!(policy.redact_uri("/users?page=1"), Cow::Borrowed(_)));
+        assert_eq!(
+            policy.redact_str("invalid header: Bearer eyJ.a.b"),
+            "invalid header: ***"
+        );
+
+        let mut value = json!({"user": {"Password": "p", "tokens": ["a"], "name": "John"}, "list": [{"secret": 1}]});
+        policy.redact_json(&mut value);
+        assert_eq!(
+            value,
+            json!({"user": {"Password": "***", "tokens": "***", "name": "John"}, "list": [{"secret": "***"}]})
+        );
+    }
+
+    #[test]
+    fn test_redaction_policy_custom_rules() {
+        let policy = RedactionPolicy::empty()
+            .with_header("X-Internal")
+            .with_field("ssn")
+            .with_pointer("/cards/0/number")
+            .with_pattern(r"\d{3}-\d{2}-\d{4}")
+            .unwrap();
+
+        assert!(policy.is_sensitive_header("x-internal"));
+        assert!(!policy.is_sensitive_header("authorization"));
+
+        let mut value =
+            json!({"ssn_number": "1", "cards": [{"number": "4242"}], "note": "ssn 123-45-6789", "password": "p"});
+        policy.redact_json(&mut value);
+        assert_eq!(
+            value,
+            json!({"ssn_number": "***", "cards": [{"number": "***"}], "note": "ssn ***", "password": "p"})
+        );
+
+        assert!(matches!(
+            RedactionPolicy::new().with_pattern("("),
+            Err(RedactionError::InvalidPattern(_))
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! Request-scoped storage shared between layers and handlers
+//!
+//! [`RequestStore`] is a typed map stored in the request extensions. The crate's
+//! own layers write well-known values in it ([`Principal`] by the authentication
+//! layers, [`Tenant`] by the `JwtAuthLayer`, [`Locale`] by the `LocaleLayer` and
+//! [`Deadline`] by the `DeadlineLayer`) and handlers read them back through one accessor:
+//!
+//! ```rust
+//! use api_tools::server::axum::request_store::{Principal, RequestStore};
+//!
+//! async fn handler(store: RequestStore) -> String {
+//!     store
+//!         .get::<Principal>()
+//!         .map(|principal| principal.0.clone())
+//!         .unwrap_or_default()
+//! }
+//! ```
+//!
+//! User layers write in the store with [`RequestStore::from_extensions_mut`]:
+//!
+//! ```rust
+//! use api_tools::server::axum::request_store::{RequestStore, Tenant};
+//! use axum::http::Request;
+//!
+//! let mut request = Request::new(());
+//! RequestStore::from_extensions_mut(request.extensions_mut()).insert(Tenant("acme".to_string()));
+//!
+//! let store = RequestStore::from_extensions(request.extensions()).unwrap();
+//! assert_eq!(store.get::<Tenant>(), Some(&Tenant("acme".to_string())));
+//! ```
+
+use axum::extract::FromRequestParts;
+use axum::http::Extensions;
+use axum::http::request::Parts;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Authenticated principal (user ID, username, client ID, etc.)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+/// Tenant of the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant(pub String);
+
+/// Locale of the request (e.g. `fr-FR`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale(pub String);
+
+/// Instant after which the request should be abandoned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(pub Instant);
+
+impl Deadline {
+    /// Return true if the deadline is exceeded
+    pub fn is_exceeded(&self) -> bool {
+        Instant::now() >= self.0
+    }
+}
+
+/// Typed map of request-scoped values
+#[derive(Clone, Default)]
+pub struct RequestStore {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Debug for RequestStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RequestStore => values: {}", self.values.len())
+    }
+}
+
+impl RequestStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the store from request extensions
+    pub fn from_extensions(extensions: &Extensions) -> Option<&Self> {
+        extensions.get::<Self>()
+    }
+
+    /// Get the store from request extensions, creating it if needed
+    pub fn from_extensions_mut(extensions: &mut Extensions) -> &mut Self {
+        if extensions.get::<Self>().is_none() {
+            extensions.insert(Self::new());
+        }
+        extensions.get_mut::<Self>().expect("request store just inserted")
+    }
+
+    /// Insert a value, returning the previous one of the same type if any
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<Arc<T>> {
+        self.values
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .and_then(|previous| previous.downcast::<T>().ok())
+    }
+
+    /// Get a value
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// Remove a value
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Arc<T>> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    /// Return true if a value of type `T` is stored
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Number of stored values
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Return true if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Request store extractor
+///
+/// Returns an empty store if no layer has written in it.
+impl<S> FromRequestParts<S> for RequestStore
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_extensions(&parts.extensions).cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn insert_get_and_remove_values() {
+        let mut store = RequestStore::new();
+        assert!(store.is_empty());
+
+        assert!(store.insert(Principal("user".to_string())).is_none());
+        store.insert(Tenant("acme".to_string()));
+        assert_eq!(store.len(), 2);
+        assert!(store.contains::<Principal>());
+        assert_eq!(store.get::<Tenant>(), Some(&Tenant("acme".to_string())));
+        assert!(store.get::<Locale>().is_none());
+
+        let previous = store.insert(Principal("admin".to_string())).unwrap();
+        assert_eq!(*previous, Principal("user".to_string()));
+        assert_eq!(store.get::<Principal>(), Some(&Principal("admin".to_string())));
+
+        assert_eq!(*store.remove::<Tenant>().unwrap(), Tenant("acme".to_string()));
+        assert!(!store.contains::<Tenant>());
+        assert_eq!(format!("{store:?}"), "RequestStore => values: 1");
+    }
+
+    #[test]
+    fn from_extensions_mut_creates_the_store_once() {
+        let mut extensions = Extensions::new();
+        assert!(RequestStore::from_extensions(&extensions).is_none());
+
+        RequestStore::from_extensions_mut(&mut extensions).insert(Locale("fr-FR".to_string()));
+        RequestStore::from_extensions_mut(&mut extensions).insert(Tenant("acme".to_string()));
+
+        let store = RequestStore::from_extensions(&extensions).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get::<Locale>(), Some(&Locale("fr-FR".to_string())));
+    }
+
+    #[test]
+    fn deadline_is_exceeded() {
+        assert!(Deadline(Instant::now()).is_exceeded());
+        assert!(!Deadline(Instant::now() + Duration::from_secs(60)).is_exceeded());
+    }
+
+    #[tokio::test]
+    async fn extractor_returns_stored_values_or_empty_store() {
+        let (mut parts, _) = axum::http::Request::new(()).into_parts();
+        let store = RequestStore::from_request_parts(&mut parts, &()).await.unwrap();
+        assert!(store.is_empty());
+
+        RequestStore::from_extensions_mut(&mut parts.extensions).insert(Principal("user".to_string()));
+        let store = RequestStore::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(store.get::<Principal>(), Some(&Principal("user".to_string())));
+    }
+}
@@ -1,39 +1,128 @@
 //! API response module
 
+use crate::server::axum::layers::request_id::current_request_id;
+use crate::server::axum::redaction::redaction_policy;
+use crate::value_objects::pagination::PaginationResponse;
 use axum::Json;
-use axum::http::StatusCode;
-use axum::response::{IntoResponse, Response};
-use opentelemetry::TraceId;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, IntoResponseParts, Response, ResponseParts};
+use chrono::{DateTime, Utc};
 use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{SpanId, TraceId};
 use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::time::Duration;
 use thiserror::Error;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 /// API response success
+///
+/// The data is serialized as is, or in an envelope (`{ "data": ..., "meta": { ... } }`) when
+/// [`ApiSuccess::enveloped`] or a meta information builder (e.g. [`ApiSuccess::with_pagination`])
+/// is used. The `request_id` meta information is filled with the current request ID
+/// (`RequestIdLayer`) if it is not set.
 #[derive(Debug, Clone)]
-pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<T>);
+pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<T>, Option<ResponseMeta>);
 
 impl<T> PartialEq for ApiSuccess<T>
 where
     T: Serialize + PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0 && self.1.0 == other.1.0
+        self.0 == other.0 && self.1.0 == other.1.0 && self.2 == other.2
     }
 }
 
 impl<T: Serialize + PartialEq> ApiSuccess<T> {
     pub fn new(status: StatusCode, data: T) -> Self {
-        ApiSuccess(status, Json(data))
+        ApiSuccess(status, Json(data), None)
+    }
+
+    /// Serialize the response in an envelope (`{ "data": ..., "meta": { ... } }`)
+    pub fn enveloped(mut self) -> Self {
+        self.meta_mut();
+        self
+    }
+
+    /// Set the meta information (enables the envelope)
+    pub fn with_meta(mut self, meta: ResponseMeta) -> Self {
+        self.2 = Some(meta);
+        self
+    }
+
+    /// Set the request ID meta information (enables the envelope)
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.meta_mut().request_id = Some(request_id.to_string());
+        self
+    }
+
+    /// Set the pagination meta information (enables the envelope)
+    pub fn with_pagination(mut self, pagination: PaginationResponse) -> Self {
+        self.meta_mut().pagination = Some(pagination);
+        self
+    }
+
+    /// Set the processing duration meta information, in milliseconds (enables the envelope)
+    pub fn with_timing(mut self, duration: Duration) -> Self {
+        self.meta_mut().duration_ms = Some(duration.as_secs_f64() * 1_000.0);
+        self
+    }
+
+    /// Add a custom meta information (enables the envelope)
+    pub fn with_extra(mut self, key: &str, value: serde_json::Value) -> Self {
+        self.meta_mut().extra.insert(key.to_string(), value);
+        self
+    }
+
+    fn meta_mut(&mut self) -> &mut ResponseMeta {
+        self.2.get_or_insert_with(ResponseMeta::default)
     }
 }
 
 impl<T: Serialize + PartialEq> IntoResponse for ApiSuccess<T> {
     fn into_response(self) -> Response {
-        (self.0, self.1).into_response()
+        match self.2 {
+            Some(mut meta) => {
+                if meta.request_id.is_none() {
+                    meta.request_id = current_request_id();
+                }
+
+                (self.0, Json(ResponseEnvelope { data: self.1.0, meta })).into_response()
+            }
+            None => (self.0, self.1).into_response(),
+        }
     }
 }
 
+/// Meta information of an enveloped `ApiSuccess` response
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ResponseMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<PaginationResponse>,
+
+    /// Processing duration in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+
+    /// Custom meta information
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// Envelope of an `ApiSuccess` response
+#[derive(Serialize)]
+struct ResponseEnvelope<T: Serialize> {
+    data: T,
+    meta: ResponseMeta,
+}
+
 /// Generic response structure shared by all API responses.
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub(crate) struct ApiErrorResponse<T: Serialize + PartialEq> {
@@ -53,6 +142,89 @@ impl<T: Serialize + PartialEq> ApiErrorResponse<T> {
     }
 }
 
+/// Get the current OpenTelemetry trace ID, if any
+pub(crate) fn current_trace_id() -> Option<String> {
+    let ctx = tracing::Span::current().context();
+    let trace_id = ctx.span().span_context().trace_id();
+    if trace_id == TraceId::INVALID {
+        None
+    } else {
+        Some(trace_id.to_string())
+    }
+}
+
+/// Get the current OpenTelemetry span ID, if any
+pub(crate) fn current_span_id() -> Option<String> {
+    let ctx = tracing::Span::current().context();
+    let span_id = ctx.span().span_context().span_id();
+    if span_id == SpanId::INVALID {
+        None
+    } else {
+        Some(span_id.to_string())
+    }
+}
+
+/// Field validation error
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    /// Create a new field error
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Aggregated validation errors, returned as a `422 Unprocessable Entity` response
+///
+/// The response `message` is the list of field errors:
+/// `{ "code": 422, "message": [{ "field": "page", "message": "must be at least 1" }] }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    /// Create an empty list of errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field error
+    pub fn add(&mut self, field: &str, message: &str) {
+        self.0.push(FieldError::new(field, message));
+    }
+
+    /// Return true if there is no error
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of errors
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ApiErrorResponse::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                self.0,
+                current_trace_id(),
+            )),
+        )
+            .into_response()
+    }
+}
+
 /// API error
 #[derive(Debug, Clone, PartialEq, Error)]
 pub enum ApiError {
@@ -92,13 +264,7 @@ pub enum ApiError {
 
 impl ApiError {
     fn response(code: StatusCode, message: &str) -> impl IntoResponse + '_ {
-        let ctx = tracing::Span::current().context();
-        let trace_id = ctx.span().span_context().trace_id();
-        let trace_id = if trace_id == TraceId::INVALID {
-            None
-        } else {
-            Some(trace_id.to_string())
-        };
+        let trace_id = current_trace_id();
 
         match code {
             StatusCode::REQUEST_TIMEOUT => (
@@ -161,39 +327,493 @@ impl ApiError {
     }
 }
 
+impl ApiError {
+    /// HTTP status code of the error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Timeout => StatusCode::REQUEST_TIMEOUT,
+            ApiError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ApiError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Message sent in the response body
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::UnprocessableEntity(message)
+            | ApiError::InternalServerError(message) => message,
+            ApiError::Timeout => "Request timeout",
+            ApiError::TooManyRequests => "Too many requests",
+            ApiError::MethodNotAllowed => "Method not allowed",
+            ApiError::PayloadTooLarge => "Payload too large",
+            ApiError::ServiceUnavailable => "Service unavailable",
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let message = redaction_policy().redact_str(self.message());
+
+        Self::response(self.status_code(), &message).into_response()
+    }
+}
+
+/// Result of one item of a bulk operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MultiStatusItem<T: Serialize + PartialEq> {
+    id: String,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ApiErrorResponse<String>>,
+}
+
+impl<T: Serialize + PartialEq> MultiStatusItem<T> {
+    /// Item identifier
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Item status code
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Return true if the item failed
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+/// `207 Multi-Status` response for bulk operations
+///
+/// Each item has its own status. Failures use the standard error shape:
+///
+/// ```json
+/// {
+///   "items": [
+///     { "id": "1", "status": 201, "data": { "name": "book" } },
+///     { "id": "2", "status": 404, "error": { "code": 404, "message": "Product not found" } }
+///   ]
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```rust
+/// use api_tools::server::axum::response::{ApiError, MultiStatusResponse};
+/// use axum::http::StatusCode;
+///
+/// let mut response = MultiStatusResponse::new();
+/// response.push_success("1", StatusCode::CREATED, "book");
+/// response.push_error("2", ApiError::NotFound("Product not found".to_string()));
+/// assert!(response.has_errors());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MultiStatusResponse<T: Serialize + PartialEq> {
+    items: Vec<MultiStatusItem<T>>,
+}
+
+impl<T: Serialize + PartialEq> Default for MultiStatusResponse<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T: Serialize + PartialEq> MultiStatusResponse<T> {
+    /// Create an empty response
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a successful item
+    pub fn push_success(&mut self, id: impl Into<String>, status: StatusCode, data: T) {
+        self.items.push(MultiStatusItem {
+            id: id.into(),
+            status: status.as_u16(),
+            data: Some(data),
+            error: None,
+        });
+    }
+
+    /// Add a failed item
+    pub fn push_error(&mut self, id: impl Into<String>, error: ApiError) {
+        let status = error.status_code();
+        self.items.push(MultiStatusItem {
+            id: id.into(),
+            status: status.as_u16(),
+            data: None,
+            error: Some(ApiErrorResponse::new(status, error.message().to_string(), None)),
+        });
+    }
+
+    /// Add an item from a result, `success_status` is used if the result is `Ok`
+    pub fn push_result(&mut self, id: impl Into<String>, success_status: StatusCode, result: Result<T, ApiError>) {
+        match result {
+            Ok(data) => self.push_success(id, success_status, data),
+            Err(error) => self.push_error(id, error),
+        }
+    }
+
+    /// Items
+    pub fn items(&self) -> &[MultiStatusItem<T>] {
+        &self.items
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Return true if there is no item
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Return true if at least one item failed
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(MultiStatusItem::is_error)
+    }
+}
+
+impl<T: Serialize + PartialEq> IntoResponse for MultiStatusResponse<T> {
+    fn into_response(self) -> Response {
+        (StatusCode::MULTI_STATUS, Json(self)).into_response()
+    }
+}
+
+/// Serialization format of a body, negotiated with the `Accept` and `Content-Type` headers
+///
+/// MessagePack (`msgpack` feature) and CBOR (`cbor` feature) are compact binary formats for
+/// bandwidth-sensitive machine-to-machine APIs. JSON is always available and is the fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyFormat {
+    /// `application/json`
+    #[default]
+    Json,
+
+    /// `application/msgpack` (also `application/x-msgpack` and `application/vnd.msgpack`)
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+
+    /// `application/cbor`
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl BodyFormat {
+    /// Media type of the format
+    pub fn media_type(&self) -> &'static str {
         match self {
-            ApiError::Timeout => Self::response(StatusCode::REQUEST_TIMEOUT, "Request timeout").into_response(),
-            ApiError::TooManyRequests => {
-                Self::response(StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response()
-            }
-            ApiError::MethodNotAllowed => {
-                Self::response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed").into_response()
-            }
-            ApiError::PayloadTooLarge => {
-                Self::response(StatusCode::PAYLOAD_TOO_LARGE, "Payload too large").into_response()
-            }
-            ApiError::ServiceUnavailable => {
-                Self::response(StatusCode::SERVICE_UNAVAILABLE, "Service unavailable").into_response()
-            }
-            ApiError::BadRequest(message) => Self::response(StatusCode::BAD_REQUEST, &message).into_response(),
-            ApiError::Unauthorized(message) => Self::response(StatusCode::UNAUTHORIZED, &message).into_response(),
-            ApiError::Forbidden(message) => Self::response(StatusCode::FORBIDDEN, &message).into_response(),
-            ApiError::NotFound(message) => Self::response(StatusCode::NOT_FOUND, &message).into_response(),
-            ApiError::UnprocessableEntity(message) => {
-                Self::response(StatusCode::UNPROCESSABLE_ENTITY, &message).into_response()
-            }
-            ApiError::InternalServerError(message) => {
-                Self::response(StatusCode::INTERNAL_SERVER_ERROR, &message).into_response()
+            Self::Json => mime::APPLICATION_JSON.as_ref(),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => "application/msgpack",
+            #[cfg(feature = "cbor")]
+            Self::Cbor => "application/cbor",
+        }
+    }
+
+    /// Format of a media type, if supported (parameters are ignored)
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let essence = media_type.split(';').next().unwrap_or_default().trim();
+
+        match essence.to_ascii_lowercase().as_str() {
+            "application/json" => Some(Self::Json),
+            #[cfg(feature = "msgpack")]
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => Some(Self::MessagePack),
+            #[cfg(feature = "cbor")]
+            "application/cbor" => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Preferred supported format of the `Accept` header (by quality, then by order), JSON if
+    /// none is supported
+    ///
+    /// ```rust
+    /// use api_tools::server::axum::response::BodyFormat;
+    /// use axum::http::{HeaderMap, HeaderValue, header};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(header::ACCEPT, HeaderValue::from_static("text/html, */*;q=0.1"));
+    ///
+    /// assert_eq!(BodyFormat::from_accept(&headers), BodyFormat::Json);
+    /// ```
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        let mut ranges = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let media_type = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                (quality > 0.0).then_some((media_type, quality))
+            })
+            .collect::<Vec<_>>();
+        // Stable sort: the order is kept for the same quality
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges
+            .into_iter()
+            .find_map(|(media_type, _)| Self::from_media_type(media_type))
+            .unwrap_or_default()
+    }
+
+    /// Format of the `Content-Type` header, if supported
+    pub fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_media_type)
+    }
+
+    /// Serialize a value
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec(value).map_err(|err| err.to_string()),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|err| err.to_string())?;
+                Ok(bytes)
             }
         }
     }
+
+    /// Deserialize a value
+    pub fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|err| err.to_string()),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::from_slice(bytes).map_err(|err| err.to_string()),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => ciborium::from_reader(bytes).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/// The format is the preferred format of the `Accept` header
+impl<S> FromRequestParts<S> for BodyFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_accept(&parts.headers))
+    }
+}
+
+/// Response serialized in the format negotiated with the `Accept` header (see [`BodyFormat`])
+///
+/// # Example
+///
+/// ```rust
+/// use api_tools::server::axum::response::{BodyFormat, NegotiatedResponse};
+/// use axum::http::StatusCode;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u32,
+/// }
+///
+/// async fn create_user(format: BodyFormat) -> NegotiatedResponse<User> {
+///     NegotiatedResponse::new(format, User { id: 1 }).with_status(StatusCode::CREATED)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegotiatedResponse<T: Serialize> {
+    format: BodyFormat,
+    status: StatusCode,
+    data: T,
+}
+
+impl<T: Serialize> NegotiatedResponse<T> {
+    /// Create a new `200 OK` response
+    pub fn new(format: BodyFormat, data: T) -> Self {
+        Self {
+            format,
+            status: StatusCode::OK,
+            data,
+        }
+    }
+
+    /// Set the status code
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for NegotiatedResponse<T> {
+    fn into_response(self) -> Response {
+        match self.format.serialize(&self.data) {
+            Ok(body) => (
+                self.status,
+                [(header::CONTENT_TYPE, HeaderValue::from_static(self.format.media_type()))],
+                body,
+            )
+                .into_response(),
+            Err(err) => ApiError::InternalServerError(err).into_response(),
+        }
+    }
+}
+
+/// Format of the HTTP dates (`Last-Modified`, `If-Modified-Since`)
+pub(crate) const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// `304 Not Modified` response of a conditional `GET`, with the validators of the resource
+///
+/// [`NotModified::check`] compares the `If-None-Match` and `If-Modified-Since` headers with the
+/// current version of the resource. It returns the `304` response when the client version is up
+/// to date, otherwise the [`CacheValidators`] to set on the full response.
+///
+/// # Example
+///
+/// ```rust
+/// use api_tools::server::axum::response::NotModified;
+/// use axum::Json;
+/// use axum::http::HeaderMap;
+/// use axum::response::IntoResponse;
+///
+/// async fn get_user(headers: HeaderMap) -> Result<impl IntoResponse, NotModified> {
+///     let user = serde_json::json!({ "id": 1, "version": 3 });
+///     let validators = NotModified::check(&headers, Some("user-1-v3"), None)?;
+///
+///     Ok((validators, Json(user)))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotModified(CacheValidators);
+
+impl NotModified {
+    /// Check the conditional headers against the current `ETag` (quoted if needed) and
+    /// modification date of the resource
+    ///
+    /// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 9110).
+    pub fn check(
+        headers: &HeaderMap,
+        etag: Option<&str>,
+        last_modified: Option<DateTime<Utc>>,
+    ) -> Result<CacheValidators, Self> {
+        let validators = CacheValidators {
+            etag: etag.map(|etag| match etag.starts_with('"') || etag.starts_with("W/\"") {
+                true => etag.to_string(),
+                false => format!("\"{etag}\""),
+            }),
+            last_modified,
+        };
+
+        if is_not_modified(headers, validators.etag.as_deref(), last_modified) {
+            Err(Self(validators))
+        } else {
+            Ok(validators)
+        }
+    }
+}
+
+impl IntoResponse for NotModified {
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_MODIFIED, self.0.headers()).into_response()
+    }
+}
+
+/// `ETag` and `Last-Modified` headers of a resource
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl CacheValidators {
+    /// Quoted entity tag
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// Modification date
+    pub fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.last_modified
+    }
+
+    /// `ETag` and `Last-Modified` headers
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = self.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(last_modified) = self
+            .last_modified
+            .and_then(|date| HeaderValue::from_str(&date.format(HTTP_DATE_FORMAT).to_string()).ok())
+        {
+            headers.insert(header::LAST_MODIFIED, last_modified);
+        }
+
+        headers
+    }
+}
+
+impl IntoResponseParts for CacheValidators {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut response: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        response.headers_mut().extend(self.headers());
+
+        Ok(response)
+    }
+}
+
+/// Return true if the conditional headers match the current version of a resource
+///
+/// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 9110).
+pub(crate) fn is_not_modified(headers: &HeaderMap, etag: Option<&str>, modified: Option<DateTime<Utc>>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok()) {
+        let etag = etag.map(|etag| etag.trim_start_matches("W/"));
+
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || Some(tag) == etag);
+    }
+
+    let if_modified_since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+
+    match (if_modified_since, modified) {
+        (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::server::axum::layers::request_id::scope_request_id;
     use serde_json::json;
 
     #[test]
@@ -219,6 +839,34 @@ mod tests {
         assert_eq!(body_str, data.to_string());
     }
 
+    #[tokio::test]
+    async fn test_api_success_enveloped() {
+        let response = ApiSuccess::new(StatusCode::OK, json!([{"id": 1}]))
+            .with_pagination(PaginationResponse::new(1, 10, 42))
+            .with_timing(Duration::from_micros(1_500))
+            .with_extra("version", json!("v2"));
+        let response = scope_request_id("req-1".to_string(), async move { response.into_response() }).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            json!({
+                "data": [{"id": 1}],
+                "meta": {
+                    "request_id": "req-1",
+                    "pagination": {"page": 1, "limit": 10, "total": 42},
+                    "duration_ms": 1.5,
+                    "version": "v2"
+                }
+            })
+        );
+
+        let response = ApiSuccess::new(StatusCode::CREATED, "ok").enveloped().into_response();
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, r#"{"data":"ok","meta":{}}"#);
+    }
+
     #[test]
     fn test_new_api_error_response() {
         let error = ApiErrorResponse::new(StatusCode::BAD_REQUEST, "Bad request", None);
@@ -240,6 +888,18 @@ mod tests {
         assert_eq!(body_str, json!({ "code": 400, "message": "Invalid input" }).to_string());
     }
 
+    #[tokio::test]
+    async fn test_api_error_into_response_is_redacted() {
+        let response = ApiError::Unauthorized("Invalid header: Bearer eyJhbGciOi.abc".to_string()).into_response();
+
+        let body_bytes = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        let body_str = String::from_utf8(body_bytes.to_vec()).unwrap();
+        assert_eq!(
+            body_str,
+            json!({ "code": 401, "message": "Invalid header: ***" }).to_string()
+        );
+    }
+
     #[tokio::test]
     async fn test_api_error_into_response_unauthorized() {
         let error = ApiError::Unauthorized("Not authorized".to_string());
@@ -415,4 +1075,164 @@ mod tests {
             json!({ "code": 500, "message": "Internal server error" }).to_string()
         );
     }
+
+    #[tokio::test]
+    async fn test_validation_errors_into_response() {
+        let mut errors = ValidationErrors::new();
+        assert!(errors.is_empty());
+        errors.add("page", "must be at least 1");
+        errors.add("name", "is required");
+        assert_eq!(errors.len(), 2);
+
+        let response = errors.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = response.into_body();
+        let body_bytes = axum::body::to_bytes(body, 1_024).await.unwrap();
+        let body_str = String::from_utf8(body_bytes.to_vec()).unwrap();
+        assert_eq!(
+            body_str,
+            json!({
+                "code": 422,
+                "message": [
+                    { "field": "page", "message": "must be at least 1" },
+                    { "field": "name", "message": "is required" },
+                ]
+            })
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_api_error_status_code_and_message() {
+        let error = ApiError::NotFound("Product not found".to_string());
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(error.message(), "Product not found");
+
+        assert_eq!(ApiError::Timeout.status_code(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(ApiError::Timeout.message(), "Request timeout");
+    }
+
+    #[tokio::test]
+    async fn test_multi_status_response() {
+        let mut response = MultiStatusResponse::new();
+        assert!(response.is_empty());
+
+        response.push_success("1", StatusCode::CREATED, json!({ "name": "book" }));
+        response.push_result(
+            "2",
+            StatusCode::CREATED,
+            Err(ApiError::NotFound("Product not found".to_string())),
+        );
+        response.push_result("3", StatusCode::OK, Ok(json!({ "name": "pen" })));
+        assert_eq!(response.len(), 3);
+        assert!(response.has_errors());
+        assert!(response.items()[1].is_error());
+        assert_eq!(response.items()[1].id(), "2");
+        assert_eq!(response.items()[1].status(), 404);
+
+        let response = response.into_response();
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+
+        let body = response.into_body();
+        let body_bytes = axum::body::to_bytes(body, 1_024).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "items": [
+                    { "id": "1", "status": 201, "data": { "name": "book" } },
+                    { "id": "2", "status": 404, "error": { "code": 404, "message": "Product not found" } },
+                    { "id": "3", "status": 200, "data": { "name": "pen" } },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_multi_status_response_without_errors() {
+        let mut response = MultiStatusResponse::new();
+        response.push_success("1", StatusCode::OK, 1);
+        assert!(!response.has_errors());
+    }
+
+    #[test]
+    fn test_not_modified_check_etag() {
+        let mut headers = HeaderMap::new();
+        let validators = NotModified::check(&headers, Some("v1"), None).unwrap();
+        assert_eq!(validators.etag(), Some("\"v1\""));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"v0\", W/\"v1\""));
+        let not_modified = NotModified::check(&headers, Some("v1"), None).unwrap_err();
+        let response = not_modified.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+
+        assert!(NotModified::check(&headers, Some("\"v2\""), None).is_ok());
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(NotModified::check(&headers, None, None).is_err());
+    }
+
+    #[test]
+    fn test_not_modified_check_last_modified() {
+        let modified = DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap().to_utc();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_MODIFIED_SINCE,
+            HeaderValue::from_static("Wed, 01 May 2024 10:00:00 GMT"),
+        );
+        assert!(NotModified::check(&headers, None, Some(modified)).is_err());
+
+        let validators = NotModified::check(&headers, None, Some(modified + Duration::from_secs(1))).unwrap();
+        let response = (validators, "body").into_response();
+        assert_eq!(
+            response.headers()[header::LAST_MODIFIED],
+            "Wed, 01 May 2024 10:00:01 GMT"
+        );
+
+        // If-None-Match takes precedence
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"v0\""));
+        assert!(NotModified::check(&headers, Some("v1"), Some(modified)).is_ok());
+    }
+
+    #[test]
+    fn test_body_format_from_accept() {
+        let accept = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+            BodyFormat::from_accept(&headers)
+        };
+        assert_eq!(BodyFormat::from_accept(&HeaderMap::new()), BodyFormat::Json);
+        assert_eq!(accept("text/html"), BodyFormat::Json);
+        assert_eq!(accept("application/json;q=0"), BodyFormat::Json);
+        assert_eq!(
+            BodyFormat::from_media_type("Application/JSON; charset=utf-8"),
+            Some(BodyFormat::Json)
+        );
+
+        #[cfg(all(feature = "msgpack", feature = "cbor"))]
+        {
+            assert_eq!(
+                accept("application/json;q=0.5, application/msgpack"),
+                BodyFormat::MessagePack
+            );
+            assert_eq!(accept("application/cbor, application/x-msgpack"), BodyFormat::Cbor);
+            assert_eq!(
+                accept("application/cbor;q=0.1, application/vnd.msgpack;q=0.2"),
+                BodyFormat::MessagePack
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_response() {
+        let response = NegotiatedResponse::new(BodyFormat::Json, json!({ "id": 1 }))
+            .with_status(StatusCode::CREATED)
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, r#"{"id":1}"#);
+    }
 }
@@ -0,0 +1,369 @@
+//! Standard layer stack
+//!
+//! [`ApiRouterBuilder`] wraps a `Router` with the usual layers, from the outermost to the
+//! innermost:
+//! 1. `RequestIdLayer`: the request ID is set before anything is logged
+//! 2. `ContextLayer`: the `RequestContext` read by the logger
+//! 3. `LoggerLayer`: the logged status is the final one (rewritten errors, timeouts, CORS)
+//! 4. `HttpErrorsLayer`: the errors of the inner layers (e.g. `408` timeouts) are rewritten as JSON
+//! 5. `CorsLayer`: preflight requests are answered before being compressed or timed out
+//! 6. `CompressionLayer` (gzip and brotli): the rewritten error statuses are never compressed, so
+//!    that their bodies can still be read by the `HttpErrorsLayer`
+//! 7. `TimeoutLayer`: `408 Request Timeout` responses, with the `DeadlineLayer` storing the
+//!    `Deadline` of the request in the `RequestStore`
+//! 8. `MetricsLayer` (`metrics` feature): the matched route is known
+//!
+//! The stack is configured by a single [`ApiRouterConfig`], which can be loaded and validated by
+//! the `ConfigLoader`:
+//!
+//! ```rust
+//! use api_tools::server::axum::config::CorsSettings;
+//! use api_tools::server::axum::router::{ApiRouterBuilder, ApiRouterConfig};
+//! use axum::{Router, routing::get};
+//!
+//! let config = ApiRouterConfig::new("users-api")
+//!     .with_cors(CorsSettings::default())
+//!     .with_timeout(30);
+//!
+//! let app: Router = ApiRouterBuilder::new(config)
+//!     .build(Router::new().route("/health", get(|| async { "ok" })))
+//!     .unwrap();
+//! ```
+
+use crate::server::axum::config::{ConfigLoadError, CorsSettings, ValidateConfig, validate_section};
+use crate::server::axum::layers::context::ContextLayer;
+use crate::server::axum::layers::deadline::DeadlineLayer;
+use crate::server::axum::layers::http_errors::{ErrorRewrite, HttpErrorsConfig, HttpErrorsLayer, HttpErrorsRules};
+use crate::server::axum::layers::logger::LoggerLayer;
+#[cfg(feature = "metrics")]
+use crate::server::axum::layers::metrics::MetricsLayer;
+use crate::server::axum::layers::request_id::RequestIdLayer;
+use crate::server::axum::response::{ApiError, ValidationErrors};
+use axum::Router;
+use axum::http::{Extensions, HeaderMap, StatusCode, Version};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::timeout::TimeoutLayer;
+
+/// Default maximum size of the bodies read by the `HttpErrorsLayer` (1 MiB)
+pub const ROUTER_DEFAULT_BODY_MAX_SIZE: usize = 1_024 * 1_024;
+
+fn default_body_max_size() -> usize {
+    ROUTER_DEFAULT_BODY_MAX_SIZE
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Configuration of the standard layer stack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiRouterConfig {
+    /// Service name (label of the metrics)
+    pub service_name: String,
+
+    /// Maximum size of the bodies read by the `HttpErrorsLayer` (default: 1 MiB)
+    #[serde(default = "default_body_max_size")]
+    pub body_max_size: usize,
+
+    /// CORS settings (no CORS layer if not set)
+    #[serde(default)]
+    pub cors: Option<CorsSettings>,
+
+    /// Compress the responses (default: `true`)
+    #[serde(default = "default_true")]
+    pub compression: bool,
+
+    /// Request timeout in seconds (no timeout if not set)
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    /// Record the HTTP metrics (default: `true`, `metrics` feature)
+    #[serde(default = "default_true")]
+    pub metrics: bool,
+}
+
+impl ApiRouterConfig {
+    /// Create a configuration with compression and metrics, without CORS and timeout
+    pub fn new(service_name: &str) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            body_max_size: ROUTER_DEFAULT_BODY_MAX_SIZE,
+            cors: None,
+            compression: true,
+            timeout: None,
+            metrics: true,
+        }
+    }
+
+    /// Set the maximum size of the bodies read by the `HttpErrorsLayer`
+    pub fn with_body_max_size(mut self, body_max_size: usize) -> Self {
+        self.body_max_size = body_max_size;
+        self
+    }
+
+    /// Set the CORS settings
+    pub fn with_cors(mut self, cors: CorsSettings) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Enable or disable the compression
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the request timeout in seconds
+    pub fn with_timeout(mut self, timeout: u64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Enable or disable the HTTP metrics
+    pub fn with_metrics(mut self, metrics: bool) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+impl ValidateConfig for ApiRouterConfig {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if self.service_name.trim().is_empty() {
+            errors.add("service_name", "must not be empty");
+        }
+        if self.body_max_size == 0 {
+            errors.add("body_max_size", "must be greater than 0");
+        }
+        if self.timeout == Some(0) {
+            errors.add("timeout", "must be greater than 0");
+        }
+        if let Some(cors) = &self.cors {
+            validate_section(errors, "cors", cors);
+        }
+    }
+}
+
+/// Builder of the standard layer stack
+#[derive(Clone)]
+pub struct ApiRouterBuilder {
+    config: ApiRouterConfig,
+    request_id: RequestIdLayer,
+    context: ContextLayer,
+    http_errors: Option<HttpErrorsRules>,
+}
+
+impl ApiRouterBuilder {
+    /// Create a new builder
+    pub fn new(config: ApiRouterConfig) -> Self {
+        Self {
+            config,
+            request_id: RequestIdLayer::new(),
+            context: ContextLayer::new(),
+            http_errors: None,
+        }
+    }
+
+    /// Replace the default `RequestIdLayer`
+    pub fn with_request_id(mut self, request_id: RequestIdLayer) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    /// Replace the default `ContextLayer` (e.g. to trust the proxy headers)
+    pub fn with_context(mut self, context: ContextLayer) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Replace the default `HttpErrorsRules` (the `408` rewrite is added if a timeout is set)
+    pub fn with_http_errors(mut self, http_errors: HttpErrorsRules) -> Self {
+        self.http_errors = Some(http_errors);
+        self
+    }
+
+    /// Rules of the `HttpErrorsLayer`
+    fn http_errors_rules(&self) -> HttpErrorsRules {
+        let rules = self.http_errors.clone().unwrap_or_default();
+
+        if self.config.timeout.is_some() && !rules.is_rewritten(StatusCode::REQUEST_TIMEOUT) {
+            rules.with_rewrite(
+                StatusCode::REQUEST_TIMEOUT,
+                ErrorRewrite::EmptyBody(ApiError::Timeout.message().to_string()),
+            )
+        } else {
+            rules
+        }
+    }
+
+    /// Wrap the router with the layer stack
+    ///
+    /// The configuration is validated first, all the invalid fields are returned at once.
+    pub fn build<S>(&self, router: Router<S>) -> Result<Router<S>, ConfigLoadError>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let mut errors = ValidationErrors::new();
+        self.config.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(ConfigLoadError::Invalid(errors));
+        }
+
+        let http_errors = self.http_errors_rules();
+        let mut router = router;
+
+        // Layers are added from the innermost to the outermost
+        #[cfg(feature = "metrics")]
+        if self.config.metrics {
+            router = router.layer(MetricsLayer {
+                service_name: self.config.service_name.clone(),
+            });
+        }
+
+        if let Some(timeout) = self.config.timeout {
+            router =
+                router
+                    .layer(DeadlineLayer::new(Duration::from_secs(timeout)))
+                    .layer(TimeoutLayer::with_status_code(
+                        StatusCode::REQUEST_TIMEOUT,
+                        Duration::from_secs(timeout),
+                    ));
+        }
+
+        if self.config.compression {
+            let rules = http_errors.clone();
+            let predicate = DefaultPredicate::new()
+                .and(move |status: StatusCode, _: Version, _: &HeaderMap, _: &Extensions| !rules.is_rewritten(status));
+            router = router.layer(CompressionLayer::new().compress_when(predicate));
+        }
+
+        if let Some(cors) = &self.config.cors {
+            let layer = cors
+                .builder()?
+                .build()
+                .map_err(|err| ConfigLoadError::Parse(err.to_string()))?;
+            router = router.layer(layer);
+        }
+
+        Ok(router
+            .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(self.config.body_max_size)).with_rules(http_errors))
+            .layer(LoggerLayer)
+            .layer(self.context.clone())
+            .layer(self.request_id.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::request_store::{Deadline, RequestStore};
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        Router::new()
+            .route("/text", get(|| async { "a".repeat(1_024) }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    "slow"
+                }),
+            )
+            .route(
+                "/deadline",
+                get(|store: RequestStore| async move { store.contains::<Deadline>().to_string() }),
+            )
+            .route(
+                "/invalid",
+                get(|| async { (StatusCode::UNPROCESSABLE_ENTITY, "x".repeat(1_024)) }),
+            )
+    }
+
+    fn request(uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::ORIGIN, "https://example.com")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn read_json(response: axum::response::Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), 4_096).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_router_builder_stack() {
+        let app = ApiRouterBuilder::new(
+            ApiRouterConfig::new("test")
+                .with_cors(CorsSettings::default())
+                .with_timeout(1),
+        )
+        .build(router())
+        .unwrap();
+
+        // Request ID, CORS and compression
+        let response = app.clone().oneshot(request("/text")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key("x-request-id"));
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        // Rewritten errors are not compressed
+        let response = app.clone().oneshot(request("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().contains_key("x-request-id"));
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(read_json(response).await["message"], "Resource Not Found");
+
+        let response = app.clone().oneshot(request("/invalid")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(read_json(response).await["message"], "x".repeat(1_024));
+
+        // Timeout
+        let response = app.clone().oneshot(request("/deadline")).await.unwrap();
+        assert_eq!(axum::body::to_bytes(response.into_body(), 16).await.unwrap(), "true");
+
+        let response = app.oneshot(request("/slow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(read_json(response).await["message"], "Request timeout");
+    }
+
+    #[tokio::test]
+    async fn test_api_router_builder_without_optional_layers() {
+        let app = ApiRouterBuilder::new(ApiRouterConfig::new("test").with_compression(false))
+            .build(router())
+            .unwrap();
+
+        let response = app.oneshot(request("/text")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn test_api_router_config_validation() {
+        let config: ApiRouterConfig = serde_json::from_value(serde_json::json!({
+            "service_name": "",
+            "timeout": 0,
+            "cors": {"origins": "example.com"}
+        }))
+        .unwrap();
+        assert!(config.compression);
+        assert!(config.metrics);
+        assert_eq!(config.body_max_size, ROUTER_DEFAULT_BODY_MAX_SIZE);
+
+        let err = ApiRouterBuilder::new(config).build(Router::<()>::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid configuration: service_name: must not be empty, timeout: must be greater than 0, \
+             cors.origins: Invalid CORS origin: example.com"
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//! Background job scheduler
+//!
+//! [`JobScheduler`] runs periodic jobs (cleanup tasks, cache refreshes, ...) on Tokio tasks:
+//! - triggers: fixed interval ([`Trigger::every`]) or cron expression ([`Trigger::cron`], in UTC)
+//! - per-job timeout ([`Job::with_timeout`])
+//! - overlap policy when a run is still in progress ([`OverlapPolicy::Skip`] or
+//!   [`OverlapPolicy::Queue`])
+//! - graceful shutdown: no run is started after the [`Shutdown`] signal, the running ones complete
+//!   and each stopped job is reported with `Shutdown::job_cancelled`
+//! - metrics (`metrics` feature): `scheduler_job_runs_total` (`job` and `outcome` labels) and
+//!   `scheduler_job_duration_seconds` (`job` label)
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::scheduler::{Job, JobScheduler, OverlapPolicy, Trigger};
+//! use api_tools::server::axum::shutdown::Shutdown;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let shutdown = Shutdown::new();
+//! let scheduler = JobScheduler::new().with_shutdown(shutdown.clone());
+//!
+//! scheduler.spawn(
+//!     Trigger::every(Duration::from_secs(60)),
+//!     Job::new("purge_sessions", || async { Ok::<_, String>(()) }).with_timeout(Duration::from_secs(30)),
+//! );
+//! scheduler.spawn(
+//!     Trigger::cron("0 3 * * 1-5")?,
+//!     Job::new("daily_report", || async { Ok::<_, String>(()) }).with_overlap(OverlapPolicy::Queue),
+//! );
+//!
+//! // At shutdown, wait for the running jobs
+//! shutdown.begin();
+//! scheduler.join().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::axum::shutdown::Shutdown;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, DurationRound, Timelike, Utc};
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::{AbortHandle, JoinHandle, JoinSet};
+use tokio::time::MissedTickBehavior;
+
+/// Maximum number of years searched for the next time of a cron expression
+const CRON_MAX_YEARS: i32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SchedulerError {
+    #[error("Invalid cron expression: {0}")]
+    InvalidCron(String),
+}
+
+/// Cron schedule (`minute hour day-of-month month day-of-week`, in UTC)
+///
+/// Each field accepts `*`, values, ranges (`1-5`), steps (`*/15`, `0-30/10`) and lists (`1,15`).
+/// The day of week goes from 0 (Sunday) to 7 (Sunday). As with cron, when both the day of month
+/// and the day of week are restricted, a day matching either of them is selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = SchedulerError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(SchedulerError::InvalidCron(format!("{expression} (5 fields expected)")));
+        };
+
+        let weekdays = parse_cron_field(weekdays, 0, 7)?;
+
+        Ok(Self {
+            minutes: parse_cron_field(minutes, 0, 59)?,
+            hours: parse_cron_field(hours, 0, 23)? as u32,
+            days: parse_cron_field(days, 1, 31)? as u32,
+            months: parse_cron_field(months, 1, 12)? as u16,
+            // 7 is also Sunday
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7f) as u8,
+            any_day: days == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+}
+
+impl CronSchedule {
+    /// Next time strictly after `after` (truncated to the minute)
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.duration_trunc(ChronoDuration::minutes(1)).ok()? + ChronoDuration::minutes(1);
+        let max_year = after.year() + CRON_MAX_YEARS;
+
+        while time.year() <= max_year {
+            if !has_bit(self.months as u64, time.month()) {
+                time = time.with_day(1)?.with_hour(0)?.with_minute(0)?;
+                time = match time.month() {
+                    12 => time.with_year(time.year() + 1)?.with_month(1)?,
+                    month => time.with_month(month + 1)?,
+                };
+            } else if !self.matches_day(&time) {
+                time = time.with_hour(0)?.with_minute(0)? + ChronoDuration::days(1);
+            } else if !has_bit(self.hours as u64, time.hour()) {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+            } else if !has_bit(self.minutes, time.minute()) {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day = has_bit(self.days as u64, time.day());
+        let weekday = has_bit(self.weekdays as u64, time.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn has_bit(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Parse a cron field into a bit set
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, SchedulerError> {
+    let invalid = || SchedulerError::InvalidCron(format!("{field} (values from {min} to {max} expected)"));
+    let parse = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(invalid)
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse(start)?, parse(end)?),
+                None if step > 1 => (parse(range)?, max),
+                None => (parse(range)?, parse(range)?),
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// Trigger of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Fixed interval, the first run is immediate
+    Interval(Duration),
+
+    /// Cron schedule
+    Cron(CronSchedule),
+}
+
+impl Trigger {
+    /// Fixed interval trigger
+    pub fn every(interval: Duration) -> Self {
+        Self::Interval(interval)
+    }
+
+    /// Cron trigger (`minute hour day-of-month month day-of-week`, in UTC)
+    pub fn cron(expression: &str) -> Result<Self, SchedulerError> {
+        Ok(Self::Cron(expression.parse()?))
+    }
+}
+
+impl From<Duration> for Trigger {
+    fn from(interval: Duration) -> Self {
+        Self::Interval(interval)
+    }
+}
+
+impl From<CronSchedule> for Trigger {
+    fn from(schedule: CronSchedule) -> Self {
+        Self::Cron(schedule)
+    }
+}
+
+/// Policy when a run is triggered while the previous one is still running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlapPolicy {
+    /// Skip the run
+    #[default]
+    Skip,
+
+    /// Start the run when the previous one completes
+    Queue,
+}
+
+/// Outcome of a job run, used as the `outcome` metrics label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobOutcome {
+    Success,
+    Error,
+    Timeout,
+    Panic,
+    Skipped,
+}
+
+impl Display for JobOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self {
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Timeout => "timeout",
+            Self::Panic => "panic",
+            Self::Skipped => "skipped",
+        };
+
+        write!(f, "{outcome}")
+    }
+}
+
+type JobFn = Arc<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Job run by the [`JobScheduler`]
+#[derive(Clone)]
+pub struct Job {
+    pub name: String,
+    pub timeout: Option<Duration>,
+    pub overlap: OverlapPolicy,
+    run: JobFn,
+}
+
+impl Job {
+    /// Create a new job without timeout, skipping the overlapping runs
+    pub fn new<F, Fut, E>(name: &str, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        Self {
+            name: name.to_string(),
+            timeout: None,
+            overlap: OverlapPolicy::default(),
+            run: Arc::new(move || Box::pin(run().map(|result| result.map_err(|err| err.to_string())))),
+        }
+    }
+
+    /// Set the timeout of a run
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the overlap policy
+    pub fn with_overlap(mut self, overlap: OverlapPolicy) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Run the job once, returning its outcome
+    async fn execute(&self) -> JobOutcome {
+        let start = Instant::now();
+        let run = AssertUnwindSafe((self.run)()).catch_unwind();
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, run).await.ok(),
+            None => Some(run.await),
+        };
+
+        let outcome = match result {
+            Some(Ok(Ok(()))) => JobOutcome::Success,
+            Some(Ok(Err(err))) => {
+                error!(job = self.name, error = %err, "Scheduled job failed");
+                JobOutcome::Error
+            }
+            Some(Err(_)) => {
+                error!(job = self.name, "Scheduled job panicked");
+                JobOutcome::Panic
+            }
+            None => {
+                warn!(job = self.name, timeout = ?self.timeout, "Scheduled job timed out");
+                JobOutcome::Timeout
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        metrics::histogram!("scheduler_job_duration_seconds", "job" => self.name.clone())
+            .record(start.elapsed().as_secs_f64());
+        #[cfg(not(feature = "metrics"))]
+        let _ = start;
+
+        outcome
+    }
+}
+
+/// Record the outcome of a run
+fn record_outcome(job: &str, outcome: JobOutcome) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("scheduler_job_runs_total", "job" => job.to_string(), "outcome" => outcome.to_string())
+        .increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (job, outcome);
+}
+
+/// Background job scheduler, cheap to clone
+#[derive(Clone, Default)]
+pub struct JobScheduler {
+    shutdown: Shutdown,
+    handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl JobScheduler {
+    /// Create a new scheduler, stopped with [`JobScheduler::stop`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the scheduler with the application graceful shutdown
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Spawn a job on a Tokio task
+    ///
+    /// The task ends when the shutdown starts, after the completion of the running runs. The
+    /// returned handle aborts it immediately.
+    pub fn spawn(&self, trigger: impl Into<Trigger>, job: Job) -> AbortHandle {
+        let trigger = trigger.into();
+        let shutdown = self.shutdown.clone();
+
+        let handle = tokio::spawn(async move {
+            let job = Arc::new(job);
+            let lock = Arc::new(tokio::sync::Mutex::new(()));
+            let mut runs = JoinSet::new();
+            let mut interval = match trigger {
+                Trigger::Interval(period) => {
+                    let mut interval = tokio::time::interval(period.max(Duration::from_millis(1)));
+                    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                    Some(interval)
+                }
+                Trigger::Cron(_) => None,
+            };
+
+            loop {
+                let tick = async {
+                    match (&mut interval, trigger) {
+                        (Some(interval), _) => {
+                            interval.tick().await;
+                            true
+                        }
+                        (None, Trigger::Cron(schedule)) => match schedule.next_after(Utc::now()) {
+                            Some(next) => {
+                                let delay = (next - Utc::now()).to_std().unwrap_or_default();
+                                tokio::time::sleep(delay).await;
+                                true
+                            }
+                            None => false,
+                        },
+                        (None, Trigger::Interval(_)) => false,
+                    }
+                };
+
+                tokio::select! {
+                    biased;
+                    _ = shutdown.wait() => break,
+                    triggered = tick => {
+                        if !triggered {
+                            warn!(job = job.name, "Scheduled job has no next run");
+                            break;
+                        }
+                    },
+                }
+
+                while runs.try_join_next().is_some() {}
+
+                let guard = match job.overlap {
+                    OverlapPolicy::Skip => match lock.clone().try_lock_owned() {
+                        Ok(guard) => Some(guard),
+                        Err(_) => {
+                            warn!(job = job.name, "Scheduled job skipped, the previous run is in progress");
+                            record_outcome(&job.name, JobOutcome::Skipped);
+                            continue;
+                        }
+                    },
+                    OverlapPolicy::Queue => None,
+                };
+
+                let (job, lock) = (job.clone(), lock.clone());
+                runs.spawn(async move {
+                    let _guard = match guard {
+                        Some(guard) => guard,
+                        None => lock.lock_owned().await,
+                    };
+                    let outcome = job.execute().await;
+                    record_outcome(&job.name, outcome);
+                });
+            }
+
+            runs.join_all().await;
+            if shutdown.is_started() {
+                shutdown.job_cancelled();
+            }
+        });
+
+        let abort_handle = handle.abort_handle();
+        if let Ok(mut handles) = self.handles.lock() {
+            handles.retain(|handle| !handle.is_finished());
+            handles.push(handle);
+        }
+
+        abort_handle
+    }
+
+    /// Stop the scheduler (start the shutdown)
+    pub fn stop(&self) {
+        self.shutdown.begin();
+    }
+
+    /// Wait for the end of the jobs, after the shutdown
+    pub async fn join(&self) {
+        let handles = self
+            .handles
+            .lock()
+            .map(|mut handles| std::mem::take(&mut *handles))
+            .unwrap_or_default();
+
+        for handle in handles {
+            let _ = handle.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule_next_after() {
+        let schedule = "*/15 * * * *".parse::<CronSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(utc(2026, 1, 1, 10, 7)),
+            Some(utc(2026, 1, 1, 10, 15))
+        );
+        assert_eq!(
+            schedule.next_after(utc(2026, 1, 1, 23, 45)),
+            Some(utc(2026, 1, 2, 0, 0))
+        );
+
+        // Weekdays at 03:30 (2026-01-02 is a Friday)
+        let schedule = "30 3 * * 1-5".parse::<CronSchedule>().unwrap();
+        assert_eq!(schedule.next_after(utc(2026, 1, 2, 4, 0)), Some(utc(2026, 1, 5, 3, 30)));
+
+        // First day of the month or Sunday (7)
+        let schedule = "0 0 1 * 7".parse::<CronSchedule>().unwrap();
+        assert_eq!(schedule.next_after(utc(2026, 1, 1, 0, 0)), Some(utc(2026, 1, 4, 0, 0)));
+        assert_eq!(schedule.next_after(utc(2026, 1, 25, 0, 0)), Some(utc(2026, 2, 1, 0, 0)));
+
+        let schedule = "0 12 29 2 *".parse::<CronSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(utc(2026, 3, 1, 0, 0)),
+            Some(utc(2028, 2, 29, 12, 0))
+        );
+        assert_eq!(
+            "0 0 30 2 *"
+                .parse::<CronSchedule>()
+                .unwrap()
+                .next_after(utc(2026, 1, 1, 0, 0)),
+            None
+        );
+
+        for expression in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(
+                matches!(Trigger::cron(expression), Err(SchedulerError::InvalidCron(_))),
+                "{expression}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_scheduler_interval() {
+        let shutdown = Shutdown::new();
+        let scheduler = JobScheduler::new().with_shutdown(shutdown.clone());
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let counter = runs.clone();
+        scheduler.spawn(
+            Trigger::every(Duration::from_millis(10)),
+            Job::new("count", move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Err::<(), _>("failed")
+                }
+            }),
+        );
+
+        tokio::time::sleep(Duration::from_millis(55)).await;
+        scheduler.stop();
+        scheduler.join().await;
+
+        let count = runs.load(Ordering::SeqCst);
+        assert!((3..=7).contains(&count), "{count} runs");
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), count);
+        assert_eq!(shutdown.report().jobs_cancelled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_job_scheduler_overlap_and_timeout() {
+        let scheduler = JobScheduler::new();
+        let skipped = Arc::new(AtomicUsize::new(0));
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        for (counter, overlap) in [(&skipped, OverlapPolicy::Skip), (&queued, OverlapPolicy::Queue)] {
+            let counter = counter.clone();
+            scheduler.spawn(
+                Duration::from_millis(10),
+                Job::new("slow", move || {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(35)).await;
+                        Ok::<_, String>(())
+                    }
+                })
+                .with_overlap(overlap),
+            );
+        }
+
+        // Runs started at 0 and ~40 ms (skip), every 35 ms (queue)
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        scheduler.stop();
+        scheduler.join().await;
+        assert!((1..=3).contains(&skipped.load(Ordering::SeqCst)));
+        assert!(
+            queued.load(Ordering::SeqCst) >= 5,
+            "{} runs",
+            queued.load(Ordering::SeqCst)
+        );
+
+        let job = Job::new("timeout", || {
+            tokio::time::sleep(Duration::from_secs(1)).map(Ok::<_, String>)
+        })
+        .with_timeout(Duration::from_millis(10));
+        assert_eq!(job.execute().await, JobOutcome::Timeout);
+
+        let job = Job::new("panic", || async { panic!("job panic") as Result<(), String> });
+        assert_eq!(job.execute().await, JobOutcome::Panic);
+    }
+}
@@ -0,0 +1,228 @@
+//! Role, permission and scope based authorization
+//!
+//! The authentication layer (e.g. `JwtAuthLayer`) stores the [`Grants`] of the caller in the
+//! `RequestStore`, then the `AuthorizeLayer` checks them against the [`Requirement`] of a route.
+//!
+//! ```rust
+//! use api_tools::server::axum::security::authorization::{Grant, Grants, Requirement};
+//!
+//! let grants = Grants::new()
+//!     .with_roles(&["editor"])
+//!     .with_scope_claim("users:read users:write");
+//!
+//! let requirement = Requirement::all_of(&[Grant::scope("users:write"), Grant::role("editor")]);
+//! assert!(grants.check(&requirement).is_ok());
+//!
+//! let requirement = Requirement::any_of(&[Grant::role("admin"), Grant::permission("users.delete")]);
+//! assert_eq!(grants.check(&requirement).unwrap_err().missing.len(), 2);
+//! ```
+
+use crate::server::axum::response::{ApiErrorResponse, current_trace_id};
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// Role, permission or scope
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Grant {
+    Role(String),
+    Permission(String),
+    Scope(String),
+}
+
+impl Grant {
+    /// Role grant
+    pub fn role(role: &str) -> Self {
+        Self::Role(role.to_string())
+    }
+
+    /// Permission grant
+    pub fn permission(permission: &str) -> Self {
+        Self::Permission(permission.to_string())
+    }
+
+    /// Scope grant
+    pub fn scope(scope: &str) -> Self {
+        Self::Scope(scope.to_string())
+    }
+}
+
+impl Display for Grant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Role(value) => write!(f, "role:{value}"),
+            Self::Permission(value) => write!(f, "permission:{value}"),
+            Self::Scope(value) => write!(f, "scope:{value}"),
+        }
+    }
+}
+
+/// Roles, permissions and scopes of the caller
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grants(HashSet<Grant>);
+
+impl Grants {
+    /// Create an empty set of grants
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add roles
+    pub fn with_roles(mut self, roles: &[&str]) -> Self {
+        self.0.extend(roles.iter().map(|role| Grant::role(role)));
+        self
+    }
+
+    /// Add permissions
+    pub fn with_permissions(mut self, permissions: &[&str]) -> Self {
+        self.0
+            .extend(permissions.iter().map(|permission| Grant::permission(permission)));
+        self
+    }
+
+    /// Add scopes
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.0.extend(scopes.iter().map(|scope| Grant::scope(scope)));
+        self
+    }
+
+    /// Add the scopes of an OAuth2 `scope` claim (space-separated list)
+    pub fn with_scope_claim(mut self, scope: &str) -> Self {
+        self.0.extend(scope.split_whitespace().map(Grant::scope));
+        self
+    }
+
+    /// Return true if the grant is present
+    pub fn contains(&self, grant: &Grant) -> bool {
+        self.0.contains(grant)
+    }
+
+    /// Check a requirement, returning the missing grants if it is not satisfied
+    pub fn check(&self, requirement: &Requirement) -> Result<(), MissingGrants> {
+        let satisfied = match requirement {
+            Requirement::AllOf(grants) => grants.iter().all(|grant| self.contains(grant)),
+            Requirement::AnyOf(grants) => grants.iter().any(|grant| self.contains(grant)),
+        };
+        if satisfied {
+            return Ok(());
+        }
+
+        let (Requirement::AllOf(grants) | Requirement::AnyOf(grants)) = requirement;
+        Err(MissingGrants {
+            missing: grants.iter().filter(|grant| !self.contains(grant)).cloned().collect(),
+        })
+    }
+}
+
+/// Grants required by a route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    /// All the grants are required
+    AllOf(Vec<Grant>),
+
+    /// At least one of the grants is required (an empty list is never satisfied)
+    AnyOf(Vec<Grant>),
+}
+
+impl Requirement {
+    /// All the grants are required
+    pub fn all_of(grants: &[Grant]) -> Self {
+        Self::AllOf(grants.to_vec())
+    }
+
+    /// At least one of the grants is required
+    pub fn any_of(grants: &[Grant]) -> Self {
+        Self::AnyOf(grants.to_vec())
+    }
+}
+
+/// Unsatisfied requirement, returned as a `403 Forbidden` response
+///
+/// The response `message` is the list of missing grants:
+/// `{ "code": 403, "message": [{ "type": "scope", "value": "users:write" }] }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingGrants {
+    pub missing: Vec<Grant>,
+}
+
+impl IntoResponse for MissingGrants {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::FORBIDDEN,
+            Json(ApiErrorResponse::new(
+                StatusCode::FORBIDDEN,
+                self.missing,
+                current_trace_id(),
+            )),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_grants_check() {
+        let grants = Grants::new()
+            .with_roles(&["editor"])
+            .with_permissions(&["users.read"])
+            .with_scope_claim(" users:read  users:write ");
+        assert!(grants.contains(&Grant::scope("users:write")));
+
+        let requirement = Requirement::all_of(&[Grant::role("editor"), Grant::scope("users:read")]);
+        assert_eq!(grants.check(&requirement), Ok(()));
+
+        let requirement = Requirement::all_of(&[Grant::role("editor"), Grant::role("admin")]);
+        assert_eq!(
+            grants.check(&requirement),
+            Err(MissingGrants {
+                missing: vec![Grant::role("admin")]
+            })
+        );
+
+        let requirement = Requirement::any_of(&[Grant::role("admin"), Grant::permission("users.read")]);
+        assert_eq!(grants.check(&requirement), Ok(()));
+
+        let requirement = Requirement::any_of(&[Grant::role("admin"), Grant::scope("users:delete")]);
+        assert_eq!(
+            grants.check(&requirement).unwrap_err().missing,
+            vec![Grant::role("admin"), Grant::scope("users:delete")]
+        );
+
+        assert!(grants.check(&Requirement::any_of(&[])).is_err());
+        assert!(grants.check(&Requirement::all_of(&[])).is_ok());
+    }
+
+    #[test]
+    fn test_grant_display() {
+        assert_eq!(Grant::role("admin").to_string(), "role:admin");
+        assert_eq!(Grant::permission("users.read").to_string(), "permission:users.read");
+        assert_eq!(Grant::scope("users:write").to_string(), "scope:users:write");
+    }
+
+    #[tokio::test]
+    async fn test_missing_grants_into_response() {
+        let response = MissingGrants {
+            missing: vec![Grant::scope("users:write")],
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            json!({
+                "code": 403,
+                "message": [{ "type": "scope", "value": "users:write" }]
+            })
+            .to_string()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Access token entity
 
+use super::cookie::AuthCookie;
 use crate::{server::axum::response::ApiError, value_objects::datetime::UtcDateTime};
 use axum::{extract::FromRequestParts, http::request::Parts};
 use hyper::{HeaderMap, header};
@@ -52,6 +53,9 @@ impl AccessToken {
 }
 
 /// JWT extractor from HTTP headers
+///
+/// The token is read from the `Authorization` header, or from the cookie if an [`AuthCookie`] is in
+/// the request extensions (added by `JwtAuthLayer::with_cookie`).
 impl<S> FromRequestParts<S> for AccessToken
 where
     S: Send + Sync,
@@ -60,6 +64,12 @@ where
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         Self::extract_bearer_token_from_headers(&parts.headers)
+            .or_else(|| {
+                parts
+                    .extensions
+                    .get::<AuthCookie>()
+                    .and_then(|cookie| cookie.token_from_headers(&parts.headers))
+            })
             .ok_or(ApiError::Unauthorized("Missing or invalid token".to_string()))
     }
 }
@@ -108,4 +118,18 @@ mod tests {
         let err = AccessToken::from_request_parts(&mut parts, &()).await.unwrap_err();
         assert!(matches!(err, ApiError::Unauthorized(_)));
     }
+
+    #[tokio::test]
+    async fn from_request_parts_reads_the_auth_cookie_when_configured() {
+        let req = axum::http::Request::builder()
+            .header(header::COOKIE, "access_token=cookie.jwt.token")
+            .body(())
+            .unwrap();
+        let (mut parts, _) = req.into_parts();
+        assert!(AccessToken::from_request_parts(&mut parts, &()).await.is_err());
+
+        parts.extensions.insert(AuthCookie::new());
+        let token = AccessToken::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(token.token, "cookie.jwt.token");
+    }
 }
@@ -0,0 +1,204 @@
+//! Cookie transport of the access token
+//!
+//! Browser clients cannot safely store a bearer token: [`AuthCookie`] sends it in an `HttpOnly`
+//! cookie instead, with the `SameSite` and `Secure` flags. The login handler sets the cookie with
+//! [`AuthCookie::set`] and the logout handler clears it with [`AuthCookie::clear`].
+//!
+//! The `JwtAuthLayer` configured with [`JwtAuthLayer::with_cookie`](crate::server::axum::layers::jwt_auth::JwtAuthLayer::with_cookie)
+//! reads the token from the cookie when there is no `Authorization` header, and adds the
+//! `AuthCookie` to the request extensions so that the `AccessToken` extractor also reads it.
+//!
+//! ```rust
+//! use api_tools::server::axum::security::jwt::access_token::AccessToken;
+//! use api_tools::server::axum::security::jwt::cookie::{AuthCookie, SameSite};
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use axum::response::IntoResponse;
+//! use chrono::TimeDelta;
+//!
+//! let cookie = AuthCookie::new().with_name("session").with_same_site(SameSite::Strict);
+//! let token = AccessToken::new("header.payload.signature".to_string(), UtcDateTime::now().add(TimeDelta::minutes(15)));
+//!
+//! let response = ([cookie.set(&token)], "logged in").into_response();
+//! assert!(response.headers()["set-cookie"].to_str().unwrap().starts_with("session=header.payload.signature;"));
+//! ```
+
+use super::access_token::AccessToken;
+use crate::value_objects::datetime::UtcDateTime;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use std::fmt;
+
+/// Default name of the access token cookie
+pub const AUTH_COOKIE_DEFAULT_NAME: &str = "access_token";
+
+/// `SameSite` attribute of a cookie
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SameSite {
+    /// Only sent by requests from the same site
+    Strict,
+
+    /// Also sent by the top-level navigations from other sites
+    #[default]
+    Lax,
+
+    /// Sent by all the requests (requires `Secure`)
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Strict => write!(f, "Strict"),
+            Self::Lax => write!(f, "Lax"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Access token cookie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthCookie {
+    pub name: String,
+    pub path: String,
+    pub domain: Option<String>,
+    pub same_site: SameSite,
+    pub secure: bool,
+}
+
+impl Default for AuthCookie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthCookie {
+    /// `HttpOnly` and `Secure` cookie named `access_token`, with `Path=/` and `SameSite=Lax`
+    pub fn new() -> Self {
+        Self {
+            name: AUTH_COOKIE_DEFAULT_NAME.to_string(),
+            path: "/".to_string(),
+            domain: None,
+            same_site: SameSite::default(),
+            secure: true,
+        }
+    }
+
+    /// Set the name of the cookie
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the path of the cookie
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Set the domain of the cookie
+    pub fn with_domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Set the `SameSite` attribute of the cookie
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Remove the `Secure` attribute (local development over HTTP only, ignored with `SameSite=None`)
+    pub fn insecure(mut self) -> Self {
+        self.secure = false;
+        self
+    }
+
+    /// `Set-Cookie` header storing the token until its expiration
+    pub fn set(&self, token: &AccessToken) -> (HeaderName, HeaderValue) {
+        let max_age = (token.expired_at.timestamp() - UtcDateTime::now().timestamp()).max(0);
+
+        self.header(&token.token, max_age)
+    }
+
+    /// `Set-Cookie` header removing the token
+    pub fn clear(&self) -> (HeaderName, HeaderValue) {
+        self.header("", 0)
+    }
+
+    /// Token of the cookie
+    pub fn token_from_headers(&self, headers: &HeaderMap) -> Option<AccessToken> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, value)| *name == self.name && !value.is_empty())
+            .map(|(_, value)| AccessToken::new(value.trim_matches('"').to_string(), UtcDateTime::now()))
+    }
+
+    /// `Set-Cookie` header with a value and a max age (in seconds)
+    fn header(&self, value: &str, max_age: i64) -> (HeaderName, HeaderValue) {
+        let mut cookie = format!(
+            "{}={value}; Path={}; Max-Age={max_age}; HttpOnly; SameSite={}",
+            self.name, self.path, self.same_site
+        );
+        if let Some(domain) = &self.domain {
+            cookie.push_str("; Domain=");
+            cookie.push_str(domain);
+        }
+        if self.secure || self.same_site == SameSite::None {
+            cookie.push_str("; Secure");
+        }
+
+        let value = HeaderValue::from_str(&cookie).unwrap_or_else(|_| HeaderValue::from_static(""));
+
+        (header::SET_COOKIE, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_auth_cookie_set_and_clear() {
+        let cookie = AuthCookie::new().with_domain("example.com");
+        let token = AccessToken::new("a.b.c".to_string(), UtcDateTime::now().add(TimeDelta::seconds(900)));
+
+        let (name, value) = cookie.set(&token);
+        assert_eq!(name, header::SET_COOKIE);
+        let value = value.to_str().unwrap();
+        assert!(value.starts_with("access_token=a.b.c; Path=/; Max-Age="));
+        assert!(value.ends_with("; HttpOnly; SameSite=Lax; Domain=example.com; Secure"));
+
+        let (_, value) = cookie.clear();
+        assert_eq!(
+            value,
+            "access_token=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax; Domain=example.com; Secure"
+        );
+
+        // `SameSite=None` cookies are always secure
+        let (_, value) = AuthCookie::new().insecure().with_same_site(SameSite::None).clear();
+        assert_eq!(
+            value,
+            "access_token=; Path=/; Max-Age=0; HttpOnly; SameSite=None; Secure"
+        );
+
+        let (_, value) = AuthCookie::new().insecure().with_path("/api").clear();
+        assert_eq!(value, "access_token=; Path=/api; Max-Age=0; HttpOnly; SameSite=Lax");
+    }
+
+    #[test]
+    fn test_auth_cookie_token_from_headers() {
+        let cookie = AuthCookie::new().with_name("session");
+        let mut headers = HeaderMap::new();
+        assert!(cookie.token_from_headers(&headers).is_none());
+
+        headers.append(header::COOKIE, HeaderValue::from_static("theme=dark; session="));
+        assert!(cookie.token_from_headers(&headers).is_none());
+
+        headers.append(header::COOKIE, HeaderValue::from_static("lang=fr; session=a.b.c"));
+        assert_eq!(cookie.token_from_headers(&headers).unwrap().token, "a.b.c");
+    }
+}
@@ -0,0 +1,159 @@
+//! JWT key rotation
+//!
+//! A [`Jwt`](super::Jwt) can hold several keys identified by their key ID (`kid`): the tokens are
+//! signed with the primary key (its `kid` is set in the token header) and verified with the key
+//! matching their `kid`. To rotate the keys without invalidating the outstanding tokens:
+//!
+//! 1. add the new key and make it the primary key, keeping the previous key for verification
+//! 2. remove the previous key once the tokens it signed have expired
+//!
+//! The keys come from a configuration structure (`JwtKey` is deserializable) or from a directory
+//! ([`JwtKey::load_dir`]) containing, for each key ID, the files:
+//!
+//! - `{kid}.secret`: secret of the `HS*` algorithms
+//! - `{kid}.private.pem`: private key of the other algorithms (only needed for the primary key)
+//! - `{kid}.public.pem`: public key of the other algorithms
+//!
+//! ```rust
+//! use api_tools::server::axum::security::jwt::Jwt;
+//! use api_tools::server::axum::security::jwt::keys::JwtKey;
+//!
+//! let keys = [JwtKey::secret("2026-10", "new-secret"), JwtKey::secret("2026-04", "old-secret")];
+//! let jwt = Jwt::init_with_keys("HS256", 15, 24, &keys, "2026-10").unwrap();
+//! assert_eq!(jwt.primary_kid(), Some("2026-10"));
+//! ```
+
+use super::JwtError;
+use crate::server::axum::config::Secret;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Extension of the secret files
+const SECRET_EXTENSION: &str = ".secret";
+
+/// Extension of the private key files
+const PRIVATE_KEY_EXTENSION: &str = ".private.pem";
+
+/// Extension of the public key files
+const PUBLIC_KEY_EXTENSION: &str = ".public.pem";
+
+/// Key identified by its key ID (`kid`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JwtKey {
+    /// Key ID
+    pub kid: String,
+
+    /// Secret of the `HS*` algorithms
+    #[serde(default)]
+    pub secret: Option<Secret<String>>,
+
+    /// Private key (PEM) of the other algorithms
+    #[serde(default)]
+    pub private_key: Option<Secret<String>>,
+
+    /// Public key (PEM) of the other algorithms
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+impl JwtKey {
+    /// Key of the `HS*` algorithms
+    pub fn secret(kid: &str, secret: &str) -> Self {
+        Self {
+            kid: kid.to_string(),
+            secret: Some(Secret::new(secret.to_string())),
+            private_key: None,
+            public_key: None,
+        }
+    }
+
+    /// Key pair (PEM) of the other algorithms, the private key is only needed to sign tokens
+    pub fn pair(kid: &str, private_key: Option<&str>, public_key: &str) -> Self {
+        Self {
+            kid: kid.to_string(),
+            secret: None,
+            private_key: private_key.map(|key| Secret::new(key.to_string())),
+            public_key: Some(public_key.to_string()),
+        }
+    }
+
+    /// Load the keys of a directory, sorted by key ID
+    pub fn load_dir(path: impl AsRef<Path>) -> Result<Vec<Self>, JwtError> {
+        let entries = std::fs::read_dir(path.as_ref()).map_err(|err| JwtError::KeySetError(err.to_string()))?;
+        let mut keys = BTreeMap::<String, Self>::new();
+
+        for entry in entries {
+            let path = entry.map_err(|err| JwtError::KeySetError(err.to_string()))?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let (kid, extension) = match [SECRET_EXTENSION, PRIVATE_KEY_EXTENSION, PUBLIC_KEY_EXTENSION]
+                .into_iter()
+                .find_map(|extension| Some((name.strip_suffix(extension)?, extension)))
+            {
+                Some((kid, extension)) if !kid.is_empty() => (kid.to_string(), extension),
+                _ => continue,
+            };
+
+            let content = std::fs::read_to_string(&path)
+                .map_err(|err| JwtError::KeySetError(format!("{}: {err}", path.display())))?;
+            let content = content.trim().to_string();
+            let key = keys.entry(kid.clone()).or_insert_with(|| Self {
+                kid,
+                secret: None,
+                private_key: None,
+                public_key: None,
+            });
+
+            match extension {
+                SECRET_EXTENSION => key.secret = Some(Secret::new(content)),
+                PRIVATE_KEY_EXTENSION => key.private_key = Some(Secret::new(content)),
+                _ => key.public_key = Some(content),
+            }
+        }
+
+        Ok(keys.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jwt_key_load_dir() {
+        let dir = std::env::temp_dir().join(format!("jwt-keys-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2026-10.secret"), "new-secret\n").unwrap();
+        std::fs::write(dir.join("2026-04.secret"), "old-secret").unwrap();
+        std::fs::write(dir.join("es-1.private.pem"), "private").unwrap();
+        std::fs::write(dir.join("es-1.public.pem"), "public").unwrap();
+        std::fs::write(dir.join("README.md"), "ignored").unwrap();
+
+        let keys = JwtKey::load_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            keys,
+            vec![
+                JwtKey::secret("2026-04", "old-secret"),
+                JwtKey::secret("2026-10", "new-secret"),
+                JwtKey::pair("es-1", Some("private"), "public"),
+            ]
+        );
+        assert!(matches!(
+            JwtKey::load_dir("/missing/jwt/keys"),
+            Err(JwtError::KeySetError(_))
+        ));
+    }
+
+    #[test]
+    fn test_jwt_key_deserialize_and_debug() {
+        let key: JwtKey = serde_json::from_str(r#"{"kid": "2026-10", "secret": "new-secret"}"#).unwrap();
+        assert_eq!(key, JwtKey::secret("2026-10", "new-secret"));
+        assert!(!format!("{key:?}").contains("new-secret"));
+    }
+}
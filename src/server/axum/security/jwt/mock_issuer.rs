@@ -0,0 +1,316 @@
+//! Mock JWT issuer (`testing` feature)
+//!
+//! [`MockJwtIssuer`] generates an RSA (`RS256`) or EC (`ES256`) key pair on the fly, signs
+//! arbitrary claims and exposes its public JWKS, so that the services validating tokens with a
+//! JWKS can run end-to-end authentication tests without an identity provider.
+//!
+//! [`MockJwtIssuer::router`] serves the JWKS (`/.well-known/jwks.json`) and, if an issuer is set,
+//! the OpenID Connect discovery document (`/.well-known/openid-configuration`) in process:
+//!
+//! ```rust
+//! use api_tools::server::axum::security::jwt::access_token::AccessToken;
+//! use api_tools::server::axum::security::jwt::mock_issuer::MockJwtIssuer;
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use serde::Deserialize;
+//! use std::time::Duration;
+//!
+//! #[derive(Debug, Clone, Deserialize)]
+//! struct Claims {
+//!     sub: String,
+//! }
+//!
+//! let issuer = MockJwtIssuer::ec().unwrap().with_issuer("https://auth.example.com");
+//! let token = issuer.token_for("alice", Duration::from_secs(60)).unwrap();
+//!
+//! // Validation with the public JWKS only
+//! let claims = issuer
+//!     .jwt()
+//!     .parse::<Claims>(&AccessToken::new(token, UtcDateTime::now()))
+//!     .unwrap();
+//! assert_eq!(claims.sub, "alice");
+//! ```
+
+use super::{Jwt, JwtError};
+use axum::routing::get;
+use axum::{Json, Router};
+use jsonwebtoken::jwk::{Jwk, JwkSet, KeyAlgorithm, PublicKeyUse};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+use p256::pkcs8::EncodePrivateKey;
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::rand_core::OsRng;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Size of the generated RSA keys
+const MOCK_RSA_KEY_BITS: usize = 2_048;
+
+/// JWKS path
+pub const MOCK_JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// OpenID Connect discovery document path
+pub const MOCK_DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// JWT issuer with a generated key pair, for tests
+#[derive(Clone)]
+pub struct MockJwtIssuer {
+    algorithm: Algorithm,
+    kid: String,
+    encoding_key: EncodingKey,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Debug for MockJwtIssuer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockJwtIssuer")
+            .field("algorithm", &self.algorithm)
+            .field("kid", &self.kid)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .finish()
+    }
+}
+
+impl MockJwtIssuer {
+    /// Create an issuer with a new 2048-bit RSA key pair (`RS256`)
+    pub fn rsa() -> Result<Self, JwtError> {
+        let key = rsa::RsaPrivateKey::new(&mut OsRng, MOCK_RSA_KEY_BITS)
+            .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?;
+        let der = key
+            .to_pkcs1_der()
+            .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?;
+
+        Ok(Self::new(Algorithm::RS256, EncodingKey::from_rsa_der(der.as_bytes())))
+    }
+
+    /// Create an issuer with a new P-256 key pair (`ES256`)
+    pub fn ec() -> Result<Self, JwtError> {
+        let der = p256::SecretKey::random(&mut OsRng)
+            .to_pkcs8_der()
+            .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?;
+
+        Ok(Self::new(Algorithm::ES256, EncodingKey::from_ec_der(der.as_bytes())))
+    }
+
+    fn new(algorithm: Algorithm, encoding_key: EncodingKey) -> Self {
+        Self {
+            algorithm,
+            kid: Uuid::new_v4().to_string(),
+            encoding_key,
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    /// Set the issuer (`iss` claim of [`MockJwtIssuer::token_for`], expected by [`MockJwtIssuer::jwt`])
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Set the audience (`aud` claim of [`MockJwtIssuer::token_for`], expected by [`MockJwtIssuer::jwt`])
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Set the key ID (`kid`, a random UUID by default)
+    pub fn with_key_id(mut self, kid: &str) -> Self {
+        self.kid = kid.to_string();
+        self
+    }
+
+    /// Signing algorithm
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Key ID
+    pub fn key_id(&self) -> &str {
+        &self.kid
+    }
+
+    /// Issuer
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// Public JWKS
+    pub fn jwks(&self) -> Result<JwkSet, JwtError> {
+        let mut jwk = Jwk::from_encoding_key(&self.encoding_key, self.algorithm)
+            .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?;
+        jwk.common.key_id = Some(self.kid.clone());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+        jwk.common.key_algorithm = Some(match self.algorithm {
+            Algorithm::ES256 => KeyAlgorithm::ES256,
+            _ => KeyAlgorithm::RS256,
+        });
+
+        Ok(JwkSet { keys: vec![jwk] })
+    }
+
+    /// Sign arbitrary claims (the `kid` header is set)
+    pub fn sign<P: Serialize>(&self, claims: &P) -> Result<String, JwtError> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+
+        encode(&header, claims, &self.encoding_key).map_err(|err| JwtError::GenerateError(err.to_string()))
+    }
+
+    /// Sign a token for a subject, valid for `lifetime`
+    ///
+    /// The `sub`, `iat` and `exp` claims are set, and `iss` and `aud` if they are configured.
+    pub fn token_for(&self, subject: &str, lifetime: Duration) -> Result<String, JwtError> {
+        self.token_with(subject, lifetime, Map::new())
+    }
+
+    /// Sign a token for a subject, valid for `lifetime`, with extra claims (e.g. `roles`)
+    pub fn token_with(&self, subject: &str, lifetime: Duration, extra: Map<String, Value>) -> Result<String, JwtError> {
+        let now = chrono::Utc::now().timestamp();
+        let mut claims = extra;
+        claims.insert("sub".to_string(), json!(subject));
+        claims.insert("iat".to_string(), json!(now));
+        claims.insert("exp".to_string(), json!(now + lifetime.as_secs() as i64));
+        if let Some(issuer) = &self.issuer {
+            claims.insert("iss".to_string(), json!(issuer));
+        }
+        if let Some(audience) = &self.audience {
+            claims.insert("aud".to_string(), json!(audience));
+        }
+
+        self.sign(&claims)
+    }
+
+    /// `Jwt` validating the tokens of this issuer with its public JWKS (and its issuer and audience)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the JWKS cannot be built from the generated key.
+    pub fn jwt(&self) -> Jwt {
+        let mut jwt = Jwt::default();
+        jwt.set_jwks(&self.jwks().expect("invalid mock JWKS"));
+        if let Some(issuer) = &self.issuer {
+            jwt.set_issuer(issuer);
+        }
+        if let Some(audience) = &self.audience {
+            jwt.set_audience(&[audience]);
+        }
+
+        jwt
+    }
+
+    /// Router serving the JWKS and, if an issuer is set, the discovery document
+    ///
+    /// Serve it on the issuer URL (e.g. `http://127.0.0.1:<port>`) to test the OpenID Connect
+    /// discovery.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the JWKS cannot be built from the generated key.
+    pub fn router(&self) -> Router {
+        let jwks = self.jwks().expect("invalid mock JWKS");
+        let mut router = Router::new().route(MOCK_JWKS_PATH, get(move || async move { Json(jwks) }));
+
+        if let Some(issuer) = &self.issuer {
+            let discovery = json!({
+                "issuer": issuer,
+                "jwks_uri": format!("{issuer}{MOCK_JWKS_PATH}"),
+                "id_token_signing_alg_values_supported": [format!("{:?}", self.algorithm)],
+            });
+            router = router.route(MOCK_DISCOVERY_PATH, get(move || async move { Json(discovery) }));
+        }
+
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::layers::jwt_auth::{JwtAuthLayer, JwtClaims};
+    use crate::server::axum::security::jwt::access_token::AccessToken;
+    use crate::testing::TestClient;
+    use crate::value_objects::datetime::UtcDateTime;
+    use axum::http::StatusCode;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Claims {
+        sub: String,
+        #[serde(default)]
+        roles: Vec<String>,
+    }
+
+    impl JwtClaims for Claims {}
+
+    #[test]
+    fn test_mock_jwt_issuer_ec() {
+        let issuer = MockJwtIssuer::ec()
+            .unwrap()
+            .with_issuer("https://auth.example.com/")
+            .with_audience("api")
+            .with_key_id("key-1");
+        let token = issuer
+            .token_with(
+                "alice",
+                Duration::from_secs(60),
+                Map::from_iter([("roles".to_string(), json!(["admin"]))]),
+            )
+            .unwrap();
+
+        let claims = issuer
+            .jwt()
+            .parse::<Claims>(&AccessToken::new(token, UtcDateTime::now()))
+            .unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.roles, vec!["admin".to_string()]);
+
+        let jwks = issuer.jwks().unwrap();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(
+            jwks.find("key-1").unwrap().common.key_algorithm,
+            Some(KeyAlgorithm::ES256)
+        );
+
+        // Tokens of another issuer are rejected
+        let other = MockJwtIssuer::ec().unwrap().with_key_id("key-1");
+        let token = other.token_for("alice", Duration::from_secs(60)).unwrap();
+        assert!(
+            issuer
+                .jwt()
+                .parse::<Claims>(&AccessToken::new(token, UtcDateTime::now()))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_jwt_issuer_rsa_end_to_end() {
+        let issuer = MockJwtIssuer::rsa().unwrap().with_issuer("http://127.0.0.1");
+        assert_eq!(issuer.algorithm(), Algorithm::RS256);
+
+        // JWKS and discovery document
+        let client = TestClient::new(issuer.router());
+        let jwks = client.get(MOCK_JWKS_PATH).send().await.json::<JwkSet>();
+        assert!(jwks.find(issuer.key_id()).is_some());
+        let discovery = client.get(MOCK_DISCOVERY_PATH).send().await.json::<Value>();
+        assert_eq!(discovery["jwks_uri"], "http://127.0.0.1/.well-known/jwks.json");
+
+        // Routes protected by the `JwtAuthLayer`
+        let mut jwt = Jwt::default();
+        jwt.set_jwks(&jwks);
+        let app = Router::new()
+            .route("/me", get(|| async { "me" }))
+            .layer(JwtAuthLayer::<Claims>::new(jwt));
+        let token = issuer.token_for("alice", Duration::from_secs(60)).unwrap();
+
+        let client = TestClient::new(app);
+        assert_eq!(client.get("/me").bearer(&token).send().await.text(), "me");
+        assert_eq!(
+            client.get("/me").bearer("invalid").send().await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -1,20 +1,36 @@
 //! JWT module
 
 pub mod access_token;
+pub mod cookie;
+pub mod keys;
+#[cfg(feature = "testing")]
+pub mod mock_issuer;
+#[cfg(feature = "oidc")]
+pub mod oidc;
 pub mod payload;
+pub mod revocation;
 
 use crate::server::axum::response::ApiError;
 use crate::server::axum::security::jwt::access_token::AccessToken;
+use crate::server::axum::security::jwt::keys::JwtKey;
+use crate::server::axum::security::jwt::revocation::TokenRevocationStore;
 use crate::value_objects::datetime::UtcDateTime;
 use jsonwebtoken::errors::ErrorKind::ExpiredSignature;
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Validation, decode, encode};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Validation, decode, decode_header, encode};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
 use thiserror::Error;
 
 const JWT_ACCESS_LIFETIME_IN_MINUTES: i64 = 15; // 15 minutes
 const JWT_REFRESH_LIFETIME_IN_HOURS: i64 = 7 * 24; // 7 days
 
+/// Default leeway of the time-based claims (`exp`, `nbf`), in seconds
+pub const JWT_DEFAULT_LEEWAY: u64 = 60;
+
 /// JWT errors
 #[derive(Debug, Clone, PartialEq, Error)]
 pub enum JwtError {
@@ -35,6 +51,21 @@ pub enum JwtError {
 
     #[error("Expired token")]
     ExpiredToken,
+
+    #[error("Unknown key ID: {0}")]
+    UnknownKeyId(String),
+
+    #[error("Key set error: {0}")]
+    KeySetError(String),
+
+    #[error("OIDC discovery error: {0}")]
+    DiscoveryError(String),
+
+    #[error("Revoked token")]
+    RevokedToken,
+
+    #[error("Revocation store error: {0}")]
+    RevocationStoreError(String),
 }
 
 /// JWT error
@@ -58,11 +89,26 @@ pub struct Jwt {
     /// The default value is 7 days.
     refresh_lifetime: i64,
 
-    /// Encoding key
-    encoding_key: Option<EncodingKey>,
+    /// Encoding key (shared by the clones, never copied when a token is generated)
+    encoding_key: Option<Arc<EncodingKey>>,
+
+    /// Key ID (`kid`) of the encoding key, set in the header of the generated tokens
+    encoding_kid: Option<String>,
 
-    /// Decoding key
-    decoding_key: Option<DecodingKey>,
+    /// Decoding key (shared by the clones, never copied when a token is parsed)
+    decoding_key: Option<Arc<DecodingKey>>,
+
+    /// Decoding keys selected by the token key ID (`kid`), used instead of `decoding_key` if set
+    key_set: Option<Arc<RwLock<JwtKeySet>>>,
+
+    /// Validation of the registered claims
+    validation: JwtValidation,
+
+    /// `jsonwebtoken` validation built from `validation` for `algorithm` on the first parsing
+    compiled_validation: Arc<OnceLock<Validation>>,
+
+    /// Store of the revoked token IDs (`jti` claim)
+    revocation_store: Option<Arc<dyn TokenRevocationStore>>,
 }
 
 impl Default for Jwt {
@@ -72,8 +118,184 @@ impl Default for Jwt {
             access_lifetime: JWT_ACCESS_LIFETIME_IN_MINUTES,
             refresh_lifetime: JWT_REFRESH_LIFETIME_IN_HOURS,
             encoding_key: None,
+            encoding_kid: None,
             decoding_key: None,
+            key_set: None,
+            validation: JwtValidation::default(),
+            compiled_validation: Arc::default(),
+            revocation_store: None,
+        }
+    }
+}
+
+/// Validation of the registered claims of the tokens
+///
+/// The `Jwt` has a default validation (see [`Jwt::set_validation`]) which can be overridden for
+/// one call with [`Jwt::parse_with`]:
+///
+/// ```rust
+/// use api_tools::server::axum::security::jwt::{Jwt, JwtValidation};
+///
+/// let mut jwt = Jwt::init("HS256", 15, 24, Some("secret"), None, None).unwrap();
+/// jwt.set_validation(
+///     JwtValidation::default()
+///         .with_issuer("https://idp.example.com")
+///         .with_audience(&["users-api"])
+///         .with_leeway(5)
+///         .with_required_claims(&["exp", "iss", "aud", "sub"]),
+/// );
+///
+/// // Stricter validation for the admin routes
+/// let admin_validation = jwt.validation().clone().with_audience(&["admin-api"]);
+/// assert_eq!(admin_validation.audience, Some(vec!["admin-api".to_string()]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtValidation {
+    /// Expected issuer (`iss` claim)
+    pub issuer: Option<String>,
+
+    /// Accepted audiences (`aud` claim)
+    pub audience: Option<Vec<String>>,
+
+    /// Expected subject (`sub` claim)
+    pub subject: Option<String>,
+
+    /// Leeway of the time-based claims, in seconds (60 seconds by default)
+    pub leeway: u64,
+
+    /// Claims which must be present (`exp` by default)
+    pub required_claims: Vec<String>,
+
+    /// Validate the `nbf` claim if present (disabled by default)
+    pub validate_not_before: bool,
+}
+
+impl Default for JwtValidation {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audience: None,
+            subject: None,
+            leeway: JWT_DEFAULT_LEEWAY,
+            required_claims: vec!["exp".to_string()],
+            validate_not_before: false,
+        }
+    }
+}
+
+impl JwtValidation {
+    /// Set the expected issuer (`iss` claim)
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Set the accepted audiences (`aud` claim)
+    pub fn with_audience(mut self, audience: &[&str]) -> Self {
+        self.audience = Some(audience.iter().map(|aud| aud.to_string()).collect());
+        self
+    }
+
+    /// Set the expected subject (`sub` claim)
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Set the leeway of the time-based claims, in seconds
+    pub fn with_leeway(mut self, leeway: u64) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Set the claims which must be present (e.g. `["exp", "iss", "aud", "sub"]`)
+    pub fn with_required_claims(mut self, claims: &[&str]) -> Self {
+        self.required_claims = claims.iter().map(|claim| claim.to_string()).collect();
+        self
+    }
+
+    /// Validate the `nbf` claim if present
+    pub fn with_not_before(mut self) -> Self {
+        self.validate_not_before = true;
+        self
+    }
+
+    /// `jsonwebtoken` validation for an algorithm
+    fn to_validation(&self, algorithm: Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
         }
+        if let Some(audience) = &self.audience {
+            validation.set_audience(audience);
+        }
+        validation.sub = self.subject.clone();
+        validation.leeway = self.leeway;
+        validation.set_required_spec_claims(&self.required_claims);
+        validation.validate_nbf = self.validate_not_before;
+
+        validation
+    }
+}
+
+/// Decoding keys indexed by key ID (`kid`), built from a JWK set
+#[derive(Clone, Default)]
+pub struct JwtKeySet {
+    keys: HashMap<String, (Arc<DecodingKey>, Option<Algorithm>)>,
+}
+
+impl JwtKeySet {
+    /// Build a key set from a JWK set
+    ///
+    /// Keys without `kid` or with an unsupported algorithm (e.g. encryption keys) are ignored.
+    pub fn from_jwks(jwks: &JwkSet) -> Self {
+        let keys = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone()?;
+                let algorithm = match jwk.common.key_algorithm {
+                    Some(key_algorithm) => Some(Algorithm::from_str(&key_algorithm.to_string()).ok()?),
+                    None => None,
+                };
+                let key = Arc::new(DecodingKey::from_jwk(jwk).ok()?);
+
+                Some((kid, (key, algorithm)))
+            })
+            .collect();
+
+        Self { keys }
+    }
+
+    /// Number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Key IDs of the set
+    pub fn kids(&self) -> Vec<&str> {
+        let mut kids = self.keys.keys().map(String::as_str).collect::<Vec<_>>();
+        kids.sort_unstable();
+        kids
+    }
+
+    /// Return true if the key set is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Get the key and its algorithm for a key ID
+    ///
+    /// A token without key ID is accepted if the set contains only one key.
+    fn get(&self, kid: Option<&str>) -> Result<(Arc<DecodingKey>, Option<Algorithm>), JwtError> {
+        let key = match kid {
+            Some(kid) => self.keys.get(kid),
+            None if self.keys.len() == 1 => self.keys.values().next(),
+            None => None,
+        };
+
+        key.cloned()
+            .ok_or_else(|| JwtError::UnknownKeyId(kid.unwrap_or_default().to_string()))
     }
 }
 
@@ -121,6 +343,35 @@ impl Jwt {
         Ok(jwt)
     }
 
+    /// Initialize a new `Jwt` with several keys, the tokens being signed by the `primary_kid` key
+    ///
+    /// The other keys are only used to verify the tokens (e.g. the previous key during a rotation).
+    pub fn init_with_keys(
+        algorithm: &str,
+        access_lifetime: i64,
+        refresh_lifetime: i64,
+        keys: &[JwtKey],
+        primary_kid: &str,
+    ) -> Result<Self, JwtError> {
+        let mut jwt = Jwt {
+            algorithm: Self::algorithm_from_str(algorithm)?,
+            access_lifetime,
+            refresh_lifetime,
+            ..Default::default()
+        };
+
+        for key in keys {
+            jwt.add_key(key)?;
+        }
+        let primary = keys
+            .iter()
+            .find(|key| key.kid == primary_kid)
+            .ok_or_else(|| JwtError::UnknownKeyId(primary_kid.to_string()))?;
+        jwt.set_primary_key(primary)?;
+
+        Ok(jwt)
+    }
+
     /// Get access token lifetime
     pub fn access_lifetime(&self) -> i64 {
         self.access_lifetime
@@ -143,7 +394,91 @@ impl Jwt {
 
     /// Update encoding key
     pub fn set_encoding_key(&mut self, secret: &str) -> Result<(), JwtError> {
-        let key = match self.algorithm {
+        self.encoding_key = Some(Arc::new(self.encoding_key_from(secret)?));
+        self.encoding_kid = None;
+
+        Ok(())
+    }
+
+    /// Update decoding key
+    pub fn set_decoding_key(&mut self, secret: &str) -> Result<(), JwtError> {
+        self.decoding_key = Some(Arc::new(self.decoding_key_from(secret)?));
+
+        Ok(())
+    }
+
+    /// Add a key used to verify the tokens with its key ID (`kid`)
+    ///
+    /// The keys are shared with the clones of the `Jwt` and replace its decoding key.
+    pub fn add_key(&mut self, key: &JwtKey) -> Result<(), JwtError> {
+        let material = match (&key.secret, &key.public_key, self.use_secret()) {
+            (Some(secret), _, true) => secret.expose().as_str(),
+            (_, Some(public_key), false) => public_key.as_str(),
+            _ => return Err(JwtError::DecodingKeyError(format!("missing key {}", key.kid))),
+        };
+        let decoding_key = Arc::new(self.decoding_key_from(material.trim())?);
+
+        let key_set = self
+            .key_set
+            .get_or_insert_with(|| Arc::new(RwLock::new(JwtKeySet::default())));
+        key_set
+            .write()
+            .map_err(|err| JwtError::KeySetError(err.to_string()))?
+            .keys
+            .insert(key.kid.clone(), (decoding_key, Some(self.algorithm)));
+
+        Ok(())
+    }
+
+    /// Sign the tokens with this key (also added to the verification keys)
+    pub fn set_primary_key(&mut self, key: &JwtKey) -> Result<(), JwtError> {
+        let material = match (&key.secret, &key.private_key, self.use_secret()) {
+            (Some(secret), _, true) => secret.expose(),
+            (_, Some(private_key), false) => private_key.expose(),
+            _ => return Err(JwtError::EncodingKeyError(format!("missing key {}", key.kid))),
+        };
+        let encoding_key = self.encoding_key_from(material.trim())?;
+        self.add_key(key)?;
+
+        self.encoding_key = Some(Arc::new(encoding_key));
+        self.encoding_kid = Some(key.kid.clone());
+
+        Ok(())
+    }
+
+    /// Remove a verification key (e.g. the previous key once its tokens have expired)
+    pub fn remove_key(&mut self, kid: &str) -> Result<(), JwtError> {
+        if self.encoding_kid.as_deref() == Some(kid) {
+            return Err(JwtError::KeySetError(format!("{kid} is the primary key")));
+        }
+        if let Some(key_set) = &self.key_set {
+            key_set
+                .write()
+                .map_err(|err| JwtError::KeySetError(err.to_string()))?
+                .keys
+                .remove(kid);
+        }
+
+        Ok(())
+    }
+
+    /// Key ID of the key signing the tokens
+    pub fn primary_kid(&self) -> Option<&str> {
+        self.encoding_kid.as_deref()
+    }
+
+    /// Key IDs of the keys verifying the tokens
+    pub fn kids(&self) -> Vec<String> {
+        self.key_set
+            .as_ref()
+            .and_then(|key_set| key_set.read().ok())
+            .map(|key_set| key_set.kids().into_iter().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Build an encoding key for the algorithm
+    fn encoding_key_from(&self, secret: &str) -> Result<EncodingKey, JwtError> {
+        Ok(match self.algorithm {
             Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => EncodingKey::from_secret(secret.as_bytes()),
             Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(secret.as_bytes())
                 .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?,
@@ -153,16 +488,12 @@ impl Jwt {
                 .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?,
             Algorithm::EdDSA => EncodingKey::from_ed_pem(secret.as_bytes())
                 .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?,
-        };
-
-        self.encoding_key = Some(key);
-
-        Ok(())
+        })
     }
 
-    /// Update decoding key
-    pub fn set_decoding_key(&mut self, secret: &str) -> Result<(), JwtError> {
-        let key = match self.algorithm {
+    /// Build a decoding key for the algorithm
+    fn decoding_key_from(&self, secret: &str) -> Result<DecodingKey, JwtError> {
+        Ok(match self.algorithm {
             Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => DecodingKey::from_secret(secret.as_bytes()),
             Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(secret.as_bytes())
                 .map_err(|err| JwtError::DecodingKeyError(err.to_string()))?,
@@ -172,20 +503,102 @@ impl Jwt {
                 .map_err(|err| JwtError::DecodingKeyError(err.to_string()))?,
             Algorithm::EdDSA => DecodingKey::from_ed_pem(secret.as_bytes())
                 .map_err(|err| JwtError::DecodingKeyError(err.to_string()))?,
-        };
+        })
+    }
 
-        self.decoding_key = Some(key);
+    /// Use keys from a JWK set to verify tokens (selected by the token `kid` header)
+    pub fn set_jwks(&mut self, jwks: &JwkSet) {
+        let key_set = JwtKeySet::from_jwks(jwks);
+        match &self.key_set {
+            Some(current) => {
+                if let Ok(mut current) = current.write() {
+                    *current = key_set;
+                }
+            }
+            None => self.key_set = Some(Arc::new(RwLock::new(key_set))),
+        }
+    }
 
-        Ok(())
+    /// Get expected issuer
+    pub fn issuer(&self) -> Option<&str> {
+        self.validation.issuer.as_deref()
+    }
+
+    /// Update expected issuer (`iss` claim)
+    pub fn set_issuer(&mut self, issuer: &str) {
+        self.validation.issuer = Some(issuer.to_string());
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Update expected audience (`aud` claim)
+    pub fn set_audience(&mut self, audience: &[&str]) {
+        self.validation.audience = Some(audience.iter().map(|aud| aud.to_string()).collect());
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Update expected subject (`sub` claim)
+    pub fn set_subject(&mut self, subject: &str) {
+        self.validation.subject = Some(subject.to_string());
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Update the leeway of the time-based claims (in second)
+    pub fn set_leeway(&mut self, leeway: u64) {
+        self.validation.leeway = leeway;
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Update the claims which must be present in the tokens
+    pub fn set_required_claims(&mut self, claims: &[&str]) {
+        self.validation.required_claims = claims.iter().map(|claim| claim.to_string()).collect();
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Get the default validation of the tokens
+    pub fn validation(&self) -> &JwtValidation {
+        &self.validation
+    }
+
+    /// Update the default validation of the tokens
+    pub fn set_validation(&mut self, validation: JwtValidation) {
+        self.validation = validation;
+        self.compiled_validation = Arc::default();
+    }
+
+    /// Set the store of the revoked token IDs (`jti` claim), checked by the `JwtAuthLayer`
+    pub fn set_revocation_store(&mut self, store: impl TokenRevocationStore) {
+        self.revocation_store = Some(Arc::new(store));
+    }
+
+    /// Return true if a revocation store is set
+    pub fn has_revocation_store(&self) -> bool {
+        self.revocation_store.is_some()
+    }
+
+    /// Revoke a token ID until the expiration of the token (e.g. on logout)
+    pub async fn revoke(&self, jti: &str, expires_at: UtcDateTime) -> Result<(), JwtError> {
+        match &self.revocation_store {
+            Some(store) => store.revoke(jti, expires_at).await,
+            None => Err(JwtError::RevocationStoreError("no revocation store".to_owned())),
+        }
+    }
+
+    /// Return true if a token ID is revoked (always false without revocation store)
+    pub async fn is_revoked(&self, jti: &str) -> Result<bool, JwtError> {
+        match &self.revocation_store {
+            Some(store) => store.is_revoked(jti).await,
+            None => Ok(false),
+        }
     }
 
     /// Generate JWT
     pub fn generate<P: Debug + Serialize>(&self, payload: P, expired_at: UtcDateTime) -> Result<AccessToken, JwtError> {
-        let header = jsonwebtoken::Header::new(self.algorithm);
+        let mut header = jsonwebtoken::Header::new(self.algorithm);
+        header.kid = self.encoding_kid.clone();
 
-        match self.encoding_key.clone() {
+        match &self.encoding_key {
             Some(encoding_key) => {
-                let token = encode(&header, &payload, &encoding_key)
+                let token = encode(&header, &payload, encoding_key)
                     .map_err(|err| JwtError::EncodingKeyError(err.to_string()))?;
 
                 Ok(AccessToken { token, expired_at })
@@ -196,18 +609,63 @@ impl Jwt {
 
     /// Parse JWT
     pub fn parse<P: Clone + Debug + for<'de> Deserialize<'de>>(&self, token: &AccessToken) -> Result<P, JwtError> {
-        let validation = Validation::new(self.algorithm);
+        let (decoding_key, algorithm) = self.decoding_key_for(&token.token)?;
+
+        // The default validation is only built once, unless the key set accepts another algorithm
+        if algorithm == self.algorithm {
+            let validation = self
+                .compiled_validation
+                .get_or_init(|| self.validation.to_validation(self.algorithm));
+            Self::decode(&token.token, &decoding_key, validation)
+        } else {
+            Self::decode(&token.token, &decoding_key, &self.validation.to_validation(algorithm))
+        }
+    }
 
-        match self.decoding_key.clone() {
-            Some(decoding_key) => {
-                let token = decode::<P>(&token.token, &decoding_key, &validation).map_err(|err| match err.kind() {
-                    ExpiredSignature => JwtError::ExpiredToken,
-                    _ => JwtError::DecodingKeyError(err.to_string()),
-                })?;
+    /// Parse JWT with a specific validation instead of the default one
+    pub fn parse_with<P: Clone + Debug + for<'de> Deserialize<'de>>(
+        &self,
+        token: &AccessToken,
+        validation: &JwtValidation,
+    ) -> Result<P, JwtError> {
+        let (decoding_key, algorithm) = self.decoding_key_for(&token.token)?;
 
-                Ok(token.claims)
+        Self::decode(&token.token, &decoding_key, &validation.to_validation(algorithm))
+    }
+
+    /// Verify a token and deserialize its claims
+    fn decode<P: Clone + Debug + for<'de> Deserialize<'de>>(
+        token: &str,
+        decoding_key: &DecodingKey,
+        validation: &Validation,
+    ) -> Result<P, JwtError> {
+        let token = decode::<P>(token, decoding_key, validation).map_err(|err| match err.kind() {
+            ExpiredSignature => JwtError::ExpiredToken,
+            _ => JwtError::DecodingKeyError(err.to_string()),
+        })?;
+
+        Ok(token.claims)
+    }
+
+    /// Get the decoding key and the algorithm used to verify a token
+    fn decoding_key_for(&self, token: &str) -> Result<(Arc<DecodingKey>, Algorithm), JwtError> {
+        match (&self.key_set, &self.decoding_key) {
+            (Some(key_set), _) => {
+                let header = decode_header(token).map_err(|err| JwtError::ParseError(err.to_string()))?;
+                let (key, algorithm) = key_set
+                    .read()
+                    .map_err(|err| JwtError::DecodingKeyError(err.to_string()))?
+                    .get(header.kid.as_deref())?;
+
+                match algorithm {
+                    Some(algorithm) if algorithm != header.alg => {
+                        Err(JwtError::InvalidAlgorithm(format!("{:?}", header.alg)))
+                    }
+                    _ => Ok((key, header.alg)),
+                }
             }
-            _ => Err(JwtError::DecodingKeyError("empty key".to_owned())),
+            (None, Some(decoding_key)) => Ok((Arc::clone(decoding_key), self.algorithm)),
+            (None, None) => Err(JwtError::DecodingKeyError("empty key".to_owned())),
         }
     }
 
@@ -478,4 +936,274 @@ mod tests {
         let err = verifier.parse::<TestClaims>(&token).unwrap_err();
         assert!(matches!(err, JwtError::DecodingKeyError(_)));
     }
+
+    fn hs256_jwks(keys: &[(&str, &str)]) -> JwkSet {
+        use base64::Engine;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+        let keys = keys
+            .iter()
+            .map(|(kid, secret)| {
+                serde_json::json!({
+                    "kty": "oct",
+                    "kid": kid,
+                    "alg": "HS256",
+                    "k": URL_SAFE_NO_PAD.encode(secret),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({ "keys": keys })).unwrap()
+    }
+
+    fn token_with_kid(kid: Option<&str>, secret: &str, claims: &impl Serialize) -> AccessToken {
+        let mut header = jsonwebtoken::Header::new(Algorithm::HS256);
+        header.kid = kid.map(str::to_string);
+        let token = encode(&header, claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap();
+
+        AccessToken {
+            token,
+            expired_at: UtcDateTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_jwt_parse_with_jwks_selects_key_by_kid() {
+        let mut jwt = Jwt::default();
+        jwt.set_jwks(&hs256_jwks(&[("key-1", "secret-1"), ("key-2", "secret-2")]));
+        let claims = TestClaims {
+            sub: "user".to_string(),
+            exp: future_exp(60),
+        };
+
+        let parsed: TestClaims = jwt.parse(&token_with_kid(Some("key-2"), "secret-2", &claims)).unwrap();
+        assert_eq!(parsed, claims);
+
+        let err = jwt
+            .parse::<TestClaims>(&token_with_kid(Some("key-3"), "secret-2", &claims))
+            .unwrap_err();
+        assert_eq!(err, JwtError::UnknownKeyId("key-3".to_string()));
+
+        let err = jwt
+            .parse::<TestClaims>(&token_with_kid(Some("key-1"), "secret-2", &claims))
+            .unwrap_err();
+        assert!(matches!(err, JwtError::DecodingKeyError(_)));
+
+        // Without `kid`, a key cannot be chosen among several keys
+        let err = jwt
+            .parse::<TestClaims>(&token_with_kid(None, "secret-1", &claims))
+            .unwrap_err();
+        assert_eq!(err, JwtError::UnknownKeyId(String::new()));
+    }
+
+    #[test]
+    fn test_jwt_set_jwks_replaces_shared_keys() {
+        let mut jwt = Jwt::default();
+        jwt.set_jwks(&hs256_jwks(&[("key-1", "secret-1")]));
+        let clone = jwt.clone();
+        jwt.set_jwks(&hs256_jwks(&[("key-2", "secret-2")]));
+
+        let claims = TestClaims {
+            sub: "user".to_string(),
+            exp: future_exp(60),
+        };
+        assert!(
+            clone
+                .parse::<TestClaims>(&token_with_kid(None, "secret-2", &claims))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_jwt_parse_validates_issuer_and_audience() {
+        #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+        struct Claims {
+            sub: String,
+            exp: i64,
+            iss: String,
+            aud: String,
+        }
+
+        let mut jwt = Jwt::init("HS256", 15, 7 * 24, Some("secret"), None, None).expect("init");
+        jwt.set_issuer("https://idp.example.com");
+        jwt.set_audience(&["api"]);
+        assert_eq!(jwt.issuer(), Some("https://idp.example.com"));
+
+        let mut claims = Claims {
+            sub: "user".to_string(),
+            exp: future_exp(60),
+            iss: "https://idp.example.com".to_string(),
+            aud: "api".to_string(),
+        };
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(jwt.parse::<Claims>(&token).unwrap(), claims);
+
+        claims.iss = "https://other.example.com".to_string();
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert!(jwt.parse::<Claims>(&token).is_err());
+
+        claims.iss = "https://idp.example.com".to_string();
+        claims.aud = "other".to_string();
+        let token = jwt.generate(claims, UtcDateTime::now()).unwrap();
+        assert!(jwt.parse::<Claims>(&token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_jwt_revocation() {
+        let mut jwt = Jwt::init("HS256", 15, 24, Some("secret"), None, None).expect("init");
+        let expires_at = UtcDateTime::now().add(chrono::TimeDelta::minutes(5));
+        assert!(!jwt.is_revoked("token-1").await.unwrap());
+        assert!(matches!(
+            jwt.revoke("token-1", expires_at.clone()).await,
+            Err(JwtError::RevocationStoreError(_))
+        ));
+
+        jwt.set_revocation_store(revocation::MemoryRevocationStore::new());
+        assert!(jwt.has_revocation_store());
+        jwt.revoke("token-1", expires_at).await.unwrap();
+        assert!(jwt.is_revoked("token-1").await.unwrap());
+
+        // The clones share the store
+        assert!(jwt.clone().is_revoked("token-1").await.unwrap());
+        assert!(!jwt.is_revoked("token-2").await.unwrap());
+    }
+
+    #[test]
+    fn test_jwt_parse_with_validation() {
+        #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+        struct Claims {
+            sub: String,
+            exp: i64,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            aud: Option<String>,
+        }
+
+        let mut jwt = Jwt::init("HS256", 15, 7 * 24, Some("secret"), None, None).expect("init");
+        jwt.set_subject("user");
+        jwt.set_required_claims(&["exp", "sub"]);
+        assert_eq!(jwt.validation().leeway, JWT_DEFAULT_LEEWAY);
+
+        let mut claims = Claims {
+            sub: "user".to_string(),
+            exp: future_exp(-30),
+            aud: None,
+        };
+
+        // Expired for 30 seconds, accepted with the default leeway
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(jwt.parse::<Claims>(&token).unwrap(), claims);
+
+        jwt.set_leeway(0);
+        assert_eq!(jwt.parse::<Claims>(&token).unwrap_err(), JwtError::ExpiredToken);
+
+        claims.exp = future_exp(60);
+        claims.sub = "other".to_string();
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert!(jwt.parse::<Claims>(&token).is_err());
+
+        // Per-call override: the audience is required and checked
+        claims.sub = "user".to_string();
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        let admin = jwt
+            .validation()
+            .clone()
+            .with_audience(&["admin"])
+            .with_required_claims(&["exp", "sub", "aud"]);
+        assert!(jwt.parse::<Claims>(&token).is_ok());
+        assert!(jwt.parse_with::<Claims>(&token, &admin).is_err());
+
+        claims.aud = Some("admin".to_string());
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(jwt.parse_with::<Claims>(&token, &admin).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_jwt_clones_share_keys_and_refresh_validation() {
+        let mut jwt = Jwt::init("HS256", 15, 7 * 24, Some("secret"), None, None).expect("init");
+        let clone = jwt.clone();
+        assert!(Arc::ptr_eq(
+            jwt.encoding_key.as_ref().unwrap(),
+            clone.encoding_key.as_ref().unwrap()
+        ));
+        assert!(Arc::ptr_eq(
+            jwt.decoding_key.as_ref().unwrap(),
+            clone.decoding_key.as_ref().unwrap()
+        ));
+
+        let claims = TestClaims {
+            sub: "user".to_string(),
+            exp: future_exp(60),
+        };
+        let token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(jwt.parse::<TestClaims>(&token).unwrap(), claims);
+        assert!(jwt.compiled_validation.get().is_some());
+
+        // The cached validation is rebuilt after an update, the clone keeps its own
+        jwt.set_subject("other");
+        assert!(jwt.compiled_validation.get().is_none());
+        assert!(jwt.parse::<TestClaims>(&token).is_err());
+        assert_eq!(clone.parse::<TestClaims>(&token).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_jwt_key_rotation() {
+        let claims = TestClaims {
+            sub: "user".to_string(),
+            exp: future_exp(60),
+        };
+        let keys = [JwtKey::secret("key-1", "secret-1")];
+        let mut jwt = Jwt::init_with_keys("HS256", 15, 24, &keys, "key-1").expect("init");
+        assert_eq!(jwt.primary_kid(), Some("key-1"));
+        let old_token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(decode_header(&old_token.token).unwrap().kid.as_deref(), Some("key-1"));
+
+        // Rotation: the new key signs, the previous one still verifies
+        jwt.set_primary_key(&JwtKey::secret("key-2", "secret-2")).unwrap();
+        assert_eq!(jwt.kids(), vec!["key-1", "key-2"]);
+        let new_token = jwt.generate(claims.clone(), UtcDateTime::now()).unwrap();
+        assert_eq!(decode_header(&new_token.token).unwrap().kid.as_deref(), Some("key-2"));
+        assert_eq!(jwt.parse::<TestClaims>(&old_token).unwrap(), claims);
+        assert_eq!(jwt.parse::<TestClaims>(&new_token).unwrap(), claims);
+
+        // Retirement of the previous key
+        assert!(matches!(jwt.remove_key("key-2"), Err(JwtError::KeySetError(_))));
+        jwt.remove_key("key-1").unwrap();
+        assert_eq!(
+            jwt.parse::<TestClaims>(&old_token).unwrap_err(),
+            JwtError::UnknownKeyId("key-1".to_string())
+        );
+        assert_eq!(jwt.parse::<TestClaims>(&new_token).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_jwt_init_with_keys_errors() {
+        let keys = [JwtKey::secret("key-1", "secret-1")];
+        assert_eq!(
+            Jwt::init_with_keys("HS256", 15, 24, &keys, "key-2").unwrap_err(),
+            JwtError::UnknownKeyId("key-2".to_string())
+        );
+
+        // The verification keys of the other algorithms do not need a private key
+        let keys = [JwtKey::pair("key-1", None, "invalid")];
+        assert!(matches!(
+            Jwt::init_with_keys("ES256", 15, 24, &keys, "key-1"),
+            Err(JwtError::DecodingKeyError(_))
+        ));
+        assert!(matches!(
+            Jwt::init_with_keys("ES256", 15, 24, &[JwtKey::secret("key-1", "secret")], "key-1"),
+            Err(JwtError::DecodingKeyError(_))
+        ));
+    }
+
+    #[test]
+    fn test_jwt_key_set_ignores_keys_without_kid() {
+        let mut jwks = hs256_jwks(&[("key-1", "secret-1")]);
+        let mut no_kid = jwks.keys[0].clone();
+        no_kid.common.key_id = None;
+        jwks.keys.push(no_kid);
+
+        let key_set = JwtKeySet::from_jwks(&jwks);
+        assert_eq!(key_set.len(), 1);
+        assert!(!key_set.is_empty());
+    }
 }
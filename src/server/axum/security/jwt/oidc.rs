@@ -0,0 +1,439 @@
+//! OpenID Connect discovery (`oidc` feature)
+//!
+//! Build a [`Jwt`] verifying tokens issued by an identity provider:
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::security::jwt::Jwt;
+//!
+//! # async fn run() -> Result<(), api_tools::server::axum::security::jwt::JwtError> {
+//! let jwt = Jwt::from_oidc_discovery("https://accounts.example.com", Some("my-api")).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The validated identity is available in the handlers with the [`OidcIdentity`] extractor when
+//! the routes are wrapped by the `JwtAuthLayer<OidcIdentity>`:
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::layers::jwt_auth::JwtAuthLayer;
+//! use api_tools::server::axum::security::jwt::oidc::OidcIdentity;
+//! use axum::{Router, routing::get};
+//!
+//! async fn me(identity: OidcIdentity) -> String {
+//!     identity.sub
+//! }
+//!
+//! # async fn run() -> Result<(), api_tools::server::axum::security::jwt::JwtError> {
+//! let layer = JwtAuthLayer::<OidcIdentity>::from_oidc_discovery("https://accounts.example.com", Some("my-api")).await?;
+//! let app: Router = Router::new().route("/me", get(me)).layer(layer);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Jwt, JwtError, JwtKeySet};
+use crate::server::axum::layers::jwt_auth::{JwtAuthLayer, JwtClaims};
+use crate::server::axum::response::ApiError;
+use crate::server::axum::security::authorization::Grants;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::jwk::JwkSet;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Default JWKS refresh interval (1 hour)
+pub const OIDC_JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(3_600);
+
+/// HTTP requests timeout
+const OIDC_HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discovery document path
+const OIDC_DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// OpenID provider metadata (subset)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+}
+
+impl OidcDiscovery {
+    /// Fetch the discovery document of an issuer
+    ///
+    /// The `issuer` of the document must match `issuer_url`.
+    pub async fn fetch(client: &reqwest::Client, issuer_url: &str) -> Result<Self, JwtError> {
+        let issuer_url = issuer_url.trim_end_matches('/');
+        let discovery: Self = get_json(client, &format!("{issuer_url}{OIDC_DISCOVERY_PATH}")).await?;
+
+        if discovery.issuer.trim_end_matches('/') != issuer_url {
+            return Err(JwtError::DiscoveryError(format!(
+                "issuer mismatch: expected {issuer_url}, got {}",
+                discovery.issuer
+            )));
+        }
+
+        Ok(discovery)
+    }
+
+    /// Fetch the JWK set
+    pub async fn fetch_jwks(&self, client: &reqwest::Client) -> Result<JwkSet, JwtError> {
+        get_json(client, &self.jwks_uri).await
+    }
+}
+
+impl Jwt {
+    /// Create a `Jwt` from the OpenID Connect discovery document of an issuer
+    ///
+    /// The JWKS is refreshed every hour, see [`Jwt::from_oidc_discovery_with_refresh`].
+    pub async fn from_oidc_discovery(issuer_url: &str, audience: Option<&str>) -> Result<Self, JwtError> {
+        Self::from_oidc_discovery_with_refresh(issuer_url, audience, OIDC_JWKS_REFRESH_INTERVAL).await
+    }
+
+    /// Create a `Jwt` from the OpenID Connect discovery document of an issuer
+    ///
+    /// - The expected issuer is the one of the discovery document
+    /// - Tokens are verified with the JWKS keys (selected by `kid`)
+    /// - The JWKS is refreshed every `refresh_interval` in a background task, which stops
+    ///   when all the `Jwt` clones are dropped. If a refresh fails, the previous keys are kept.
+    ///
+    /// The returned `Jwt` can only parse tokens.
+    pub async fn from_oidc_discovery_with_refresh(
+        issuer_url: &str,
+        audience: Option<&str>,
+        refresh_interval: Duration,
+    ) -> Result<Self, JwtError> {
+        let client = reqwest::Client::builder()
+            .timeout(OIDC_HTTP_TIMEOUT)
+            .build()
+            .map_err(|err| JwtError::DiscoveryError(err.to_string()))?;
+
+        let discovery = OidcDiscovery::fetch(&client, issuer_url).await?;
+        let jwks = discovery.fetch_jwks(&client).await?;
+
+        let algorithm = discovery
+            .id_token_signing_alg_values_supported
+            .iter()
+            .find_map(|alg| Algorithm::from_str(alg).ok())
+            .unwrap_or(Algorithm::RS256);
+        let key_set = Arc::new(RwLock::new(JwtKeySet::from_jwks(&jwks)));
+
+        let mut jwt = Jwt {
+            algorithm,
+            key_set: Some(key_set.clone()),
+            ..Default::default()
+        };
+        jwt.set_issuer(&discovery.issuer);
+        if let Some(audience) = audience {
+            jwt.set_audience(&[audience]);
+        }
+
+        spawn_jwks_refresh(client, discovery, Arc::downgrade(&key_set), refresh_interval);
+
+        Ok(jwt)
+    }
+}
+
+/// Identity of a token validated by the `JwtAuthLayer<OidcIdentity>` (standard OpenID Connect claims)
+///
+/// The issuer, audience, expiration and signature are validated by the [`Jwt`].
+/// The grants are the `roles` claim and the scopes of the `scope` claim.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OidcIdentity {
+    /// Subject
+    pub sub: String,
+
+    /// Issuer
+    pub iss: String,
+
+    /// Expiration time (timestamp)
+    pub exp: i64,
+
+    #[serde(default)]
+    pub email: Option<String>,
+
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+
+    /// Space-separated list of scopes
+    #[serde(default)]
+    pub scope: Option<String>,
+
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl JwtClaims for OidcIdentity {
+    fn subject(&self) -> Option<&str> {
+        Some(&self.sub)
+    }
+
+    fn grants(&self) -> Grants {
+        let roles = self.roles.iter().map(String::as_str).collect::<Vec<_>>();
+        Grants::new()
+            .with_roles(&roles)
+            .with_scope_claim(self.scope.as_deref().unwrap_or_default())
+    }
+}
+
+impl<S> FromRequestParts<S> for OidcIdentity
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<OidcIdentity>()
+            .cloned()
+            .ok_or_else(|| ApiError::Unauthorized("Missing OIDC identity".to_string()))
+    }
+}
+
+impl JwtAuthLayer<OidcIdentity> {
+    /// Create a `JwtAuthLayer` validating the tokens of an OpenID Connect issuer
+    ///
+    /// See [`Jwt::from_oidc_discovery`].
+    pub async fn from_oidc_discovery(issuer_url: &str, audience: Option<&str>) -> Result<Self, JwtError> {
+        Ok(Self::new(Jwt::from_oidc_discovery(issuer_url, audience).await?))
+    }
+}
+
+/// Refresh periodically the JWKS until the key set is dropped
+fn spawn_jwks_refresh(
+    client: reqwest::Client,
+    discovery: OidcDiscovery,
+    key_set: std::sync::Weak<RwLock<JwtKeySet>>,
+    refresh_interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(refresh_interval).await;
+
+            if key_set.strong_count() == 0 {
+                break;
+            }
+            let jwks = match discovery.fetch_jwks(&client).await {
+                Ok(jwks) => jwks,
+                Err(err) => {
+                    tracing::warn!(issuer = %discovery.issuer, "JWKS refresh failed: {err}");
+                    continue;
+                }
+            };
+            let Some(key_set) = key_set.upgrade() else {
+                break;
+            };
+            if let Ok(mut key_set) = key_set.write() {
+                *key_set = JwtKeySet::from_jwks(&jwks);
+            }
+        }
+    });
+}
+
+/// Send a GET request and deserialize the JSON response
+async fn get_json<T: for<'de> Deserialize<'de>>(client: &reqwest::Client, url: &str) -> Result<T, JwtError> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| JwtError::DiscoveryError(err.to_string()))?
+        .json::<T>()
+        .await
+        .map_err(|err| JwtError::DiscoveryError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::axum::security::authorization::Grant;
+    use crate::server::axum::security::jwt::access_token::AccessToken;
+    use crate::value_objects::datetime::UtcDateTime;
+    use axum::Router;
+    use axum::extract::State;
+    use axum::routing::get;
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde::{Deserialize, Serialize};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Claims {
+        sub: String,
+        iss: String,
+        aud: String,
+        exp: i64,
+    }
+
+    #[derive(Clone)]
+    struct Provider {
+        issuer: Arc<Mutex<String>>,
+        kid: Arc<Mutex<&'static str>>,
+    }
+
+    fn jwks(kid: &str) -> serde_json::Value {
+        serde_json::json!({
+            "keys": [{ "kty": "oct", "kid": kid, "alg": "HS256", "k": URL_SAFE_NO_PAD.encode("secret") }]
+        })
+    }
+
+    /// Start a fake identity provider, returning its URL
+    async fn start_provider(provider: Provider) -> String {
+        let app = Router::new()
+            .route(
+                OIDC_DISCOVERY_PATH,
+                get(|State(provider): State<Provider>| async move {
+                    let issuer = provider.issuer.lock().unwrap().clone();
+                    axum::Json(serde_json::json!({
+                        "issuer": issuer,
+                        "jwks_uri": format!("{issuer}/jwks"),
+                        "id_token_signing_alg_values_supported": ["HS256"],
+                    }))
+                }),
+            )
+            .route(
+                "/jwks",
+                get(|State(provider): State<Provider>| async move { axum::Json(jwks(*provider.kid.lock().unwrap())) }),
+            )
+            .with_state(provider.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        *provider.issuer.lock().unwrap() = url.clone();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        url
+    }
+
+    fn token(kid: &str, issuer: &str) -> AccessToken {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(kid.to_string());
+        let claims = Claims {
+            sub: "user".to_string(),
+            iss: issuer.to_string(),
+            aud: "api".to_string(),
+            exp: chrono::Utc::now().timestamp() + 60,
+        };
+
+        AccessToken {
+            token: encode(&header, &claims, &EncodingKey::from_secret(b"secret")).unwrap(),
+            expired_at: UtcDateTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_oidc_discovery() {
+        let provider = Provider {
+            issuer: Arc::new(Mutex::new(String::new())),
+            kid: Arc::new(Mutex::new("key-1")),
+        };
+        let url = start_provider(provider.clone()).await;
+
+        let jwt = Jwt::from_oidc_discovery_with_refresh(&format!("{url}/"), Some("api"), Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(jwt.issuer(), Some(url.as_str()));
+        assert_eq!(jwt.algorithm, Algorithm::HS256);
+
+        let claims: Claims = jwt.parse(&token("key-1", &url)).unwrap();
+        assert_eq!(claims.sub, "user");
+        assert!(
+            jwt.parse::<Claims>(&token("key-1", "https://other.example.com"))
+                .is_err()
+        );
+
+        // Keys rotation
+        *provider.kid.lock().unwrap() = "key-2";
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            jwt.parse::<Claims>(&token("key-1", &url)).unwrap_err(),
+            JwtError::UnknownKeyId("key-1".to_string())
+        );
+        assert!(jwt.parse::<Claims>(&token("key-2", &url)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_from_oidc_discovery_rejects_issuer_mismatch() {
+        let provider = Provider {
+            issuer: Arc::new(Mutex::new(String::new())),
+            kid: Arc::new(Mutex::new("key-1")),
+        };
+        let url = start_provider(provider.clone()).await;
+        *provider.issuer.lock().unwrap() = "https://other.example.com".to_string();
+
+        let err = Jwt::from_oidc_discovery(&url, None).await.unwrap_err();
+        assert!(matches!(err, JwtError::DiscoveryError(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer_with_oidc_identity() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode, header};
+        use tower::ServiceExt;
+
+        let provider = Provider {
+            issuer: Arc::new(Mutex::new(String::new())),
+            kid: Arc::new(Mutex::new("key-1")),
+        };
+        let url = start_provider(provider).await;
+
+        let layer = JwtAuthLayer::<OidcIdentity>::from_oidc_discovery(&url, Some("api"))
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route("/me", get(|identity: OidcIdentity| async move { identity.sub }))
+            .layer(layer);
+
+        let request = Request::get("/me")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token("key-1", &url).token))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1_024).await.unwrap();
+        assert_eq!(body, "user");
+
+        let request = Request::get("/me")
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token("key-1", "https://other.example.com").token),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_oidc_identity_grants() {
+        let identity: OidcIdentity = serde_json::from_value(serde_json::json!({
+            "sub": "user",
+            "iss": "https://accounts.example.com",
+            "exp": 0,
+            "scope": "users:read users:write",
+            "roles": ["admin"],
+        }))
+        .unwrap();
+        let grants = identity.grants();
+        assert_eq!(identity.subject(), Some("user"));
+        assert!(grants.contains(&Grant::role("admin")));
+        assert!(grants.contains(&Grant::scope("users:write")));
+    }
+
+    #[tokio::test]
+    async fn test_from_oidc_discovery_unreachable_issuer() {
+        let err = Jwt::from_oidc_discovery("http://127.0.0.1:1", None).await.unwrap_err();
+        assert!(matches!(err, JwtError::DiscoveryError(_)));
+    }
+}
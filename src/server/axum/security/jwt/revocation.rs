@@ -0,0 +1,129 @@
+//! JWT revocation
+//!
+//! A [`TokenRevocationStore`] keeps the IDs (`jti` claim) of the tokens revoked before their
+//! expiration (logout, compromised token). Set it with [`Jwt::set_revocation_store`](super::Jwt::set_revocation_store):
+//! the `JwtAuthLayer` then rejects the revoked tokens, and the logout handlers call
+//! [`Jwt::revoke`](super::Jwt::revoke).
+//!
+//! A revoked ID only needs to be kept until the token expires: the expired tokens are rejected
+//! anyway.
+//!
+//! ```rust
+//! use api_tools::server::axum::security::jwt::Jwt;
+//! use api_tools::server::axum::security::jwt::revocation::MemoryRevocationStore;
+//! use api_tools::value_objects::datetime::UtcDateTime;
+//! use chrono::TimeDelta;
+//!
+//! # async fn run() -> Result<(), api_tools::server::axum::security::jwt::JwtError> {
+//! let mut jwt = Jwt::init("HS256", 15, 24, Some("secret"), None, None).unwrap();
+//! jwt.set_revocation_store(MemoryRevocationStore::new());
+//!
+//! let expires_at = UtcDateTime::now().add(TimeDelta::minutes(15));
+//! jwt.revoke("0f8fad5b-d9cb-469f-a165-70867728950e", expires_at).await?;
+//! assert!(jwt.is_revoked("0f8fad5b-d9cb-469f-a165-70867728950e").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::JwtError;
+use crate::value_objects::datetime::UtcDateTime;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Store of the revoked token IDs (`jti` claim)
+pub trait TokenRevocationStore: Send + Sync + 'static {
+    /// Revoke a token ID until the expiration of the token
+    fn revoke<'a>(&'a self, jti: &'a str, expires_at: UtcDateTime) -> BoxFuture<'a, Result<(), JwtError>>;
+
+    /// Return true if the token ID is revoked
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, Result<bool, JwtError>>;
+}
+
+/// In-memory revocation store, not shared between the instances of the service
+///
+/// The expired IDs are purged when a token is revoked.
+#[derive(Debug, Default)]
+pub struct MemoryRevocationStore {
+    revoked: Mutex<HashMap<String, i64>>,
+}
+
+impl MemoryRevocationStore {
+    /// Create a new `MemoryRevocationStore`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of revoked IDs (including the expired ones not purged yet)
+    pub fn len(&self) -> usize {
+        self.revoked.lock().map(|revoked| revoked.len()).unwrap_or_default()
+    }
+
+    /// Return true if no token is revoked
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn revoke_sync(&self, jti: &str, expires_at: UtcDateTime) -> Result<(), JwtError> {
+        let mut revoked = self
+            .revoked
+            .lock()
+            .map_err(|err| JwtError::RevocationStoreError(err.to_string()))?;
+        let now = UtcDateTime::now().timestamp();
+
+        revoked.retain(|_, expiration| *expiration > now);
+        if expires_at.timestamp() > now {
+            revoked.insert(jti.to_string(), expires_at.timestamp());
+        }
+
+        Ok(())
+    }
+
+    fn is_revoked_sync(&self, jti: &str) -> Result<bool, JwtError> {
+        let revoked = self
+            .revoked
+            .lock()
+            .map_err(|err| JwtError::RevocationStoreError(err.to_string()))?;
+
+        Ok(revoked
+            .get(jti)
+            .is_some_and(|expiration| *expiration > UtcDateTime::now().timestamp()))
+    }
+}
+
+impl TokenRevocationStore for MemoryRevocationStore {
+    fn revoke<'a>(&'a self, jti: &'a str, expires_at: UtcDateTime) -> BoxFuture<'a, Result<(), JwtError>> {
+        Box::pin(async move { self.revoke_sync(jti, expires_at) })
+    }
+
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, Result<bool, JwtError>> {
+        Box::pin(async move { self.is_revoked_sync(jti) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[tokio::test]
+    async fn test_memory_revocation_store() {
+        let store = MemoryRevocationStore::new();
+        assert!(store.is_empty());
+
+        store
+            .revoke("token-1", UtcDateTime::now().add(TimeDelta::minutes(5)))
+            .await
+            .unwrap();
+        assert!(store.is_revoked("token-1").await.unwrap());
+        assert!(!store.is_revoked("token-2").await.unwrap());
+
+        // An expired token does not need to be kept
+        store
+            .revoke("token-2", UtcDateTime::now().sub(TimeDelta::minutes(5)))
+            .await
+            .unwrap();
+        assert!(!store.is_revoked("token-2").await.unwrap());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -1,4 +1,9 @@
 //! Security module
 
+pub mod authorization;
 #[cfg(feature = "axum")]
 pub mod jwt;
+#[cfg(feature = "password")]
+pub mod password;
+#[cfg(feature = "oidc")]
+pub mod token_exchange;
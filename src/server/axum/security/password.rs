@@ -0,0 +1,391 @@
+//! Password hashing and strength checking (`password` feature)
+//!
+//! Passwords are hashed with Argon2id in the PHC string format (`$argon2id$v=19$m=...`).
+//! [`verify_password`] returns a new hash when the stored one uses other parameters,
+//! so that hashes are upgraded transparently at login:
+//!
+//! ```rust
+//! use api_tools::server::axum::security::password::{
+//!     PasswordParams, PasswordVerification, hash_password, verify_password,
+//! };
+//!
+//! let params = PasswordParams::default();
+//! let hash = hash_password("correct horse battery staple", &params).unwrap();
+//!
+//! match verify_password("correct horse battery staple", &hash, &params).unwrap() {
+//!     PasswordVerification::Valid => {}
+//!     PasswordVerification::ValidRehashed(new_hash) => { /* Save `new_hash` */ }
+//!     PasswordVerification::Invalid => { /* 401 */ }
+//! }
+//! ```
+//!
+//! Hashing is CPU intensive (tens of milliseconds), call it with `tokio::task::spawn_blocking`
+//! in request handlers.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/// Password errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PasswordError {
+    #[error("Invalid password hash parameters: {0}")]
+    InvalidParams(String),
+
+    #[error("Password hash error: {0}")]
+    HashError(String),
+
+    #[error("Invalid password hash: {0}")]
+    InvalidHash(String),
+}
+
+/// Argon2id parameters
+///
+/// The default values are the OWASP recommendations (19 MiB, 2 iterations, 1 degree of parallelism).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordParams {
+    /// Memory size in KiB
+    pub memory_cost: u32,
+
+    /// Number of iterations
+    pub time_cost: u32,
+
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for PasswordParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl PasswordParams {
+    fn argon2(&self) -> Result<Argon2<'static>, PasswordError> {
+        let params = Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+            .map_err(|err| PasswordError::InvalidParams(err.to_string()))?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Return true if the hash uses other parameters (or another algorithm)
+    fn need_rehash(&self, hash: &PasswordHash<'_>) -> bool {
+        let Ok(params) = Params::try_from(hash) else {
+            return true;
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.memory_cost
+            || params.t_cost() != self.time_cost
+            || params.p_cost() != self.parallelism
+    }
+}
+
+/// Result of a password verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordVerification {
+    /// Wrong password
+    Invalid,
+
+    /// Valid password
+    Valid,
+
+    /// Valid password, with a new hash to store because the parameters have changed
+    ValidRehashed(String),
+}
+
+impl PasswordVerification {
+    /// Return true if the password is valid
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, Self::Invalid)
+    }
+}
+
+/// Hash a password with Argon2id and a random salt
+pub fn hash_password(password: &str, params: &PasswordParams) -> Result<String, PasswordError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    params
+        .argon2()?
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| PasswordError::HashError(err.to_string()))
+}
+
+/// Verify a password against a hash
+///
+/// If the password is valid but the hash does not use `params`, the password is hashed again.
+pub fn verify_password(
+    password: &str,
+    hash: &str,
+    params: &PasswordParams,
+) -> Result<PasswordVerification, PasswordError> {
+    let parsed_hash = PasswordHash::new(hash).map_err(|err| PasswordError::InvalidHash(err.to_string()))?;
+
+    // The hash parameters are used for the verification
+    match Argon2::default().verify_password(password.as_bytes(), &parsed_hash) {
+        Ok(()) if params.need_rehash(&parsed_hash) => {
+            Ok(PasswordVerification::ValidRehashed(hash_password(password, params)?))
+        }
+        Ok(()) => Ok(PasswordVerification::Valid),
+        Err(argon2::password_hash::Error::Password) => Ok(PasswordVerification::Invalid),
+        Err(err) => Err(PasswordError::InvalidHash(err.to_string())),
+    }
+}
+
+/// Password policy violation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort(usize),
+    TooLong(usize),
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    Forbidden,
+}
+
+impl Display for PasswordViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooShort(min) => write!(f, "Password must contain at least {min} characters"),
+            Self::TooLong(max) => write!(f, "Password must contain at most {max} characters"),
+            Self::MissingLowercase => write!(f, "Password must contain a lowercase letter"),
+            Self::MissingUppercase => write!(f, "Password must contain an uppercase letter"),
+            Self::MissingDigit => write!(f, "Password must contain a digit"),
+            Self::MissingSymbol => write!(f, "Password must contain a symbol"),
+            Self::Forbidden => write!(f, "Password is too common"),
+        }
+    }
+}
+
+/// Password strength
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordStrength {
+    /// Score from 0 (empty) to 4: one point per character class (lowercase, uppercase, digit,
+    /// symbol), plus one point if the password is at least twice the minimum length
+    pub score: u8,
+
+    /// Policy violations
+    pub violations: Vec<PasswordViolation>,
+}
+
+impl PasswordStrength {
+    /// Return true if the password respects the policy
+    pub fn is_acceptable(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Password policy
+///
+/// The default policy requires 12 to 128 characters with lowercase and uppercase letters and digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+
+    /// Forbidden passwords (case-insensitive), e.g. the most common ones
+    pub forbidden: Vec<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            max_length: 128,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            forbidden: Vec::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Create the default policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum and maximum lengths (in characters)
+    pub fn with_length(mut self, min_length: usize, max_length: usize) -> Self {
+        self.min_length = min_length;
+        self.max_length = max_length;
+        self
+    }
+
+    /// Require a symbol (non-alphanumeric character)
+    pub fn with_symbol(mut self) -> Self {
+        self.require_symbol = true;
+        self
+    }
+
+    /// Set the forbidden passwords
+    pub fn with_forbidden(mut self, passwords: &[&str]) -> Self {
+        self.forbidden = passwords.iter().map(|password| password.to_lowercase()).collect();
+        self
+    }
+
+    /// Check the strength of a password
+    pub fn check(&self, password: &str) -> PasswordStrength {
+        let length = password.chars().count();
+        let lowercase = password.chars().any(char::is_lowercase);
+        let uppercase = password.chars().any(char::is_uppercase);
+        let digit = password.chars().any(|c| c.is_ascii_digit());
+        let symbol = password.chars().any(|c| !c.is_alphanumeric());
+
+        let mut violations = Vec::new();
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort(self.min_length));
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong(self.max_length));
+        }
+        if self.require_lowercase && !lowercase {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !uppercase {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+        if self.require_digit && !digit {
+            violations.push(PasswordViolation::MissingDigit);
+        }
+        if self.require_symbol && !symbol {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+        if self.forbidden.contains(&password.to_lowercase()) {
+            violations.push(PasswordViolation::Forbidden);
+        }
+
+        let classes = [lowercase, uppercase, digit, symbol]
+            .iter()
+            .filter(|class| **class)
+            .count() as u8;
+        let long = length > 0 && length >= 2 * self.min_length;
+
+        PasswordStrength {
+            score: (classes + u8::from(long)).min(4),
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Low-cost parameters to keep the tests fast
+    fn params() -> PasswordParams {
+        PasswordParams {
+            memory_cost: 1_024,
+            time_cost: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_hash_and_verify_password() {
+        let hash = hash_password("my password", &params()).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_ne!(hash, hash_password("my password", &params()).unwrap());
+
+        assert_eq!(
+            verify_password("my password", &hash, &params()).unwrap(),
+            PasswordVerification::Valid
+        );
+        assert_eq!(
+            verify_password("wrong password", &hash, &params()).unwrap(),
+            PasswordVerification::Invalid
+        );
+        assert!(matches!(
+            verify_password("my password", "invalid", &params()),
+            Err(PasswordError::InvalidHash(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_password_rehashes_with_new_params() {
+        let hash = hash_password("my password", &params()).unwrap();
+        let new_params = PasswordParams {
+            time_cost: 2,
+            ..params()
+        };
+
+        let PasswordVerification::ValidRehashed(new_hash) = verify_password("my password", &hash, &new_params).unwrap()
+        else {
+            panic!("password should be rehashed");
+        };
+        assert!(new_hash.starts_with("$argon2id$v=19$m=1024,t=2,p=1$"));
+        assert_eq!(
+            verify_password("my password", &new_hash, &new_params).unwrap(),
+            PasswordVerification::Valid
+        );
+        assert_eq!(
+            verify_password("wrong password", &hash, &new_params).unwrap(),
+            PasswordVerification::Invalid
+        );
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let params = PasswordParams {
+            memory_cost: 1,
+            ..params()
+        };
+        assert!(matches!(
+            hash_password("my password", &params),
+            Err(PasswordError::InvalidParams(_))
+        ));
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy::new().with_forbidden(&["Password1234"]);
+
+        let strength = policy.check("Tr0ub4dor&3xyz");
+        assert!(strength.is_acceptable());
+        assert_eq!(strength.score, 4);
+
+        let strength = policy.check("password");
+        assert_eq!(
+            strength.violations,
+            vec![
+                PasswordViolation::TooShort(12),
+                PasswordViolation::MissingUppercase,
+                PasswordViolation::MissingDigit,
+            ]
+        );
+        assert_eq!(strength.score, 1);
+        assert_eq!(
+            strength.violations[0].to_string(),
+            "Password must contain at least 12 characters"
+        );
+
+        assert_eq!(
+            policy.check("PASSWORD1234").violations,
+            vec![PasswordViolation::MissingLowercase, PasswordViolation::Forbidden]
+        );
+        assert_eq!(
+            PasswordPolicy::new().with_symbol().check("Password1234").violations,
+            vec![PasswordViolation::MissingSymbol]
+        );
+        assert_eq!(
+            PasswordPolicy::new().with_length(1, 4).check("Pass1").violations,
+            vec![PasswordViolation::TooLong(4)]
+        );
+        assert_eq!(policy.check("").score, 0);
+    }
+}
@@ -0,0 +1,372 @@
+//! OAuth 2.0 Token Exchange ([RFC 8693](https://www.rfc-editor.org/rfc/rfc8693)) (`oidc` feature)
+//!
+//! A gateway exchanges the user token it received for a token dedicated to an internal
+//! service (other audience, reduced scopes):
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::security::token_exchange::{TokenExchangeClient, TokenExchangeRequest};
+//!
+//! # async fn run(user_token: &str) -> Result<(), api_tools::server::axum::security::token_exchange::TokenExchangeError> {
+//! let client = TokenExchangeClient::new("https://idp.example.com/oauth/token", "gateway", Some("secret"))?;
+//! let request = TokenExchangeRequest::new(user_token)
+//!     .with_audience("orders-service")
+//!     .with_scopes(&["orders:read"]);
+//! let token = client.exchange(&request).await?;
+//! println!("{}", token.access_token);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::axum::response::ApiError;
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Token exchange grant type
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+
+/// Access token type identifier
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// JWT token type identifier
+pub const JWT_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+/// HTTP requests timeout
+const TOKEN_EXCHANGE_HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Token exchange errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TokenExchangeError {
+    #[error("Token exchange HTTP error: {0}")]
+    Http(String),
+
+    #[error("Token exchange rejected: {error}")]
+    Rejected { error: String, description: Option<String> },
+
+    #[error("Invalid token exchange response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Token exchange error
+impl From<TokenExchangeError> for ApiError {
+    fn from(value: TokenExchangeError) -> Self {
+        Self::InternalServerError(value.to_string())
+    }
+}
+
+/// Token exchange request parameters
+#[derive(Clone, PartialEq)]
+pub struct TokenExchangeRequest {
+    pub subject_token: String,
+    pub subject_token_type: String,
+    pub requested_token_type: Option<String>,
+    pub audience: Option<String>,
+    pub resource: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+impl Debug for TokenExchangeRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TokenExchangeRequest => audience: {:?}, resource: {:?}, scopes: {:?}",
+            self.audience, self.resource, self.scopes
+        )
+    }
+}
+
+impl TokenExchangeRequest {
+    /// Create a new request for an access token
+    pub fn new(subject_token: &str) -> Self {
+        Self {
+            subject_token: subject_token.to_string(),
+            subject_token_type: ACCESS_TOKEN_TYPE.to_string(),
+            requested_token_type: None,
+            audience: None,
+            resource: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Update the subject token type (default: access token)
+    pub fn with_subject_token_type(mut self, token_type: &str) -> Self {
+        self.subject_token_type = token_type.to_string();
+        self
+    }
+
+    /// Set the requested token type
+    pub fn with_requested_token_type(mut self, token_type: &str) -> Self {
+        self.requested_token_type = Some(token_type.to_string());
+        self
+    }
+
+    /// Set the target service
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Set the target resource URI
+    pub fn with_resource(mut self, resource: &str) -> Self {
+        self.resource = Some(resource.to_string());
+        self
+    }
+
+    /// Set the requested scopes
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    /// Form parameters sent to the token endpoint
+    fn form(&self) -> Vec<(&'static str, String)> {
+        let mut form = vec![
+            ("grant_type", TOKEN_EXCHANGE_GRANT_TYPE.to_string()),
+            ("subject_token", self.subject_token.clone()),
+            ("subject_token_type", self.subject_token_type.clone()),
+        ];
+        if let Some(token_type) = &self.requested_token_type {
+            form.push(("requested_token_type", token_type.clone()));
+        }
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience.clone()));
+        }
+        if let Some(resource) = &self.resource {
+            form.push(("resource", resource.clone()));
+        }
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+
+        form
+    }
+}
+
+/// Token exchange response
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct TokenExchangeResponse {
+    pub access_token: String,
+    pub issued_token_type: String,
+    pub token_type: String,
+    pub expires_in: Option<u64>,
+    pub scope: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+impl Debug for TokenExchangeResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TokenExchangeResponse => issued_token_type: {}, token_type: {}, expires_in: {:?}, scope: {:?}",
+            self.issued_token_type, self.token_type, self.expires_in, self.scope
+        )
+    }
+}
+
+/// Error response of the authorization server
+#[derive(Deserialize)]
+struct OAuthErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Token exchange client of an authorization server
+#[derive(Clone)]
+pub struct TokenExchangeClient {
+    token_endpoint: String,
+    client_id: String,
+    client_secret: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Debug for TokenExchangeClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TokenExchangeClient => token_endpoint: {}, client_id: {}",
+            self.token_endpoint, self.client_id
+        )
+    }
+}
+
+impl TokenExchangeClient {
+    /// Create a new client
+    ///
+    /// The client authenticates with HTTP Basic if `client_secret` is set,
+    /// otherwise `client_id` is sent in the form (public client).
+    pub fn new(token_endpoint: &str, client_id: &str, client_secret: Option<&str>) -> Result<Self, TokenExchangeError> {
+        let http = reqwest::Client::builder()
+            .timeout(TOKEN_EXCHANGE_HTTP_TIMEOUT)
+            .build()
+            .map_err(|err| TokenExchangeError::Http(err.to_string()))?;
+
+        Ok(Self {
+            token_endpoint: token_endpoint.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.map(str::to_string),
+            http,
+        })
+    }
+
+    /// Exchange a token
+    pub async fn exchange(&self, request: &TokenExchangeRequest) -> Result<TokenExchangeResponse, TokenExchangeError> {
+        let mut form = request.form();
+        let mut http_request = self.http.post(&self.token_endpoint);
+        match &self.client_secret {
+            Some(secret) => http_request = http_request.basic_auth(&self.client_id, Some(secret)),
+            None => form.push(("client_id", self.client_id.clone())),
+        }
+
+        let response = http_request
+            .form(&form)
+            .send()
+            .await
+            .map_err(|err| TokenExchangeError::Http(err.to_string()))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| TokenExchangeError::Http(err.to_string()))?;
+
+        if !status.is_success() {
+            return Err(match serde_json::from_slice::<OAuthErrorResponse>(&body) {
+                Ok(error) => TokenExchangeError::Rejected {
+                    error: error.error,
+                    description: error.error_description,
+                },
+                Err(_) => TokenExchangeError::Http(format!("unexpected status code {status}")),
+            });
+        }
+
+        serde_json::from_slice(&body).map_err(|err| TokenExchangeError::InvalidResponse(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Form;
+    use axum::Router;
+    use axum::http::{HeaderMap, StatusCode, header};
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use std::collections::HashMap;
+
+    /// Fake token endpoint echoing the received parameters in the issued token
+    async fn token_endpoint(headers: HeaderMap, Form(form): Form<HashMap<String, String>>) -> axum::response::Response {
+        if form.get("subject_token").map(String::as_str) == Some("revoked") {
+            return (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({ "error": "invalid_grant", "error_description": "revoked token" })),
+            )
+                .into_response();
+        }
+        if form.get("subject_token").map(String::as_str) == Some("broken") {
+            return (StatusCode::OK, "not json").into_response();
+        }
+
+        let authorization = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("none");
+        let mut params = form.into_iter().collect::<Vec<_>>();
+        params.sort();
+        let params = params
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        axum::Json(serde_json::json!({
+            "access_token": format!("{authorization}|{params}"),
+            "issued_token_type": ACCESS_TOKEN_TYPE,
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "scope": "orders:read",
+        }))
+        .into_response()
+    }
+
+    async fn start_server() -> String {
+        let app = Router::new().route("/token", post(token_endpoint));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_exchange_with_confidential_client() {
+        let url = start_server().await;
+        let client = TokenExchangeClient::new(&url, "gateway", Some("secret")).unwrap();
+        let request = TokenExchangeRequest::new("user-token")
+            .with_audience("orders")
+            .with_scopes(&["orders:read", "orders:write"]);
+
+        let response = client.exchange(&request).await.unwrap();
+        assert_eq!(
+            response.access_token,
+            format!(
+                "Basic Z2F0ZXdheTpzZWNyZXQ=|audience=orders&grant_type={TOKEN_EXCHANGE_GRANT_TYPE}\
+                &scope=orders:read orders:write&subject_token=user-token&subject_token_type={ACCESS_TOKEN_TYPE}"
+            )
+        );
+        assert_eq!(response.token_type, "Bearer");
+        assert_eq!(response.expires_in, Some(300));
+        assert_eq!(response.scope.as_deref(), Some("orders:read"));
+        assert!(!format!("{response:?}").contains("user-token"));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_with_public_client() {
+        let url = start_server().await;
+        let client = TokenExchangeClient::new(&url, "gateway", None).unwrap();
+        let request = TokenExchangeRequest::new("user-token")
+            .with_subject_token_type(JWT_TOKEN_TYPE)
+            .with_requested_token_type(JWT_TOKEN_TYPE)
+            .with_resource("https://orders.internal");
+
+        let response = client.exchange(&request).await.unwrap();
+        assert_eq!(
+            response.access_token,
+            format!(
+                "none|client_id=gateway&grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&requested_token_type={JWT_TOKEN_TYPE}\
+                &resource=https://orders.internal&subject_token=user-token&subject_token_type={JWT_TOKEN_TYPE}"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exchange_errors() {
+        let url = start_server().await;
+        let client = TokenExchangeClient::new(&url, "gateway", Some("secret")).unwrap();
+
+        let err = client
+            .exchange(&TokenExchangeRequest::new("revoked"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            TokenExchangeError::Rejected {
+                error: "invalid_grant".to_string(),
+                description: Some("revoked token".to_string()),
+            }
+        );
+
+        let err = client.exchange(&TokenExchangeRequest::new("broken")).await.unwrap_err();
+        assert!(matches!(err, TokenExchangeError::InvalidResponse(_)));
+
+        let client = TokenExchangeClient::new("http://127.0.0.1:1/token", "gateway", None).unwrap();
+        let err = client.exchange(&TokenExchangeRequest::new("token")).await.unwrap_err();
+        assert!(matches!(err, TokenExchangeError::Http(_)));
+        assert!(matches!(ApiError::from(err), ApiError::InternalServerError(_)));
+    }
+
+    #[test]
+    fn test_request_debug_hides_subject_token() {
+        let request = TokenExchangeRequest::new("secret-token").with_audience("orders");
+        assert!(!format!("{request:?}").contains("secret-token"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Registry of trait-object services
+//!
+//! [`ServiceRegistry`] holds the services registered at startup behind their trait
+//! (`Arc<dyn MailSender>`), so that the handlers resolve them with the
+//! [`Inject`](crate::server::axum::extractors::Inject) extractor instead of carrying generic
+//! parameters through the whole handler stack.
+//!
+//! It is a facade over an [`Injector`] of singletons: the services are shared by the clones of
+//! the registry, so that a test can replace a service by a fake one
+//! ([`ServiceRegistry::replace`]) after the router is built.
+//!
+//! ```rust
+//! use api_tools::server::axum::extractors::Inject;
+//! use api_tools::server::axum::service_registry::ServiceRegistry;
+//! use axum::{Router, routing::post};
+//! use std::sync::Arc;
+//!
+//! trait MailSender: Send + Sync {
+//!     fn send(&self, to: &str) -> bool;
+//! }
+//!
+//! struct SmtpSender;
+//!
+//! impl MailSender for SmtpSender {
+//!     fn send(&self, _to: &str) -> bool {
+//!         true
+//!     }
+//! }
+//!
+//! let registry = ServiceRegistry::new().register::<dyn MailSender>(Arc::new(SmtpSender));
+//!
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/mails",
+//!         post(|Inject(sender): Inject<dyn MailSender>| async move { sender.send("john@example.com").to_string() }),
+//!     )
+//!     .layer(registry.layer());
+//! ```
+
+use crate::server::axum::layers::injector::{Injector, InjectorError, InjectorLayer};
+use std::any::type_name;
+use std::sync::Arc;
+
+/// Registry of services, shared by its clones
+#[derive(Debug, Clone, Default)]
+pub struct ServiceRegistry {
+    injector: Injector,
+}
+
+impl ServiceRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a service (e.g. `register::<dyn MailSender>(Arc::new(SmtpSender))`)
+    pub fn register<T: ?Sized + Send + Sync + 'static>(self, service: Arc<T>) -> Self {
+        Self {
+            injector: self.injector.singleton_arc(service),
+        }
+    }
+
+    /// Replace a registered service (e.g. by a fake one in a test), returning the previous one
+    pub fn replace<T: ?Sized + Send + Sync + 'static>(&self, service: Arc<T>) -> Result<Option<Arc<T>>, InjectorError> {
+        self.injector.replace(service)
+    }
+
+    /// Resolve a service
+    pub fn resolve<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<T>, InjectorError> {
+        self.injector
+            .get::<T>()
+            .ok_or(InjectorError::NotRegistered(type_name::<T>()))
+    }
+
+    /// Return true if a service of type `T` is registered
+    pub fn contains<T: ?Sized + 'static>(&self) -> bool {
+        self.injector.lifetime::<T>().is_some()
+    }
+
+    /// Return true if no service is registered
+    pub fn is_empty(&self) -> bool {
+        self.injector.is_empty()
+    }
+
+    /// Get the underlying injector
+    pub fn injector(&self) -> &Injector {
+        &self.injector
+    }
+
+    /// Layer attaching the registry to each request, read by the `Inject` extractor
+    pub fn layer(&self) -> InjectorLayer {
+        InjectorLayer::new(self.injector.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> String {
+            "Hello".to_string()
+        }
+    }
+
+    struct French;
+
+    impl Greeter for French {
+        fn greet(&self) -> String {
+            "Bonjour".to_string()
+        }
+    }
+
+    #[test]
+    fn test_service_registry_resolve() {
+        let registry = ServiceRegistry::new()
+            .register::<dyn Greeter>(Arc::new(English))
+            .register(Arc::new(42_u32));
+        assert!(!registry.is_empty());
+        assert!(registry.contains::<dyn Greeter>());
+        assert_eq!(registry.resolve::<dyn Greeter>().unwrap().greet(), "Hello");
+        assert_eq!(*registry.resolve::<u32>().unwrap(), 42);
+        assert_eq!(
+            registry.resolve::<String>().unwrap_err(),
+            InjectorError::NotRegistered("alloc::string::String")
+        );
+    }
+
+    #[test]
+    fn test_service_registry_replace() {
+        let registry = ServiceRegistry::new().register::<dyn Greeter>(Arc::new(English));
+        let clone = registry.clone();
+
+        let previous = clone.replace::<dyn Greeter>(Arc::new(French)).unwrap();
+        assert_eq!(previous.unwrap().greet(), "Hello");
+        assert_eq!(registry.resolve::<dyn Greeter>().unwrap().greet(), "Bonjour");
+        assert!(ServiceRegistry::new().replace(Arc::new(1_u8)).is_err());
+    }
+}
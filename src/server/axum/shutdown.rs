@@ -0,0 +1,425 @@
+//! Graceful shutdown with a structured report
+//!
+//! [`Shutdown`] tracks what happens between the shutdown signal and the end of the server:
+//!
+//! - the requests drained (in-flight requests completed after the signal, counted by the
+//!   [`ShutdownLayer`])
+//! - the background jobs cancelled and the stores flushed (reported by the application)
+//! - the last errors
+//!
+//! [`Shutdown::finish`] emits the [`ShutdownReport`] with `tracing` and writes it in a JSON file
+//! if [`Shutdown::with_report_file`] is set, so operators can verify clean shutdowns.
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::shutdown::{Shutdown, ShutdownLayer};
+//! use axum::{Router, routing::get};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let shutdown = Shutdown::new().with_report_file("shutdown.json");
+//! let app: Router = Router::new()
+//!     .route("/", get(|| async { "Hello" }))
+//!     .layer(ShutdownLayer::new(shutdown.clone()));
+//!
+//! // Background job
+//! let job_shutdown = shutdown.clone();
+//! tokio::spawn(async move {
+//!     job_shutdown.wait().await;
+//!     job_shutdown.job_cancelled();
+//! });
+//!
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+//! axum::serve(listener, app)
+//!     .with_graceful_shutdown(shutdown.clone().signal())
+//!     .await?;
+//!
+//! // Flush the stores, then emit the report
+//! shutdown.store_flushed("cache");
+//! shutdown.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use axum::body::Body;
+use axum::http::Request;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use thiserror::Error;
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+/// Number of errors kept in the report
+const MAX_LAST_ERRORS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ShutdownError {
+    #[error("Shutdown report file error: {0}")]
+    ReportFile(String),
+}
+
+/// Report of a graceful shutdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShutdownReport {
+    /// Requests completed after the shutdown signal
+    pub requests_drained: u64,
+
+    /// Requests still in flight when the report is emitted
+    pub requests_in_flight: usize,
+
+    /// Duration between the shutdown signal and the report, in milliseconds
+    pub duration_ms: u64,
+
+    /// Background jobs cancelled
+    pub jobs_cancelled: usize,
+
+    /// Stores flushed
+    pub stores_flushed: Vec<String>,
+
+    /// Last errors (at most 10, the oldest first)
+    pub last_errors: Vec<String>,
+}
+
+impl ShutdownReport {
+    /// Return true if no request was interrupted and no error occurred
+    pub fn is_clean(&self) -> bool {
+        self.requests_in_flight == 0 && self.last_errors.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct ShutdownState {
+    started: watch::Sender<Option<Instant>>,
+    in_flight: AtomicUsize,
+    drained: AtomicU64,
+    jobs_cancelled: AtomicUsize,
+    stores_flushed: Mutex<Vec<String>>,
+    last_errors: Mutex<VecDeque<String>>,
+}
+
+/// Graceful shutdown tracker, cheap to clone
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    state: Arc<ShutdownState>,
+    report_file: Option<PathBuf>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// Create a new `Shutdown`
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(ShutdownState {
+                started: watch::Sender::new(None),
+                in_flight: AtomicUsize::new(0),
+                drained: AtomicU64::new(0),
+                jobs_cancelled: AtomicUsize::new(0),
+                stores_flushed: Mutex::new(Vec::new()),
+                last_errors: Mutex::new(VecDeque::new()),
+            }),
+            report_file: None,
+        }
+    }
+
+    /// Also write the report in a JSON file
+    pub fn with_report_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.report_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Start the shutdown (only the first call is taken into account)
+    pub fn begin(&self) {
+        let started = self.state.started.send_if_modified(|started| {
+            if started.is_none() {
+                *started = Some(Instant::now());
+                true
+            } else {
+                false
+            }
+        });
+        if started {
+            info!(
+                requests_in_flight = self.state.in_flight.load(Ordering::Relaxed),
+                "Graceful shutdown started"
+            );
+        }
+    }
+
+    /// Return true if the shutdown is started
+    pub fn is_started(&self) -> bool {
+        self.state.started.borrow().is_some()
+    }
+
+    /// Wait for the shutdown to start
+    pub async fn wait(&self) {
+        let mut receiver = self.state.started.subscribe();
+        // The sender lives as long as `self`, so `wait_for` cannot fail
+        let _ = receiver.wait_for(Option::is_some).await;
+    }
+
+    /// Wait for `Ctrl+C` or `SIGTERM` (on Unix), then start the shutdown
+    ///
+    /// Use it with `axum::serve(...).with_graceful_shutdown(shutdown.clone().signal())`.
+    pub async fn signal(self) {
+        let ctrl_c = async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        };
+
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(_) => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = terminate => {},
+        }
+
+        self.begin();
+    }
+
+    /// Report a cancelled background job
+    pub fn job_cancelled(&self) {
+        self.state.jobs_cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Report a flushed store
+    pub fn store_flushed(&self, name: &str) {
+        if let Ok(mut stores) = self.state.stores_flushed.lock() {
+            stores.push(name.to_string());
+        }
+    }
+
+    /// Report an error (only the last 10 are kept)
+    pub fn record_error(&self, error: impl Display) {
+        if let Ok(mut errors) = self.state.last_errors.lock() {
+            if errors.len() == MAX_LAST_ERRORS {
+                errors.pop_front();
+            }
+            errors.push_back(error.to_string());
+        }
+    }
+
+    /// Current report
+    pub fn report(&self) -> ShutdownReport {
+        let duration = self
+            .state
+            .started
+            .borrow()
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+
+        ShutdownReport {
+            requests_drained: self.state.drained.load(Ordering::Relaxed),
+            requests_in_flight: self.state.in_flight.load(Ordering::Relaxed),
+            duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            jobs_cancelled: self.state.jobs_cancelled.load(Ordering::Relaxed),
+            stores_flushed: self
+                .state
+                .stores_flushed
+                .lock()
+                .map(|stores| stores.clone())
+                .unwrap_or_default(),
+            last_errors: self
+                .state
+                .last_errors
+                .lock()
+                .map(|errors| errors.iter().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Emit the report with `tracing` and write it in the report file (if set)
+    pub fn finish(&self) -> Result<ShutdownReport, ShutdownError> {
+        self.begin();
+
+        let report = self.report();
+        if report.is_clean() {
+            info!(
+                requests_drained = report.requests_drained,
+                duration_ms = report.duration_ms,
+                jobs_cancelled = report.jobs_cancelled,
+                stores_flushed = ?report.stores_flushed,
+                "Graceful shutdown completed"
+            );
+        } else {
+            warn!(
+                requests_drained = report.requests_drained,
+                requests_in_flight = report.requests_in_flight,
+                duration_ms = report.duration_ms,
+                jobs_cancelled = report.jobs_cancelled,
+                stores_flushed = ?report.stores_flushed,
+                last_errors = ?report.last_errors,
+                "Graceful shutdown completed with interrupted requests or errors"
+            );
+        }
+
+        if let Some(path) = &self.report_file {
+            let json = serde_json::to_vec_pretty(&report).map_err(|err| ShutdownError::ReportFile(err.to_string()))?;
+            std::fs::write(path, json).map_err(|err| ShutdownError::ReportFile(err.to_string()))?;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Decrement the in-flight requests when the request completes (or is dropped)
+struct InFlightGuard(Arc<ShutdownState>);
+
+impl InFlightGuard {
+    fn new(state: Arc<ShutdownState>) -> Self {
+        state.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(state)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+        if self.0.started.borrow().is_some() {
+            self.0.drained.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Layer counting the in-flight requests for the [`ShutdownReport`]
+#[derive(Clone)]
+pub struct ShutdownLayer {
+    pub shutdown: Shutdown,
+}
+
+impl ShutdownLayer {
+    /// Create a new `ShutdownLayer`
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self { shutdown }
+    }
+}
+
+impl<S> Layer<S> for ShutdownLayer {
+    type Service = ShutdownMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ShutdownMiddleware {
+            inner,
+            state: self.shutdown.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ShutdownMiddleware<S> {
+    inner: S,
+    state: Arc<ShutdownState>,
+}
+
+impl<S> Service<Request<Body>> for ShutdownMiddleware<S>
+where
+    S: Service<Request<Body>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    // `BoxFuture` is a type alias for `Pin<Box<dyn Future + Send + 'a>>`
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let guard = InFlightGuard::new(self.state.clone());
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await;
+            drop(guard);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::Response;
+    use std::convert::Infallible;
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_shutdown_report() {
+        let shutdown = Shutdown::new();
+        let release = Arc::new(Notify::new());
+
+        let service_release = release.clone();
+        let service = ShutdownLayer::new(shutdown.clone()).layer(tower::service_fn(move |_: Request<Body>| {
+            let release = service_release.clone();
+            async move {
+                release.notified().await;
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }
+        }));
+        let request = tokio::spawn(service.oneshot(Request::new(Body::empty())));
+        tokio::task::yield_now().await;
+        assert_eq!(shutdown.report().requests_in_flight, 1);
+        assert!(!shutdown.is_started());
+
+        shutdown.begin();
+        shutdown.wait().await;
+        release.notify_one();
+        request.await.unwrap().unwrap();
+
+        shutdown.job_cancelled();
+        shutdown.store_flushed("cache");
+        let report = shutdown.finish().unwrap();
+        assert_eq!(report.requests_drained, 1);
+        assert_eq!(report.requests_in_flight, 0);
+        assert_eq!(report.jobs_cancelled, 1);
+        assert_eq!(report.stores_flushed, vec!["cache".to_string()]);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_shutdown_report_last_errors() {
+        let shutdown = Shutdown::new();
+        for i in 0..12 {
+            shutdown.record_error(format!("error {i}"));
+        }
+
+        let report = shutdown.report();
+        assert_eq!(report.last_errors.len(), MAX_LAST_ERRORS);
+        assert_eq!(report.last_errors[0], "error 2");
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_shutdown_report_file() {
+        let path = std::env::temp_dir().join(format!("shutdown-report-{}.json", std::process::id()));
+        let shutdown = Shutdown::new().with_report_file(&path);
+        shutdown.finish().unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(json["requests_drained"], 0);
+        assert_eq!(json["last_errors"], serde_json::json!([]));
+    }
+}
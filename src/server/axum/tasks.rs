@@ -0,0 +1,547 @@
+//! Background task queue
+//!
+//! [`TaskQueue`] enqueues typed tasks (serialized in JSON) and spawns workers processing them,
+//! with retries (exponential backoff) and a dead-letter queue for the tasks failing after the
+//! last retry or which cannot be deserialized.
+//!
+//! The storage is a [`TaskBackend`]. [`MemoryBackend`] (Tokio channels) is used by default; a
+//! Redis or database backend can implement the trait without changing the user code.
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::shutdown::Shutdown;
+//! use api_tools::server::axum::tasks::{Task, TaskQueue};
+//! use serde::{Deserialize, Serialize};
+//! use std::time::Duration;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct SendEmail {
+//!     to: String,
+//! }
+//!
+//! impl Task for SendEmail {
+//!     const QUEUE: &'static str = "emails";
+//! }
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let shutdown = Shutdown::new();
+//! let queue = TaskQueue::memory();
+//!
+//! queue
+//!     .worker(|task: SendEmail| async move {
+//!         println!("Sending email to {}", task.to);
+//!         Ok::<_, String>(())
+//!     })
+//!     .with_concurrency(4)
+//!     .with_retries(5, Duration::from_secs(1))
+//!     .with_shutdown(shutdown.clone())
+//!     .spawn();
+//!
+//! queue.enqueue(&SendEmail { to: "john@example.com".to_string() }).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::axum::shutdown::Shutdown;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Default maximum number of retries of a task
+pub const TASK_DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Default delay before the first retry (doubled on each retry)
+pub const TASK_DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Default maximum delay between two retries
+pub const TASK_DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TaskError {
+    #[error("Task serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Task backend error: {0}")]
+    Backend(String),
+}
+
+/// Typed task
+pub trait Task: Serialize + DeserializeOwned + Send + 'static {
+    /// Name of the queue of the task
+    const QUEUE: &'static str;
+}
+
+/// Task stored in a backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskMessage {
+    pub id: Uuid,
+    pub queue: String,
+    pub payload: Value,
+
+    /// Number of failed attempts
+    pub attempts: u32,
+
+    pub enqueued_at: DateTime<Utc>,
+
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+}
+
+/// Storage of the tasks
+pub trait TaskBackend: Send + Sync + 'static {
+    /// Push a task, available after the delay
+    fn push(&self, message: TaskMessage, delay: Duration) -> BoxFuture<'_, Result<(), TaskError>>;
+
+    /// Wait for the next available task of a queue (`None` if the backend is closed)
+    fn pop<'a>(&'a self, queue: &'a str) -> BoxFuture<'a, Result<Option<TaskMessage>, TaskError>>;
+
+    /// Move a task to the dead-letter queue
+    fn dead_letter(&self, message: TaskMessage) -> BoxFuture<'_, Result<(), TaskError>>;
+
+    /// Tasks of the dead-letter queue of a queue
+    fn dead_letters<'a>(&'a self, queue: &'a str) -> BoxFuture<'a, Result<Vec<TaskMessage>, TaskError>>;
+}
+
+type MemoryChannel = (
+    mpsc::UnboundedSender<TaskMessage>,
+    Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<TaskMessage>>>,
+);
+
+/// In-memory backend (Tokio channels), the tasks are lost when the process stops
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    queues: Mutex<HashMap<String, MemoryChannel>>,
+    dead_letters: Mutex<HashMap<String, Vec<TaskMessage>>>,
+}
+
+impl MemoryBackend {
+    /// Create a new `MemoryBackend`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn channel(&self, queue: &str) -> Result<MemoryChannel, TaskError> {
+        let mut queues = self.queues.lock().map_err(|err| TaskError::Backend(err.to_string()))?;
+        let (sender, receiver) = queues.entry(queue.to_string()).or_insert_with(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            (sender, Arc::new(tokio::sync::Mutex::new(receiver)))
+        });
+
+        Ok((sender.clone(), receiver.clone()))
+    }
+}
+
+impl TaskBackend for MemoryBackend {
+    fn push(&self, message: TaskMessage, delay: Duration) -> BoxFuture<'_, Result<(), TaskError>> {
+        Box::pin(async move {
+            let (sender, _) = self.channel(&message.queue)?;
+            if delay.is_zero() {
+                sender.send(message).map_err(|err| TaskError::Backend(err.to_string()))
+            } else {
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = sender.send(message);
+                });
+                Ok(())
+            }
+        })
+    }
+
+    fn pop<'a>(&'a self, queue: &'a str) -> BoxFuture<'a, Result<Option<TaskMessage>, TaskError>> {
+        Box::pin(async move {
+            let (_, receiver) = self.channel(queue)?;
+            let mut receiver = receiver.lock().await;
+
+            Ok(receiver.recv().await)
+        })
+    }
+
+    fn dead_letter(&self, message: TaskMessage) -> BoxFuture<'_, Result<(), TaskError>> {
+        Box::pin(async move {
+            self.dead_letters
+                .lock()
+                .map_err(|err| TaskError::Backend(err.to_string()))?
+                .entry(message.queue.clone())
+                .or_default()
+                .push(message);
+            Ok(())
+        })
+    }
+
+    fn dead_letters<'a>(&'a self, queue: &'a str) -> BoxFuture<'a, Result<Vec<TaskMessage>, TaskError>> {
+        Box::pin(async move {
+            Ok(self
+                .dead_letters
+                .lock()
+                .map_err(|err| TaskError::Backend(err.to_string()))?
+                .get(queue)
+                .cloned()
+                .unwrap_or_default())
+        })
+    }
+}
+
+/// Task queue, cheap to clone
+pub struct TaskQueue<B: TaskBackend = MemoryBackend> {
+    backend: Arc<B>,
+}
+
+impl<B: TaskBackend> Clone for TaskQueue<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+        }
+    }
+}
+
+impl TaskQueue<MemoryBackend> {
+    /// Create a new task queue with the in-memory backend
+    pub fn memory() -> Self {
+        Self::new(MemoryBackend::new())
+    }
+}
+
+impl<B: TaskBackend> TaskQueue<B> {
+    /// Create a new task queue with a backend
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Backend of the queue
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Enqueue a task
+    pub async fn enqueue<T: Task>(&self, task: &T) -> Result<Uuid, TaskError> {
+        self.enqueue_in(task, Duration::ZERO).await
+    }
+
+    /// Enqueue a task, available after a delay
+    pub async fn enqueue_in<T: Task>(&self, task: &T, delay: Duration) -> Result<Uuid, TaskError> {
+        let message = TaskMessage {
+            id: Uuid::new_v4(),
+            queue: T::QUEUE.to_string(),
+            payload: serde_json::to_value(task).map_err(|err| TaskError::Serialization(err.to_string()))?,
+            attempts: 0,
+            enqueued_at: Utc::now(),
+            last_error: None,
+        };
+        let id = message.id;
+        self.backend.push(message, delay).await?;
+
+        Ok(id)
+    }
+
+    /// Tasks of the dead-letter queue of a task type
+    pub async fn dead_letters<T: Task>(&self) -> Result<Vec<TaskMessage>, TaskError> {
+        self.backend.dead_letters(T::QUEUE).await
+    }
+
+    /// Create a worker processing the tasks of type `T`
+    pub fn worker<T, F, Fut, E>(&self, handler: F) -> Worker<T, B>
+    where
+        T: Task,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        Worker {
+            backend: self.backend.clone(),
+            handler: Arc::new(move |task| {
+                let future = handler(task);
+                Box::pin(async move { future.await.map_err(|err| err.to_string()) })
+            }),
+            concurrency: 1,
+            max_retries: TASK_DEFAULT_MAX_RETRIES,
+            retry_delay: TASK_DEFAULT_RETRY_DELAY,
+            max_retry_delay: TASK_DEFAULT_MAX_RETRY_DELAY,
+            shutdown: Shutdown::new(),
+            task: PhantomData,
+        }
+    }
+}
+
+type TaskHandler<T> = Arc<dyn Fn(T) -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Worker processing the tasks of a queue
+pub struct Worker<T: Task, B: TaskBackend = MemoryBackend> {
+    backend: Arc<B>,
+    handler: TaskHandler<T>,
+    concurrency: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    shutdown: Shutdown,
+    task: PhantomData<fn() -> T>,
+}
+
+impl<T: Task, B: TaskBackend> Worker<T, B> {
+    /// Set the number of tasks processed concurrently (at least 1)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the maximum number of retries and the delay before the first retry (doubled on each
+    /// retry)
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Set the maximum delay between two retries
+    pub fn with_max_retry_delay(mut self, max_retry_delay: Duration) -> Self {
+        self.max_retry_delay = max_retry_delay;
+        self
+    }
+
+    /// Stop the worker with the application graceful shutdown
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Spawn the worker on Tokio tasks
+    ///
+    /// The worker stops when the shutdown starts, after the completion of the running tasks.
+    pub fn spawn(self) -> JoinHandle<()> {
+        let worker = Arc::new(self);
+
+        tokio::spawn(async move {
+            let loops = (0..worker.concurrency)
+                .map(|_| {
+                    let worker = worker.clone();
+                    tokio::spawn(async move { worker.run().await })
+                })
+                .collect::<Vec<_>>();
+
+            for handle in loops {
+                let _ = handle.await;
+            }
+            if worker.shutdown.is_started() {
+                worker.shutdown.job_cancelled();
+            }
+        })
+    }
+
+    /// Process the tasks until the shutdown
+    async fn run(&self) {
+        loop {
+            let message = tokio::select! {
+                biased;
+                _ = self.shutdown.wait() => break,
+                message = self.backend.pop(T::QUEUE) => message,
+            };
+
+            match message {
+                Ok(Some(message)) => self.process(message).await,
+                Ok(None) => break,
+                Err(err) => {
+                    error!(queue = T::QUEUE, error = %err, "Task queue error");
+                    tokio::time::sleep(self.retry_delay).await;
+                }
+            }
+        }
+    }
+
+    /// Process a task, retrying or moving it to the dead-letter queue on failure
+    async fn process(&self, mut message: TaskMessage) {
+        let result = match serde_json::from_value::<T>(message.payload.clone()) {
+            Ok(task) => (self.handler)(task).await,
+            Err(err) => {
+                message.last_error = Some(TaskError::Serialization(err.to_string()).to_string());
+                self.dead_letter(message, "invalid").await;
+                return;
+            }
+        };
+
+        match result {
+            Ok(()) => record_outcome(T::QUEUE, "success"),
+            Err(err) => {
+                message.attempts += 1;
+                message.last_error = Some(err);
+
+                if message.attempts > self.max_retries {
+                    self.dead_letter(message, "dead_letter").await;
+                    return;
+                }
+
+                let delay = self
+                    .retry_delay
+                    .saturating_mul(2_u32.saturating_pow(message.attempts - 1))
+                    .min(self.max_retry_delay);
+                warn!(
+                    queue = T::QUEUE,
+                    id = %message.id,
+                    attempts = message.attempts,
+                    error = message.last_error.as_deref().unwrap_or_default(),
+                    "Task failed, retrying in {delay:?}"
+                );
+                record_outcome(T::QUEUE, "retry");
+                if let Err(err) = self.backend.push(message, delay).await {
+                    error!(queue = T::QUEUE, error = %err, "Task retry error");
+                }
+            }
+        }
+    }
+
+    async fn dead_letter(&self, message: TaskMessage, outcome: &str) {
+        error!(
+            queue = T::QUEUE,
+            id = %message.id,
+            attempts = message.attempts,
+            error = message.last_error.as_deref().unwrap_or_default(),
+            "Task moved to the dead-letter queue"
+        );
+        record_outcome(T::QUEUE, outcome);
+        if let Err(err) = self.backend.dead_letter(message).await {
+            error!(queue = T::QUEUE, error = %err, "Task dead-letter error");
+        }
+    }
+}
+
+/// Record the outcome of a task (`tasks_processed_total` metric)
+fn record_outcome(queue: &'static str, outcome: &str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("tasks_processed_total", "queue" => queue, "outcome" => outcome.to_string()).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (queue, outcome);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Email {
+        to: String,
+    }
+
+    impl Task for Email {
+        const QUEUE: &'static str = "emails";
+    }
+
+    #[tokio::test]
+    async fn test_task_queue_retry_and_dead_letter() {
+        let shutdown = Shutdown::new();
+        let queue = TaskQueue::memory();
+        let calls = Arc::new(AtomicU32::new(0));
+
+        let counter = calls.clone();
+        let worker = queue
+            .worker(move |email: Email| {
+                let counter = counter.clone();
+                async move {
+                    let calls = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    match email.to.as_str() {
+                        "flaky@example.com" if calls < 3 => Err(format!("attempt {calls} failed")),
+                        "invalid" => Err("invalid address".to_string()),
+                        _ => Ok(()),
+                    }
+                }
+            })
+            .with_retries(2, Duration::from_millis(5))
+            .with_shutdown(shutdown.clone())
+            .spawn();
+
+        queue
+            .enqueue(&Email {
+                to: "flaky@example.com".to_string(),
+            })
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(queue.dead_letters::<Email>().await.unwrap().is_empty());
+
+        let id = queue
+            .enqueue(&Email {
+                to: "invalid".to_string(),
+            })
+            .await
+            .unwrap();
+        queue
+            .backend()
+            .push(
+                TaskMessage {
+                    id: Uuid::new_v4(),
+                    queue: "emails".to_string(),
+                    payload: Value::Null,
+                    attempts: 0,
+                    enqueued_at: Utc::now(),
+                    last_error: None,
+                },
+                Duration::ZERO,
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        // The invalid payload is moved to the dead-letter queue before the last retry
+        let dead_letters = queue.dead_letters::<Email>().await.unwrap();
+        assert_eq!(dead_letters.len(), 2);
+        assert!(
+            dead_letters[0]
+                .last_error
+                .as_deref()
+                .unwrap()
+                .starts_with("Task serialization error")
+        );
+        assert_eq!(dead_letters[1].id, id);
+        assert_eq!(dead_letters[1].attempts, 3);
+        assert_eq!(dead_letters[1].last_error.as_deref(), Some("invalid address"));
+
+        shutdown.begin();
+        worker.await.unwrap();
+        assert_eq!(shutdown.report().jobs_cancelled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_task_queue_delayed_and_concurrent() {
+        let queue = TaskQueue::memory();
+        let calls = Arc::new(AtomicU32::new(0));
+
+        let counter = calls.clone();
+        queue
+            .worker(move |_email: Email| {
+                let counter = counter.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok::<_, String>(())
+                }
+            })
+            .with_concurrency(4)
+            .spawn();
+
+        for _ in 0..4 {
+            queue.enqueue(&Email { to: "a".to_string() }).await.unwrap();
+        }
+        queue
+            .enqueue_in(&Email { to: "b".to_string() }, Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+}
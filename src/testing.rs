@@ -0,0 +1,970 @@
+//! Testing helpers (`testing` feature)
+//!
+//! # Snapshots
+//!
+//! [`snapshot_response`] guards API contracts: the status and the JSON body of a response are
+//! normalized (request IDs, trace IDs and timestamps are replaced by placeholders) and compared
+//! with a snapshot stored in `tests/snapshots/<name>.json`.
+//!
+//! - A missing snapshot is created
+//! - Snapshots are updated instead of compared if the `API_TOOLS_UPDATE_SNAPSHOTS` environment
+//!   variable is set (`API_TOOLS_UPDATE_SNAPSHOTS=1 cargo test`)
+//!
+//! ```rust,no_run
+//! use api_tools::testing::snapshot_response;
+//! use axum::{Json, Router, body::Body, http::Request, routing::get};
+//! use tower::ServiceExt;
+//!
+//! # async fn run() {
+//! let app: Router = Router::new().route("/users/{id}", get(|| async { Json(serde_json::json!({ "id": 1 })) }));
+//! let response = app
+//!     .oneshot(Request::get("/users/1").body(Body::empty()).unwrap())
+//!     .await
+//!     .unwrap();
+//!
+//! snapshot_response("get_user", response).await.unwrap();
+//! # }
+//! ```
+//!
+//! # Fuzzing
+//!
+//! [`fuzz_router`] sends malformed paths, query strings, headers and bodies to a router and checks
+//! that every error response is a well-formed `ApiErrorResponse` (JSON with the `code` and
+//! `message` fields), without panic, timeout or HTML error page:
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::extractors::Path;
+//! use api_tools::testing::fuzz_router;
+//! use axum::{Router, routing::get};
+//!
+//! # async fn run() {
+//! let app: Router = Router::new().route("/users/{id}", get(|Path(id): Path<u32>| async move { id.to_string() }));
+//!
+//! let report = fuzz_router(app, &["/users/1"]).await;
+//! assert!(report.is_ok(), "{report}");
+//! # }
+//! ```
+//!
+//! # Test client
+//!
+//! [`TestClient`] sends requests to a router (with `tower::ServiceExt::oneshot`) and reads the
+//! whole response in a [`TestResponse`]. Default headers (e.g. Basic Auth or a bearer JWT signed
+//! with a test `Jwt`) are added to every request. [`assert_api_error`] checks the standard JSON
+//! error envelope:
+//!
+//! ```rust,no_run
+//! use api_tools::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+//! use api_tools::testing::{TestClient, assert_api_error};
+//! use axum::{Router, http::StatusCode, routing::get};
+//!
+//! # async fn run() {
+//! let app: Router = Router::new()
+//!     .route("/health", get(|| async { "ok" }))
+//!     .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024)));
+//! let client = TestClient::new(app).with_basic_auth("admin", "secret");
+//!
+//! let response = client.get("/health").send().await;
+//! response.assert_status(StatusCode::OK);
+//! assert_eq!(response.text(), "ok");
+//!
+//! assert_api_error(&client.get("/missing").send().await, 404, "Resource Not Found");
+//! # }
+//! ```
+
+use crate::server::axum::security::jwt::{Jwt, JwtError};
+use crate::value_objects::datetime::UtcDateTime;
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum::response::Response;
+use chrono::DateTime;
+use http_auth_basic::Credentials;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::fmt::Debug;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Environment variable enabling the update mode
+pub const UPDATE_SNAPSHOTS_ENV: &str = "API_TOOLS_UPDATE_SNAPSHOTS";
+
+/// Maximum size of the snapshotted bodies
+const SNAPSHOT_BODY_LIMIT: usize = 10 * 1_024 * 1_024;
+
+/// Snapshot errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot I/O error: {0}")]
+    Io(String),
+
+    #[error("Snapshot `{name}` does not match\n--- expected\n{expected}\n+++ actual\n{actual}")]
+    Mismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Snapshots configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshots {
+    /// Snapshots directory
+    pub dir: PathBuf,
+
+    /// Overwrite the snapshots instead of comparing them
+    pub update: bool,
+
+    /// Fields always replaced by a placeholder, whatever their value
+    pub volatile_fields: Vec<String>,
+}
+
+impl Default for Snapshots {
+    /// Snapshots in `tests/snapshots` of the crate under test, update mode from `API_TOOLS_UPDATE_SNAPSHOTS`
+    fn default() -> Self {
+        let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+
+        Self {
+            dir: PathBuf::from(root).join("tests").join("snapshots"),
+            update: std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some(),
+            volatile_fields: Vec::new(),
+        }
+    }
+}
+
+impl Snapshots {
+    /// Create a configuration storing the snapshots in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Enable or disable the update mode
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Replace these fields by a placeholder (e.g. generated IDs)
+    pub fn with_volatile_fields(mut self, fields: &[&str]) -> Self {
+        self.volatile_fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Compare a response with the snapshot `name`, returning the normalized snapshot
+    pub async fn assert_response(&self, name: &str, response: Response) -> Result<Value, SnapshotError> {
+        let status = response.status().as_u16();
+        let body = axum::body::to_bytes(response.into_body(), SNAPSHOT_BODY_LIMIT)
+            .await
+            .map_err(|err| SnapshotError::Io(err.to_string()))?;
+        let body = match serde_json::from_slice::<Value>(&body) {
+            Ok(body) => body,
+            Err(_) => Value::String(String::from_utf8_lossy(&body).into_owned()),
+        };
+
+        let actual = json!({ "status": status, "body": self.normalize(body) });
+        self.assert_value(name, actual)
+    }
+
+    /// Compare a JSON value with the snapshot `name`
+    fn assert_value(&self, name: &str, actual: Value) -> Result<Value, SnapshotError> {
+        let path = self.dir.join(format!("{name}.json"));
+        let actual_text = serde_json::to_string_pretty(&actual).map_err(|err| SnapshotError::Io(err.to_string()))?;
+
+        if !self.update && path.exists() {
+            let expected_text = std::fs::read_to_string(&path).map_err(|err| SnapshotError::Io(err.to_string()))?;
+            let expected = serde_json::from_str::<Value>(&expected_text)
+                .map_err(|err| SnapshotError::Io(format!("{}: {err}", path.display())))?;
+            if expected != actual {
+                return Err(SnapshotError::Mismatch {
+                    name: name.to_string(),
+                    expected: expected_text.trim_end().to_string(),
+                    actual: actual_text,
+                });
+            }
+        } else {
+            std::fs::create_dir_all(&self.dir).map_err(|err| SnapshotError::Io(err.to_string()))?;
+            std::fs::write(&path, format!("{actual_text}\n")).map_err(|err| SnapshotError::Io(err.to_string()))?;
+        }
+
+        Ok(actual)
+    }
+
+    /// Replace the volatile values by placeholders
+    ///
+    /// - `[uuid]`: UUIDs (request IDs, generated IDs)
+    /// - `[trace_id]`: 32 hexadecimal characters (W3C trace IDs)
+    /// - `[timestamp]`: RFC 3339 dates
+    /// - `[volatile]`: values of the volatile fields
+    pub fn normalize(&self, value: Value) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| match self.volatile_fields.contains(&key) {
+                        true => (key, Value::String("[volatile]".to_string())),
+                        false => (key, self.normalize(value)),
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(values.into_iter().map(|value| self.normalize(value)).collect()),
+            Value::String(value) => Value::String(normalize_str(value)),
+            value => value,
+        }
+    }
+}
+
+/// Replace a volatile string by a placeholder
+fn normalize_str(value: String) -> String {
+    // Checked before the UUIDs, which can also be written as 32 hexadecimal characters
+    if value.len() == 32 && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+        "[trace_id]".to_string()
+    } else if Uuid::try_parse(&value).is_ok() {
+        "[uuid]".to_string()
+    } else if DateTime::parse_from_rfc3339(&value).is_ok() {
+        "[timestamp]".to_string()
+    } else {
+        value
+    }
+}
+
+/// Compare a response with the snapshot `tests/snapshots/<name>.json`
+///
+/// See [`Snapshots`] to configure the directory and the volatile fields.
+pub async fn snapshot_response(name: &str, response: Response) -> Result<Value, SnapshotError> {
+    Snapshots::default().assert_response(name, response).await
+}
+
+/// Methods used by the fuzzer
+const FUZZ_METHODS: [Method; 5] = [Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE];
+
+/// Malformed path segments
+const FUZZ_SEGMENTS: [&str; 10] = [
+    "",
+    "%",
+    "%zz",
+    "%00",
+    "..",
+    "%2e%2e",
+    "-1",
+    "99999999999999999999",
+    "%C3%A9%F0%9F%98%80",
+    "'%20OR%201=1%20--",
+];
+
+/// Malformed query strings
+const FUZZ_QUERIES: [&str; 8] = [
+    "",
+    "?",
+    "?%",
+    "?a=%zz",
+    "?page=-1&size=abc",
+    "?filter[=",
+    "?&&==&",
+    "?sort=%00",
+];
+
+/// Malformed content types
+const FUZZ_CONTENT_TYPES: [&str; 5] = [
+    "application/json",
+    "text/plain",
+    "application/x-www-form-urlencoded",
+    "multipart/form-data",
+    "application/json; charset=",
+];
+
+/// Malformed `Authorization` headers
+const FUZZ_AUTHORIZATIONS: [&str; 4] = ["Bearer", "Bearer a.b.c", "Basic !!!", "Digest"];
+
+/// Malformed bodies
+const FUZZ_BODIES: [&[u8]; 9] = [
+    b"",
+    b"{",
+    b"null",
+    b"[]",
+    b"{\"id\": \"abc\", \"name\": 42}",
+    b"\xff\xfe\x00",
+    b"<html></html>",
+    b"{\"a\":{\"a\":{\"a\":{\"a\":{\"a\":{\"a\":{\"a\":{\"a\":{}}}}}}}}}",
+    b"id=1&name=",
+];
+
+/// Fuzzer configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzConfig {
+    /// Number of requests
+    pub iterations: usize,
+
+    /// Seed of the pseudo-random generator, the same seed generates the same requests
+    pub seed: u64,
+
+    /// Maximum duration of a request
+    pub timeout: Duration,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 500,
+            seed: 42,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl FuzzConfig {
+    /// Set the number of requests
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Set the seed of the pseudo-random generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Send malformed requests derived from `paths` to the router
+    pub async fn run(&self, router: Router, paths: &[&str]) -> FuzzReport {
+        let mut rng = FuzzRng(self.seed);
+        let mut report = FuzzReport::default();
+        if paths.is_empty() {
+            return report;
+        }
+
+        for _ in 0..self.iterations {
+            let method = rng.pick(&FUZZ_METHODS).clone();
+            let path = *rng.pick(paths);
+            let uri = fuzz_uri(&mut rng, path);
+
+            // URIs rejected by the HTTP client are skipped
+            let Ok(request) = fuzz_request(&mut rng, method.clone(), &uri) else {
+                continue;
+            };
+            report.requests += 1;
+
+            let failure = |reason: String| FuzzFailure {
+                method: method.clone(),
+                uri: uri.clone(),
+                reason,
+            };
+            let call = tokio::spawn(router.clone().oneshot(request));
+            match tokio::time::timeout(self.timeout, call).await {
+                Err(_) => report
+                    .failures
+                    .push(failure(format!("no response after {:?}", self.timeout))),
+                Ok(Err(err)) if err.is_panic() => report.failures.push(failure("handler panicked".to_string())),
+                Ok(Err(err)) => report.failures.push(failure(err.to_string())),
+                Ok(Ok(response)) => {
+                    let Ok(response) = response;
+                    if let Err(reason) = check_error_response(response).await {
+                        report.failures.push(failure(reason));
+                    }
+                }
+            }
+        }
+
+        report
+    }
+}
+
+/// Invalid response found by the fuzzer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzFailure {
+    pub method: Method,
+    pub uri: String,
+    pub reason: String,
+}
+
+/// Result of a fuzzing session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzReport {
+    /// Number of requests sent
+    pub requests: usize,
+
+    /// Invalid responses
+    pub failures: Vec<FuzzFailure>,
+}
+
+impl FuzzReport {
+    /// Return true if no invalid response was found
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Display for FuzzReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} requests, {} failures", self.requests, self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n- {} {}: {}", failure.method, failure.uri, failure.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// SplitMix64 pseudo-random generator
+struct FuzzRng(u64);
+
+impl FuzzRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[(self.next() % values.len() as u64) as usize]
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+/// Mutate a path and add a malformed query string
+fn fuzz_uri(rng: &mut FuzzRng, path: &str) -> String {
+    let mut segments = path.split('/').map(str::to_string).collect::<Vec<_>>();
+    if rng.chance(70) {
+        let index = (rng.next() % segments.len() as u64) as usize;
+        segments[index] = rng.pick(&FUZZ_SEGMENTS).to_string();
+    }
+    if rng.chance(10) {
+        segments.push("a".repeat(2_048));
+    }
+    if rng.chance(10) {
+        segments.push(String::new());
+    }
+
+    let mut path = segments.join("/");
+    if !path.starts_with('/') {
+        path.insert(0, '/');
+    }
+
+    format!("{path}{}", rng.pick(&FUZZ_QUERIES))
+}
+
+/// Build a request with malformed headers and body
+fn fuzz_request(rng: &mut FuzzRng, method: Method, uri: &str) -> Result<Request<Body>, axum::http::Error> {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if rng.chance(70) {
+        builder = builder.header(CONTENT_TYPE, *rng.pick(&FUZZ_CONTENT_TYPES));
+    }
+    if rng.chance(30) {
+        builder = builder.header(AUTHORIZATION, *rng.pick(&FUZZ_AUTHORIZATIONS));
+    }
+    if rng.chance(20) {
+        builder = builder.header(ACCEPT, "text/html");
+    }
+
+    builder.body(Body::from(*rng.pick(&FUZZ_BODIES)))
+}
+
+/// Check that an error response is a well-formed `ApiErrorResponse`
+async fn check_error_response(response: Response) -> Result<(), String> {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(());
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with(mime::APPLICATION_JSON.as_ref()) {
+        return Err(format!("{status}: not a JSON error (content type: {content_type:?})"));
+    }
+
+    let body = axum::body::to_bytes(response.into_body(), SNAPSHOT_BODY_LIMIT)
+        .await
+        .map_err(|err| format!("{status}: {err}"))?;
+    let body = serde_json::from_slice::<Value>(&body).map_err(|err| format!("{status}: invalid JSON body: {err}"))?;
+    if body["code"] != status.as_u16() || body.get("message").is_none() {
+        return Err(format!("{status}: not an `ApiErrorResponse`: {body}"));
+    }
+
+    Ok(())
+}
+
+/// Send malformed requests derived from `paths` to the router (500 requests, see [`FuzzConfig`])
+pub async fn fuzz_router(router: Router, paths: &[&str]) -> FuzzReport {
+    FuzzConfig::default().run(router, paths).await
+}
+
+/// Test client sending requests to a router
+#[derive(Clone)]
+pub struct TestClient {
+    router: Router,
+    headers: HeaderMap,
+}
+
+impl TestClient {
+    /// Create a new client without default headers
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Add a header to every request
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or the value is not a valid header.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(header_name(name), header_value(value));
+        self
+    }
+
+    /// Authenticate every request with Basic Auth
+    pub fn with_basic_auth(self, username: &str, password: &str) -> Self {
+        self.with_header(AUTHORIZATION.as_str(), &basic_auth(username, password))
+    }
+
+    /// Authenticate every request with a bearer token
+    pub fn with_bearer(self, token: &str) -> Self {
+        self.with_header(AUTHORIZATION.as_str(), &format!("Bearer {token}"))
+    }
+
+    /// Authenticate every request with a bearer JWT signed by `jwt`
+    pub fn with_jwt<P: Debug + Serialize>(self, jwt: &Jwt, claims: P) -> Result<Self, JwtError> {
+        let token = jwt.generate(claims, UtcDateTime::now())?;
+
+        Ok(self.with_bearer(&token.token))
+    }
+
+    /// Build a request
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest {
+        TestRequest {
+            router: self.router.clone(),
+            method,
+            uri: uri.to_string(),
+            headers: self.headers.clone(),
+            body: Body::empty(),
+        }
+    }
+
+    /// Build a `GET` request
+    pub fn get(&self, uri: &str) -> TestRequest {
+        self.request(Method::GET, uri)
+    }
+
+    /// Build a `POST` request
+    pub fn post(&self, uri: &str) -> TestRequest {
+        self.request(Method::POST, uri)
+    }
+
+    /// Build a `PUT` request
+    pub fn put(&self, uri: &str) -> TestRequest {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Build a `PATCH` request
+    pub fn patch(&self, uri: &str) -> TestRequest {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Build a `DELETE` request
+    pub fn delete(&self, uri: &str) -> TestRequest {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Send a request built by the caller (the default headers are not added)
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        TestResponse::read(
+            self.router
+                .clone()
+                .oneshot(request)
+                .await
+                .unwrap_or_else(|err| match err {}),
+        )
+        .await
+    }
+}
+
+/// Request built by a [`TestClient`]
+pub struct TestRequest {
+    router: Router,
+    method: Method,
+    uri: String,
+    headers: HeaderMap,
+    body: Body,
+}
+
+impl TestRequest {
+    /// Add or replace a header
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or the value is not a valid header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(header_name(name), header_value(value));
+        self
+    }
+
+    /// Authenticate the request with Basic Auth
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.header(AUTHORIZATION.as_str(), &basic_auth(username, password))
+    }
+
+    /// Authenticate the request with a bearer token
+    pub fn bearer(self, token: &str) -> Self {
+        self.header(AUTHORIZATION.as_str(), &format!("Bearer {token}"))
+    }
+
+    /// Remove a header (e.g. a default `Authorization` header of the client)
+    pub fn without_header(mut self, name: &str) -> Self {
+        self.headers.remove(header_name(name));
+        self
+    }
+
+    /// Set a raw body
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set a JSON body and the `application/json` content type
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    pub fn json<T: Serialize>(self, body: &T) -> Self {
+        let body = serde_json::to_vec(body).expect("invalid JSON body");
+
+        self.header(CONTENT_TYPE.as_str(), mime::APPLICATION_JSON.as_ref())
+            .body(body)
+    }
+
+    /// Send the request and read the response
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request URI is invalid.
+    pub async fn send(self) -> TestResponse {
+        let mut request = Request::builder()
+            .method(self.method)
+            .uri(&self.uri)
+            .body(self.body)
+            .unwrap_or_else(|err| panic!("invalid request {}: {err}", self.uri));
+        *request.headers_mut() = self.headers;
+
+        TestResponse::read(self.router.oneshot(request).await.unwrap_or_else(|err| match err {})).await
+    }
+}
+
+/// Response read by a [`TestClient`]
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    /// Read the whole response
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be read.
+    pub async fn read(response: Response) -> Self {
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, SNAPSHOT_BODY_LIMIT)
+            .await
+            .expect("unreadable response body");
+
+        Self {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }
+    }
+
+    /// Status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Header value, if it is set and visible ASCII
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Raw body
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Body as a string (invalid UTF-8 sequences are replaced)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserialize the JSON body
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body is not a valid JSON `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|err| panic!("invalid JSON body ({err}): {}", self.text()))
+    }
+
+    /// Assert the status code
+    ///
+    /// # Panics
+    ///
+    /// Panics with the body if the status code is different.
+    pub fn assert_status(&self, status: StatusCode) -> &Self {
+        assert_eq!(self.status, status, "unexpected status, body: {}", self.text());
+        self
+    }
+}
+
+/// Assert that a response is a standard JSON error (`{"code": status, "message": message}`)
+///
+/// # Panics
+///
+/// Panics if the status, the content type, the code or the message are different.
+pub fn assert_api_error(response: &TestResponse, status: u16, message: &str) {
+    assert_eq!(
+        response.status.as_u16(),
+        status,
+        "unexpected status, body: {}",
+        response.text()
+    );
+
+    let content_type = response.header(CONTENT_TYPE.as_str()).unwrap_or_default();
+    assert!(
+        content_type.starts_with(mime::APPLICATION_JSON.as_ref()),
+        "not a JSON error (content type: {content_type:?}): {}",
+        response.text()
+    );
+
+    let body = response.json::<Value>();
+    assert_eq!(body["code"], status, "unexpected code: {body}");
+    assert_eq!(body["message"], message, "unexpected message: {body}");
+}
+
+/// `Authorization` header value of Basic Auth credentials
+fn basic_auth(username: &str, password: &str) -> String {
+    Credentials::new(username, password).as_http_header()
+}
+
+fn header_name(name: &str) -> HeaderName {
+    HeaderName::try_from(name).unwrap_or_else(|err| panic!("invalid header name {name}: {err}"))
+}
+
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::try_from(value).unwrap_or_else(|err| panic!("invalid header value {value}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use axum::response::IntoResponse;
+
+    fn response(name: &str) -> Response {
+        (
+            StatusCode::CREATED,
+            Json(json!({
+                "id": Uuid::new_v4(),
+                "name": name,
+                "created_at": chrono::Utc::now().to_rfc3339(),
+                "trace_id": "4bf92f3577b34da6a3ce929d0e0e4736",
+                "version": 3,
+                "tags": [Uuid::new_v4()],
+            })),
+        )
+            .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_fuzz_router() {
+        use crate::server::axum::extractors::{Path, Query};
+        use crate::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+        use axum::routing::get;
+        use serde::Deserialize;
+
+        #[derive(Deserialize)]
+        struct Pagination {
+            #[allow(dead_code)]
+            page: Option<u32>,
+        }
+
+        let app = Router::new()
+            .route(
+                "/users/{id}",
+                get(|Path(id): Path<u32>, Query(_): Query<Pagination>| async move { id.to_string() }),
+            )
+            .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024)));
+
+        let report = FuzzConfig::default().with_iterations(200).run(app, &["/users/1"]).await;
+        assert!(report.is_ok(), "{report}");
+        assert!(report.requests > 150, "{report}");
+    }
+
+    #[tokio::test]
+    async fn test_fuzz_router_reports_failures() {
+        use axum::routing::get;
+
+        let app = Router::new()
+            .route(
+                "/html",
+                get(|| async { (StatusCode::BAD_REQUEST, "<html>Bad request</html>") }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    "ok"
+                }),
+            );
+        let config = FuzzConfig {
+            iterations: 20,
+            seed: 1,
+            timeout: Duration::from_millis(50),
+        };
+
+        let report = config.run(app, &["/html", "/slow"]).await;
+        assert!(!report.is_ok());
+        assert!(
+            report
+                .failures
+                .iter()
+                .any(|failure| failure.reason.contains("not a JSON error")),
+            "{report}"
+        );
+        assert_eq!(fuzz_router(Router::new(), &[]).await, FuzzReport::default());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let snapshots = Snapshots::new("unused").with_volatile_fields(&["version"]);
+        assert_eq!(
+            snapshots.normalize(json!({
+                "request_id": "b2f5f0a4-3c1e-4f4b-8a8e-6c1f4a2d9e10",
+                "trace_id": "4bf92f3577b34da6a3ce929d0e0e4736",
+                "at": "2026-10-16T08:00:00+02:00",
+                "version": 3,
+                "items": [{ "name": "abc" }],
+            })),
+            json!({
+                "request_id": "[uuid]",
+                "trace_id": "[trace_id]",
+                "at": "[timestamp]",
+                "version": "[volatile]",
+                "items": [{ "name": "abc" }],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_response() {
+        let dir = std::env::temp_dir().join(format!("api-tools-snapshots-{}", std::process::id()));
+        let snapshots = Snapshots::new(&dir).with_update(false);
+
+        // Created if missing, then compared
+        let snapshot = snapshots.assert_response("user", response("Alice")).await.unwrap();
+        assert_eq!(snapshot["status"], 201);
+        assert_eq!(snapshot["body"]["id"], "[uuid]");
+        assert!(dir.join("user.json").exists());
+        assert!(snapshots.assert_response("user", response("Alice")).await.is_ok());
+
+        let err = snapshots.assert_response("user", response("Bob")).await.unwrap_err();
+        assert!(matches!(&err, SnapshotError::Mismatch { name, .. } if name == "user"));
+        assert!(err.to_string().contains("\"name\": \"Bob\""));
+
+        // Update mode
+        let snapshots = snapshots.with_update(true);
+        assert!(snapshots.assert_response("user", response("Bob")).await.is_ok());
+        let snapshots = snapshots.with_update(false);
+        assert!(snapshots.assert_response("user", response("Bob")).await.is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_test_client() {
+        use crate::server::axum::layers::basic_auth::BasicAuthLayer;
+        use crate::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+        use crate::server::axum::security::jwt::access_token::AccessToken;
+        use axum::routing::{get, post};
+        use serde::Deserialize;
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        struct Claims {
+            sub: String,
+            exp: i64,
+        }
+
+        let jwt = Jwt::init("HS256", 15, 24, Some("secret"), None, None).unwrap();
+        let parser = jwt.clone();
+        let app = Router::new()
+            .route("/echo", post(|Json(body): Json<Value>| async move { Json(body) }))
+            .route(
+                "/me",
+                get(move |token: AccessToken| async move {
+                    parser
+                        .parse::<Claims>(&token)
+                        .map(|claims| claims.sub)
+                        .unwrap_or_default()
+                }),
+            )
+            .route(
+                "/admin",
+                get(|| async { "admin" }).route_layer(BasicAuthLayer::new("admin", "secret")),
+            )
+            .layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024)));
+
+        let claims = Claims {
+            sub: "alice".to_string(),
+            exp: chrono::Utc::now().timestamp() + 60,
+        };
+        let client = TestClient::new(app).with_jwt(&jwt, claims).unwrap();
+
+        let response = client.get("/me").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "alice");
+
+        let response = client.post("/echo").json(&json!({ "id": 1 })).send().await;
+        assert_eq!(response.json::<Value>(), json!({ "id": 1 }));
+        assert_eq!(response.header("content-type"), Some("application/json"));
+
+        let response = client.get("/admin").basic_auth("admin", "secret").send().await;
+        assert_eq!(response.text(), "admin");
+        assert_eq!(client.get("/admin").send().await.status(), StatusCode::UNAUTHORIZED);
+
+        assert_api_error(&client.get("/missing").send().await, 404, "Resource Not Found");
+        let response = client.send(Request::delete("/echo").body(Body::empty()).unwrap()).await;
+        assert_api_error(&response, 405, "Method not allowed");
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "unexpected message")]
+    async fn test_assert_api_error_panics() {
+        use crate::server::axum::layers::http_errors::{HttpErrorsConfig, HttpErrorsLayer};
+
+        let app = Router::new().layer(HttpErrorsLayer::new(&HttpErrorsConfig::new(1_024)));
+
+        assert_api_error(&TestClient::new(app).get("/").send().await, 404, "Not found");
+    }
+}
@@ -1,9 +1,19 @@
 //! Datetime represents a date and time value in the UTC timezone.
 
-use chrono::{DateTime, TimeDelta, Utc};
-use serde::Deserialize;
-use std::fmt::{Display, Formatter};
-use std::ops::Add;
+#[cfg(feature = "tz")]
+use super::timezone::Timezone;
+use alloc::format;
+use alloc::string::String;
+#[cfg(feature = "tz")]
+use alloc::string::ToString;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Timelike, Utc};
+#[cfg(feature = "tz")]
+use chrono::{LocalResult, NaiveDateTime};
+#[cfg(feature = "tz")]
+use chrono_tz::Tz;
+use core::fmt::{Display, Formatter};
+use core::ops::{Add, Sub};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// UTC Datetime possible errors
@@ -11,16 +21,38 @@ use thiserror::Error;
 pub enum UtcDateTimeError {
     #[error("Invalid date time: {0}")]
     InvalidDateTime(String),
+
+    #[error("Nonexistent local date time: {0}")]
+    NonexistentLocalDateTime(String),
+
+    #[error("Ambiguous local date time: {0}")]
+    AmbiguousLocalDateTime(String),
+}
+
+/// Policy applied when a local date time is ambiguous (DST fall back, the same
+/// local time happens twice)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DstPolicy {
+    /// Use the earliest instant
+    #[default]
+    Earliest,
+
+    /// Use the latest instant
+    Latest,
+
+    /// Return an error
+    Reject,
 }
 
 /// Date time with UTC timezone
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Eq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct UtcDateTime {
     value: DateTime<Utc>,
 }
 
 impl UtcDateTime {
     /// Create a new date time for now
+    #[cfg(feature = "std")]
     pub fn now() -> Self {
         Self { value: Utc::now() }
     }
@@ -50,6 +82,72 @@ impl UtcDateTime {
         })
     }
 
+    /// Create a new date time from a local date time string in a timezone
+    ///
+    /// Accepted formats are `YYYY-MM-DDTHH:MM[:SS[.fff]]` and `YYYY-MM-DD HH:MM[:SS[.fff]]`.
+    /// A local time inside a DST gap is always rejected, an ambiguous one is resolved with `policy`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::value_objects::datetime::{DstPolicy, UtcDateTime};
+    /// use api_tools::value_objects::timezone::Timezone;
+    ///
+    /// let tz = Timezone::try_from("Europe/Paris").unwrap();
+    /// let datetime = UtcDateTime::from_local("2024-08-28 14:00", &tz, DstPolicy::Earliest).unwrap();
+    /// assert_eq!(datetime.to_string(), "2024-08-28T12:00:00Z");
+    ///
+    /// // 02:30 happens twice on 2024-10-27 in Paris
+    /// assert!(UtcDateTime::from_local("2024-10-27T02:30:00", &tz, DstPolicy::Reject).is_err());
+    /// ```
+    #[cfg(feature = "tz")]
+    pub fn from_local(value: &str, timezone: &Timezone, policy: DstPolicy) -> Result<Self, UtcDateTimeError> {
+        let normalized = value.trim().replacen(' ', "T", 1);
+        let local = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(&normalized, format).ok())
+            .ok_or_else(|| UtcDateTimeError::InvalidDateTime(value.to_string()))?;
+
+        let dt = match timezone.value().from_local_datetime(&local) {
+            LocalResult::Single(dt) => dt,
+            LocalResult::Ambiguous(earliest, latest) => match policy {
+                DstPolicy::Earliest => earliest,
+                DstPolicy::Latest => latest,
+                DstPolicy::Reject => {
+                    return Err(UtcDateTimeError::AmbiguousLocalDateTime(format!(
+                        "{value} ({timezone})"
+                    )));
+                }
+            },
+            LocalResult::None => {
+                return Err(UtcDateTimeError::NonexistentLocalDateTime(format!(
+                    "{value} ({timezone})"
+                )));
+            }
+        };
+
+        Ok(Self {
+            value: dt.with_timezone(&Utc),
+        })
+    }
+
+    /// Convert to a date time in a timezone
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::value_objects::datetime::UtcDateTime;
+    /// use api_tools::value_objects::timezone::Timezone;
+    ///
+    /// let tz = Timezone::try_from("Europe/Paris").unwrap();
+    /// let datetime = UtcDateTime::from_rfc3339("2024-08-28T12:00:00Z").unwrap();
+    /// assert_eq!(datetime.to_timezone(&tz).to_string(), "2024-08-28T14:00:00+02:00");
+    /// ```
+    #[cfg(feature = "tz")]
+    pub fn to_timezone(&self, timezone: &Timezone) -> LocalizedDateTime {
+        LocalizedDateTime {
+            value: self.value.with_timezone(&timezone.value()),
+        }
+    }
+
     /// Get timestamp value
     pub fn timestamp(&self) -> i64 {
         self.value.timestamp()
@@ -66,6 +164,157 @@ impl UtcDateTime {
             value: self.value.add(rhs),
         }
     }
+
+    /// Subtract a duration
+    pub fn sub(&self, rhs: TimeDelta) -> Self {
+        Self {
+            value: self.value.sub(rhs),
+        }
+    }
+
+    /// Duration elapsed since `earlier` (negative if `earlier` is after `self`)
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::value_objects::datetime::UtcDateTime;
+    /// use chrono::TimeDelta;
+    ///
+    /// let start = UtcDateTime::from_rfc3339("2024-08-28T12:00:00Z").unwrap();
+    /// let end = UtcDateTime::from_rfc3339("2024-08-28T13:30:00Z").unwrap();
+    /// assert_eq!(end.duration_since(&start), TimeDelta::minutes(90));
+    /// ```
+    pub fn duration_since(&self, earlier: &Self) -> TimeDelta {
+        self.value - earlier.value
+    }
+
+    /// Return true if the date time is before now
+    #[cfg(feature = "std")]
+    pub fn is_past(&self) -> bool {
+        self.value < Utc::now()
+    }
+
+    /// Return true if the date time is after now
+    #[cfg(feature = "std")]
+    pub fn is_future(&self) -> bool {
+        self.value > Utc::now()
+    }
+
+    /// Truncate to the second (remove nanoseconds)
+    pub fn truncate_to_seconds(&self) -> Self {
+        Self {
+            value: self.value.with_nanosecond(0).unwrap_or(self.value),
+        }
+    }
+
+    /// Truncate to the minute
+    pub fn truncate_to_minutes(&self) -> Self {
+        let value = self.truncate_to_seconds().value;
+        Self {
+            value: value.with_second(0).unwrap_or(value),
+        }
+    }
+
+    /// Truncate to the hour
+    pub fn truncate_to_hours(&self) -> Self {
+        let value = self.truncate_to_minutes().value;
+        Self {
+            value: value.with_minute(0).unwrap_or(value),
+        }
+    }
+
+    /// Truncate to the day (UTC midnight)
+    pub fn truncate_to_day(&self) -> Self {
+        self.start_of_day()
+    }
+
+    /// Start of the day in UTC (`00:00:00`)
+    pub fn start_of_day(&self) -> Self {
+        Self::start_of_date(self.value.date_naive(), &Utc)
+    }
+
+    /// End of the day in UTC (`23:59:59.999999999`)
+    pub fn end_of_day(&self) -> Self {
+        self.start_of_day()
+            .add(TimeDelta::days(1))
+            .sub(TimeDelta::nanoseconds(1))
+    }
+
+    /// Start of the day in a timezone, converted to UTC
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::value_objects::datetime::UtcDateTime;
+    /// use api_tools::value_objects::timezone::Timezone;
+    ///
+    /// let tz = Timezone::try_from("Europe/Paris").unwrap();
+    /// let datetime = UtcDateTime::from_rfc3339("2024-08-28T23:00:00Z").unwrap();
+    /// assert_eq!(datetime.start_of_day_in(&tz).to_string(), "2024-08-28T22:00:00Z");
+    /// ```
+    #[cfg(feature = "tz")]
+    pub fn start_of_day_in(&self, timezone: &Timezone) -> Self {
+        let tz = timezone.value();
+        Self::start_of_date(self.value.with_timezone(&tz).date_naive(), &tz)
+    }
+
+    /// End of the day in a timezone, converted to UTC
+    #[cfg(feature = "tz")]
+    pub fn end_of_day_in(&self, timezone: &Timezone) -> Self {
+        let tz = timezone.value();
+        match self.value.with_timezone(&tz).date_naive().succ_opt() {
+            Some(next_day) => Self::start_of_date(next_day, &tz).sub(TimeDelta::nanoseconds(1)),
+            None => Self::new(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+
+    /// First instant of a date in a timezone
+    ///
+    /// If midnight does not exist (DST gap), the first valid instant after the gap is used.
+    fn start_of_date<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> Self {
+        let midnight = date.and_time(NaiveTime::MIN);
+        let value = (0..=24)
+            .find_map(|hour| tz.from_local_datetime(&(midnight + TimeDelta::hours(hour))).earliest())
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|| midnight.and_utc());
+
+        Self { value }
+    }
+}
+
+/// Date time in a specific timezone
+#[cfg(feature = "tz")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalizedDateTime {
+    value: DateTime<Tz>,
+}
+
+#[cfg(feature = "tz")]
+impl LocalizedDateTime {
+    /// Get date time value
+    pub fn value(&self) -> DateTime<Tz> {
+        self.value
+    }
+
+    /// Get timezone
+    pub fn timezone(&self) -> Timezone {
+        Timezone::new(self.value.timezone())
+    }
+
+    /// Get local date and time (without timezone)
+    pub fn naive_local(&self) -> NaiveDateTime {
+        self.value.naive_local()
+    }
+
+    /// Convert to UTC
+    pub fn to_utc(&self) -> UtcDateTime {
+        UtcDateTime::new(self.value.with_timezone(&Utc))
+    }
+}
+
+#[cfg(feature = "tz")]
+impl Display for LocalizedDateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.value.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+    }
 }
 
 impl From<DateTime<Utc>> for UtcDateTime {
@@ -75,7 +324,7 @@ impl From<DateTime<Utc>> for UtcDateTime {
 }
 
 impl Display for UtcDateTime {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.value.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
     }
 }
@@ -133,6 +382,7 @@ mod test {
         assert_eq!(datetime.value(), dt);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn now_returns_a_value_between_before_and_after_calls() {
         let before = Utc::now();
@@ -142,6 +392,153 @@ mod test {
         assert!(dt.value() <= after);
     }
 
+    fn datetime(value: &str) -> UtcDateTime {
+        UtcDateTime::from_rfc3339(value).unwrap()
+    }
+
+    #[test]
+    fn test_sub_and_duration_since() {
+        let dt = datetime("2024-08-28T12:00:00Z");
+        assert_eq!(dt.sub(TimeDelta::hours(13)).to_string(), "2024-08-27T23:00:00Z");
+
+        let later = datetime("2024-08-29T12:00:30Z");
+        assert_eq!(later.duration_since(&dt), TimeDelta::seconds(86_430));
+        assert_eq!(dt.duration_since(&later), TimeDelta::seconds(-86_430));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_is_past_and_is_future() {
+        let now = UtcDateTime::now();
+        assert!(now.sub(TimeDelta::minutes(1)).is_past());
+        assert!(!now.sub(TimeDelta::minutes(1)).is_future());
+        assert!(now.add(TimeDelta::minutes(1)).is_future());
+        assert!(!now.add(TimeDelta::minutes(1)).is_past());
+    }
+
+    #[test]
+    fn test_comparison() {
+        let dt = datetime("2024-08-28T12:00:00Z");
+        let later = datetime("2024-08-28T12:00:01Z");
+        assert!(dt < later);
+        assert_eq!(dt.clone().max(later.clone()), later);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let dt = datetime("2024-08-28T12:34:56.789Z");
+        assert_eq!(
+            dt.truncate_to_seconds().value(),
+            datetime("2024-08-28T12:34:56Z").value()
+        );
+        assert_eq!(dt.truncate_to_minutes().to_string(), "2024-08-28T12:34:00Z");
+        assert_eq!(dt.truncate_to_hours().to_string(), "2024-08-28T12:00:00Z");
+        assert_eq!(dt.truncate_to_day().to_string(), "2024-08-28T00:00:00Z");
+    }
+
+    #[test]
+    fn test_start_and_end_of_day() {
+        let dt = datetime("2024-08-28T12:34:56Z");
+        assert_eq!(dt.start_of_day().to_string(), "2024-08-28T00:00:00Z");
+        assert_eq!(
+            dt.end_of_day().value(),
+            datetime("2024-08-28T23:59:59.999999999Z").value()
+        );
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_start_and_end_of_day_in_timezone() {
+        let paris = Timezone::try_from("Europe/Paris").unwrap();
+
+        // 2024-08-29 01:00 in Paris
+        let dt = datetime("2024-08-28T23:00:00Z");
+        assert_eq!(dt.start_of_day_in(&paris).to_string(), "2024-08-28T22:00:00Z");
+        assert_eq!(
+            dt.end_of_day_in(&paris).value(),
+            datetime("2024-08-29T21:59:59.999999999Z").value()
+        );
+
+        // DST change day in Paris lasts 23 hours
+        let dt = datetime("2024-03-31T12:00:00Z");
+        assert_eq!(
+            dt.end_of_day_in(&paris).duration_since(&dt.start_of_day_in(&paris)),
+            TimeDelta::hours(23) - TimeDelta::nanoseconds(1)
+        );
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_start_of_day_in_timezone_without_midnight() {
+        // Midnight did not exist in Santiago on 2024-09-08 (clocks jumped to 01:00)
+        let santiago = Timezone::try_from("America/Santiago").unwrap();
+        let dt = datetime("2024-09-08T12:00:00Z");
+        assert_eq!(dt.start_of_day_in(&santiago).to_string(), "2024-09-08T04:00:00Z");
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_to_timezone() {
+        let paris = Timezone::try_from("Europe/Paris").unwrap();
+        let dt = datetime("2024-01-15T23:30:00Z");
+        let local = dt.to_timezone(&paris);
+
+        assert_eq!(local.to_string(), "2024-01-16T00:30:00+01:00");
+        assert_eq!(local.timezone(), paris);
+        assert_eq!(local.naive_local().to_string(), "2024-01-16 00:30:00");
+        assert_eq!(local.to_utc(), dt);
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_from_local() {
+        let paris = Timezone::try_from("Europe/Paris").unwrap();
+
+        for value in [
+            "2024-08-28T14:00:00",
+            "2024-08-28 14:00:00",
+            "2024-08-28T14:00",
+            "2024-08-28 14:00:00.000",
+        ] {
+            let dt = UtcDateTime::from_local(value, &paris, DstPolicy::Reject).unwrap();
+            assert_eq!(dt.to_string(), "2024-08-28T12:00:00Z", "{value}");
+        }
+
+        assert_eq!(
+            UtcDateTime::from_local("2024-08-28", &paris, DstPolicy::Earliest),
+            Err(UtcDateTimeError::InvalidDateTime("2024-08-28".to_string()))
+        );
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_from_local_dst() {
+        let paris = Timezone::try_from("Europe/Paris").unwrap();
+
+        // Fall back: 02:30 happens twice
+        let earliest = UtcDateTime::from_local("2024-10-27T02:30:00", &paris, DstPolicy::Earliest).unwrap();
+        assert_eq!(earliest.to_string(), "2024-10-27T00:30:00Z");
+        let latest = UtcDateTime::from_local("2024-10-27T02:30:00", &paris, DstPolicy::Latest).unwrap();
+        assert_eq!(latest.to_string(), "2024-10-27T01:30:00Z");
+        assert!(matches!(
+            UtcDateTime::from_local("2024-10-27T02:30:00", &paris, DstPolicy::Reject),
+            Err(UtcDateTimeError::AmbiguousLocalDateTime(_))
+        ));
+
+        // Spring forward: 02:30 does not exist
+        assert!(matches!(
+            UtcDateTime::from_local("2024-03-31T02:30:00", &paris, DstPolicy::Earliest),
+            Err(UtcDateTimeError::NonexistentLocalDateTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let dt = datetime("2024-08-28T12:00:00Z");
+        let json = serde_json::to_string(&dt).unwrap();
+        assert_eq!(serde_json::from_str::<UtcDateTime>(&json).unwrap(), dt);
+    }
+
     #[test]
     fn test_add() {
         let dt = DateTime::parse_from_rfc3339("2024-08-28T12:00:00Z")
@@ -0,0 +1,242 @@
+//! Date time range value object representation
+//!
+//! A range is half-open: `start` is included and `end` is excluded, so that
+//! consecutive ranges (e.g. daily chunks) never overlap.
+
+use super::datetime::UtcDateTime;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use chrono::{DateTime, TimeDelta, Utc};
+use core::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Date time range possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DateTimeRangeError {
+    #[error("Invalid date time range: start ({start}) is after end ({end})")]
+    StartAfterEnd { start: String, end: String },
+}
+
+/// Date time range (`[start, end)`) in UTC
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "RawDateTimeRange", try_from = "RawDateTimeRange")]
+pub struct DateTimeRange {
+    start: UtcDateTime,
+    end: UtcDateTime,
+}
+
+/// Serialized representation of a range (RFC3339 dates)
+#[derive(Serialize, Deserialize)]
+struct RawDateTimeRange {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl DateTimeRange {
+    /// Create a new range
+    ///
+    /// # Example
+    /// ```rust
+    /// use api_tools::value_objects::datetime::UtcDateTime;
+    /// use api_tools::value_objects::datetime_range::DateTimeRange;
+    ///
+    /// let start = UtcDateTime::from_rfc3339("2024-08-28T12:00:00Z").unwrap();
+    /// let end = UtcDateTime::from_rfc3339("2024-08-30T06:00:00Z").unwrap();
+    /// let range = DateTimeRange::new(start.clone(), end.clone()).unwrap();
+    /// assert_eq!(range.split_by_day().len(), 3);
+    ///
+    /// assert!(DateTimeRange::new(end, start).is_err());
+    /// ```
+    pub fn new(start: UtcDateTime, end: UtcDateTime) -> Result<Self, DateTimeRangeError> {
+        if start > end {
+            return Err(DateTimeRangeError::StartAfterEnd {
+                start: start.to_string(),
+                end: end.to_string(),
+            });
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// Get range start (included)
+    pub fn start(&self) -> &UtcDateTime {
+        &self.start
+    }
+
+    /// Get range end (excluded)
+    pub fn end(&self) -> &UtcDateTime {
+        &self.end
+    }
+
+    /// Range duration
+    pub fn duration(&self) -> TimeDelta {
+        self.end.duration_since(&self.start)
+    }
+
+    /// Return true if the range is empty (`start == end`)
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Return true if the date time is in the range
+    pub fn contains(&self, datetime: &UtcDateTime) -> bool {
+        &self.start <= datetime && datetime < &self.end
+    }
+
+    /// Return true if both ranges share at least one instant
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// Common part of both ranges, `None` if they do not overlap
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        Some(Self {
+            start: self.start.clone().max(other.start.clone()),
+            end: self.end.clone().min(other.end.clone()),
+        })
+    }
+
+    /// Split the range into daily chunks (UTC days)
+    ///
+    /// The first and last chunks are truncated to the range bounds.
+    pub fn split_by_day(&self) -> Vec<Self> {
+        let mut chunks = Vec::new();
+        let mut start = self.start.clone();
+
+        while start < self.end {
+            let next_day = start.start_of_day().add(TimeDelta::days(1));
+            let end = next_day.min(self.end.clone());
+            chunks.push(Self {
+                start,
+                end: end.clone(),
+            });
+            start = end;
+        }
+
+        chunks
+    }
+}
+
+impl Display for DateTimeRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}/{}", self.start, self.end)
+    }
+}
+
+impl From<DateTimeRange> for RawDateTimeRange {
+    fn from(range: DateTimeRange) -> Self {
+        Self {
+            start: range.start.value(),
+            end: range.end.value(),
+        }
+    }
+}
+
+impl TryFrom<RawDateTimeRange> for DateTimeRange {
+    type Error = DateTimeRangeError;
+
+    fn try_from(raw: RawDateTimeRange) -> Result<Self, Self::Error> {
+        Self::new(raw.start.into(), raw.end.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(value: &str) -> UtcDateTime {
+        UtcDateTime::from_rfc3339(value).unwrap()
+    }
+
+    fn range(start: &str, end: &str) -> DateTimeRange {
+        DateTimeRange::new(datetime(start), datetime(end)).unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        let r = range("2024-08-28T12:00:00Z", "2024-08-28T12:00:00Z");
+        assert!(r.is_empty());
+        assert_eq!(r.duration(), TimeDelta::zero());
+
+        let err = DateTimeRange::new(datetime("2024-08-29T00:00:00Z"), datetime("2024-08-28T00:00:00Z"));
+        assert_eq!(
+            err,
+            Err(DateTimeRangeError::StartAfterEnd {
+                start: "2024-08-29T00:00:00Z".to_string(),
+                end: "2024-08-28T00:00:00Z".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_contains_and_duration() {
+        let r = range("2024-08-28T12:00:00Z", "2024-08-28T14:00:00Z");
+        assert_eq!(r.duration(), TimeDelta::hours(2));
+        assert!(r.contains(&datetime("2024-08-28T12:00:00Z")));
+        assert!(r.contains(&datetime("2024-08-28T13:59:59Z")));
+        assert!(!r.contains(&datetime("2024-08-28T14:00:00Z")));
+        assert!(!r.contains(&datetime("2024-08-28T11:59:59Z")));
+    }
+
+    #[test]
+    fn test_overlaps_and_intersection() {
+        let a = range("2024-08-28T10:00:00Z", "2024-08-28T14:00:00Z");
+        let b = range("2024-08-28T12:00:00Z", "2024-08-28T16:00:00Z");
+        let c = range("2024-08-28T14:00:00Z", "2024-08-28T16:00:00Z");
+
+        assert!(a.overlaps(&b));
+        assert!(b.overlaps(&a));
+        assert_eq!(
+            a.intersection(&b),
+            Some(range("2024-08-28T12:00:00Z", "2024-08-28T14:00:00Z"))
+        );
+
+        // Adjacent ranges do not overlap
+        assert!(!a.overlaps(&c));
+        assert_eq!(a.intersection(&c), None);
+    }
+
+    #[test]
+    fn test_split_by_day() {
+        let chunks = range("2024-08-28T12:00:00Z", "2024-08-30T06:00:00Z").split_by_day();
+        assert_eq!(
+            chunks,
+            vec![
+                range("2024-08-28T12:00:00Z", "2024-08-29T00:00:00Z"),
+                range("2024-08-29T00:00:00Z", "2024-08-30T00:00:00Z"),
+                range("2024-08-30T00:00:00Z", "2024-08-30T06:00:00Z"),
+            ]
+        );
+
+        let chunks = range("2024-08-28T00:00:00Z", "2024-08-29T00:00:00Z").split_by_day();
+        assert_eq!(chunks.len(), 1);
+
+        assert!(
+            range("2024-08-28T00:00:00Z", "2024-08-28T00:00:00Z")
+                .split_by_day()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let r = range("2024-08-28T12:00:00Z", "2024-08-28T14:00:00Z");
+        assert_eq!(r.to_string(), "2024-08-28T12:00:00Z/2024-08-28T14:00:00Z");
+    }
+
+    #[test]
+    fn test_serde() {
+        let r = range("2024-08-28T12:00:00Z", "2024-08-28T14:00:00Z");
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(json, r#"{"start":"2024-08-28T12:00:00Z","end":"2024-08-28T14:00:00Z"}"#);
+        assert_eq!(serde_json::from_str::<DateTimeRange>(&json).unwrap(), r);
+
+        let invalid = r#"{"start":"2024-08-28T14:00:00Z","end":"2024-08-28T12:00:00Z"}"#;
+        assert!(serde_json::from_str::<DateTimeRange>(invalid).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Email value object representation
+//!
+//! The address is validated and lowercased on construction. `Display` and
+//! `Debug` redact the local part (`j***@example.com`) so that an email never
+//! leaks in logs. Use [`Email::value`] to get the full address.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display, Formatter};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// Email max length
+const EMAIL_MAX_LENGTH: usize = 254;
+
+/// Email local part max length
+const EMAIL_LOCAL_MAX_LENGTH: usize = 64;
+
+/// Email domain label max length
+const EMAIL_LABEL_MAX_LENGTH: usize = 63;
+
+/// Special characters allowed in the local part
+const EMAIL_LOCAL_SPECIAL_CHARS: &str = ".!#$%&'*+/=?^_`{|}~-";
+
+/// Email possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum EmailError {
+    #[error("Invalid email: {0}")]
+    Invalid(String),
+}
+
+/// Email address
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Email {
+    value: String,
+}
+
+impl Email {
+    /// Create a new email
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::email::Email;
+    ///
+    /// let email = Email::new(" John.Doe@Example.com ").unwrap();
+    /// assert_eq!(email.value(), "john.doe@example.com");
+    /// assert_eq!(email.to_string(), "j***@example.com");
+    ///
+    /// assert!(Email::new("john.doe@").is_err());
+    /// ```
+    pub fn new(value: &str) -> Result<Self, EmailError> {
+        let value = value.trim().to_lowercase();
+        Self::validate(&value).map_err(|reason| EmailError::Invalid(reason.to_string()))?;
+
+        Ok(Self { value })
+    }
+
+    /// Get the full email address
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get the local part (before `@`)
+    pub fn local_part(&self) -> &str {
+        self.value.split_once('@').map(|(local, _)| local).unwrap_or_default()
+    }
+
+    /// Get the domain (after `@`)
+    pub fn domain(&self) -> &str {
+        self.value.split_once('@').map(|(_, domain)| domain).unwrap_or_default()
+    }
+
+    /// Validate the syntax of a (lowercased) email
+    fn validate(value: &str) -> Result<(), &'static str> {
+        if value.len() > EMAIL_MAX_LENGTH {
+            return Err("too long");
+        }
+
+        let (local, domain) = value.rsplit_once('@').ok_or("missing @")?;
+
+        // Local part
+        if local.is_empty() || local.len() > EMAIL_LOCAL_MAX_LENGTH {
+            return Err("invalid local part length");
+        }
+        if !local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || EMAIL_LOCAL_SPECIAL_CHARS.contains(c))
+        {
+            return Err("invalid character in local part");
+        }
+        if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+            return Err("invalid dot in local part");
+        }
+
+        // Domain
+        let labels = domain.split('.').collect::<Vec<_>>();
+        if labels.len() < 2 {
+            return Err("invalid domain");
+        }
+        for label in labels {
+            if label.is_empty() || label.len() > EMAIL_LABEL_MAX_LENGTH {
+                return Err("invalid domain label length");
+            }
+            if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err("invalid character in domain");
+            }
+            if label.starts_with('-') || label.ends_with('-') {
+                return Err("invalid hyphen in domain");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for Email {
+    type Error = EmailError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<String> for Email {
+    type Error = EmailError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(&value)
+    }
+}
+
+impl Display for Email {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let first = self.local_part().chars().next().unwrap_or_default();
+        write!(f, "{first}***@{}", self.domain())
+    }
+}
+
+impl Debug for Email {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Email({self})")
+    }
+}
+
+impl Serialize for Email {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Email {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Self::new(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_normalizes_case_and_whitespace() {
+        let email = Email::new("  John.Doe+Tag@Sub.Example.COM ").unwrap();
+        assert_eq!(email.value(), "john.doe+tag@sub.example.com");
+        assert_eq!(email.local_part(), "john.doe+tag");
+        assert_eq!(email.domain(), "sub.example.com");
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_emails() {
+        for value in [
+            "",
+            "john",
+            "@example.com",
+            "john@",
+            "john@localhost",
+            "john@@example.com",
+            "jo hn@example.com",
+            ".john@example.com",
+            "john.@example.com",
+            "jo..hn@example.com",
+            "john@-example.com",
+            "john@example-.com",
+            "john@exa_mple.com",
+            "john@example..com",
+            "jöhn@example.com",
+        ] {
+            assert!(Email::new(value).is_err(), "{value:?} must be rejected");
+        }
+
+        let long_local = format!("{}@example.com", "a".repeat(EMAIL_LOCAL_MAX_LENGTH + 1));
+        assert!(Email::new(&long_local).is_err());
+
+        let long_label = format!("john@{}.com", "a".repeat(EMAIL_LABEL_MAX_LENGTH + 1));
+        assert!(Email::new(&long_label).is_err());
+
+        let too_long = format!("john@{}.com", vec!["a".repeat(60); 5].join("."));
+        assert!(Email::new(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_display_and_debug_are_redacted() {
+        let email = Email::new("john.doe@example.com").unwrap();
+        assert_eq!(email.to_string(), "j***@example.com");
+        assert_eq!(format!("{email:?}"), "Email(j***@example.com)");
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let email = Email::new("John@Example.com").unwrap();
+        let json = serde_json::to_string(&email).unwrap();
+        assert_eq!(json, "\"john@example.com\"");
+
+        let email: Email = serde_json::from_str("\"JOHN@example.com\"").unwrap();
+        assert_eq!(email.value(), "john@example.com");
+
+        let err = serde_json::from_str::<Email>("\"john@\"");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert!(Email::try_from("john@example.com").is_ok());
+        assert!(Email::try_from("john".to_string()).is_err());
+    }
+}
@@ -1,6 +1,19 @@
 //! Value objects list
+//!
+//! Value objects only need `core` and `alloc` (with `chrono`, `serde` and `thiserror`), so that
+//! domain types can be shared with `no_std` targets (`default-features = false`):
+//! - `std` (default): `UtcDateTime::now`, `is_past`, `is_future`, `Timezone::now` and `QueryFilters::parse`
+//! - `tz` (default): `Timezone` and the time zone conversions of `UtcDateTime` (`chrono-tz`)
+//! - `typed-id`: `Id<T>` (`uuid`)
 
 pub mod datetime;
+pub mod datetime_range;
+pub mod email;
+pub mod money;
 pub mod pagination;
+pub mod query_filter;
 pub mod query_sort;
+#[cfg(feature = "tz")]
 pub mod timezone;
+#[cfg(feature = "typed-id")]
+pub mod typed_id;
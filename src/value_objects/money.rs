@@ -0,0 +1,545 @@
+//! Money value object representation
+//!
+//! An amount is stored as a fixed-point integer in the minor unit of its
+//! currency (cents for `EUR`, yen for `JPY`, etc.), so no floating point is
+//! ever involved. Arithmetic between two amounts of different currencies is
+//! refused.
+//!
+//! Serialized as `{ "amount": "12.34", "currency": "EUR" }`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// ISO-4217 currencies supported with their number of minor units
+const CURRENCIES: &[(&str, u32)] = &[
+    ("AED", 2),
+    ("ARS", 2),
+    ("AUD", 2),
+    ("BGN", 2),
+    ("BHD", 3),
+    ("BRL", 2),
+    ("CAD", 2),
+    ("CHF", 2),
+    ("CLP", 0),
+    ("CNY", 2),
+    ("COP", 2),
+    ("CZK", 2),
+    ("DKK", 2),
+    ("EGP", 2),
+    ("EUR", 2),
+    ("GBP", 2),
+    ("HKD", 2),
+    ("HUF", 2),
+    ("IDR", 2),
+    ("ILS", 2),
+    ("INR", 2),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("MAD", 2),
+    ("MXN", 2),
+    ("MYR", 2),
+    ("NOK", 2),
+    ("NZD", 2),
+    ("OMR", 3),
+    ("PEN", 2),
+    ("PHP", 2),
+    ("PLN", 2),
+    ("RON", 2),
+    ("SAR", 2),
+    ("SEK", 2),
+    ("SGD", 2),
+    ("THB", 2),
+    ("TND", 3),
+    ("TRY", 2),
+    ("UAH", 2),
+    ("USD", 2),
+    ("VND", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+    ("ZAR", 2),
+];
+
+/// Money possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MoneyError {
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error("Invalid or unsupported currency: {0}")]
+    InvalidCurrency(String),
+
+    #[error("Currency mismatch: {0} and {1}")]
+    CurrencyMismatch(Currency, Currency),
+
+    #[error("Amount has too many decimals for {0}: {1}")]
+    TooManyDecimals(Currency, String),
+
+    #[error("Amount overflow")]
+    Overflow,
+}
+
+/// ISO-4217 currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency {
+    code: &'static str,
+    minor_units: u32,
+}
+
+impl Currency {
+    /// Get the ISO-4217 code
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Get the number of minor units (decimals)
+    pub fn minor_units(&self) -> u32 {
+        self.minor_units
+    }
+}
+
+impl TryFrom<&str> for Currency {
+    type Error = MoneyError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let code = value.trim().to_uppercase();
+        CURRENCIES
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(code, minor_units)| Self {
+                code,
+                minor_units: *minor_units,
+            })
+            .ok_or(MoneyError::InvalidCurrency(value.to_string()))
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+/// Rounding mode used when an amount has more decimals than its currency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to nearest, ties away from zero (`1.005` → `1.01`)
+    #[default]
+    HalfUp,
+
+    /// Round to nearest, ties to even (`1.005` → `1.00`, `1.015` → `1.02`)
+    HalfEven,
+
+    /// Round towards zero
+    Down,
+
+    /// Round away from zero
+    Up,
+
+    /// Round towards negative infinity
+    Floor,
+
+    /// Round towards positive infinity
+    Ceiling,
+}
+
+impl RoundingMode {
+    /// Divide `value` by `divisor` (> 0) and round the result
+    fn divide(&self, value: i128, divisor: i128) -> i128 {
+        let quotient = value / divisor;
+        let remainder = value % divisor;
+        if remainder == 0 {
+            return quotient;
+        }
+
+        let away = quotient + value.signum();
+        let twice = 2 * remainder.abs();
+        match self {
+            Self::Down => quotient,
+            Self::Up => away,
+            Self::Floor if value < 0 => away,
+            Self::Floor => quotient,
+            Self::Ceiling if value > 0 => away,
+            Self::Ceiling => quotient,
+            Self::HalfUp if twice >= divisor => away,
+            Self::HalfUp => quotient,
+            Self::HalfEven if twice > divisor || (twice == divisor && quotient % 2 != 0) => away,
+            Self::HalfEven => quotient,
+        }
+    }
+}
+
+/// Amount of money in a currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    /// Amount in minor units
+    amount: i64,
+    currency: Currency,
+}
+
+impl Money {
+    /// Create a new amount from minor units (e.g. cents)
+    pub fn new(amount: i64, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    /// Create a zero amount
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Parse a decimal amount, refusing amounts with more decimals than the currency
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::money::{Currency, Money};
+    ///
+    /// let eur = Currency::try_from("EUR").unwrap();
+    /// let money = Money::parse("12.34", eur).unwrap();
+    /// assert_eq!(money.amount(), 1_234);
+    /// assert_eq!(money.to_string(), "12.34 EUR");
+    ///
+    /// assert!(Money::parse("12.345", eur).is_err());
+    /// ```
+    pub fn parse(value: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let (digits, scale) = Self::parse_decimal(value)?;
+        if scale > currency.minor_units {
+            return Err(MoneyError::TooManyDecimals(currency, value.to_string()));
+        }
+
+        let amount = digits
+            .checked_mul(Self::pow10(currency.minor_units - scale)?)
+            .ok_or(MoneyError::Overflow)?;
+
+        Ok(Self::new(Self::to_i64(amount)?, currency))
+    }
+
+    /// Parse a decimal amount, rounding the extra decimals
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::money::{Currency, Money, RoundingMode};
+    ///
+    /// let eur = Currency::try_from("EUR").unwrap();
+    /// assert_eq!(Money::parse_with_rounding("1.005", eur, RoundingMode::HalfUp).unwrap().amount(), 101);
+    /// assert_eq!(Money::parse_with_rounding("1.005", eur, RoundingMode::HalfEven).unwrap().amount(), 100);
+    /// ```
+    pub fn parse_with_rounding(value: &str, currency: Currency, rounding: RoundingMode) -> Result<Self, MoneyError> {
+        let (digits, scale) = Self::parse_decimal(value)?;
+        Self::rescale(digits, scale, currency, rounding)
+    }
+
+    /// Get the amount in minor units
+    pub fn amount(&self) -> i64 {
+        self.amount
+    }
+
+    /// Get the currency
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Return true if the amount is zero
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Return true if the amount is negative
+    pub fn is_negative(&self) -> bool {
+        self.amount < 0
+    }
+
+    /// Add two amounts of the same currency
+    pub fn checked_add(&self, other: &Self) -> Result<Self, MoneyError> {
+        self.check_currency(other)?;
+        let amount = self.amount.checked_add(other.amount).ok_or(MoneyError::Overflow)?;
+
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Subtract two amounts of the same currency
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, MoneyError> {
+        self.check_currency(other)?;
+        let amount = self.amount.checked_sub(other.amount).ok_or(MoneyError::Overflow)?;
+
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Multiply by a decimal factor (quantity, rate, etc.) and round the result
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::money::{Currency, Money, RoundingMode};
+    ///
+    /// let eur = Currency::try_from("EUR").unwrap();
+    /// let price = Money::parse("10.00", eur).unwrap();
+    /// let vat = price.multiply("0.055", RoundingMode::HalfUp).unwrap();
+    /// assert_eq!(vat.to_string(), "0.55 EUR");
+    /// ```
+    pub fn multiply(&self, factor: &str, rounding: RoundingMode) -> Result<Self, MoneyError> {
+        let (digits, scale) = Self::parse_decimal(factor)?;
+        let product = (self.amount as i128).checked_mul(digits).ok_or(MoneyError::Overflow)?;
+        let amount = rounding.divide(product, Self::pow10(scale)?);
+
+        Ok(Self::new(Self::to_i64(amount)?, self.currency))
+    }
+
+    /// Split the amount into `parts` amounts whose sum is the original amount
+    ///
+    /// The remainder is spread, one minor unit at a time, over the first parts.
+    pub fn allocate(&self, parts: u32) -> Vec<Self> {
+        if parts == 0 {
+            return Vec::new();
+        }
+
+        let parts_i64 = parts as i64;
+        let base = self.amount / parts_i64;
+        let remainder = self.amount % parts_i64;
+
+        (0..parts_i64)
+            .map(|i| {
+                let extra = if i < remainder.abs() { remainder.signum() } else { 0 };
+                Self::new(base + extra, self.currency)
+            })
+            .collect()
+    }
+
+    /// Format the amount as a decimal string (without currency)
+    pub fn amount_to_string(&self) -> String {
+        let scale = self.currency.minor_units as usize;
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let digits = self.amount.unsigned_abs().to_string();
+
+        if scale == 0 {
+            return format!("{sign}{digits}");
+        }
+
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        format!("{sign}{integer}.{fraction}")
+    }
+
+    fn check_currency(&self, other: &Self) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(self.currency, other.currency));
+        }
+        Ok(())
+    }
+
+    /// Convert a decimal (digits, scale) into the currency minor units
+    fn rescale(digits: i128, scale: u32, currency: Currency, rounding: RoundingMode) -> Result<Self, MoneyError> {
+        let amount = if scale > currency.minor_units {
+            rounding.divide(digits, Self::pow10(scale - currency.minor_units)?)
+        } else {
+            digits
+                .checked_mul(Self::pow10(currency.minor_units - scale)?)
+                .ok_or(MoneyError::Overflow)?
+        };
+
+        Ok(Self::new(Self::to_i64(amount)?, currency))
+    }
+
+    /// Parse a decimal string into its digits and its scale (`"-12.340"` → `(-12340, 3)`)
+    fn parse_decimal(value: &str) -> Result<(i128, u32), MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(value.to_string());
+
+        let trimmed = value.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+        if integer.is_empty()
+            || !integer.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+            || (unsigned.contains('.') && fraction.is_empty())
+        {
+            return Err(invalid());
+        }
+
+        let digits = format!("{integer}{fraction}")
+            .parse::<i128>()
+            .map_err(|_| MoneyError::Overflow)?;
+        let scale = u32::try_from(fraction.len()).map_err(|_| invalid())?;
+
+        Ok((if negative { -digits } else { digits }, scale))
+    }
+
+    fn pow10(exponent: u32) -> Result<i128, MoneyError> {
+        10i128.checked_pow(exponent).ok_or(MoneyError::Overflow)
+    }
+
+    fn to_i64(value: i128) -> Result<i64, MoneyError> {
+        i64::try_from(value).map_err(|_| MoneyError::Overflow)
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {}", self.amount_to_string(), self.currency)
+    }
+}
+
+/// Money JSON representation
+#[derive(Serialize, Deserialize)]
+struct MoneyRepr {
+    amount: String,
+    currency: String,
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyRepr {
+            amount: self.amount_to_string(),
+            currency: self.currency.code.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoneyRepr::deserialize(deserializer)?;
+        let currency = Currency::try_from(repr.currency.as_str()).map_err(D::Error::custom)?;
+
+        Self::parse(&repr.amount, currency).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eur() -> Currency {
+        Currency::try_from("EUR").unwrap()
+    }
+
+    fn jpy() -> Currency {
+        Currency::try_from("jpy").unwrap()
+    }
+
+    #[test]
+    fn test_currency_try_from() {
+        assert_eq!(eur().code(), "EUR");
+        assert_eq!(eur().minor_units(), 2);
+        assert_eq!(jpy().minor_units(), 0);
+        assert_eq!(Currency::try_from("KWD").unwrap().minor_units(), 3);
+        assert_eq!(
+            Currency::try_from("ABC"),
+            Err(MoneyError::InvalidCurrency("ABC".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Money::parse("12.34", eur()).unwrap().amount(), 1_234);
+        assert_eq!(Money::parse("12.3", eur()).unwrap().amount(), 1_230);
+        assert_eq!(Money::parse("12", eur()).unwrap().amount(), 1_200);
+        assert_eq!(Money::parse("-0.05", eur()).unwrap().amount(), -5);
+        assert_eq!(Money::parse("+1", jpy()).unwrap().amount(), 1);
+
+        assert!(matches!(
+            Money::parse("1.234", eur()),
+            Err(MoneyError::TooManyDecimals(_, _))
+        ));
+        assert!(matches!(
+            Money::parse("1.5", jpy()),
+            Err(MoneyError::TooManyDecimals(_, _))
+        ));
+        for value in ["", "abc", "1.", ".5", "1,5", "1.2.3", "--1", "1e3"] {
+            assert!(
+                matches!(Money::parse(value, eur()), Err(MoneyError::InvalidAmount(_))),
+                "{value:?} must be rejected"
+            );
+        }
+        assert_eq!(Money::parse("99999999999999999999", eur()), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let cases = [
+            (RoundingMode::HalfUp, ["1.005", "1.015", "-1.005"], [101, 102, -101]),
+            (RoundingMode::HalfEven, ["1.005", "1.015", "-1.005"], [100, 102, -100]),
+            (RoundingMode::Down, ["1.009", "1.001", "-1.009"], [100, 100, -100]),
+            (RoundingMode::Up, ["1.001", "1.009", "-1.001"], [101, 101, -101]),
+            (RoundingMode::Floor, ["1.009", "1.001", "-1.001"], [100, 100, -101]),
+            (RoundingMode::Ceiling, ["1.001", "1.009", "-1.009"], [101, 101, -100]),
+        ];
+        for (mode, values, expected) in cases {
+            for (value, expected) in values.iter().zip(expected) {
+                let money = Money::parse_with_rounding(value, eur(), mode).unwrap();
+                assert_eq!(money.amount(), expected, "{mode:?} {value}");
+            }
+        }
+        assert_eq!(
+            Money::parse_with_rounding("1.5", eur(), RoundingMode::HalfUp)
+                .unwrap()
+                .amount(),
+            150
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_refuses_to_mix_currencies() {
+        let a = Money::new(1_000, eur());
+        let b = Money::new(250, eur());
+        assert_eq!(a.checked_add(&b).unwrap().amount(), 1_250);
+        assert_eq!(b.checked_sub(&a).unwrap().amount(), -750);
+        assert!(b.checked_sub(&a).unwrap().is_negative());
+
+        let yen = Money::new(100, jpy());
+        assert_eq!(a.checked_add(&yen), Err(MoneyError::CurrencyMismatch(eur(), jpy())));
+        assert_eq!(
+            Money::new(i64::MAX, eur()).checked_add(&Money::new(1, eur())),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_multiply_and_allocate() {
+        let price = Money::new(999, eur());
+        assert_eq!(price.multiply("3", RoundingMode::HalfUp).unwrap().amount(), 2_997);
+        assert_eq!(price.multiply("0.5", RoundingMode::HalfUp).unwrap().amount(), 500);
+        assert_eq!(price.multiply("0.5", RoundingMode::Down).unwrap().amount(), 499);
+
+        let parts = Money::new(1_000, eur()).allocate(3);
+        assert_eq!(parts.iter().map(Money::amount).collect::<Vec<_>>(), vec![334, 333, 333]);
+        let parts = Money::new(-1_000, eur()).allocate(3);
+        assert_eq!(
+            parts.iter().map(Money::amount).collect::<Vec<_>>(),
+            vec![-334, -333, -333]
+        );
+        assert!(Money::zero(eur()).allocate(0).is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Money::new(1_234, eur()).to_string(), "12.34 EUR");
+        assert_eq!(Money::new(5, eur()).to_string(), "0.05 EUR");
+        assert_eq!(Money::new(-5, eur()).to_string(), "-0.05 EUR");
+        assert_eq!(Money::new(1_234, jpy()).to_string(), "1234 JPY");
+        assert!(Money::zero(eur()).is_zero());
+    }
+
+    #[test]
+    fn test_serde() {
+        let money = Money::new(1_234, eur());
+        let json = serde_json::to_value(money).unwrap();
+        assert_eq!(json, serde_json::json!({ "amount": "12.34", "currency": "EUR" }));
+
+        let money: Money = serde_json::from_value(json).unwrap();
+        assert_eq!(money, Money::new(1_234, eur()));
+
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": "1.234", "currency": "EUR" }"#).is_err());
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": "1.23", "currency": "XXX" }"#).is_err());
+    }
+}
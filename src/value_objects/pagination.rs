@@ -1,5 +1,7 @@
 //! Pagination value object representation
 
+use serde::{Deserialize, Serialize};
+
 /// Pagination min limit
 pub const PAGINATION_MIN_LIMIT: u32 = 10;
 
@@ -100,8 +102,8 @@ impl Default for Pagination {
 }
 
 /// Pagination for response
-#[derive(Debug, Clone, PartialEq)]
-
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct PaginationResponse {
     pub page: u32,
     pub limit: u32,
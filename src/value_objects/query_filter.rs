@@ -0,0 +1,495 @@
+//! Query filters value object representation
+//!
+//! Parses query parameters like `?filter[age][gte]=18&filter[name][like]=bob`
+//! into a list of field / operator / value triples. Only fields and operators
+//! declared in [`QueryFilterRules`] are accepted, so the result can safely be
+//! turned into SQL predicates with [`QueryFilters::to_sql`]. The filters can only
+//! be built by [`QueryFilters::parse`], the field names are never unchecked input.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use thiserror::Error;
+
+/// Query filter prefix in the query string
+#[cfg(feature = "std")]
+const QUERY_FILTER_PREFIX: &str = "filter";
+
+/// Query filter possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum QueryFilterError {
+    #[error("Invalid query string: {0}")]
+    InvalidQuery(String),
+
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Invalid filter operator: {0}")]
+    InvalidOperator(String),
+
+    #[error("Filter field not allowed: {0}")]
+    FieldNotAllowed(String),
+
+    #[error("Filter operator not allowed for field {0}: {1}")]
+    OperatorNotAllowed(String, QueryFilterOperator),
+}
+
+/// Filter operator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QueryFilterOperator {
+    /// Equal (default when no operator is given)
+    /// Example: `?filter[id]=1` or `?filter[id][eq]=1`
+    #[default]
+    Eq,
+
+    /// Not equal
+    /// Example: `?filter[id][ne]=1`
+    Ne,
+
+    /// Greater than
+    /// Example: `?filter[age][gt]=18`
+    Gt,
+
+    /// Greater than or equal
+    /// Example: `?filter[age][gte]=18`
+    Gte,
+
+    /// Less than
+    /// Example: `?filter[age][lt]=18`
+    Lt,
+
+    /// Less than or equal
+    /// Example: `?filter[age][lte]=18`
+    Lte,
+
+    /// Pattern matching
+    /// Example: `?filter[name][like]=bob%`
+    Like,
+
+    /// In a comma separated list
+    /// Example: `?filter[id][in]=1,2,3`
+    In,
+}
+
+impl QueryFilterOperator {
+    /// All operators
+    pub const ALL: [QueryFilterOperator; 8] = [
+        Self::Eq,
+        Self::Ne,
+        Self::Gt,
+        Self::Gte,
+        Self::Lt,
+        Self::Lte,
+        Self::Like,
+        Self::In,
+    ];
+
+    /// Get the SQL operator
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "<>",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Like => "LIKE",
+            Self::In => "IN",
+        }
+    }
+}
+
+impl TryFrom<&str> for QueryFilterOperator {
+    type Error = QueryFilterError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "eq" => Self::Eq,
+            "ne" => Self::Ne,
+            "gt" => Self::Gt,
+            "gte" => Self::Gte,
+            "lt" => Self::Lt,
+            "lte" => Self::Lte,
+            "like" => Self::Like,
+            "in" => Self::In,
+            _ => return Err(QueryFilterError::InvalidOperator(value.to_string())),
+        })
+    }
+}
+
+impl Display for QueryFilterOperator {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Eq => "eq",
+                Self::Ne => "ne",
+                Self::Gt => "gt",
+                Self::Gte => "gte",
+                Self::Lt => "lt",
+                Self::Lte => "lte",
+                Self::Like => "like",
+                Self::In => "in",
+            }
+        )
+    }
+}
+
+/// Allow-list of filterable fields and their operators
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryFilterRules {
+    fields: BTreeMap<String, Vec<QueryFilterOperator>>,
+}
+
+impl QueryFilterRules {
+    /// Create an empty allow-list (every filter is rejected)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a field with a list of operators
+    pub fn allow(mut self, field: &str, operators: &[QueryFilterOperator]) -> Self {
+        self.fields.insert(field.to_string(), operators.to_vec());
+        self
+    }
+
+    /// Check a field and an operator against the allow-list
+    pub fn check(&self, field: &str, operator: QueryFilterOperator) -> Result<(), QueryFilterError> {
+        let operators = self
+            .fields
+            .get(field)
+            .ok_or_else(|| QueryFilterError::FieldNotAllowed(field.to_string()))?;
+
+        if operators.contains(&operator) {
+            Ok(())
+        } else {
+            Err(QueryFilterError::OperatorNotAllowed(field.to_string(), operator))
+        }
+    }
+}
+
+/// Query filter, checked against the allow-list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryFilter {
+    field: String,
+    operator: QueryFilterOperator,
+    value: String,
+}
+
+impl QueryFilter {
+    /// Create a new query filter
+    #[cfg(any(feature = "std", test))]
+    fn new(field: &str, operator: QueryFilterOperator, value: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+        }
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Get the operator
+    pub fn operator(&self) -> QueryFilterOperator {
+        self.operator
+    }
+
+    /// Get the raw value
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get the values of the filter (comma separated values for `In` operator)
+    pub fn values(&self) -> Vec<&str> {
+        match self.operator {
+            QueryFilterOperator::In => self.value.split(',').filter(|v| !v.is_empty()).collect(),
+            _ => vec![self.value.as_str()],
+        }
+    }
+}
+
+/// SQL bind parameter placeholder style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SqlPlaceholder {
+    /// `?` (MySQL, SQLite)
+    #[default]
+    QuestionMark,
+
+    /// `$1`, `$2`, ... (PostgreSQL)
+    Numbered,
+}
+
+/// Query filters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryFilters(Vec<QueryFilter>);
+
+impl QueryFilters {
+    /// Parse the filters of a query string, checking them against an allow-list
+    ///
+    /// Query parameters which do not start with `filter[` are ignored. An `in` filter
+    /// without any value (`filter[id][in]=`) is rejected.
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::query_filter::{QueryFilterOperator, QueryFilterRules, QueryFilters};
+    ///
+    /// let rules = QueryFilterRules::new()
+    ///     .allow("age", &[QueryFilterOperator::Gte, QueryFilterOperator::Lte])
+    ///     .allow("name", &[QueryFilterOperator::Eq, QueryFilterOperator::Like]);
+    ///
+    /// let filters = QueryFilters::parse("filter[age][gte]=18&filter[name][like]=bob&page=1", &rules).unwrap();
+    /// assert_eq!(filters.len(), 2);
+    /// assert_eq!(filters.filters()[0].field(), "age");
+    /// assert_eq!(filters.filters()[0].operator(), QueryFilterOperator::Gte);
+    /// assert_eq!(filters.filters()[0].value(), "18");
+    ///
+    /// // Field not in the allow-list
+    /// assert!(QueryFilters::parse("filter[password]=secret", &rules).is_err());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn parse(query: &str, rules: &QueryFilterRules) -> Result<Self, QueryFilterError> {
+        let params: Vec<(String, String)> =
+            serde_urlencoded::from_str(query).map_err(|err| QueryFilterError::InvalidQuery(err.to_string()))?;
+
+        let mut filters = Vec::new();
+        for (key, value) in params {
+            let Some(rest) = key.strip_prefix(QUERY_FILTER_PREFIX) else {
+                continue;
+            };
+            if !rest.starts_with('[') {
+                continue;
+            }
+
+            let (field, operator) =
+                Self::parse_key(rest).ok_or_else(|| QueryFilterError::InvalidFilter(key.clone()))?;
+            let operator = match operator {
+                Some(operator) => QueryFilterOperator::try_from(operator)?,
+                None => QueryFilterOperator::default(),
+            };
+            rules.check(field, operator)?;
+
+            let filter = QueryFilter::new(field, operator, &value);
+            if filter.values().is_empty() {
+                return Err(QueryFilterError::InvalidFilter(key));
+            }
+            filters.push(filter);
+        }
+
+        Ok(Self(filters))
+    }
+
+    /// Get the filters
+    pub fn filters(&self) -> &[QueryFilter] {
+        &self.0
+    }
+
+    /// Number of filters
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return true if there is no filter
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Build a SQL `WHERE` clause (without the `WHERE` keyword) and its bind values
+    ///
+    /// Field names come from the allow-list and values are never inlined, they are
+    /// returned in order to be bound by the query backend.
+    ///
+    /// # Example
+    /// ```
+    /// use api_tools::value_objects::query_filter::{QueryFilterOperator, QueryFilterRules, QueryFilters, SqlPlaceholder};
+    ///
+    /// # #[cfg(feature = "std")] {
+    /// let rules = QueryFilterRules::new()
+    ///     .allow("age", &[QueryFilterOperator::Gte])
+    ///     .allow("id", &[QueryFilterOperator::In]);
+    /// let filters = QueryFilters::parse("filter[age][gte]=18&filter[id][in]=1,2", &rules).unwrap();
+    ///
+    /// let (clause, values) = filters.to_sql(SqlPlaceholder::Numbered);
+    /// assert_eq!(clause, "age >= $1 AND id IN ($2, $3)");
+    /// assert_eq!(values, vec!["18", "1", "2"]);
+    /// # }
+    /// ```
+    pub fn to_sql(&self, placeholder: SqlPlaceholder) -> (String, Vec<String>) {
+        let mut predicates = Vec::with_capacity(self.0.len());
+        let mut values = Vec::new();
+
+        for filter in &self.0 {
+            let mut placeholders = Vec::new();
+            for value in filter.values() {
+                values.push(value.to_string());
+                placeholders.push(match placeholder {
+                    SqlPlaceholder::QuestionMark => "?".to_string(),
+                    SqlPlaceholder::Numbered => format!("${}", values.len()),
+                });
+            }
+
+            let predicate = match filter.operator {
+                QueryFilterOperator::In => format!("{} IN ({})", filter.field, placeholders.join(", ")),
+                _ => format!("{} {} {}", filter.field, filter.operator.sql(), placeholders.join("")),
+            };
+            predicates.push(predicate);
+        }
+
+        (predicates.join(" AND "), values)
+    }
+
+    /// Parse `[field]` or `[field][operator]`
+    #[cfg(feature = "std")]
+    fn parse_key(key: &str) -> Option<(&str, Option<&str>)> {
+        let rest = key.strip_prefix('[')?;
+        let (field, rest) = rest.split_once(']')?;
+        if field.is_empty() {
+            return None;
+        }
+
+        if rest.is_empty() {
+            return Some((field, None));
+        }
+
+        let operator = rest.strip_prefix('[')?.strip_suffix(']')?;
+        if operator.is_empty() || operator.contains(['[', ']']) {
+            return None;
+        }
+
+        Some((field, Some(operator)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    fn rules() -> QueryFilterRules {
+        QueryFilterRules::new()
+            .allow("age", &[QueryFilterOperator::Gte, QueryFilterOperator::Lt])
+            .allow("name", &[QueryFilterOperator::Eq, QueryFilterOperator::Like])
+            .allow("id", &QueryFilterOperator::ALL)
+    }
+
+    #[test]
+    fn test_query_filter_operator_try_from_and_display() {
+        for operator in QueryFilterOperator::ALL {
+            assert_eq!(
+                QueryFilterOperator::try_from(operator.to_string().as_str()),
+                Ok(operator)
+            );
+        }
+        assert_eq!(
+            QueryFilterOperator::try_from("between"),
+            Err(QueryFilterError::InvalidOperator("between".to_string()))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_query_filters_parse() {
+        let filters = QueryFilters::parse("filter[age][gte]=18&filter[name]=bob&sort=-name", &rules()).unwrap();
+        assert_eq!(
+            filters.0,
+            vec![
+                QueryFilter::new("age", QueryFilterOperator::Gte, "18"),
+                QueryFilter::new("name", QueryFilterOperator::Eq, "bob"),
+            ]
+        );
+
+        let filters = QueryFilters::parse("", &rules()).unwrap();
+        assert!(filters.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_query_filters_parse_decodes_urlencoded_keys_and_values() {
+        let filters = QueryFilters::parse("filter%5Bname%5D%5Blike%5D=bob%25", &rules()).unwrap();
+        assert_eq!(
+            filters.0,
+            vec![QueryFilter::new("name", QueryFilterOperator::Like, "bob%")]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_query_filters_parse_rejects_invalid_filters() {
+        assert_eq!(
+            QueryFilters::parse("filter[password]=x", &rules()),
+            Err(QueryFilterError::FieldNotAllowed("password".to_string()))
+        );
+        assert_eq!(
+            QueryFilters::parse("filter[age][like]=1", &rules()),
+            Err(QueryFilterError::OperatorNotAllowed(
+                "age".to_string(),
+                QueryFilterOperator::Like
+            ))
+        );
+        assert_eq!(
+            QueryFilters::parse("filter[age][foo]=1", &rules()),
+            Err(QueryFilterError::InvalidOperator("foo".to_string()))
+        );
+        for key in [
+            "filter[]",
+            "filter[age",
+            "filter[age][gte",
+            "filter[age][]",
+            "filter[age]x",
+        ] {
+            assert_eq!(
+                QueryFilters::parse(&format!("{key}=1"), &rules()),
+                Err(QueryFilterError::InvalidFilter(key.to_string())),
+                "{key} must be rejected"
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_query_filters_parse_rejects_empty_in_list() {
+        for query in ["filter[id][in]=", "filter[id][in]=,,"] {
+            assert_eq!(
+                QueryFilters::parse(query, &rules()),
+                Err(QueryFilterError::InvalidFilter("filter[id][in]".to_string())),
+                "{query} must be rejected"
+            );
+        }
+
+        let filters = QueryFilters::parse("filter[id][in]=1,,2", &rules()).unwrap();
+        assert_eq!(filters.filters()[0].values(), vec!["1", "2"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_query_filters_parse_ignores_other_parameters() {
+        let filters = QueryFilters::parse("filters=1&filterx=2&page=3", &rules()).unwrap();
+        assert!(filters.is_empty());
+    }
+
+    #[test]
+    fn test_query_filters_to_sql() {
+        let filters = QueryFilters(vec![
+            QueryFilter::new("age", QueryFilterOperator::Lt, "30"),
+            QueryFilter::new("id", QueryFilterOperator::In, "1,2,3"),
+            QueryFilter::new("name", QueryFilterOperator::Like, "bob%"),
+        ]);
+
+        let (clause, values) = filters.to_sql(SqlPlaceholder::QuestionMark);
+        assert_eq!(clause, "age < ? AND id IN (?, ?, ?) AND name LIKE ?");
+        assert_eq!(values, vec!["30", "1", "2", "3", "bob%"]);
+
+        let (clause, _) = filters.to_sql(SqlPlaceholder::Numbered);
+        assert_eq!(clause, "age < $1 AND id IN ($2, $3, $4) AND name LIKE $5");
+
+        let (clause, values) = QueryFilters::default().to_sql(SqlPlaceholder::Numbered);
+        assert!(clause.is_empty());
+        assert!(values.is_empty());
+    }
+}
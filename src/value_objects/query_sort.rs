@@ -1,12 +1,15 @@
 //! Query sorts value object representation
 
-use std::fmt::Display;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
 
 /// Filter sort field
 pub type QuerySortField = String;
 
 /// Filter sort direction (ASC or DESC)
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum QuerySortDirection {
     /// Ascending sort (`'+'` prefix)
     /// Example: `?sort=+id`
@@ -19,7 +22,7 @@ pub enum QuerySortDirection {
 }
 
 impl Display for QuerySortDirection {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{}",
@@ -32,6 +35,7 @@ impl Display for QuerySortDirection {
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct QuerySort {
     pub field: QuerySortField,
     pub direction: QuerySortDirection,
@@ -1,8 +1,11 @@
 //! Timezone value object representation
 
+#[cfg(feature = "std")]
+use super::datetime::{LocalizedDateTime, UtcDateTime};
+use alloc::string::{String, ToString};
 use chrono_tz::Tz;
-use std::fmt::Display;
-use std::str::FromStr;
+use core::fmt::Display;
+use core::str::FromStr;
 use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq, Error)]
@@ -21,6 +24,17 @@ impl Timezone {
     pub fn new(tz: Tz) -> Self {
         Self { value: tz }
     }
+
+    /// Get timezone value
+    pub fn value(&self) -> Tz {
+        self.value
+    }
+
+    /// Current date time in the timezone
+    #[cfg(feature = "std")]
+    pub fn now(&self) -> LocalizedDateTime {
+        UtcDateTime::now().to_timezone(self)
+    }
 }
 
 impl TryFrom<&str> for Timezone {
@@ -34,7 +48,7 @@ impl TryFrom<&str> for Timezone {
 }
 
 impl Display for Timezone {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.value)
     }
 }
@@ -70,4 +84,14 @@ mod test {
         let tz = Timezone::default();
         assert_eq!(tz.value, Europe__Paris);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_now() {
+        let tz = Timezone::new(Europe__Paris);
+        let before = UtcDateTime::now();
+        let now = tz.now();
+        assert_eq!(now.timezone(), tz);
+        assert!(now.to_utc() >= before);
+    }
 }
@@ -0,0 +1,285 @@
+//! Strongly-typed ID value object representation (`typed-id` feature)
+//!
+//! [`Id<T>`] is a UUID tagged with the kind of resource it identifies, so that the ID of a user
+//! can not be passed where the ID of an order is expected. Its string form is prefixed by the
+//! kind ([`IdKind::PREFIX`]): `usr_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b`.
+//!
+//! The IDs are serialized as their string form, implement `ToSchema` (`openapi` feature) and
+//! are stored as UUIDs by `sqlx` (`pool-sqlx` feature, with a database supporting `Uuid`).
+//!
+//! ```
+//! use api_tools::value_objects::typed_id::{Id, IdKind};
+//!
+//! struct User;
+//!
+//! impl IdKind for User {
+//!     const PREFIX: &'static str = "usr";
+//! }
+//!
+//! type UserId = Id<User>;
+//!
+//! let id = UserId::new();
+//! assert!(id.to_string().starts_with("usr_"));
+//! assert_eq!(id.to_string().parse::<UserId>(), Ok(id));
+//! assert!("ord_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".parse::<UserId>().is_err());
+//! ```
+
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Kind of resource identified by an [`Id`]
+pub trait IdKind {
+    /// Prefix of the string form (e.g. `usr`), without the `_` separator
+    const PREFIX: &'static str;
+}
+
+/// Typed ID possible errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum IdError {
+    #[error("Invalid ID prefix: {0} (expected: {1})")]
+    InvalidPrefix(String, &'static str),
+
+    #[error("Invalid ID: {0}")]
+    Invalid(String),
+}
+
+/// UUID-backed ID of a resource of kind `T`
+pub struct Id<T: IdKind> {
+    uuid: Uuid,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T: IdKind> Id<T> {
+    /// Create a new random ID (UUID v4)
+    pub fn new() -> Self {
+        Self::from_uuid(Uuid::new_v4())
+    }
+
+    /// Create an ID from a UUID
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        Self {
+            uuid,
+            kind: PhantomData,
+        }
+    }
+
+    /// Get the UUID
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+}
+
+impl<T: IdKind> Default for Id<T> {
+    /// New random ID
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IdKind> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: IdKind> Copy for Id<T> {}
+
+impl<T: IdKind> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+impl<T: IdKind> Eq for Id<T> {}
+
+impl<T: IdKind> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: IdKind> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.uuid.cmp(&other.uuid)
+    }
+}
+
+impl<T: IdKind> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uuid.hash(state);
+    }
+}
+
+impl<T: IdKind> From<Uuid> for Id<T> {
+    fn from(uuid: Uuid) -> Self {
+        Self::from_uuid(uuid)
+    }
+}
+
+impl<T: IdKind> From<Id<T>> for Uuid {
+    fn from(id: Id<T>) -> Self {
+        id.uuid
+    }
+}
+
+impl<T: IdKind> Display for Id<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if T::PREFIX.is_empty() {
+            write!(f, "{}", self.uuid.simple())
+        } else {
+            write!(f, "{}_{}", T::PREFIX, self.uuid.simple())
+        }
+    }
+}
+
+impl<T: IdKind> Debug for Id<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Id({self})")
+    }
+}
+
+impl<T: IdKind> FromStr for Id<T> {
+    type Err = IdError;
+
+    /// Parse the prefixed string form (the UUID can be simple or hyphenated)
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let uuid = if T::PREFIX.is_empty() {
+            value
+        } else {
+            value
+                .strip_prefix(T::PREFIX)
+                .and_then(|uuid| uuid.strip_prefix('_'))
+                .ok_or_else(|| IdError::InvalidPrefix(value.to_string(), T::PREFIX))?
+        };
+
+        Uuid::parse_str(uuid)
+            .map(Self::from_uuid)
+            .map_err(|_| IdError::Invalid(value.to_string()))
+    }
+}
+
+impl<T: IdKind> TryFrom<&str> for Id<T> {
+    type Error = IdError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl<T: IdKind> Serialize for Id<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T: IdKind> Deserialize<'de> for Id<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "pool-sqlx")]
+impl<T: IdKind, DB: sqlx::Database> sqlx::Type<DB> for Id<T>
+where
+    Uuid: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <Uuid as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <Uuid as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "pool-sqlx")]
+impl<'q, T: IdKind, DB: sqlx::Database> sqlx::Encode<'q, DB> for Id<T>
+where
+    Uuid: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.uuid.encode_by_ref(buf)
+    }
+}
+
+#[cfg(feature = "pool-sqlx")]
+impl<'r, T: IdKind, DB: sqlx::Database> sqlx::Decode<'r, DB> for Id<T>
+where
+    Uuid: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        Uuid::decode(value).map(Self::from_uuid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    struct User;
+
+    impl IdKind for User {
+        const PREFIX: &'static str = "usr";
+    }
+
+    struct Raw;
+
+    impl IdKind for Raw {
+        const PREFIX: &'static str = "";
+    }
+
+    const UUID: &str = "9f8e4c0b-2a7d-4e6f-8a1b-3c5d7e9f0a2b";
+
+    #[test]
+    fn test_display_and_from_str() {
+        let id = Id::<User>::from_uuid(Uuid::parse_str(UUID).unwrap());
+        assert_eq!(id.to_string(), "usr_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b");
+        assert_eq!(format!("{id:?}"), "Id(usr_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b)");
+        assert_eq!("usr_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".parse::<Id<User>>(), Ok(id));
+        assert_eq!(format!("usr_{UUID}").parse::<Id<User>>(), Ok(id));
+
+        let raw = Id::<Raw>::from_uuid(id.uuid());
+        assert_eq!(raw.to_string(), "9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b");
+        assert_eq!(UUID.parse::<Id<Raw>>(), Ok(raw));
+    }
+
+    #[test]
+    fn test_from_str_errors() {
+        assert_eq!(
+            "ord_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".parse::<Id<User>>(),
+            Err(IdError::InvalidPrefix(
+                "ord_9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".to_string(),
+                "usr"
+            ))
+        );
+        assert!(matches!(
+            "usr9f8e4c0b2a7d4e6f8a1b3c5d7e9f0a2b".parse::<Id<User>>(),
+            Err(IdError::InvalidPrefix(..))
+        ));
+        assert_eq!(
+            "usr_123".parse::<Id<User>>(),
+            Err(IdError::Invalid("usr_123".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let id = Id::<User>::new();
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{id}\""));
+        assert_eq!(serde_json::from_str::<Id<User>>(&json).unwrap(), id);
+        assert!(serde_json::from_str::<Id<User>>(&format!("\"{UUID}\"")).is_err());
+    }
+}